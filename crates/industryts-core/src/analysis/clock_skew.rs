@@ -0,0 +1,323 @@
+//! Clock skew estimation
+//!
+//! Two correlated signals recorded by different systems, e.g. a flow measured
+//! by the PLC and the same flow in the historian, or a valve position and the
+//! flow it drives, should move together. `estimate_skew` averages both onto a
+//! common grid and finds the time shift of the second signal that best
+//! correlates it with the first. Estimated per segment of the history, the
+//! shifts also reveal a clock that drifts: a line fitted through them gives the
+//! offset and its drift, which `SkewEstimate::correction` turns into a
+//! `ClockSkewOperation`.
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::temporal::ClockSkewOperation;
+use crate::utils::duration::format_offset_ms;
+use polars::prelude::*;
+use std::fmt;
+use std::time::Duration;
+
+/// Grid points two signals must share for a shift to be considered
+const MIN_OVERLAP: usize = 10;
+
+/// Settings of a skew estimation
+#[derive(Debug, Clone)]
+pub struct SkewOptions {
+    /// Grid interval; the estimated offset is refined below it
+    pub resolution: Duration,
+    /// Largest offset searched, either way
+    pub max_offset: Duration,
+    /// Estimate per segment of this length to detect drift
+    pub segment: Option<Duration>,
+}
+
+impl Default for SkewOptions {
+    fn default() -> Self {
+        Self {
+            resolution: Duration::from_secs(10),
+            max_offset: Duration::from_secs(15 * 60),
+            segment: None,
+        }
+    }
+}
+
+/// Offset estimated over one segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentSkew {
+    /// Middle of the segment (ms since epoch)
+    pub time_ms: i64,
+    pub offset_ms: i64,
+    pub correlation: f64,
+}
+
+/// Estimated skew of a clock relative to a reference clock
+#[derive(Debug, Clone)]
+pub struct SkewEstimate {
+    /// Correction to add to the skewed timestamps at `reference_ms`
+    pub offset_ms: i64,
+    /// Change of the correction, in ppm of the elapsed time
+    pub drift_ppm: f64,
+    /// Instant the offset applies to (ms since epoch)
+    pub reference_ms: i64,
+    /// Correlation of the signals once corrected (mean over segments)
+    pub correlation: f64,
+    /// Per-segment estimates, empty without segmentation
+    pub segments: Vec<SegmentSkew>,
+}
+
+impl SkewEstimate {
+    /// Operation moving the skewed timestamps onto the reference clock
+    pub fn correction(&self) -> ClockSkewOperation {
+        ClockSkewOperation::new(self.offset_ms).with_drift(self.drift_ppm, self.reference_ms)
+    }
+}
+
+impl fmt::Display for SkewEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "offset {} (drift {:.1} ppm, correlation {:.2})",
+            format_offset_ms(self.offset_ms),
+            self.drift_ppm,
+            self.correlation
+        )
+    }
+}
+
+/// Mean of `column` per grid interval of `resolution_ms`, indexed from `start`
+fn grid_means(
+    data: &TimeSeriesData,
+    column: &str,
+    resolution_ms: i64,
+    start: i64,
+    len: usize,
+) -> Result<Vec<Option<f64>>> {
+    let times = data.timestamps_ms()?;
+    let values = data.dataframe().column(column)?.cast(&DataType::Float64)?;
+    let mut sums = vec![(0.0, 0usize); len];
+    for (t, value) in times.into_iter().zip(values.f64()?) {
+        let (Some(t), Some(value)) = (t, value) else {
+            continue;
+        };
+        let index = t.div_euclid(resolution_ms) - start;
+        if value.is_finite() && (0..len as i64).contains(&index) {
+            let slot = &mut sums[index as usize];
+            slot.0 += value;
+            slot.1 += 1;
+        }
+    }
+    Ok(sums
+        .into_iter()
+        .map(|(sum, n)| (n > 0).then(|| sum / n as f64))
+        .collect())
+}
+
+/// Correlation of `reference[i]` with `other[i + lag]` over `range`
+fn correlation_at(
+    reference: &[Option<f64>],
+    other: &[Option<f64>],
+    range: std::ops::Range<usize>,
+    lag: i64,
+) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = range
+        .filter_map(|i| {
+            let j = usize::try_from(i as i64 + lag).ok()?;
+            Some((reference[i]?, (*other.get(j)?)?))
+        })
+        .collect();
+    if pairs.len() < MIN_OVERLAP {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let (mean_x, mean_y) = pairs
+        .iter()
+        .fold((0.0, 0.0), |(x, y), (a, b)| (x + a / n, y + b / n));
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        sxy += (x - mean_x) * (y - mean_y);
+        sxx += (x - mean_x) * (x - mean_x);
+        syy += (y - mean_y) * (y - mean_y);
+    }
+    (sxx > 0.0 && syy > 0.0).then(|| sxy / (sxx * syy).sqrt())
+}
+
+/// Lag (in grid steps, refined below a step) of the best correlation, and the
+/// correlation
+fn best_lag(
+    reference: &[Option<f64>],
+    other: &[Option<f64>],
+    range: std::ops::Range<usize>,
+    max_lag: i64,
+) -> Option<(f64, f64)> {
+    let correlations: Vec<Option<f64>> = (-max_lag..=max_lag)
+        .map(|lag| correlation_at(reference, other, range.clone(), lag))
+        .collect();
+    let (best, peak) = correlations
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, (*c)?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // Parabola through the peak and its neighbours
+    let neighbour = |i: Option<usize>| i.and_then(|i| *correlations.get(i)?);
+    let refinement = match (neighbour(best.checked_sub(1)), neighbour(Some(best + 1))) {
+        (Some(before), Some(after)) => {
+            let curvature = before - 2.0 * peak + after;
+            if curvature < 0.0 {
+                0.5 * (before - after) / curvature
+            } else {
+                0.0
+            }
+        }
+        _ => 0.0,
+    };
+    Some((best as f64 - max_lag as f64 + refinement, peak))
+}
+
+/// Estimate the skew of the clock of `other` relative to that of `reference`
+///
+/// `reference_column` and `other_column` must be correlated signals, each in a
+/// single-asset series. The estimate's offset is the correction to add to the
+/// timestamps of `other`; a clock running 2 minutes ahead gives -2 minutes.
+pub fn estimate_skew(
+    reference: &TimeSeriesData,
+    reference_column: &str,
+    other: &TimeSeriesData,
+    other_column: &str,
+    options: &SkewOptions,
+) -> Result<SkewEstimate> {
+    let resolution = options.resolution.as_millis().max(1) as i64;
+    let max_lag = options.max_offset.as_millis() as i64 / resolution;
+    let reference_times = reference.timestamps_ms()?;
+    let other_times = other.timestamps_ms()?;
+    let (Some(first), Some(last)) = (
+        reference_times
+            .min()
+            .into_iter()
+            .chain(other_times.min())
+            .min(),
+        reference_times
+            .max()
+            .into_iter()
+            .chain(other_times.max())
+            .max(),
+    ) else {
+        return Err(IndustrytsError::InvalidOperation(
+            "Cannot estimate clock skew without timestamps".to_string(),
+        ));
+    };
+    let start = first.div_euclid(resolution);
+    let len = (last.div_euclid(resolution) - start + 1) as usize;
+    let x = grid_means(reference, reference_column, resolution, start, len)?;
+    let y = grid_means(other, other_column, resolution, start, len)?;
+
+    let steps = options
+        .segment
+        .map_or(len, |s| (s.as_millis() as i64 / resolution).max(1) as usize);
+    let mut segments = Vec::new();
+    for begin in (0..len).step_by(steps) {
+        let range = begin..(begin + steps).min(len);
+        let middle = (range.start + range.end) as f64 / 2.0;
+        if let Some((lag, correlation)) = best_lag(&x, &y, range, max_lag) {
+            segments.push(SegmentSkew {
+                time_ms: ((start as f64 + middle) * resolution as f64).round() as i64,
+                // `other` lags by `lag` steps: its timestamps are late
+                offset_ms: (-lag * resolution as f64).round() as i64,
+                correlation,
+            });
+        }
+    }
+    if segments.is_empty() {
+        return Err(IndustrytsError::InvalidOperation(format!(
+            "{} and {} do not overlap enough to estimate clock skew",
+            reference_column, other_column
+        )));
+    }
+
+    let n = segments.len() as f64;
+    let correlation = segments.iter().map(|s| s.correlation).sum::<f64>() / n;
+    let mean_t = segments.iter().map(|s| s.time_ms as f64).sum::<f64>() / n;
+    let mean_offset = segments.iter().map(|s| s.offset_ms as f64).sum::<f64>() / n;
+    let (mut stt, mut sto) = (0.0, 0.0);
+    for s in &segments {
+        let dt = s.time_ms as f64 - mean_t;
+        stt += dt * dt;
+        sto += dt * (s.offset_ms as f64 - mean_offset);
+    }
+    let drift = if stt > 0.0 { sto / stt } else { 0.0 };
+    let reference_ms = start * resolution;
+    let offset = mean_offset + drift * (reference_ms as f64 - mean_t);
+
+    let segments = if options.segment.is_some() {
+        segments
+    } else {
+        Vec::new()
+    };
+    Ok(SkewEstimate {
+        offset_ms: offset.round() as i64,
+        drift_ppm: drift * 1e6,
+        reference_ms,
+        correlation,
+        segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Operation;
+    use crate::testing::Fixture;
+
+    /// Smooth process signal sampled every `step_ms` from `t0_ms`
+    fn signal(t0_ms: i64, step_ms: i64, rows: usize, clock: impl Fn(i64) -> i64) -> TimeSeriesData {
+        let true_times: Vec<i64> = (0..rows as i64).map(|i| t0_ms + i * step_ms).collect();
+        let values: Vec<f64> = true_times
+            .iter()
+            .map(|&t| {
+                let s = (t - t0_ms) as f64 / 1000.0;
+                (s / 170.0).sin() + 0.6 * (s / 53.0).sin() + 0.3 * (s / 23.0).cos()
+            })
+            .collect();
+        Fixture::at_times_ms(true_times.into_iter().map(clock).collect())
+            .with_column("flow", values)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_estimates_offset_and_drift() {
+        let t0 = 1_704_067_200_000;
+        let day = 86_400_000;
+        let reference = signal(t0, 10_000, 2000, |t| t);
+        // This clock runs 2 minutes ahead
+        let ahead = signal(t0, 10_000, 2000, |t| t + 120_000);
+        let options = SkewOptions {
+            resolution: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let estimate = estimate_skew(&reference, "flow", &ahead, "flow", &options).unwrap();
+        assert_eq!(estimate.offset_ms, -120_000);
+        assert!(estimate.drift_ppm.abs() < 1e-9);
+        assert!(estimate.correlation > 0.99);
+
+        let corrected = estimate.correction().execute(ahead).unwrap();
+        assert_eq!(
+            corrected.timestamps_ms().unwrap().get(0),
+            reference.timestamps_ms().unwrap().get(0)
+        );
+
+        // 30 s behind, losing another 20 s per day (about 231 ppm) over 2 days
+        // Sampled every 2 s, so the 10 s grid resolves shifts below a step
+        let long = signal(t0, 2_000, 86_400, |t| t);
+        let drifting = signal(t0, 2_000, 86_400, |t| t - 30_000 - (t - t0) * 20_000 / day);
+        let options = SkewOptions {
+            segment: Some(Duration::from_secs(6 * 3600)),
+            ..options
+        };
+        let estimate = estimate_skew(&long, "flow", &drifting, "flow", &options).unwrap();
+        assert_eq!(estimate.segments.len(), 8);
+        // Within a fifth of the grid interval
+        assert!((estimate.offset_ms - 30_000).abs() <= 2_000, "{}", estimate);
+        assert!((estimate.drift_ppm - 231.5).abs() < 10.0, "{}", estimate);
+    }
+}
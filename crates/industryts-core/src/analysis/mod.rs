@@ -0,0 +1,23 @@
+//! Data analysis
+//!
+//! Read-only inspections of time series that inform how a pipeline should be
+//! configured:
+//! - clock_skew: offset and drift between the clocks of two systems
+//! - ranking: top-N and bottom-N time buckets and events by a metric
+//! - sampling: sampling-irregularity detection and regularization advice
+//! - sketch: mergeable column sketches (quantiles, distinct counts, frequencies)
+//! - tag_quality: plant-wide ranking of tags by nulls, flatlines and gaps
+
+pub mod clock_skew;
+pub mod ranking;
+pub mod sampling;
+pub mod sketch;
+pub mod tag_quality;
+
+pub use clock_skew::{SegmentSkew, SkewEstimate, SkewOptions, estimate_skew};
+pub use ranking::{EventMetric, RankOrder, RankedPeriod, Ranking, rank_buckets, rank_events};
+pub use sampling::{ColumnSampling, SamplingPattern, SamplingReport, analyze_sampling};
+pub use sketch::{ColumnSketch, CountMinSketch, DatasetSketch, HyperLogLog, TDigest};
+pub use tag_quality::{
+    PlantQualityReport, QualityCriteria, QualityMetric, TagQuality, plant_quality, tag_quality,
+};
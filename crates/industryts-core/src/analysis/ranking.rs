@@ -0,0 +1,427 @@
+//! Top-N and bottom-N rankings of periods
+//!
+//! Operations dashboards keep asking for the same report: the hours of highest
+//! energy consumption, the longest downtime events, the worst quality shifts.
+//! `rank_buckets` aggregates a column over fixed time buckets and `rank_events`
+//! ranks the episodes during which a condition holds, by duration or by an
+//! aggregate over the episode. Grouped data is ranked across all its entities.
+//! Both return a `Ranking` of the `n` highest or lowest periods, which converts
+//! to a compact table (`Ranking::to_data`) for export.
+
+use crate::config::AggMethod;
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::event_stats::aggregate;
+use crate::utils::parse_frequency;
+use polars::prelude::*;
+use std::fmt;
+
+/// Which end of a ranking is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankOrder {
+    /// Highest values first
+    #[default]
+    Top,
+    /// Lowest values first
+    Bottom,
+}
+
+/// Value an event is ranked by
+#[derive(Debug, Clone)]
+pub enum EventMetric {
+    /// Length of the episode in seconds
+    Duration,
+    /// Aggregate of a column over the rows of the episode
+    Aggregate(String, AggMethod),
+}
+
+impl EventMetric {
+    fn name(&self) -> String {
+        match self {
+            EventMetric::Duration => "duration_s".to_string(),
+            EventMetric::Aggregate(column, aggregation) => metric_name(column, *aggregation),
+        }
+    }
+}
+
+/// One ranked bucket or event
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedPeriod {
+    /// Position in the ranking, from 1
+    pub rank: usize,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Values of the group columns of the entity, as strings
+    pub keys: Vec<Option<String>>,
+    pub value: f64,
+}
+
+/// The `n` highest or lowest periods by a metric
+#[derive(Debug, Clone)]
+pub struct Ranking {
+    /// Name of the metric, e.g. `energy_sum` or `duration_s`
+    pub metric: String,
+    pub order: RankOrder,
+    pub group_columns: Vec<String>,
+    pub periods: Vec<RankedPeriod>,
+}
+
+impl Ranking {
+    /// Keep the `n` first of `periods` by `order`
+    fn new(
+        metric: String,
+        order: RankOrder,
+        group_columns: Vec<String>,
+        mut periods: Vec<RankedPeriod>,
+        n: usize,
+    ) -> Self {
+        periods.sort_by(|a, b| match order {
+            RankOrder::Top => b.value.total_cmp(&a.value),
+            RankOrder::Bottom => a.value.total_cmp(&b.value),
+        });
+        periods.truncate(n);
+        for (index, period) in periods.iter_mut().enumerate() {
+            period.rank = index + 1;
+        }
+        Self {
+            metric,
+            order,
+            group_columns,
+            periods,
+        }
+    }
+
+    /// Table of the ranking: `rank`, `start` (the time column), `end`, the group
+    /// columns and the metric, in rank order
+    pub fn to_data(&self) -> Result<TimeSeriesData> {
+        let datetime = DataType::Datetime(TimeUnit::Milliseconds, None);
+        let mut columns: Vec<Column> = vec![
+            Series::new(
+                "rank".into(),
+                self.periods
+                    .iter()
+                    .map(|p| p.rank as u32)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Series::new(
+                "start".into(),
+                self.periods.iter().map(|p| p.start_ms).collect::<Vec<_>>(),
+            )
+            .cast(&datetime)?
+            .into(),
+            Series::new(
+                "end".into(),
+                self.periods.iter().map(|p| p.end_ms).collect::<Vec<_>>(),
+            )
+            .cast(&datetime)?
+            .into(),
+        ];
+        for (index, group) in self.group_columns.iter().enumerate() {
+            let keys: Vec<Option<&str>> = self
+                .periods
+                .iter()
+                .map(|p| p.keys[index].as_deref())
+                .collect();
+            columns.push(Series::new(group.into(), keys).into());
+        }
+        columns.push(
+            Series::new(
+                self.metric.as_str().into(),
+                self.periods.iter().map(|p| p.value).collect::<Vec<_>>(),
+            )
+            .into(),
+        );
+        TimeSeriesData::new(DataFrame::new(columns)?, Some("start"))
+    }
+}
+
+impl fmt::Display for Ranking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |ms: i64| {
+            chrono::DateTime::from_timestamp_millis(ms)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+        for period in &self.periods {
+            let keys: Vec<&str> = period
+                .keys
+                .iter()
+                .map(|k| k.as_deref().unwrap_or("null"))
+                .collect();
+            let entity = if keys.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", keys.join(", "))
+            };
+            writeln!(
+                f,
+                "{:>3}. {} - {}{}: {} = {}",
+                period.rank,
+                format(period.start_ms),
+                format(period.end_ms),
+                entity,
+                self.metric,
+                period.value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn metric_name(column: &str, aggregation: AggMethod) -> String {
+    format!("{}_{}", column, format!("{:?}", aggregation).to_lowercase())
+}
+
+/// Group keys of each row of `df`, as strings
+fn row_keys(df: &DataFrame, groups: &[String]) -> Result<Vec<Vec<Option<String>>>> {
+    let mut keys = vec![Vec::with_capacity(groups.len()); df.height()];
+    for group in groups {
+        let values = df.column(group)?.cast(&DataType::String)?;
+        for (row, value) in values.str()?.into_iter().enumerate() {
+            keys[row].push(value.map(str::to_string));
+        }
+    }
+    Ok(keys)
+}
+
+/// Rank the buckets of length `every` (e.g. "1h", "1d", "1mo") by `aggregation`
+/// of `column`
+///
+/// Buckets start at multiples of `every` since the epoch; buckets whose
+/// aggregate is null are not ranked.
+pub fn rank_buckets(
+    data: &TimeSeriesData,
+    column: &str,
+    every: &str,
+    aggregation: AggMethod,
+    order: RankOrder,
+    n: usize,
+) -> Result<Ranking> {
+    let every = parse_frequency(every)?;
+    if every.is_zero() {
+        return Err(IndustrytsError::ConfigError(
+            "Ranking bucket length must be positive".to_string(),
+        ));
+    }
+    let metric = metric_name(column, aggregation);
+    let groups = data.group_columns();
+    if data.is_empty() {
+        return Ok(Ranking::new(metric, order, groups.to_vec(), Vec::new(), n));
+    }
+    let time_col = data.time_column();
+    let buckets = data
+        .dataframe()
+        .clone()
+        .lazy()
+        .sort([time_col], SortMultipleOptions::default())
+        .group_by_dynamic(
+            col(time_col),
+            groups.iter().map(|c| col(c.as_str())).collect::<Vec<_>>(),
+            DynamicGroupOptions {
+                every,
+                period: every,
+                offset: Duration::parse("0ns"),
+                include_boundaries: true,
+                closed_window: ClosedWindow::Left,
+                start_by: StartBy::WindowBound,
+                ..Default::default()
+            },
+        )
+        .agg([aggregation
+            .expr(column)
+            .cast(DataType::Float64)
+            .alias(metric.as_str())])
+        .collect()?;
+
+    let boundary = |name: &str| -> Result<Vec<Option<i64>>> {
+        Ok(TimeSeriesData::new(buckets.clone(), Some(name))?
+            .timestamps_ms()?
+            .into_iter()
+            .collect())
+    };
+    let (starts, ends) = (boundary("_lower_boundary")?, boundary("_upper_boundary")?);
+    let keys = row_keys(&buckets, groups)?;
+    let values = buckets.column(&metric)?.f64()?.clone();
+    let periods = (0..buckets.height())
+        .filter_map(|row| {
+            Some(RankedPeriod {
+                rank: 0,
+                start_ms: starts[row]?,
+                end_ms: ends[row]?,
+                keys: keys[row].clone(),
+                value: values.get(row).filter(|v| !v.is_nan())?,
+            })
+        })
+        .collect();
+    Ok(Ranking::new(metric, order, groups.to_vec(), periods, n))
+}
+
+/// Rank the episodes during which `condition` holds, e.g.
+/// `col("state").eq(lit("down"))`, by `metric`
+///
+/// An episode is a run of consecutive rows of an entity where the condition is
+/// true (null counts as false). It ends at the first row after the run, or at
+/// its last row when the data ends during the episode.
+pub fn rank_events(
+    data: &TimeSeriesData,
+    condition: Expr,
+    metric: EventMetric,
+    order: RankOrder,
+    n: usize,
+) -> Result<Ranking> {
+    const HOLDS: &str = "__rank_condition";
+    let time_col = data.time_column();
+    let groups = data.group_columns();
+    let df = data
+        .dataframe()
+        .clone()
+        .lazy()
+        .with_column(condition.fill_null(lit(false)).alias(HOLDS))
+        .sort(
+            [time_col],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )
+        .collect()?;
+    let partitions = if groups.is_empty() {
+        vec![df]
+    } else {
+        df.partition_by_stable(groups, true)?
+    };
+
+    let mut periods = Vec::new();
+    for partition in &partitions {
+        let times: Vec<Option<i64>> = TimeSeriesData::new(partition.clone(), Some(time_col))?
+            .timestamps_ms()?
+            .into_iter()
+            .collect();
+        let holds: Vec<bool> = partition
+            .column(HOLDS)?
+            .bool()?
+            .into_iter()
+            .map(|h| h.unwrap_or(false))
+            .collect();
+        let keys = row_keys(&partition.head(Some(1)), groups)?
+            .pop()
+            .unwrap_or_default();
+        let values = match &metric {
+            EventMetric::Duration => None,
+            EventMetric::Aggregate(column, _) => {
+                Some(partition.column(column)?.cast(&DataType::Float64)?)
+            }
+        };
+
+        let mut row = 0;
+        while row < holds.len() {
+            if !holds[row] {
+                row += 1;
+                continue;
+            }
+            let first = row;
+            while row < holds.len() && holds[row] {
+                row += 1;
+            }
+            let (Some(start), Some(end)) = (times[first], times[row.min(holds.len() - 1)]) else {
+                continue;
+            };
+            let value = match (&metric, &values) {
+                (EventMetric::Aggregate(_, aggregation), Some(values)) => aggregate(
+                    values
+                        .slice(first as i64, row - first)
+                        .as_materialized_series(),
+                    *aggregation,
+                )?,
+                _ => Some((end - start) as f64 / 1000.0),
+            };
+            if let Some(value) = value.filter(|v| !v.is_nan()) {
+                periods.push(RankedPeriod {
+                    rank: 0,
+                    start_ms: start,
+                    end_ms: end,
+                    keys: keys.clone(),
+                    value,
+                });
+            }
+        }
+    }
+    Ok(Ranking::new(
+        metric.name(),
+        order,
+        groups.to_vec(),
+        periods,
+        n,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rank_buckets_and_events() {
+        // Four hours of 15-minute energy readings, the third hour the busiest
+        let energy: Vec<f64> = (0..16)
+            .map(|i| match i / 4 {
+                2 => 30.0,
+                0 => 5.0,
+                _ => 10.0,
+            })
+            .collect();
+        let state: Vec<&str> = (0..16)
+            .map(|i| match i {
+                2 | 9..=11 => "down",
+                _ => "run",
+            })
+            .collect();
+        let data = Fixture::every(Duration::from_secs(15 * 60), 16)
+            .with_column("energy", energy)
+            .with_column("state", state)
+            .build()
+            .unwrap();
+
+        let hours = rank_buckets(&data, "energy", "1h", AggMethod::Sum, RankOrder::Top, 2).unwrap();
+        assert_eq!(hours.metric, "energy_sum");
+        let values: Vec<f64> = hours.periods.iter().map(|p| p.value).collect();
+        assert_eq!(values, [120.0, 40.0]);
+        let busiest = &hours.periods[0];
+        assert_eq!(busiest.end_ms - busiest.start_ms, 3_600_000);
+        let quietest =
+            rank_buckets(&data, "energy", "1h", AggMethod::Sum, RankOrder::Bottom, 1).unwrap();
+        assert_eq!(quietest.periods[0].value, 20.0);
+
+        let downtime = rank_events(
+            &data,
+            col("state").eq(lit("down")),
+            EventMetric::Duration,
+            RankOrder::Top,
+            5,
+        )
+        .unwrap();
+        let durations: Vec<f64> = downtime.periods.iter().map(|p| p.value).collect();
+        assert_eq!(durations, [2700.0, 900.0]);
+        assert_eq!(downtime.periods[0].rank, 1);
+
+        let energy_lost = rank_events(
+            &data,
+            col("state").eq(lit("down")),
+            EventMetric::Aggregate("energy".to_string(), AggMethod::Sum),
+            RankOrder::Top,
+            1,
+        )
+        .unwrap();
+        assert_eq!(energy_lost.periods[0].value, 90.0);
+
+        let table = downtime.to_data().unwrap();
+        assert_eq!(table.time_column(), "start");
+        let names: Vec<&str> = table
+            .dataframe()
+            .get_column_names()
+            .into_iter()
+            .map(|c| c.as_str())
+            .collect();
+        assert_eq!(names, ["rank", "start", "end", "duration_s"]);
+        assert_eq!(downtime.to_string().lines().count(), 2);
+    }
+}
@@ -0,0 +1,374 @@
+//! Sampling-irregularity detection
+//!
+//! Historian exports rarely sit on a clean grid: scan-based tags jitter around
+//! their nominal rate, tags are reconfigured to a different rate mid-history, and
+//! exception (deadband) storage only writes a sample when the value changes.
+//! `analyze_sampling` classifies each column from the intervals between its non-null
+//! samples and `SamplingReport::recommend` turns the result into resample/fill
+//! operation configs that put the frame on a common grid.
+
+use crate::config::{
+    AggMethod, FillMethod, OperationConfig, StateAggregation, WindowClosed, WindowLabel,
+};
+use crate::core::{ColumnSelector, TimeSeriesData};
+use crate::error::Result;
+use crate::utils::duration::format_duration;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Share of intervals within 1% of the median for a column to count as regular
+const REGULAR_SHARE: f64 = 0.95;
+/// Share of intervals within 25% of the median for a column to count as jittered
+const JITTERED_SHARE: f64 = 0.9;
+/// Combined share of the two dominant rates for a column to count as mixed-rate
+const MIXED_SHARE: f64 = 0.8;
+
+/// Grid sizes (ms) that intervals are snapped to when within 10%
+const NICE_INTERVALS_MS: &[i64] = &[
+    1_000, 2_000, 5_000, 10_000, 15_000, 30_000, 60_000, 120_000, 300_000, 600_000, 900_000,
+    1_800_000, 3_600_000, 7_200_000, 21_600_000, 43_200_000, 86_400_000,
+];
+
+/// How a column was sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingPattern {
+    /// Constant interval
+    Regular,
+    /// Nominal rate with timing noise
+    Jittered,
+    /// Two or more distinct rates, e.g. after a scan-rate change
+    MixedRate,
+    /// Irregular intervals typical of exception/deadband storage
+    EventDriven,
+    /// Too few samples to tell
+    Insufficient,
+}
+
+impl fmt::Display for SamplingPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SamplingPattern::Regular => "regular",
+            SamplingPattern::Jittered => "jittered",
+            SamplingPattern::MixedRate => "mixed-rate",
+            SamplingPattern::EventDriven => "event-driven",
+            SamplingPattern::Insufficient => "insufficient",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sampling characteristics of one column
+#[derive(Debug, Clone)]
+pub struct ColumnSampling {
+    pub column: String,
+    /// Number of non-null samples
+    pub samples: usize,
+    /// Median interval between samples
+    pub median_interval: Duration,
+    /// Median absolute deviation of the intervals relative to the median interval
+    pub jitter: f64,
+    /// Dominant intervals, most frequent first, with their share of all intervals
+    pub rates: Vec<(Duration, f64)>,
+    pub pattern: SamplingPattern,
+}
+
+impl ColumnSampling {
+    /// Grid interval this column should be regularized to
+    pub fn suggested_interval(&self) -> Option<Duration> {
+        match self.pattern {
+            SamplingPattern::Insufficient => None,
+            SamplingPattern::Regular | SamplingPattern::Jittered => {
+                Some(snap(self.median_interval))
+            }
+            // The coarsest dominant rate is available over the whole history
+            SamplingPattern::MixedRate => self.rates.iter().map(|(d, _)| snap(*d)).max(),
+            SamplingPattern::EventDriven => Some(snap(self.median_interval)),
+        }
+    }
+}
+
+/// Sampling analysis of a frame
+#[derive(Debug, Clone)]
+pub struct SamplingReport {
+    pub columns: Vec<ColumnSampling>,
+}
+
+impl SamplingReport {
+    /// Get the analysis of a column
+    pub fn column(&self, name: &str) -> Option<&ColumnSampling> {
+        self.columns.iter().find(|c| c.column == name)
+    }
+
+    /// Check whether every analyzed column is already regular
+    pub fn is_regular(&self) -> bool {
+        self.columns.iter().all(|c| {
+            matches!(
+                c.pattern,
+                SamplingPattern::Regular | SamplingPattern::Insufficient
+            )
+        })
+    }
+
+    /// Coarsest suggested interval across columns
+    pub fn common_interval(&self) -> Option<Duration> {
+        self.columns
+            .iter()
+            .filter_map(|c| c.suggested_interval())
+            .max()
+    }
+
+    /// Operation configs that regularize the frame, empty if it is already regular
+    ///
+    /// Event-driven columns hold their last value until the next change, so they are
+    /// resampled with `last` and forward-filled; all other columns are averaged.
+    pub fn recommend(&self) -> Vec<OperationConfig> {
+        let Some(every) = self.common_interval() else {
+            return Vec::new();
+        };
+        let distinct_intervals = self
+            .columns
+            .iter()
+            .filter_map(|c| c.suggested_interval())
+            .any(|d| d != every);
+        if self.is_regular() && !distinct_intervals {
+            return Vec::new();
+        }
+
+        let rule = format_duration(every);
+        let (held, averaged): (Vec<&ColumnSampling>, Vec<&ColumnSampling>) = self
+            .columns
+            .iter()
+            .partition(|c| c.pattern == SamplingPattern::EventDriven);
+        let names = |cols: &[&ColumnSampling]| -> Vec<String> {
+            cols.iter().map(|c| c.column.clone()).collect()
+        };
+
+        let resample = |aggregation, columns| OperationConfig::Resample {
+            rule: rule.clone(),
+            aggregation,
+            columns: Some(ColumnSelector::Names(columns)),
+            offset: None,
+            label: WindowLabel::Left,
+            closed: WindowClosed::Left,
+            state_aggregation: StateAggregation::Last,
+            empty_buckets: Default::default(),
+            weights: Default::default(),
+            min_coverage: None,
+            sample_interval: None,
+        };
+
+        let mut configs = Vec::new();
+        if held.is_empty() {
+            configs.push(resample(AggMethod::Mean, names(&averaged)));
+        } else if averaged.is_empty() {
+            configs.push(resample(AggMethod::Last, names(&held)));
+        } else {
+            // Resampling can only apply one aggregation per step
+            configs.push(resample(
+                AggMethod::Mean,
+                names(&self.columns.iter().collect::<Vec<_>>()),
+            ));
+        }
+        if !held.is_empty() {
+            configs.push(OperationConfig::FillNull {
+                method: FillMethod::Forward,
+                columns: Some(ColumnSelector::Names(names(&held))),
+                column_methods: Default::default(),
+                max_gap: None,
+            });
+        }
+        configs
+    }
+}
+
+impl fmt::Display for SamplingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in &self.columns {
+            writeln!(
+                f,
+                "{}: {} ({} samples, median {}, jitter {:.1}%)",
+                c.column,
+                c.pattern,
+                c.samples,
+                format_duration(c.median_interval),
+                c.jitter * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Snap an interval to a nearby round grid size
+fn snap(interval: Duration) -> Duration {
+    let ms = interval.as_millis() as i64;
+    NICE_INTERVALS_MS
+        .iter()
+        .find(|&&nice| (ms - nice).abs() * 10 <= nice)
+        .map(|&nice| Duration::from_millis(nice as u64))
+        .unwrap_or(Duration::from_millis(ms.max(1) as u64))
+}
+
+fn median(sorted: &[i64]) -> i64 {
+    sorted[sorted.len() / 2]
+}
+
+fn analyze_column(column: &str, times: &[i64]) -> ColumnSampling {
+    let mut intervals: Vec<i64> = times.windows(2).map(|w| w[1] - w[0]).collect();
+    intervals.sort_unstable();
+
+    if intervals.len() < 2 || median(&intervals) <= 0 {
+        return ColumnSampling {
+            column: column.to_string(),
+            samples: times.len(),
+            median_interval: Duration::from_millis(
+                intervals.first().copied().unwrap_or(0).max(0) as u64
+            ),
+            jitter: 0.0,
+            rates: Vec::new(),
+            pattern: SamplingPattern::Insufficient,
+        };
+    }
+
+    let med = median(&intervals);
+    let n = intervals.len() as f64;
+    let share_within = |tolerance: f64| {
+        intervals
+            .iter()
+            .filter(|&&dt| ((dt - med).abs() as f64) <= tolerance * med as f64)
+            .count() as f64
+            / n
+    };
+
+    let mut deviations: Vec<i64> = intervals.iter().map(|dt| (dt - med).abs()).collect();
+    deviations.sort_unstable();
+    let jitter = median(&deviations) as f64 / med as f64;
+
+    // Dominant rates: intervals snapped to round grid sizes
+    let mut counts: HashMap<Duration, usize> = HashMap::new();
+    for &dt in &intervals {
+        *counts
+            .entry(snap(Duration::from_millis(dt.max(0) as u64)))
+            .or_default() += 1;
+    }
+    let mut rates: Vec<(Duration, f64)> = counts
+        .into_iter()
+        .map(|(d, count)| (d, count as f64 / n))
+        .collect();
+    rates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    rates.truncate(3);
+
+    let pattern = if share_within(0.01) >= REGULAR_SHARE {
+        SamplingPattern::Regular
+    } else if share_within(0.25) >= JITTERED_SHARE {
+        SamplingPattern::Jittered
+    } else if rates.len() >= 2 && rates[1].1 >= 0.2 && rates[0].1 + rates[1].1 >= MIXED_SHARE {
+        SamplingPattern::MixedRate
+    } else {
+        SamplingPattern::EventDriven
+    };
+
+    ColumnSampling {
+        column: column.to_string(),
+        samples: times.len(),
+        median_interval: Duration::from_millis(med as u64),
+        jitter,
+        rates,
+        pattern,
+    }
+}
+
+/// Characterize the sampling of every feature column
+///
+/// Each column is judged on the timestamps of its own non-null values, so wide
+/// frames that join tags with different rates are analyzed per tag.
+pub fn analyze_sampling(data: &TimeSeriesData) -> Result<SamplingReport> {
+    let timestamps = data.timestamps_ms()?;
+    let mut columns = Vec::new();
+    for name in data.feature_columns() {
+        let column = data.dataframe().column(name)?;
+        let valid = column.is_not_null();
+        let mut times: Vec<i64> = timestamps
+            .into_iter()
+            .zip(&valid)
+            .filter_map(|(t, valid)| if valid == Some(true) { t } else { None })
+            .collect();
+        times.sort_unstable();
+        columns.push(analyze_column(name, &times));
+    }
+    Ok(SamplingReport { columns })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::{DataFrame, DataType, NamedFrom, Series, TimeUnit};
+
+    fn make_data(times: Vec<i64>) -> TimeSeriesData {
+        let n = times.len();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), vec![1.0; n]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_classifies_patterns() {
+        let regular = make_data((0..100).map(|i| i * 60_000).collect());
+        let report = analyze_sampling(&regular).unwrap();
+        assert_eq!(report.columns[0].pattern, SamplingPattern::Regular);
+        assert!(report.recommend().is_empty());
+
+        // One-minute scans with up to +-3 s of timing noise
+        let jittered = make_data(
+            (0..100)
+                .map(|i| i * 60_000 + (i * 7919 % 6_000) - 3_000)
+                .collect(),
+        );
+        let column = &analyze_sampling(&jittered).unwrap().columns[0];
+        assert_eq!(column.pattern, SamplingPattern::Jittered);
+        assert_eq!(column.suggested_interval(), Some(Duration::from_secs(60)));
+
+        // 10 s scans switched to 60 s halfway through the history
+        let mut times: Vec<i64> = (0..50).map(|i| i * 10_000).collect();
+        times.extend((0..50).map(|i| 500_000 + i * 60_000));
+        let column = &analyze_sampling(&make_data(times)).unwrap().columns[0];
+        assert_eq!(column.pattern, SamplingPattern::MixedRate);
+        assert_eq!(column.suggested_interval(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_event_driven_recommendation() {
+        // Exception storage: bursts while the value moves, long silences otherwise
+        let mut t = 0;
+        let mut times = Vec::new();
+        for i in 0..100i64 {
+            t += [1_000, 4_000, 45_000, 600_000, 17_000][(i % 5) as usize] + i * 13;
+            times.push(t);
+        }
+        let report = analyze_sampling(&make_data(times)).unwrap();
+        assert_eq!(report.columns[0].pattern, SamplingPattern::EventDriven);
+
+        let configs = report.recommend();
+        assert_eq!(configs.len(), 2);
+        assert!(matches!(
+            &configs[0],
+            OperationConfig::Resample {
+                aggregation: AggMethod::Last,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &configs[1],
+            OperationConfig::FillNull {
+                method: FillMethod::Forward,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,539 @@
+//! Mergeable column sketches
+//!
+//! Describing a dataset too large for memory cannot sort or deduplicate whole
+//! columns. `DatasetSketch` instead summarizes every feature column in bounded
+//! memory as chunks pass through: exact counts, extremes, mean and standard
+//! deviation, a t-digest for quantiles, HyperLogLog for the distinct count and a
+//! count-min sketch for the frequency of single values. Sketches of separate
+//! chunks merge into the sketch of their union, so they can be built in parallel.
+//!
+//! `TimeSeriesData::sketch` summarizes data in memory; `ChunkStream::with_sketch`
+//! summarizes the outputs of a chunked pipeline run as they are produced.
+//!
+//! Quantiles are typically within 0.1-1% rank of the exact value (closer in the
+//! tails), distinct counts within about 2% (4096 registers), and value frequencies
+//! are never underestimated.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Centroids a t-digest keeps, roughly; larger is more accurate
+const COMPRESSION: f64 = 100.0;
+/// HyperLogLog register index bits
+const HLL_BITS: u32 = 12;
+/// Count-min rows and counters per row
+const CM_DEPTH: usize = 4;
+const CM_WIDTH: usize = 2048;
+
+/// Finalizer of splitmix64, spreading the bits of `x`
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn hash_f64(value: f64) -> u64 {
+    // Equal values hash equally: -0.0 == 0.0
+    let value = if value == 0.0 { 0.0 } else { value };
+    mix(value.to_bits())
+}
+
+fn hash_str(value: &str) -> u64 {
+    // FNV-1a, then mixed
+    let hash = value.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    mix(hash)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Quantile sketch (merging t-digest)
+#[derive(Debug, Clone, Default)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value; NaN is ignored
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if self.count == 0.0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1.0;
+        self.buffer.push(value);
+        if self.buffer.len() >= 10 * COMPRESSION as usize {
+            self.compress(Vec::new());
+        }
+    }
+
+    /// Number of values added
+    pub fn count(&self) -> u64 {
+        self.count as u64
+    }
+
+    /// Add the values summarized by `other`
+    pub fn merge(&mut self, other: &TDigest) {
+        if other.count == 0.0 {
+            return;
+        }
+        if self.count == 0.0 {
+            self.min = other.min;
+            self.max = other.max;
+        } else {
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+        }
+        self.count += other.count;
+        let mut incoming = other.centroids.clone();
+        incoming.extend(
+            other
+                .buffer
+                .iter()
+                .map(|&mean| Centroid { mean, weight: 1.0 }),
+        );
+        self.compress(incoming);
+    }
+
+    /// Estimated `q` quantile (0 to 1), `None` without values
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0.0 {
+            return None;
+        }
+        let mut digest = self.clone();
+        digest.compress(Vec::new());
+        let centroids = &digest.centroids;
+        let target = q.clamp(0.0, 1.0) * self.count;
+
+        let first = centroids[0];
+        if target < first.weight / 2.0 {
+            let position = target / (first.weight / 2.0);
+            return Some(self.min + position * (first.mean - self.min));
+        }
+        let mut cumulative = 0.0;
+        for pair in centroids.windows(2) {
+            let (left, right) = (pair[0], pair[1]);
+            let left_center = cumulative + left.weight / 2.0;
+            let right_center = cumulative + left.weight + right.weight / 2.0;
+            if target < right_center {
+                let position = (target - left_center) / (right_center - left_center);
+                return Some(left.mean + position * (right.mean - left.mean));
+            }
+            cumulative += left.weight;
+        }
+        let last = centroids[centroids.len() - 1];
+        let last_center = self.count - last.weight / 2.0;
+        let position = ((target - last_center) / (last.weight / 2.0)).min(1.0);
+        Some(last.mean + position * (self.max - last.mean))
+    }
+
+    /// Merge the buffer and `incoming` into the centroids
+    fn compress(&mut self, mut incoming: Vec<Centroid>) {
+        if self.buffer.is_empty() && incoming.is_empty() {
+            return;
+        }
+        incoming.append(&mut self.centroids);
+        incoming.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        incoming.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total: f64 = incoming.iter().map(|c| c.weight).sum();
+        // Scale function k1: centroids near the tails stay small
+        let k = |q: f64| COMPRESSION / (2.0 * PI) * (2.0 * q.clamp(0.0, 1.0) - 1.0).asin();
+        let mut merged = Vec::with_capacity(2 * COMPRESSION as usize);
+        let mut iter = incoming.into_iter();
+        let mut current = iter.next().expect("at least one centroid");
+        let mut before = 0.0;
+        for next in iter {
+            let q_left = before / total;
+            let q_right = (before + current.weight + next.weight) / total;
+            if k(q_right) - k(q_left) <= 1.0 {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+/// Distinct-count sketch (HyperLogLog)
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; 1 << HLL_BITS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - HLL_BITS)) as usize;
+        let rest = (hash << HLL_BITS) | (1 << (HLL_BITS - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Add the values counted by `other`
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// Estimated number of distinct values
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            // Linear counting for small cardinalities
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Value frequency sketch (count-min); estimates never undercount
+#[derive(Debug, Clone)]
+pub struct CountMinSketch {
+    counters: Vec<u64>,
+}
+
+impl Default for CountMinSketch {
+    fn default() -> Self {
+        Self {
+            counters: vec![0; CM_DEPTH * CM_WIDTH],
+        }
+    }
+}
+
+impl CountMinSketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter of each row for `hash` (double hashing)
+    fn cells(hash: u64) -> impl Iterator<Item = usize> {
+        let (h1, h2) = (hash as u32 as usize, ((hash >> 32) | 1) as usize);
+        (0..CM_DEPTH).map(move |row| row * CM_WIDTH + h1.wrapping_add(row * h2) % CM_WIDTH)
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        for cell in Self::cells(hash) {
+            self.counters[cell] += 1;
+        }
+    }
+
+    fn count_hash(&self, hash: u64) -> u64 {
+        Self::cells(hash)
+            .map(|cell| self.counters[cell])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Add the values counted by `other`
+    pub fn merge(&mut self, other: &CountMinSketch) {
+        for (counter, other) in self.counters.iter_mut().zip(&other.counters) {
+            *counter += other;
+        }
+    }
+}
+
+/// Sketch of one column
+#[derive(Debug, Clone, Default)]
+pub struct ColumnSketch {
+    /// Values seen, nulls included
+    pub count: u64,
+    pub null_count: u64,
+    /// Smallest and largest value (numeric columns)
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    numeric: u64,
+    mean: f64,
+    /// Sum of squared deviations from the mean
+    m2: f64,
+    digest: TDigest,
+    distinct: HyperLogLog,
+    frequencies: CountMinSketch,
+}
+
+impl ColumnSketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the values of `series`
+    ///
+    /// Numeric, boolean and temporal columns are summarized as numbers (temporal
+    /// ones by their physical value); other columns as strings.
+    pub fn update(&mut self, series: &Series) -> Result<()> {
+        self.count += series.len() as u64;
+        self.null_count += series.null_count() as u64;
+        let dtype = series.dtype();
+        if dtype.is_primitive_numeric() || dtype.is_bool() || dtype.is_temporal() {
+            let values = series.to_physical_repr().cast(&DataType::Float64)?;
+            let mut chunk = ColumnSketch::default();
+            for value in values.f64()?.into_no_null_iter() {
+                if value.is_nan() {
+                    continue;
+                }
+                let hash = hash_f64(value);
+                chunk.distinct.insert_hash(hash);
+                chunk.frequencies.insert_hash(hash);
+                chunk.digest.add(value);
+                // Welford's update
+                chunk.numeric += 1;
+                let delta = value - chunk.mean;
+                chunk.mean += delta / chunk.numeric as f64;
+                chunk.m2 += delta * (value - chunk.mean);
+            }
+            chunk.min = chunk.digest.quantile(0.0);
+            chunk.max = chunk.digest.quantile(1.0);
+            self.merge_values(&chunk);
+        } else {
+            let values = series.cast(&DataType::String)?;
+            for value in values.str()?.into_no_null_iter() {
+                let hash = hash_str(value);
+                self.distinct.insert_hash(hash);
+                self.frequencies.insert_hash(hash);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the values summarized by `other`
+    pub fn merge(&mut self, other: &ColumnSketch) {
+        self.count += other.count;
+        self.null_count += other.null_count;
+        self.merge_values(other);
+    }
+
+    fn merge_values(&mut self, other: &ColumnSketch) {
+        if other.numeric > 0 {
+            // Chan et al. combination of means and squared deviations
+            let total = (self.numeric + other.numeric) as f64;
+            let delta = other.mean - self.mean;
+            self.m2 +=
+                other.m2 + delta * delta * self.numeric as f64 * other.numeric as f64 / total;
+            self.mean += delta * other.numeric as f64 / total;
+            self.numeric += other.numeric;
+            self.min = self.min.into_iter().chain(other.min).reduce(f64::min);
+            self.max = self.max.into_iter().chain(other.max).reduce(f64::max);
+            self.digest.merge(&other.digest);
+        }
+        self.distinct.merge(&other.distinct);
+        self.frequencies.merge(&other.frequencies);
+    }
+
+    /// Mean of the numeric values
+    pub fn mean(&self) -> Option<f64> {
+        (self.numeric > 0).then_some(self.mean)
+    }
+
+    /// Sample standard deviation of the numeric values
+    pub fn std(&self) -> Option<f64> {
+        (self.numeric > 1).then(|| (self.m2 / (self.numeric - 1) as f64).sqrt())
+    }
+
+    /// Estimated `q` quantile of the numeric values
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        self.digest.quantile(q)
+    }
+
+    /// Estimated number of distinct non-null values
+    pub fn distinct_count(&self) -> u64 {
+        self.distinct.estimate()
+    }
+
+    /// Estimated occurrences of `value` (at least the true count)
+    ///
+    /// Numbers are matched as `f64`, anything else by its string form.
+    pub fn frequency(&self, value: &AnyValue) -> u64 {
+        let hash = match value {
+            AnyValue::String(s) => hash_str(s),
+            AnyValue::StringOwned(s) => hash_str(s),
+            AnyValue::Null => return 0,
+            value => match value.extract::<f64>() {
+                Some(number) => hash_f64(number),
+                None => hash_str(&value.to_string()),
+            },
+        };
+        self.frequencies.count_hash(hash)
+    }
+}
+
+/// Sketches of every feature column of a dataset
+#[derive(Debug, Clone, Default)]
+pub struct DatasetSketch {
+    pub rows: u64,
+    pub columns: BTreeMap<String, ColumnSketch>,
+}
+
+impl DatasetSketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the rows of `data`
+    pub fn update(&mut self, data: &TimeSeriesData) -> Result<()> {
+        self.rows += data.len() as u64;
+        for column in data.feature_columns() {
+            let series = data.dataframe().column(column)?.as_materialized_series();
+            self.columns
+                .entry(column.clone())
+                .or_default()
+                .update(series)?;
+        }
+        Ok(())
+    }
+
+    /// Add the rows summarized by `other`
+    pub fn merge(&mut self, other: &DatasetSketch) {
+        self.rows += other.rows;
+        for (column, sketch) in &other.columns {
+            self.columns
+                .entry(column.clone())
+                .or_default()
+                .merge(sketch);
+        }
+    }
+
+    /// One row of summary statistics per column
+    pub fn describe(&self) -> Result<DataFrame> {
+        let sketches: Vec<&ColumnSketch> = self.columns.values().collect();
+        let stat = |f: &dyn Fn(&ColumnSketch) -> Option<f64>| -> Vec<Option<f64>> {
+            sketches.iter().map(|s| f(s)).collect()
+        };
+        let names: Vec<&str> = self.columns.keys().map(String::as_str).collect();
+        let df = df!(
+            "column" => names,
+            "count" => sketches.iter().map(|s| s.count).collect::<Vec<_>>(),
+            "null_count" => sketches.iter().map(|s| s.null_count).collect::<Vec<_>>(),
+            "distinct" => sketches.iter().map(|s| s.distinct_count()).collect::<Vec<_>>(),
+            "mean" => stat(&|s| s.mean()),
+            "std" => stat(&|s| s.std()),
+            "min" => stat(&|s| s.min),
+            "p25" => stat(&|s| s.quantile(0.25)),
+            "median" => stat(&|s| s.quantile(0.5)),
+            "p75" => stat(&|s| s.quantile(0.75)),
+            "p99" => stat(&|s| s.quantile(0.99)),
+            "max" => stat(&|s| s.max),
+        )?;
+        Ok(df)
+    }
+}
+
+impl TimeSeriesData {
+    /// Sketch of the feature columns
+    pub fn sketch(&self) -> Result<DatasetSketch> {
+        let mut sketch = DatasetSketch::new();
+        sketch.update(self)?;
+        Ok(sketch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sketches_of_chunks_match_the_whole() {
+        let rows = 20_000;
+        // A shuffled ramp and 500 distinct states
+        let ramp: Vec<f64> = (0..rows).map(|i| ((i * 7_919) % rows) as f64).collect();
+        let states: Vec<String> = (0..rows).map(|i| format!("s{}", i % 500)).collect();
+        let data = Fixture::every(Duration::from_secs(1), rows)
+            .with_column("ramp", ramp)
+            .with_column("state", states)
+            .build()
+            .unwrap();
+
+        let pipeline = Pipeline::new();
+        let chunks: Vec<TimeSeriesData> = (0..4)
+            .map(|i| {
+                let df = data.dataframe().slice(i * 5_000, 5_000);
+                TimeSeriesData::with_metadata(df, data.metadata().clone()).unwrap()
+            })
+            .collect();
+        let mut stream = pipeline.process_stream(chunks).unwrap().with_sketch();
+        for output in stream.by_ref() {
+            output.unwrap();
+        }
+        let sketch = stream.sketch().unwrap();
+        assert_eq!(sketch.rows, rows as u64);
+
+        let ramp = &sketch.columns["ramp"];
+        let whole = &data.sketch().unwrap().columns["ramp"];
+        assert_eq!((ramp.min, ramp.max), (Some(0.0), Some(19_999.0)));
+        assert!((ramp.mean().unwrap() - 9_999.5).abs() < 1e-6);
+        assert!((ramp.std().unwrap() - whole.std().unwrap()).abs() < 1e-6);
+        for q in [0.01, 0.25, 0.5, 0.9, 0.999] {
+            let estimate = ramp.quantile(q).unwrap();
+            assert!(
+                (estimate - q * rows as f64).abs() < 0.01 * rows as f64,
+                "q{}",
+                q
+            );
+        }
+        let distinct = ramp.distinct_count() as f64;
+        assert!((distinct - rows as f64).abs() < 0.05 * rows as f64);
+        assert!(ramp.frequency(&AnyValue::Float64(42.0)) >= 1);
+
+        let state = &sketch.columns["state"];
+        assert!(state.distinct_count().abs_diff(500) <= 10);
+        assert!(state.frequency(&AnyValue::String("s7")) >= 40);
+        assert!(state.quantile(0.5).is_none());
+
+        let described = sketch.describe().unwrap();
+        assert_eq!(described.height(), 2);
+        assert_eq!(described.width(), 12);
+    }
+}
@@ -0,0 +1,273 @@
+//! Tag quality scoring across a plant
+//!
+//! With hundreds of tags per unit, maintenance teams need to know which sensors
+//! to look at first. `tag_quality` scores each feature column of a dataset on
+//! three symptoms of a failing sensor: missing values, flatlines (a stuck
+//! transmitter) and gaps (a lost connection). `plant_quality` scores many
+//! datasets in parallel and ranks all tags, worst first.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use crate::operations::anomaly::flatline::flatline_flags;
+use crate::utils::duration::format_duration;
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::fmt;
+use std::time::Duration;
+
+/// Thresholds used to detect flatlines and gaps
+#[derive(Debug, Clone)]
+pub struct QualityCriteria {
+    /// Minimum duration of a run of unchanged values to count as a flatline
+    pub flatline_window: Duration,
+    /// Values within this tolerance of each other count as unchanged
+    pub flatline_tolerance: f64,
+    /// An interval longer than this multiple of the median interval is a gap
+    pub gap_factor: f64,
+}
+
+impl Default for QualityCriteria {
+    fn default() -> Self {
+        Self {
+            flatline_window: Duration::from_secs(3600),
+            flatline_tolerance: 0.0,
+            gap_factor: 5.0,
+        }
+    }
+}
+
+/// Quality of one tag
+#[derive(Debug, Clone)]
+pub struct TagQuality {
+    pub dataset: String,
+    pub column: String,
+    pub rows: usize,
+    /// Fraction of null rows
+    pub null_ratio: f64,
+    /// Fraction of non-null samples in flatline runs
+    pub flatline_ratio: f64,
+    /// Number of gaps between non-null samples
+    pub gaps: usize,
+    /// Fraction of the covered time span spent in gaps
+    pub gap_ratio: f64,
+    pub longest_gap: Duration,
+}
+
+impl TagQuality {
+    /// Score between 0 (unusable) and 1 (no symptom found)
+    pub fn score(&self) -> f64 {
+        (1.0 - self.null_ratio) * (1.0 - self.flatline_ratio) * (1.0 - self.gap_ratio)
+    }
+}
+
+/// Symptom tags can be ranked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityMetric {
+    Score,
+    NullRatio,
+    Flatline,
+    Gaps,
+}
+
+impl QualityMetric {
+    /// Severity of `tag` for this metric, higher is worse
+    fn severity(self, tag: &TagQuality) -> f64 {
+        match self {
+            QualityMetric::Score => 1.0 - tag.score(),
+            QualityMetric::NullRatio => tag.null_ratio,
+            QualityMetric::Flatline => tag.flatline_ratio,
+            QualityMetric::Gaps => tag.gap_ratio,
+        }
+    }
+}
+
+/// Quality of the tags of many datasets, worst score first
+#[derive(Debug, Clone)]
+pub struct PlantQualityReport {
+    pub tags: Vec<TagQuality>,
+}
+
+impl PlantQualityReport {
+    /// The `n` tags with the lowest score
+    pub fn worst(&self, n: usize) -> &[TagQuality] {
+        &self.tags[..n.min(self.tags.len())]
+    }
+
+    /// The `n` tags most affected by `metric`, skipping unaffected tags
+    pub fn worst_by(&self, metric: QualityMetric, n: usize) -> Vec<&TagQuality> {
+        let mut tags: Vec<&TagQuality> = self
+            .tags
+            .iter()
+            .filter(|t| metric.severity(t) > 0.0)
+            .collect();
+        tags.sort_by(|a, b| metric.severity(b).total_cmp(&metric.severity(a)));
+        tags.truncate(n);
+        tags
+    }
+}
+
+impl fmt::Display for PlantQualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for t in &self.tags {
+            writeln!(
+                f,
+                "{}/{}: score {:.2} (nulls {:.1}%, flatline {:.1}%, {} gap(s), longest {})",
+                t.dataset,
+                t.column,
+                t.score(),
+                t.null_ratio * 100.0,
+                t.flatline_ratio * 100.0,
+                t.gaps,
+                format_duration(t.longest_gap)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn analyze_column(
+    dataset: &str,
+    column: &str,
+    rows: usize,
+    samples: &[(i64, f64)],
+    criteria: &QualityCriteria,
+) -> TagQuality {
+    let mut quality = TagQuality {
+        dataset: dataset.to_string(),
+        column: column.to_string(),
+        rows,
+        null_ratio: if rows == 0 {
+            0.0
+        } else {
+            1.0 - samples.len() as f64 / rows as f64
+        },
+        flatline_ratio: 0.0,
+        gaps: 0,
+        gap_ratio: 0.0,
+        longest_gap: Duration::ZERO,
+    };
+    if samples.len() < 2 {
+        return quality;
+    }
+
+    let window = criteria.flatline_window.as_millis() as i64;
+    let stuck = flatline_flags(samples, window, criteria.flatline_tolerance);
+    quality.flatline_ratio = stuck.iter().filter(|&&s| s).count() as f64 / samples.len() as f64;
+
+    let intervals: Vec<i64> = samples.windows(2).map(|w| w[1].0 - w[0].0).collect();
+    let mut sorted = intervals.clone();
+    sorted.sort_unstable();
+    let threshold = sorted[sorted.len() / 2] as f64 * criteria.gap_factor;
+    let gaps: Vec<i64> = intervals
+        .into_iter()
+        .filter(|&dt| dt as f64 > threshold)
+        .collect();
+    let span = samples[samples.len() - 1].0 - samples[0].0;
+    quality.gaps = gaps.len();
+    if span > 0 {
+        quality.gap_ratio = gaps.iter().sum::<i64>() as f64 / span as f64;
+    }
+    quality.longest_gap = Duration::from_millis(gaps.into_iter().max().unwrap_or(0) as u64);
+    quality
+}
+
+/// Score every feature column of `data`
+///
+/// Numeric columns only; data must hold a single asset (split grouped frames
+/// first), sorted by time.
+pub fn tag_quality(
+    dataset: &str,
+    data: &TimeSeriesData,
+    criteria: &QualityCriteria,
+) -> Result<Vec<TagQuality>> {
+    let times = data.timestamps_ms()?;
+    let mut tags = Vec::new();
+    for name in data.feature_columns() {
+        let column = data.dataframe().column(name)?;
+        if !column.dtype().is_primitive_numeric() {
+            continue;
+        }
+        let samples: Vec<(i64, f64)> = column
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .zip(times.iter())
+            .filter_map(|(value, t)| Some((t?, value?)))
+            .collect();
+        tags.push(analyze_column(
+            dataset,
+            name,
+            data.len(),
+            &samples,
+            criteria,
+        ));
+    }
+    Ok(tags)
+}
+
+/// Score the tags of named datasets in parallel and rank them, worst first
+pub fn plant_quality(
+    datasets: &[(String, TimeSeriesData)],
+    criteria: &QualityCriteria,
+) -> Result<PlantQualityReport> {
+    let scored = datasets
+        .par_iter()
+        .map(|(name, data)| tag_quality(name, data, criteria))
+        .collect::<Result<Vec<_>>>()?;
+    let mut tags: Vec<TagQuality> = scored.into_iter().flatten().collect();
+    tags.sort_by(|a, b| a.score().total_cmp(&b.score()));
+    Ok(PlantQualityReport { tags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_plant_ranking() {
+        let minute = Duration::from_secs(60);
+        let healthy: Vec<f64> = (0..120).map(|i| (i as f64 * 0.1).sin()).collect();
+        let mut stuck = healthy.clone();
+        stuck[60..].fill(4.2);
+        let mut sparse: Vec<Option<f64>> = healthy.iter().copied().map(Some).collect();
+        sparse[10..40].fill(None);
+
+        let unit_a = Fixture::every(minute, 120)
+            .with_column("TI_101", healthy.clone())
+            .with_column("PI_102", stuck)
+            .build()
+            .unwrap();
+        let unit_b = Fixture::every(minute, 120)
+            .with_column("TI_201", healthy)
+            .with_column("FI_202", sparse)
+            .build()
+            .unwrap();
+
+        let criteria = QualityCriteria {
+            flatline_window: Duration::from_secs(30 * 60),
+            ..Default::default()
+        };
+        let report = plant_quality(
+            &[
+                ("unit_a".to_string(), unit_a),
+                ("unit_b".to_string(), unit_b),
+            ],
+            &criteria,
+        )
+        .unwrap();
+        assert_eq!(report.tags.len(), 4);
+
+        let worst: Vec<&str> = report.worst(2).iter().map(|t| t.column.as_str()).collect();
+        assert_eq!(worst, ["PI_102", "FI_202"]);
+        let stuck = &report.tags[0];
+        assert!((stuck.flatline_ratio - 0.5).abs() < 1e-9);
+
+        let gaps = report.worst_by(QualityMetric::Gaps, 10);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!((gaps[0].dataset.as_str(), gaps[0].gaps), ("unit_b", 1));
+        assert_eq!(gaps[0].longest_gap, minute * 31);
+        assert!((gaps[0].null_ratio - 0.25).abs() < 1e-9);
+        assert_eq!(report.worst_by(QualityMetric::NullRatio, 10).len(), 1);
+    }
+}
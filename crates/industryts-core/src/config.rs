@@ -1,12 +1,70 @@
 //! Pipeline configuration structures
 
+use crate::core::{ArithmeticPolicy, ColumnSelector, FloatPrecision, NanPolicy};
+use crate::io::{SinkConfig, SourceConfig};
+use crate::operations::anomaly::ControlReference;
+use crate::operations::data_quality::{
+    DuplicateKeep, FlagAction, Limits, OutlierAction, Quality, QualityScheme, SentinelValue,
+    ValidationPolicy, ValidationRules,
+};
+use crate::operations::fleet::FleetStat;
+use crate::operations::merge::{AsofStrategy, MergeHow, SchemaReconciliation};
+use crate::operations::monitoring::baseline::SeasonalPeriod;
+use crate::operations::privacy::NoiseMechanism;
+use crate::operations::temporal::holidays::DayFilter;
+use crate::operations::temporal::reconstruct::Interpolation;
+use crate::operations::text::TextCase;
+use crate::pipeline::materialize::Materialization;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Pipeline configuration loaded from TOML
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PipelineConfig {
     pub pipeline: PipelineMetadata,
-    pub operations: Vec<OperationConfig>,
+    /// Working-day calendar shared by calendar-aware operations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<CalendarConfig>,
+    /// How the pipeline is executed
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+    /// Per-run resource limits
+    #[serde(default)]
+    pub settings: SettingsConfig,
+    /// Sampled trail of rows dropped or modified by each step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditConfig>,
+    /// File read by `Pipeline::run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceConfig>,
+    /// File written by `Pipeline::run`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink: Option<SinkConfig>,
+    #[serde(default)]
+    pub operations: Vec<StepConfig>,
+    /// Named stages run as a DAG on the output of `operations`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageConfig>,
+}
+
+/// Stage of a pipeline DAG (see `pipeline::dag`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StageConfig {
+    pub name: String,
+    /// Stages whose outputs are the input; the pipeline input if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Feature columns routed into the stage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<ColumnSelector>,
+    /// Prefix of the columns the stage adds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Largest time distance of rows joined from several inputs (default exact)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<String>,
+    #[serde(default)]
+    pub operations: Vec<StepConfig>,
 }
 
 /// Pipeline metadata
@@ -15,6 +73,198 @@ pub struct PipelineMetadata {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_column: Option<String>,
+    /// Entity key columns of interleaved series (e.g. ["equipment_id"])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_columns: Vec<String>,
+    /// Local time zone of UTC inputs (e.g. "Europe/Berlin"); calendar resampling
+    /// follows its clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Root seed for stochastic operations (each step derives its own sub-seed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Stamp outputs with run information (`run.*` tags)
+    #[serde(default)]
+    pub stamp_run_info: bool,
+    /// Record which steps created or modified each column
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub track_provenance: bool,
+    /// Tag-metadata file (TOML or CSV) applied to every input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_metadata: Option<String>,
+    /// Template of generated column names, e.g. "{col}__{op}_{param}"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub naming: Option<String>,
+}
+
+/// Execution settings
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExecutionConfig {
+    /// Run the pipeline as one lazy Polars query (see `Pipeline::process_lazy`)
+    #[serde(default)]
+    pub lazy: bool,
+    /// Precision of floating-point columns during the run ("f64" or "f32")
+    #[serde(default)]
+    pub float: FloatPrecision,
+    /// Treatment of NaN in floating-point columns ("keep", "null" or "nan")
+    #[serde(default)]
+    pub nans: NanPolicy,
+    /// Commit the sinks only if every step and sink succeeds (see
+    /// `Pipeline::set_transactional`)
+    #[serde(default)]
+    pub transactional: bool,
+    /// How `Pipeline::run_materialized` returns the output ("memory", "spill" or
+    /// "sink")
+    #[serde(default)]
+    pub materialize: Materialization,
+    /// Directory of spilled outputs (default: the system temp directory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_dir: Option<String>,
+    /// JSON Lines file recording the feature statistics of every run (see
+    /// `StatsRegistry`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats_registry: Option<String>,
+    /// Handling of inputs without rows ("process", "skip" or "error")
+    #[serde(default)]
+    pub empty_input: EmptyInput,
+}
+
+/// Per-run resource limits (see `RunLimits`)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SettingsConfig {
+    /// Wall-clock timeout of a run (e.g. "30s")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Maximum number of output rows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_rows: Option<usize>,
+    /// Maximum estimated memory of the data held by a run (e.g. "512MB")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<String>,
+}
+
+/// Row audit settings (see `RowAudit`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuditConfig {
+    /// Name of the secondary output holding the audit table
+    #[serde(default = "default_audit_output")]
+    pub output: String,
+    /// Sampled rows per step and kind of change
+    #[serde(default = "default_audit_max_rows")]
+    pub max_rows: usize,
+    /// Operation names to audit (default all steps)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operations: Option<Vec<String>>,
+    /// Directory the audit table is written to as `<output>.arrow`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+}
+
+/// Holiday calendar configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CalendarConfig {
+    /// Country whose public holidays apply (e.g. "DE")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Weekend days (default Saturday and Sunday)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekend: Option<Vec<String>>,
+    /// Additional non-working dates (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holidays: Vec<String>,
+    /// Plant shutdown periods
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shutdowns: Vec<ShutdownConfig>,
+}
+
+/// Plant shutdown period (inclusive dates, YYYY-MM-DD)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShutdownConfig {
+    pub start: String,
+    pub end: String,
+}
+
+/// Steps applied to a family of columns (see `RecipeOperation`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecipeConfig {
+    /// Columns of the family; columns of an earlier recipe are skipped
+    pub columns: ColumnSelector,
+    /// Steps run on the family's columns, which must keep the rows
+    pub operations: Vec<OperationConfig>,
+}
+
+/// Regex replacement of a `clean_text` step
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TextReplacement {
+    pub pattern: String,
+    /// Replacement text, may refer to capture groups as `$1`
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// Handling of columns a step requires but its input lacks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingColumns {
+    /// Fail the run
+    #[default]
+    Error,
+    /// Run the step on the columns present, or skip it if none are
+    Skip,
+    /// Add the missing columns as nulls
+    CreateNull,
+}
+
+impl MissingColumns {
+    pub fn is_error(&self) -> bool {
+        *self == MissingColumns::Error
+    }
+}
+
+/// Handling of a run whose input has no rows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyInput {
+    /// Run the steps; every operation accepts empty data
+    #[default]
+    Process,
+    /// Return the input unchanged with a warning, without running steps or sinks
+    Skip,
+    /// Fail the run
+    Error,
+}
+
+/// A pipeline step: an operation and the step-wide options
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StepConfig {
+    #[serde(flatten)]
+    pub operation: OperationConfig,
+    /// Handling of missing required columns ("error", "skip" or "create_null")
+    #[serde(default, skip_serializing_if = "MissingColumns::is_error")]
+    pub missing_columns: MissingColumns,
+}
+
+impl From<OperationConfig> for StepConfig {
+    fn from(operation: OperationConfig) -> Self {
+        Self {
+            operation,
+            missing_columns: MissingColumns::default(),
+        }
+    }
+}
+
+impl std::ops::Deref for StepConfig {
+    type Target = OperationConfig;
+
+    fn deref(&self) -> &OperationConfig {
+        &self.operation
+    }
+}
+
+impl std::ops::DerefMut for StepConfig {
+    fn deref_mut(&mut self) -> &mut OperationConfig {
+        &mut self.operation
+    }
 }
 
 /// Configuration for a single operation
@@ -24,26 +274,835 @@ pub enum OperationConfig {
     FillNull {
         method: FillMethod,
         #[serde(skip_serializing_if = "Option::is_none")]
-        columns: Option<Vec<String>>,
+        columns: Option<ColumnSelector>,
+        /// Methods replacing `method` for individual columns
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        column_methods: BTreeMap<String, FillMethod>,
+        /// Maximum duration to carry a value forward/backward (e.g. "10min")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_gap: Option<String>,
+    },
+    ReplaceSentinels {
+        /// Sentinels replaced in every feature column (numbers or strings)
+        #[serde(default)]
+        values: Vec<SentinelValue>,
+        /// Per-column sentinels, overriding `values` for that column
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        columns: BTreeMap<String, Vec<SentinelValue>>,
+        /// Keep string columns as strings even when only numbers remain
+        #[serde(default)]
+        keep_strings: bool,
+    },
+    NormalizeNans {
+        /// "null" turns NaN into null (default), "nan" turns null into NaN
+        #[serde(default = "default_nan_policy")]
+        policy: NanPolicy,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    Deduplicate {
+        /// Row kept of duplicates: "first", "last" (default) or "aggregate"
+        #[serde(default)]
+        keep: DuplicateKeep,
+        /// Aggregation of duplicates when `keep` is "aggregate" (default mean)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregation: Option<AggMethod>,
+        /// Further key columns besides the time and group columns
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        keys: Vec<String>,
+    },
+    Deadband {
+        /// Largest change ignored (default 0: only exact repeats are dropped)
+        #[serde(default)]
+        tolerance: f64,
+        /// Tolerances of individual columns, overriding `tolerance`
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        tolerances: BTreeMap<String, f64>,
+        /// Keep a row at least this often (e.g. "15min")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_interval: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    SortByTime,
+    Outlier {
+        method: OutlierDetector,
+        /// Standard deviations (zscore, default 3), IQR factor (iqr, default 1.5)
+        /// or scaled MADs (rolling_mad, default 3.5)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        threshold: Option<f64>,
+        /// Trailing window of rolling_mad (e.g. "1h")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        window: Option<String>,
+        /// Physical limits per column, for the limits method
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        limits: BTreeMap<String, Limits>,
+        #[serde(default)]
+        action: OutlierAction,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    /// Act on values flagged by an earlier step, e.g. after resampling
+    ApplyFlags {
+        #[serde(default)]
+        action: FlagAction,
+        /// Keep the flag columns after applying them
+        #[serde(default)]
+        keep_flags: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
     },
     Resample {
         rule: String,
         aggregation: AggMethod,
         #[serde(skip_serializing_if = "Option::is_none")]
-        columns: Option<Vec<String>>,
+        columns: Option<ColumnSelector>,
+        /// Shift of the bucket boundaries from the epoch grid (e.g. "6h")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        offset: Option<String>,
+        #[serde(default)]
+        label: WindowLabel,
+        #[serde(default)]
+        closed: WindowClosed,
+        /// Aggregation of string state columns when `aggregation` is numeric
+        #[serde(default)]
+        state_aggregation: StateAggregation,
+        /// Buckets without samples between the first and last bucket
+        #[serde(default)]
+        empty_buckets: EmptyBuckets,
+        /// Weight column per value column (e.g. flow for a concentration), for
+        /// weighted means and sums
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        weights: BTreeMap<String, String>,
+        /// Minimum share of expected samples (e.g. 0.5); sparser buckets are null
+        /// and `<column>_coverage` columns are added
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_coverage: Option<f64>,
+        /// Sampling interval the expected samples are derived from (inferred when
+        /// omitted)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sample_interval: Option<String>,
+    },
+    Regularize {
+        /// Grid interval (e.g. "1min"), inferred from the data when omitted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interval: Option<String>,
+        /// Longest interval not reported as a gap (default 1.5 grid intervals)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tolerance: Option<String>,
+        /// Insert null rows at missing grid timestamps
+        #[serde(default = "default_insert_missing")]
+        insert_missing: bool,
+        /// Secondary output receiving the table of gaps
+        #[serde(skip_serializing_if = "Option::is_none")]
+        gap_output: Option<String>,
+    },
+    Reconstruct {
+        /// Grid interval of the reconstructed signal (e.g. "1min")
+        every: String,
+        /// Interpolation of columns without an `interpolation` property
+        #[serde(default)]
+        interpolation: Interpolation,
+        /// Longest interval between stored samples that is bridged
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_gap: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    Downsample {
+        #[serde(default)]
+        method: DownsampleMethod,
+        /// Target number of points per column and series
+        #[serde(skip_serializing_if = "Option::is_none")]
+        points: Option<usize>,
+        /// Bucket duration instead of a point count (e.g. "1min")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bucket: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
     },
     Lag {
         periods: Vec<i32>,
         #[serde(skip_serializing_if = "Option::is_none")]
-        columns: Option<Vec<String>>,
+        columns: Option<ColumnSelector>,
+        /// Segment columns (batch, run, machine) that windows must not cross
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        partition_by: Vec<String>,
+    },
+    Difference {
+        /// Rows between the differenced values (default 1)
+        #[serde(default = "default_difference_lag")]
+        lag: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        partition_by: Vec<String>,
+        /// Handling of integer overflow ("null", "clamp" or "error"); wraps when omitted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        on_overflow: Option<ArithmeticPolicy>,
+    },
+    TrendSlope {
+        /// Trailing regression window (e.g. "6h")
+        window: String,
+        /// Time unit of the slope (default "1h")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        slope_per: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_periods: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        partition_by: Vec<String>,
+    },
+    RollingFeatures {
+        /// Trailing windows: durations (e.g. "15m") or row counts (e.g. "60")
+        windows: Vec<String>,
+        /// Statistics among mean, std, min, max and median
+        #[serde(default = "default_rolling_stats")]
+        stats: Vec<String>,
+        /// Quantiles in [0, 1] computed in addition to `stats`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        quantiles: Vec<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Windows replacing `windows` for individual columns
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        column_windows: BTreeMap<String, Vec<String>>,
+        /// Output column name with `{column}`, `{stat}` and `{window}` placeholders
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name_template: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_periods: Option<usize>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        partition_by: Vec<String>,
+    },
+    Derive {
+        /// Formulas such as "power = voltage * current", evaluated in order
+        exprs: Vec<String>,
+        /// Handling of overflow and division by zero (default "null")
+        #[serde(default)]
+        on_overflow: ArithmeticPolicy,
+    },
+    FleetAggregate {
+        /// Prefix of the output columns (default "fleet")
+        #[serde(default = "default_fleet_name")]
+        name: String,
+        /// Statistics among mean, min, max, sum and count
+        #[serde(default = "default_fleet_stats")]
+        stats: Vec<FleetStat>,
+        /// Hierarchy depth of the aggregated nodes; 0 aggregates all columns
+        #[serde(default)]
+        level: usize,
+        /// Asset paths (e.g. "plant_a/line_1/pump_3") replacing column properties
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        assets: BTreeMap<String, String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    EwCorrelation {
+        /// Column pairs, e.g. [["speed", "flow"]]
+        pairs: Vec<[String; 2]>,
+        /// Time for a sample's weight to halve (e.g. "1h")
+        half_life: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_periods: Option<usize>,
+    },
+    Cusum {
+        /// Allowance in sigma units (default 0.5)
+        #[serde(default = "default_cusum_k")]
+        k: f64,
+        /// Decision interval in sigma units (default 5)
+        #[serde(default = "default_cusum_h")]
+        h: f64,
+        /// Known in-control mean; self-tuned when omitted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target: Option<f64>,
+        /// Known in-control standard deviation (required with `target`)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sigma: Option<f64>,
+        /// Number of leading samples used for self-tuning (default: all)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reference_window: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Secondary output explaining each alarm
+        #[serde(skip_serializing_if = "Option::is_none")]
+        explanations: Option<String>,
+        /// Preceding rows summarized per explanation (default 30)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_rows: Option<usize>,
+    },
+    Ewma {
+        /// Smoothing weight in (0, 1] (default 0.2)
+        #[serde(default = "default_ewma_lambda")]
+        lambda: f64,
+        /// Control limit width in sigma units (default 3)
+        #[serde(default = "default_ewma_width")]
+        width: f64,
+        /// Known in-control mean; self-tuned when omitted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target: Option<f64>,
+        /// Known in-control standard deviation (required with `target`)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sigma: Option<f64>,
+        /// Known in-control mean and sigma per column
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        references: BTreeMap<String, ControlReference>,
+        /// Number of leading samples used for self-tuning (default: all)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reference_window: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    RateOfChange {
+        /// Largest allowed absolute change per `per` (or per sample)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_rate: Option<f64>,
+        /// Time unit of the rate (e.g. "1min"); per sample when omitted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        per: Option<String>,
+        /// Limits replacing `max_rate` for individual columns
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        limits: BTreeMap<String, f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    Flatline {
+        /// Shortest flagged run (e.g. "30m")
+        window: String,
+        /// Largest spread of a stuck run (default 0)
+        #[serde(default)]
+        tolerance: f64,
+        /// Tolerances replacing `tolerance` for individual columns
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        column_tolerances: BTreeMap<String, f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    QuantileBand {
+        /// Trailing window the bands are computed over (e.g. "7d")
+        window: String,
+        /// Quantile of the lower band (default 0.01)
+        #[serde(default = "default_band_lower")]
+        lower: f64,
+        /// Quantile of the upper band (default 0.99)
+        #[serde(default = "default_band_upper")]
+        upper: f64,
+        /// Past samples needed before a band is drawn
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_periods: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    SeasonalBaseline {
+        #[serde(default)]
+        period: SeasonalPeriod,
+        /// Profile bucket width, e.g. "15m"; must divide the period
+        bucket: String,
+        /// Zone whose local clock defines the buckets
+        #[serde(default = "default_time_zone")]
+        time_zone: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_samples: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Secondary output explaining each sample beyond `explain_threshold`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        explanations: Option<String>,
+        /// Absolute deviation flagged in explanations (default 3.5)
+        #[serde(default = "default_explain_threshold")]
+        explain_threshold: f64,
+        /// Preceding rows summarized per explanation (default 30)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_rows: Option<usize>,
     },
     Standardize {
         #[serde(skip_serializing_if = "Option::is_none")]
-        columns: Option<Vec<String>>,
+        columns: Option<ColumnSelector>,
+        #[serde(default)]
+        method: ScaleMethod,
+        /// Clip values to these quantiles (e.g. [0.01, 0.99]) before scaling
+        #[serde(skip_serializing_if = "Option::is_none")]
+        clip_quantiles: Option<[f64; 2]>,
+        /// Learn the statistics from the first rows only, then keep them
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reference_window: Option<usize>,
+    },
+    /// Drop mostly-null, near-constant and collinear feature columns
+    FeatureSelection {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Drop columns with a larger share of null or NaN values
+        #[serde(default = "default_max_null_ratio")]
+        max_null_ratio: f64,
+        /// Drop columns whose most frequent value has a larger share of the values
+        #[serde(default = "default_max_dominant_ratio")]
+        max_dominant_ratio: f64,
+        /// Drop columns correlated more strongly with an earlier kept column
+        #[serde(default = "default_max_correlation")]
+        max_correlation: f64,
+        /// Secondary output listing the dropped columns and reasons
+        #[serde(skip_serializing_if = "Option::is_none")]
+        report: Option<String>,
+    },
+    OptimizeDtypes {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Categorize strings with at most this ratio of distinct values (0 disables)
+        #[serde(default = "default_max_category_ratio")]
+        max_category_ratio: f64,
+        /// Never downcast Float64 to Float32, even when lossless
+        #[serde(default)]
+        keep_f64: bool,
+    },
+    Quantize {
+        /// Keep this many significant digits
+        #[serde(skip_serializing_if = "Option::is_none")]
+        significant_digits: Option<u32>,
+        /// Round to a multiple of this step
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resolution: Option<f64>,
+        /// Store quantized columns as f32
+        #[serde(default)]
+        downcast: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    Validate {
+        rules: ValidationRules,
+        /// Record failures as tags instead of failing the pipeline
+        #[serde(default)]
+        warn_only: bool,
+        /// Handling of failed rules ("fail", "warn" or "drop"; overrides `warn_only`)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        policy: Option<ValidationPolicy>,
+    },
+    Expect {
+        /// Path to an expectation suite TOML file
+        suite: String,
+        #[serde(default)]
+        warn_only: bool,
+    },
+    EventLabel {
+        /// Path to an Arrow IPC file with one row per event
+        events: String,
+        #[serde(default = "default_event_start")]
+        start_column: String,
+        #[serde(default = "default_event_end")]
+        end_column: String,
+        /// Asset ID column present in both the events and the data
+        #[serde(skip_serializing_if = "Option::is_none")]
+        asset_column: Option<String>,
+        /// How long before an event windows count as pre-event (e.g. "24h")
+        pre_horizon: String,
+        /// Length of the window each sample stands for
+        #[serde(skip_serializing_if = "Option::is_none")]
+        window: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    EventWindowStats {
+        /// Path to an Arrow IPC file with one row per event
+        events: String,
+        #[serde(default = "default_event_start")]
+        start_column: String,
+        #[serde(default = "default_event_end")]
+        end_column: String,
+        /// Asset ID column present in both the events and the data
+        #[serde(skip_serializing_if = "Option::is_none")]
+        asset_column: Option<String>,
+        /// Window before each event start (e.g. "30min")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        before: Option<String>,
+        /// Window after each event end
+        #[serde(skip_serializing_if = "Option::is_none")]
+        after: Option<String>,
+        /// Aggregate over the events themselves
+        #[serde(default = "default_event_during")]
+        during: bool,
+        /// Statistics per window (default mean, min and max)
+        #[serde(default = "default_event_stats")]
+        stats: Vec<AggMethod>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    CleanText {
+        /// Strip leading and trailing whitespace
+        #[serde(default)]
+        trim: bool,
+        /// Case of the cleaned text ("lower" or "upper")
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        case: Option<TextCase>,
+        /// Regex replacements applied in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        replace: Vec<TextReplacement>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    ConvertUnits {
+        /// Target unit, e.g. "degC"
+        unit: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    ExtractPattern {
+        column: String,
+        /// Regex whose first capture group (or whole match) is extracted
+        pattern: String,
+        /// Capture group extracted instead, 0 for the whole match
+        #[serde(skip_serializing_if = "Option::is_none")]
+        group: Option<usize>,
+        /// Output column (default "<column>_match")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    Merge {
+        /// File holding the other input
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<SourceConfig>,
+        /// Named dataset of the run holding the other input, instead of `source`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        input: Option<String>,
+        /// "asof" adds its columns, "concat" appends its rows
+        #[serde(default)]
+        how: MergeHow,
+        /// Largest time distance of an as-of match (e.g. "8h")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tolerance: Option<String>,
+        /// Row matched by an as-of join: "backward", "forward" or "nearest"
+        #[serde(default)]
+        strategy: AsofStrategy,
+        /// Columns kept when appending parts with different columns: "union",
+        /// "intersection" or "strict"
+        #[serde(default)]
+        schema: SchemaReconciliation,
+    },
+    MapColumns {
+        /// Path to a tag dictionary (.toml or .csv)
+        mapping: String,
+        /// Fail when a mapped tag is missing
+        #[serde(default)]
+        strict: bool,
+        /// Drop feature columns without a mapping
+        #[serde(default)]
+        drop_unmapped: bool,
+    },
+    ParseTimestamp {
+        column: String,
+        /// Candidate formats tried in order (chrono syntax, "epoch_s", "epoch_ms", "rfc3339")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        formats: Option<Vec<String>>,
+        /// Turn unparsable values into nulls instead of failing
+        #[serde(default)]
+        lenient: bool,
+        /// Secondary output receiving rows with unparsable values (e.g. "dead_letter")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dead_letter: Option<String>,
+    },
+    ConvertTimezone {
+        /// Zone the timestamps are currently expressed in
+        #[serde(default = "default_time_zone")]
+        from: String,
+        /// Target zone
+        to: String,
+    },
+    ClockSkew {
+        /// Correction added to the timestamps, e.g. "-2min" for a clock running ahead
+        offset: String,
+        /// Drift of the correction in ppm of the time since `reference_time`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        drift_ppm: Option<f64>,
+        /// Instant the offset applies to, required with a drift
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reference_time: Option<String>,
+    },
+    CalendarBucket {
+        time_zone: String,
+        aggregation: AggMethod,
+        /// Local start of the production day (HH:MM)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        day_start: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        shifts_per_day: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Drop buckets on non-working days of the pipeline calendar
+        #[serde(default)]
+        working_days_only: bool,
+        /// Aggregation of string state columns when `aggregation` is numeric
+        #[serde(default)]
+        state_aggregation: StateAggregation,
+        /// Weight column per value column, for weighted means and sums
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        weights: BTreeMap<String, String>,
+    },
+    StateDwellTime {
+        /// String state column
+        column: String,
+        /// Bucket length (e.g. "1h")
+        every: String,
+        /// States to report, in order (default: all states seen, sorted)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        states: Option<Vec<String>>,
+        /// Longest time a sample's state is held before the gap counts as unknown
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_hold: Option<String>,
+    },
+    CalendarFilter {
+        /// Zone in which dates are judged
+        #[serde(default = "default_time_zone")]
+        time_zone: String,
+        #[serde(default)]
+        keep: DayFilter,
+    },
+    QualityFilter {
+        scheme: QualityScheme,
+        /// Suffix of the quality companion columns (default "_quality")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suffix: Option<String>,
+        /// Lowest quality kept (default "uncertain")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_quality: Option<Quality>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    Anonymize {
+        /// JSON file holding the anonymization key, generated from the data if missing
+        key_file: String,
+        /// Columns masked by a generated key (default: all feature columns)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+        /// Prefix of the column aliases (default "signal")
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alias_prefix: Option<String>,
+        /// Largest timestamp shift of a generated key (default 365 days)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_time_shift: Option<String>,
+        /// Random per-row timestamp jitter (e.g. "30s"), not reversible
+        #[serde(skip_serializing_if = "Option::is_none")]
+        jitter: Option<String>,
+    },
+    PrivacyNoise {
+        /// Privacy budget per noised value
+        epsilon: f64,
+        /// Largest change of a value caused by one contributor
+        sensitivity: f64,
+        #[serde(default)]
+        mechanism: NoiseMechanism,
+        /// Failure probability of Gaussian noise
+        #[serde(skip_serializing_if = "Option::is_none")]
+        delta: Option<f64>,
+        /// Lower clamp of noised values (e.g. 0 for consumption)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        /// Upper clamp of noised values
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        columns: Option<ColumnSelector>,
+    },
+    PerGroup {
+        /// Column identifying the entity (unit, site, sensor) of each row
+        id_column: String,
+        /// Steps applied to each entity's series
+        operations: Vec<OperationConfig>,
+    },
+    Recipe {
+        /// Step chains by column family, each column taken by its first match
+        recipes: Vec<RecipeConfig>,
+    },
+    /// Operation of the global `OperationRegistry`, e.g. one added by the application
+    Custom {
+        /// Registered name of the operation
+        name: String,
+        /// Parameters passed to the operation's factory
+        #[serde(default = "default_custom_params")]
+        params: toml::Value,
     },
     // Add more operation types as needed
 }
 
+impl OperationConfig {
+    /// Column selection of the step, for steps that take one
+    pub fn columns(&self) -> Option<&ColumnSelector> {
+        match self {
+            OperationConfig::FillNull { columns, .. }
+            | OperationConfig::NormalizeNans { columns, .. }
+            | OperationConfig::Outlier { columns, .. }
+            | OperationConfig::ApplyFlags { columns, .. }
+            | OperationConfig::Reconstruct { columns, .. }
+            | OperationConfig::Resample { columns, .. }
+            | OperationConfig::Downsample { columns, .. }
+            | OperationConfig::Lag { columns, .. }
+            | OperationConfig::Difference { columns, .. }
+            | OperationConfig::TrendSlope { columns, .. }
+            | OperationConfig::RollingFeatures { columns, .. }
+            | OperationConfig::FleetAggregate { columns, .. }
+            | OperationConfig::Cusum { columns, .. }
+            | OperationConfig::Ewma { columns, .. }
+            | OperationConfig::RateOfChange { columns, .. }
+            | OperationConfig::Flatline { columns, .. }
+            | OperationConfig::QuantileBand { columns, .. }
+            | OperationConfig::EventWindowStats { columns, .. }
+            | OperationConfig::CleanText { columns, .. }
+            | OperationConfig::ConvertUnits { columns, .. }
+            | OperationConfig::Deadband { columns, .. }
+            | OperationConfig::SeasonalBaseline { columns, .. }
+            | OperationConfig::Standardize { columns, .. }
+            | OperationConfig::FeatureSelection { columns, .. }
+            | OperationConfig::OptimizeDtypes { columns, .. }
+            | OperationConfig::Quantize { columns, .. }
+            | OperationConfig::CalendarBucket { columns, .. }
+            | OperationConfig::QualityFilter { columns, .. }
+            | OperationConfig::Anonymize { columns, .. }
+            | OperationConfig::PrivacyNoise { columns, .. } => columns.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Replace the column selection of a step that takes one
+    pub fn set_columns(&mut self, selector: Option<ColumnSelector>) {
+        match self {
+            OperationConfig::FillNull { columns, .. }
+            | OperationConfig::NormalizeNans { columns, .. }
+            | OperationConfig::Outlier { columns, .. }
+            | OperationConfig::ApplyFlags { columns, .. }
+            | OperationConfig::Reconstruct { columns, .. }
+            | OperationConfig::Resample { columns, .. }
+            | OperationConfig::Downsample { columns, .. }
+            | OperationConfig::Lag { columns, .. }
+            | OperationConfig::Difference { columns, .. }
+            | OperationConfig::TrendSlope { columns, .. }
+            | OperationConfig::RollingFeatures { columns, .. }
+            | OperationConfig::FleetAggregate { columns, .. }
+            | OperationConfig::Cusum { columns, .. }
+            | OperationConfig::Ewma { columns, .. }
+            | OperationConfig::RateOfChange { columns, .. }
+            | OperationConfig::Flatline { columns, .. }
+            | OperationConfig::QuantileBand { columns, .. }
+            | OperationConfig::EventWindowStats { columns, .. }
+            | OperationConfig::CleanText { columns, .. }
+            | OperationConfig::ConvertUnits { columns, .. }
+            | OperationConfig::Deadband { columns, .. }
+            | OperationConfig::SeasonalBaseline { columns, .. }
+            | OperationConfig::Standardize { columns, .. }
+            | OperationConfig::FeatureSelection { columns, .. }
+            | OperationConfig::OptimizeDtypes { columns, .. }
+            | OperationConfig::Quantize { columns, .. }
+            | OperationConfig::CalendarBucket { columns, .. }
+            | OperationConfig::QualityFilter { columns, .. }
+            | OperationConfig::Anonymize { columns, .. }
+            | OperationConfig::PrivacyNoise { columns, .. } => *columns = selector,
+            _ => {}
+        }
+    }
+}
+
+fn default_custom_params() -> toml::Value {
+    toml::Value::Table(toml::Table::new())
+}
+
+fn default_audit_output() -> String {
+    "audit".to_string()
+}
+
+fn default_audit_max_rows() -> usize {
+    100
+}
+
+fn default_nan_policy() -> NanPolicy {
+    NanPolicy::Null
+}
+
+fn default_insert_missing() -> bool {
+    true
+}
+
+fn default_difference_lag() -> usize {
+    1
+}
+
+fn default_rolling_stats() -> Vec<String> {
+    ["mean", "std", "min", "max"].map(String::from).to_vec()
+}
+
+fn default_fleet_name() -> String {
+    "fleet".to_string()
+}
+
+fn default_fleet_stats() -> Vec<FleetStat> {
+    vec![
+        FleetStat::Mean,
+        FleetStat::Min,
+        FleetStat::Max,
+        FleetStat::Count,
+    ]
+}
+
+fn default_explain_threshold() -> f64 {
+    3.5
+}
+
+fn default_max_category_ratio() -> f64 {
+    crate::operations::dtypes::DEFAULT_MAX_CATEGORY_RATIO
+}
+
+fn default_band_lower() -> f64 {
+    0.01
+}
+
+fn default_band_upper() -> f64 {
+    0.99
+}
+
+fn default_max_null_ratio() -> f64 {
+    crate::operations::selection::DEFAULT_MAX_NULL_RATIO
+}
+
+fn default_max_dominant_ratio() -> f64 {
+    crate::operations::selection::DEFAULT_MAX_DOMINANT_RATIO
+}
+
+fn default_max_correlation() -> f64 {
+    crate::operations::selection::DEFAULT_MAX_CORRELATION
+}
+
+fn default_time_zone() -> String {
+    "UTC".to_string()
+}
+
+fn default_event_start() -> String {
+    "start".to_string()
+}
+
+fn default_event_end() -> String {
+    "end".to_string()
+}
+
+fn default_event_during() -> bool {
+    true
+}
+
+fn default_event_stats() -> Vec<AggMethod> {
+    vec![AggMethod::Mean, AggMethod::Min, AggMethod::Max]
+}
+
+fn default_cusum_k() -> f64 {
+    0.5
+}
+
+fn default_cusum_h() -> f64 {
+    5.0
+}
+
+fn default_ewma_lambda() -> f64 {
+    0.2
+}
+
+fn default_ewma_width() -> f64 {
+    3.0
+}
+
 /// Fill method for handling null values
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -52,6 +1111,21 @@ pub enum FillMethod {
     Backward,
     Mean,
     Zero,
+    /// Linear interpolation by row position
+    Linear,
+    /// Linear interpolation weighted by timestamp
+    Time,
+}
+
+/// Outlier detection method
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierDetector {
+    #[serde(rename = "zscore")]
+    ZScore,
+    Iqr,
+    RollingMad,
+    Limits,
 }
 
 /// Aggregation method
@@ -67,7 +1141,234 @@ pub enum AggMethod {
     Count,
 }
 
+impl AggMethod {
+    /// Build the Polars aggregation expression for a column
+    pub fn expr(self, column: &str) -> polars::prelude::Expr {
+        let col_expr = polars::prelude::col(column);
+
+        match self {
+            AggMethod::Mean => col_expr.mean(),
+            AggMethod::Sum => col_expr.sum(),
+            AggMethod::Min => col_expr.min(),
+            AggMethod::Max => col_expr.max(),
+            AggMethod::First => col_expr.first(),
+            AggMethod::Last => col_expr.last(),
+            AggMethod::Count => col_expr.count(),
+        }
+    }
+
+    /// Aggregation of `column` weighted by the `weight` column
+    ///
+    /// `Mean` gives the weighted average over the samples where both are present
+    /// (null when their weights sum to zero) and `Sum` the sum of the products,
+    /// e.g. a mass from concentration and flow. Other methods ignore the weight.
+    pub fn weighted_expr(self, column: &str, weight: &str) -> polars::prelude::Expr {
+        use polars::prelude::*;
+
+        let value = col(column).cast(DataType::Float64);
+        let weight = col(weight).cast(DataType::Float64);
+        let product = (value.clone() * weight.clone()).sum();
+        match self {
+            AggMethod::Mean => {
+                let total = when(value.is_not_null())
+                    .then(weight)
+                    .otherwise(lit(NULL).cast(DataType::Float64))
+                    .sum();
+                when(total.clone().neq(lit(0.0)))
+                    .then(product / total)
+                    .otherwise(lit(NULL).cast(DataType::Float64))
+                    .alias(column)
+            }
+            AggMethod::Sum => product.alias(column),
+            _ => self.expr(column),
+        }
+    }
+
+    /// Whether `weighted_expr` uses the weight
+    pub fn supports_weights(self) -> bool {
+        matches!(self, AggMethod::Mean | AggMethod::Sum)
+    }
+}
+
+impl AggMethod {
+    /// Whether the method only makes sense for numeric values
+    pub fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            AggMethod::Mean | AggMethod::Sum | AggMethod::Min | AggMethod::Max
+        )
+    }
+}
+
+/// Aggregation of string state columns (machine state, batch phase, ...) in buckets
+///
+/// Used in place of numeric aggregations such as `mean`, which would turn states
+/// into nulls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateAggregation {
+    /// Most frequent state (ties go to the lexically smallest)
+    #[default]
+    Mode,
+    First,
+    Last,
+}
+
+impl StateAggregation {
+    /// Build the Polars aggregation expression for a column
+    pub fn expr(self, column: &str) -> polars::prelude::Expr {
+        let col_expr = polars::prelude::col(column);
+
+        match self {
+            StateAggregation::Mode => col_expr
+                .drop_nulls()
+                .mode()
+                .sort(Default::default())
+                .first(),
+            StateAggregation::First => col_expr.first(),
+            StateAggregation::Last => col_expr.last(),
+        }
+    }
+}
+
+/// Statistics used to center and scale columns in standardization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMethod {
+    /// Mean and standard deviation
+    #[default]
+    Standard,
+    /// Median and scaled median absolute deviation, insensitive to spikes
+    Robust,
+}
+
+/// Sample selection of the downsample operation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownsampleMethod {
+    /// Largest triangle three buckets, preserving the visual shape
+    #[default]
+    Lttb,
+    /// Lowest and highest sample of each bucket, preserving extremes
+    MinMax,
+}
+
+/// Timestamp assigned to an aggregated bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowLabel {
+    /// Bucket start
+    #[default]
+    Left,
+    /// Bucket end (e.g. "hour ending" reports)
+    Right,
+}
+
+/// Resampled buckets that contain no samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyBuckets {
+    /// Left out of the output
+    #[default]
+    Drop,
+    /// Emitted with nulls
+    Null,
+    /// Emitted with zeros in numeric columns and nulls in state columns
+    Zero,
+}
+
+/// Bucket edges that include their boundary timestamp
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowClosed {
+    #[default]
+    Left,
+    Right,
+    Both,
+    None,
+}
+
+/// Serialization format of a pipeline configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format implied by the extension of `path` (.toml or .json)
+    pub fn from_path(path: &std::path::Path) -> crate::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            Some("yaml" | "yml") => Err(crate::IndustrytsError::ConfigError(format!(
+                "YAML configurations are not supported ({}); use TOML or JSON",
+                path.display()
+            ))),
+            _ => Err(crate::IndustrytsError::ConfigError(format!(
+                "Cannot infer the configuration format of {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
 impl PipelineConfig {
+    /// Parse a configuration in `format`
+    pub fn from_str_as(s: &str, format: ConfigFormat) -> crate::Result<Self> {
+        match format {
+            ConfigFormat::Toml => Self::from_toml_str(s),
+            ConfigFormat::Json => Self::from_json_str(s),
+        }
+    }
+
+    /// Serialize to a string in `format`
+    pub fn to_string_as(&self, format: ConfigFormat) -> crate::Result<String> {
+        match format {
+            ConfigFormat::Toml => self.to_toml_string(),
+            ConfigFormat::Json => self.to_json_string(),
+        }
+    }
+
+    /// Load configuration from a file, in the format implied by its extension
+    pub fn from_file(path: &std::path::Path) -> crate::Result<Self> {
+        let format = ConfigFormat::from_path(path)?;
+        Self::from_str_as(&std::fs::read_to_string(path)?, format)
+    }
+
+    /// Save to a file, in the format implied by its extension
+    pub fn to_file(&self, path: &std::path::Path) -> crate::Result<()> {
+        let content = self.to_string_as(ConfigFormat::from_path(path)?)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Load configuration from JSON string
+    pub fn from_json_str(s: &str) -> crate::Result<Self> {
+        serde_json::from_str(s).map_err(Into::into)
+    }
+
+    /// Load configuration from JSON file
+    pub fn from_json_file(path: &std::path::Path) -> crate::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_json_str(&contents)
+    }
+
+    /// Serialize to JSON string
+    pub fn to_json_string(&self) -> crate::Result<String> {
+        serde_json::to_string_pretty(self).map_err(Into::into)
+    }
+
+    /// Save to JSON file
+    pub fn to_json_file(&self, path: &std::path::Path) -> crate::Result<()> {
+        std::fs::write(path, self.to_json_string()?)?;
+        Ok(())
+    }
+
     /// Load configuration from TOML string
     pub fn from_toml_str(s: &str) -> crate::Result<Self> {
         toml::from_str(s).map_err(Into::into)
@@ -0,0 +1,317 @@
+//! Arithmetic between columns
+//!
+//! For a one-off derived signal (a differential pressure, a specific energy) the
+//! `derive` operation or a polars expression is more machinery than needed.
+//! `TimeSeriesData::col` returns a `Signal` supporting `+ - * /` with other
+//! signals of the same data and with scalars:
+//!
+//! ```ignore
+//! let dp = data.col("p_in") - data.col("p_out");
+//! let data = data.with_signal("dp", dp)?;
+//! ```
+//!
+//! Values are computed as `f64`. Signals carry the engineering unit of their
+//! column (`TimeSeriesData::column_unit`): adding or subtracting signals with
+//! different known units fails, products and quotients get a compound unit such
+//! as `kW/m3/h`, and scalars keep the unit. Errors (a missing column, signals of
+//! different lengths) are kept in the signal and returned by `with_signal` or
+//! `into_series`, so expressions chain without `?` at every step.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Values of a column or of arithmetic between columns
+pub struct Signal {
+    values: Result<Series>,
+    unit: Option<String>,
+}
+
+impl Signal {
+    /// Signal of `series` with an optional unit
+    pub fn new(series: Series, unit: Option<&str>) -> Self {
+        let values = series.cast(&DataType::Float64).map_err(Into::into);
+        Self {
+            values,
+            unit: unit.map(str::to_string),
+        }
+    }
+
+    /// Set or replace the unit, e.g. after a conversion factor
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    /// Unit of the signal, if known
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// The values, or the first error of the expression
+    pub fn into_series(self) -> Result<Series> {
+        self.values
+    }
+
+    /// Combine with `other` element-wise; `unit` decides the resulting unit
+    fn combine(
+        self,
+        other: Signal,
+        symbol: char,
+        unit: impl FnOnce(Option<String>, Option<String>) -> Result<Option<String>>,
+        op: impl FnOnce(&Series, &Series) -> PolarsResult<Series>,
+    ) -> Self {
+        let values = (|| {
+            let (left, right) = (self.values?, other.values?);
+            if left.len() != right.len() {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "cannot combine {} and {} with '{}': {} and {} rows",
+                    left.name(),
+                    right.name(),
+                    symbol,
+                    left.len(),
+                    right.len()
+                )));
+            }
+            Ok(op(&left, &right)?)
+        })();
+        match unit(self.unit, other.unit) {
+            Ok(unit) => Self { values, unit },
+            Err(e) => Self {
+                values: Err(e),
+                unit: None,
+            },
+        }
+    }
+
+    fn map(self, op: impl FnOnce(&Series) -> Series) -> Self {
+        Self {
+            values: self.values.map(|values| op(&values)),
+            unit: self.unit,
+        }
+    }
+}
+
+/// Unit of a sum or difference: known units must match
+fn same_unit(left: Option<String>, right: Option<String>) -> Result<Option<String>> {
+    match (left, right) {
+        (Some(left), Some(right)) if left != right => Err(IndustrytsError::InvalidOperation(
+            format!("cannot add or subtract {} and {}", left, right),
+        )),
+        (left, right) => Ok(left.or(right)),
+    }
+}
+
+/// Unit of a product or quotient: both units joined by `symbol`, if both are known
+fn compound_unit(
+    symbol: char,
+) -> impl FnOnce(Option<String>, Option<String>) -> Result<Option<String>> {
+    move |left, right| {
+        Ok(left
+            .zip(right)
+            .map(|(l, r)| format!("{}{}{}", l, symbol, r)))
+    }
+}
+
+impl Add for Signal {
+    type Output = Signal;
+
+    fn add(self, other: Signal) -> Signal {
+        self.combine(other, '+', same_unit, |l, r| l + r)
+    }
+}
+
+impl Sub for Signal {
+    type Output = Signal;
+
+    fn sub(self, other: Signal) -> Signal {
+        self.combine(other, '-', same_unit, |l, r| l - r)
+    }
+}
+
+impl Mul for Signal {
+    type Output = Signal;
+
+    fn mul(self, other: Signal) -> Signal {
+        self.combine(other, '*', compound_unit('*'), |l, r| l * r)
+    }
+}
+
+impl Div for Signal {
+    type Output = Signal;
+
+    fn div(self, other: Signal) -> Signal {
+        self.combine(other, '/', compound_unit('/'), |l, r| l / r)
+    }
+}
+
+impl Add<f64> for Signal {
+    type Output = Signal;
+
+    fn add(self, scalar: f64) -> Signal {
+        self.map(|values| values + scalar)
+    }
+}
+
+impl Sub<f64> for Signal {
+    type Output = Signal;
+
+    fn sub(self, scalar: f64) -> Signal {
+        self.map(|values| values - scalar)
+    }
+}
+
+impl Mul<f64> for Signal {
+    type Output = Signal;
+
+    fn mul(self, scalar: f64) -> Signal {
+        self.map(|values| values * scalar)
+    }
+}
+
+impl Div<f64> for Signal {
+    type Output = Signal;
+
+    fn div(self, scalar: f64) -> Signal {
+        self.map(|values| values / scalar)
+    }
+}
+
+impl Neg for Signal {
+    type Output = Signal;
+
+    fn neg(self) -> Signal {
+        self.map(|values| values * -1.0)
+    }
+}
+
+impl Add<Signal> for f64 {
+    type Output = Signal;
+
+    fn add(self, signal: Signal) -> Signal {
+        signal + self
+    }
+}
+
+impl Sub<Signal> for f64 {
+    type Output = Signal;
+
+    fn sub(self, signal: Signal) -> Signal {
+        -signal + self
+    }
+}
+
+impl Mul<Signal> for f64 {
+    type Output = Signal;
+
+    fn mul(self, signal: Signal) -> Signal {
+        signal * self
+    }
+}
+
+impl Div<Signal> for f64 {
+    type Output = Signal;
+
+    /// The reciprocal unit is not tracked; the result has no unit
+    fn div(self, signal: Signal) -> Signal {
+        Signal {
+            values: signal
+                .values
+                .and_then(|values| Ok(values.f64()?.apply_values(|v| self / v).into_series())),
+            unit: None,
+        }
+    }
+}
+
+impl TimeSeriesData {
+    /// Values of `column` for arithmetic with other columns and scalars
+    pub fn col(&self, column: &str) -> Signal {
+        match self.dataframe().column(column) {
+            Ok(values) => Signal::new(
+                values.as_materialized_series().clone(),
+                self.column_unit(column),
+            ),
+            Err(_) => Signal {
+                values: Err(IndustrytsError::ColumnNotFound(column.to_string())),
+                unit: None,
+            },
+        }
+    }
+
+    /// Add `signal` as the feature column `name`, replacing an existing column
+    ///
+    /// The unit of the signal, if known, is stored as the column's unit.
+    pub fn with_signal(mut self, name: &str, signal: Signal) -> Result<Self> {
+        if name == self.time_column() || self.group_columns().iter().any(|c| c == name) {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "cannot replace the key column '{}' with a signal",
+                name
+            )));
+        }
+        let unit = signal.unit.clone();
+        let values = signal.into_series()?.with_name(name.into());
+        if values.len() != self.len() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "signal '{}' has {} rows, the data {}",
+                name,
+                values.len(),
+                self.len()
+            )));
+        }
+        self.dataframe_mut().with_column(values)?;
+        if !self.feature_columns().iter().any(|c| c == name) {
+            self.metadata_mut().feature_columns.push(name.to_string());
+        }
+        if let Some(unit) = unit {
+            self.set_column_property(name, "unit", &unit);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_column_arithmetic_with_units() {
+        let mut data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("p_in", [5.0, 6.0, 7.0])
+            .with_column("p_out", [1i32, 2, 4])
+            .with_column("power", [10.0, 20.0, 30.0])
+            .with_column("flow", [2.0, 0.0, 3.0])
+            .build()
+            .unwrap();
+        data.set_column_property("p_in", "unit", "bar");
+        data.set_column_property("p_out", "unit", "bar");
+        data.set_column_property("power", "unit", "kW");
+        data.set_column_property("flow", "unit", "m3/h");
+
+        let dp = (data.col("p_in") - data.col("p_out")) * 100.0;
+        let specific = data.col("power") / data.col("flow");
+        let data = data
+            .with_signal("dp", dp.with_unit("kPa"))
+            .unwrap()
+            .with_signal("specific_energy", specific)
+            .unwrap();
+        let values = |name: &str| -> Vec<f64> {
+            let column = data.dataframe().column(name).unwrap();
+            column.f64().unwrap().into_no_null_iter().collect()
+        };
+        assert_eq!(values("dp"), [400.0, 400.0, 300.0]);
+        assert_eq!(values("specific_energy"), [5.0, f64::INFINITY, 10.0]);
+        assert_eq!(data.column_unit("dp"), Some("kPa"));
+        assert_eq!(data.column_unit("specific_energy"), Some("kW/m3/h"));
+        assert!(data.feature_columns().contains(&"dp".to_string()));
+
+        let mixed = data.col("p_in") + data.col("power");
+        let Err(e) = data.clone().with_signal("bad", mixed) else {
+            panic!("adding bar and kW must fail");
+        };
+        assert!(e.to_string().contains("bar and kW"));
+        assert!((data.col("missing") * 2.0).into_series().is_err());
+        assert_eq!((1.0 - data.col("p_in")).unit(), Some("bar"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Checked arithmetic for derived columns
+//!
+//! Polars wraps integer overflow and turns division by zero into infinities or
+//! NaN, which then surface far from their cause (a wrapped counter difference,
+//! an infinite efficiency ratio averaged into a KPI). Operations deriving columns
+//! from arithmetic run it through an `ArithmeticPolicy` instead, which nulls,
+//! clamps or rejects offending values and counts them so they can be reported.
+
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Binary arithmetic operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    /// True division; the result is always floating-point
+    Div,
+}
+
+/// Handling of overflow and division by zero
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArithmeticPolicy {
+    /// Replace offending values with null
+    #[default]
+    Null,
+    /// Saturate at the largest magnitude of the result type (0 / 0 becomes null)
+    Clamp,
+    /// Fail the operation
+    Error,
+}
+
+/// Number of offending values of a checked computation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArithmeticViolations {
+    pub overflows: usize,
+    pub divisions_by_zero: usize,
+}
+
+impl ArithmeticViolations {
+    pub fn total(&self) -> usize {
+        self.overflows + self.divisions_by_zero
+    }
+}
+
+/// Range of an integer dtype
+fn integer_bounds(dtype: &DataType) -> Option<(i128, i128)> {
+    Some(match dtype {
+        DataType::Int8 => (i8::MIN as i128, i8::MAX as i128),
+        DataType::Int16 => (i16::MIN as i128, i16::MAX as i128),
+        DataType::Int32 => (i32::MIN as i128, i32::MAX as i128),
+        DataType::Int64 => (i64::MIN as i128, i64::MAX as i128),
+        DataType::UInt8 => (0, u8::MAX as i128),
+        DataType::UInt16 => (0, u16::MAX as i128),
+        DataType::UInt32 => (0, u32::MAX as i128),
+        DataType::UInt64 => (0, u64::MAX as i128),
+        _ => return None,
+    })
+}
+
+fn integer_values(series: &Series) -> Result<Vec<Option<i128>>> {
+    Ok(match series.dtype() {
+        DataType::UInt64 => series
+            .u64()?
+            .into_iter()
+            .map(|v| v.map(i128::from))
+            .collect(),
+        _ => series
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|v| v.map(i128::from))
+            .collect(),
+    })
+}
+
+impl ArithmeticPolicy {
+    /// Compute `lhs op rhs` element-wise, named after `lhs`
+    ///
+    /// Integer operands of one type keep it and overflow at its range; mixed
+    /// integer types are computed as Int64. Float operands overflow when finite
+    /// inputs give a non-finite result. Nulls propagate.
+    pub fn apply(
+        self,
+        op: BinaryOp,
+        lhs: &Series,
+        rhs: &Series,
+    ) -> Result<(Series, ArithmeticViolations)> {
+        let integer = lhs.dtype().is_integer() && rhs.dtype().is_integer() && op != BinaryOp::Div;
+        if integer {
+            self.apply_integer(op, lhs, rhs)
+        } else {
+            self.apply_float(op, lhs, rhs)
+        }
+    }
+
+    /// Resolve one offending value; `Ok(None)` nulls it
+    fn resolve<T>(
+        self,
+        lhs: &Series,
+        row: usize,
+        what: &str,
+        clamped: Option<T>,
+    ) -> Result<Option<T>> {
+        match self {
+            ArithmeticPolicy::Null => Ok(None),
+            ArithmeticPolicy::Clamp => Ok(clamped),
+            ArithmeticPolicy::Error => Err(IndustrytsError::OperationError(format!(
+                "{} at row {} deriving from column: {}",
+                what,
+                row,
+                lhs.name()
+            ))),
+        }
+    }
+
+    fn apply_integer(
+        self,
+        op: BinaryOp,
+        lhs: &Series,
+        rhs: &Series,
+    ) -> Result<(Series, ArithmeticViolations)> {
+        let dtype = if lhs.dtype() == rhs.dtype() {
+            lhs.dtype().clone()
+        } else {
+            DataType::Int64
+        };
+        let (min, max) = integer_bounds(&dtype).unwrap_or((i64::MIN as i128, i64::MAX as i128));
+        let mut violations = ArithmeticViolations::default();
+        let mut values = Vec::with_capacity(lhs.len());
+        for (row, (a, b)) in integer_values(lhs)?
+            .into_iter()
+            .zip(integer_values(rhs)?)
+            .enumerate()
+        {
+            let value = match (a, b) {
+                (Some(a), Some(b)) => {
+                    // Operands are at most 64 bits wide, so i128 cannot overflow
+                    let value = match op {
+                        BinaryOp::Add => a + b,
+                        BinaryOp::Sub => a - b,
+                        _ => a * b,
+                    };
+                    if (min..=max).contains(&value) {
+                        Some(value)
+                    } else {
+                        violations.overflows += 1;
+                        let clamped = value.clamp(min, max);
+                        self.resolve(lhs, row, "Arithmetic overflow", Some(clamped))?
+                    }
+                }
+                _ => None,
+            };
+            values.push(value);
+        }
+
+        let series = if dtype == DataType::UInt64 {
+            let values: Vec<Option<u64>> = values.iter().map(|v| v.map(|v| v as u64)).collect();
+            Series::new(lhs.name().clone(), values)
+        } else {
+            let values: Vec<Option<i64>> = values.iter().map(|v| v.map(|v| v as i64)).collect();
+            Series::new(lhs.name().clone(), values).cast(&dtype)?
+        };
+        Ok((series, violations))
+    }
+
+    fn apply_float(
+        self,
+        op: BinaryOp,
+        lhs: &Series,
+        rhs: &Series,
+    ) -> Result<(Series, ArithmeticViolations)> {
+        let single = lhs.dtype() == &DataType::Float32 && rhs.dtype() == &DataType::Float32;
+        let max = if single { f32::MAX as f64 } else { f64::MAX };
+        let lhs_values = lhs.cast(&DataType::Float64)?;
+        let rhs_values = rhs.cast(&DataType::Float64)?;
+
+        let mut violations = ArithmeticViolations::default();
+        let mut values = Vec::with_capacity(lhs.len());
+        for (row, (a, b)) in lhs_values
+            .f64()?
+            .into_iter()
+            .zip(rhs_values.f64()?)
+            .enumerate()
+        {
+            let value = match (a, b) {
+                (Some(a), Some(b)) if op == BinaryOp::Div && b == 0.0 && a.is_finite() => {
+                    violations.divisions_by_zero += 1;
+                    let clamped = (a != 0.0).then(|| max.copysign(a));
+                    self.resolve(lhs, row, "Division by zero", clamped)?
+                }
+                (Some(a), Some(b)) => {
+                    let value = match op {
+                        BinaryOp::Add => a + b,
+                        BinaryOp::Sub => a - b,
+                        BinaryOp::Mul => a * b,
+                        BinaryOp::Div => a / b,
+                    };
+                    if value.abs() > max && a.is_finite() && b.is_finite() {
+                        violations.overflows += 1;
+                        let clamped = max.copysign(value);
+                        self.resolve(lhs, row, "Arithmetic overflow", Some(clamped))?
+                    } else {
+                        Some(value)
+                    }
+                }
+                _ => None,
+            };
+            values.push(value);
+        }
+
+        let series = Series::new(lhs.name().clone(), values);
+        let series = if single {
+            series.cast(&DataType::Float32)?
+        } else {
+            series
+        };
+        Ok((series, violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let a = Series::new("a".into(), [Some(100i8), Some(-100), None, Some(1)]);
+        let b = Series::new("b".into(), [Some(100i8), Some(100), Some(1), Some(1)]);
+
+        let (sum, violations) = ArithmeticPolicy::Clamp
+            .apply(BinaryOp::Add, &a, &b)
+            .unwrap();
+        assert_eq!(sum.dtype(), &DataType::Int8);
+        let sum: Vec<Option<i8>> = sum.i8().unwrap().into_iter().collect();
+        assert_eq!(sum, vec![Some(127), Some(0), None, Some(2)]);
+        assert_eq!(violations.overflows, 1);
+
+        let (ratio, violations) = ArithmeticPolicy::Null
+            .apply(BinaryOp::Div, &a, &(&b - &b).unwrap())
+            .unwrap();
+        assert_eq!(ratio.null_count(), 4);
+        assert_eq!(violations.divisions_by_zero, 3);
+
+        let error = ArithmeticPolicy::Error.apply(BinaryOp::Sub, &a, &b);
+        assert!(error.unwrap_err().to_string().contains("row 1"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Arrow interop
+//!
+//! Services receiving Arrow data (e.g. over Flight) or handing results to another
+//! Arrow-native runtime exchange `TimeSeriesData` as Arrow record batches, or
+//! through the Arrow C stream interface across a language boundary, instead of
+//! going through CSV or Parquet files. Column buffers are shared, not copied;
+//! only Arrow types Polars stores differently (e.g. `large_utf8` strings) are
+//! converted on import.
+//!
+//! Record batches carry only the columns. The C stream also carries the time
+//! column, group columns, time zone and tags as metadata of its struct field
+//! (the schema metadata of a pyarrow `RecordBatchReader`), so a round trip
+//! preserves them.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use polars_arrow::array::{Array, StructArray};
+use polars_arrow::datatypes::{ArrowDataType, Field, Metadata};
+use polars_arrow::ffi::{ArrowArrayStream, ArrowArrayStreamReader, export_iterator};
+use polars_arrow::record_batch::RecordBatch;
+use std::collections::HashMap;
+
+const TIME_COLUMN_KEY: &str = "industryts.time_column";
+const GROUP_COLUMNS_KEY: &str = "industryts.group_columns";
+const TIME_ZONE_KEY: &str = "industryts.time_zone";
+const TAGS_KEY: &str = "industryts.tags";
+
+impl TimeSeriesData {
+    /// Build a time series from Arrow record batches with the same schema
+    ///
+    /// The time column is detected by name and dtype when `time_column` is `None`.
+    pub fn from_arrow(batches: Vec<RecordBatch>, time_column: Option<&str>) -> Result<Self> {
+        let Some(first) = batches.first() else {
+            return Err(IndustrytsError::InvalidOperation(
+                "Cannot build a time series from zero record batches".to_string(),
+            ));
+        };
+        let schema = first.schema().clone();
+        let mut chunks: Vec<Vec<Box<dyn Array>>> = vec![Vec::new(); schema.len()];
+        for batch in batches {
+            if batch.schema() != &schema {
+                return Err(IndustrytsError::InvalidOperation(
+                    "Record batches have different schemas".to_string(),
+                ));
+            }
+            for (column, array) in chunks.iter_mut().zip(batch.into_arrays()) {
+                column.push(array);
+            }
+        }
+
+        let columns = schema
+            .iter_values()
+            .zip(chunks)
+            .map(|(field, arrays)| {
+                Ok(Series::from_arrow_chunks(field.name.clone(), arrays)?.into_column())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        TimeSeriesData::new(DataFrame::new(columns)?, time_column)
+    }
+
+    /// The data as Arrow record batches, one per chunk of the dataframe
+    pub fn to_arrow(&self) -> Vec<RecordBatch> {
+        self.dataframe()
+            .iter_chunks(CompatLevel::newest(), false)
+            .collect()
+    }
+
+    /// Export the data through the Arrow C stream interface
+    ///
+    /// Each record batch becomes a struct array of the stream. The consumer
+    /// releases the stream, e.g. `pyarrow.RecordBatchReader._import_from_c`.
+    pub fn to_arrow_stream(&self) -> ArrowArrayStream {
+        let batches = self.to_arrow();
+        let fields: Vec<Field> = self
+            .dataframe()
+            .get_columns()
+            .iter()
+            .map(|c| c.field().to_arrow(CompatLevel::newest()))
+            .collect();
+        let dtype = ArrowDataType::Struct(fields);
+
+        let metadata = self.metadata();
+        let mut stream_metadata = Metadata::new();
+        let mut insert = |key: &str, value: String| {
+            stream_metadata.insert(key.into(), value.into());
+        };
+        insert(TIME_COLUMN_KEY, metadata.time_column.clone());
+        insert(
+            GROUP_COLUMNS_KEY,
+            serde_json::Value::from(metadata.group_columns.clone()).to_string(),
+        );
+        if let Some(zone) = &metadata.time_zone {
+            insert(TIME_ZONE_KEY, zone.clone());
+        }
+        insert(TAGS_KEY, serde_json::json!(metadata.tags).to_string());
+        let field = Field::new("".into(), dtype.clone(), false).with_metadata(stream_metadata);
+
+        let arrays = batches.into_iter().map(move |batch| {
+            let length = batch.height();
+            let array = StructArray::new(dtype.clone(), length, batch.into_arrays(), None);
+            Ok(array.boxed())
+        });
+        export_iterator(Box::new(arrays), field)
+    }
+
+    /// Import data from the Arrow C stream interface
+    ///
+    /// The stream must yield struct arrays, one per record batch. Metadata written
+    /// by `to_arrow_stream` is restored; `time_column` overrides the stored one.
+    ///
+    /// # Safety
+    ///
+    /// `stream` must be a valid Arrow C stream that has not been released.
+    pub unsafe fn from_arrow_stream(
+        stream: &mut ArrowArrayStream,
+        time_column: Option<&str>,
+    ) -> Result<Self> {
+        // SAFETY: validity of the stream is the caller's contract
+        let mut reader = unsafe { ArrowArrayStreamReader::try_new(stream)? };
+        let field = reader.field().clone();
+        let ArrowDataType::Struct(fields) = &field.dtype else {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "Arrow stream must hold struct arrays, got {:?}",
+                field.dtype
+            )));
+        };
+        let schema: ArrowSchema = fields.iter().map(|f| (f.name.clone(), f.clone())).collect();
+        let schema = Arc::new(schema);
+
+        let mut batches = Vec::new();
+        // SAFETY: as above
+        while let Some(array) = unsafe { reader.next() } {
+            let array = array?;
+            let Some(array) = array.as_any().downcast_ref::<StructArray>() else {
+                return Err(IndustrytsError::InvalidOperation(
+                    "Arrow stream yielded a non-struct array".to_string(),
+                ));
+            };
+            let columns = array.values().to_vec();
+            batches.push(RecordBatch::try_new(array.len(), schema.clone(), columns)?);
+        }
+
+        let stored = |key: &str| {
+            field
+                .metadata
+                .as_ref()
+                .and_then(|m| m.get(key))
+                .map(|v| v.to_string())
+        };
+        let time_column = time_column.map(str::to_string).or(stored(TIME_COLUMN_KEY));
+        let mut data = if batches.is_empty() {
+            let columns = fields
+                .iter()
+                .map(|f| {
+                    let empty = polars_arrow::array::new_empty_array(f.dtype.clone());
+                    Ok(Series::from_arrow(f.name.clone(), empty)?.into_column())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            TimeSeriesData::new(DataFrame::new(columns)?, time_column.as_deref())?
+        } else {
+            TimeSeriesData::from_arrow(batches, time_column.as_deref())?
+        };
+
+        if let Some(groups) = stored(GROUP_COLUMNS_KEY) {
+            let groups: Vec<String> = serde_json::from_str(&groups)?;
+            data = data.with_group_columns(&groups)?;
+        }
+        if let Some(tags) = stored(TAGS_KEY) {
+            let tags: HashMap<String, String> = serde_json::from_str(&tags)?;
+            data.metadata_mut().tags = tags;
+        }
+        data.metadata_mut().time_zone = stored(TIME_ZONE_KEY);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_arrow_round_trips() {
+        let mut data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("unit", ["a", "b", "a"])
+            .with_column("flow", [Some(1.5), None, Some(2.5)])
+            .with_group_columns(&["unit"])
+            .build()
+            .unwrap();
+        data.set_column_property("flow", "unit", "m3/h");
+
+        let batches = data.to_arrow();
+        assert_eq!(batches[0].height(), 3);
+        let imported = TimeSeriesData::from_arrow(batches, None).unwrap();
+        assert!(imported.dataframe().equals_missing(data.dataframe()));
+        assert_eq!(imported.time_column(), "time");
+
+        let mut stream = data.to_arrow_stream();
+        let imported = unsafe { TimeSeriesData::from_arrow_stream(&mut stream, None) }.unwrap();
+        assert!(imported.dataframe().equals_missing(data.dataframe()));
+        assert_eq!(imported.group_columns(), ["unit"]);
+        assert_eq!(imported.column_unit("flow"), Some("m3/h"));
+    }
+}
@@ -0,0 +1,19 @@
+//! Async operation trait
+//!
+//! Operations that wait on network I/O (connectors, model-serving calls) implement
+//! `AsyncOperation` so they can be awaited on a tokio runtime by
+//! `Pipeline::process_async` instead of blocking a worker thread.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::Result;
+use async_trait::async_trait;
+
+/// Trait for asynchronous time series operations
+#[async_trait]
+pub trait AsyncOperation: Send + Sync {
+    /// Execute the operation on time series data
+    async fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData>;
+
+    /// Get the name of the operation
+    fn name(&self) -> &str;
+}
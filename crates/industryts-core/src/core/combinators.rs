@@ -0,0 +1,570 @@
+//! Operation combinators
+//!
+//! `OperationExt` composes operations inline without building a `Pipeline`, e.g.
+//! `fill.then(standardize).on_columns(cols).or_else(fallback)`. It is implemented
+//! for every operation, including `Box<dyn Operation>`.
+
+use crate::core::context::OpContext;
+use crate::core::data::TimeSeriesData;
+use crate::core::naming::NamingPolicy;
+use crate::core::operation::{Operation, OperationMetadata};
+use crate::error::{IndustrytsError, Result};
+use crate::random::SeedSequence;
+use polars::prelude::LazyFrame;
+use std::time::Duration;
+
+impl Operation for Box<dyn Operation> {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        (**self).execute(data)
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        (**self).execute_with_context(data, ctx)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        (**self).set_seed(seed)
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        (**self).set_naming(naming)
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        (**self).required_columns()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        (**self).added_columns(feature_columns)
+    }
+
+    fn catalog_inputs(&self) -> Vec<String> {
+        (**self).catalog_inputs()
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        (**self).lookahead()
+    }
+
+    fn removes_rows(&self) -> bool {
+        (**self).removes_rows()
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        (**self).row_bounds(input_rows)
+    }
+
+    fn reorders_rows(&self) -> bool {
+        (**self).reorders_rows()
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        (**self).manages_time_zone()
+    }
+
+    fn apply_lazy(&self, lf: LazyFrame, time_column: &str) -> Result<LazyFrame> {
+        (**self).apply_lazy(lf, time_column)
+    }
+
+    fn warmup(&self) -> Duration {
+        (**self).warmup()
+    }
+
+    fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+        (**self).validate(data)
+    }
+
+    fn metadata(&self) -> OperationMetadata {
+        (**self).metadata()
+    }
+}
+
+/// Combinator methods available on every operation
+pub trait OperationExt: Operation + Sized {
+    /// Run `next` on the output of this operation
+    fn then<B: Operation>(self, next: B) -> Then<Self, B> {
+        let name = format!("{}.then({})", self.name(), next.name());
+        Then {
+            first: self,
+            second: next,
+            name,
+        }
+    }
+
+    /// Apply this operation to `columns` only, leaving other columns untouched
+    ///
+    /// The operation sees the time and group columns plus `columns`; its results
+    /// replace (or are added next to) the original columns. The operation must keep
+    /// the rows in place: neither remove nor reorder them.
+    fn on_columns(self, columns: Vec<String>) -> OnColumns<Self> {
+        let name = format!("{}.on_columns({})", self.name(), columns.join(","));
+        OnColumns {
+            inner: self,
+            columns,
+            name,
+        }
+    }
+
+    /// Run `fallback` on the original input if this operation fails
+    fn or_else<B: Operation>(self, fallback: B) -> OrElse<Self, B> {
+        let name = format!("{}.or_else({})", self.name(), fallback.name());
+        OrElse {
+            primary: self,
+            fallback,
+            name,
+        }
+    }
+}
+
+impl<T: Operation + Sized> OperationExt for T {}
+
+/// Sequential composition, see [`OperationExt::then`]
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+    name: String,
+}
+
+impl<A: Operation, B: Operation> Operation for Then<A, B> {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.second.execute(self.first.execute(data)?)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let data = self.first.execute_with_context(data, ctx)?;
+        ctx.check_cancelled()?;
+        self.second.execute_with_context(data, ctx)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        let seeds = SeedSequence::new(seed);
+        self.first.set_seed(seeds.derive("first"));
+        self.second.set_seed(seeds.derive("second"));
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.first.set_naming(naming);
+        self.second.set_naming(naming);
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let produced = self.first.added_columns(&[]);
+        let mut columns = self.first.required_columns();
+        for column in self.second.required_columns() {
+            if !produced.contains(&column) && !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let mut added = self.first.added_columns(feature_columns);
+        let mut next = feature_columns.to_vec();
+        next.extend(added.iter().cloned());
+        for column in self.second.added_columns(&next) {
+            if !added.contains(&column) {
+                added.push(column);
+            }
+        }
+        added
+    }
+
+    fn catalog_inputs(&self) -> Vec<String> {
+        let mut inputs = self.first.catalog_inputs();
+        for input in self.second.catalog_inputs() {
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+        inputs
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        let mut reasons = self.first.lookahead();
+        reasons.extend(self.second.lookahead());
+        reasons
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.first.removes_rows() || self.second.removes_rows()
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        let (min, max) = self.first.row_bounds(input_rows);
+        (
+            self.second.row_bounds(min).0,
+            max.and_then(|max| self.second.row_bounds(max).1),
+        )
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.first.reorders_rows() || self.second.reorders_rows()
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        self.first.manages_time_zone() || self.second.manages_time_zone()
+    }
+
+    fn apply_lazy(&self, lf: LazyFrame, time_column: &str) -> Result<LazyFrame> {
+        let lf = self.first.apply_lazy(lf, time_column)?;
+        self.second.apply_lazy(lf, time_column)
+    }
+
+    fn warmup(&self) -> Duration {
+        self.first.warmup() + self.second.warmup()
+    }
+
+    fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+        self.first.validate(data)
+    }
+}
+
+/// Column-restricted operation, see [`OperationExt::on_columns`]
+pub struct OnColumns<O> {
+    inner: O,
+    columns: Vec<String>,
+    name: String,
+}
+
+impl<O: Operation> OnColumns<O> {
+    fn run(
+        &self,
+        data: TimeSeriesData,
+        mut execute: impl FnMut(&O, TimeSeriesData) -> Result<TimeSeriesData>,
+    ) -> Result<TimeSeriesData> {
+        // Results are written back by position
+        if self.inner.reorders_rows() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "{} reorders rows and cannot be applied to a column subset",
+                self.inner.name()
+            )));
+        }
+        let time_col = data.time_column().to_string();
+        let mut selected = vec![time_col.clone()];
+        for column in data.group_columns().iter().chain(&self.columns) {
+            if !selected.contains(column) {
+                selected.push(column.clone());
+            }
+        }
+
+        let subset = data.dataframe().select(selected)?;
+        let mut metadata = data.metadata().clone();
+        metadata.feature_columns = self.columns.clone();
+        let result = execute(
+            &self.inner,
+            TimeSeriesData::with_metadata(subset, metadata)?,
+        )?;
+
+        if result.len() != data.len() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "{} changed the row count ({} -> {}) and cannot be applied to a column subset",
+                self.inner.name(),
+                data.len(),
+                result.len()
+            )));
+        }
+
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for column in result.dataframe().get_columns() {
+            let name = column.name().as_str();
+            if name == time_col || metadata.group_columns.iter().any(|g| g == name) {
+                continue;
+            }
+            if !metadata.feature_columns.iter().any(|c| c == name) {
+                metadata.feature_columns.push(name.to_string());
+            }
+            df.with_column(column.clone())?;
+        }
+
+        let mut output = TimeSeriesData::with_metadata(df, metadata)?;
+        for (key, value) in &result.metadata().tags {
+            output.add_tag(key.clone(), value.clone());
+        }
+        Ok(output)
+    }
+}
+
+impl<O: Operation> Operation for OnColumns<O> {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, |op, data| op.execute(data))
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, |op, data| op.execute_with_context(data, ctx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.inner.set_seed(seed)
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.inner.set_naming(naming)
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone();
+        for column in self.inner.required_columns() {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    fn added_columns(&self, _feature_columns: &[String]) -> Vec<String> {
+        self.inner.added_columns(&self.columns)
+    }
+
+    fn catalog_inputs(&self) -> Vec<String> {
+        self.inner.catalog_inputs()
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        self.inner.lookahead()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.inner.warmup()
+    }
+
+    fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+        let df = data.dataframe();
+        for col in &self.columns {
+            if df.column(col).is_err() {
+                return Err(IndustrytsError::ColumnNotFound(col.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fallback composition, see [`OperationExt::or_else`]
+pub struct OrElse<A, B> {
+    primary: A,
+    fallback: B,
+    name: String,
+}
+
+impl<A: Operation, B: Operation> Operation for OrElse<A, B> {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        match self.primary.execute(data.clone()) {
+            Ok(result) => Ok(result),
+            Err(_) => self.fallback.execute(data),
+        }
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        // Outputs of a failed primary are discarded along with its result
+        let outputs = ctx.outputs().clone();
+        match self.primary.execute_with_context(data.clone(), ctx) {
+            Ok(result) => Ok(result),
+            Err(IndustrytsError::Cancelled) => Err(IndustrytsError::Cancelled),
+            Err(e) => {
+                *ctx.outputs_mut() = outputs;
+                ctx.warn(format!(
+                    "{} failed, falling back to {}: {}",
+                    self.primary.name(),
+                    self.fallback.name(),
+                    e
+                ));
+                self.fallback.execute_with_context(data, ctx)
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        let seeds = SeedSequence::new(seed);
+        self.primary.set_seed(seeds.derive("primary"));
+        self.fallback.set_seed(seeds.derive("fallback"));
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.primary.set_naming(naming);
+        self.fallback.set_naming(naming);
+    }
+
+    // Only what both branches agree on is guaranteed, whichever one runs
+
+    fn required_columns(&self) -> Vec<String> {
+        let fallback = self.fallback.required_columns();
+        self.primary
+            .required_columns()
+            .into_iter()
+            .filter(|c| fallback.contains(c))
+            .collect()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let fallback = self.fallback.added_columns(feature_columns);
+        self.primary
+            .added_columns(feature_columns)
+            .into_iter()
+            .filter(|c| fallback.contains(c))
+            .collect()
+    }
+
+    // Either branch may run, so both branches' inputs are needed
+    fn catalog_inputs(&self) -> Vec<String> {
+        let mut inputs = self.primary.catalog_inputs();
+        for input in self.fallback.catalog_inputs() {
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+        inputs
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        let mut reasons = self.primary.lookahead();
+        reasons.extend(self.fallback.lookahead());
+        reasons
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.primary.removes_rows() || self.fallback.removes_rows()
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        let (primary_min, primary_max) = self.primary.row_bounds(input_rows);
+        let (fallback_min, fallback_max) = self.fallback.row_bounds(input_rows);
+        (
+            primary_min.min(fallback_min),
+            primary_max.zip(fallback_max).map(|(a, b)| a.max(b)),
+        )
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.primary.reorders_rows() || self.fallback.reorders_rows()
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        self.primary.manages_time_zone() || self.fallback.manages_time_zone()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.primary.warmup().max(self.fallback.warmup())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    struct Scale(f64);
+
+    impl Operation for Scale {
+        fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+            let time_col = data.time_column().to_string();
+            let df = data
+                .dataframe()
+                .clone()
+                .lazy()
+                .with_columns(
+                    data.feature_columns()
+                        .iter()
+                        .map(|c| col(c.as_str()) * lit(self.0))
+                        .collect::<Vec<_>>(),
+                )
+                .collect()?;
+            TimeSeriesData::new(df, Some(&time_col))
+        }
+
+        fn name(&self) -> &str {
+            "scale"
+        }
+    }
+
+    struct Fail;
+
+    impl Operation for Fail {
+        fn execute(&self, _data: TimeSeriesData) -> Result<TimeSeriesData> {
+            Err(IndustrytsError::OperationError("boom".to_string()))
+        }
+
+        fn name(&self) -> &str {
+            "fail"
+        }
+    }
+
+    fn make_data() -> TimeSeriesData {
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("a".into(), &[1.0, 2.0]).into(),
+            Series::new("b".into(), &[10.0, 20.0]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn values(data: &TimeSeriesData, column: &str) -> Vec<Option<f64>> {
+        data.dataframe()
+            .column(column)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_then_on_columns() {
+        let op = Scale(2.0)
+            .then(Scale(3.0))
+            .on_columns(vec!["a".to_string()]);
+        let result = op.execute(make_data()).unwrap();
+
+        assert_eq!(op.name(), "scale.then(scale).on_columns(a)");
+        assert_eq!(values(&result, "a"), vec![Some(6.0), Some(12.0)]);
+        assert_eq!(values(&result, "b"), vec![Some(10.0), Some(20.0)]);
+
+        // Rows are written back by position, so reordering steps are refused
+        let sorted =
+            crate::operations::SortByTimeOperation::new().on_columns(vec!["a".to_string()]);
+        assert!(sorted.execute(make_data()).is_err());
+    }
+
+    #[test]
+    fn test_or_else_falls_back() {
+        let boxed: Box<dyn Operation> = Box::new(Fail);
+        let result = boxed.or_else(Scale(10.0)).execute(make_data()).unwrap();
+        assert_eq!(values(&result, "a"), vec![Some(10.0), Some(20.0)]);
+    }
+}
@@ -0,0 +1,367 @@
+//! Comparison of consecutive runs
+//!
+//! A scheduled pipeline whose input feed silently changed (a tag renamed, an
+//! archive gap, a unit switch upstream) still runs successfully; only its results
+//! look different. `ExecutionContext::compare` checks the row, column and null
+//! counts of each step against the previous run, and `OutputProfile` checks the
+//! statistics of the output columns. Both return a `RunComparison` listing the
+//! deviations beyond the tolerances, so a run can be flagged before its results
+//! are published. Profiles serialize to JSON to be kept between runs.
+
+use crate::core::context::ExecutionContext;
+use crate::core::data::TimeSeriesData;
+use crate::error::Result;
+use crate::operations::data_quality::expectations::{ColumnProfile, ExpectationSuite};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Deviations tolerated between two runs
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ComparisonTolerance {
+    /// Maximum relative change of a row count
+    pub row_ratio: f64,
+    /// Maximum change of the null ratio of a step or column
+    pub null_ratio_delta: f64,
+    /// Maximum shift of a column mean, in standard deviations of the previous run
+    pub mean_shift_sigmas: f64,
+    /// Maximum ratio between the standard deviations of the runs (either way)
+    pub std_ratio: f64,
+}
+
+impl Default for ComparisonTolerance {
+    fn default() -> Self {
+        Self {
+            row_ratio: 0.1,
+            null_ratio_delta: 0.05,
+            mean_shift_sigmas: 3.0,
+            std_ratio: 2.0,
+        }
+    }
+}
+
+/// A figure that changed beyond its tolerance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deviation {
+    /// Step (`"2:fill_null"`) or column the figure belongs to
+    pub subject: String,
+    /// Figure compared, e.g. `output_rows` or `mean`
+    pub measure: String,
+    pub previous: f64,
+    pub current: f64,
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.subject, self.measure, self.previous, self.current
+        )
+    }
+}
+
+/// Result of comparing a run with the previous one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunComparison {
+    pub deviations: Vec<Deviation>,
+}
+
+impl RunComparison {
+    /// Whether any figure deviates beyond its tolerance
+    pub fn is_suspicious(&self) -> bool {
+        !self.deviations.is_empty()
+    }
+
+    fn push(&mut self, subject: &str, measure: &str, previous: f64, current: f64) {
+        self.deviations.push(Deviation {
+            subject: subject.to_string(),
+            measure: measure.to_string(),
+            previous,
+            current,
+        });
+    }
+
+    /// Flag `measure` if it changed by more than `ratio` of its previous value
+    fn check_ratio(
+        &mut self,
+        subject: &str,
+        measure: &str,
+        previous: f64,
+        current: f64,
+        ratio: f64,
+    ) {
+        if (current - previous).abs() > ratio * previous.abs().max(1.0) {
+            self.push(subject, measure, previous, current);
+        }
+    }
+
+    /// Flag `measure` if it changed by more than `delta`
+    fn check_delta(
+        &mut self,
+        subject: &str,
+        measure: &str,
+        previous: f64,
+        current: f64,
+        delta: f64,
+    ) {
+        if (current - previous).abs() > delta {
+            self.push(subject, measure, previous, current);
+        }
+    }
+}
+
+impl fmt::Display for RunComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.deviations.is_empty() {
+            return write!(f, "no deviation from the previous run");
+        }
+        for (i, deviation) in self.deviations.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", deviation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts of one step summed over its chunks
+#[derive(Default)]
+struct StepTotals {
+    output_rows: usize,
+    output_columns: usize,
+    output_nulls: usize,
+}
+
+impl StepTotals {
+    /// Nulls per output cell (the time column counts as a cell)
+    fn null_ratio(&self) -> f64 {
+        let cells = self.output_rows * self.output_columns;
+        if cells == 0 {
+            0.0
+        } else {
+            self.output_nulls as f64 / cells as f64
+        }
+    }
+}
+
+/// Totals per step, keyed `"<position>:<name>"` in execution order
+fn step_totals(context: &ExecutionContext) -> Vec<(String, StepTotals)> {
+    let mut steps: Vec<(String, StepTotals)> = Vec::new();
+    for metrics in context.metrics() {
+        let key = match metrics.step {
+            Some(step) => format!("{}:{}", step, metrics.operation_name),
+            None => metrics.operation_name.clone(),
+        };
+        let index = match steps.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                steps.push((key, StepTotals::default()));
+                steps.len() - 1
+            }
+        };
+        let totals = &mut steps[index].1;
+        totals.output_rows += metrics.output_rows;
+        totals.output_columns = totals.output_columns.max(metrics.output_columns);
+        totals.output_nulls += metrics.output_nulls;
+    }
+    steps
+}
+
+impl ExecutionContext {
+    /// Compare the steps of this run with `previous` at the default tolerances
+    pub fn compare(&self, previous: &ExecutionContext) -> RunComparison {
+        self.compare_with_tolerance(previous, &ComparisonTolerance::default())
+    }
+
+    /// Compare output rows, columns and null ratios of each step with `previous`
+    ///
+    /// Steps are matched by position and name; a step present in only one run
+    /// is reported with a `ran` count of 0 or 1.
+    pub fn compare_with_tolerance(
+        &self,
+        previous: &ExecutionContext,
+        tolerance: &ComparisonTolerance,
+    ) -> RunComparison {
+        let current = step_totals(self);
+        let previous = step_totals(previous);
+        let mut comparison = RunComparison::default();
+        for (step, before) in &previous {
+            let Some((_, after)) = current.iter().find(|(k, _)| k == step) else {
+                comparison.push(step, "ran", 1.0, 0.0);
+                continue;
+            };
+            comparison.check_ratio(
+                step,
+                "output_rows",
+                before.output_rows as f64,
+                after.output_rows as f64,
+                tolerance.row_ratio,
+            );
+            comparison.check_delta(
+                step,
+                "output_columns",
+                before.output_columns as f64,
+                after.output_columns as f64,
+                0.0,
+            );
+            comparison.check_delta(
+                step,
+                "null_ratio",
+                before.null_ratio(),
+                after.null_ratio(),
+                tolerance.null_ratio_delta,
+            );
+        }
+        for (step, _) in &current {
+            if !previous.iter().any(|(k, _)| k == step) {
+                comparison.push(step, "ran", 0.0, 1.0);
+            }
+        }
+        comparison
+    }
+}
+
+/// Row count and per-column statistics of a pipeline output
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OutputProfile {
+    pub rows: usize,
+    /// Profiles per feature column
+    pub columns: BTreeMap<String, ColumnProfile>,
+}
+
+impl OutputProfile {
+    /// Profile the feature columns of `data`
+    pub fn of(data: &TimeSeriesData) -> Result<Self> {
+        let suite = ExpectationSuite::profile("output", data)?;
+        Ok(Self {
+            rows: suite.rows,
+            columns: suite.columns,
+        })
+    }
+
+    /// Compare with the output of the previous run
+    ///
+    /// Missing or new columns are reported with a `present` count of 0 or 1.
+    pub fn compare(
+        &self,
+        previous: &OutputProfile,
+        tolerance: &ComparisonTolerance,
+    ) -> RunComparison {
+        let mut comparison = RunComparison::default();
+        comparison.check_ratio(
+            "output",
+            "rows",
+            previous.rows as f64,
+            self.rows as f64,
+            tolerance.row_ratio,
+        );
+        for (column, before) in &previous.columns {
+            let Some(after) = self.columns.get(column) else {
+                comparison.push(column, "present", 1.0, 0.0);
+                continue;
+            };
+            comparison.check_delta(
+                column,
+                "null_ratio",
+                before.null_ratio,
+                after.null_ratio,
+                tolerance.null_ratio_delta,
+            );
+            if let (Some(mean_before), Some(mean_after)) = (before.mean, after.mean) {
+                let std = before.std.unwrap_or(0.0);
+                comparison.check_delta(
+                    column,
+                    "mean",
+                    mean_before,
+                    mean_after,
+                    tolerance.mean_shift_sigmas * std,
+                );
+            }
+            if let (Some(std_before), Some(std_after)) = (before.std, after.std) {
+                let spread_changed = if std_before > 0.0 && std_after > 0.0 {
+                    let ratio = std_after / std_before;
+                    ratio > tolerance.std_ratio || ratio < 1.0 / tolerance.std_ratio
+                } else {
+                    (std_before > 0.0) != (std_after > 0.0)
+                };
+                if spread_changed {
+                    comparison.push(column, "std", std_before, std_after);
+                }
+            }
+        }
+        for column in self.columns.keys() {
+            if !previous.columns.contains_key(column) {
+                comparison.push(column, "present", 0.0, 1.0);
+            }
+        }
+        comparison
+    }
+
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FillMethod;
+    use crate::operations::FillNullOperation;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_runs_deviating_from_previous() {
+        let run = |values: Vec<Option<f64>>| {
+            let data = Fixture::every(Duration::from_secs(60), values.len())
+                .with_column("flow", values)
+                .build()
+                .unwrap();
+            let mut pipeline = Pipeline::new();
+            let fill = FillNullOperation::new(FillMethod::Forward, None);
+            pipeline.add_operation(Box::new(fill));
+            pipeline
+                .process_with_context(data, ExecutionContext::new())
+                .unwrap()
+        };
+        let (output, previous) = run(vec![Some(1.0), None, Some(2.0), Some(3.0)]);
+        let (same_output, same) = run(vec![Some(1.0), Some(1.5), None, Some(3.0)]);
+        assert!(!same.compare(&previous).is_suspicious());
+
+        let (_, short) = run(vec![None, Some(2.0)]);
+        let comparison = short.compare(&previous);
+        let measures: Vec<&str> = comparison
+            .deviations
+            .iter()
+            .map(|d| d.measure.as_str())
+            .collect();
+        assert_eq!(measures, ["output_rows", "null_ratio"]);
+        assert_eq!(comparison.deviations[0].subject, "0:fill_null");
+
+        let tolerance = ComparisonTolerance::default();
+        let profile = OutputProfile::of(&output).unwrap();
+        let stored = OutputProfile::from_json_str(&profile.to_json_string().unwrap()).unwrap();
+        let same_profile = OutputProfile::of(&same_output).unwrap();
+        assert!(!same_profile.compare(&stored, &tolerance).is_suspicious());
+
+        let (shifted, _) = run(vec![Some(100.0), Some(101.0), Some(100.0), Some(101.0)]);
+        let comparison = OutputProfile::of(&shifted)
+            .unwrap()
+            .compare(&stored, &tolerance);
+        let measures: Vec<&str> = comparison
+            .deviations
+            .iter()
+            .map(|d| d.measure.as_str())
+            .collect();
+        assert_eq!(measures, ["mean"]);
+        assert!(comparison.to_string().starts_with("flow mean: "));
+    }
+}
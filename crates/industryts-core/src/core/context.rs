@@ -1,9 +1,30 @@
 //! Execution context for tracking and metrics
 //!
 //! This module provides execution context that tracks operation execution,
-//! performance metrics, and intermediate results.
+//! performance metrics, and intermediate results, and the per-operation `OpContext`
+//! handle through which operations read settings, emit warnings and custom metrics,
+//! add secondary outputs and check for cancellation.
+//!
+//! In chunked and streaming runs every chunk records its own metrics, so
+//! `ExecutionContext::timing_breakdown` reports the latency distribution of each
+//! step across chunks (p50/p95/max) instead of one average.
+//!
+//! For capacity planning, `ExecutionContext::summary` also reports the resources a
+//! run used: peak estimated memory of the data held, bytes read and written, and
+//! the CPU time of the process (Linux only), exportable with
+//! `ExecutionSummary::to_json`.
+//!
+//! Long runs can push interim metrics to external monitoring while they run; see
+//! `ExecutionContext::with_metrics_push`.
 
-use std::collections::HashMap;
+use crate::core::data::TimeSeriesData;
+use crate::core::output::OutputStore;
+use crate::core::push::{MetricsPush, MetricsSnapshot, PushInterval};
+use crate::error::{IndustrytsError, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
 /// Execution metrics for an operation
@@ -11,6 +32,8 @@ use std::time::{Duration, Instant};
 pub struct OperationMetrics {
     /// Name of the operation
     pub operation_name: String,
+    /// Position of the step in the pipeline, if recorded for a step
+    pub step: Option<usize>,
     /// Time taken to execute
     pub duration: Duration,
     /// Input row count
@@ -21,6 +44,16 @@ pub struct OperationMetrics {
     pub input_columns: usize,
     /// Output column count
     pub output_columns: usize,
+    /// Estimated memory of the output table in bytes
+    pub output_bytes: usize,
+    /// Nulls in the feature columns of the input
+    pub input_nulls: usize,
+    /// Nulls in the feature columns of the output
+    pub output_nulls: usize,
+    /// Warnings emitted by the operation
+    pub warnings: Vec<String>,
+    /// Custom metrics recorded by the operation
+    pub custom: HashMap<String, f64>,
 }
 
 impl OperationMetrics {
@@ -28,11 +61,17 @@ impl OperationMetrics {
     pub fn new(operation_name: String) -> Self {
         Self {
             operation_name,
+            step: None,
             duration: Duration::ZERO,
             input_rows: 0,
             output_rows: 0,
             input_columns: 0,
             output_columns: 0,
+            output_bytes: 0,
+            input_nulls: 0,
+            output_nulls: 0,
+            warnings: Vec::new(),
+            custom: HashMap::new(),
         }
     }
 
@@ -54,6 +93,18 @@ pub struct ExecutionContext {
     start_time: Instant,
     /// Custom metadata
     metadata: HashMap<String, String>,
+    /// Cancellation flag shared with running operations
+    cancellation: CancellationToken,
+    /// Largest estimated memory of the data held at once
+    peak_memory_bytes: usize,
+    bytes_read: u64,
+    bytes_written: u64,
+    /// CPU time of the process when the context was created
+    start_cpu_time: Option<Duration>,
+    /// Interim metrics pushed during the run
+    metrics_push: Option<MetricsPush>,
+    chunks: usize,
+    push_failures: usize,
 }
 
 impl ExecutionContext {
@@ -63,12 +114,95 @@ impl ExecutionContext {
             metrics: Vec::new(),
             start_time: Instant::now(),
             metadata: HashMap::new(),
+            cancellation: CancellationToken::new(),
+            peak_memory_bytes: 0,
+            bytes_read: 0,
+            bytes_written: 0,
+            start_cpu_time: process_cpu_time(),
+            metrics_push: None,
+            chunks: 0,
+            push_failures: 0,
         }
     }
 
+    /// Push interim metrics while the run progresses
+    pub fn with_metrics_push(mut self, push: MetricsPush) -> Self {
+        self.metrics_push = Some(push);
+        self
+    }
+
+    /// Use an externally controlled cancellation token
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Get the cancellation token (clone it to cancel from another thread)
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
     /// Record metrics for an operation
     pub fn record_metrics(&mut self, metrics: OperationMetrics) {
+        self.record_memory(metrics.output_bytes);
         self.metrics.push(metrics);
+        if let Some(PushInterval::Operations(n)) = self.metrics_push.as_ref().map(|p| p.interval())
+            && self.metrics.len().is_multiple_of(n)
+        {
+            self.push_metrics();
+        }
+    }
+
+    /// Record a completed chunk of a chunked or streaming run
+    pub fn record_chunk(&mut self) {
+        self.chunks += 1;
+        if let Some(PushInterval::Chunks(n)) = self.metrics_push.as_ref().map(|p| p.interval())
+            && self.chunks.is_multiple_of(n)
+        {
+            self.push_metrics();
+        }
+    }
+
+    /// Chunks completed so far
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+
+    /// Push the current metrics now, e.g. at the end of a run
+    ///
+    /// Does nothing without a `MetricsPush`. A failed push is counted, not returned.
+    pub fn push_metrics(&mut self) {
+        let Some(push) = &self.metrics_push else {
+            return;
+        };
+        let snapshot = MetricsSnapshot {
+            operations: self.metrics.len(),
+            chunks: self.chunks,
+            summary: self.summary(),
+        };
+        if push.push(&snapshot).is_err() {
+            self.push_failures += 1;
+        }
+    }
+
+    /// Number of metrics pushes that failed
+    pub fn push_failures(&self) -> usize {
+        self.push_failures
+    }
+
+    /// Record the estimated memory of the data held at some point of the run
+    pub fn record_memory(&mut self, bytes: usize) {
+        self.peak_memory_bytes = self.peak_memory_bytes.max(bytes);
+    }
+
+    /// Record bytes read from sources (files, databases, catalog inputs)
+    pub fn record_bytes_read(&mut self, bytes: u64) {
+        self.bytes_read += bytes;
+    }
+
+    /// Record bytes written to sinks
+    pub fn record_bytes_written(&mut self, bytes: u64) {
+        self.bytes_written += bytes;
     }
 
     /// Get all recorded metrics
@@ -113,6 +247,73 @@ impl ExecutionContext {
                 self.metrics.iter().map(|m| m.throughput()).sum::<f64>()
                     / self.metrics.len() as f64
             },
+            peak_memory_bytes: self.peak_memory_bytes,
+            bytes_read: self.bytes_read,
+            bytes_written: self.bytes_written,
+            cpu_time: process_cpu_time()
+                .zip(self.start_cpu_time)
+                .map(|(now, start)| now.saturating_sub(start)),
+        }
+    }
+
+    /// Latency distribution of each step over all recorded runs
+    ///
+    /// Metrics are grouped by step position and operation name, in the order the
+    /// steps first ran.
+    pub fn timing_breakdown(&self) -> Vec<OperationTiming> {
+        let mut groups: Vec<(Option<usize>, &str, Vec<Duration>)> = Vec::new();
+        for metrics in &self.metrics {
+            let key = (metrics.step, metrics.operation_name.as_str());
+            match groups
+                .iter_mut()
+                .find(|(step, name, _)| (*step, *name) == key)
+            {
+                Some((_, _, durations)) => durations.push(metrics.duration),
+                None => groups.push((key.0, key.1, vec![metrics.duration])),
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(step, name, durations)| OperationTiming::new(name, step, durations))
+            .collect()
+    }
+}
+
+/// Latency of one step across chunks
+#[derive(Debug, Clone)]
+pub struct OperationTiming {
+    /// Name of the operation
+    pub operation_name: String,
+    /// Position of the step in the pipeline
+    pub step: Option<usize>,
+    /// Number of recorded runs (chunks)
+    pub runs: usize,
+    /// Time spent over all runs
+    pub total: Duration,
+    /// Median run time
+    pub p50: Duration,
+    /// 95th percentile run time
+    pub p95: Duration,
+    /// Slowest run
+    pub max: Duration,
+}
+
+impl OperationTiming {
+    fn new(name: &str, step: Option<usize>, mut durations: Vec<Duration>) -> Self {
+        durations.sort_unstable();
+        // Nearest-rank percentile of the sorted run times
+        let percentile = |p: f64| {
+            let rank = (p * durations.len() as f64).ceil() as usize;
+            durations[rank.clamp(1, durations.len()) - 1]
+        };
+        Self {
+            operation_name: name.to_string(),
+            step,
+            runs: durations.len(),
+            total: durations.iter().sum(),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            max: durations.last().copied().unwrap_or_default(),
         }
     }
 }
@@ -134,6 +335,191 @@ pub struct ExecutionSummary {
     pub total_rows_processed: usize,
     /// Average throughput (rows per second)
     pub average_throughput: f64,
+    /// Largest estimated memory of the data held at once, in bytes
+    pub peak_memory_bytes: usize,
+    /// Bytes read: the estimated size of the run's input plus what sources record
+    pub bytes_read: u64,
+    /// Bytes written: the estimated size of the tables handed to sinks
+    pub bytes_written: u64,
+    /// CPU time used by the process during the run, on Linux
+    ///
+    /// Includes all threads of the process, so concurrent runs share it.
+    pub cpu_time: Option<Duration>,
+}
+
+impl ExecutionSummary {
+    /// The summary as a JSON object, with durations in milliseconds
+    pub fn to_json(&self) -> Result<String> {
+        let value = serde_json::json!({
+            "total_operations": self.total_operations,
+            "total_duration_ms": self.total_duration.as_secs_f64() * 1000.0,
+            "total_rows_processed": self.total_rows_processed,
+            "average_throughput": self.average_throughput,
+            "peak_memory_bytes": self.peak_memory_bytes,
+            "bytes_read": self.bytes_read,
+            "bytes_written": self.bytes_written,
+            "cpu_time_ms": self.cpu_time.map(|t| t.as_secs_f64() * 1000.0),
+        });
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+}
+
+/// User plus system CPU time of this process, from `/proc/self/stat`
+fn process_cpu_time() -> Option<Duration> {
+    // Clock ticks of /proc are fixed at 100 per second for user space
+    const TICKS_PER_SECOND: u64 = 100;
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // Fields after the parenthesized command name, starting with the state (field 3)
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(Duration::from_millis(
+        (utime + stime) * 1000 / TICKS_PER_SECOND,
+    ))
+}
+
+/// Cooperative cancellation flag shared between a caller and running operations
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Named datasets a run can read besides its input, shared between forks
+#[derive(Clone, Default)]
+struct Catalog(Arc<BTreeMap<String, TimeSeriesData>>);
+
+impl fmt::Debug for Catalog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Handle passed to `Operation::execute_with_context`
+#[derive(Debug, Default)]
+pub struct OpContext {
+    settings: HashMap<String, String>,
+    catalog: Catalog,
+    outputs: OutputStore,
+    warnings: Vec<String>,
+    metrics: HashMap<String, f64>,
+    cancellation: CancellationToken,
+}
+
+impl OpContext {
+    /// Create a context with no settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runtime settings visible to operations
+    pub fn with_settings(mut self, settings: HashMap<String, String>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Cancellation token checked by operations
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Named datasets readable with `input`, e.g. weather or lab data to join
+    pub fn with_catalog(mut self, catalog: BTreeMap<String, TimeSeriesData>) -> Self {
+        self.catalog = Catalog(Arc::new(catalog));
+        self
+    }
+
+    /// Get a runtime setting
+    pub fn setting(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(|s| s.as_str())
+    }
+
+    /// Get a named dataset of the run's catalog
+    pub fn input(&self, name: &str) -> Option<&TimeSeriesData> {
+        self.catalog.0.get(name)
+    }
+
+    /// Secondary outputs of the current run
+    pub fn outputs(&self) -> &OutputStore {
+        &self.outputs
+    }
+
+    /// Mutable access to secondary outputs
+    pub fn outputs_mut(&mut self) -> &mut OutputStore {
+        &mut self.outputs
+    }
+
+    /// Consume the context, returning the collected outputs
+    pub fn into_outputs(self) -> OutputStore {
+        self.outputs
+    }
+
+    /// Emit a warning
+    pub fn warn<S: Into<String>>(&mut self, message: S) {
+        self.warnings.push(message.into());
+    }
+
+    /// Record a custom metric
+    pub fn record_metric(&mut self, name: &str, value: f64) {
+        self.metrics.insert(name.to_string(), value);
+    }
+
+    /// Check whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Return `Err(Cancelled)` if cancellation was requested
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(IndustrytsError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a context sharing settings, catalog and cancellation, with empty outputs and
+    /// diagnostics
+    pub fn fork(&self) -> Self {
+        Self {
+            settings: self.settings.clone(),
+            catalog: self.catalog.clone(),
+            cancellation: self.cancellation.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Merge the outputs and diagnostics of a forked context back into this one
+    pub fn join(&mut self, other: OpContext) -> Result<()> {
+        for (name, table) in other.outputs.into_tables() {
+            self.outputs.insert(&name, table)?;
+        }
+        self.warnings.extend(other.warnings);
+        self.metrics.extend(other.metrics);
+        Ok(())
+    }
+
+    /// Take the warnings and metrics emitted since the last call
+    pub fn take_diagnostics(&mut self) -> (Vec<String>, HashMap<String, f64>) {
+        (
+            std::mem::take(&mut self.warnings),
+            std::mem::take(&mut self.metrics),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -167,6 +553,10 @@ mod tests {
 
     #[test]
     fn test_execution_summary() {
+        use crate::operations::LagOperation;
+        use crate::pipeline::Pipeline;
+        use crate::testing::Fixture;
+
         let mut ctx = ExecutionContext::new();
         let mut metrics = OperationMetrics::new("op1".to_string());
         metrics.input_rows = 1000;
@@ -177,5 +567,50 @@ mod tests {
         let summary = ctx.summary();
         assert_eq!(summary.total_operations, 1);
         assert_eq!(summary.total_rows_processed, 1000);
+
+        // A pipeline run reports its input and peak memory
+        let data = Fixture::every(Duration::from_secs(60), 100)
+            .with_column("temp", (0..100).map(f64::from).collect::<Vec<_>>())
+            .build()
+            .unwrap();
+        let input_bytes = data.dataframe().estimated_size() as u64;
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        let (_, ctx) = pipeline
+            .process_with_context(data, ExecutionContext::new())
+            .unwrap();
+        let summary = ctx.summary();
+        assert_eq!(summary.bytes_read, input_bytes);
+        assert!(summary.peak_memory_bytes as u64 > input_bytes);
+        assert_eq!(summary.bytes_written, 0);
+        if cfg!(target_os = "linux") {
+            assert!(summary.cpu_time.is_some());
+        }
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["bytes_read"], input_bytes);
+    }
+
+    #[test]
+    fn test_timing_breakdown_percentiles() {
+        let mut ctx = ExecutionContext::new();
+        for chunk in 1..=20u64 {
+            for (step, name) in ["lag", "lag"].iter().enumerate() {
+                let mut metrics = OperationMetrics::new(name.to_string());
+                metrics.step = Some(step);
+                // The second step has one slow chunk
+                let millis = if step == 1 && chunk == 7 { 500 } else { chunk };
+                metrics.duration = Duration::from_millis(millis);
+                ctx.record_metrics(metrics);
+            }
+        }
+
+        let timings = ctx.timing_breakdown();
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].runs, 20);
+        assert_eq!(timings[0].p50, Duration::from_millis(10));
+        assert_eq!(timings[0].p95, Duration::from_millis(19));
+        assert_eq!(timings[0].max, Duration::from_millis(20));
+        assert_eq!(timings[1].max, Duration::from_millis(500));
+        assert_eq!(timings[1].p95, Duration::from_millis(20));
     }
 }
@@ -4,20 +4,110 @@
 //! and provides time series-specific functionality.
 
 use crate::error::{IndustrytsError, Result};
+use crate::operations::temporal::parse::{DEFAULT_FORMATS, parse_timestamp_series};
 use polars::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Metadata about the time series data
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeSeriesMetadata {
     /// Name of the time column
     pub time_column: String,
     /// Names of feature columns
     pub feature_columns: Vec<String>,
+    /// Key columns identifying the entity of each row (e.g. `equipment_id`)
+    ///
+    /// Group columns are not feature columns. Group-aware operations (lag,
+    /// difference, fill, resample, rolling features) work within each entity.
+    pub group_columns: Vec<String>,
+    /// Local (plant) time zone of the data, e.g. "Europe/Berlin"
+    ///
+    /// The time column then holds UTC instants; calendar-aligned operations
+    /// (daily resampling, regularizing to a daily grid) follow the local clock.
+    pub time_zone: Option<String>,
     /// Additional metadata (key-value pairs)
     pub tags: HashMap<String, String>,
 }
 
+/// Options controlling how `TimeSeriesData::new_with_options` treats the time column
+#[derive(Debug, Clone, Default)]
+pub struct TimeColumnOptions {
+    /// Convert integer epoch columns (seconds or milliseconds) into Datetime
+    pub coerce_epoch: bool,
+    /// Convert string columns into Datetime (ISO-8601 and common formats by default)
+    pub coerce_strings: bool,
+    /// Candidate formats for string parsing (see `operations::temporal::parse`)
+    pub formats: Option<Vec<String>>,
+}
+
+impl TimeColumnOptions {
+    /// Coerce both epoch and string time columns with the default formats
+    pub fn coerce() -> Self {
+        Self {
+            coerce_epoch: true,
+            coerce_strings: true,
+            formats: None,
+        }
+    }
+}
+
+/// Floating-point precision of the feature columns held during a pipeline run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FloatPrecision {
+    /// Keep columns as produced
+    #[default]
+    F64,
+    /// Store floating-point columns as `Float32`
+    F32,
+}
+
+/// How NaN and null are treated in floating-point columns
+///
+/// Polars keeps NaN and null apart: aggregations and rolling statistics skip nulls
+/// but let a NaN poison every window it falls in, so data mixing both gives
+/// inconsistent results. A policy makes one of them the single marker of a
+/// missing value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NanPolicy {
+    /// Keep NaN and null as produced
+    #[default]
+    Keep,
+    /// Treat NaN as missing: convert NaN to null
+    Null,
+    /// Propagate missing values deliberately: convert null to NaN
+    Nan,
+}
+
+impl NanPolicy {
+    /// Apply the policy to a floating-point series; returns it and the values converted
+    ///
+    /// Other series are returned unchanged.
+    pub(crate) fn apply(self, series: &Series) -> Result<(Series, usize)> {
+        if self == NanPolicy::Keep || !series.dtype().is_float() {
+            return Ok((series.clone(), 0));
+        }
+        let keep = match self {
+            NanPolicy::Null => !&series.is_nan()?.fill_null_with_values(false)?,
+            _ => series.is_not_null(),
+        };
+        let converted = series.len() - keep.sum().unwrap_or(0) as usize;
+        if converted == 0 {
+            return Ok((series.clone(), 0));
+        }
+        let replacement = match self {
+            NanPolicy::Null => {
+                Series::full_null(series.name().clone(), series.len(), series.dtype())
+            }
+            _ => Series::new(series.name().clone(), vec![f64::NAN; series.len()])
+                .cast(series.dtype())?,
+        };
+        Ok((series.zip_with(&keep, &replacement)?, converted))
+    }
+}
+
 /// Core time series data structure wrapping a Polars DataFrame
 #[derive(Clone)]
 pub struct TimeSeriesData {
@@ -59,12 +149,49 @@ impl TimeSeriesData {
         let metadata = TimeSeriesMetadata {
             time_column: time_col,
             feature_columns,
+            group_columns: Vec::new(),
+            time_zone: None,
             tags: HashMap::new(),
         };
 
         Ok(Self { df, metadata })
     }
 
+    /// Create a new TimeSeriesData, coercing the time column according to `options`
+    ///
+    /// Integer epoch and string time columns are converted to millisecond Datetime
+    /// when the corresponding option is enabled; otherwise this behaves like `new`.
+    pub fn new_with_options(
+        mut df: DataFrame,
+        time_column: Option<&str>,
+        options: &TimeColumnOptions,
+    ) -> Result<Self> {
+        let time_col = if let Some(col) = time_column {
+            col.to_string()
+        } else {
+            Self::detect_time_column(&df)?
+        };
+
+        let series = df
+            .column(&time_col)
+            .map_err(|_| IndustrytsError::TimeColumnNotFound(time_col.clone()))?
+            .as_materialized_series()
+            .clone();
+
+        let coerce = (options.coerce_epoch && series.dtype().is_integer())
+            || (options.coerce_strings && *series.dtype() == DataType::String);
+        if coerce {
+            let formats: Vec<String> = match &options.formats {
+                Some(formats) => formats.clone(),
+                None => DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect(),
+            };
+            let parsed = parse_timestamp_series(&series, &formats, true)?;
+            df.replace(&time_col, parsed)?;
+        }
+
+        Self::new(df, Some(&time_col))
+    }
+
     /// Create a new TimeSeriesData with metadata
     pub fn with_metadata(df: DataFrame, metadata: TimeSeriesMetadata) -> Result<Self> {
         // Validate time column exists and has appropriate type
@@ -74,7 +201,7 @@ impl TimeSeriesData {
     }
 
     /// Auto-detect time column based on common naming patterns
-    fn detect_time_column(df: &DataFrame) -> Result<String> {
+    pub(crate) fn detect_time_column(df: &DataFrame) -> Result<String> {
         let common_names = [
             "DateTime",
             "datetime",
@@ -112,7 +239,7 @@ impl TimeSeriesData {
             .map_err(|_| IndustrytsError::TimeColumnNotFound(col_name.to_string()))?;
 
         match col.dtype() {
-            DataType::Date | DataType::Datetime(_, _) => Ok(()),
+            DataType::Date | DataType::Datetime(_, _) | DataType::Duration(_) => Ok(()),
             dtype => Err(IndustrytsError::InvalidTimeColumnType(format!(
                 "{:?}",
                 dtype
@@ -140,6 +267,114 @@ impl TimeSeriesData {
         &self.metadata.feature_columns
     }
 
+    /// Typical sampling interval: the median interval between distinct timestamps
+    ///
+    /// Grouped data is measured within each entity. Returns `None` with fewer than
+    /// two distinct timestamps.
+    pub fn infer_frequency(&self) -> Result<Option<std::time::Duration>> {
+        let partitions = if self.group_columns().is_empty() {
+            vec![self.clone()]
+        } else {
+            self.df
+                .partition_by(self.group_columns(), true)?
+                .into_iter()
+                .map(|df| Self::new(df, Some(self.time_column())))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut intervals = Vec::new();
+        for partition in partitions {
+            let mut times: Vec<i64> = partition.timestamps_ms()?.into_iter().flatten().collect();
+            times.sort_unstable();
+            times.dedup();
+            intervals.extend(times.windows(2).map(|w| w[1] - w[0]));
+        }
+        if intervals.is_empty() {
+            return Ok(None);
+        }
+        intervals.sort_unstable();
+        Ok(Some(std::time::Duration::from_millis(
+            intervals[intervals.len() / 2] as u64,
+        )))
+    }
+
+    /// Mark `columns` as the keys of interleaved series, one per entity
+    ///
+    /// The columns must exist and are removed from the feature columns.
+    pub fn with_group_columns(mut self, columns: &[String]) -> Result<Self> {
+        for column in columns {
+            if column == self.time_column() {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "time column '{}' cannot be a group column",
+                    column
+                )));
+            }
+            if self.df.column(column).is_err() {
+                return Err(IndustrytsError::ColumnNotFound(format!(
+                    "{} (group column)",
+                    column
+                )));
+            }
+        }
+        self.metadata
+            .feature_columns
+            .retain(|c| !columns.contains(c));
+        self.metadata.group_columns = columns.to_vec();
+        Ok(self)
+    }
+
+    /// Key columns of interleaved series (empty for a single series)
+    pub fn group_columns(&self) -> &[String] {
+        &self.metadata.group_columns
+    }
+
+    /// Local time zone of the data, if declared (see `TimeSeriesData::localize`)
+    pub fn time_zone(&self) -> Option<&str> {
+        self.metadata.time_zone.as_deref()
+    }
+
+    /// Feature column names of a lazy query over time series data
+    ///
+    /// Resolves the schema of the query without running it.
+    pub fn lazy_feature_columns(lf: &mut LazyFrame, time_column: &str) -> Result<Vec<String>> {
+        Ok(lf
+            .collect_schema()?
+            .iter_names()
+            .filter(|name| name.as_str() != time_column)
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    /// Get the time column as milliseconds since the Unix epoch
+    ///
+    /// Works for both `Date` and `Datetime` time columns regardless of time unit.
+    /// Elapsed-time (`Duration`) columns give milliseconds since their origin.
+    pub fn timestamps_ms(&self) -> Result<Int64Chunked> {
+        let series = self.df.column(self.time_column())?.as_materialized_series();
+        let physical = series.to_physical_repr().cast(&DataType::Int64)?;
+        let values = physical.i64()?;
+
+        let millis = match series.dtype() {
+            DataType::Datetime(TimeUnit::Nanoseconds, _)
+            | DataType::Duration(TimeUnit::Nanoseconds) => values / 1_000_000,
+            DataType::Datetime(TimeUnit::Microseconds, _)
+            | DataType::Duration(TimeUnit::Microseconds) => values / 1_000,
+            DataType::Date => values * 86_400_000,
+            _ => values.clone(),
+        };
+
+        Ok(millis)
+    }
+
+    /// Number of nulls over all feature columns
+    pub fn feature_null_count(&self) -> usize {
+        self.feature_columns()
+            .iter()
+            .filter_map(|c| self.df.column(c).ok())
+            .map(|c| c.null_count())
+            .sum()
+    }
+
     /// Get metadata reference
     pub fn metadata(&self) -> &TimeSeriesMetadata {
         &self.metadata
@@ -174,6 +409,103 @@ impl TimeSeriesData {
     pub fn get_tag(&self, key: &str) -> Option<&str> {
         self.metadata.tags.get(key).map(|s| s.as_str())
     }
+
+    /// Attach a property (unit, description, source tag, ...) to a column
+    ///
+    /// Stored as the tag `column.<column>.<field>`, so it travels with the tags
+    /// through later operations.
+    pub fn set_column_property(&mut self, column: &str, field: &str, value: &str) {
+        self.add_tag(column_tag(column, field), value.to_string());
+    }
+
+    /// Get a column property set with `set_column_property`
+    pub fn column_property(&self, column: &str, field: &str) -> Option<&str> {
+        self.get_tag(&column_tag(column, field))
+    }
+
+    /// Engineering unit of a column, if known
+    pub fn column_unit(&self, column: &str) -> Option<&str> {
+        self.column_property(column, "unit")
+    }
+
+    /// Description of a column, if known
+    pub fn column_description(&self, column: &str) -> Option<&str> {
+        self.column_property(column, "description")
+    }
+
+    /// Asset hierarchy path of a column, e.g. `plant_a/line_1/pump_3`
+    pub fn column_asset(&self, column: &str) -> Option<&str> {
+        self.column_property(column, "asset")
+    }
+
+    /// Copy with feature columns downcast to the smallest safe dtypes
+    ///
+    /// See `OptimizeDtypesOperation` for the rules applied.
+    pub fn shrink_dtypes(&self) -> Result<Self> {
+        use crate::core::Operation;
+        crate::operations::OptimizeDtypesOperation::new(None).execute(self.clone())
+    }
+
+    /// Store floating-point feature columns with `precision`
+    ///
+    /// `F32` casts every `Float64` feature column to `Float32`; `F64` leaves the
+    /// data unchanged. Returns `self` without copying when nothing is cast.
+    pub fn with_float_precision(self, precision: FloatPrecision) -> Result<Self> {
+        if precision == FloatPrecision::F64 {
+            return Ok(self);
+        }
+        let wide: Vec<String> = self
+            .feature_columns()
+            .iter()
+            .filter(|c| {
+                self.df
+                    .column(c)
+                    .is_ok_and(|s| s.dtype() == &DataType::Float64)
+            })
+            .cloned()
+            .collect();
+        if wide.is_empty() {
+            return Ok(self);
+        }
+        let mut df = self.df;
+        for column in &wide {
+            let narrowed = df.column(column)?.cast(&DataType::Float32)?;
+            df.with_column(narrowed)?;
+        }
+        Ok(Self {
+            df,
+            metadata: self.metadata,
+        })
+    }
+
+    /// Apply `policy` to the floating-point feature columns
+    ///
+    /// `NanPolicy::Keep` leaves the data unchanged. Returns `self` without copying
+    /// when nothing is converted.
+    pub fn with_nan_policy(self, policy: NanPolicy) -> Result<Self> {
+        if policy == NanPolicy::Keep {
+            return Ok(self);
+        }
+        let mut df = self.df;
+        for column in &self.metadata.feature_columns {
+            let Ok(series) = df.column(column) else {
+                continue;
+            };
+            let (converted, count) = policy.apply(series.as_materialized_series())?;
+            if count > 0 {
+                df.replace(column, converted)?;
+            }
+        }
+        Ok(Self {
+            df,
+            metadata: self.metadata,
+        })
+    }
+}
+
+/// Tag key of a column property
+pub fn column_tag(column: &str, field: &str) -> String {
+    format!("column.{}.{}", column, field)
 }
 
 #[cfg(test)]
@@ -221,6 +553,23 @@ mod tests {
         assert_eq!(ts.feature_columns(), &["temp", "pressure"]);
     }
 
+    #[test]
+    fn test_new_with_options_coerces_epoch() {
+        use polars::prelude::*;
+
+        let df = DataFrame::new(vec![
+            Series::new("timestamp".into(), &[1704067200i64, 1704067260]).into(),
+            Series::new("value".into(), &[10.0, 20.0]).into(),
+        ])
+        .unwrap();
+
+        assert!(TimeSeriesData::new(df.clone(), None).is_err());
+
+        let ts = TimeSeriesData::new_with_options(df, None, &TimeColumnOptions::coerce()).unwrap();
+        assert_eq!(ts.time_column(), "timestamp");
+        assert_eq!(ts.timestamps_ms().unwrap().get(1), Some(1704067260000));
+    }
+
     #[test]
     fn test_metadata_tags() {
         use polars::prelude::*;
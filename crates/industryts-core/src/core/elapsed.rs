@@ -0,0 +1,350 @@
+//! Elapsed-time series indexed by time since batch start
+//!
+//! Batch-process analytics compares trajectories on relative time: the third
+//! hour of one batch against the third hour of another, whenever they ran.
+//! `TimeSeriesData::to_elapsed` replaces wall-clock timestamps with a `Duration`
+//! time column measured from each batch's origin, the first sample of each
+//! group by default, and records the origins and the original time type in the
+//! metadata so that `to_absolute_time` can restore the timestamps exactly,
+//! keeping their unit. Elapsed data behaves like
+//! timestamped data elsewhere: `timestamps_ms` returns milliseconds since the
+//! origin, so rolling windows, lags and resampling grids are relative to it.
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::merge::row_keys;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Tag holding the origin of each batch of elapsed-time data, since the epoch in
+/// the unit of the elapsed time column
+pub const ELAPSED_ORIGINS: &str = "time.elapsed_origins";
+
+/// Tag holding the type of the time column before it became elapsed time:
+/// `date`, `datetime[ms]`, `datetime[us]` or `datetime[ns]`
+pub const ELAPSED_DTYPE: &str = "time.elapsed_dtype";
+
+/// Tag holding the time zone of elapsed-time data, restored with the timestamps
+pub const ELAPSED_ZONE: &str = "time.elapsed_zone";
+
+impl TimeSeriesData {
+    /// Whether the time column holds elapsed time rather than timestamps
+    pub fn is_elapsed(&self) -> bool {
+        self.dataframe()
+            .column(self.time_column())
+            .is_ok_and(|c| matches!(c.dtype(), DataType::Duration(_)))
+    }
+
+    /// Index the data by elapsed time since `origin_ms` (ms since epoch)
+    ///
+    /// Without an origin, each group (batch) is measured from its own first
+    /// timestamp. The time column becomes a `Duration` column in the unit of
+    /// the timestamps (milliseconds for dates), and the time zone is set aside
+    /// until the timestamps are restored.
+    pub fn to_elapsed(self, origin_ms: Option<i64>) -> Result<Self> {
+        if self.is_elapsed() {
+            return Err(IndustrytsError::InvalidOperation(
+                "data is already indexed by elapsed time".to_string(),
+            ));
+        }
+        let series = self
+            .dataframe()
+            .column(self.time_column())?
+            .as_materialized_series();
+        let (unit, dtype_tag) = match series.dtype() {
+            DataType::Date => (TimeUnit::Milliseconds, "date".to_string()),
+            DataType::Datetime(unit, _) => (*unit, format!("datetime[{}]", unit_name(*unit))),
+            dtype => {
+                return Err(IndustrytsError::InvalidTimeColumnType(format!(
+                    "{:?}",
+                    dtype
+                )));
+            }
+        };
+        let physical = series.to_physical_repr().cast(&DataType::Int64)?;
+        let times = match series.dtype() {
+            DataType::Date => physical.i64()? * 86_400_000,
+            _ => physical.i64()?.clone(),
+        };
+        let origin = origin_ms.map(|origin| origin * per_ms(unit));
+        let keys = row_keys(self.dataframe(), self.group_columns())?;
+        let mut origins: BTreeMap<String, i64> = BTreeMap::new();
+        for (key, time) in keys.iter().zip(times.iter()) {
+            let (Some(time), None) = (time, origin) else {
+                continue;
+            };
+            origins
+                .entry(key.clone())
+                .and_modify(|origin| *origin = (*origin).min(time))
+                .or_insert(time);
+        }
+        let elapsed: Vec<Option<i64>> = keys
+            .iter()
+            .zip(times.iter())
+            .map(|(key, time)| Some(time? - origin.or_else(|| origins.get(key).copied())?))
+            .collect();
+
+        let time_col = self.time_column().to_string();
+        let elapsed =
+            Series::new(time_col.as_str().into(), elapsed).cast(&DataType::Duration(unit))?;
+        let value = match origin {
+            Some(origin) => serde_json::to_string(&origin)?,
+            None => serde_json::to_string(&origins)?,
+        };
+        let mut metadata = self.metadata().clone();
+        if let Some(zone) = metadata.time_zone.take() {
+            metadata.tags.insert(ELAPSED_ZONE.to_string(), zone);
+        }
+        metadata.tags.insert(ELAPSED_ORIGINS.to_string(), value);
+        metadata.tags.insert(ELAPSED_DTYPE.to_string(), dtype_tag);
+        let mut df = self.into_dataframe();
+        df.replace(&time_col, elapsed)?;
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    /// Restore timestamps of elapsed-time data as elapsed time plus `origin_ms`
+    ///
+    /// Without an origin, uses the origins recorded by `to_elapsed`, which must
+    /// cover every group. The time column gets back the type recorded by
+    /// `to_elapsed`, or becomes a `Datetime` in the unit of the elapsed time.
+    pub fn to_absolute_time(self, origin_ms: Option<i64>) -> Result<Self> {
+        let series = self
+            .dataframe()
+            .column(self.time_column())?
+            .as_materialized_series();
+        let DataType::Duration(unit) = *series.dtype() else {
+            return Err(IndustrytsError::InvalidOperation(
+                "data is not indexed by elapsed time".to_string(),
+            ));
+        };
+        let dtype = match self.get_tag(ELAPSED_DTYPE) {
+            None => DataType::Datetime(unit, None),
+            Some("date") => DataType::Date,
+            Some(tag) => [
+                TimeUnit::Milliseconds,
+                TimeUnit::Microseconds,
+                TimeUnit::Nanoseconds,
+            ]
+            .into_iter()
+            .find(|u| tag == format!("datetime[{}]", unit_name(*u)))
+            .map(|u| DataType::Datetime(u, None))
+            .ok_or_else(|| {
+                IndustrytsError::InvalidOperation(format!("unknown original time type '{}'", tag))
+            })?,
+        };
+        let origins = match (origin_ms, self.get_tag(ELAPSED_ORIGINS)) {
+            (Some(origin), _) => Origins::Shared(origin * per_ms(unit)),
+            (None, Some(value)) => match serde_json::from_str::<i64>(value) {
+                Ok(origin) => Origins::Shared(origin),
+                Err(_) => Origins::PerGroup(serde_json::from_str(value)?),
+            },
+            (None, None) => {
+                return Err(IndustrytsError::InvalidOperation(
+                    "elapsed-time data has no recorded origin; pass one explicitly".to_string(),
+                ));
+            }
+        };
+        let physical = series.to_physical_repr().cast(&DataType::Int64)?;
+        let elapsed = physical.i64()?;
+        let keys = row_keys(self.dataframe(), self.group_columns())?;
+        let times = keys
+            .iter()
+            .zip(elapsed.iter())
+            .map(|(key, elapsed)| {
+                let Some(elapsed) = elapsed else {
+                    return Ok(None);
+                };
+                let origin = match &origins {
+                    Origins::Shared(origin) => *origin,
+                    Origins::PerGroup(origins) => *origins.get(key).ok_or_else(|| {
+                        IndustrytsError::InvalidOperation(format!(
+                            "no recorded origin for batch '{}'",
+                            key.trim_end_matches('\u{1f}').replace('\u{1f}', ", ")
+                        ))
+                    })?,
+                };
+                Ok(Some(origin + elapsed))
+            })
+            .collect::<Result<Vec<Option<i64>>>>()?;
+
+        // Rescale from the unit of the elapsed time to that of the restored type
+        let target = match &dtype {
+            DataType::Datetime(target, _) => *target,
+            _ => TimeUnit::Milliseconds,
+        };
+        let (from, to) = (per_ms(unit), per_ms(target));
+        let times = times.into_iter().map(|time| {
+            let time = time?;
+            Some(if to >= from {
+                time * (to / from)
+            } else {
+                time.div_euclid(from / to)
+            })
+        });
+        let time_col = self.time_column().to_string();
+        let times = match dtype {
+            DataType::Date => Series::new(
+                time_col.as_str().into(),
+                times
+                    .map(|time| time.map(|time| time.div_euclid(86_400_000) as i32))
+                    .collect::<Vec<_>>(),
+            )
+            .cast(&DataType::Date)?,
+            dtype => {
+                Series::new(time_col.as_str().into(), times.collect::<Vec<_>>()).cast(&dtype)?
+            }
+        };
+        let mut metadata = self.metadata().clone();
+        metadata.tags.remove(ELAPSED_ORIGINS);
+        metadata.tags.remove(ELAPSED_DTYPE);
+        metadata.time_zone = metadata.tags.remove(ELAPSED_ZONE);
+        let mut df = self.into_dataframe();
+        df.replace(&time_col, times)?;
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    /// Elapsed time as naive timestamps since the epoch, for operations that
+    /// need a `Datetime` index; the physical values are unchanged
+    pub(crate) fn elapsed_as_datetime(self) -> Result<Self> {
+        let time_col = self.time_column().to_string();
+        let series = self.dataframe().column(&time_col)?.as_materialized_series();
+        let DataType::Duration(unit) = series.dtype() else {
+            return Ok(self);
+        };
+        let dtype = DataType::Datetime(*unit, None);
+        let series = series.to_physical_repr().cast(&dtype)?;
+        self.replace_time_column(series)
+    }
+
+    /// Inverse of `elapsed_as_datetime`
+    pub(crate) fn datetime_as_elapsed(self) -> Result<Self> {
+        let time_col = self.time_column().to_string();
+        let series = self.dataframe().column(&time_col)?.as_materialized_series();
+        let DataType::Datetime(unit, _) = series.dtype() else {
+            return Ok(self);
+        };
+        let dtype = DataType::Duration(*unit);
+        let series = series.to_physical_repr().cast(&dtype)?;
+        self.replace_time_column(series)
+    }
+
+    fn replace_time_column(self, series: Series) -> Result<Self> {
+        let time_col = self.time_column().to_string();
+        let metadata = self.metadata().clone();
+        let mut df = self.into_dataframe();
+        df.replace(&time_col, series)?;
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+}
+
+/// Ticks of `unit` per millisecond
+fn per_ms(unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Milliseconds => 1,
+        TimeUnit::Microseconds => 1_000,
+        TimeUnit::Nanoseconds => 1_000_000,
+    }
+}
+
+fn unit_name(unit: TimeUnit) -> &'static str {
+    match unit {
+        TimeUnit::Milliseconds => "ms",
+        TimeUnit::Microseconds => "us",
+        TimeUnit::Nanoseconds => "ns",
+    }
+}
+
+/// Origins of elapsed-time data as recorded by `to_elapsed`
+enum Origins {
+    Shared(i64),
+    PerGroup(BTreeMap<String, i64>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AggMethod;
+    use crate::core::Operation;
+    use crate::operations::ResampleOperation;
+
+    const HOUR: i64 = 3_600_000;
+
+    #[test]
+    fn test_elapsed_round_trip_per_batch() {
+        // Two batches started a day apart, compared on time since their start
+        let times = [0, HOUR, 2 * HOUR, 24 * HOUR, 25 * HOUR];
+        let time_series = Series::new("time".into(), &times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("batch".into(), &["a", "a", "a", "b", "b"]).into(),
+            Series::new("temp".into(), &[20.0, 60.0, 80.0, 21.0, 58.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time"))
+            .unwrap()
+            .with_group_columns(&["batch".to_string()])
+            .unwrap();
+        let original = data.dataframe().clone();
+
+        let elapsed = data.to_elapsed(None).unwrap();
+        assert!(elapsed.is_elapsed());
+        assert_eq!(
+            elapsed
+                .timestamps_ms()
+                .unwrap()
+                .into_no_null_iter()
+                .collect::<Vec<_>>(),
+            [0, HOUR, 2 * HOUR, 0, HOUR]
+        );
+
+        let restored = elapsed.clone().to_absolute_time(None).unwrap();
+        assert!(!restored.is_elapsed());
+        assert!(restored.dataframe().equals_missing(&original));
+        assert!(restored.get_tag(ELAPSED_ORIGINS).is_none());
+
+        // Buckets of the two batches line up on elapsed time
+        let hourly = ResampleOperation::new("2h", AggMethod::Mean, None)
+            .unwrap()
+            .execute(elapsed.clone())
+            .unwrap();
+        assert!(hourly.is_elapsed());
+        assert_eq!(
+            hourly
+                .timestamps_ms()
+                .unwrap()
+                .into_no_null_iter()
+                .collect::<Vec<_>>(),
+            [0, 0, 2 * HOUR]
+        );
+
+        // An explicit origin aligns every batch to the same timestamps
+        let aligned = elapsed.to_absolute_time(Some(0)).unwrap();
+        assert_eq!(
+            aligned.timestamps_ms().unwrap().get(3),
+            Some(0),
+            "second batch starts at the shared origin"
+        );
+
+        // Sub-millisecond timestamps keep their unit and precision
+        let micros = Series::new("time".into(), &[1_000_001i64, 1_000_502, 1_002_003])
+            .cast(&DataType::Datetime(TimeUnit::Microseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            micros.into(),
+            Series::new("temp".into(), &[1.0, 2.0, 3.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+        let original = data.dataframe().clone();
+        let elapsed = data.to_elapsed(None).unwrap();
+        assert_eq!(
+            elapsed.dataframe().column("time").unwrap().dtype(),
+            &DataType::Duration(TimeUnit::Microseconds)
+        );
+        let restored = elapsed.to_absolute_time(None).unwrap();
+        assert!(restored.dataframe().equals_missing(&original));
+        assert!(restored.get_tag(ELAPSED_DTYPE).is_none());
+    }
+}
@@ -0,0 +1,161 @@
+//! Stable content hashing of time series data
+//!
+//! Fingerprints cover schema (column names, order, dtypes) and values, but not
+//! metadata tags. They are computed from the physical values with BLAKE3, so they
+//! are stable across processes and platforms and can be persisted for caching and
+//! lineage checks.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+const NULL: u8 = 0;
+const VALID: u8 = 1;
+
+/// Feed a series' name, dtype and values into `hasher`
+fn hash_series(hasher: &mut blake3::Hasher, series: &Series) -> Result<()> {
+    hasher.update(series.name().as_bytes());
+    hasher.update(&[NULL]);
+    hasher.update(series.dtype().to_string().as_bytes());
+    hasher.update(&[NULL]);
+    hasher.update(&(series.len() as u64).to_le_bytes());
+
+    let physical = series.to_physical_repr();
+    let dtype = physical.dtype();
+
+    if *dtype == DataType::Boolean {
+        for value in physical.bool()? {
+            match value {
+                Some(v) => hasher.update(&[VALID, v as u8]),
+                None => hasher.update(&[NULL]),
+            };
+        }
+    } else if dtype.is_float() {
+        for value in physical.cast(&DataType::Float64)?.f64()? {
+            match value {
+                // Canonicalize -0.0 and NaN payloads so equal data hashes equally
+                Some(v) if v.is_nan() => hasher
+                    .update(&[VALID])
+                    .update(&f64::NAN.to_bits().to_le_bytes()),
+                Some(v) => hasher
+                    .update(&[VALID])
+                    .update(&(v + 0.0).to_bits().to_le_bytes()),
+                None => hasher.update(&[NULL]),
+            };
+        }
+    } else if dtype.is_unsigned_integer() {
+        for value in physical.cast(&DataType::UInt64)?.u64()? {
+            match value {
+                Some(v) => hasher.update(&[VALID]).update(&v.to_le_bytes()),
+                None => hasher.update(&[NULL]),
+            };
+        }
+    } else if dtype.is_integer() {
+        for value in physical.cast(&DataType::Int64)?.i64()? {
+            match value {
+                Some(v) => hasher.update(&[VALID]).update(&v.to_le_bytes()),
+                None => hasher.update(&[NULL]),
+            };
+        }
+    } else if *dtype == DataType::String {
+        for value in physical.str()? {
+            match value {
+                Some(v) => hasher
+                    .update(&[VALID])
+                    .update(&(v.len() as u64).to_le_bytes())
+                    .update(v.as_bytes()),
+                None => hasher.update(&[NULL]),
+            };
+        }
+    } else {
+        // Nested and other types: fall back to the display representation
+        for i in 0..physical.len() {
+            let value = physical.get(i)?;
+            let text = value.to_string();
+            hasher
+                .update(&[u8::from(!value.is_null())])
+                .update(&(text.len() as u64).to_le_bytes())
+                .update(text.as_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+impl TimeSeriesData {
+    /// Stable hex-encoded hash of schema and content
+    ///
+    /// Two datasets have the same fingerprint exactly when they have the same time
+    /// column, column names, column order, dtypes and values (tags are ignored).
+    pub fn fingerprint(&self) -> Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.time_column().as_bytes());
+        hasher.update(&[NULL]);
+        for column in self.dataframe().get_columns() {
+            hash_series(&mut hasher, column.as_materialized_series())?;
+        }
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    /// Hex-encoded hash per column (name, dtype and values)
+    pub fn column_fingerprints(&self) -> Result<BTreeMap<String, String>> {
+        self.dataframe()
+            .get_columns()
+            .iter()
+            .map(|column| {
+                let mut hasher = blake3::Hasher::new();
+                hash_series(&mut hasher, column.as_materialized_series())?;
+                Ok((
+                    column.name().to_string(),
+                    hasher.finalize().to_hex().to_string(),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data(temp: &[f64], pressure: &[f64]) -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..temp.len() as i64)
+            .map(|i| 1704067200000i64 + i * 60_000)
+            .collect();
+        let time_series = Series::new("DateTime".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), temp).into(),
+            Series::new("pressure".into(), pressure).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("DateTime")).unwrap()
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        let a = make_data(&[1.0, 2.0], &[10.0, 20.0]);
+        let mut b = make_data(&[1.0, 2.0], &[10.0, 20.0]);
+        b.add_tag("source".to_string(), "sensor".to_string());
+
+        assert_eq!(a.fingerprint().unwrap(), b.fingerprint().unwrap());
+        assert_eq!(a.fingerprint().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_fingerprint_detects_changes() {
+        let a = make_data(&[1.0, 2.0], &[10.0, 20.0]);
+        let b = make_data(&[1.0, 2.5], &[10.0, 20.0]);
+        assert_ne!(a.fingerprint().unwrap(), b.fingerprint().unwrap());
+
+        let cols_a = a.column_fingerprints().unwrap();
+        let cols_b = b.column_fingerprints().unwrap();
+        assert_ne!(cols_a["temp"], cols_b["temp"]);
+        assert_eq!(cols_a["pressure"], cols_b["pressure"]);
+        assert_eq!(cols_a["DateTime"], cols_b["DateTime"]);
+    }
+}
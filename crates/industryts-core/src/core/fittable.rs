@@ -0,0 +1,111 @@
+//! Train/apply split for operations with learned parameters
+//!
+//! Scalers and imputers compute statistics from the data they are applied to.
+//! Scoring new data that way leaks its statistics into the transform, and the
+//! same raw value scales differently from batch to batch. A `FittableOperation`
+//! learns its parameters once with `fit`; from then on `execute` applies them
+//! unchanged. `Pipeline::fit` fits every step on training data in order, and
+//! `FittedParams` saves the parameters as JSON next to the pipeline config so a
+//! production pipeline built from the same config can load them.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// An operation whose parameters can be learned from training data
+pub trait FittableOperation {
+    /// Learn the parameters from `data`, replacing earlier ones
+    fn fit(&mut self, data: &TimeSeriesData) -> Result<()>;
+
+    /// Learned parameters, `None` before fitting
+    fn params(&self) -> Option<serde_json::Value>;
+
+    /// Restore parameters returned by `params`
+    fn load_params(&mut self, params: serde_json::Value) -> Result<()>;
+
+    fn is_fitted(&self) -> bool {
+        self.params().is_some()
+    }
+}
+
+/// Parameters of one fitted pipeline step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepParams {
+    /// Position of the step in the pipeline
+    pub step: usize,
+    /// Name of the step's operation, checked when loading
+    pub operation: String,
+    pub params: serde_json::Value,
+}
+
+/// Fitted parameters of a pipeline (see `Pipeline::fit`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FittedParams {
+    pub steps: Vec<StepParams>,
+}
+
+impl FittedParams {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    /// Write the parameters to a JSON file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Read parameters written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pipeline;
+    use crate::config::FillMethod;
+    use crate::operations::{FillNullOperation, StandardizeOperation};
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    fn pipeline() -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(FillNullOperation::new(FillMethod::Mean, None)));
+        pipeline.add_operation(Box::new(StandardizeOperation::new(None)));
+        pipeline
+    }
+
+    #[test]
+    fn test_fit_save_and_transform() {
+        let train = Fixture::every(Duration::from_secs(60), 4)
+            .with_column("temp", [Some(10.0), None, Some(20.0), Some(30.0)])
+            .build()
+            .unwrap();
+        let live = Fixture::every(Duration::from_secs(60), 2)
+            .with_column("temp", [None, Some(40.0)])
+            .build()
+            .unwrap();
+
+        let mut fitted = pipeline();
+        assert!(fitted.transform(live.clone()).is_err());
+        fitted.fit(train).unwrap();
+        let params = FittedParams::from_json(&fitted.fitted_params().to_json().unwrap()).unwrap();
+        assert_eq!(params.steps.len(), 2);
+
+        // Nulls take the training mean, scaling uses the training mean and std
+        let mut loaded = pipeline();
+        loaded.load_fitted_params(&params).unwrap();
+        let result = loaded.transform(live).unwrap();
+        let temp = result.dataframe().column("temp").unwrap().f64().unwrap();
+        let std = (200.0f64 / 3.0).sqrt();
+        assert!(temp.get(0).unwrap().abs() < 1e-9);
+        assert!((temp.get(1).unwrap() - 20.0 / std).abs() < 1e-9);
+    }
+}
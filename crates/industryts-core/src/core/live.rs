@@ -0,0 +1,247 @@
+//! Bounded in-memory windows of live data
+//!
+//! Streaming connectors deliver tags one sample at a time and at their own rates.
+//! `LiveSeries` keeps the most recent samples of each column in a ring buffer
+//! bounded by a sample count and optionally by age, so memory stays constant
+//! however long a connector runs. Realtime code reads the latest value or a
+//! trailing window of a column directly; periodic batch steps take a `snapshot`
+//! as `TimeSeriesData`, with the columns outer-joined on their timestamps.
+//!
+//! Samples are kept in time order. A sample older than the newest one of its
+//! column is inserted in place; one older than a full window is dropped. Share a
+//! `LiveSeries` between threads behind a lock, e.g. `Arc<RwLock<LiveSeries>>`.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::collections::{BTreeSet, VecDeque};
+use std::time::Duration;
+
+/// Ring buffer of one column's samples, ordered by time
+#[derive(Debug, Clone, Default)]
+struct Window {
+    samples: VecDeque<(i64, f64)>,
+}
+
+/// Bounded windows of the latest samples of each column
+#[derive(Debug, Clone)]
+pub struct LiveSeries {
+    time_column: String,
+    capacity: usize,
+    max_age: Option<Duration>,
+    /// Columns in the order they were first seen
+    columns: Vec<(String, Window)>,
+    dropped: u64,
+}
+
+impl LiveSeries {
+    /// Keep up to `capacity` samples per column
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            time_column: "time".to_string(),
+            capacity: capacity.max(1),
+            max_age: None,
+            columns: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Also evict samples older than `max_age` before the newest of their column
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Name of the time column of snapshots (default "time")
+    pub fn with_time_column(mut self, name: &str) -> Self {
+        self.time_column = name.to_string();
+        self
+    }
+
+    /// Append a sample of `column` at `time_ms` (Unix milliseconds)
+    ///
+    /// Returns false if the sample was too old to be kept.
+    pub fn push(&mut self, column: &str, time_ms: i64, value: f64) -> bool {
+        let index = match self.columns.iter().position(|(name, _)| name == column) {
+            Some(index) => index,
+            None => {
+                self.columns.push((column.to_string(), Window::default()));
+                self.columns.len() - 1
+            }
+        };
+        let window = &mut self.columns[index].1.samples;
+
+        let position = match window.back() {
+            Some(&(last, _)) if time_ms < last => window.partition_point(|&(t, _)| t <= time_ms),
+            _ => window.len(),
+        };
+        let kept = if position == 0 && window.len() >= self.capacity {
+            false
+        } else {
+            window.insert(position, (time_ms, value));
+            if window.len() > self.capacity {
+                window.pop_front();
+            }
+            if let (Some(max_age), Some(&(newest, _))) = (self.max_age, window.back()) {
+                let oldest = newest - max_age.as_millis() as i64;
+                while window.front().is_some_and(|&(t, _)| t < oldest) {
+                    window.pop_front();
+                }
+            }
+            window.front().is_some_and(|&(t, _)| t <= time_ms)
+        };
+        if !kept {
+            self.dropped += 1;
+        }
+        kept
+    }
+
+    /// Append one value per column at `time_ms`
+    pub fn push_row(&mut self, time_ms: i64, values: &[(&str, f64)]) {
+        for &(column, value) in values {
+            self.push(column, time_ms, value);
+        }
+    }
+
+    /// Append the non-null samples of the numeric feature columns of `data`
+    pub fn append(&mut self, data: &TimeSeriesData) -> Result<()> {
+        let times = data.timestamps_ms()?;
+        for name in data.feature_columns() {
+            let column = data.dataframe().column(name)?;
+            if !column.dtype().is_primitive_numeric() {
+                continue;
+            }
+            let values = column.cast(&DataType::Float64)?;
+            for (time, value) in times.into_iter().zip(values.f64()?) {
+                if let (Some(time), Some(value)) = (time, value) {
+                    self.push(name, time, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Column names in the order they were first seen
+    pub fn columns(&self) -> Vec<&str> {
+        self.columns.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Number of samples held for `column`
+    pub fn len(&self, column: &str) -> usize {
+        self.window(column).map_or(0, |w| w.samples.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.iter().all(|(_, w)| w.samples.is_empty())
+    }
+
+    /// Samples dropped for being older than a full window
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Newest sample of `column` as (time in ms, value)
+    pub fn latest(&self, column: &str) -> Option<(i64, f64)> {
+        self.window(column)?.samples.back().copied()
+    }
+
+    /// Samples of `column` within `duration` of its newest sample, oldest first
+    pub fn trailing(&self, column: &str, duration: Duration) -> Vec<(i64, f64)> {
+        let Some(window) = self.window(column) else {
+            return Vec::new();
+        };
+        let Some(&(newest, _)) = window.samples.back() else {
+            return Vec::new();
+        };
+        let start = newest - duration.as_millis() as i64;
+        let from = window.samples.partition_point(|&(t, _)| t < start);
+        window.samples.range(from..).copied().collect()
+    }
+
+    /// All held samples as a time series, columns outer-joined on timestamps
+    pub fn snapshot(&self) -> Result<TimeSeriesData> {
+        self.snapshot_since(i64::MIN)
+    }
+
+    /// Held samples at or after `start_ms` as a time series
+    pub fn snapshot_since(&self, start_ms: i64) -> Result<TimeSeriesData> {
+        if self
+            .columns
+            .iter()
+            .any(|(name, _)| *name == self.time_column)
+        {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "live column '{}' clashes with the time column",
+                self.time_column
+            )));
+        }
+        let times: Vec<i64> = self
+            .columns
+            .iter()
+            .flat_map(|(_, w)| w.samples.iter().map(|&(t, _)| t))
+            .filter(|&t| t >= start_ms)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let time = Series::new(self.time_column.as_str().into(), &times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+        let mut frame = vec![time.into()];
+        for (name, window) in &self.columns {
+            // Merge the column's samples into the joint timestamps
+            let mut samples = window.samples.iter().peekable();
+            let values: Vec<Option<f64>> = times
+                .iter()
+                .map(|&t| {
+                    while samples.next_if(|&&(s, _)| s < t).is_some() {}
+                    let mut value = None;
+                    while let Some(&(_, v)) = samples.next_if(|&&(s, _)| s == t) {
+                        value = Some(v);
+                    }
+                    value
+                })
+                .collect();
+            frame.push(Series::new(name.as_str().into(), values).into());
+        }
+        TimeSeriesData::new(DataFrame::new(frame)?, Some(&self.time_column))
+    }
+
+    fn window(&self, column: &str) -> Option<&Window> {
+        self.columns
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, window)| window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_windows_and_snapshot() {
+        let mut live = LiveSeries::new(3);
+        for i in 0..5 {
+            live.push("flow", i * 1_000, i as f64);
+        }
+        live.push("level", 2_500, 7.0);
+        // Inserted in place, then too old for the full window
+        live.push("flow", 2_500, 2.5);
+        assert!(!live.push("flow", 500, 0.5));
+
+        assert_eq!(live.len("flow"), 3);
+        assert_eq!(live.latest("flow"), Some((4_000, 4.0)));
+        assert_eq!(
+            live.trailing("flow", Duration::from_millis(1_500)),
+            vec![(2_500, 2.5), (3_000, 3.0), (4_000, 4.0)]
+        );
+        assert_eq!(live.dropped(), 1);
+
+        let snapshot = live.snapshot().unwrap();
+        let times: Vec<Option<i64>> = snapshot.timestamps_ms().unwrap().into_iter().collect();
+        assert_eq!(times, vec![Some(2_500), Some(3_000), Some(4_000)]);
+        let level = snapshot.dataframe().column("level").unwrap().f64().unwrap();
+        assert_eq!(level.get(0), Some(7.0));
+        assert_eq!(level.null_count(), 2);
+    }
+}
@@ -3,12 +3,70 @@
 //! This module provides the fundamental abstractions used throughout the library:
 //! - `data`: TimeSeriesData structure and metadata
 //! - `operation`: Operation trait and base implementations
+//! - `async_operation`: Async operation trait for I/O-bound steps
+//! - `algebra`: Arithmetic between columns with unit checks
+//! - `arithmetic`: Overflow and division-by-zero policies for derived columns
+//! - `arrow`: Arrow record batch and C stream interface interop
+//! - `comparison`: Deviations of a run from the previous run
 //! - `context`: Execution context for tracking and metrics
+//! - `elapsed`: Elapsed-time series indexed by time since batch start
+//! - `combinators`: Inline composition of operations
+//! - `fingerprint`: Stable content hashing of TimeSeriesData
+//! - `fittable`: Parameters learned from training data and applied unchanged
+//! - `live`: Ring-buffer windows of live samples per column
+//! - `naming`: Naming policy for generated columns
+//! - `output`: Named secondary outputs and sinks
+//! - `push`: Interim metrics pushed to monitoring during long runs
+//! - `provenance`: Steps that created or modified each column
+//! - `report`: Serializable execution reports
+//! - `selector`: Column selection by name, wildcard, regex or dtype
+//! - `stateful`: State carried across chunks for chunk-by-chunk execution
+//! - `tag_metadata`: Units, ranges and descriptions of tags from a metadata file
+//! - `units`: Physical quantities of columns and conversion between units
 
+pub mod algebra;
+pub mod arithmetic;
+pub mod arrow;
+pub mod async_operation;
+pub mod combinators;
+pub mod comparison;
 pub mod context;
 pub mod data;
+pub mod elapsed;
+pub mod fingerprint;
+pub mod fittable;
+pub mod live;
+pub mod naming;
 pub mod operation;
+pub mod output;
+pub mod provenance;
+pub mod push;
+pub mod report;
+pub mod selector;
+pub mod stateful;
+pub mod tag_metadata;
+pub mod units;
 
-pub use context::ExecutionContext;
-pub use data::TimeSeriesData;
+pub use algebra::Signal;
+pub use arithmetic::{ArithmeticPolicy, ArithmeticViolations, BinaryOp};
+pub use async_operation::AsyncOperation;
+pub use combinators::OperationExt;
+pub use comparison::{ComparisonTolerance, Deviation, OutputProfile, RunComparison};
+pub use context::{CancellationToken, ExecutionContext, OpContext, OperationTiming};
+pub use data::{FloatPrecision, NanPolicy, TimeColumnOptions, TimeSeriesData};
+pub use fittable::{FittableOperation, FittedParams, StepParams};
+pub use live::LiveSeries;
+pub use naming::NamingPolicy;
 pub use operation::{Operation, OperationCategory, OperationMetadata};
+pub use output::{
+    DirectorySink, OutputSink, OutputStore, SinkTransaction, StagedFile, StagedOutput,
+};
+pub use provenance::{ProvenanceAction, ProvenanceEntry};
+pub use push::{
+    MetricsPush, MetricsPusher, MetricsSnapshot, PushInterval, PushgatewayPusher, StatsdPusher,
+};
+pub use report::{ExecutionReport, StepReport};
+pub use selector::{ColumnSelector, DtypeClass};
+pub use stateful::{Carry, CarryMode, StatefulOperation, StepState};
+pub use tag_metadata::{TagInfo, TagMetadata};
+pub use units::{UnitInfo, convert_units, unit_info};
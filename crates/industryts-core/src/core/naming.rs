@@ -0,0 +1,174 @@
+//! Names of generated columns
+//!
+//! Feature-generating operations (lags, differences, rolling statistics, trend
+//! slopes) name each output after its source column, the operation and its
+//! parameter. A `NamingPolicy` fixes how these parts are joined, so that generated
+//! names are predictable and can be split again by downstream tools, e.g.
+//! `{col}__{op}_{param}` gives `temp__lag_1` and `temp__rollmean_15m`. Set on a
+//! pipeline (or as `naming` in the `[pipeline]` section), it applies to every step.
+
+use crate::error::{IndustrytsError, Result};
+use std::collections::BTreeSet;
+
+/// Default template, giving e.g. `temp_lag_1` and `temp_slope`
+pub const DEFAULT_NAMING_TEMPLATE: &str = "{col}_{op}_{param}";
+
+const PLACEHOLDERS: [&str; 3] = ["col", "op", "param"];
+
+/// Template for the names of generated columns
+///
+/// `{col}` is replaced by the source column, `{op}` by the operation's short name
+/// (`lag`, `diff`, `rollmean`, `slope`, ...) and `{param}` by its parameter (the
+/// period or window). For outputs without a parameter, `{param}` is dropped with
+/// the separator before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamingPolicy {
+    template: String,
+}
+
+impl Default for NamingPolicy {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_NAMING_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl NamingPolicy {
+    /// Policy with `template`, which must contain `{col}` and `{op}`
+    pub fn new(template: &str) -> Result<Self> {
+        let mut found = BTreeSet::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Naming template '{}' has an unclosed '{{'",
+                    template
+                )));
+            };
+            let placeholder = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Naming template '{}' has unknown placeholder '{{{}}}'; use {{col}}, {{op}} \
+                     and {{param}}",
+                    template, placeholder
+                )));
+            }
+            found.insert(placeholder);
+            rest = &rest[start + end + 1..];
+        }
+        if !found.contains("col") || !found.contains("op") {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Naming template '{}' must contain {{col}} and {{op}}",
+                template
+            )));
+        }
+        Ok(Self {
+            template: template.to_string(),
+        })
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Name of the output of `op` (with `param`, if any) for `column`
+    pub fn name(&self, column: &str, op: &str, param: Option<&str>) -> String {
+        let template = match param {
+            Some(param) => self.template.replace("{param}", param),
+            None => Self::without_param(&self.template),
+        };
+        template.replace("{op}", op).replace("{col}", column)
+    }
+
+    /// `template` with `{param}` and the separator before it removed (after it,
+    /// when `{param}` comes first)
+    fn without_param(template: &str) -> String {
+        let Some(index) = template.find("{param}") else {
+            return template.to_string();
+        };
+        let end = index + "{param}".len();
+        match template[..index].rfind('}') {
+            Some(previous) => format!("{}{}", &template[..previous + 1], &template[end..]),
+            None => {
+                let next = template[end..]
+                    .find('{')
+                    .map_or(template.len(), |i| end + i);
+                format!("{}{}", &template[..index], &template[next..])
+            }
+        }
+    }
+}
+
+/// Check that `added` names neither repeat nor collide with `existing` columns
+pub fn check_collisions<S: AsRef<str>>(
+    operation: &str,
+    existing: impl IntoIterator<Item = S>,
+    added: &[String],
+) -> Result<()> {
+    let existing: BTreeSet<String> = existing
+        .into_iter()
+        .map(|c| c.as_ref().to_string())
+        .collect();
+    let mut seen = BTreeSet::new();
+    for name in added {
+        if existing.contains(name) {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "{} would overwrite existing column '{}'",
+                operation, name
+            )));
+        }
+        if !seen.insert(name.as_str()) {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "{} generates column '{}' twice",
+                operation, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Operation;
+    use crate::operations::{LagOperation, RollingFeaturesOperation, RollingStat, RollingWindow};
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pipeline_naming_policy() {
+        let policy = NamingPolicy::new("{col}__{op}_{param}").unwrap();
+        assert_eq!(policy.name("temp", "slope", None), "temp__slope");
+        let leading = NamingPolicy::new("{param}-{op}:{col}").unwrap();
+        assert_eq!(leading.name("temp", "slope", None), "slope:temp");
+        assert!(NamingPolicy::new("{col}_{param}").is_err());
+        assert!(NamingPolicy::new("{col}_{op}_{window}").is_err());
+
+        let data = Fixture::every(Duration::from_secs(60), 4)
+            .with_column("temp", [1.0, 2.0, 3.0, 4.0])
+            .build()
+            .unwrap();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.set_naming(policy.clone());
+        pipeline.add_operation(Box::new(RollingFeaturesOperation::new(
+            vec![RollingWindow::Time(Duration::from_secs(120))],
+            vec![RollingStat::Mean],
+            Some(vec!["temp".to_string()]),
+        )));
+        let result = pipeline.process(data.clone()).unwrap();
+        let df = result.dataframe();
+        assert!(df.column("temp__lag_1").is_ok());
+        assert!(df.column("temp__rollmean_2m").is_ok());
+
+        // Without a parameter in the template, two lags map to one name
+        let mut lags = LagOperation::new(vec![1, 2], None);
+        lags.set_naming(&NamingPolicy::new("{col}_{op}").unwrap());
+        let Err(e) = lags.execute(data) else {
+            panic!("both lags are named temp_lag");
+        };
+        assert!(e.to_string().contains("'temp_lag' twice"));
+    }
+}
@@ -5,7 +5,13 @@
 
 use crate::error::Result;
 use crate::core::data::TimeSeriesData;
+use crate::core::context::OpContext;
+use crate::core::fittable::FittableOperation;
+use crate::core::naming::NamingPolicy;
+use crate::core::stateful::StatefulOperation;
+use polars::prelude::{IntoLazy, LazyFrame};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// Metadata about an operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +37,8 @@ pub enum OperationCategory {
     Features,
     /// Data transformation operations
     Transform,
+    /// Anomaly detection operations (control charts, sensor faults)
+    Anomaly,
 }
 
 impl std::fmt::Display for OperationCategory {
@@ -40,6 +48,7 @@ impl std::fmt::Display for OperationCategory {
             OperationCategory::Temporal => write!(f, "temporal"),
             OperationCategory::Features => write!(f, "features"),
             OperationCategory::Transform => write!(f, "transform"),
+            OperationCategory::Anomaly => write!(f, "anomaly"),
         }
     }
 }
@@ -47,6 +56,12 @@ impl std::fmt::Display for OperationCategory {
 /// Trait for time series operations
 ///
 /// All operations that can be applied to time series data must implement this trait.
+///
+/// Operations accept empty and single-row data without failing or panicking:
+/// empty input gives empty output with the usual columns, and statistics that
+/// cannot be estimated from fewer than two values (standard deviations,
+/// self-tuned control limits, sampling intervals) come out null. Constant
+/// data of more rows is still an error where a spread is required.
 pub trait Operation: Send + Sync {
     /// Execute the operation on time series data
     fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData>;
@@ -54,6 +69,138 @@ pub trait Operation: Send + Sync {
     /// Get the name of the operation
     fn name(&self) -> &str;
 
+    /// Execute the operation with access to the execution context
+    ///
+    /// The pipeline always calls this method. Operations override it to read runtime
+    /// settings, emit warnings and custom metrics, check for cancellation, or add
+    /// side tables (rejected rows, events, ...) to the secondary outputs, which also
+    /// hold outputs of earlier steps. The default implementation delegates to
+    /// `execute`.
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        _ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.execute(data)
+    }
+
+    /// Seed the operation's random number generator
+    ///
+    /// Operations with stochastic behaviour (sampling, isolation forests, clustering)
+    /// override this; the pipeline calls it with a per-step seed derived from the
+    /// pipeline seed. The default implementation does nothing.
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// Name generated columns after `naming`
+    ///
+    /// Feature-generating operations override this; the pipeline calls it with its
+    /// naming policy. The default implementation does nothing.
+    fn set_naming(&mut self, _naming: &NamingPolicy) {}
+
+    /// Columns that must exist before the operation runs
+    ///
+    /// Used by the pipeline to check steps before execution. The default declares
+    /// no requirements.
+    fn required_columns(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Columns the operation adds, given the feature columns of its input
+    ///
+    /// Used by the pipeline to propagate the schema through later steps.
+    fn added_columns(&self, _feature_columns: &[String]) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Named datasets the operation reads besides its input (see `OpContext::input`)
+    ///
+    /// Used to order the pipelines of a `PipelineSet` and to check that a run
+    /// supplies them. The default reads none.
+    fn catalog_inputs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Ways the output at a row depends on later rows, e.g. a lead or a backward fill
+    ///
+    /// Used by `DatasetBuilder` to reject features that see past the prediction
+    /// time. The default reads no later rows.
+    fn lookahead(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the operation may drop, filter or aggregate rows
+    fn removes_rows(&self) -> bool {
+        false
+    }
+
+    /// Bounds on the output row count for `input_rows` input rows
+    ///
+    /// The pipeline checks every step's output against these bounds. The default is
+    /// exactly `input_rows`, or any count for operations that remove rows (resampling
+    /// may also add rows).
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        if self.removes_rows() {
+            (0, None)
+        } else {
+            (input_rows, Some(input_rows))
+        }
+    }
+
+    /// Whether the operation may reorder rows
+    ///
+    /// Operations that do not reorder rows must keep a sorted time column sorted.
+    fn reorders_rows(&self) -> bool {
+        false
+    }
+
+    /// Whether the operation sets the time zone of its output itself
+    ///
+    /// The pipeline otherwise gives an output without a time zone the zone of its
+    /// input. Operations that convert timestamps to another clock return `true`.
+    fn manages_time_zone(&self) -> bool {
+        false
+    }
+
+    /// Apply the operation to a lazy query with time column `time_column`
+    ///
+    /// Column-wise operations override this to extend the query plan, so a chain
+    /// of them runs as one optimized Polars query. The default collects the query
+    /// and calls `execute`.
+    fn apply_lazy(&self, lf: LazyFrame, time_column: &str) -> Result<LazyFrame> {
+        let data = TimeSeriesData::new(lf.collect()?, Some(time_column))?;
+        Ok(self.execute(data)?.into_dataframe().lazy())
+    }
+
+    /// History needed before the first output row is exact
+    ///
+    /// Used to overlap time chunks when backfilling; windowed operations return
+    /// their window. The default is no warm-up.
+    fn warmup(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// State for processing the data chunk by chunk
+    ///
+    /// Used by `Pipeline::process_stream`. Operations whose output depends on
+    /// earlier rows return a state carrying them across chunk boundaries. Without
+    /// one, the pipeline carries `warmup` of preceding rows, or applies the
+    /// operation to each chunk independently when there is no warm-up.
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        None
+    }
+
+    /// Parameters learned by `Pipeline::fit`, for operations that have any
+    ///
+    /// The default is `None`.
+    fn fittable(&self) -> Option<&dyn FittableOperation> {
+        None
+    }
+
+    /// Mutable access to the learned parameters, see `fittable`
+    fn fittable_mut(&mut self) -> Option<&mut dyn FittableOperation> {
+        None
+    }
+
     /// Validate that the operation can be applied to the given data
     ///
     /// This method should check preconditions like required columns, data types, etc.
@@ -114,6 +261,7 @@ mod tests {
         assert_eq!(OperationCategory::Temporal.to_string(), "temporal");
         assert_eq!(OperationCategory::Features.to_string(), "features");
         assert_eq!(OperationCategory::Transform.to_string(), "transform");
+        assert_eq!(OperationCategory::Anomaly.to_string(), "anomaly");
     }
 
     #[test]
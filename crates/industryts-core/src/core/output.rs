@@ -0,0 +1,308 @@
+//! Secondary outputs of operations
+//!
+//! Besides the main time series, an operation may emit named side tables such as
+//! rejected rows or detected events. They are collected in the `OutputStore` of the
+//! run's `OpContext`, so later steps can read them, and routed to `OutputSink`s once
+//! the pipeline finishes.
+//!
+//! A pipeline writing several sinks can treat them as one unit of work: each sink
+//! stages its output (e.g. to a temporary file) and a `SinkTransaction` commits
+//! the staged outputs only once all of them were staged, so a failing sink does
+//! not leave the others half-written.
+
+use crate::error::Result;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Named secondary outputs collected during execution
+#[derive(Debug, Clone, Default)]
+pub struct OutputStore {
+    tables: BTreeMap<String, DataFrame>,
+}
+
+impl OutputStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a table; rows are appended if an output with this name already exists
+    pub fn insert(&mut self, name: &str, table: DataFrame) -> Result<()> {
+        match self.tables.get_mut(name) {
+            Some(existing) => {
+                existing.vstack_mut(&table)?;
+            }
+            None => {
+                self.tables.insert(name.to_string(), table);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get an output by name
+    pub fn get(&self, name: &str) -> Option<&DataFrame> {
+        self.tables.get(name)
+    }
+
+    /// Remove and return an output
+    pub fn remove(&mut self, name: &str) -> Option<DataFrame> {
+        self.tables.remove(name)
+    }
+
+    /// Names of all outputs
+    pub fn names(&self) -> Vec<&str> {
+        self.tables.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// Number of outputs
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Estimated heap size of all outputs in bytes
+    pub fn estimated_size(&self) -> usize {
+        self.tables.values().map(|t| t.estimated_size()).sum()
+    }
+
+    /// Consume the store, returning all outputs
+    pub fn into_tables(self) -> BTreeMap<String, DataFrame> {
+        self.tables
+    }
+}
+
+/// Destination for a secondary output
+pub trait OutputSink: Send + Sync {
+    /// Write the output table `name`
+    fn write(&self, name: &str, table: &DataFrame) -> Result<()>;
+
+    /// Prepare writing the output table `name` without publishing it
+    ///
+    /// The default defers `write` to the commit, which can then still fail.
+    /// Sinks that can stage their output override it.
+    fn stage<'a>(&'a self, name: &str, table: &DataFrame) -> Result<Box<dyn StagedOutput + 'a>> {
+        Ok(Box::new(DeferredWrite {
+            sink: self,
+            name: name.to_string(),
+            table: table.clone(),
+        }))
+    }
+}
+
+/// Output prepared by a sink, published on commit
+pub trait StagedOutput: Send {
+    /// Publish the output
+    fn commit(self: Box<Self>) -> Result<()>;
+
+    /// Discard the output
+    fn rollback(self: Box<Self>);
+}
+
+/// Write of a sink that cannot stage, run on commit
+struct DeferredWrite<'a, S: ?Sized> {
+    sink: &'a S,
+    name: String,
+    table: DataFrame,
+}
+
+impl<S: OutputSink + ?Sized> StagedOutput for DeferredWrite<'_, S> {
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.sink.write(&self.name, &self.table)
+    }
+
+    fn rollback(self: Box<Self>) {}
+}
+
+/// File written under a temporary name next to its path, renamed into place on
+/// commit
+pub struct StagedFile {
+    temp: PathBuf,
+    path: PathBuf,
+}
+
+impl StagedFile {
+    /// Stage `path`, writing the file with `write` at the temporary path
+    pub fn create<F>(path: &Path, write: F) -> Result<Self>
+    where
+        F: FnOnce(&Path) -> Result<()>,
+    {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+        if let Err(e) = write(&temp) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+        Ok(Self {
+            temp,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl StagedOutput for StagedFile {
+    fn commit(self: Box<Self>) -> Result<()> {
+        std::fs::rename(&self.temp, &self.path)?;
+        Ok(())
+    }
+
+    fn rollback(self: Box<Self>) {
+        let _ = std::fs::remove_file(&self.temp);
+    }
+}
+
+/// Staged outputs committed together
+///
+/// Dropping an uncommitted transaction rolls back its outputs.
+#[derive(Default)]
+pub struct SinkTransaction<'a> {
+    staged: Vec<Box<dyn StagedOutput + 'a>>,
+}
+
+impl<'a> SinkTransaction<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a staged output
+    pub fn push(&mut self, staged: Box<dyn StagedOutput + 'a>) {
+        self.staged.push(staged);
+    }
+
+    /// Number of staged outputs
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Publish all outputs in the order staged
+    ///
+    /// If one fails, the outputs not yet published are rolled back.
+    pub fn commit(mut self) -> Result<()> {
+        let mut staged = std::mem::take(&mut self.staged).into_iter();
+        while let Some(output) = staged.next() {
+            if let Err(e) = output.commit() {
+                staged.for_each(|output| output.rollback());
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SinkTransaction<'_> {
+    fn drop(&mut self) {
+        for output in self.staged.drain(..) {
+            output.rollback();
+        }
+    }
+}
+
+impl<F> OutputSink for F
+where
+    F: Fn(&str, &DataFrame) -> Result<()> + Send + Sync,
+{
+    fn write(&self, name: &str, table: &DataFrame) -> Result<()> {
+        self(name, table)
+    }
+}
+
+/// Sink writing each output to `<dir>/<name>.arrow` (Arrow IPC)
+///
+/// Record batches are encoded in parallel and uncompressed by default; ZSTD
+/// typically shrinks sensor data several times at little cost.
+pub struct DirectorySink {
+    dir: PathBuf,
+    compression: Option<IpcCompression>,
+}
+
+impl DirectorySink {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            compression: None,
+        }
+    }
+
+    /// Compress record batches with `compression` (LZ4 or ZSTD)
+    pub fn with_compression(mut self, compression: IpcCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    fn write_to(&self, path: &Path, table: &DataFrame) -> Result<()> {
+        let mut file = File::create(path)?;
+        IpcWriter::new(&mut file)
+            .with_compression(self.compression)
+            .with_parallel(true)
+            .finish(&mut table.clone())?;
+        Ok(())
+    }
+}
+
+impl OutputSink for DirectorySink {
+    fn write(&self, name: &str, table: &DataFrame) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        self.write_to(&self.dir.join(format!("{}.arrow", name)), table)
+    }
+
+    fn stage<'a>(&'a self, name: &str, table: &DataFrame) -> Result<Box<dyn StagedOutput + 'a>> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.arrow", name));
+        let staged = StagedFile::create(&path, |temp| self.write_to(temp, table))?;
+        Ok(Box::new(staged))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_store_appends() {
+        let mut store = OutputStore::new();
+        store
+            .insert("rejected", df!("value" => &[1.0]).unwrap())
+            .unwrap();
+        store
+            .insert("rejected", df!("value" => &[2.0, 3.0]).unwrap())
+            .unwrap();
+
+        assert_eq!(store.names(), vec!["rejected"]);
+        assert_eq!(store.get("rejected").unwrap().height(), 3);
+    }
+
+    #[test]
+    fn test_directory_sink_compression() {
+        let dir = std::env::temp_dir().join(format!("industryts_sink_{}", std::process::id()));
+        let table = df!("value" => (0..1000).map(|i| (i % 7) as f64).collect::<Vec<_>>()).unwrap();
+        DirectorySink::new(dir.join("plain"))
+            .write("values", &table)
+            .unwrap();
+        DirectorySink::new(dir.join("zstd"))
+            .with_compression(IpcCompression::ZSTD)
+            .write("values", &table)
+            .unwrap();
+
+        let size = |sub: &str| {
+            std::fs::metadata(dir.join(sub).join("values.arrow"))
+                .unwrap()
+                .len()
+        };
+        assert!(size("zstd") < size("plain"));
+        let read = IpcReader::new(File::open(dir.join("zstd").join("values.arrow")).unwrap())
+            .finish()
+            .unwrap();
+        assert!(read.equals(&table));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+//! Column provenance
+//!
+//! When a feature looks wrong months after a model went live, the first question
+//! is where the column came from. With provenance tracking enabled
+//! (`Pipeline::set_track_provenance`, or `track_provenance` in the `[pipeline]`
+//! section), every synchronous step records which columns it created or modified,
+//! with the step's configuration when the pipeline was built from one. The
+//! records travel with the data as the column property `provenance` and are read
+//! back with `TimeSeriesData::column_provenance`.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Column property holding the provenance records of a column (JSON)
+pub const PROVENANCE_PROPERTY: &str = "provenance";
+
+/// How a step changed a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceAction {
+    Created,
+    Modified,
+}
+
+/// A step that created or modified a column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceEntry {
+    /// Position of the step in the pipeline
+    pub step: usize,
+    pub operation: String,
+    pub action: ProvenanceAction,
+    /// Configuration of the step, if the pipeline was built from one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+}
+
+/// Step whose column changes are recorded
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProvenanceStep {
+    pub params: Option<serde_json::Value>,
+}
+
+impl ProvenanceStep {
+    /// Record on `output` the columns step `index` created or modified in `input`
+    ///
+    /// Records of earlier steps are carried over from `input`, since operations
+    /// do not necessarily keep the tags of their input.
+    pub(crate) fn record(
+        &self,
+        index: usize,
+        operation: &str,
+        input: &TimeSeriesData,
+        output: &mut TimeSeriesData,
+    ) -> Result<()> {
+        let columns: Vec<String> = output
+            .dataframe()
+            .get_column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        for column in columns {
+            let action = match input.dataframe().column(&column) {
+                Err(_) => Some(ProvenanceAction::Created),
+                Ok(before) => {
+                    let after = output.dataframe().column(&column)?;
+                    let unchanged = before.dtype() == after.dtype()
+                        && before
+                            .as_materialized_series()
+                            .equals_missing(after.as_materialized_series());
+                    (!unchanged).then_some(ProvenanceAction::Modified)
+                }
+            };
+            let mut entries = input.provenance_of(&column)?;
+            if let Some(action) = action {
+                entries.push(ProvenanceEntry {
+                    step: index,
+                    operation: operation.to_string(),
+                    action,
+                    params: self.params.clone(),
+                });
+            }
+            if !entries.is_empty() {
+                let value = serde_json::to_string(&entries)?;
+                output.set_column_property(&column, PROVENANCE_PROPERTY, &value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TimeSeriesData {
+    /// Steps that created or modified each column, oldest first
+    ///
+    /// Columns passed through unchanged from the input have no entries. Empty
+    /// unless the data comes from a pipeline tracking provenance.
+    pub fn column_provenance(&self) -> Result<BTreeMap<String, Vec<ProvenanceEntry>>> {
+        self.dataframe()
+            .get_column_names()
+            .iter()
+            .map(|c| Ok((c.to_string(), self.provenance_of(c)?)))
+            .collect()
+    }
+
+    fn provenance_of(&self, column: &str) -> Result<Vec<ProvenanceEntry>> {
+        match self.column_property(column, PROVENANCE_PROPERTY) {
+            Some(value) => Ok(serde_json::from_str(value)?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PipelineConfig;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_provenance_of_pipeline_outputs() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "provenance"
+            track_provenance = true
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            columns = ["flow"]
+
+            [[operations]]
+            type = "fill_null"
+            method = "zero"
+            "#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::from_config(config).unwrap();
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("flow", [1.0, 2.0, 3.0])
+            .with_column("temp", [20.0, 21.0, 22.0])
+            .build()
+            .unwrap();
+
+        let provenance = pipeline.process(data).unwrap().column_provenance().unwrap();
+        assert!(provenance["temp"].is_empty());
+        assert!(provenance["flow"].is_empty());
+        let lag = &provenance["flow_lag_1"];
+        assert_eq!(lag.len(), 2);
+        assert_eq!(
+            (lag[0].step, lag[0].operation.as_str(), lag[0].action),
+            (0, "lag", ProvenanceAction::Created)
+        );
+        assert_eq!(lag[0].params.as_ref().unwrap()["periods"][0], 1);
+        // The fill replaced the null the lag left in the first row
+        assert_eq!(
+            (lag[1].step, lag[1].action),
+            (1, ProvenanceAction::Modified)
+        );
+    }
+}
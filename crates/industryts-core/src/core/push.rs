@@ -0,0 +1,265 @@
+//! Interim metrics pushed to external monitoring during a run
+//!
+//! A backfill over years of history runs for hours; its `ExecutionSummary` only
+//! arrives at the end. A `MetricsPush` attached to the `ExecutionContext` sends a
+//! `MetricsSnapshot` every N recorded operations or completed chunks to a
+//! `MetricsPusher`: a closure, a StatsD daemon (`StatsdPusher`) or a Prometheus
+//! pushgateway (`PushgatewayPusher`). A failing push never fails the run; failures
+//! are counted by `ExecutionContext::push_failures`.
+
+use crate::core::context::ExecutionSummary;
+use crate::error::{IndustrytsError, Result};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Timeout of network pushes, so a monitoring outage cannot stall a run
+const PUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Progress of a run at the time of a push
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    /// Operations recorded so far, over all chunks
+    pub operations: usize,
+    /// Chunks completed so far
+    pub chunks: usize,
+    pub summary: ExecutionSummary,
+}
+
+impl MetricsSnapshot {
+    /// Named gauge values of the snapshot
+    pub fn gauges(&self) -> Vec<(&'static str, f64)> {
+        let summary = &self.summary;
+        let mut gauges = vec![
+            ("operations", self.operations as f64),
+            ("chunks", self.chunks as f64),
+            ("rows_processed", summary.total_rows_processed as f64),
+            ("duration_seconds", summary.total_duration.as_secs_f64()),
+            ("throughput_rows_per_second", summary.average_throughput),
+            ("peak_memory_bytes", summary.peak_memory_bytes as f64),
+            ("bytes_read", summary.bytes_read as f64),
+            ("bytes_written", summary.bytes_written as f64),
+        ];
+        if let Some(cpu) = summary.cpu_time {
+            gauges.push(("cpu_seconds", cpu.as_secs_f64()));
+        }
+        gauges
+    }
+}
+
+/// Receiver of interim metrics
+pub trait MetricsPusher: Send + Sync {
+    fn push(&self, snapshot: &MetricsSnapshot) -> Result<()>;
+}
+
+impl<F> MetricsPusher for F
+where
+    F: Fn(&MetricsSnapshot) -> Result<()> + Send + Sync,
+{
+    fn push(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        self(snapshot)
+    }
+}
+
+/// What the interval of a `MetricsPush` counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushInterval {
+    Operations(usize),
+    Chunks(usize),
+}
+
+/// Pusher and interval of interim metrics
+#[derive(Clone)]
+pub struct MetricsPush {
+    pusher: Arc<dyn MetricsPusher>,
+    interval: PushInterval,
+}
+
+impl MetricsPush {
+    /// Push after every `n` recorded operations
+    pub fn every_operations(pusher: Arc<dyn MetricsPusher>, n: usize) -> Self {
+        Self {
+            pusher,
+            interval: PushInterval::Operations(n.max(1)),
+        }
+    }
+
+    /// Push after every `n` completed chunks
+    pub fn every_chunks(pusher: Arc<dyn MetricsPusher>, n: usize) -> Self {
+        Self {
+            pusher,
+            interval: PushInterval::Chunks(n.max(1)),
+        }
+    }
+
+    pub fn interval(&self) -> PushInterval {
+        self.interval
+    }
+
+    pub(crate) fn push(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        self.pusher.push(snapshot)
+    }
+}
+
+/// Pusher sending gauges to a StatsD daemon over UDP
+///
+/// Each gauge is sent as `<prefix>.<name>:<value>|g`.
+pub struct StatsdPusher {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+impl StatsdPusher {
+    /// Send to the daemon at `address` (e.g. "127.0.0.1:8125")
+    pub fn new(address: &str, prefix: &str) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+impl MetricsPusher for StatsdPusher {
+    fn push(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        let lines: Vec<String> = snapshot
+            .gauges()
+            .into_iter()
+            .map(|(name, value)| format!("{}.{}:{}|g", self.prefix, name, value))
+            .collect();
+        self.socket.send(lines.join("\n").as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Pusher replacing the metrics of a job on a Prometheus pushgateway
+///
+/// Gauges are named `industryts_<name>` and sent with `PUT` over plain HTTP to
+/// `<url>/metrics/job/<job>`.
+pub struct PushgatewayPusher {
+    host: String,
+    path: String,
+}
+
+impl PushgatewayPusher {
+    /// Push to the gateway at `url` (e.g. "http://pushgateway:9091") as `job`
+    pub fn new(url: &str, job: &str) -> Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            IndustrytsError::ConfigError(format!(
+                "Pushgateway URL must start with http://, got '{}'",
+                url
+            ))
+        })?;
+        let (host, base) = rest.split_once('/').unwrap_or((rest, ""));
+        let base = base.trim_end_matches('/');
+        let prefix = if base.is_empty() {
+            String::new()
+        } else {
+            format!("/{}", base)
+        };
+        Ok(Self {
+            host: host.to_string(),
+            path: format!("{}/metrics/job/{}", prefix, job),
+        })
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    fn body(snapshot: &MetricsSnapshot) -> String {
+        snapshot
+            .gauges()
+            .into_iter()
+            .map(|(name, value)| {
+                format!(
+                    "# TYPE industryts_{name} gauge\nindustryts_{name} {value}\n",
+                    name = name,
+                    value = value
+                )
+            })
+            .collect()
+    }
+}
+
+impl MetricsPusher for PushgatewayPusher {
+    fn push(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        let address = self.host.to_socket_addrs()?.next().ok_or_else(|| {
+            IndustrytsError::ConfigError(format!("Cannot resolve '{}'", self.host))
+        })?;
+        let mut stream = TcpStream::connect_timeout(&address, PUSH_TIMEOUT)?;
+        stream.set_read_timeout(Some(PUSH_TIMEOUT))?;
+        stream.set_write_timeout(Some(PUSH_TIMEOUT))?;
+        let body = Self::body(snapshot);
+        write!(
+            stream,
+            "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status = response.split_whitespace().nth(1).unwrap_or_default();
+        if status.starts_with('2') {
+            Ok(())
+        } else {
+            Err(IndustrytsError::OperationError(format!(
+                "Pushgateway answered '{}'",
+                response.lines().next().unwrap_or_default()
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ExecutionContext;
+    use crate::core::context::OperationMetrics;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_push_every_chunks_and_statsd() {
+        let pushed = Arc::new(Mutex::new(Vec::new()));
+        let received = pushed.clone();
+        let pusher = move |snapshot: &MetricsSnapshot| -> Result<()> {
+            received
+                .lock()
+                .unwrap()
+                .push((snapshot.operations, snapshot.chunks));
+            Ok(())
+        };
+        let mut context = ExecutionContext::new()
+            .with_metrics_push(MetricsPush::every_chunks(Arc::new(pusher), 2));
+        for _ in 0..5 {
+            context.record_metrics(OperationMetrics::new("fill_null".to_string()));
+            context.record_chunk();
+        }
+        assert_eq!(*pushed.lock().unwrap(), [(2, 2), (4, 4)]);
+
+        let failing = |_: &MetricsSnapshot| -> Result<()> {
+            Err(IndustrytsError::OperationError("gateway down".to_string()))
+        };
+        let mut context = ExecutionContext::new()
+            .with_metrics_push(MetricsPush::every_operations(Arc::new(failing), 1));
+        context.record_metrics(OperationMetrics::new("fill_null".to_string()));
+        assert_eq!(context.push_failures(), 1);
+
+        let daemon = UdpSocket::bind("127.0.0.1:0").unwrap();
+        daemon.set_read_timeout(Some(PUSH_TIMEOUT)).unwrap();
+        let statsd = StatsdPusher::new(&daemon.local_addr().unwrap().to_string(), "plant").unwrap();
+        let mut context = ExecutionContext::new()
+            .with_metrics_push(MetricsPush::every_operations(Arc::new(statsd), 1));
+        context.record_metrics(OperationMetrics::new("fill_null".to_string()));
+        let mut buffer = [0u8; 1024];
+        let n = daemon.recv(&mut buffer).unwrap();
+        let text = std::str::from_utf8(&buffer[..n]).unwrap();
+        assert!(text.starts_with("plant.operations:1|g\nplant.chunks:0|g"));
+
+        let gateway = PushgatewayPusher::new("http://localhost:9091/", "backfill").unwrap();
+        assert_eq!(gateway.path, "/metrics/job/backfill");
+        assert!(PushgatewayPusher::new("https://gateway", "backfill").is_err());
+    }
+}
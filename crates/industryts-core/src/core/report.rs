@@ -0,0 +1,159 @@
+//! Serializable execution reports
+//!
+//! `ExecutionReport` turns the metrics collected in an `ExecutionContext` into a
+//! plain structure for export, e.g. as JSON shipped to a metrics store and charted
+//! in Grafana. `Pipeline::process_with_report` runs a pipeline and returns the
+//! report of that run.
+//!
+//! The same figures are available live through `tracing`: every synchronous step
+//! runs in an `operation` span with the fields `name`, `step`, `input_rows`,
+//! `output_rows` and `duration_ms`.
+
+use crate::core::context::{ExecutionContext, OperationMetrics};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Metrics of one executed step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepReport {
+    /// Position of the step in the pipeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+    pub operation: String,
+    pub duration_ms: f64,
+    pub input_rows: usize,
+    pub output_rows: usize,
+    /// Output rows minus input rows
+    pub row_delta: i64,
+    pub input_columns: usize,
+    pub output_columns: usize,
+    /// Estimated memory of the output table in bytes
+    pub output_bytes: usize,
+    /// Nulls in the feature columns before the step
+    pub input_nulls: usize,
+    /// Nulls in the feature columns after the step
+    pub output_nulls: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Custom metrics recorded by the operation
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl From<&OperationMetrics> for StepReport {
+    fn from(metrics: &OperationMetrics) -> Self {
+        Self {
+            step: metrics.step,
+            operation: metrics.operation_name.clone(),
+            duration_ms: metrics.duration.as_secs_f64() * 1000.0,
+            input_rows: metrics.input_rows,
+            output_rows: metrics.output_rows,
+            row_delta: metrics.output_rows as i64 - metrics.input_rows as i64,
+            input_columns: metrics.input_columns,
+            output_columns: metrics.output_columns,
+            output_bytes: metrics.output_bytes,
+            input_nulls: metrics.input_nulls,
+            output_nulls: metrics.output_nulls,
+            warnings: metrics.warnings.clone(),
+            metrics: metrics
+                .custom
+                .iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+        }
+    }
+}
+
+/// Report of a pipeline run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    /// Name of the pipeline configuration, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    pub total_duration_ms: f64,
+    /// Input rows of the first step
+    pub input_rows: usize,
+    /// Output rows of the last step
+    pub output_rows: usize,
+    pub steps: Vec<StepReport>,
+    /// Custom metadata of the execution context
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ExecutionReport {
+    /// Report of the steps recorded in `context`
+    pub fn from_context(context: &ExecutionContext) -> Self {
+        let steps: Vec<StepReport> = context.metrics().iter().map(StepReport::from).collect();
+        Self {
+            pipeline: None,
+            total_duration_ms: context.total_duration().as_secs_f64() * 1000.0,
+            input_rows: steps.first().map_or(0, |s| s.input_rows),
+            output_rows: steps.last().map_or(0, |s| s.output_rows),
+            steps,
+            metadata: context
+                .metadata()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    pub fn to_json_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json_string()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TimeSeriesData;
+    use crate::operations::{DifferenceOperation, FillNullOperation};
+    use crate::pipeline::Pipeline;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_report_from_run() {
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000, 180_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[Some(1.0), None, Some(4.0), Some(7.0)]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(FillNullOperation::new(
+            crate::config::FillMethod::Forward,
+            None,
+        )));
+        pipeline.add_operation(Box::new(DifferenceOperation::new(1, None)));
+        let (_, report) = pipeline.process_with_report(data).unwrap();
+
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!((report.input_rows, report.output_rows), (4, 4));
+        let fill = &report.steps[0];
+        assert_eq!((fill.input_nulls, fill.output_nulls), (1, 0));
+        assert_eq!(report.steps[1].output_columns, 2);
+        assert_eq!(report.steps[1].row_delta, 0);
+        assert!(report.steps[1].output_bytes > 0);
+
+        let json = report.to_json_string().unwrap();
+        assert!(json.contains("\"operation\": \"difference\""));
+        let parsed = ExecutionReport::from_json_str(&json).unwrap();
+        assert_eq!(parsed.steps[0].input_nulls, 1);
+    }
+}
@@ -0,0 +1,436 @@
+//! Column selectors
+//!
+//! Wide historian extracts carry hundreds of tags, so listing every column an
+//! operation applies to is unmanageable. A `ColumnSelector` picks feature columns
+//! by name, wildcard, regex, dtype or physical quantity, and can exclude some of
+//! them. In TOML:
+//!
+//! ```toml
+//! columns = ["TI_101", "FI_*"]                        # names and wildcards
+//! columns = "TI_*"                                     # a single pattern
+//! columns = "quantity:temperature"                     # see `core::units`
+//! columns = { regex = "^TI_\\d{3}$" }
+//! columns = { dtype = "numeric", exclude = ["*_quality"] }
+//! ```
+//!
+//! In a table, every given field (`names`, `regex`, `dtype`, `quantity`, `all`)
+//! must match;
+//! `exclude` takes any selector. Wildcards are `*` (any run of characters) and `?`
+//! (one character).
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::DataType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Family of dtypes a selector can filter on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DtypeClass {
+    /// Integers and floats
+    Numeric,
+    Float,
+    Integer,
+    /// Strings and categoricals
+    String,
+    Boolean,
+    /// Dates, datetimes and durations
+    Temporal,
+}
+
+impl DtypeClass {
+    pub fn matches(self, dtype: &DataType) -> bool {
+        match self {
+            DtypeClass::Numeric => dtype.is_primitive_numeric(),
+            DtypeClass::Float => dtype.is_float(),
+            DtypeClass::Integer => dtype.is_integer(),
+            DtypeClass::String => dtype == &DataType::String || dtype.is_categorical(),
+            DtypeClass::Boolean => dtype == &DataType::Boolean,
+            DtypeClass::Temporal => dtype.is_temporal(),
+        }
+    }
+}
+
+/// Selection of feature columns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawSelector", into = "RawSelector")]
+pub enum ColumnSelector {
+    /// Column names; entries containing `*` or `?` are wildcards
+    Names(Vec<String>),
+    Regex(String),
+    Dtype(DtypeClass),
+    /// Columns measuring a physical quantity (see `TimeSeriesData::column_quantity`)
+    Quantity(String),
+    /// Columns matched by every selector (every column when empty)
+    All(Vec<ColumnSelector>),
+    /// Columns of the first selector not matched by the second
+    Exclude(Box<ColumnSelector>, Box<ColumnSelector>),
+}
+
+impl ColumnSelector {
+    /// Columns matching a wildcard pattern such as `TI_*`
+    pub fn pattern(pattern: &str) -> Self {
+        ColumnSelector::Names(vec![pattern.to_string()])
+    }
+
+    /// Columns whose name matches `pattern` anywhere (anchor with `^`/`$`)
+    pub fn regex(pattern: &str) -> Result<Self> {
+        compile_regex(pattern)?;
+        Ok(ColumnSelector::Regex(pattern.to_string()))
+    }
+
+    /// Columns of a dtype family
+    pub fn dtype(class: DtypeClass) -> Self {
+        ColumnSelector::Dtype(class)
+    }
+
+    /// Columns measuring `quantity`, e.g. `temperature`
+    pub fn quantity(quantity: &str) -> Self {
+        ColumnSelector::Quantity(quantity.to_string())
+    }
+
+    /// Every feature column
+    pub fn all() -> Self {
+        ColumnSelector::All(Vec::new())
+    }
+
+    /// Drop columns matched by `exclude` from this selection
+    pub fn excluding<S: Into<ColumnSelector>>(self, exclude: S) -> Self {
+        ColumnSelector::Exclude(Box::new(self), Box::new(exclude.into()))
+    }
+
+    /// The names, if this is a plain list of names without wildcards
+    pub fn exact_names(&self) -> Option<&[String]> {
+        match self {
+            ColumnSelector::Names(names) if !names.iter().any(|n| is_wildcard(n)) => Some(names),
+            _ => None,
+        }
+    }
+
+    /// Feature columns of `data` selected, in data order
+    ///
+    /// Plain name lists are returned as given and fail on missing columns.
+    pub fn resolve(&self, data: &TimeSeriesData) -> Result<Vec<String>> {
+        let df = data.dataframe();
+        if let Some(names) = self.exact_names() {
+            for name in names {
+                df.column(name)?;
+            }
+            return Ok(names.to_vec());
+        }
+        let matcher = self.compile()?;
+        let mut selected = Vec::new();
+        for name in data.feature_columns() {
+            let column = Column {
+                dtype: df.column(name)?.dtype(),
+                quantity: data.column_quantity(name),
+            };
+            if matcher.matches(name, Some(&column)) {
+                selected.push(name.clone());
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Names among `columns` selected when dtypes are unknown
+    ///
+    /// Dtype and quantity filters are assumed to match, so this over-approximates
+    /// the selection;
+    /// used for schema propagation before any data exists.
+    pub fn resolve_names(&self, columns: &[String]) -> Result<Vec<String>> {
+        if let Some(names) = self.exact_names() {
+            return Ok(names.to_vec());
+        }
+        let matcher = self.compile()?;
+        Ok(columns
+            .iter()
+            .filter(|name| matcher.matches(name, None))
+            .cloned()
+            .collect())
+    }
+
+    fn compile(&self) -> Result<Matcher> {
+        Ok(match self {
+            ColumnSelector::Names(names) => Matcher::Names(names.clone()),
+            ColumnSelector::Regex(pattern) => Matcher::Regex(compile_regex(pattern)?),
+            ColumnSelector::Dtype(class) => Matcher::Dtype(*class),
+            ColumnSelector::Quantity(quantity) => Matcher::Quantity(quantity.clone()),
+            ColumnSelector::All(selectors) => Matcher::All(
+                selectors
+                    .iter()
+                    .map(ColumnSelector::compile)
+                    .collect::<Result<_>>()?,
+            ),
+            ColumnSelector::Exclude(include, exclude) => {
+                Matcher::Exclude(Box::new(include.compile()?), Box::new(exclude.compile()?))
+            }
+        })
+    }
+}
+
+impl From<Vec<String>> for ColumnSelector {
+    fn from(names: Vec<String>) -> Self {
+        ColumnSelector::Names(names)
+    }
+}
+
+impl From<Vec<&str>> for ColumnSelector {
+    fn from(names: Vec<&str>) -> Self {
+        ColumnSelector::Names(names.into_iter().map(str::to_string).collect())
+    }
+}
+
+impl From<DtypeClass> for ColumnSelector {
+    fn from(class: DtypeClass) -> Self {
+        ColumnSelector::Dtype(class)
+    }
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| {
+        IndustrytsError::ConfigError(format!("Invalid column regex '{}': {}", pattern, e))
+    })
+}
+
+fn is_wildcard(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+/// Match `name` against a pattern with `*` and `?` wildcards
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last star absorb one more character
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Dtype and quantity of a column being matched
+struct Column<'a> {
+    dtype: &'a DataType,
+    quantity: Option<&'a str>,
+}
+
+/// Selector with compiled regexes
+enum Matcher {
+    Names(Vec<String>),
+    Regex(Regex),
+    Dtype(DtypeClass),
+    Quantity(String),
+    All(Vec<Matcher>),
+    Exclude(Box<Matcher>, Box<Matcher>),
+}
+
+impl Matcher {
+    /// Whether `name` matches; filters on an unknown `column` are assumed to match
+    fn matches(&self, name: &str, column: Option<&Column>) -> bool {
+        match self {
+            Matcher::Names(names) => names.iter().any(|n| wildcard_match(n, name)),
+            Matcher::Regex(regex) => regex.is_match(name),
+            Matcher::Dtype(class) => column.is_none_or(|c| class.matches(c.dtype)),
+            Matcher::Quantity(quantity) => {
+                column.is_none_or(|c| c.quantity == Some(quantity.as_str()))
+            }
+            Matcher::All(matchers) => matchers.iter().all(|m| m.matches(name, column)),
+            Matcher::Exclude(include, exclude) => {
+                include.matches(name, column) && !exclude.matches(name, column)
+            }
+        }
+    }
+}
+
+/// Serialized form: a pattern, a list of names/patterns or a table
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawSelector {
+    One(String),
+    Many(Vec<String>),
+    Table(RawTable),
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTable {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dtype: Option<DtypeClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quantity: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    all: Vec<RawSelector>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exclude: Option<Box<RawSelector>>,
+}
+
+impl TryFrom<RawSelector> for ColumnSelector {
+    type Error = IndustrytsError;
+
+    fn try_from(raw: RawSelector) -> Result<Self> {
+        let table = match raw {
+            RawSelector::One(pattern) => {
+                return Ok(match pattern.strip_prefix("quantity:") {
+                    Some(quantity) => ColumnSelector::quantity(quantity),
+                    None => ColumnSelector::pattern(&pattern),
+                });
+            }
+            RawSelector::Many(names) => return Ok(ColumnSelector::Names(names)),
+            RawSelector::Table(table) => table,
+        };
+        let mut parts = Vec::new();
+        if !table.names.is_empty() {
+            parts.push(ColumnSelector::Names(table.names));
+        }
+        if let Some(pattern) = table.regex {
+            parts.push(ColumnSelector::regex(&pattern)?);
+        }
+        if let Some(class) = table.dtype {
+            parts.push(ColumnSelector::Dtype(class));
+        }
+        if let Some(quantity) = table.quantity {
+            parts.push(ColumnSelector::Quantity(quantity));
+        }
+        for raw in table.all {
+            parts.push(ColumnSelector::try_from(raw)?);
+        }
+        let selector = if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            ColumnSelector::All(parts)
+        };
+        match table.exclude {
+            Some(exclude) => Ok(selector.excluding(ColumnSelector::try_from(*exclude)?)),
+            None => Ok(selector),
+        }
+    }
+}
+
+impl From<ColumnSelector> for RawSelector {
+    fn from(selector: ColumnSelector) -> Self {
+        let table = match selector {
+            ColumnSelector::Names(names) => return RawSelector::Many(names),
+            ColumnSelector::Regex(pattern) => RawTable {
+                regex: Some(pattern),
+                ..Default::default()
+            },
+            ColumnSelector::Dtype(class) => RawTable {
+                dtype: Some(class),
+                ..Default::default()
+            },
+            ColumnSelector::Quantity(quantity) => {
+                return RawSelector::One(format!("quantity:{}", quantity));
+            }
+            ColumnSelector::All(selectors) => RawTable {
+                all: selectors.into_iter().map(RawSelector::from).collect(),
+                ..Default::default()
+            },
+            ColumnSelector::Exclude(include, exclude) => RawTable {
+                all: vec![RawSelector::from(*include)],
+                exclude: Some(Box::new(RawSelector::from(*exclude))),
+                ..Default::default()
+            },
+        };
+        RawSelector::Table(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    fn make_data() -> TimeSeriesData {
+        let time_series = Series::new("time".into(), [0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("TI_101".into(), [1.0, 2.0]).into(),
+            Series::new("TI_101_quality".into(), [192i32, 192]).into(),
+            Series::new("TI_2".into(), [3.0, 4.0]).into(),
+            Series::new("FI_101".into(), [5.0, 6.0]).into(),
+            Series::new("state".into(), ["run", "idle"]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_selectors() {
+        let data = make_data();
+        let resolve = |selector: ColumnSelector| selector.resolve(&data).unwrap();
+
+        assert_eq!(
+            resolve(ColumnSelector::pattern("TI_*")),
+            ["TI_101", "TI_101_quality", "TI_2"]
+        );
+        assert_eq!(
+            resolve(ColumnSelector::regex(r"^TI_\d{3}$").unwrap()),
+            ["TI_101"]
+        );
+        assert_eq!(
+            resolve(ColumnSelector::dtype(DtypeClass::Numeric).excluding(vec!["*_quality"])),
+            ["TI_101", "TI_2", "FI_101"]
+        );
+        assert_eq!(resolve(vec!["FI_101", "TI_2"].into()), ["FI_101", "TI_2"]);
+        assert!(
+            ColumnSelector::from(vec!["missing"])
+                .resolve(&data)
+                .is_err()
+        );
+        assert!(wildcard_match("T?_*1", "TI_101"));
+        assert!(!wildcard_match("T?_*1", "TI_102"));
+    }
+
+    #[test]
+    fn test_selector_from_toml() {
+        #[derive(Deserialize, Serialize)]
+        struct Step {
+            columns: ColumnSelector,
+        }
+        let parse = |s: &str| toml::from_str::<Step>(s).unwrap().columns;
+
+        assert_eq!(
+            parse(r#"columns = "TI_*""#),
+            ColumnSelector::pattern("TI_*")
+        );
+        assert_eq!(
+            parse(r#"columns = ["TI_101", "FI_101"]"#).exact_names(),
+            Some(&["TI_101".to_string(), "FI_101".to_string()][..])
+        );
+        let selector = parse(r#"columns = { dtype = "float", exclude = "FI_*" }"#);
+        assert_eq!(selector.resolve(&make_data()).unwrap(), ["TI_101", "TI_2"]);
+        assert!(toml::from_str::<Step>(r#"columns = { regex = "(" }"#).is_err());
+
+        let step = Step { columns: selector };
+        let round_trip = toml::from_str::<Step>(&toml::to_string(&step).unwrap()).unwrap();
+        assert_eq!(
+            round_trip.columns.resolve(&make_data()).unwrap(),
+            ["TI_101", "TI_2"]
+        );
+    }
+}
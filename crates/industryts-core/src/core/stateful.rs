@@ -0,0 +1,278 @@
+//! Chunk-by-chunk execution of operations
+//!
+//! `Pipeline::process_stream` runs a pipeline over a sequence of time-ordered
+//! chunks that together form one dataset, e.g. a historian export too large for
+//! memory. Each step keeps a `StatefulOperation` that carries what the next chunk
+//! needs across the boundary: the last rows for lags and forward fill, running
+//! statistics for standardization, the unfinished bucket for resampling.
+//! Operations provide it through `Operation::stateful`; `Carry` covers the common
+//! case of an operation that only needs some preceding rows.
+//!
+//! States that can be saved as a `StepState` also carry across separate runs,
+//! e.g. scheduled incremental runs (see `Pipeline::process_warm`).
+
+use crate::core::data::TimeSeriesData;
+use crate::core::operation::Operation;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::time::Duration;
+
+/// An operation applied chunk by chunk with state kept between chunks
+pub trait StatefulOperation: Send {
+    /// Process the next chunk and return the output rows that are final
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData>;
+
+    /// Output held back for later chunks, called once after the last chunk
+    fn finish(&mut self) -> Result<Option<TimeSeriesData>> {
+        Ok(None)
+    }
+
+    /// Snapshot of the state, to continue in a later run
+    ///
+    /// The default fails: the state cannot be saved.
+    fn save_state(&self) -> Result<StepState> {
+        Err(IndustrytsError::InvalidOperation(
+            "the state of this step cannot be saved".to_string(),
+        ))
+    }
+
+    /// Continue from a snapshot taken with `save_state`
+    fn restore_state(&mut self, _state: StepState) -> Result<()> {
+        Err(IndustrytsError::InvalidOperation(
+            "the state of this step cannot be restored".to_string(),
+        ))
+    }
+}
+
+/// Saved state of a `StatefulOperation`
+#[derive(Debug, Clone, Default)]
+pub struct StepState {
+    /// Rows carried into the next chunk, e.g. the tail kept for lags
+    pub rows: Option<DataFrame>,
+    /// Other state, e.g. running statistics
+    pub values: serde_json::Value,
+}
+
+/// What a `Carry` keeps of each chunk for the next one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CarryMode {
+    /// The last input rows of each series
+    InputRows(usize),
+    /// The input rows within this duration of the last sample of each series
+    InputWindow(Duration),
+    /// The last output rows of each series, for operations that feed on their own
+    /// output (forward fill); the output must have the input's schema
+    OutputRows(usize),
+}
+
+/// Runs an operation on each chunk prefixed with rows kept from earlier chunks
+///
+/// The carried rows are removed from the output again: by position for
+/// operations that keep rows in place, by time for operations that remove or
+/// reorder rows. Rows sharing a timestamp should not be split across chunks.
+pub struct Carry<'a> {
+    operation: &'a dyn Operation,
+    mode: CarryMode,
+    keys: Vec<String>,
+    tail: Option<DataFrame>,
+}
+
+impl<'a> Carry<'a> {
+    pub fn new(operation: &'a dyn Operation, mode: CarryMode) -> Self {
+        Self {
+            operation,
+            mode,
+            keys: Vec::new(),
+            tail: None,
+        }
+    }
+
+    /// Keep rows per segment of these columns instead of per group
+    pub fn with_keys(mut self, keys: &[String]) -> Self {
+        self.keys = keys.to_vec();
+        self
+    }
+
+    /// Rows of `data` to carry into the next chunk
+    fn tail_of(&self, data: &TimeSeriesData) -> Result<DataFrame> {
+        const ROW_INDEX: &str = "__industryts_row";
+        let keys = if self.keys.is_empty() {
+            data.group_columns()
+        } else {
+            &self.keys
+        };
+        let df = data.dataframe();
+        let indexed = df.with_row_index(ROW_INDEX.into(), None)?;
+        let partitions = if keys.is_empty() {
+            vec![indexed]
+        } else {
+            indexed.partition_by_stable(keys.iter().map(String::as_str), true)?
+        };
+        let times = match self.mode {
+            CarryMode::InputWindow(_) => Some(data.timestamps_ms()?),
+            _ => None,
+        };
+
+        let mut rows: Vec<IdxSize> = Vec::new();
+        for partition in partitions {
+            let index: Vec<IdxSize> = partition
+                .column(ROW_INDEX)?
+                .idx()?
+                .into_no_null_iter()
+                .collect();
+            let from = match (self.mode, &times) {
+                (CarryMode::InputWindow(window), Some(times)) => {
+                    let last = index.last().and_then(|&i| times.get(i as usize));
+                    match last {
+                        Some(last) => {
+                            let start = last - window.as_millis() as i64;
+                            index
+                                .iter()
+                                .position(|&i| times.get(i as usize).is_some_and(|t| t >= start))
+                                .unwrap_or(index.len())
+                        }
+                        None => index.len(),
+                    }
+                }
+                (CarryMode::InputRows(n) | CarryMode::OutputRows(n), _) => {
+                    index.len().saturating_sub(n)
+                }
+                _ => index.len(),
+            };
+            rows.extend_from_slice(&index[from..]);
+        }
+        rows.sort_unstable();
+        Ok(df.take(&IdxCa::from_vec("rows".into(), rows))?)
+    }
+}
+
+impl StatefulOperation for Carry<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        let chunk_start = chunk.timestamps_ms()?.min();
+        let groups = chunk.group_columns().to_vec();
+        let zone = chunk
+            .time_zone()
+            .filter(|_| !self.operation.manages_time_zone())
+            .map(str::to_string);
+        let carried = self.tail.as_ref().map_or(0, DataFrame::height);
+        let input = match self.tail.take() {
+            Some(mut tail) if carried > 0 => {
+                tail.vstack_mut(chunk.dataframe())?;
+                tail.as_single_chunk_par();
+                TimeSeriesData::with_metadata(tail, chunk.metadata().clone())?
+            }
+            _ => chunk,
+        };
+
+        if let CarryMode::InputRows(_) | CarryMode::InputWindow(_) = self.mode {
+            self.tail = Some(self.tail_of(&input)?);
+        }
+        let output = restore_metadata(self.operation.execute(input)?, &groups, zone.as_deref())?;
+        if let CarryMode::OutputRows(_) = self.mode {
+            self.tail = Some(self.tail_of(&output)?);
+        }
+        if carried == 0 {
+            return Ok(output);
+        }
+
+        let df = if self.operation.removes_rows() || self.operation.reorders_rows() {
+            match chunk_start {
+                Some(start) => output
+                    .dataframe()
+                    .filter(&output.timestamps_ms()?.gt_eq(start))?,
+                None => output.dataframe().clear(),
+            }
+        } else {
+            let rows = output.len().saturating_sub(carried);
+            output.dataframe().slice(carried as i64, rows)
+        };
+        TimeSeriesData::with_metadata(df, output.metadata().clone())
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(StepState {
+            rows: self.tail.clone(),
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        self.tail = state.rows;
+        Ok(())
+    }
+}
+
+/// `output` with the group columns and time zone of the input it was computed from
+pub(crate) fn restore_metadata(
+    output: TimeSeriesData,
+    groups: &[String],
+    time_zone: Option<&str>,
+) -> Result<TimeSeriesData> {
+    let mut output = if groups.is_empty() || output.group_columns() == groups {
+        output
+    } else {
+        output.with_group_columns(groups)?
+    };
+    if output.time_zone().is_none()
+        && let Some(zone) = time_zone
+    {
+        output.metadata_mut().time_zone = Some(zone.to_string());
+    }
+    Ok(output)
+}
+
+/// `first` followed by the rows of `rest`
+pub(crate) fn concat_chunks(
+    first: TimeSeriesData,
+    rest: &TimeSeriesData,
+) -> Result<TimeSeriesData> {
+    let metadata = first.metadata().clone();
+    let mut df = first.into_dataframe();
+    df.vstack_mut(rest.dataframe())?;
+    df.as_single_chunk_par();
+    TimeSeriesData::with_metadata(df, metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::LagOperation;
+
+    #[test]
+    fn test_carry_matches_whole_frame() {
+        let times: Vec<i64> = (0..10).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new(
+                "value".into(),
+                (0..10).map(|i| i as f64).collect::<Vec<_>>(),
+            )
+            .into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+        let op = LagOperation::new(vec![2], None);
+        let expected = op.execute(data.clone()).unwrap();
+
+        let mut state = Carry::new(&op, CarryMode::InputRows(2));
+        let mut output: Option<TimeSeriesData> = None;
+        for (offset, len) in [(0, 3), (3, 1), (4, 6)] {
+            let chunk = data.dataframe().slice(offset, len);
+            let chunk = TimeSeriesData::with_metadata(chunk, data.metadata().clone()).unwrap();
+            let result = state.process_chunk(chunk).unwrap();
+            output = Some(match output {
+                Some(output) => concat_chunks(output, &result).unwrap(),
+                None => result,
+            });
+        }
+        assert!(
+            output
+                .unwrap()
+                .dataframe()
+                .equals_missing(expected.dataframe())
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Column metadata loaded from a tag-metadata file
+//!
+//! Units, plausible ranges and descriptions of a plant's tags are usually kept
+//! in one engineering list, not repeated in every pipeline. A tag-metadata file
+//! holds them per column name, as TOML:
+//!
+//! ```toml
+//! [[tags]]
+//! tag = "reactor_temp"
+//! unit = "degC"
+//! quantity = "temperature"
+//! min = 0.0
+//! max = 400.0
+//! description = "Reactor inlet temperature"
+//! interpolation = "linear"
+//! ```
+//!
+//! or as a CSV file with a header row naming the `tag` column and any of `unit`,
+//! `quantity`, `description`, `asset`, `min`, `max` and `interpolation`.
+//! Referenced as `tag_metadata` in the `[pipeline]` section, the file is applied
+//! to every input at ingest: columns get the properties of the same names (see
+//! `TimeSeriesData::column_property`) unless the input already sets them.
+//! Validation checks the ranges, column arithmetic the units, `quantity:`
+//! selectors group columns by quantity, and `reconstruct` follows the declared
+//! interpolation ("step" or "linear").
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::mapping::split_csv_line;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Metadata of one tag
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagInfo {
+    /// Column name the metadata applies to
+    pub tag: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Physical quantity measured, e.g. `temperature` (default: from the unit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Path in the asset hierarchy, e.g. `plant_a/line_1/pump_3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Lowest plausible value (inclusive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Highest plausible value (inclusive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Value between stored samples, "step" or "linear" (see `Interpolation`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpolation: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagMetadataFile {
+    tags: Vec<TagInfo>,
+}
+
+/// Metadata of a set of tags, keyed by column name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagMetadata {
+    tags: BTreeMap<String, TagInfo>,
+}
+
+impl TagMetadata {
+    pub fn new(tags: Vec<TagInfo>) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        for info in tags {
+            if let Some(previous) = map.insert(info.tag.clone(), info) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Tag metadata lists '{}' twice",
+                    previous.tag
+                )));
+            }
+        }
+        Ok(Self { tags: map })
+    }
+
+    /// Load tag metadata from a `.toml` or `.csv` file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let tags = if is_csv {
+            parse_csv(&contents)?
+        } else {
+            toml::from_str::<TagMetadataFile>(&contents)?.tags
+        };
+        Self::new(tags)
+    }
+
+    /// Metadata of `tag`, if listed
+    pub fn get(&self, tag: &str) -> Option<&TagInfo> {
+        self.tags.get(tag)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// Parse a CSV tag-metadata file
+fn parse_csv(contents: &str) -> Result<Vec<TagInfo>> {
+    let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+    let header = split_csv_line(lines.next().unwrap_or_default());
+    let position = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let Some(tag) = position("tag") else {
+        return Err(IndustrytsError::ConfigError(
+            "Tag metadata CSV needs a 'tag' header column".to_string(),
+        ));
+    };
+    let (unit, quantity) = (position("unit"), position("quantity"));
+    let (description, asset) = (position("description"), position("asset"));
+    let (min, max) = (position("min"), position("max"));
+    let interpolation = position("interpolation");
+
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let fields = split_csv_line(line);
+            let get = |index: Option<usize>| {
+                index
+                    .and_then(|i| fields.get(i))
+                    .filter(|f| !f.is_empty())
+                    .cloned()
+            };
+            let number = |index: Option<usize>| {
+                get(index)
+                    .map(|v| {
+                        v.parse::<f64>().map_err(|_| {
+                            IndustrytsError::ConfigError(format!(
+                                "Tag metadata CSV line {}: '{}' is not a number",
+                                i + 2,
+                                v
+                            ))
+                        })
+                    })
+                    .transpose()
+            };
+            let Some(tag) = get(Some(tag)) else {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Tag metadata CSV line {} lacks a tag",
+                    i + 2
+                )));
+            };
+            Ok(TagInfo {
+                tag,
+                unit: get(unit),
+                quantity: get(quantity),
+                description: get(description),
+                asset: get(asset),
+                min: number(min)?,
+                max: number(max)?,
+                interpolation: get(interpolation),
+            })
+        })
+        .collect()
+}
+
+impl TimeSeriesData {
+    /// Set the properties listed in `metadata` on the matching columns
+    ///
+    /// Properties the data already has are kept.
+    pub fn apply_tag_metadata(&mut self, metadata: &TagMetadata) {
+        let columns: Vec<String> = self
+            .dataframe()
+            .get_column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        for column in columns {
+            let Some(info) = metadata.get(&column) else {
+                continue;
+            };
+            let properties = [
+                ("unit", info.unit.clone()),
+                ("quantity", info.quantity.clone()),
+                ("description", info.description.clone()),
+                ("asset", info.asset.clone()),
+                ("min", info.min.map(|v| v.to_string())),
+                ("max", info.max.map(|v| v.to_string())),
+                ("interpolation", info.interpolation.clone()),
+            ];
+            for (field, value) in properties {
+                if let Some(value) = value
+                    && self.column_property(&column, field).is_none()
+                {
+                    self.set_column_property(&column, field, &value);
+                }
+            }
+        }
+    }
+
+    /// Plausible range of a column from its `min` and `max` properties
+    pub fn column_range(&self, column: &str) -> (Option<f64>, Option<f64>) {
+        let bound = |field| self.column_property(column, field)?.parse().ok();
+        (bound("min"), bound("max"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::ValidateOperation;
+    use crate::operations::data_quality::ValidationRules;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_metadata_is_applied_from_csv() {
+        let path = std::env::temp_dir().join(format!("industryts_tags_{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "tag,unit,min,max,description\n\
+             reactor_temp,degC,0,400,\"Reactor inlet, east\"\n\
+             flow,m3/h,,,\n",
+        )
+        .unwrap();
+        let metadata = TagMetadata::from_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(metadata.len(), 2);
+
+        let mut data = Fixture::every(Duration::from_secs(60), 2)
+            .with_column("reactor_temp", [350.0, 351.0])
+            .with_column("flow", [1.0, 2.0])
+            .build()
+            .unwrap();
+        data.set_column_property("flow", "unit", "l/s");
+        data.apply_tag_metadata(&metadata);
+        assert_eq!(data.column_unit("reactor_temp"), Some("degC"));
+        assert_eq!(data.column_range("reactor_temp"), (Some(0.0), Some(400.0)));
+        assert_eq!(
+            data.column_description("reactor_temp"),
+            Some("Reactor inlet, east")
+        );
+        assert_eq!(data.column_unit("flow"), Some("l/s"));
+        assert_eq!(data.column_range("flow"), (None, None));
+        assert!(parse_csv("tag,min\nflow,high\n").is_err());
+
+        // Applied at ingest, the ranges are checked by validation without rules
+        let mut pipeline = Pipeline::new();
+        pipeline.set_tag_metadata(metadata);
+        pipeline.add_operation(Box::new(ValidateOperation::new(ValidationRules::default())));
+        let hot = Fixture::every(Duration::from_secs(60), 2)
+            .with_column("reactor_temp", [350.0, 450.0])
+            .build()
+            .unwrap();
+        let Err(e) = pipeline.process(hot) else {
+            panic!("450 degC is outside the tag's range");
+        };
+        assert!(e.to_string().contains("reactor_temp.range"));
+    }
+}
@@ -0,0 +1,151 @@
+//! Physical quantities and conversion of engineering units
+//!
+//! Columns are grouped by the physical quantity they measure (temperature,
+//! pressure, flow, ...) so a step can apply to all of them with the selector
+//! `columns = "quantity:temperature"`. A column's quantity is its `quantity`
+//! property, set from the tag-metadata file, or else the quantity of its unit
+//! when the unit is listed here. Units of one quantity convert into each other
+//! (see `convert_units`), so a group can be normalized to one unit.
+
+use crate::core::data::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+
+/// Physical quantity of a known unit and its conversion to the base unit
+///
+/// A value `v` in this unit is `v * scale + offset` in the base unit of the
+/// quantity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitInfo {
+    pub quantity: &'static str,
+    pub scale: f64,
+    pub offset: f64,
+}
+
+/// Known units: symbol, quantity, scale and offset to the base unit
+const UNITS: &[(&str, &str, f64, f64)] = &[
+    ("K", "temperature", 1.0, 0.0),
+    ("degC", "temperature", 1.0, 273.15),
+    ("°C", "temperature", 1.0, 273.15),
+    ("degF", "temperature", 5.0 / 9.0, 459.67 * 5.0 / 9.0),
+    ("°F", "temperature", 5.0 / 9.0, 459.67 * 5.0 / 9.0),
+    ("Pa", "pressure", 1.0, 0.0),
+    ("kPa", "pressure", 1e3, 0.0),
+    ("MPa", "pressure", 1e6, 0.0),
+    ("mbar", "pressure", 1e2, 0.0),
+    ("bar", "pressure", 1e5, 0.0),
+    ("psi", "pressure", 6_894.757_293_168, 0.0),
+    ("atm", "pressure", 101_325.0, 0.0),
+    ("m3/s", "volume_flow", 1.0, 0.0),
+    ("m3/h", "volume_flow", 1.0 / 3_600.0, 0.0),
+    ("l/s", "volume_flow", 1e-3, 0.0),
+    ("l/min", "volume_flow", 1e-3 / 60.0, 0.0),
+    ("l/h", "volume_flow", 1e-3 / 3_600.0, 0.0),
+    ("gpm", "volume_flow", 6.309_019_64e-5, 0.0),
+    ("kg/s", "mass_flow", 1.0, 0.0),
+    ("kg/h", "mass_flow", 1.0 / 3_600.0, 0.0),
+    ("t/h", "mass_flow", 1_000.0 / 3_600.0, 0.0),
+    ("W", "power", 1.0, 0.0),
+    ("kW", "power", 1e3, 0.0),
+    ("MW", "power", 1e6, 0.0),
+    ("hp", "power", 745.699_872, 0.0),
+    ("J", "energy", 1.0, 0.0),
+    ("kJ", "energy", 1e3, 0.0),
+    ("MJ", "energy", 1e6, 0.0),
+    ("Wh", "energy", 3.6e3, 0.0),
+    ("kWh", "energy", 3.6e6, 0.0),
+    ("MWh", "energy", 3.6e9, 0.0),
+    ("mm", "length", 1e-3, 0.0),
+    ("cm", "length", 1e-2, 0.0),
+    ("m", "length", 1.0, 0.0),
+    ("km", "length", 1e3, 0.0),
+    ("in", "length", 0.0254, 0.0),
+    ("ft", "length", 0.3048, 0.0),
+    ("g", "mass", 1e-3, 0.0),
+    ("kg", "mass", 1.0, 0.0),
+    ("t", "mass", 1e3, 0.0),
+    ("lb", "mass", 0.453_592_37, 0.0),
+    ("mA", "current", 1e-3, 0.0),
+    ("A", "current", 1.0, 0.0),
+    ("mV", "voltage", 1e-3, 0.0),
+    ("V", "voltage", 1.0, 0.0),
+    ("kV", "voltage", 1e3, 0.0),
+];
+
+/// Quantity and conversion of `unit`, if known
+pub fn unit_info(unit: &str) -> Option<UnitInfo> {
+    let unit = unit.trim();
+    UNITS
+        .iter()
+        .find(|(symbol, ..)| *symbol == unit)
+        .map(|&(_, quantity, scale, offset)| UnitInfo {
+            quantity,
+            scale,
+            offset,
+        })
+}
+
+/// Scale and offset converting values in `from` to `to`: `v * scale + offset`
+///
+/// Fails if either unit is unknown or they measure different quantities.
+pub fn convert_units(from: &str, to: &str) -> Result<(f64, f64)> {
+    let known = |unit: &str| {
+        unit_info(unit)
+            .ok_or_else(|| IndustrytsError::InvalidOperation(format!("Unknown unit '{}'", unit)))
+    };
+    let (from_info, to_info) = (known(from)?, known(to)?);
+    if from_info.quantity != to_info.quantity {
+        return Err(IndustrytsError::InvalidOperation(format!(
+            "Cannot convert {} ({}) to {} ({})",
+            from, from_info.quantity, to, to_info.quantity
+        )));
+    }
+    let scale = from_info.scale / to_info.scale;
+    let offset = (from_info.offset - to_info.offset) / to_info.scale;
+    Ok((scale, offset))
+}
+
+impl TimeSeriesData {
+    /// Physical quantity of a column: its `quantity` property, or else the
+    /// quantity of its unit
+    pub fn column_quantity(&self, column: &str) -> Option<&str> {
+        self.column_property(column, "quantity").or_else(|| {
+            self.column_unit(column)
+                .and_then(unit_info)
+                .map(|info| info.quantity)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_quantities_and_conversions() {
+        let convert = |v: f64, from: &str, to: &str| {
+            let (scale, offset) = convert_units(from, to).unwrap();
+            v * scale + offset
+        };
+        assert!((convert(100.0, "degC", "degF") - 212.0).abs() < 1e-9);
+        assert!((convert(32.0, "°F", "K") - 273.15).abs() < 1e-9);
+        assert!((convert(2.5, "bar", "kPa") - 250.0).abs() < 1e-9);
+        assert!((convert(36.0, "m3/h", "l/s") - 10.0).abs() < 1e-9);
+        assert!(convert_units("bar", "degC").is_err());
+        assert!(convert_units("furlong", "m").is_err());
+
+        let mut data = Fixture::every(Duration::from_secs(60), 1)
+            .with_column("TI_101", [80.0])
+            .with_column("PI_101", [2.0])
+            .with_column("level", [40.0])
+            .build()
+            .unwrap();
+        data.set_column_property("TI_101", "unit", "degC");
+        data.set_column_property("PI_101", "unit", "barg");
+        data.set_column_property("PI_101", "quantity", "pressure");
+        assert_eq!(data.column_quantity("TI_101"), Some("temperature"));
+        assert_eq!(data.column_quantity("PI_101"), Some("pressure"));
+        assert_eq!(data.column_quantity("level"), None);
+    }
+}
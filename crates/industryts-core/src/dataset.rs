@@ -0,0 +1,576 @@
+//! Supervised dataset construction
+//!
+//! A predictive-maintenance dataset pairs the features known at a prediction
+//! time `t` with a target observed at `t + horizon`. Its most common silent flaw
+//! is a feature that already holds information from after `t`: a lead (a
+//! negative lag), a backward fill or interpolation towards the next reading,
+//! resampled buckets labelled by their start, or a scaler fitted on the whole
+//! history. Such features look excellent offline and fail in production.
+//!
+//! `DatasetBuilder` runs a feature pipeline and joins the target, refusing the
+//! pipeline if any step reads ahead (see `Pipeline::lookahead`). The target of a
+//! row at `t` is the last non-null reading of the target column at or before
+//! `t + horizon` of the same entity (group columns). Rows without a target are
+//! dropped: those whose horizon passes the end of the entity's data, and those
+//! without a reading in `(t, t + horizon]`.
+//!
+//! Sequence models take windows instead: `TimeSeriesData::to_supervised` slices
+//! each entity's rows into `window` consecutive rows of every feature column (X)
+//! and the `horizon` rows that follow them (y), one sample every `stride` rows,
+//! as DataFrames or row-major tensors.
+//!
+//! Models are evaluated with `TimeSeriesSplit`: each fold tests on a later time
+//! window than it trains on, optionally with a gap (embargo) between the two so
+//! features and targets spanning the boundary do not leak, and with a training
+//! window that either grows from the start of the data or slides along.
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::merge::row_keys;
+use crate::pipeline::Pipeline;
+use crate::utils::duration::format_duration;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Feature rows paired with a future target
+pub struct SupervisedDataset {
+    /// Feature rows with the target column, which is not a feature column
+    pub data: TimeSeriesData,
+    pub target_column: String,
+    /// Rows dropped for lack of a target
+    pub dropped_rows: usize,
+}
+
+/// Builder of (features, target, horizon) datasets guarded against target leakage
+pub struct DatasetBuilder {
+    target: String,
+    horizon: Duration,
+    features: Option<Pipeline>,
+    target_name: Option<String>,
+}
+
+impl DatasetBuilder {
+    /// Predict the column `target` `horizon` ahead
+    pub fn new(target: &str, horizon: Duration) -> Self {
+        Self {
+            target: target.to_string(),
+            horizon,
+            features: None,
+            target_name: None,
+        }
+    }
+
+    /// Compute the features with `pipeline` (default: the input feature columns)
+    pub fn with_features(mut self, pipeline: Pipeline) -> Self {
+        self.features = Some(pipeline);
+        self
+    }
+
+    /// Name of the target column (default `<target>_in_<horizon>`, e.g. `vibration_in_1h`)
+    pub fn with_target_name(mut self, name: &str) -> Self {
+        self.target_name = Some(name.to_string());
+        self
+    }
+
+    fn target_name(&self) -> String {
+        self.target_name
+            .clone()
+            .unwrap_or_else(|| format!("{}_in_{}", self.target, format_duration(self.horizon)))
+    }
+
+    /// Fail if the horizon is zero or a feature step reads past the prediction time
+    pub fn check(&self) -> Result<()> {
+        if self.horizon.is_zero() {
+            return Err(IndustrytsError::ConfigError(
+                "Prediction horizon must be positive".to_string(),
+            ));
+        }
+        let reasons = self
+            .features
+            .as_ref()
+            .map(Pipeline::lookahead)
+            .unwrap_or_default();
+        if !reasons.is_empty() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "Features use data after the prediction time:\n  {}",
+                reasons.join("\n  ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compute the features of `data` and join the target `horizon` ahead
+    pub fn build(&self, data: TimeSeriesData) -> Result<SupervisedDataset> {
+        self.check()?;
+        let target = data
+            .dataframe()
+            .column(&self.target)
+            .map_err(|_| IndustrytsError::ColumnNotFound(self.target.clone()))?
+            .clone();
+        let group_columns = data.group_columns().to_vec();
+
+        // Non-null readings of the target per entity, ordered by time, and the end
+        // of each entity's data
+        let mut readings: HashMap<String, Vec<(i64, usize)>> = HashMap::new();
+        let mut ends: HashMap<String, i64> = HashMap::new();
+        let times = data.timestamps_ms()?;
+        let keys = row_keys(data.dataframe(), &group_columns)?;
+        for (row, (key, time)) in keys.into_iter().zip(times.iter()).enumerate() {
+            let Some(time) = time else {
+                continue;
+            };
+            let end = ends.entry(key.clone()).or_insert(time);
+            *end = (*end).max(time);
+            if !target.get(row)?.is_null() {
+                readings.entry(key).or_default().push((time, row));
+            }
+        }
+        for rows in readings.values_mut() {
+            rows.sort_unstable();
+        }
+
+        let features = match &self.features {
+            Some(pipeline) => pipeline.process(data)?,
+            None => data,
+        };
+        let horizon_ms = self.horizon.as_millis() as i64;
+        let times = features.timestamps_ms()?;
+        let rows: IdxCa = row_keys(features.dataframe(), &group_columns)?
+            .iter()
+            .zip(times.iter())
+            .map(|(key, time)| {
+                let (rows, time) = (readings.get(key)?, time?);
+                let due = time + horizon_ms;
+                if due > ends[key] {
+                    return None;
+                }
+                let (t, row) = rows[rows.partition_point(|&(t, _)| t <= due).checked_sub(1)?];
+                (t > time).then_some(row as IdxSize)
+            })
+            .collect();
+
+        let target_column = self.target_name();
+        let mut df = features.dataframe().clone();
+        if df.column(&target_column).is_ok() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "Target column '{}' clashes with a feature column",
+                target_column
+            )));
+        }
+        let mut values = target.take(&rows)?;
+        values.rename(target_column.as_str().into());
+        df.with_column(values)?;
+        let df = df.filter(&rows.is_not_null())?;
+        let dropped_rows = features.len() - df.height();
+        Ok(SupervisedDataset {
+            data: TimeSeriesData::with_metadata(df, features.metadata().clone())?,
+            target_column,
+            dropped_rows,
+        })
+    }
+}
+
+/// Row-major `f64` tensor, e.g. for `numpy.reshape` or `ndarray::Array::from_shape_vec`
+///
+/// Nulls are NaN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+/// Windowed samples returned by `TimeSeriesData::to_supervised`
+///
+/// Both frames have one row per sample, keyed by the time of the sample's last
+/// window row (the prediction time) and its group columns. `x` holds
+/// `<feature>_t-<k>` for the `window - 1` preceding rows and `<feature>_t` for
+/// the last one; `y` holds `<feature>_t+<h>` for the `horizon` following rows.
+pub struct SupervisedWindows {
+    pub x: DataFrame,
+    pub y: DataFrame,
+    features: Vec<String>,
+    window: usize,
+    horizon: usize,
+}
+
+impl SupervisedWindows {
+    /// Windowed feature columns, in tensor order
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// Number of samples
+    pub fn len(&self) -> usize {
+        self.x.height()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.height() == 0
+    }
+
+    /// X as a tensor of shape `[samples, window, features]`
+    pub fn x_tensor(&self) -> Result<Tensor> {
+        let steps: Vec<String> = (0..self.window).rev().map(step_suffix).collect();
+        self.tensor(&self.x, &steps)
+    }
+
+    /// y as a tensor of shape `[samples, horizon, features]`
+    pub fn y_tensor(&self) -> Result<Tensor> {
+        let steps: Vec<String> = (1..=self.horizon).map(|h| format!("t+{}", h)).collect();
+        self.tensor(&self.y, &steps)
+    }
+
+    fn tensor(&self, df: &DataFrame, steps: &[String]) -> Result<Tensor> {
+        let (samples, features) = (df.height(), self.features.len());
+        let mut values = vec![f64::NAN; samples * steps.len() * features];
+        for (j, feature) in self.features.iter().enumerate() {
+            for (k, step) in steps.iter().enumerate() {
+                let column = df.column(&format!("{}_{}", feature, step))?;
+                for (i, v) in column.f64()?.into_iter().enumerate() {
+                    if let Some(v) = v {
+                        values[(i * steps.len() + k) * features + j] = v;
+                    }
+                }
+            }
+        }
+        Ok(Tensor {
+            shape: vec![samples, steps.len(), features],
+            values,
+        })
+    }
+}
+
+/// Column suffix of the row `k` rows before the prediction time
+fn step_suffix(k: usize) -> String {
+    match k {
+        0 => "t".to_string(),
+        k => format!("t-{}", k),
+    }
+}
+
+impl TimeSeriesData {
+    /// Slice each entity's rows into X windows of `window` rows and y targets of the
+    /// `horizon` rows after them, starting a sample every `stride` rows
+    ///
+    /// Steps are rows, so irregular data should be regularized first. Windows never
+    /// span two entities, and samples whose targets would pass the end of the
+    /// entity's rows are left out. Feature columns must be numeric.
+    pub fn to_supervised(
+        &self,
+        window: usize,
+        horizon: usize,
+        stride: usize,
+    ) -> Result<SupervisedWindows> {
+        if window == 0 || horizon == 0 || stride == 0 {
+            return Err(IndustrytsError::ConfigError(format!(
+                "window, horizon and stride must be positive, got {}, {} and {}",
+                window, horizon, stride
+            )));
+        }
+        let df = self.dataframe();
+        for feature in self.feature_columns() {
+            let dtype = df.column(feature)?.dtype();
+            if !dtype.is_primitive_numeric() {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "to_supervised needs numeric feature columns, '{}' is {}",
+                    feature, dtype
+                )));
+            }
+        }
+
+        // Rows of each entity in time order, entities in order of appearance
+        let times = self.timestamps_ms()?;
+        let mut entities: Vec<Vec<(i64, usize)>> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let keys = row_keys(df, self.group_columns())?;
+        for (row, (key, time)) in keys.into_iter().zip(times.iter()).enumerate() {
+            let Some(time) = time else {
+                continue;
+            };
+            let position = *positions.entry(key).or_insert_with(|| {
+                entities.push(Vec::new());
+                entities.len() - 1
+            });
+            entities[position].push((time, row));
+        }
+
+        // Row of each sample at every offset from the prediction time
+        let offsets = -(window as i64 - 1)..=horizon as i64;
+        let mut rows: Vec<Vec<IdxSize>> = offsets.clone().map(|_| Vec::new()).collect();
+        for entity in &mut entities {
+            entity.sort_by_key(|&(time, _)| time);
+            let span = window + horizon;
+            for start in (0..entity.len().saturating_sub(span - 1)).step_by(stride) {
+                for (offset, rows) in rows.iter_mut().enumerate() {
+                    rows.push(entity[start + offset].1 as IdxSize);
+                }
+            }
+        }
+        let rows: Vec<IdxCa> = rows
+            .into_iter()
+            .map(|rows| IdxCa::from_vec("".into(), rows))
+            .collect();
+        let at = |offset: i64| &rows[(offset + window as i64 - 1) as usize];
+
+        let mut keys: Vec<String> = vec![self.time_column().to_string()];
+        keys.extend(self.group_columns().iter().cloned());
+        let keyed = df.select(keys)?.take(at(0))?;
+        let (mut x, mut y) = (keyed.clone(), keyed);
+        for feature in self.feature_columns() {
+            let values = df.column(feature)?.cast(&DataType::Float64)?;
+            for offset in offsets.clone() {
+                let (frame, name) = match offset {
+                    o if o <= 0 => (&mut x, format!("{}_{}", feature, step_suffix(-o as usize))),
+                    o => (&mut y, format!("{}_t+{}", feature, o)),
+                };
+                let mut column = values.take(at(offset))?;
+                column.rename(name.as_str().into());
+                frame.with_column(column)?;
+            }
+        }
+        Ok(SupervisedWindows {
+            x,
+            y,
+            features: self.feature_columns().to_vec(),
+            window,
+            horizon,
+        })
+    }
+}
+
+/// Training window of the folds of a `TimeSeriesSplit`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitWindow {
+    /// From the start of the data up to the gap before the test window
+    #[default]
+    Expanding,
+    /// The given duration before the gap
+    Sliding(Duration),
+}
+
+/// Row ranges of one fold of time-sorted data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    pub train: Range<usize>,
+    pub test: Range<usize>,
+}
+
+/// Walk-forward cross-validation splitter
+///
+/// The last `n_splits` test windows of `test_size` (default: the time span
+/// divided by `n_splits + 1`) end at the last sample. Each fold trains on the
+/// samples before its test window, less the `gap`. Folds are split by time, so
+/// rows of all entities sharing a timestamp land on the same side.
+#[derive(Debug, Clone)]
+pub struct TimeSeriesSplit {
+    n_splits: usize,
+    test_size: Option<Duration>,
+    gap: Duration,
+    window: SplitWindow,
+}
+
+impl TimeSeriesSplit {
+    pub fn new(n_splits: usize) -> Self {
+        Self {
+            n_splits,
+            test_size: None,
+            gap: Duration::ZERO,
+            window: SplitWindow::Expanding,
+        }
+    }
+
+    /// Duration of each test window
+    pub fn with_test_size(mut self, test_size: Duration) -> Self {
+        self.test_size = Some(test_size);
+        self
+    }
+
+    /// Leave out the samples within `gap` before each test window
+    pub fn with_gap(mut self, gap: Duration) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Expanding (default) or sliding training window
+    pub fn with_window(mut self, window: SplitWindow) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Row ranges of each fold; the rows of `data` must be sorted by time
+    pub fn split_indices(&self, data: &TimeSeriesData) -> Result<Vec<Fold>> {
+        if self.n_splits == 0 {
+            return Err(IndustrytsError::ConfigError(
+                "TimeSeriesSplit needs at least one split".to_string(),
+            ));
+        }
+        let times = data
+            .timestamps_ms()?
+            .into_iter()
+            .collect::<Option<Vec<i64>>>()
+            .ok_or_else(|| {
+                IndustrytsError::InvalidOperation("cannot split data with null timestamps".into())
+            })?;
+        if times.windows(2).any(|w| w[1] < w[0]) {
+            return Err(IndustrytsError::InvalidOperation(
+                "cannot split data that is not sorted by time".to_string(),
+            ));
+        }
+        let (Some(&first), Some(&last)) = (times.first(), times.last()) else {
+            return Ok(Vec::new());
+        };
+
+        let end = last + 1;
+        let n = self.n_splits as i64;
+        let test_ms = match self.test_size {
+            Some(size) => size.as_millis() as i64,
+            None => (end - first) / (n + 1),
+        };
+        let gap_ms = self.gap.as_millis() as i64;
+        let row = |time: i64| times.partition_point(|&t| t < time);
+
+        (0..n)
+            .map(|k| {
+                let test_start = end - (n - k) * test_ms;
+                let train_end = test_start - gap_ms;
+                let train_start = match self.window {
+                    SplitWindow::Expanding => first,
+                    SplitWindow::Sliding(window) => train_end - window.as_millis() as i64,
+                };
+                let fold = Fold {
+                    train: row(train_start)..row(train_end),
+                    test: row(test_start)..row(test_start + test_ms),
+                };
+                if fold.train.is_empty() || fold.test.is_empty() {
+                    return Err(IndustrytsError::InvalidOperation(format!(
+                        "fold {} of {} has no {} rows; use fewer splits or a smaller test size",
+                        k + 1,
+                        n,
+                        if fold.train.is_empty() {
+                            "training"
+                        } else {
+                            "test"
+                        }
+                    )));
+                }
+                Ok(fold)
+            })
+            .collect()
+    }
+
+    /// Training and test data of each fold; the rows of `data` must be sorted by time
+    pub fn split(&self, data: &TimeSeriesData) -> Result<Vec<(TimeSeriesData, TimeSeriesData)>> {
+        let slice = |rows: &Range<usize>| {
+            let df = data.dataframe().slice(rows.start as i64, rows.len());
+            TimeSeriesData::with_metadata(df, data.metadata().clone())
+        };
+        self.split_indices(data)?
+            .iter()
+            .map(|fold| Ok((slice(&fold.train)?, slice(&fold.test)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::LagOperation;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_leaking_features_are_rejected() {
+        let data = Fixture::every(Duration::from_secs(60), 10)
+            .with_column("vib", (0..10).map(f64::from).collect::<Vec<_>>())
+            .build()
+            .unwrap();
+        let horizon = Duration::from_secs(120);
+
+        let mut leaking = Pipeline::new();
+        leaking.add_operation(Box::new(LagOperation::new(vec![1, -1], None)));
+        let builder = DatasetBuilder::new("vib", horizon).with_features(leaking);
+        let Err(e) = builder.build(data.clone()) else {
+            panic!("a lead must be rejected");
+        };
+        assert!(e.to_string().contains("step 0 (lag): lag period -1"));
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        let dataset = DatasetBuilder::new("vib", horizon)
+            .with_features(pipeline)
+            .build(data)
+            .unwrap();
+        assert_eq!(dataset.target_column, "vib_in_2m");
+        assert_eq!(dataset.dropped_rows, 2);
+        assert_eq!(dataset.data.feature_columns(), ["vib", "vib_lag_1"]);
+        let target: Vec<f64> = dataset
+            .data
+            .dataframe()
+            .column("vib_in_2m")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(target, (2..10).map(f64::from).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_windows_stay_within_entities() {
+        let data = Fixture::every(Duration::from_secs(60), 12)
+            .with_column("unit", ["a", "b"].repeat(6))
+            .with_column("temp", (0..12).map(f64::from).collect::<Vec<_>>())
+            .with_column("flow", (0..12).map(|i| i * 10).collect::<Vec<i64>>())
+            .with_group_columns(&["unit"])
+            .build()
+            .unwrap();
+
+        // Each unit has 6 rows: samples start at rows 0 and 2 of the unit
+        let windows = data.to_supervised(2, 1, 2).unwrap();
+        assert_eq!(windows.len(), 4);
+        let column = |df: &DataFrame, name: &str| -> Vec<f64> {
+            let values = df.column(name).unwrap();
+            values.f64().unwrap().into_no_null_iter().collect()
+        };
+        assert_eq!(column(&windows.x, "temp_t-1"), [0.0, 4.0, 1.0, 5.0]);
+        assert_eq!(column(&windows.x, "temp_t"), [2.0, 6.0, 3.0, 7.0]);
+        assert_eq!(column(&windows.y, "temp_t+1"), [4.0, 8.0, 5.0, 9.0]);
+
+        let x = windows.x_tensor().unwrap();
+        assert_eq!(x.shape, [4, 2, 2]);
+        assert_eq!(&x.values[..4], [0.0, 0.0, 2.0, 20.0]);
+        assert_eq!(windows.y_tensor().unwrap().shape, [4, 1, 2]);
+        assert!(data.to_supervised(2, 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_walk_forward_folds() {
+        let data = Fixture::every(Duration::from_secs(60), 12)
+            .with_column("value", (0..12).map(f64::from).collect::<Vec<_>>())
+            .build()
+            .unwrap();
+
+        let folds = TimeSeriesSplit::new(3).split_indices(&data).unwrap();
+        let ranges: Vec<_> = folds
+            .iter()
+            .map(|f| (f.train.clone(), f.test.clone()))
+            .collect();
+        assert_eq!(ranges, [(0..3, 3..6), (0..6, 6..9), (0..9, 9..12)]);
+
+        let folds = TimeSeriesSplit::new(2)
+            .with_test_size(Duration::from_secs(180))
+            .with_gap(Duration::from_secs(120))
+            .with_window(SplitWindow::Sliding(Duration::from_secs(240)))
+            .split(&data)
+            .unwrap();
+        let (train, test) = &folds[1];
+        let values = |data: &TimeSeriesData| -> Vec<f64> {
+            let column = data.dataframe().column("value").unwrap();
+            column.f64().unwrap().into_no_null_iter().collect()
+        };
+        assert_eq!(values(train), [3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(values(test), [9.0, 10.0, 11.0]);
+        assert!(TimeSeriesSplit::new(20).split_indices(&data).is_err());
+    }
+}
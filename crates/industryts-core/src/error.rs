@@ -23,6 +23,22 @@ pub enum IndustrytsError {
     #[error("Operation error: {0}")]
     OperationError(String),
 
+    #[error("Validation failed: {0}")]
+    ValidationError(String),
+
+    #[error("Execution cancelled")]
+    Cancelled,
+
+    #[error("step {step} ({operation}) panicked: {message}")]
+    OperationPanicked {
+        step: usize,
+        operation: String,
+        message: String,
+    },
+
+    #[error("Resource limit exceeded: {0}")]
+    LimitExceeded(String),
+
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
@@ -32,6 +48,9 @@ pub enum IndustrytsError {
     #[error("TOML parsing error: {0}")]
     TomlError(#[from] toml::de::Error),
 
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -0,0 +1,325 @@
+//! Versioned feature dataset export
+//!
+//! `FeatureStoreExporter` writes pipeline outputs as an immutable, versioned
+//! dataset: each export creates `<root>/<dataset>/v<N>/` holding Arrow IPC
+//! partitions and a `manifest.json` describing the features, the steps that
+//! generated them and the lineage of the run (input/output fingerprints, pipeline
+//! configuration and seed, parent version).
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use chrono::DateTime;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Origin of columns passed through from the pipeline input
+pub const INPUT_ORIGIN: &str = "input";
+
+/// How rows are split into partition files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionPeriod {
+    /// One file for the whole dataset
+    None,
+    /// One file per UTC day
+    #[default]
+    Day,
+    /// One file per UTC month
+    Month,
+}
+
+impl PartitionPeriod {
+    fn key(self, utc_ms: i64) -> String {
+        let format = match self {
+            PartitionPeriod::None => return "all".to_string(),
+            PartitionPeriod::Day => "%Y-%m-%d",
+            PartitionPeriod::Month => "%Y-%m",
+        };
+        DateTime::from_timestamp_millis(utc_ms)
+            .map(|t| t.format(format).to_string())
+            .unwrap_or_else(|| "invalid".to_string())
+    }
+}
+
+/// Description of one exported column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureInfo {
+    pub name: String,
+    pub dtype: String,
+    /// Step that added the column, or "input" for passed-through input columns
+    pub origin: String,
+    pub fingerprint: String,
+}
+
+/// One partition file of a version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionInfo {
+    /// File name relative to the version directory
+    pub path: String,
+    pub rows: usize,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Provenance of an export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub input_fingerprint: String,
+    pub output_fingerprint: String,
+    pub operations: Vec<String>,
+    /// Pipeline configuration, when the pipeline was loaded from one
+    pub pipeline_config: Option<serde_json::Value>,
+    pub seed: Option<u64>,
+    /// Latest version of the dataset before this export
+    pub parent_version: Option<u32>,
+}
+
+/// Manifest of one dataset version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureManifest {
+    pub dataset: String,
+    pub version: u32,
+    /// Export time in UTC milliseconds
+    pub created_ms: i64,
+    pub time_column: String,
+    pub rows: usize,
+    pub features: Vec<FeatureInfo>,
+    pub partitions: Vec<PartitionInfo>,
+    pub lineage: Lineage,
+}
+
+/// Writes pipeline outputs as versioned feature datasets
+pub struct FeatureStoreExporter {
+    dir: PathBuf,
+    dataset: String,
+    partition: PartitionPeriod,
+}
+
+impl FeatureStoreExporter {
+    /// Exporter for `dataset` under the store directory `root`
+    pub fn new<P: Into<PathBuf>>(root: P, dataset: &str) -> Self {
+        Self {
+            dir: root.into().join(dataset),
+            dataset: dataset.to_string(),
+            partition: PartitionPeriod::Day,
+        }
+    }
+
+    /// Partition period of new versions (default daily)
+    pub fn with_partition(mut self, partition: PartitionPeriod) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    fn version_dir(&self, version: u32) -> PathBuf {
+        self.dir.join(format!("v{}", version))
+    }
+
+    /// Existing versions, oldest first
+    pub fn versions(&self) -> Result<Vec<u32>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut versions = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(version) = name.strip_prefix('v').and_then(|v| v.parse().ok())
+                && entry.path().join(MANIFEST_FILE).exists()
+            {
+                versions.push(version);
+            }
+        }
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    /// Run `pipeline` on `input` and export the result as a new version
+    pub fn export(&self, pipeline: &Pipeline, input: &TimeSeriesData) -> Result<FeatureManifest> {
+        let output = pipeline.process(input.clone())?;
+        self.export_output(pipeline, input, &output)
+    }
+
+    /// Export an output already produced by `pipeline` from `input`
+    pub fn export_output(
+        &self,
+        pipeline: &Pipeline,
+        input: &TimeSeriesData,
+        output: &TimeSeriesData,
+    ) -> Result<FeatureManifest> {
+        let parent_version = self.versions()?.last().copied();
+        let version = parent_version.map_or(1, |v| v + 1);
+        let dir = self.version_dir(version);
+        std::fs::create_dir_all(&dir)?;
+
+        let origins: BTreeMap<String, String> = pipeline
+            .column_origins(input)?
+            .into_iter()
+            .map(|(column, origin)| (column, origin.unwrap_or_else(|| INPUT_ORIGIN.into())))
+            .collect();
+        let fingerprints = output.column_fingerprints()?;
+        let features = output
+            .feature_columns()
+            .iter()
+            .map(|name| {
+                Ok(FeatureInfo {
+                    name: name.clone(),
+                    dtype: output.dataframe().column(name)?.dtype().to_string(),
+                    origin: origins
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(|| "undeclared".to_string()),
+                    fingerprint: fingerprints[name].clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let partitions = self.write_partitions(output, &dir)?;
+        let created_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        let manifest = FeatureManifest {
+            dataset: self.dataset.clone(),
+            version,
+            created_ms,
+            time_column: output.time_column().to_string(),
+            rows: output.len(),
+            features,
+            partitions,
+            lineage: Lineage {
+                input_fingerprint: input.fingerprint()?,
+                output_fingerprint: output.fingerprint()?,
+                operations: pipeline.operation_names(),
+                pipeline_config: pipeline.config().map(serde_json::to_value).transpose()?,
+                seed: pipeline.seed(),
+                parent_version,
+            },
+        };
+        let file = File::create(dir.join(MANIFEST_FILE))?;
+        serde_json::to_writer_pretty(file, &manifest)?;
+        Ok(manifest)
+    }
+
+    fn write_partitions(&self, data: &TimeSeriesData, dir: &Path) -> Result<Vec<PartitionInfo>> {
+        let mut rows: BTreeMap<String, (Vec<IdxSize>, i64, i64)> = BTreeMap::new();
+        for (i, t) in data.timestamps_ms()?.into_iter().enumerate() {
+            let Some(t) = t else {
+                return Err(IndustrytsError::ValidationError(
+                    "Cannot partition rows with null timestamps".to_string(),
+                ));
+            };
+            let entry =
+                rows.entry(self.partition.key(t))
+                    .or_insert((Vec::new(), i64::MAX, i64::MIN));
+            entry.0.push(i as IdxSize);
+            entry.1 = entry.1.min(t);
+            entry.2 = entry.2.max(t);
+        }
+
+        let mut partitions = Vec::with_capacity(rows.len());
+        for (key, (indices, start_ms, end_ms)) in rows {
+            let mut part = data
+                .dataframe()
+                .take(&IdxCa::from_vec("idx".into(), indices))?;
+            let path = format!("part-{}.arrow", key);
+            IpcWriter::new(&mut File::create(dir.join(&path))?).finish(&mut part)?;
+            partitions.push(PartitionInfo {
+                path,
+                rows: part.height(),
+                start_ms,
+                end_ms,
+            });
+        }
+        Ok(partitions)
+    }
+
+    /// Read the manifest of `version`
+    pub fn manifest(&self, version: u32) -> Result<FeatureManifest> {
+        let file = File::open(self.version_dir(version).join(MANIFEST_FILE))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Load all partitions of `version`
+    pub fn load(&self, version: u32) -> Result<TimeSeriesData> {
+        let manifest = self.manifest(version)?;
+        let dir = self.version_dir(version);
+        let mut df: Option<DataFrame> = None;
+        for partition in &manifest.partitions {
+            let part = IpcReader::new(File::open(dir.join(&partition.path))?).finish()?;
+            match &mut df {
+                Some(df) => {
+                    df.vstack_mut(&part)?;
+                }
+                None => df = Some(part),
+            }
+        }
+        let df = df.ok_or_else(|| {
+            IndustrytsError::ValidationError(format!(
+                "Version {} of {} has no partitions",
+                version, self.dataset
+            ))
+        })?;
+        TimeSeriesData::new(df, Some(&manifest.time_column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::LagOperation;
+
+    fn make_data() -> TimeSeriesData {
+        // Two days of hourly samples
+        let times: Vec<i64> = (0..48).map(|i| 1704067200000i64 + i * 3_600_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("flow".into(), (0..48).map(|i| i as f64).collect::<Vec<_>>()).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_export_versions_with_lineage() {
+        let root = std::env::temp_dir().join("industryts_feature_store_test");
+        let _ = std::fs::remove_dir_all(&root);
+        let exporter = FeatureStoreExporter::new(&root, "pump_features");
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        let data = make_data();
+
+        let first = exporter.export(&pipeline, &data).unwrap();
+        assert_eq!(first.version, 1);
+        assert_eq!(first.partitions.len(), 2);
+        assert_eq!(first.partitions[0].path, "part-2024-01-01.arrow");
+        let origins: Vec<(&str, &str)> = first
+            .features
+            .iter()
+            .map(|f| (f.name.as_str(), f.origin.as_str()))
+            .collect();
+        assert_eq!(origins, vec![("flow", INPUT_ORIGIN), ("flow_lag_1", "lag")]);
+
+        let second = exporter.export(&pipeline, &data).unwrap();
+        assert_eq!(second.version, 2);
+        assert_eq!(second.lineage.parent_version, Some(1));
+        assert_eq!(exporter.versions().unwrap(), vec![1, 2]);
+
+        assert_eq!(exporter.manifest(1).unwrap(), first);
+        let loaded = exporter.load(2).unwrap();
+        assert_eq!(
+            loaded.fingerprint().unwrap(),
+            second.lineage.output_fingerprint
+        );
+    }
+}
@@ -0,0 +1,762 @@
+//! Reading and writing time series files
+//!
+//! `TimeSeriesData::read_csv` and `read_ipc` load a file and build the time
+//! series in one step. The time column is parsed according to `ReadOptions`: strings with chrono format strings,
+//! integers with an epoch unit, and wall-clock times of a time zone converted to
+//! UTC. Group columns are set from the options. The matching `write_*` methods
+//! write the data back, time and group columns included.
+//!
+//! Long-format files (one row per timestamp, tag and value, as exported by many
+//! historians) are read with `ReadOptions::with_long_format`: rows of tags outside
+//! the `LongFormat` allow/deny lists are filtered out in the scan, and the
+//! remaining tags are pivoted into one column each.
+//!
+//! Exports of European plants write numbers such as `1.234,5` and dates such as
+//! `03/04/2024` meaning 3 April. `ReadOptions::with_decimal`, `with_thousands` and
+//! `with_day_first` read them without preprocessing: CSV columns are then read as
+//! text and converted with the locale's `NumberFormat`.
+//!
+//! `SourceConfig` and `SinkConfig` describe such files in the `[source]` and
+//! `[sink]` sections of a pipeline configuration, run with `Pipeline::run`.
+
+use crate::core::{ColumnSelector, NanPolicy, StagedFile, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::temporal::parse::{
+    DEFAULT_FORMATS, formats_with_date_order, parse_timestamp_series,
+};
+use crate::operations::temporal::timezone::{local_to_utc, parse_time_zone};
+use crate::utils::NumberFormat;
+use chrono::DateTime;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// File format of a source or sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Csv,
+    /// Arrow IPC (Feather v2)
+    Ipc,
+}
+
+impl FileFormat {
+    /// Format implied by the extension of `path`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("csv") => Ok(Self::Csv),
+            Some("arrow" | "ipc" | "feather") => Ok(Self::Ipc),
+            _ => Err(IndustrytsError::ConfigError(format!(
+                "Cannot infer the file format of {}; set `format`",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Unit of integer epoch timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EpochUnit {
+    #[serde(rename = "s")]
+    Seconds,
+    #[serde(rename = "ms")]
+    Milliseconds,
+    #[serde(rename = "us")]
+    Microseconds,
+    #[serde(rename = "ns")]
+    Nanoseconds,
+}
+
+impl EpochUnit {
+    fn to_millis(self, value: i64) -> i64 {
+        match self {
+            Self::Seconds => value * 1000,
+            Self::Milliseconds => value,
+            Self::Microseconds => value.div_euclid(1000),
+            Self::Nanoseconds => value.div_euclid(1_000_000),
+        }
+    }
+}
+
+/// Layout and tag selection of long-format (timestamp, tag, value) files
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LongFormat {
+    /// Column naming the tag of each row (default "tag")
+    #[serde(default = "default_tag_column")]
+    pub tag_column: String,
+    /// Column holding the values (default "value")
+    #[serde(default = "default_value_column")]
+    pub value_column: String,
+    /// Tags kept, with wildcards or regexes as in column selectors (default all)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include: Option<ColumnSelector>,
+    /// Tags dropped even when included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<ColumnSelector>,
+}
+
+fn default_tag_column() -> String {
+    "tag".to_string()
+}
+
+fn default_value_column() -> String {
+    "value".to_string()
+}
+
+impl Default for LongFormat {
+    fn default() -> Self {
+        Self {
+            tag_column: default_tag_column(),
+            value_column: default_value_column(),
+            include: None,
+            exclude: None,
+        }
+    }
+}
+
+impl LongFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names of the tag and value columns
+    pub fn with_columns(mut self, tag_column: &str, value_column: &str) -> Self {
+        self.tag_column = tag_column.to_string();
+        self.value_column = value_column.to_string();
+        self
+    }
+
+    pub fn with_include(mut self, selector: impl Into<ColumnSelector>) -> Self {
+        self.include = Some(selector.into());
+        self
+    }
+
+    pub fn with_exclude(mut self, selector: impl Into<ColumnSelector>) -> Self {
+        self.exclude = Some(selector.into());
+        self
+    }
+
+    /// Mask of the rows whose tag is selected
+    fn select(&self, tags: &Column) -> Result<BooleanChunked> {
+        let tags = tags.cast(&DataType::String)?;
+        let tags = tags.str()?;
+        let unique: Vec<String> = tags
+            .unique()?
+            .into_iter()
+            .flatten()
+            .map(str::to_string)
+            .collect();
+        let mut selected: HashSet<String> = match &self.include {
+            Some(include) => include.resolve_names(&unique)?.into_iter().collect(),
+            None => unique.iter().cloned().collect(),
+        };
+        if let Some(exclude) = &self.exclude {
+            for tag in exclude.resolve_names(&unique)? {
+                selected.remove(&tag);
+            }
+        }
+        Ok(tags
+            .into_iter()
+            .map(|tag| tag.is_some_and(|tag| selected.contains(tag)))
+            .collect())
+    }
+
+    /// Keep the rows of selected tags and pivot each tag into a column
+    ///
+    /// Rows are keyed by the time column and `keys`, in order of first appearance;
+    /// tag columns are sorted by name. The last value wins for duplicate rows.
+    fn pivot(
+        &self,
+        lf: LazyFrame,
+        time_column: Option<&str>,
+        keys: &[String],
+    ) -> Result<DataFrame> {
+        // Check the selectors before scanning
+        self.select(&Column::new_empty("tag".into(), &DataType::String))?;
+
+        let format = self.clone();
+        let mask = col(self.tag_column.as_str()).map(
+            move |tags| {
+                format
+                    .select(&tags)
+                    .map(IntoColumn::into_column)
+                    .map_err(|e| PolarsError::ComputeError(e.to_string().into()))
+            },
+            |_, field| Ok(Field::new(field.name().clone(), DataType::Boolean)),
+        );
+        let df = lf.filter(mask).collect()?;
+
+        let time_col = match time_column {
+            Some(column) => column.to_string(),
+            None => TimeSeriesData::detect_time_column(&df)?,
+        };
+        let tags = df.column(&self.tag_column)?.cast(&DataType::String)?;
+        let mut names: Vec<String> = tags
+            .str()?
+            .unique()?
+            .into_iter()
+            .flatten()
+            .map(str::to_string)
+            .collect();
+        names.sort();
+
+        let mut by = vec![col(time_col.as_str())];
+        by.extend(keys.iter().map(|key| col(key.as_str())));
+        let tag = col(self.tag_column.as_str()).cast(DataType::String);
+        let columns: Vec<Expr> = names
+            .iter()
+            .map(|name| {
+                col(self.value_column.as_str())
+                    .filter(tag.clone().eq(lit(name.as_str())))
+                    .last()
+                    .alias(name.as_str())
+            })
+            .collect();
+        Ok(df.lazy().group_by_stable(by).agg(columns).collect()?)
+    }
+}
+
+/// How a file is turned into `TimeSeriesData`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ReadOptions {
+    /// Time column (detected by name if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_column: Option<String>,
+    /// Formats tried on string timestamps (see `operations::temporal::parse`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formats: Option<Vec<String>>,
+    /// Unit of integer timestamps (seconds or milliseconds are guessed if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_unit: Option<EpochUnit>,
+    /// Time zone of naive wall-clock timestamps, converted to UTC (e.g. "Europe/Berlin")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Entity key columns of interleaved series
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_columns: Vec<String>,
+    /// Field separator of CSV files (default ',')
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<char>,
+    /// Read a long-format file, pivoting the selected tags into columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_format: Option<LongFormat>,
+    /// Treatment of NaN in floating-point columns read (see `NanPolicy`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nan_policy: Option<NanPolicy>,
+    /// Decimal separator of numbers read as text (default '.'), e.g. ',' for `12,5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimal: Option<char>,
+    /// Thousands separator of numbers read as text, e.g. '.' for `1.234,5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thousands: Option<char>,
+    /// Read slash and dash dates day first (`true`) or month first (`false`)
+    /// when no formats are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day_first: Option<bool>,
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_time_column(mut self, column: &str) -> Self {
+        self.time_column = Some(column.to_string());
+        self
+    }
+
+    pub fn with_formats(mut self, formats: Vec<String>) -> Self {
+        self.formats = Some(formats);
+        self
+    }
+
+    pub fn with_epoch_unit(mut self, unit: EpochUnit) -> Self {
+        self.epoch_unit = Some(unit);
+        self
+    }
+
+    pub fn with_time_zone(mut self, zone: &str) -> Self {
+        self.time_zone = Some(zone.to_string());
+        self
+    }
+
+    pub fn with_group_columns(mut self, columns: Vec<String>) -> Self {
+        self.group_columns = columns;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+
+    pub fn with_long_format(mut self, long_format: LongFormat) -> Self {
+        self.long_format = Some(long_format);
+        self
+    }
+
+    /// Apply `policy` on read, e.g. `NanPolicy::Null` to load NaN as missing values
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.nan_policy = Some(policy);
+        self
+    }
+
+    pub fn with_decimal(mut self, decimal: char) -> Self {
+        self.decimal = Some(decimal);
+        self
+    }
+
+    pub fn with_thousands(mut self, thousands: char) -> Self {
+        self.thousands = Some(thousands);
+        self
+    }
+
+    pub fn with_day_first(mut self, day_first: bool) -> Self {
+        self.day_first = Some(day_first);
+        self
+    }
+
+    fn separator(&self) -> Result<u8> {
+        let separator = self.separator.unwrap_or(',');
+        u8::try_from(separator).map_err(|_| {
+            IndustrytsError::ConfigError(format!(
+                "CSV separator must be a single-byte character, got '{}'",
+                separator
+            ))
+        })
+    }
+
+    /// Locale of CSV numbers, if other than plain `1234.5`
+    fn number_format(&self) -> Result<Option<NumberFormat>> {
+        if self.decimal.is_none() && self.thousands.is_none() {
+            return Ok(None);
+        }
+        let format = NumberFormat {
+            decimal: self.decimal.unwrap_or('.'),
+            thousands: self.thousands,
+        };
+        let separator = self.separator.unwrap_or(',');
+        if format.thousands == Some(format.decimal)
+            || [Some(format.decimal), format.thousands].contains(&Some(separator))
+        {
+            return Err(IndustrytsError::ConfigError(format!(
+                "CSV separator '{}', decimal '{}' and thousands separator must differ",
+                separator, format.decimal
+            )));
+        }
+        Ok(Some(format))
+    }
+
+    /// CSV reader options, reading every column as text for localized numbers
+    fn csv_options(&self) -> Result<CsvReadOptions> {
+        let parse_options = CsvParseOptions::default()
+            .with_separator(self.separator()?)
+            .with_try_parse_dates(false);
+        let options = CsvReadOptions::default()
+            .with_has_header(true)
+            .with_parse_options(parse_options);
+        Ok(match self.number_format()? {
+            Some(_) => options.with_infer_schema_length(Some(0)),
+            None => options,
+        })
+    }
+
+    /// Convert the text columns whose values are all numbers in the locale
+    ///
+    /// Columns of whole numbers become `Int64`, other numeric columns `Float64`.
+    fn parse_numbers(&self, mut df: DataFrame) -> Result<DataFrame> {
+        let Some(format) = self.number_format()? else {
+            return Ok(df);
+        };
+        let names: Vec<PlSmallStr> = df
+            .get_columns()
+            .iter()
+            .filter(|c| c.dtype() == &DataType::String)
+            .map(|c| c.name().clone())
+            .collect();
+        for name in names {
+            let column = df.column(&name)?.str()?.clone();
+            let mut values = Vec::with_capacity(column.len());
+            let mut whole = true;
+            let numeric = column.into_iter().all(|value| {
+                let Some(value) = value.filter(|v| !v.trim().is_empty()) else {
+                    values.push(None);
+                    return true;
+                };
+                let parsed = format.parse(value);
+                whole &= !value.contains(format.decimal)
+                    && parsed.is_some_and(|v| v.is_finite() && v.abs() < 9.0e15);
+                values.push(parsed);
+                parsed.is_some()
+            });
+            if !numeric || column.null_count() == column.len() {
+                continue;
+            }
+            let series = Series::new(name.clone(), values);
+            let series = if whole {
+                series.cast(&DataType::Int64)?
+            } else {
+                series
+            };
+            df.replace(&name, series)?;
+        }
+        Ok(df)
+    }
+
+    /// Build the time series from a scanned long-format file
+    fn apply_long(&self, lf: LazyFrame, long_format: &LongFormat) -> Result<TimeSeriesData> {
+        let df = long_format.pivot(lf, self.time_column.as_deref(), &self.group_columns)?;
+        let mut options = self.clone();
+        options.time_column = Some(df.get_column_names()[0].to_string());
+        options.apply(df)
+    }
+
+    /// Build the time series from a freshly read frame
+    fn apply(&self, df: DataFrame) -> Result<TimeSeriesData> {
+        let mut df = self.parse_numbers(df)?;
+        let time_col = match &self.time_column {
+            Some(column) => column.clone(),
+            None => TimeSeriesData::detect_time_column(&df)?,
+        };
+        let series = df
+            .column(&time_col)
+            .map_err(|_| IndustrytsError::TimeColumnNotFound(time_col.clone()))?
+            .as_materialized_series()
+            .clone();
+        let formats: Vec<String> = match (&self.formats, self.day_first) {
+            (Some(formats), _) => formats.clone(),
+            (None, Some(day_first)) => formats_with_date_order(day_first),
+            (None, None) => DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect(),
+        };
+
+        let parsed = match (series.dtype(), self.epoch_unit) {
+            (DataType::String, _) => parse_timestamp_series(&series, &formats, true)?,
+            (dtype, Some(unit)) if dtype.is_integer() => {
+                let millis: Vec<Option<i64>> = series
+                    .cast(&DataType::Int64)?
+                    .i64()?
+                    .into_iter()
+                    .map(|v| v.map(|v| unit.to_millis(v)))
+                    .collect();
+                Series::new(series.name().clone(), millis)
+                    .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            }
+            (dtype, None) if dtype.is_integer() => parse_timestamp_series(&series, &formats, true)?,
+            _ => series,
+        };
+        let parsed = match &self.time_zone {
+            Some(zone) => to_utc(&parsed, zone)?,
+            None => parsed,
+        };
+        df.replace(&time_col, parsed)?;
+
+        let mut data = TimeSeriesData::new(df, Some(&time_col))?;
+        if let Some(zone) = &self.time_zone {
+            data.set_time_zone(zone)?;
+        }
+        let data = if self.group_columns.is_empty() {
+            data
+        } else {
+            data.with_group_columns(&self.group_columns)?
+        };
+        data.with_nan_policy(self.nan_policy.unwrap_or_default())
+    }
+}
+
+/// Reinterpret naive timestamps as wall-clock times of `zone`, as UTC
+///
+/// Columns that already carry a time zone are returned unchanged.
+fn to_utc(series: &Series, zone: &str) -> Result<Series> {
+    if !matches!(series.dtype(), DataType::Datetime(_, None) | DataType::Date) {
+        return Ok(series.clone());
+    }
+    let tz = parse_time_zone(zone)?;
+    let millis: Vec<Option<i64>> = series
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+        .cast(&DataType::Int64)?
+        .i64()?
+        .into_iter()
+        .map(|v| {
+            v.and_then(DateTime::from_timestamp_millis)
+                .map(|dt| local_to_utc(&tz, dt.naive_utc()))
+        })
+        .collect();
+    Ok(Series::new(series.name().clone(), millis)
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?)
+}
+
+impl TimeSeriesData {
+    /// Read a CSV file
+    ///
+    /// Timestamps are left as strings by the CSV reader and parsed with the
+    /// formats of `options`.
+    pub fn read_csv<P: AsRef<Path>>(path: P, options: &ReadOptions) -> Result<Self> {
+        if let Some(long_format) = &options.long_format {
+            let mut reader = LazyCsvReader::new(PlPath::Local(Arc::from(path.as_ref())))
+                .with_has_header(true)
+                .with_separator(options.separator()?)
+                .with_try_parse_dates(false);
+            if options.number_format()?.is_some() {
+                reader = reader.with_infer_schema_length(Some(0));
+            }
+            let lf = reader.finish()?;
+            return options.apply_long(lf, long_format);
+        }
+        let df = options
+            .csv_options()?
+            .try_into_reader_with_file_path(Some(path.as_ref().to_path_buf()))?
+            .finish()?;
+        options.apply(df)
+    }
+
+    /// Read CSV text, e.g. a fixture embedded in a test
+    pub fn read_csv_str(text: &str, options: &ReadOptions) -> Result<Self> {
+        let df = options
+            .csv_options()?
+            .into_reader_with_file_handle(std::io::Cursor::new(text.as_bytes()))
+            .finish()?;
+        match &options.long_format {
+            Some(long_format) => options.apply_long(df.lazy(), long_format),
+            None => options.apply(df),
+        }
+    }
+
+    /// Read an Arrow IPC file
+    pub fn read_ipc<P: AsRef<Path>>(path: P, options: &ReadOptions) -> Result<Self> {
+        if let Some(long_format) = &options.long_format {
+            let lf = LazyFrame::scan_ipc(
+                PlPath::Local(Arc::from(path.as_ref())),
+                ScanArgsIpc::default(),
+            )?;
+            return options.apply_long(lf, long_format);
+        }
+        let df = IpcReader::new(File::open(path)?).finish()?;
+        options.apply(df)
+    }
+
+    /// Read a file of `format`
+    pub fn read_file<P: AsRef<Path>>(
+        path: P,
+        format: FileFormat,
+        options: &ReadOptions,
+    ) -> Result<Self> {
+        match format {
+            FileFormat::Csv => Self::read_csv(path, options),
+            FileFormat::Ipc => Self::read_ipc(path, options),
+        }
+    }
+
+    /// Write a CSV file, timestamps in ISO 8601
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut df = self.dataframe().clone();
+        let mut file = File::create(path)?;
+        CsvWriter::new(&mut file).finish(&mut df)?;
+        Ok(())
+    }
+
+    /// Write an Arrow IPC file
+    pub fn write_ipc<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut df = self.dataframe().clone();
+        let mut file = File::create(path)?;
+        IpcWriter::new(&mut file).finish(&mut df)?;
+        Ok(())
+    }
+
+    /// Write a file of `format`
+    pub fn write_file<P: AsRef<Path>>(&self, path: P, format: FileFormat) -> Result<()> {
+        match format {
+            FileFormat::Csv => self.write_csv(path),
+            FileFormat::Ipc => self.write_ipc(path),
+        }
+    }
+}
+
+/// File read as the input of a configured pipeline (`[source]`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SourceConfig {
+    pub path: String,
+    /// File format (inferred from the extension if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<FileFormat>,
+    #[serde(flatten)]
+    pub options: ReadOptions,
+}
+
+impl SourceConfig {
+    /// Read the source, using `time_column` unless the source names its own
+    pub fn read(&self, time_column: Option<&str>) -> Result<TimeSeriesData> {
+        let path = Path::new(&self.path);
+        let format = match self.format {
+            Some(format) => format,
+            None => FileFormat::from_path(path)?,
+        };
+        let mut options = self.options.clone();
+        if options.time_column.is_none() {
+            options.time_column = time_column.map(str::to_string);
+        }
+        TimeSeriesData::read_file(path, format, &options)
+    }
+}
+
+/// File written with the output of a configured pipeline (`[sink]`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SinkConfig {
+    pub path: String,
+    /// File format (inferred from the extension if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<FileFormat>,
+}
+
+impl SinkConfig {
+    pub fn write(&self, data: &TimeSeriesData) -> Result<()> {
+        let path = Path::new(&self.path);
+        let format = match self.format {
+            Some(format) => format,
+            None => FileFormat::from_path(path)?,
+        };
+        data.write_file(path, format)
+    }
+
+    /// Write the file under a temporary name, renamed into place on commit
+    pub fn stage(&self, data: &TimeSeriesData) -> Result<StagedFile> {
+        let path = Path::new(&self.path);
+        let format = match self.format {
+            Some(format) => format,
+            None => FileFormat::from_path(path)?,
+        };
+        StagedFile::create(path, |temp| data.write_file(temp, format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_csv_round_trip_with_time_zone_and_groups() {
+        let dir = std::env::temp_dir().join(format!("industryts_io_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.csv");
+        fs::write(
+            &input,
+            "ts;asset;value\n\
+             01.07.2024 02:00:00;a;1.5\n\
+             01.07.2024 02:00:00;b;2.5\n\
+             01.07.2024 02:01:00;a;\n",
+        )
+        .unwrap();
+
+        let options = ReadOptions::new()
+            .with_time_column("ts")
+            .with_formats(vec!["%d.%m.%Y %H:%M:%S".to_string()])
+            .with_time_zone("Europe/Berlin")
+            .with_group_columns(vec!["asset".to_string()])
+            .with_separator(';');
+        let data = TimeSeriesData::read_csv(&input, &options).unwrap();
+        assert_eq!(data.group_columns(), ["asset"]);
+        assert_eq!(data.feature_columns(), ["value"]);
+        // 02:00 CEST is midnight UTC
+        let first = data.timestamps_ms().unwrap().get(0).unwrap();
+        assert_eq!(first, 1719792000000);
+
+        let output = dir.join("output.csv");
+        data.write_csv(&output).unwrap();
+        let options = ReadOptions::new().with_group_columns(vec!["asset".to_string()]);
+        let back = TimeSeriesData::read_csv(&output, &options).unwrap();
+        assert_eq!(back.time_column(), "ts");
+        assert!(back.dataframe().equals_missing(data.dataframe()));
+
+        let ipc = dir.join("output.arrow");
+        let format = FileFormat::from_path(&ipc).unwrap();
+        data.write_file(&ipc, format).unwrap();
+        let back = TimeSeriesData::read_file(&ipc, format, &options.with_time_column("ts"));
+        assert!(back.unwrap().dataframe().equals_missing(data.dataframe()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_european_number_and_date_formats() {
+        let text = "Zeit;Anlage;Durchfluss;Zähler\n\
+                    03/04/2024 10:00;7;1.234,5;1.000\n\
+                    03/04/2024 10:01;7;-0,25;1.001\n\
+                    03/04/2024 10:02;7;;1.002\n";
+        let options = ReadOptions::new()
+            .with_time_column("Zeit")
+            .with_separator(';')
+            .with_decimal(',')
+            .with_thousands('.')
+            .with_day_first(true);
+        let data = TimeSeriesData::read_csv_str(text, &options).unwrap();
+
+        // 3 April, not 4 March
+        assert_eq!(data.timestamps_ms().unwrap().get(0), Some(1712138400000));
+        let df = data.dataframe();
+        let flow: Vec<Option<f64>> = df
+            .column("Durchfluss")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(flow, [Some(1234.5), Some(-0.25), None]);
+        assert_eq!(
+            df.column("Zähler").unwrap().i64().unwrap().get(2),
+            Some(1002)
+        );
+        assert_eq!(df.column("Anlage").unwrap().dtype(), &DataType::Int64);
+
+        let month_first = options.clone().with_day_first(false);
+        let data = TimeSeriesData::read_csv_str(text, &month_first).unwrap();
+        assert_eq!(data.timestamps_ms().unwrap().get(0), Some(1709546400000));
+        assert!(TimeSeriesData::read_csv_str(text, &options.with_separator(',')).is_err());
+    }
+
+    #[test]
+    fn test_long_format_tag_filtering() {
+        let dir = std::env::temp_dir().join(format!("industryts_long_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("export.csv");
+        fs::write(
+            &input,
+            "timestamp,tag,value\n\
+             2024-01-01 00:00:00,45TI1234.PV,80.5\n\
+             2024-01-01 00:00:00,45TI1235.PV,70.0\n\
+             2024-01-01 00:00:00,45FI0001.PV,12.0\n\
+             2024-01-01 00:00:00,99XX0001.PV,1.0\n\
+             2024-01-01 00:01:00,45TI1234.PV,81.0\n\
+             2024-01-01 00:01:00,45FI0001.PV,13.0\n",
+        )
+        .unwrap();
+
+        let long_format = LongFormat::new()
+            .with_include(vec!["45TI*", "45FI0001.PV"])
+            .with_exclude(vec!["45TI1235.PV"]);
+        let options = ReadOptions::new().with_long_format(long_format);
+        let data = TimeSeriesData::read_csv(&input, &options).unwrap();
+
+        assert_eq!(data.time_column(), "timestamp");
+        assert_eq!(data.feature_columns(), ["45FI0001.PV", "45TI1234.PV"]);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.timestamps_ms().unwrap().get(1), Some(1704067260000));
+        let flow = data.dataframe().column("45FI0001.PV").unwrap();
+        assert_eq!(flow.f64().unwrap().get(1), Some(13.0));
+
+        let ipc = dir.join("export.arrow");
+        let long = TimeSeriesData::read_csv(&input, &ReadOptions::new()).unwrap();
+        long.write_ipc(&ipc).unwrap();
+        let options = ReadOptions::new()
+            .with_long_format(LongFormat::new().with_include(vec!["99XX0001.PV"]));
+        let data = TimeSeriesData::read_ipc(&ipc, &options).unwrap();
+        assert_eq!(data.feature_columns(), ["99XX0001.PV"]);
+        assert_eq!(data.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
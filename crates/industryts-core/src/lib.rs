@@ -2,17 +2,28 @@
 //!
 //! High-performance time series processing library powered by Polars.
 
+pub mod align;
+pub mod analysis;
 pub mod config;
 pub mod core;
+pub mod dataset;
+pub mod datasets;
 pub mod error;
+pub mod feature_store;
+pub mod io;
 pub mod operations;
 pub mod pipeline;
+pub mod random;
+pub mod rollup;
+pub mod snapshot;
+pub mod synthetic;
+pub mod testing;
 pub mod timeseries;
 pub mod utils;
 
 // Re-export main types from core
 pub use core::{ExecutionContext, Operation, TimeSeriesData};
-pub use config::PipelineConfig;
+pub use config::{ConfigFormat, PipelineConfig};
 pub use error::{IndustrytsError, Result};
 pub use pipeline::Pipeline;
 
@@ -0,0 +1,355 @@
+//! EWMA control chart
+//!
+//! The exponentially weighted moving average `z = lambda * x + (1 - lambda) * z`,
+//! started at the in-control target, is compared with the time-varying limits
+//!
+//! ```text
+//! target ± L * sigma * sqrt(lambda / (2 - lambda) * (1 - (1 - lambda)^(2i)))
+//! ```
+//!
+//! where `i` counts the samples seen. Small `lambda` (0.05 - 0.25) makes the chart
+//! sensitive to small sustained shifts; `lambda = 0.2, L = 3` is a common choice.
+//!
+//! In chunked runs the statistic continues across chunks; self-tuned references
+//! are then learned from the first chunk.
+//!
+//! A self-tuned reference needs two samples; with fewer (e.g. an empty or
+//! single-row chunk) the statistic is null and no alarm is raised.
+
+use crate::core::{OpContext, Operation, StatefulOperation, StepState, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// In-control mean and standard deviation of a monitored column
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ControlReference {
+    pub target: f64,
+    pub sigma: f64,
+}
+
+/// Position of the chart of a column, carried across chunks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct ChartState {
+    reference: ControlReference,
+    z: f64,
+    /// `(1 - lambda)^(2i)` after `i` samples
+    decay: f64,
+}
+
+/// EWMA operation - flag samples whose smoothed value leaves the control limits
+///
+/// For each column appends `<column>_ewma` (the smoothed statistic) and
+/// `<column>_ewma_alarm` (1 above the upper limit, -1 below the lower limit,
+/// 0 otherwise). Nulls leave the statistic unchanged and are never flagged.
+///
+/// Columns without a known reference are self-tuned from the mean and standard
+/// deviation of their first `reference_window` samples (default: all).
+pub struct EwmaOperation {
+    lambda: f64,
+    width: f64,
+    columns: Option<Vec<String>>,
+    reference: Option<ControlReference>,
+    column_references: BTreeMap<String, ControlReference>,
+    reference_window: Option<usize>,
+}
+
+impl EwmaOperation {
+    /// Chart with smoothing weight `lambda` in (0, 1] and limits `width` sigmas wide
+    pub fn new(lambda: f64, width: f64, columns: Option<Vec<String>>) -> Result<Self> {
+        if !(lambda > 0.0 && lambda <= 1.0) {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "EWMA lambda must be in (0, 1], got {}",
+                lambda
+            )));
+        }
+        if !(width.is_finite() && width > 0.0) {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "EWMA limit width must be positive, got {}",
+                width
+            )));
+        }
+        Ok(Self {
+            lambda,
+            width,
+            columns,
+            reference: None,
+            column_references: BTreeMap::new(),
+            reference_window: None,
+        })
+    }
+
+    /// Use a known in-control mean and standard deviation for all columns
+    pub fn with_target(mut self, target: f64, sigma: f64) -> Self {
+        self.reference = Some(ControlReference { target, sigma });
+        self
+    }
+
+    /// Use a known in-control mean and standard deviation for `column`
+    pub fn with_column_reference(mut self, column: &str, reference: ControlReference) -> Self {
+        self.column_references.insert(column.to_string(), reference);
+        self
+    }
+
+    /// Self-tune references from the first `rows` non-null samples
+    pub fn with_reference_window(mut self, rows: usize) -> Self {
+        self.reference_window = Some(rows);
+        self
+    }
+
+    /// Reference of `column`, or `None` if it is self-tuned from fewer than two samples
+    fn reference_for(
+        &self,
+        column: &str,
+        values: &[Option<f64>],
+    ) -> Result<Option<ControlReference>> {
+        let reference = match self
+            .column_references
+            .get(column)
+            .or(self.reference.as_ref())
+        {
+            Some(reference) => *reference,
+            None => {
+                let window: Vec<f64> = values
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .take(self.reference_window.unwrap_or(usize::MAX))
+                    .collect();
+                if window.len() < 2 {
+                    return Ok(None);
+                }
+                let n = window.len() as f64;
+                let target = window.iter().sum::<f64>() / n;
+                let var = window.iter().map(|v| (v - target).powi(2)).sum::<f64>() / (n - 1.0);
+                ControlReference {
+                    target,
+                    sigma: var.sqrt(),
+                }
+            }
+        };
+        if !(reference.sigma.is_finite() && reference.sigma > 0.0) {
+            return Err(IndustrytsError::OperationError(format!(
+                "EWMA chart needs a positive sigma for column: {}",
+                column
+            )));
+        }
+        Ok(Some(reference))
+    }
+
+    /// Run the chart from the positions in `charts`, which are advanced; columns
+    /// without one start at their reference. Returns the augmented data and the
+    /// number of alarms.
+    fn run(
+        &self,
+        data: TimeSeriesData,
+        charts: &mut BTreeMap<String, ChartState>,
+    ) -> Result<(TimeSeriesData, usize)> {
+        let columns = if let Some(cols) = &self.columns {
+            cols.clone()
+        } else {
+            data.feature_columns().to_vec()
+        };
+
+        let mut df = data.dataframe().clone();
+        let mut alarms = 0;
+        // Asymptotic variance factor of the statistic
+        let factor = self.lambda / (2.0 - self.lambda);
+        for col_name in &columns {
+            let values: Vec<Option<f64>> = df
+                .column(col_name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .collect();
+            let ChartState {
+                reference,
+                mut z,
+                mut decay,
+            } = match charts.get(col_name) {
+                Some(chart) => *chart,
+                None => match self.reference_for(col_name, &values)? {
+                    Some(reference) => ChartState {
+                        reference,
+                        z: reference.target,
+                        decay: 1.0,
+                    },
+                    None => {
+                        df.with_column(Series::new(
+                            format!("{}_ewma", col_name).into(),
+                            vec![None::<f64>; values.len()],
+                        ))?;
+                        df.with_column(Series::new(
+                            format!("{}_ewma_alarm", col_name).into(),
+                            vec![0i32; values.len()],
+                        ))?;
+                        continue;
+                    }
+                },
+            };
+
+            let mut statistic = Vec::with_capacity(values.len());
+            let mut alarm = vec![0i32; values.len()];
+            for (i, value) in values.iter().enumerate() {
+                if let Some(x) = value {
+                    z = self.lambda * x + (1.0 - self.lambda) * z;
+                    decay *= (1.0 - self.lambda).powi(2);
+                    let limit = self.width * reference.sigma * (factor * (1.0 - decay)).sqrt();
+                    if z - reference.target > limit {
+                        alarm[i] = 1;
+                    } else if reference.target - z > limit {
+                        alarm[i] = -1;
+                    }
+                }
+                statistic.push(value.map(|_| z));
+            }
+            charts.insert(
+                col_name.clone(),
+                ChartState {
+                    reference,
+                    z,
+                    decay,
+                },
+            );
+            alarms += alarm.iter().filter(|&&a| a != 0).count();
+
+            df.with_column(Series::new(format!("{}_ewma", col_name).into(), statistic))?;
+            df.with_column(Series::new(
+                format!("{}_ewma_alarm", col_name).into(),
+                alarm,
+            ))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok((result, alarms))
+    }
+}
+
+/// Chart positions of all columns, kept between chunks
+struct EwmaState<'a> {
+    operation: &'a EwmaOperation,
+    charts: BTreeMap<String, ChartState>,
+}
+
+impl StatefulOperation for EwmaState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.operation
+            .run(chunk, &mut self.charts)
+            .map(|(result, _)| result)
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(StepState {
+            values: serde_json::to_value(&self.charts)?,
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        self.charts = serde_json::from_value(state.values)?;
+        Ok(())
+    }
+}
+
+impl Operation for EwmaOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, &mut BTreeMap::new())
+            .map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, alarms) = self.run(data, &mut BTreeMap::new())?;
+        ctx.record_metric("ewma.alarms", alarms as f64);
+        if alarms > 0 {
+            ctx.warn(format!("EWMA chart flagged {} sample(s)", alarms));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "ewma"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        columns
+            .iter()
+            .flat_map(|c| [format!("{}_ewma", c), format!("{}_ewma_alarm", c)])
+            .collect()
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        Some(Box::new(EwmaState {
+            operation: self,
+            charts: BTreeMap::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ewma_flags_small_shift() {
+        // In control around 50, then a one-sigma upward shift on the first tag only
+        let noise = |i: usize| [0.5, -0.5, 1.0, -1.0, 0.0][i % 5];
+        let flow: Vec<f64> = (0..80)
+            .map(|i| 50.0 + noise(i) + if i < 40 { 0.0 } else { 0.7 })
+            .collect();
+        let level: Vec<f64> = (0..80).map(|i| 2.0 + noise(i) / 10.0).collect();
+        let times: Vec<i64> = (0..80).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("flow".into(), flow).into(),
+            Series::new("level".into(), level).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let sigma = (2.5f64 / 4.0).sqrt();
+        let op = EwmaOperation::new(0.2, 3.0, None)
+            .unwrap()
+            .with_column_reference(
+                "flow",
+                ControlReference {
+                    target: 50.0,
+                    sigma,
+                },
+            )
+            .with_reference_window(40);
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        let alarms: Vec<i32> = df
+            .column("flow_ewma_alarm")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let first = alarms.iter().position(|&a| a != 0).unwrap();
+        assert!((40..60).contains(&first));
+        assert_eq!(alarms[first], 1);
+        let level_alarms = df.column("level_ewma_alarm").unwrap().i32().unwrap();
+        assert_eq!(
+            level_alarms.into_no_null_iter().filter(|&a| a != 0).count(),
+            0
+        );
+        assert!(ctx.take_diagnostics().1["ewma.alarms"] >= 1.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! Flatline (stuck-sensor) detection
+//!
+//! A live process signal always shows some noise. A transmitter that freezes, a
+//! historian that repeats its last good value or a saturated input instead
+//! reports the same value for a long time. Runs of values staying within a
+//! tolerance band for at least a window of time are flagged as flatlines.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::data_quality::flags::mark_flag;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Flatline operation - flag samples where a sensor appears stuck
+///
+/// For each column appends `<column>_flatline`, true for every sample of a run
+/// whose values stay within `tolerance` (max minus min) for at least `window`.
+/// Nulls neither extend nor break a run and are never flagged.
+pub struct FlatlineOperation {
+    window: Duration,
+    tolerance: f64,
+    columns: Option<Vec<String>>,
+    column_tolerances: BTreeMap<String, f64>,
+}
+
+impl FlatlineOperation {
+    /// Flag runs of identical values lasting at least `window`
+    pub fn new(window: Duration, columns: Option<Vec<String>>) -> Self {
+        Self {
+            window,
+            tolerance: 0.0,
+            columns,
+            column_tolerances: BTreeMap::new(),
+        }
+    }
+
+    /// Treat values within `tolerance` of each other as unchanged
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Use `tolerance` for `column` instead of the common tolerance
+    pub fn with_column_tolerance(mut self, column: &str, tolerance: f64) -> Self {
+        self.column_tolerances.insert(column.to_string(), tolerance);
+        self
+    }
+
+    /// Run the detector; returns the augmented data and the number of flagged samples
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        let columns = if let Some(cols) = &self.columns {
+            cols.clone()
+        } else {
+            data.feature_columns().to_vec()
+        };
+        let times = data.timestamps_ms()?;
+        let window = self.window.as_millis() as i64;
+
+        let mut df = data.dataframe().clone();
+        let mut flagged = 0;
+        for col_name in &columns {
+            let tolerance = self
+                .column_tolerances
+                .get(col_name)
+                .copied()
+                .unwrap_or(self.tolerance);
+            let (rows, samples): (Vec<usize>, Vec<(i64, f64)>) = df
+                .column(col_name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, value)| Some((i, (times.get(i)?, value?))))
+                .unzip();
+
+            let mut flags = vec![false; df.height()];
+            let stuck = flatline_flags(&samples, window, tolerance);
+            for (i, stuck) in rows.into_iter().zip(stuck) {
+                if stuck {
+                    flags[i] = true;
+                    flagged += 1;
+                }
+            }
+
+            df.with_column(Series::new(format!("{}_flatline", col_name).into(), flags))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        for col_name in &columns {
+            mark_flag(&mut result, &format!("{}_flatline", col_name), col_name);
+        }
+        Ok((result, flagged))
+    }
+}
+
+/// Flag the `(time ms, value)` samples in runs that stay within `tolerance` for
+/// at least `window` ms
+pub(crate) fn flatline_flags(samples: &[(i64, f64)], window: i64, tolerance: f64) -> Vec<bool> {
+    let mut flags = vec![false; samples.len()];
+    let mut start = 0;
+    while start < samples.len() {
+        let (mut low, mut high) = (samples[start].1, samples[start].1);
+        let mut end = start + 1;
+        while end < samples.len() {
+            let value = samples[end].1;
+            if value.max(high) - value.min(low) > tolerance {
+                break;
+            }
+            low = low.min(value);
+            high = high.max(value);
+            end += 1;
+        }
+        if end - start > 1 && samples[end - 1].0 - samples[start].0 >= window {
+            flags[start..end].fill(true);
+        }
+        start = end;
+    }
+    flags
+}
+
+impl Operation for FlatlineOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, flagged) = self.run(data)?;
+        ctx.record_metric("flatline.flagged", flagged as f64);
+        if flagged > 0 {
+            ctx.warn(format!("{} sample(s) in flatline runs", flagged));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "flatline"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        columns.iter().map(|c| format!("{}_flatline", c)).collect()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatline_runs() {
+        let times: Vec<i64> = (0..10).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            // Stuck at 5.0 for five minutes (a null inside), then live again
+            Series::new(
+                "pressure".into(),
+                [
+                    Some(4.0),
+                    Some(5.0),
+                    Some(5.0),
+                    None,
+                    Some(5.0),
+                    Some(5.0),
+                    Some(5.0),
+                    Some(6.0),
+                    Some(6.0),
+                    Some(7.0),
+                ],
+            )
+            .into(),
+            // Noise within 0.05 is stuck under the column tolerance
+            Series::new(
+                "temp".into(),
+                [
+                    80.0, 80.02, 79.98, 80.01, 80.0, 80.03, 79.99, 80.0, 80.02, 80.01,
+                ],
+            )
+            .into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = FlatlineOperation::new(Duration::from_secs(300), None)
+            .with_column_tolerance("temp", 0.05);
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        let pressure: Vec<bool> = df
+            .column("pressure_flatline")
+            .unwrap()
+            .bool()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(
+            pressure,
+            vec![
+                false, true, true, false, true, true, true, false, false, false
+            ]
+        );
+        let temp = df.column("temp_flatline").unwrap().bool().unwrap();
+        assert_eq!(temp.sum(), Some(10));
+        assert_eq!(ctx.take_diagnostics().1["flatline.flagged"], 15.0);
+    }
+}
@@ -0,0 +1,20 @@
+//! Anomaly detection operations
+//!
+//! This module provides detectors flagging samples for operators; each adds flag
+//! (and score) columns per monitored column:
+//! - ewma: EWMA control charts
+//! - rate_of_change: limits on how fast a value may change
+//! - flatline: stuck-sensor detection
+//! - quantile_band: rolling quantile bands as adaptive alarm limits
+//!
+//! CUSUM control charts are provided by `monitoring::cusum`.
+
+pub mod ewma;
+pub mod flatline;
+pub mod quantile_band;
+pub mod rate_of_change;
+
+pub use ewma::{ControlReference, EwmaOperation};
+pub use flatline::FlatlineOperation;
+pub use quantile_band::QuantileBandOperation;
+pub use rate_of_change::RateOfChangeOperation;
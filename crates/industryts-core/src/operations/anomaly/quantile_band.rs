@@ -0,0 +1,254 @@
+//! Rolling quantile bands
+//!
+//! Fixed alarm limits are set once and rarely revisited: too tight and they
+//! chatter after every change of operating point, too loose and they miss real
+//! excursions. Quantiles of the recent past (say the 1% and 99% quantiles of the
+//! last 7 days) follow the process and flag what is unusual for its current
+//! state.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::outlier::quantile;
+use crate::operations::group::map_partitions;
+use polars::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Default number of past samples needed before a band is drawn
+pub const DEFAULT_MIN_PERIODS: usize = 10;
+
+/// Quantile band operation - flag samples outside rolling quantile bands
+///
+/// For each column appends `<column>_band_lower` and `<column>_band_upper`, the
+/// `lower` and `upper` quantiles of the non-null samples in the preceding
+/// `window` (excluding the sample itself), and `<column>_band_alarm` (1 above,
+/// -1 below the band, 0 otherwise). Bands are null until `min_periods` samples
+/// are available. Groups are banded separately; data must be sorted by time.
+pub struct QuantileBandOperation {
+    window: Duration,
+    lower: f64,
+    upper: f64,
+    min_periods: usize,
+    columns: Option<Vec<String>>,
+}
+
+impl QuantileBandOperation {
+    /// Band `columns`, or all feature columns, between the `lower` and `upper`
+    /// quantiles of the past `window`
+    pub fn new(
+        window: Duration,
+        lower: f64,
+        upper: f64,
+        columns: Option<Vec<String>>,
+    ) -> Result<Self> {
+        if !(0.0..=1.0).contains(&lower) || !(0.0..=1.0).contains(&upper) || lower >= upper {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Quantile band needs 0 <= lower < upper <= 1, got {} and {}",
+                lower, upper
+            )));
+        }
+        if window.is_zero() {
+            return Err(IndustrytsError::ConfigError(
+                "Quantile band window must be positive".to_string(),
+            ));
+        }
+        Ok(Self {
+            window,
+            lower,
+            upper,
+            min_periods: DEFAULT_MIN_PERIODS,
+            columns,
+        })
+    }
+
+    /// Draw bands once `min_periods` (at least 1) past samples are available
+    pub fn with_min_periods(mut self, min_periods: usize) -> Self {
+        self.min_periods = min_periods.max(1);
+        self
+    }
+
+    fn monitored(&self, feature_columns: &[String]) -> Vec<String> {
+        match &self.columns {
+            Some(columns) => columns.clone(),
+            None => feature_columns.to_vec(),
+        }
+    }
+
+    /// Lower band, upper band and alarm of each sample
+    fn bands(
+        &self,
+        times: &[Option<i64>],
+        values: &[Option<f64>],
+    ) -> (Vec<Option<f64>>, Vec<Option<f64>>, Vec<i32>) {
+        let window = self.window.as_millis() as i64;
+        let mut lower = vec![None; values.len()];
+        let mut upper = vec![None; values.len()];
+        let mut alarm = vec![0i32; values.len()];
+        let mut recent: VecDeque<(i64, f64)> = VecDeque::new();
+        let mut sorted: Vec<f64> = Vec::new();
+
+        for (i, (t, value)) in times.iter().zip(values).enumerate() {
+            let Some(t) = *t else { continue };
+            while let Some(&(t_old, x_old)) = recent.front() {
+                if t - t_old < window {
+                    break;
+                }
+                recent.pop_front();
+                let index = sorted.partition_point(|v| *v < x_old);
+                sorted.remove(index);
+            }
+            if sorted.len() >= self.min_periods {
+                let (lo, hi) = (quantile(&sorted, self.lower), quantile(&sorted, self.upper));
+                lower[i] = Some(lo);
+                upper[i] = Some(hi);
+                alarm[i] = match value {
+                    Some(x) if *x > hi => 1,
+                    Some(x) if *x < lo => -1,
+                    _ => 0,
+                };
+            }
+            if let Some(x) = value.filter(|x| !x.is_nan()) {
+                recent.push_back((t, x));
+                let index = sorted.partition_point(|v| *v < x);
+                sorted.insert(index, x);
+            }
+        }
+        (lower, upper, alarm)
+    }
+
+    /// Run the detector; returns the augmented data and the number of alarms
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        let columns = self.monitored(data.feature_columns());
+        let time_col = data.time_column();
+
+        let df = map_partitions(data.dataframe(), data.group_columns(), |mut partition| {
+            let times: Vec<Option<i64>> = TimeSeriesData::new(partition.clone(), Some(time_col))?
+                .timestamps_ms()?
+                .into_iter()
+                .collect();
+            for col_name in &columns {
+                let values: Vec<Option<f64>> = partition
+                    .column(col_name)?
+                    .cast(&DataType::Float64)?
+                    .f64()?
+                    .into_iter()
+                    .collect();
+                let (lower, upper, alarm) = self.bands(&times, &values);
+                partition.with_column(Series::new(
+                    format!("{}_band_lower", col_name).into(),
+                    lower,
+                ))?;
+                partition.with_column(Series::new(
+                    format!("{}_band_upper", col_name).into(),
+                    upper,
+                ))?;
+                partition.with_column(Series::new(
+                    format!("{}_band_alarm", col_name).into(),
+                    alarm,
+                ))?;
+            }
+            Ok(partition)
+        })?;
+
+        let mut alarms = 0;
+        for col_name in &columns {
+            let alarm = df.column(&format!("{}_band_alarm", col_name))?;
+            alarms += alarm.i32()?.into_no_null_iter().filter(|&a| a != 0).count();
+        }
+
+        let mut metadata = data.metadata().clone();
+        for added in self.added_columns(&columns) {
+            if !metadata.feature_columns.contains(&added) {
+                metadata.feature_columns.push(added);
+            }
+        }
+        Ok((TimeSeriesData::with_metadata(df, metadata)?, alarms))
+    }
+}
+
+impl Operation for QuantileBandOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, alarms) = self.run(data)?;
+        ctx.record_metric("quantile_band.alarms", alarms as f64);
+        if alarms > 0 {
+            ctx.warn(format!(
+                "{} sample(s) fell outside their rolling quantile band",
+                alarms
+            ));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "quantile_band"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        self.monitored(feature_columns)
+            .iter()
+            .flat_map(|c| {
+                [
+                    format!("{}_band_lower", c),
+                    format!("{}_band_upper", c),
+                    format!("{}_band_alarm", c),
+                ]
+            })
+            .collect()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_band_follows_level_and_flags_excursions() {
+        // Ten samples around 1.0, a spike, then a step to 5.0
+        let values = [
+            1.0, 1.1, 0.9, 1.0, 1.2, 0.8, 1.0, 1.1, 0.9, 1.0, 9.0, 5.0, 5.0, 5.0, 5.0, 5.0,
+        ];
+        let data = Fixture::every(Duration::from_secs(60), values.len())
+            .with_column("flow", values)
+            .build()
+            .unwrap();
+
+        let op = QuantileBandOperation::new(Duration::from_secs(5 * 60), 0.0, 1.0, None)
+            .unwrap()
+            .with_min_periods(3);
+        assert!(QuantileBandOperation::new(Duration::from_secs(60), 0.9, 0.1, None).is_err());
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        let alarm: Vec<i32> = df
+            .column("flow_band_alarm")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        // The spike alarms; it widens the band enough that the step to 5.0 does not
+        assert_eq!(alarm, vec![0, 0, 0, 0, 1, -1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]);
+        let upper = df.column("flow_band_upper").unwrap().f64().unwrap();
+        assert_eq!(upper.get(2), None);
+        assert_eq!(upper.get(10), Some(1.1));
+        assert_eq!(ctx.take_diagnostics().1["quantile_band.alarms"], 3.0);
+    }
+}
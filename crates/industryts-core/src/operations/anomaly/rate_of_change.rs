@@ -0,0 +1,210 @@
+//! Rate-of-change limits
+//!
+//! Physical processes cannot change arbitrarily fast: a tank level does not drop
+//! 2 m in a second and a furnace does not heat by 300 °C in a minute. A change
+//! faster than the process allows points to a spike, a sensor fault or a
+//! transmission error rather than a real event.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::flags::mark_flag;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Rate-of-change operation - flag samples changing faster than a limit
+///
+/// For each column appends `<column>_roc`, the change since the previous
+/// non-null sample per `per` of elapsed time (per sample without `per`), and
+/// `<column>_roc_alarm` (1 for a rise, -1 for a fall beyond the limit, 0
+/// otherwise). Samples sharing the previous sample's timestamp get no rate.
+pub struct RateOfChangeOperation {
+    max_rate: Option<f64>,
+    per: Option<Duration>,
+    columns: Option<Vec<String>>,
+    column_limits: BTreeMap<String, f64>,
+}
+
+impl RateOfChangeOperation {
+    /// Limit the absolute rate of `columns`, or of all feature columns
+    pub fn new(max_rate: Option<f64>, per: Option<Duration>, columns: Option<Vec<String>>) -> Self {
+        Self {
+            max_rate,
+            per,
+            columns,
+            column_limits: BTreeMap::new(),
+        }
+    }
+
+    /// Limit `column` to `max_rate` instead of the common limit
+    pub fn with_column_limit(mut self, column: &str, max_rate: f64) -> Self {
+        self.column_limits.insert(column.to_string(), max_rate);
+        self
+    }
+
+    /// Monitored columns: the selection, else all features with a common limit,
+    /// else the columns with their own limit
+    fn monitored(&self, feature_columns: &[String]) -> Vec<String> {
+        match (&self.columns, self.max_rate) {
+            (Some(columns), _) => columns.clone(),
+            (None, Some(_)) => feature_columns.to_vec(),
+            (None, None) => self.column_limits.keys().cloned().collect(),
+        }
+    }
+
+    /// Run the detector; returns the augmented data and the number of alarms
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        let times = data.timestamps_ms()?;
+        let per_ms = self.per.map(|per| per.as_millis() as f64);
+        let mut df = data.dataframe().clone();
+        let mut alarms = 0;
+        let columns = self.monitored(data.feature_columns());
+        for col_name in columns.clone() {
+            let limit = self
+                .column_limits
+                .get(&col_name)
+                .copied()
+                .or(self.max_rate)
+                .ok_or_else(|| {
+                    IndustrytsError::InvalidOperation(format!(
+                        "No rate-of-change limit for column: {}",
+                        col_name
+                    ))
+                })?;
+            let values: Vec<Option<f64>> = df
+                .column(&col_name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .collect();
+
+            let mut rates: Vec<Option<f64>> = vec![None; values.len()];
+            let mut alarm = vec![0i32; values.len()];
+            let mut previous: Option<(Option<i64>, f64)> = None;
+            for (i, value) in values.iter().enumerate() {
+                let Some(x) = *value else { continue };
+                let t = times.get(i);
+                if let Some((t_prev, x_prev)) = previous {
+                    let rate = match (per_ms, t, t_prev) {
+                        (None, _, _) => Some(x - x_prev),
+                        (Some(per), Some(t), Some(t_prev)) if t != t_prev => {
+                            Some((x - x_prev) / ((t - t_prev) as f64 / per))
+                        }
+                        _ => None,
+                    };
+                    if let Some(rate) = rate {
+                        if rate.abs() > limit {
+                            alarm[i] = rate.signum() as i32;
+                        }
+                        rates[i] = Some(rate);
+                    }
+                }
+                previous = Some((t, x));
+            }
+            alarms += alarm.iter().filter(|&&a| a != 0).count();
+
+            df.with_column(Series::new(format!("{}_roc", col_name).into(), rates))?;
+            df.with_column(Series::new(format!("{}_roc_alarm", col_name).into(), alarm))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        for col_name in &columns {
+            mark_flag(&mut result, &format!("{}_roc_alarm", col_name), col_name);
+        }
+        Ok((result, alarms))
+    }
+}
+
+impl Operation for RateOfChangeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, alarms) = self.run(data)?;
+        ctx.record_metric("rate_of_change.alarms", alarms as f64);
+        if alarms > 0 {
+            ctx.warn(format!(
+                "{} sample(s) exceeded the rate-of-change limit",
+                alarms
+            ));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "rate_of_change"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        match &self.columns {
+            Some(columns) => columns.clone(),
+            None => self.column_limits.keys().cloned().collect(),
+        }
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        self.monitored(feature_columns)
+            .iter()
+            .flat_map(|c| [format!("{}_roc", c), format!("{}_roc_alarm", c)])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limits_per_column() {
+        // Irregular sampling: 0, 1, 2, 4, 5 minutes
+        let times: Vec<i64> = [0, 1, 2, 4, 5].iter().map(|m| m * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new(
+                "level".into(),
+                [Some(1.0), Some(1.5), None, Some(2.5), Some(0.0)],
+            )
+            .into(),
+            Series::new("temp".into(), [20.0, 21.0, 22.0, 30.0, 31.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = RateOfChangeOperation::new(Some(3.0), Some(Duration::from_secs(60)), None)
+            .with_column_limit("level", 1.0);
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        // 1.0 over three minutes is within the limit; the 2.5 drop in a minute is not
+        let level_rate = df.column("level_roc").unwrap().f64().unwrap();
+        assert!((level_rate.get(3).unwrap() - 1.0 / 3.0).abs() < 1e-12);
+        let level: Vec<i32> = df
+            .column("level_roc_alarm")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(level, vec![0, 0, 0, 0, -1]);
+        // 8 degrees in two minutes exceeds the common limit of 3 per minute
+        let temp: Vec<i32> = df
+            .column("temp_roc_alarm")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(temp, vec![0, 0, 0, 1, 0]);
+        assert_eq!(ctx.take_diagnostics().1["rate_of_change.alarms"], 2.0);
+    }
+}
@@ -0,0 +1,442 @@
+//! Masking of plant data for sharing
+//!
+//! `AnonymizeOperation` masks a dataset before it leaves the plant (vendor
+//! analyses, demos): columns are renamed to neutral aliases (`signal_001`, ...),
+//! every timestamp is shifted by one constant offset and numeric values are
+//! scaled and offset per column. The parameters form an `AnonymizationKey`, kept
+//! apart from the shared data (e.g. as a JSON file), with which
+//! `AnonymizationKey::reveal` restores the original names, timestamps and values.
+//!
+//! Tags and column properties are dropped, since they may name the site or the
+//! raw tags. Masked numeric columns are `Float64`. Optional per-row timestamp
+//! jitter also hides the exact sampling instants; it is not part of the key and
+//! cannot be reversed.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::random::SeedSequence;
+use polars::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Masking of one column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaskedColumn {
+    pub column: String,
+    pub alias: String,
+    /// Masked value is `value * scale + offset` (numeric columns only)
+    pub scale: f64,
+    pub offset: f64,
+}
+
+/// Parameters of a reversible masking
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AnonymizationKey {
+    /// Offset added to every timestamp
+    pub time_shift_ms: i64,
+    pub columns: Vec<MaskedColumn>,
+}
+
+impl AnonymizationKey {
+    /// Random key masking `columns` of `data`
+    ///
+    /// Aliases are `{alias_prefix}_001`, ... in shuffled order. Scales lie in
+    /// [0.5, 2) and offsets within the largest magnitude of the column. The time
+    /// shift is at most `max_time_shift` either way, in whole days (keeping the
+    /// time of day) unless `max_time_shift` is shorter than a day.
+    pub fn generate(
+        data: &TimeSeriesData,
+        columns: &[String],
+        alias_prefix: &str,
+        max_time_shift: Duration,
+        seed: u64,
+    ) -> Result<Self> {
+        let mut rng = SeedSequence::new(seed).rng("anonymization_key");
+        let df = data.dataframe();
+
+        let max_ms = max_time_shift.as_millis() as i64;
+        let is_date = matches!(df.column(data.time_column())?.dtype(), DataType::Date);
+        let time_shift_ms = if max_ms >= MS_PER_DAY || is_date {
+            let days = max_ms / MS_PER_DAY;
+            rng.random_range(-days..=days) * MS_PER_DAY
+        } else {
+            rng.random_range(-max_ms..=max_ms)
+        };
+
+        let mut numbers: Vec<usize> = (1..=columns.len()).collect();
+        numbers.shuffle(&mut rng);
+        let width = columns.len().to_string().len().max(3);
+
+        let mut masked = Vec::with_capacity(columns.len());
+        for (column, number) in columns.iter().zip(numbers) {
+            if column == data.time_column() || data.group_columns().contains(column) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "cannot mask '{}': only feature columns are anonymized",
+                    column
+                )));
+            }
+            let series = df.column(column)?;
+            let (scale, offset) = if series.dtype().is_primitive_numeric() {
+                let values = series.cast(&DataType::Float64)?;
+                let magnitude = values
+                    .f64()?
+                    .into_iter()
+                    .flatten()
+                    .fold(0.0_f64, |m, v| m.max(v.abs()));
+                let magnitude = if magnitude > 0.0 { magnitude } else { 1.0 };
+                (
+                    rng.random_range(0.5..2.0),
+                    rng.random_range(-1.0..1.0) * magnitude,
+                )
+            } else {
+                (1.0, 0.0)
+            };
+            masked.push(MaskedColumn {
+                column: column.clone(),
+                alias: format!("{}_{:0width$}", alias_prefix, number, width = width),
+                scale,
+                offset,
+            });
+        }
+        Ok(Self {
+            time_shift_ms,
+            columns: masked,
+        })
+    }
+
+    pub fn from_json_file(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    pub fn to_json_file(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// Mask `data`
+    pub fn mask(&self, data: &TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        for masked in &self.columns {
+            let series = df.column(&masked.column)?.as_materialized_series().clone();
+            if series.dtype().is_primitive_numeric() {
+                let values = series.cast(&DataType::Float64)?;
+                df.replace(&masked.column, &(&values * masked.scale) + masked.offset)?;
+            }
+            df.rename(&masked.column, masked.alias.as_str().into())?;
+        }
+        shift_time(&mut df, data.time_column(), self.time_shift_ms)?;
+        TimeSeriesData::new(df, Some(data.time_column()))?.with_group_columns(data.group_columns())
+    }
+
+    /// Restore the names, timestamps and values of data masked with this key
+    pub fn reveal(&self, data: &TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        for masked in &self.columns {
+            df.rename(&masked.alias, masked.column.as_str().into())?;
+            let series = df.column(&masked.column)?.as_materialized_series().clone();
+            if series.dtype().is_primitive_numeric() {
+                let values = series.cast(&DataType::Float64)?;
+                df.replace(&masked.column, &(&values - masked.offset) / masked.scale)?;
+            }
+        }
+        shift_time(&mut df, data.time_column(), -self.time_shift_ms)?;
+        TimeSeriesData::new(df, Some(data.time_column()))?.with_group_columns(data.group_columns())
+    }
+}
+
+/// Add `offsets_ms(row)` to the time column (whole days for date columns)
+fn offset_time(
+    df: &mut DataFrame,
+    time_col: &str,
+    offsets_ms: impl Fn(usize) -> i64,
+) -> Result<()> {
+    let series = df.column(time_col)?.as_materialized_series().clone();
+    let dtype = series.dtype().clone();
+    let per_ms = match &dtype {
+        DataType::Datetime(TimeUnit::Nanoseconds, _) => 1_000_000,
+        DataType::Datetime(TimeUnit::Microseconds, _) => 1_000,
+        DataType::Datetime(TimeUnit::Milliseconds, _) => 1,
+        DataType::Date => 0,
+        other => {
+            return Err(IndustrytsError::InvalidTimeColumnType(format!(
+                "cannot shift time column '{}' of type {}",
+                time_col, other
+            )));
+        }
+    };
+    let physical = series.to_physical_repr().cast(&DataType::Int64)?;
+    let shifted: Int64Chunked = physical
+        .i64()?
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let offset = offsets_ms(i);
+            t.map(|t| match per_ms {
+                0 => t + offset.div_euclid(MS_PER_DAY),
+                _ => t + offset * per_ms,
+            })
+        })
+        .collect();
+    let shifted = shifted
+        .into_series()
+        .with_name(time_col.into())
+        .cast(&dtype)?;
+    df.replace(time_col, shifted)?;
+    Ok(())
+}
+
+fn shift_time(df: &mut DataFrame, time_col: &str, shift_ms: i64) -> Result<()> {
+    offset_time(df, time_col, |_| shift_ms)
+}
+
+/// Anonymize operation - mask names, timestamps and values with a key
+///
+/// The key is given, loaded from a key file, or generated from the first data the
+/// operation sees (and then written to the key file, if any). Generated keys use
+/// the step seed of a seeded pipeline, otherwise system entropy. Columns outside
+/// the key pass through unchanged.
+pub struct AnonymizeOperation {
+    key: Mutex<Option<AnonymizationKey>>,
+    key_file: Option<PathBuf>,
+    columns: Option<Vec<String>>,
+    alias_prefix: String,
+    max_time_shift: Duration,
+    jitter: Option<Duration>,
+    seed: Option<u64>,
+}
+
+impl AnonymizeOperation {
+    /// Mask with `key`
+    pub fn new(key: AnonymizationKey) -> Self {
+        Self::with_key(Some(key), None)
+    }
+
+    /// Mask with the key in `path`, generating and saving it there if missing
+    pub fn from_key_file(path: &Path) -> Result<Self> {
+        let key = if path.exists() {
+            Some(AnonymizationKey::from_json_file(path)?)
+        } else {
+            None
+        };
+        Ok(Self::with_key(key, Some(path.to_path_buf())))
+    }
+
+    fn with_key(key: Option<AnonymizationKey>, key_file: Option<PathBuf>) -> Self {
+        Self {
+            key: Mutex::new(key),
+            key_file,
+            columns: None,
+            alias_prefix: "signal".to_string(),
+            max_time_shift: Duration::from_secs(365 * 86_400),
+            jitter: None,
+            seed: None,
+        }
+    }
+
+    /// Columns masked by a generated key (default: all feature columns)
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Prefix of the aliases of a generated key (default "signal")
+    pub fn with_alias_prefix(mut self, prefix: &str) -> Self {
+        self.alias_prefix = prefix.to_string();
+        self
+    }
+
+    /// Largest time shift of a generated key (default 365 days)
+    pub fn with_max_time_shift(mut self, max_time_shift: Duration) -> Self {
+        self.max_time_shift = max_time_shift;
+        self
+    }
+
+    /// Move each timestamp by a random amount of at most `jitter` (irreversible)
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Key in use, once given, loaded or generated
+    pub fn key(&self) -> Option<AnonymizationKey> {
+        self.key.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn key_for(&self, data: &TimeSeriesData) -> Result<AnonymizationKey> {
+        let mut key = self.key.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(key) = key.as_ref() {
+            return Ok(key.clone());
+        }
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data.feature_columns().to_vec(),
+        };
+        let generated = AnonymizationKey::generate(
+            data,
+            &columns,
+            &self.alias_prefix,
+            self.max_time_shift,
+            self.seed.unwrap_or_else(rand::random),
+        )?;
+        if let Some(path) = &self.key_file {
+            generated.to_json_file(path)?;
+        }
+        Ok(key.insert(generated).clone())
+    }
+}
+
+impl Operation for AnonymizeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let masked = self.key_for(&data)?.mask(&data)?;
+        let Some(jitter) = self.jitter else {
+            return Ok(masked);
+        };
+
+        let jitter_ms = jitter.as_millis() as i64;
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut rng = SeedSequence::new(seed).rng("jitter");
+        let offsets: Vec<i64> = (0..masked.len())
+            .map(|_| rng.random_range(-jitter_ms..=jitter_ms))
+            .collect();
+        let time_col = masked.time_column().to_string();
+        let groups = masked.group_columns().to_vec();
+        let mut df = masked.into_dataframe();
+        offset_time(&mut df, &time_col, |i| offsets[i])?;
+        let df = df.sort(
+            [time_col.as_str()],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?;
+        TimeSeriesData::new(df, Some(&time_col))?.with_group_columns(&groups)
+    }
+
+    fn name(&self) -> &str {
+        "anonymize"
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        match self.key() {
+            Some(key) => key.columns.into_iter().map(|m| m.column).collect(),
+            None => self.columns.clone().unwrap_or_default(),
+        }
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        self.key()
+            .map(|key| {
+                key.columns
+                    .into_iter()
+                    .filter(|m| feature_columns.contains(&m.column))
+                    .map(|m| m.alias)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.jitter.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize_round_trip_through_key_file() {
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("45TI1234.PV".into(), &[80.5, 81.0, 79.5]).into(),
+            Series::new("45FI0001.PV".into(), &[12i64, 15, 11]).into(),
+            Series::new("mode".into(), &["auto", "auto", "manual"]).into(),
+        ])
+        .unwrap();
+        let mut data = TimeSeriesData::new(df, Some("time")).unwrap();
+        data.set_column_property("45TI1234.PV", "unit", "degC");
+
+        let path =
+            std::env::temp_dir().join(format!("industryts_anonymize_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut op = AnonymizeOperation::from_key_file(&path).unwrap();
+        op.set_seed(7);
+        let masked = op.execute(data.clone()).unwrap();
+
+        assert_eq!(masked.feature_columns().len(), data.feature_columns().len());
+        assert!(
+            masked
+                .feature_columns()
+                .iter()
+                .all(|c| c.starts_with("signal_"))
+        );
+        assert!(masked.metadata().tags.is_empty());
+        let key = AnonymizationKey::from_json_file(&path).unwrap();
+        assert_eq!(Some(&key), op.key().as_ref());
+        assert_eq!(key.time_shift_ms % MS_PER_DAY, 0);
+        let original = masked.timestamps_ms().unwrap();
+        assert_eq!(original.get(1), Some(60_000 + key.time_shift_ms));
+        let mode = &key
+            .columns
+            .iter()
+            .find(|m| m.column == "mode")
+            .unwrap()
+            .alias;
+        assert_eq!(
+            masked
+                .dataframe()
+                .column(mode)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(2),
+            Some("manual")
+        );
+
+        // A new operation reuses the saved key
+        let again = AnonymizeOperation::from_key_file(&path).unwrap();
+        assert!(
+            again
+                .execute(data.clone())
+                .unwrap()
+                .dataframe()
+                .equals(masked.dataframe())
+        );
+
+        let revealed = key.reveal(&masked).unwrap();
+        assert_eq!(revealed.feature_columns(), data.feature_columns());
+        assert!(
+            revealed
+                .timestamps_ms()
+                .unwrap()
+                .equal(&data.timestamps_ms().unwrap())
+                .all()
+        );
+        for column in ["45TI1234.PV", "45FI0001.PV"] {
+            let before = data.dataframe().column(column).unwrap();
+            let before = before.cast(&DataType::Float64).unwrap();
+            let after = revealed.dataframe().column(column).unwrap();
+            for (b, a) in before.f64().unwrap().into_iter().zip(after.f64().unwrap()) {
+                assert!((b.unwrap() - a.unwrap()).abs() < 1e-9);
+            }
+        }
+
+        let jittered = AnonymizeOperation::new(key)
+            .with_jitter(Duration::from_secs(10))
+            .execute(data)
+            .unwrap();
+        assert_eq!(jittered.len(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
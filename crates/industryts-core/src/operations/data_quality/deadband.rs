@@ -0,0 +1,239 @@
+//! Deadband compression of over-sampled signals
+//!
+//! Polling a slow process every second stores the same values thousands of times.
+//! `DeadbandOperation` drops a row when none of the selected columns moved by
+//! more than its tolerance since the last row kept, like the exception reporting
+//! of a historian. The first and last row of each series are always kept, and a
+//! maximum interval keeps a heartbeat row on long flat stretches so gaps can be
+//! told from steady values. The number of rows removed is recorded as the metric
+//! `deadband.removed_rows`.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Deadband operation - drop rows within tolerance of the last row kept
+///
+/// Each row of a series is compared with the last row kept of the same entity:
+/// it is dropped if every selected column changed by at most its tolerance.
+/// A value turning null or back counts as a change. Rows keep their order.
+pub struct DeadbandOperation {
+    tolerance: f64,
+    tolerances: BTreeMap<String, f64>,
+    max_interval: Option<Duration>,
+    columns: Option<Vec<String>>,
+}
+
+impl DeadbandOperation {
+    /// Compare `columns` (default: all numeric feature columns) with `tolerance`;
+    /// a tolerance of 0 drops exact repeats only
+    pub fn new(tolerance: f64, columns: Option<Vec<String>>) -> Result<Self> {
+        Self::check_tolerance("deadband", tolerance)?;
+        Ok(Self {
+            tolerance,
+            tolerances: BTreeMap::new(),
+            max_interval: None,
+            columns,
+        })
+    }
+
+    fn check_tolerance(column: &str, tolerance: f64) -> Result<()> {
+        if tolerance.is_finite() && tolerance >= 0.0 {
+            Ok(())
+        } else {
+            Err(IndustrytsError::ConfigError(format!(
+                "Tolerance of {} must be a non-negative number, got {}",
+                column, tolerance
+            )))
+        }
+    }
+
+    /// Tolerance of `column` instead of the default one
+    pub fn with_column_tolerance(mut self, column: &str, tolerance: f64) -> Result<Self> {
+        Self::check_tolerance(column, tolerance)?;
+        self.tolerances.insert(column.to_string(), tolerance);
+        Ok(self)
+    }
+
+    /// Keep a row at least this long after the last row kept
+    pub fn with_max_interval(mut self, interval: Duration) -> Self {
+        self.max_interval = Some(interval);
+        self
+    }
+
+    /// Selected columns and their tolerances
+    fn selection(&self, data: &TimeSeriesData) -> Vec<(String, f64)> {
+        let mut columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| {
+                    data.dataframe()
+                        .column(c)
+                        .is_ok_and(|c| c.dtype().is_primitive_numeric())
+                })
+                .cloned()
+                .collect(),
+        };
+        for column in self.tolerances.keys() {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        columns
+            .into_iter()
+            .map(|c| {
+                let tolerance = self.tolerances.get(&c).copied().unwrap_or(self.tolerance);
+                (c, tolerance)
+            })
+            .collect()
+    }
+
+    /// Compress `data`; returns it and the number of rows removed
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        let selection = self.selection(&data);
+        let df = data.dataframe();
+        let values = selection
+            .iter()
+            .map(|(c, _)| Ok(df.column(c)?.cast(&DataType::Float64)?.f64()?.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let times = data.timestamps_ms()?;
+        let max_interval = self.max_interval.map(|d| d.as_millis() as i64);
+
+        // Index of the last row kept and of the last row seen, per entity
+        let keys = data
+            .group_columns()
+            .iter()
+            .map(|c| Ok(df.column(c)?.cast(&DataType::String)?.str()?.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut kept_rows: HashMap<Vec<Option<&str>>, usize> = HashMap::new();
+        let mut last_rows: HashMap<Vec<Option<&str>>, usize> = HashMap::new();
+        let mut keep = vec![false; df.height()];
+        for (row, keep_row) in keep.iter_mut().enumerate() {
+            let key: Vec<Option<&str>> = keys.iter().map(|k| k.get(row)).collect();
+            last_rows.insert(key.clone(), row);
+            let changed = match kept_rows.get(&key) {
+                None => true,
+                Some(&kept) => {
+                    let timed_out = match (max_interval, times.get(kept), times.get(row)) {
+                        (Some(max), Some(from), Some(to)) => to - from >= max,
+                        _ => false,
+                    };
+                    timed_out
+                        || values.iter().zip(&selection).any(|(column, (_, tol))| {
+                            match (column.get(kept), column.get(row)) {
+                                (Some(a), Some(b)) if a.is_nan() || b.is_nan() => {
+                                    a.is_nan() != b.is_nan()
+                                }
+                                (Some(a), Some(b)) => (b - a).abs() > *tol,
+                                (a, b) => a.is_some() != b.is_some(),
+                            }
+                        })
+                }
+            };
+            if changed {
+                *keep_row = true;
+                kept_rows.insert(key, row);
+            }
+        }
+        for row in last_rows.into_values() {
+            keep[row] = true;
+        }
+
+        let mask = BooleanChunked::from_slice("keep".into(), &keep);
+        let filtered = df.filter(&mask)?;
+        let removed = df.height() - filtered.height();
+        Ok((
+            TimeSeriesData::with_metadata(filtered, data.metadata().clone())?,
+            removed,
+        ))
+    }
+}
+
+impl Operation for DeadbandOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, removed) = self.run(data)?;
+        ctx.record_metric("deadband.removed_rows", removed as f64);
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "deadband"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        for column in self.tolerances.keys() {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        columns
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_deadband_per_column_tolerance() {
+        let data = Fixture::every(Duration::from_secs(1), 8)
+            .with_column(
+                "temp",
+                [
+                    Some(20.0),
+                    Some(20.1),
+                    Some(20.2),
+                    Some(20.6),
+                    Some(20.6),
+                    None,
+                    Some(20.6),
+                    Some(20.6),
+                ],
+            )
+            .with_column("valve", [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0])
+            .build()
+            .unwrap();
+        let op = DeadbandOperation::new(0.0, None)
+            .unwrap()
+            .with_column_tolerance("temp", 0.25)
+            .unwrap();
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data.clone(), &mut ctx).unwrap();
+
+        // 20.2 is within 0.25 of 20.0, 20.6 is not; the null and the value after
+        // it are changes, and the valve opens on the last row
+        let times: Vec<i64> = result
+            .timestamps_ms()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let start = data.timestamps_ms().unwrap().get(0).unwrap();
+        let offsets: Vec<i64> = times.iter().map(|t| (t - start) / 1000).collect();
+        assert_eq!(offsets, [0, 3, 5, 6, 7]);
+        let (_, metrics) = ctx.take_diagnostics();
+        assert_eq!(metrics.get("deadband.removed_rows"), Some(&3.0));
+
+        // A heartbeat row at least every 2 s
+        let heartbeat = op.with_max_interval(Duration::from_secs(2));
+        assert_eq!(heartbeat.execute(data).unwrap().len(), 6);
+        assert!(DeadbandOperation::new(-1.0, None).is_err());
+    }
+}
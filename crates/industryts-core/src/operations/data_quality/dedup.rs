@@ -0,0 +1,217 @@
+//! Duplicate timestamp resolution
+//!
+//! Historian exports repeat rows when archives overlap or values are corrected
+//! after the fact. `DeduplicateOperation` finds rows sharing a timestamp (within
+//! each entity of grouped data, and optionally further key columns) and keeps the
+//! first or last of them in input order, or aggregates them into one row. The
+//! output is sorted by time, so lags, as-of joins and resampling downstream see a
+//! strictly increasing time axis per series. Duplicates are only found within a
+//! chunk when streaming.
+
+use crate::config::{AggMethod, StateAggregation};
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::data_quality::quality::quality_aware_aggs;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Row kept of each set of duplicates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKeep {
+    First,
+    /// The latest row, e.g. a corrected value (default)
+    #[default]
+    Last,
+    /// Aggregate the duplicates with the operation's aggregation
+    Aggregate,
+}
+
+/// Deduplicate operation - one row per timestamp and entity, sorted by time
+pub struct DeduplicateOperation {
+    keep: DuplicateKeep,
+    aggregation: AggMethod,
+    state_aggregation: StateAggregation,
+    keys: Vec<String>,
+}
+
+impl DeduplicateOperation {
+    pub fn new(keep: DuplicateKeep) -> Self {
+        Self {
+            keep,
+            aggregation: AggMethod::Mean,
+            state_aggregation: StateAggregation::Last,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Aggregation of duplicates kept with `DuplicateKeep::Aggregate` (default mean)
+    pub fn with_aggregation(mut self, aggregation: AggMethod) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Aggregation of string state columns when the aggregation is numeric (default last)
+    pub fn with_state_aggregation(mut self, state_aggregation: StateAggregation) -> Self {
+        self.state_aggregation = state_aggregation;
+        self
+    }
+
+    /// Further columns that must match for rows to be duplicates (e.g. a tag name)
+    pub fn with_keys(mut self, keys: Vec<String>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Deduplicate `data`; returns it and the number of rows removed
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        let time_col = data.time_column().to_string();
+        let mut keys = vec![time_col.clone()];
+        for key in data.group_columns().iter().chain(&self.keys) {
+            data.dataframe().column(key)?;
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        let df = data.dataframe();
+        let unique = match self.keep {
+            DuplicateKeep::First | DuplicateKeep::Last => {
+                let strategy = if self.keep == DuplicateKeep::First {
+                    UniqueKeepStrategy::First
+                } else {
+                    UniqueKeepStrategy::Last
+                };
+                df.unique_stable(Some(&keys), strategy, None)?
+            }
+            DuplicateKeep::Aggregate => {
+                let columns: Vec<String> = df
+                    .get_column_names()
+                    .into_iter()
+                    .filter(|c| !keys.iter().any(|k| k == c.as_str()))
+                    .map(|c| c.to_string())
+                    .collect();
+                let aggs = quality_aware_aggs(
+                    &data,
+                    &columns,
+                    false,
+                    self.aggregation,
+                    self.state_aggregation,
+                    &BTreeMap::new(),
+                );
+                let key_exprs: Vec<Expr> = keys.iter().map(|k| col(k.as_str())).collect();
+                let aggregated = df
+                    .clone()
+                    .lazy()
+                    .group_by_stable(key_exprs)
+                    .agg(aggs)
+                    .collect()?;
+                // Restore the input column order
+                aggregated.select(df.get_column_names().into_iter().cloned())?
+            }
+        };
+        let removed = df.height() - unique.height();
+        let sorted = unique.sort(
+            keys,
+            SortMultipleOptions::default()
+                .with_maintain_order(true)
+                .with_nulls_last(true),
+        )?;
+        Ok((
+            TimeSeriesData::with_metadata(sorted, data.metadata().clone())?,
+            removed,
+        ))
+    }
+}
+
+impl Operation for DeduplicateOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, removed) = self.run(data)?;
+        ctx.record_metric("deduplicate.duplicates", removed as f64);
+        if removed > 0 {
+            ctx.warn(format!("{} duplicate row(s) removed", removed));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "deduplicate"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.keys.clone()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+
+    fn reorders_rows(&self) -> bool {
+        true
+    }
+}
+
+impl TimeSeriesData {
+    /// Number of rows repeating the timestamp (and group keys) of an earlier row
+    pub fn duplicate_count(&self) -> Result<usize> {
+        let mut keys = vec![self.time_column().to_string()];
+        keys.extend(self.group_columns().iter().cloned());
+        let unique =
+            self.dataframe()
+                .select(keys)?
+                .unique_stable(None, UniqueKeepStrategy::First, None)?;
+        Ok(self.len() - unique.height())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_deduplicate_keep_and_aggregate() {
+        let data = Fixture::at_times_ms(vec![2_000, 0, 1_000, 0, 2_000, 0])
+            .with_column("unit", ["a", "a", "a", "a", "a", "b"])
+            .with_column("flow", [3.0, 1.0, 2.0, 5.0, 7.0, 9.0])
+            .with_group_columns(&["unit"])
+            .build()
+            .unwrap();
+        assert_eq!(data.duplicate_count().unwrap(), 2);
+
+        let flow = |result: &TimeSeriesData| -> Vec<f64> {
+            let column = result.dataframe().column("flow").unwrap();
+            column.f64().unwrap().into_no_null_iter().collect()
+        };
+        let last = DeduplicateOperation::new(DuplicateKeep::Last);
+        let mut ctx = OpContext::new();
+        let result = last.execute_with_context(data.clone(), &mut ctx).unwrap();
+        // Sorted by time, then unit
+        assert_eq!(flow(&result), vec![5.0, 9.0, 2.0, 7.0]);
+        assert_eq!(result.duplicate_count().unwrap(), 0);
+        let (_, metrics) = ctx.take_diagnostics();
+        assert_eq!(metrics.get("deduplicate.duplicates"), Some(&2.0));
+
+        let first = DeduplicateOperation::new(DuplicateKeep::First);
+        assert_eq!(
+            flow(&first.execute(data.clone()).unwrap()),
+            vec![1.0, 9.0, 2.0, 3.0]
+        );
+
+        let mean = DeduplicateOperation::new(DuplicateKeep::Aggregate);
+        let result = mean.execute(data).unwrap();
+        assert_eq!(flow(&result), vec![3.0, 9.0, 2.0, 5.0]);
+        assert_eq!(
+            result.dataframe().get_column_names()[0].as_str(),
+            result.time_column()
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Expectation suites profiled from known-good data
+//!
+//! An `ExpectationSuite` captures per-column statistics of a reference dataset and
+//! can be saved to a TOML file. New data is later checked against it: hard rules
+//! (dtype, range, null ratio) reuse `ValidationRules`, and statistical deviations
+//! (mean shift, spread change) are reported in the same `ValidationReport`.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::validation::{
+    ColumnRules, RuleResult, ValidateOperation, ValidationReport, ValidationRules,
+};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Profiled statistics of a single column
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ColumnProfile {
+    /// Dtype of the column in the reference data
+    pub dtype: String,
+    /// Fraction of null values
+    pub null_ratio: f64,
+    /// Mean (numeric columns only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean: Option<f64>,
+    /// Sample standard deviation (numeric columns only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub std: Option<f64>,
+    /// Minimum (numeric columns only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Maximum (numeric columns only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// Tolerances used when comparing new data to a profile
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExpectationTolerance {
+    /// Maximum shift of the mean, in reference standard deviations
+    pub mean_shift_sigmas: f64,
+    /// Maximum ratio between current and reference standard deviation (either way)
+    pub std_ratio: f64,
+    /// Maximum increase of the null ratio
+    pub null_ratio_delta: f64,
+    /// Margin added to the reference min/max, as a fraction of the reference range
+    pub range_margin: f64,
+}
+
+impl Default for ExpectationTolerance {
+    fn default() -> Self {
+        Self {
+            mean_shift_sigmas: 3.0,
+            std_ratio: 2.0,
+            null_ratio_delta: 0.05,
+            range_margin: 0.1,
+        }
+    }
+}
+
+/// Expectation suite profiled from a known-good dataset
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExpectationSuite {
+    /// Name of the suite
+    pub name: String,
+    /// Row count of the reference data
+    pub rows: usize,
+    /// Comparison tolerances
+    #[serde(default)]
+    pub tolerance: ExpectationTolerance,
+    /// Profiles per feature column
+    pub columns: BTreeMap<String, ColumnProfile>,
+}
+
+impl ExpectationSuite {
+    /// Profile all feature columns of a reference dataset
+    pub fn profile(name: &str, data: &TimeSeriesData) -> Result<Self> {
+        let df = data.dataframe();
+        let mut columns = BTreeMap::new();
+
+        for col_name in data.feature_columns() {
+            let series = df.column(col_name)?.as_materialized_series();
+            let null_ratio = if series.is_empty() {
+                0.0
+            } else {
+                series.null_count() as f64 / series.len() as f64
+            };
+
+            let mut profile = ColumnProfile {
+                dtype: series.dtype().to_string(),
+                null_ratio,
+                ..Default::default()
+            };
+
+            if series.dtype().is_primitive_numeric() {
+                let values = series.cast(&DataType::Float64)?;
+                profile.mean = values.mean();
+                profile.std = values.std(1);
+                profile.min = values.min::<f64>()?;
+                profile.max = values.max::<f64>()?;
+            }
+
+            columns.insert(col_name.clone(), profile);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            rows: data.len(),
+            tolerance: ExpectationTolerance::default(),
+            columns,
+        })
+    }
+
+    /// Set comparison tolerances
+    pub fn with_tolerance(mut self, tolerance: ExpectationTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Load a suite from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(Into::into)
+    }
+
+    /// Save the suite to a TOML file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string_pretty(self).map_err(|e| {
+            IndustrytsError::ConfigError(format!("Failed to serialize suite: {}", e))
+        })?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Derive hard validation rules (dtype, widened range, null ratio)
+    pub fn to_rules(&self) -> ValidationRules {
+        let tol = &self.tolerance;
+        let columns = self
+            .columns
+            .iter()
+            .map(|(name, profile)| {
+                let margin = match (profile.min, profile.max) {
+                    (Some(min), Some(max)) => (max - min) * tol.range_margin,
+                    _ => 0.0,
+                };
+                let rules = ColumnRules {
+                    dtype: Some(profile.dtype.clone()),
+                    min: profile.min.map(|v| v - margin),
+                    max: profile.max.map(|v| v + margin),
+                    max_null_ratio: Some((profile.null_ratio + tol.null_ratio_delta).min(1.0)),
+                    ..Default::default()
+                };
+                (name.clone(), rules)
+            })
+            .collect();
+
+        ValidationRules { columns }
+    }
+
+    /// Compare new data against the suite
+    pub fn compare(&self, data: &TimeSeriesData) -> Result<ValidationReport> {
+        let mut report = ValidateOperation::new(self.to_rules()).evaluate(data)?;
+        let df = data.dataframe();
+        let tol = &self.tolerance;
+
+        for (name, profile) in &self.columns {
+            let (Some(ref_mean), Some(ref_std)) = (profile.mean, profile.std) else {
+                continue;
+            };
+            let Ok(column) = df.column(name) else {
+                continue;
+            };
+            let values = column.as_materialized_series().cast(&DataType::Float64)?;
+            let (Some(mean), Some(std)) = (values.mean(), values.std(1)) else {
+                continue;
+            };
+
+            let shift = if ref_std > 0.0 {
+                (mean - ref_mean).abs() / ref_std
+            } else if mean == ref_mean {
+                0.0
+            } else {
+                f64::INFINITY
+            };
+            report.results.push(RuleResult {
+                column: name.clone(),
+                rule: "mean_shift".to_string(),
+                passed: shift <= tol.mean_shift_sigmas,
+                detail: format!(
+                    "mean {:.4} vs reference {:.4} ({:.2} sigma)",
+                    mean, ref_mean, shift
+                ),
+            });
+
+            if ref_std > 0.0 && std > 0.0 {
+                let ratio = std / ref_std;
+                report.results.push(RuleResult {
+                    column: name.clone(),
+                    rule: "std_ratio".to_string(),
+                    passed: ratio <= tol.std_ratio && ratio >= 1.0 / tol.std_ratio,
+                    detail: format!("std {:.4} vs reference {:.4}", std, ref_std),
+                });
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Expectation operation - check data against a profiled suite
+pub struct ExpectationOperation {
+    suite: ExpectationSuite,
+    warn_only: bool,
+}
+
+impl ExpectationOperation {
+    pub fn new(suite: ExpectationSuite) -> Self {
+        Self {
+            suite,
+            warn_only: false,
+        }
+    }
+
+    /// Load the suite from a TOML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(ExpectationSuite::load(path)?))
+    }
+
+    /// Record deviations in the output tags instead of failing the pipeline
+    pub fn with_warn_only(mut self, warn_only: bool) -> Self {
+        self.warn_only = warn_only;
+        self
+    }
+}
+
+impl Operation for ExpectationOperation {
+    fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let report = self.suite.compare(&data)?;
+
+        if !report.is_passed() && !self.warn_only {
+            return Err(IndustrytsError::ValidationError(format!(
+                "suite '{}': {}",
+                self.suite.name, report
+            )));
+        }
+
+        data.add_tag("expectations.suite".to_string(), self.suite.name.clone());
+        data.add_tag(
+            "expectations.passed".to_string(),
+            report.is_passed().to_string(),
+        );
+        Ok(data)
+    }
+
+    fn name(&self) -> &str {
+        "expect"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data(values: &[f64]) -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..values.len() as i64)
+            .map(|i| 1704067200000i64 + i * 60_000)
+            .collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("flow".into(), values).into(),
+        ])
+        .unwrap();
+
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_profile_and_compare() {
+        let reference = make_data(&[10.0, 11.0, 9.0, 10.5, 9.5, 10.0]);
+        let suite = ExpectationSuite::profile("flow_suite", &reference).unwrap();
+
+        let report = suite
+            .compare(&make_data(&[10.5, 9.5, 10.0, 11.0, 9.0]))
+            .unwrap();
+        assert!(report.is_passed());
+
+        let report = suite
+            .compare(&make_data(&[13.0, 13.1, 12.9, 13.0]))
+            .unwrap();
+        let failed: Vec<&str> = report.failures().iter().map(|r| r.rule.as_str()).collect();
+        assert!(failed.contains(&"mean_shift"));
+    }
+
+    #[test]
+    fn test_suite_roundtrip() {
+        let suite = ExpectationSuite::profile("roundtrip", &make_data(&[1.0, 2.0, 3.0])).unwrap();
+        let path = std::env::temp_dir().join("industryts_expectation_suite.toml");
+        suite.save(&path).unwrap();
+
+        let loaded = ExpectationSuite::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.name, "roundtrip");
+        assert_eq!(loaded.columns["flow"].mean, Some(2.0));
+    }
+}
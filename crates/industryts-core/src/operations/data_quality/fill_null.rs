@@ -1,44 +1,289 @@
 //! Fill null operation for handling missing values
+//!
+//! Besides the Polars fill strategies, nulls can be interpolated linearly by row
+//! position (`linear`) or weighted by timestamp (`time`), which handles irregular
+//! sampling. `max_gap` keeps every method from bridging long outages, and each
+//! column can use its own method.
 
+use super::quality::mark_substituted;
 use crate::config::FillMethod;
-use crate::core::{Operation, TimeSeriesData};
+use crate::core::{
+    Carry, CarryMode, FittableOperation, Operation, StatefulOperation, TimeSeriesData,
+};
 use crate::error::Result;
+use crate::operations::group::map_partitions;
 use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 /// Fill null operation
+///
+/// Once fitted (see `FittableOperation`), the `mean` method fills with the means
+/// of the training data instead of those of the data being filled.
 pub struct FillNullOperation {
     method: FillMethod,
     columns: Option<Vec<String>>,
+    column_methods: BTreeMap<String, FillMethod>,
+    max_gap: Option<Duration>,
+    fitted_means: Option<BTreeMap<String, f64>>,
 }
 
 impl FillNullOperation {
     pub fn new(method: FillMethod, columns: Option<Vec<String>>) -> Self {
-        Self { method, columns }
+        Self {
+            method,
+            columns,
+            column_methods: BTreeMap::new(),
+            max_gap: None,
+            fitted_means: None,
+        }
     }
-}
 
-impl Operation for FillNullOperation {
-    fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
-        // Get columns to fill before mutable borrow
-        let columns_to_fill = if let Some(cols) = &self.columns {
+    /// Fill `column` with `method` instead of the default method
+    ///
+    /// The column is filled even if it is not among the selected columns.
+    pub fn with_column_method(mut self, column: &str, method: FillMethod) -> Self {
+        self.column_methods.insert(column.to_string(), method);
+        self
+    }
+
+    /// Limit filling to `max_gap` around the valid observations used
+    ///
+    /// Forward/backward fill leaves nulls further than `max_gap` from the last
+    /// (next) valid observation; interpolation leaves runs whose valid neighbours
+    /// are more than `max_gap` apart. Long outages are thus not bridged with stale
+    /// or fabricated values. Has no effect on the `mean` and `zero` methods.
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = Some(max_gap);
+        self
+    }
+
+    fn method_for(&self, column: &str) -> FillMethod {
+        self.column_methods
+            .get(column)
+            .copied()
+            .unwrap_or(self.method)
+    }
+
+    /// Columns to fill in `data`
+    fn columns_to_fill(&self, data: &TimeSeriesData) -> Vec<String> {
+        let mut columns = if let Some(cols) = &self.columns {
             cols.clone()
         } else {
             data.feature_columns().to_vec()
         };
+        for column in self.column_methods.keys() {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        columns
+    }
+
+    /// Fill `series` with `method`; `times` are its timestamps
+    fn fill(&self, method: FillMethod, series: &Series, times: &[Option<i64>]) -> Result<Series> {
+        let max_gap_ms = self.max_gap.map(|gap| gap.as_millis() as i64);
+        let fitted_mean = self
+            .fitted_means
+            .as_ref()
+            .and_then(|means| means.get(series.name().as_str()));
+        let filled = match method {
+            FillMethod::Mean if let Some(&mean) = fitted_mean => {
+                let means = Series::new(series.name().clone(), vec![mean; series.len()])
+                    .cast(series.dtype())?;
+                series.zip_with(&series.is_not_null(), &means)?
+            }
+            FillMethod::Forward => series.fill_null(FillNullStrategy::Forward(None))?,
+            FillMethod::Backward => series.fill_null(FillNullStrategy::Backward(None))?,
+            FillMethod::Zero => series.fill_null(FillNullStrategy::Zero)?,
+            FillMethod::Mean => series.fill_null(FillNullStrategy::Mean)?,
+            FillMethod::Linear | FillMethod::Time => {
+                let by_time = matches!(method, FillMethod::Time);
+                return interpolate(series, times, by_time, max_gap_ms);
+            }
+        };
+        match max_gap_ms {
+            Some(max_gap_ms) => self.limit_gap(method, series, filled, times, max_gap_ms),
+            None => Ok(filled),
+        }
+    }
+
+    /// Fill `column` separately within each group of `data`
+    fn fill_groups(&self, data: &TimeSeriesData, column: &str) -> Result<Series> {
+        let time_col = data.time_column();
+        let mut selected = vec![time_col, column];
+        selected.extend(data.group_columns().iter().map(String::as_str));
+        let frame = data.dataframe().select(selected)?;
+
+        let filled = map_partitions(&frame, data.group_columns(), |mut partition| {
+            let times: Vec<Option<i64>> = TimeSeriesData::new(partition.clone(), Some(time_col))?
+                .timestamps_ms()?
+                .into_iter()
+                .collect();
+            let series = partition.column(column)?.as_materialized_series().clone();
+            let filled = self.fill(self.method_for(column), &series, &times)?;
+            partition.replace(column, filled)?;
+            Ok(partition)
+        })?;
+        Ok(filled.column(column)?.as_materialized_series().clone())
+    }
+
+    /// Null out filled values that are further than `max_gap_ms` from their source
+    fn limit_gap(
+        &self,
+        method: FillMethod,
+        original: &Series,
+        filled: Series,
+        times: &[Option<i64>],
+        max_gap_ms: i64,
+    ) -> Result<Series> {
+        let valid: Vec<bool> = original
+            .is_not_null()
+            .into_iter()
+            .map(|v| v.unwrap_or(false))
+            .collect();
+
+        let within = |source: Option<i64>, t: Option<i64>| match (source, t) {
+            (Some(s), Some(t)) => (t - s).abs() <= max_gap_ms,
+            _ => false,
+        };
+
+        let mut keep = vec![true; valid.len()];
+        let mut source = None;
+        match method {
+            FillMethod::Forward => {
+                for ((keep, &is_valid), &t) in keep.iter_mut().zip(&valid).zip(times) {
+                    if is_valid {
+                        source = t;
+                    } else {
+                        *keep = within(source, t);
+                    }
+                }
+            }
+            FillMethod::Backward => {
+                for ((keep, &is_valid), &t) in keep.iter_mut().zip(&valid).zip(times).rev() {
+                    if is_valid {
+                        source = t;
+                    } else {
+                        *keep = within(source, t);
+                    }
+                }
+            }
+            _ => return Ok(filled),
+        }
+
+        let mask = BooleanChunked::from_slice("mask".into(), &keep);
+        let nulls = Series::full_null(filled.name().clone(), filled.len(), filled.dtype());
+        Ok(filled.zip_with(&mask, &nulls)?)
+    }
+}
+
+/// Interpolate nulls between valid neighbours, by row position or by timestamp
+///
+/// Leading and trailing nulls stay null, as do runs whose neighbours are more than
+/// `max_gap_ms` apart. Numeric columns become Float64; other columns are returned
+/// unchanged.
+pub(crate) fn interpolate(
+    series: &Series,
+    times: &[Option<i64>],
+    by_time: bool,
+    max_gap_ms: Option<i64>,
+) -> Result<Series> {
+    if !series.dtype().is_primitive_numeric() {
+        return Ok(series.clone());
+    }
+    let values: Vec<Option<f64>> = series
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .collect();
+
+    let mut filled = values.clone();
+    let mut previous: Option<usize> = None;
+    for (i, value) in values.iter().enumerate() {
+        let Some(y1) = value else {
+            continue;
+        };
+        if let Some(p) = previous
+            && i > p + 1
+        {
+            let y0 = values[p].unwrap_or(*y1);
+            let bridged = match (max_gap_ms, times[p], times[i]) {
+                (None, _, _) => true,
+                (Some(max_gap_ms), Some(t0), Some(t1)) => t1 - t0 <= max_gap_ms,
+                _ => false,
+            };
+            for j in (p + 1..i).filter(|_| bridged) {
+                let weight = if by_time {
+                    match (times[p], times[j], times[i]) {
+                        (Some(t0), Some(t), Some(t1)) if t1 > t0 => {
+                            Some((t - t0) as f64 / (t1 - t0) as f64)
+                        }
+                        _ => None,
+                    }
+                } else {
+                    Some((j - p) as f64 / (i - p) as f64)
+                };
+                filled[j] = weight.map(|w| y0 + (y1 - y0) * w);
+            }
+        }
+        previous = Some(i);
+    }
+    Ok(Series::new(series.name().clone(), filled))
+}
+
+impl FittableOperation for FillNullOperation {
+    /// Learn the means of the columns filled with the `mean` method
+    fn fit(&mut self, data: &TimeSeriesData) -> Result<()> {
+        let mut means = BTreeMap::new();
+        for column in self.columns_to_fill(data) {
+            if !matches!(self.method_for(&column), FillMethod::Mean) {
+                continue;
+            }
+            let mean = data
+                .dataframe()
+                .column(&column)?
+                .as_materialized_series()
+                .mean();
+            if let Some(mean) = mean {
+                means.insert(column, mean);
+            }
+        }
+        self.fitted_means = Some(means);
+        Ok(())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.fitted_means
+            .as_ref()
+            .and_then(|means| serde_json::to_value(means).ok())
+    }
+
+    fn load_params(&mut self, params: serde_json::Value) -> Result<()> {
+        self.fitted_means = Some(serde_json::from_value(params)?);
+        Ok(())
+    }
+}
+
+impl Operation for FillNullOperation {
+    fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+        // Get columns to fill before mutable borrow
+        let columns_to_fill = self.columns_to_fill(&data);
 
-        let df = data.dataframe_mut();
+        let times: Vec<Option<i64>> = data.timestamps_ms()?.into_iter().collect();
+
+        // Grouped data is filled per entity, so values never leak between series
         for col_name in columns_to_fill {
-            let column = df.column(&col_name)?;
+            let column = data.dataframe().column(&col_name)?;
             let series = column.as_materialized_series().clone();
-
-            let filled = match self.method {
-                FillMethod::Forward => series.fill_null(FillNullStrategy::Forward(None))?,
-                FillMethod::Backward => series.fill_null(FillNullStrategy::Backward(None))?,
-                FillMethod::Zero => series.fill_null(FillNullStrategy::Zero)?,
-                FillMethod::Mean => series.fill_null(FillNullStrategy::Mean)?,
+            let filled = if data.group_columns().is_empty() {
+                self.fill(self.method_for(&col_name), &series, &times)?
+            } else {
+                self.fill_groups(&data, &col_name)?
             };
 
-            df.replace(&col_name, filled)?;
+            mark_substituted(&mut data, &col_name, &series, &filled)?;
+            data.dataframe_mut().replace(&col_name, filled)?;
         }
 
         Ok(data)
@@ -47,6 +292,58 @@ impl Operation for FillNullOperation {
     fn name(&self) -> &str {
         "fill_null"
     }
+
+    fn lookahead(&self) -> Vec<String> {
+        let mut methods = vec![self.method];
+        methods.extend(self.column_methods.values().copied());
+        let mut reasons: Vec<String> = Vec::new();
+        for method in methods {
+            let reason = match method {
+                FillMethod::Backward => "backward fill from the next valid value",
+                FillMethod::Linear | FillMethod::Time => {
+                    "interpolation towards the next valid value"
+                }
+                FillMethod::Mean if self.fitted_means.is_none() => {
+                    "mean fill with the mean of all rows; fit the step first"
+                }
+                _ => continue,
+            };
+            if !reasons.iter().any(|r| r == reason) {
+                reasons.push(reason.to_string());
+            }
+        }
+        reasons
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.column_methods.keys().cloned());
+        columns
+    }
+
+    fn fittable(&self) -> Option<&dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn fittable_mut(&mut self) -> Option<&mut dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        // Only forward fill depends on earlier rows alone
+        let forward = std::iter::once(&self.method)
+            .chain(self.column_methods.values())
+            .all(|m| matches!(m, FillMethod::Forward));
+        if !forward {
+            return None;
+        }
+        // With a gap limit the last valid observation must be carried, not its fill
+        let mode = match self.max_gap {
+            Some(max_gap) => CarryMode::InputWindow(max_gap),
+            None => CarryMode::OutputRows(1),
+        };
+        Some(Box::new(Carry::new(self, mode)))
+    }
 }
 
 #[cfg(test)]
@@ -81,4 +378,92 @@ mod tests {
         let value_col = result.dataframe().column("value").unwrap();
         assert_eq!(value_col.len(), 4);
     }
+
+    #[test]
+    fn test_fill_null_forward_max_gap() {
+        use polars::prelude::*;
+
+        // One row per minute
+        let dates_ms: Vec<i64> = (0..5).map(|i| 1704067200000i64 + i * 60_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[Some(1.0), None, None, None, Some(5.0)]).into(),
+        ])
+        .unwrap();
+
+        let ts = TimeSeriesData::new(df, Some("time")).unwrap();
+        let op = FillNullOperation::new(FillMethod::Forward, None)
+            .with_max_gap(std::time::Duration::from_secs(120));
+        let result = op.execute(ts).unwrap();
+
+        let values: Vec<Option<f64>> = result
+            .dataframe()
+            .column("value")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            values,
+            vec![Some(1.0), Some(1.0), Some(1.0), None, Some(5.0)]
+        );
+    }
+
+    #[test]
+    fn test_interpolation_per_column_with_max_gap() {
+        // Samples at 0, 1, 3, 4 and 10 minutes
+        let dates_ms: Vec<i64> = [0i64, 1, 3, 4, 10].iter().map(|m| m * 60_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new(
+                "flow".into(),
+                &[Some(0.0), None, Some(3.0), None, Some(10.0)],
+            )
+            .into(),
+            Series::new("level".into(), &[Some(0i64), None, Some(3), None, Some(10)]).into(),
+            Series::new(
+                "mode".into(),
+                &[Some("a"), None, Some("b"), None, Some("c")],
+            )
+            .into(),
+        ])
+        .unwrap();
+        let ts = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = FillNullOperation::new(FillMethod::Time, None)
+            .with_column_method("level", FillMethod::Linear)
+            .with_max_gap(Duration::from_secs(300));
+        let result = op.execute(ts).unwrap();
+
+        let values = |name: &str| -> Vec<Option<f64>> {
+            result
+                .dataframe()
+                .column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        // 0 -> 3 over three minutes; 3 -> 10 spans six minutes, beyond max_gap
+        assert_eq!(
+            values("flow"),
+            [Some(0.0), Some(1.0), Some(3.0), None, Some(10.0)]
+        );
+        // By row position, the row at 1 minute sits halfway between 0 and 3
+        assert_eq!(
+            values("level"),
+            [Some(0.0), Some(1.5), Some(3.0), None, Some(10.0)]
+        );
+        let mode = result.dataframe().column("mode").unwrap();
+        assert_eq!(mode.null_count(), 2);
+    }
 }
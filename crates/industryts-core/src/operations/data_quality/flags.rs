@@ -0,0 +1,253 @@
+//! Deferred handling of flagged samples
+//!
+//! Spikes must be detected at the raw resolution, where they are visible, but are
+//! often reported at a coarser one. Detectors that flag instead of acting
+//! (`outlier` with `action = "flag"`, `flatline`, `rate_of_change`) mark their
+//! flag columns with the `flag_of` property naming the value column. `resample`
+//! aggregates marked flags with "any", so a bucket is flagged when one of its raw
+//! samples was, and `ApplyFlagsOperation` later drops, nulls or interpolates the
+//! flagged values and removes the flags.
+
+use super::fill_null::interpolate;
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::group::map_partitions;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Column property naming the value column a flag column refers to
+pub const FLAG_OF: &str = "flag_of";
+
+/// Mark `flag` as the flag column of `column`
+pub(crate) fn mark_flag(data: &mut TimeSeriesData, flag: &str, column: &str) {
+    data.set_column_property(flag, FLAG_OF, column);
+}
+
+/// Flag columns of `data` with the value column each refers to
+pub(crate) fn flag_columns(data: &TimeSeriesData) -> Vec<(String, String)> {
+    data.dataframe()
+        .get_column_names()
+        .into_iter()
+        .filter_map(|name| {
+            let column = data.column_property(name, FLAG_OF)?;
+            Some((name.to_string(), column.to_string()))
+        })
+        .collect()
+}
+
+/// Whether `column` is a flag column marked with `mark_flag`
+pub(crate) fn is_flag_column(data: &TimeSeriesData, column: &str) -> bool {
+    data.column_property(column, FLAG_OF).is_some()
+}
+
+/// Aggregation of a flag column: set if any sample of the bucket was flagged
+pub(crate) fn any_flag_expr(column: &str) -> Expr {
+    col(column).cast(DataType::Boolean).max().alias(column)
+}
+
+/// What happens to flagged values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagAction {
+    /// Replace with null
+    #[default]
+    Null,
+    /// Drop the row (if any of its values is flagged)
+    Drop,
+    /// Interpolate by time between the unflagged neighbours
+    Interpolate,
+}
+
+/// Apply flags operation - act on values flagged by an earlier step
+///
+/// A flag is set where its column is true or non-zero. Flags of columns not
+/// selected are left alone.
+pub struct ApplyFlagsOperation {
+    action: FlagAction,
+    columns: Option<Vec<String>>,
+    keep_flags: bool,
+}
+
+impl ApplyFlagsOperation {
+    /// Apply `action` to the flagged values of `columns` (default: all flagged
+    /// columns)
+    pub fn new(action: FlagAction, columns: Option<Vec<String>>) -> Self {
+        Self {
+            action,
+            columns,
+            keep_flags: false,
+        }
+    }
+
+    /// Keep the flag columns after applying them (default: removed)
+    pub fn with_keep_flags(mut self, keep_flags: bool) -> Self {
+        self.keep_flags = keep_flags;
+        self
+    }
+
+    fn flags(&self, data: &TimeSeriesData) -> Vec<(String, String)> {
+        flag_columns(data)
+            .into_iter()
+            .filter(|(_, column)| {
+                self.columns.as_ref().is_none_or(|c| c.contains(column))
+                    && data.dataframe().column(column).is_ok()
+            })
+            .collect()
+    }
+}
+
+/// Null the values of `column` where `flag` is set
+fn null_flagged(df: &DataFrame, column: &str, flag: &str) -> Result<Series> {
+    let values = df.column(column)?.as_materialized_series();
+    let set = df
+        .column(flag)?
+        .cast(&DataType::Boolean)?
+        .bool()?
+        .fill_null_with_values(false)?;
+    let nulls = Series::full_null(values.name().clone(), values.len(), values.dtype());
+    Ok(nulls.zip_with(&set, values)?)
+}
+
+impl Operation for ApplyFlagsOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let flags = self.flags(&data);
+        let metadata = data.metadata().clone();
+        let mut df = data.dataframe().clone();
+
+        match self.action {
+            FlagAction::Drop => {
+                let mut drop = BooleanChunked::full("drop".into(), false, df.height());
+                for (flag, _) in &flags {
+                    let set = df.column(flag)?.cast(&DataType::Boolean)?;
+                    drop = &drop | &set.bool()?.fill_null_with_values(false)?;
+                }
+                df = df.filter(&!&drop)?;
+            }
+            FlagAction::Null => {
+                for (flag, column) in &flags {
+                    let nulled = null_flagged(&df, column, flag)?;
+                    df.replace(column, nulled)?;
+                }
+            }
+            FlagAction::Interpolate => {
+                let time_col = data.time_column();
+                for (flag, column) in &flags {
+                    let nulled = null_flagged(&df, column, flag)?;
+                    df.replace(column, nulled)?;
+                }
+                let columns: Vec<&String> = flags.iter().map(|(_, column)| column).collect();
+                df = map_partitions(&df, data.group_columns(), |mut partition| {
+                    let times: Vec<Option<i64>> =
+                        TimeSeriesData::new(partition.clone(), Some(time_col))?
+                            .timestamps_ms()?
+                            .into_iter()
+                            .collect();
+                    for column in &columns {
+                        let series = partition.column(column)?.as_materialized_series().clone();
+                        partition.replace(column, interpolate(&series, &times, true, None)?)?;
+                    }
+                    Ok(partition)
+                })?;
+            }
+        }
+
+        if !self.keep_flags {
+            for (flag, _) in &flags {
+                df.drop_in_place(flag)?;
+            }
+        }
+        let mut result = TimeSeriesData::new(df, Some(&metadata.time_column))?
+            .with_group_columns(&metadata.group_columns)?;
+        result.metadata_mut().tags = metadata.tags;
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "apply_flags"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.action == FlagAction::Drop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AggMethod, PipelineConfig};
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_detect_raw_act_after_resample() {
+        // 10-second samples; one spike in the second minute
+        let mut values = vec![10.0; 24];
+        values[8] = 500.0;
+        let data = Fixture::every(Duration::from_secs(10), 24)
+            .with_column("flow", values)
+            .build()
+            .unwrap();
+        let config = |action: &str| {
+            PipelineConfig::from_toml_str(&format!(
+                r#"
+                [pipeline]
+                name = "deferred"
+
+                [[operations]]
+                type = "outlier"
+                method = "limits"
+                limits = {{ flow = {{ max = 100.0 }} }}
+                action = "flag"
+
+                [[operations]]
+                type = "resample"
+                rule = "1m"
+                aggregation = "mean"
+                columns = ["flow"]
+
+                [[operations]]
+                type = "apply_flags"
+                action = "{action}"
+                "#
+            ))
+            .unwrap()
+        };
+
+        let flow = |data: &TimeSeriesData| -> Vec<Option<f64>> {
+            data.dataframe()
+                .column("flow")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        let interpolated = Pipeline::from_config(config("interpolate"))
+            .unwrap()
+            .process(data.clone())
+            .unwrap();
+        assert_eq!(
+            flow(&interpolated),
+            [Some(10.0), Some(10.0), Some(10.0), Some(10.0)]
+        );
+        assert!(interpolated.dataframe().column("flow_outlier").is_err());
+
+        let dropped = Pipeline::from_config(config("drop"))
+            .unwrap()
+            .process(data.clone())
+            .unwrap();
+        assert_eq!(dropped.len(), 3);
+
+        // Without deferral the spike leaks into the minute mean
+        let resampled = crate::operations::ResampleOperation::new("1m", AggMethod::Mean, None)
+            .unwrap()
+            .execute(data)
+            .unwrap();
+        assert!(flow(&resampled)[1].unwrap() > 50.0);
+    }
+}
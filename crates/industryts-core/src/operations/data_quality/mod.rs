@@ -1,10 +1,34 @@
 //! Data quality operations
 //!
 //! This module provides operations for data quality assurance:
+//! - dedup: resolving duplicate timestamps
+//! - deadband: dropping rows that stay within tolerance of the last row kept
 //! - fill_null: handling missing values
 //! - validation: data validation
+//! - quality: historian quality codes
+//! - sentinel: replacing magic "missing" values with nulls
+//! - nans: consistent treatment of NaN and null
 //! - outlier: outlier detection and handling
+//! - flags: deferred action on flagged values, e.g. after resampling
 
+pub mod deadband;
+pub mod dedup;
+pub mod expectations;
 pub mod fill_null;
+pub mod flags;
+pub mod nans;
+pub mod outlier;
+pub mod quality;
+pub mod sentinel;
+pub mod validation;
 
+pub use deadband::DeadbandOperation;
+pub use dedup::{DeduplicateOperation, DuplicateKeep};
+pub use expectations::{ExpectationOperation, ExpectationSuite};
 pub use fill_null::FillNullOperation;
+pub use flags::{ApplyFlagsOperation, FlagAction};
+pub use nans::NormalizeNansOperation;
+pub use outlier::{Limits, OutlierAction, OutlierMethod, OutlierOperation};
+pub use quality::{Quality, QualityFilterOperation, QualityScheme};
+pub use sentinel::{ReplaceSentinelsOperation, SentinelValue};
+pub use validation::{ValidateOperation, ValidationPolicy, ValidationReport, ValidationRules};
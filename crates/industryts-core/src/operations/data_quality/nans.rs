@@ -0,0 +1,105 @@
+//! NaN normalization
+//!
+//! Floating-point columns can hold both NaN (from sensors, divisions by zero or
+//! upstream tools) and null. Polars skips nulls in aggregations but propagates
+//! NaN, so a single NaN silently turns a resample bucket or a column statistic
+//! (standardization, z-scores) into NaN. `NormalizeNansOperation` applies a
+//! `NanPolicy` to selected columns at a chosen point of a pipeline;
+//! `Pipeline::set_nan_policy` and `ReadOptions::with_nan_policy` apply one to the
+//! whole run or on ingest.
+
+use crate::core::{NanPolicy, OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+
+/// Normalize NaN operation - make NaN or null the single missing-value marker
+pub struct NormalizeNansOperation {
+    policy: NanPolicy,
+    columns: Option<Vec<String>>,
+}
+
+impl NormalizeNansOperation {
+    /// Apply `policy` to `columns`, or to all floating-point feature columns
+    pub fn new(policy: NanPolicy, columns: Option<Vec<String>>) -> Self {
+        Self { policy, columns }
+    }
+
+    fn run(&self, mut data: TimeSeriesData, ctx: Option<&mut OpContext>) -> Result<TimeSeriesData> {
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data.feature_columns().to_vec(),
+        };
+        let mut total = 0;
+        for column in &columns {
+            let series = data.dataframe().column(column)?.as_materialized_series();
+            let (converted, count) = self.policy.apply(series)?;
+            if count > 0 {
+                data.dataframe_mut().replace(column, converted)?;
+                total += count;
+            }
+        }
+        if let Some(ctx) = ctx {
+            ctx.record_metric("normalize_nans.converted", total as f64);
+        }
+        Ok(data)
+    }
+}
+
+impl Operation for NormalizeNansOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        "normalize_nans"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_nan_policies() {
+        let times: Vec<i64> = (0..5).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new(
+                "temp".into(),
+                [Some(1.0), Some(f64::NAN), None, Some(4.0), Some(5.0)],
+            )
+            .into(),
+            Series::new("state".into(), ["run", "run", "stop", "run", "run"]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = NormalizeNansOperation::new(NanPolicy::Null, None);
+        let mut ctx = OpContext::new();
+        let nulled = op.execute_with_context(data.clone(), &mut ctx).unwrap();
+        let temp = nulled.dataframe().column("temp").unwrap();
+        assert_eq!(temp.null_count(), 2);
+        assert_eq!(temp.f64().unwrap().get(3), Some(4.0));
+        assert_eq!(ctx.take_diagnostics().1["normalize_nans.converted"], 1.0);
+
+        let nans = data.with_nan_policy(NanPolicy::Nan).unwrap();
+        let temp = nans.dataframe().column("temp").unwrap();
+        assert_eq!(temp.null_count(), 0);
+        assert!(temp.f64().unwrap().get(2).unwrap().is_nan());
+    }
+}
@@ -0,0 +1,386 @@
+//! Outlier detection and handling
+//!
+//! `OutlierOperation` computes an acceptable range for every sample with one of
+//! several detectors and handles samples outside it:
+//!
+//! - `zscore`: mean ± `threshold` standard deviations of the column
+//! - `iqr`: quartiles extended by `factor` interquartile ranges (Tukey fences)
+//! - `rolling_mad`: median ± `threshold` scaled MADs of a trailing time window,
+//!   which follows slow process changes and catches spikes
+//! - `limits`: fixed physical limits per column (e.g. a 0-150 degC transmitter)
+//!
+//! Outliers are clipped to the range, replaced with nulls, dropped or flagged in
+//! `<column>_outlier` boolean columns, which `ApplyFlagsOperation` can act on
+//! later (see `flags`).
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::data_quality::flags::mark_flag;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Factor turning a MAD into a standard deviation estimate for normal data
+pub(crate) const MAD_SCALE: f64 = 1.4826;
+
+/// Minimum samples in a rolling window before it yields a range
+const MIN_WINDOW_SAMPLES: usize = 3;
+
+/// Physical limits of a column; a missing side is unbounded
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Limits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// How the acceptable range of a sample is determined
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutlierMethod {
+    /// Mean ± `threshold` standard deviations
+    ZScore { threshold: f64 },
+    /// `[Q1 - factor * IQR, Q3 + factor * IQR]`
+    Iqr { factor: f64 },
+    /// Median ± `threshold` scaled MADs over the trailing `window`
+    RollingMad { window: Duration, threshold: f64 },
+    /// Fixed limits per column; other columns are left alone
+    Limits(BTreeMap<String, Limits>),
+}
+
+/// What happens to outliers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlierAction {
+    /// Clip to the nearest bound of the range
+    Clip,
+    /// Replace with null
+    #[default]
+    Null,
+    /// Drop the row (if any checked column is an outlier)
+    Drop,
+    /// Keep the value and add a `<column>_outlier` flag column
+    Flag,
+}
+
+/// Acceptable range of each sample; `None` where it cannot be determined
+type Bounds = Vec<Option<(f64, f64)>>;
+
+pub(crate) fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+pub(crate) fn median(sorted: &[f64]) -> f64 {
+    quantile(sorted, 0.5)
+}
+
+/// Outlier operation - detect and handle outliers
+pub struct OutlierOperation {
+    method: OutlierMethod,
+    action: OutlierAction,
+    columns: Option<Vec<String>>,
+}
+
+impl OutlierOperation {
+    pub fn new(method: OutlierMethod, action: OutlierAction, columns: Option<Vec<String>>) -> Self {
+        Self {
+            method,
+            action,
+            columns,
+        }
+    }
+
+    fn target_columns(&self, data: &TimeSeriesData) -> Vec<String> {
+        match (&self.columns, &self.method) {
+            (Some(columns), _) => columns.clone(),
+            (None, OutlierMethod::Limits(limits)) => limits.keys().cloned().collect(),
+            (None, _) => data.feature_columns().to_vec(),
+        }
+    }
+
+    /// Acceptable range of every sample of `column`
+    fn bounds(&self, column: &str, times: &[Option<i64>], values: &[Option<f64>]) -> Bounds {
+        let finite: Vec<f64> = values
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|v| v.is_finite())
+            .collect();
+        let constant = |range: Option<(f64, f64)>| vec![range; values.len()];
+        match &self.method {
+            OutlierMethod::ZScore { threshold } => {
+                if finite.len() < 2 {
+                    return constant(None);
+                }
+                let n = finite.len() as f64;
+                let mean = finite.iter().sum::<f64>() / n;
+                let std =
+                    (finite.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+                constant(Some((mean - threshold * std, mean + threshold * std)))
+            }
+            OutlierMethod::Iqr { factor } => {
+                if finite.is_empty() {
+                    return constant(None);
+                }
+                let mut sorted = finite;
+                sorted.sort_by(f64::total_cmp);
+                let (q1, q3) = (quantile(&sorted, 0.25), quantile(&sorted, 0.75));
+                let iqr = q3 - q1;
+                constant(Some((q1 - factor * iqr, q3 + factor * iqr)))
+            }
+            OutlierMethod::Limits(limits) => constant(limits.get(column).map(|l| {
+                (
+                    l.min.unwrap_or(f64::NEG_INFINITY),
+                    l.max.unwrap_or(f64::INFINITY),
+                )
+            })),
+            OutlierMethod::RollingMad { window, threshold } => {
+                let window_ms = window.as_millis() as i64;
+                let mut bounds = Vec::with_capacity(values.len());
+                let mut start = 0;
+                for i in 0..values.len() {
+                    let Some(t) = times[i] else {
+                        bounds.push(None);
+                        continue;
+                    };
+                    while start < i && times[start].is_none_or(|s| s <= t - window_ms) {
+                        start += 1;
+                    }
+                    let mut window: Vec<f64> = values[start..=i]
+                        .iter()
+                        .flatten()
+                        .copied()
+                        .filter(|v| v.is_finite())
+                        .collect();
+                    if window.len() < MIN_WINDOW_SAMPLES {
+                        bounds.push(None);
+                        continue;
+                    }
+                    window.sort_by(f64::total_cmp);
+                    let center = median(&window);
+                    let mut deviations: Vec<f64> =
+                        window.iter().map(|v| (v - center).abs()).collect();
+                    deviations.sort_by(f64::total_cmp);
+                    let spread = threshold * MAD_SCALE * median(&deviations);
+                    bounds.push(Some((center - spread, center + spread)));
+                }
+                bounds
+            }
+        }
+    }
+
+    fn run(&self, data: TimeSeriesData, mut ctx: Option<&mut OpContext>) -> Result<TimeSeriesData> {
+        let times: Vec<Option<i64>> = data.timestamps_ms()?.into_iter().collect();
+        let mut df = data.dataframe().clone();
+        let mut drop = vec![false; df.height()];
+        let columns = self.target_columns(&data);
+
+        for col_name in columns.clone() {
+            let values: Vec<Option<f64>> = df
+                .column(&col_name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .collect();
+            let bounds = self.bounds(&col_name, &times, &values);
+            let is_outlier: Vec<bool> = values
+                .iter()
+                .zip(&bounds)
+                .map(|(v, b)| matches!((v, b), (Some(v), Some((lo, hi))) if v < lo || v > hi))
+                .collect();
+            let count = is_outlier.iter().filter(|o| **o).count();
+            if let Some(ctx) = ctx.as_deref_mut() {
+                ctx.record_metric(&format!("outliers.{}", col_name), count as f64);
+            }
+
+            match self.action {
+                OutlierAction::Clip | OutlierAction::Null => {
+                    let handled: Vec<Option<f64>> = values
+                        .iter()
+                        .zip(&bounds)
+                        .zip(&is_outlier)
+                        .map(|((v, b), outlier)| match (outlier, b) {
+                            (true, Some((lo, hi))) if self.action == OutlierAction::Clip => {
+                                v.map(|v| v.clamp(*lo, *hi))
+                            }
+                            (true, _) => None,
+                            (false, _) => *v,
+                        })
+                        .collect();
+                    df.replace(&col_name, Series::new(col_name.as_str().into(), handled))?;
+                }
+                OutlierAction::Drop => {
+                    for (drop, outlier) in drop.iter_mut().zip(&is_outlier) {
+                        *drop |= outlier;
+                    }
+                }
+                OutlierAction::Flag => {
+                    df.with_column(Series::new(
+                        format!("{}_outlier", col_name).into(),
+                        is_outlier,
+                    ))?;
+                }
+            }
+        }
+
+        if self.action == OutlierAction::Drop {
+            let keep: BooleanChunked = drop.iter().map(|d| !d).collect();
+            df = df.filter(&keep)?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        if self.action == OutlierAction::Flag {
+            for col_name in &columns {
+                mark_flag(&mut result, &format!("{}_outlier", col_name), col_name);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Operation for OutlierOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        "outlier"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        match (&self.columns, &self.method) {
+            (Some(columns), _) => columns.clone(),
+            (None, OutlierMethod::Limits(limits)) => limits.keys().cloned().collect(),
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        if self.action != OutlierAction::Flag {
+            return Vec::new();
+        }
+        let columns = match (&self.columns, &self.method) {
+            (Some(columns), _) => columns.clone(),
+            (None, OutlierMethod::Limits(limits)) => limits.keys().cloned().collect(),
+            (None, _) => feature_columns.to_vec(),
+        };
+        columns.iter().map(|c| format!("{}_outlier", c)).collect()
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.action == OutlierAction::Drop
+    }
+
+    fn warmup(&self) -> Duration {
+        match self.method {
+            OutlierMethod::RollingMad { window, .. } => window,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spiky() -> TimeSeriesData {
+        let time_series = Series::new(
+            "time".into(),
+            (0..10).map(|i| i * 60_000i64).collect::<Vec<_>>(),
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new(
+                "temp".into(),
+                [20.0, 20.5, 21.0, 20.8, 95.0, 21.2, 20.9, 21.1, -40.0, 21.0],
+            )
+            .into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn temp(data: &TimeSeriesData) -> Vec<Option<f64>> {
+        data.dataframe()
+            .column("temp")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_outlier_methods_and_actions() {
+        let iqr = OutlierOperation::new(
+            OutlierMethod::Iqr { factor: 1.5 },
+            OutlierAction::Null,
+            None,
+        );
+        let mut ctx = OpContext::new();
+        let result = iqr.execute_with_context(spiky(), &mut ctx).unwrap();
+        let values = temp(&result);
+        assert_eq!(values[4], None);
+        assert_eq!(values[8], None);
+        assert_eq!(values[5], Some(21.2));
+        assert_eq!(ctx.take_diagnostics().1["outliers.temp"], 2.0);
+
+        let mad = OutlierOperation::new(
+            OutlierMethod::RollingMad {
+                window: Duration::from_secs(600),
+                threshold: 3.5,
+            },
+            OutlierAction::Drop,
+            None,
+        );
+        assert_eq!(mad.execute(spiky()).unwrap().len(), 8);
+
+        let limits = BTreeMap::from([(
+            "temp".to_string(),
+            Limits {
+                min: Some(0.0),
+                max: Some(50.0),
+            },
+        )]);
+        let clip = OutlierOperation::new(OutlierMethod::Limits(limits), OutlierAction::Clip, None);
+        let values = temp(&clip.execute(spiky()).unwrap());
+        assert_eq!(values[4], Some(50.0));
+        assert_eq!(values[8], Some(0.0));
+    }
+
+    #[test]
+    fn test_flag_outliers_with_zscore() {
+        let op = OutlierOperation::new(
+            OutlierMethod::ZScore { threshold: 2.0 },
+            OutlierAction::Flag,
+            None,
+        );
+        assert_eq!(op.added_columns(&["temp".to_string()]), ["temp_outlier"]);
+        let result = op.execute(spiky()).unwrap();
+        let flags: Vec<Option<bool>> = result
+            .dataframe()
+            .column("temp_outlier")
+            .unwrap()
+            .bool()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(flags[4], Some(true));
+        assert_eq!(flags[0], Some(false));
+        assert_eq!(temp(&result)[4], Some(95.0));
+    }
+}
@@ -0,0 +1,407 @@
+//! Historian quality codes
+//!
+//! Historians store a quality or status code next to each sample (OPC DA quality,
+//! OPC UA StatusCode, PI status text). In tabular exports the code lives in a
+//! companion column named `<value><suffix>`, e.g. `temperature_quality`.
+//! `QualityFilterOperation` classifies these codes into `Quality` levels, nulls out
+//! values below a minimum quality and normalizes the companion columns so later
+//! steps can carry quality along:
+//!
+//! - `FillNullOperation` marks filled samples as `Uncertain`
+//! - `CalendarBucketOperation` and `ResampleOperation` keep the worst quality of each
+//!   bucket
+
+use super::flags::{any_flag_expr, flag_columns, is_flag_column};
+use crate::config::{AggMethod, StateAggregation};
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Tag holding the companion column suffix once quality columns are normalized
+pub const QUALITY_SUFFIX_TAG: &str = "quality.suffix";
+
+/// Default suffix of quality companion columns
+pub const DEFAULT_QUALITY_SUFFIX: &str = "_quality";
+
+/// Quality level of a sample, ordered from worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    Bad = 0,
+    Uncertain = 1,
+    Good = 2,
+}
+
+/// Encoding of the raw quality codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityScheme {
+    /// OPC DA 16-bit quality: bits 6-7 are 11 good, 01 uncertain, 00 bad
+    OpcDa,
+    /// OPC UA 32-bit StatusCode: bits 30-31 are 00 good, 01 uncertain, 1x bad
+    OpcUa,
+    /// Status text such as PI's "Good", "Questionable" or "I/O Timeout"
+    Label,
+    /// Already normalized levels (0 bad, 1 uncertain, 2 good)
+    Level,
+}
+
+impl QualityScheme {
+    fn classify_code(self, code: i64) -> Quality {
+        match self {
+            QualityScheme::OpcDa => match code & 0xC0 {
+                0xC0 => Quality::Good,
+                0x40 => Quality::Uncertain,
+                _ => Quality::Bad,
+            },
+            QualityScheme::OpcUa => match (code as u32) >> 30 {
+                0 => Quality::Good,
+                1 => Quality::Uncertain,
+                _ => Quality::Bad,
+            },
+            QualityScheme::Label | QualityScheme::Level => match code {
+                2 => Quality::Good,
+                1 => Quality::Uncertain,
+                _ => Quality::Bad,
+            },
+        }
+    }
+
+    fn classify_label(label: &str) -> Quality {
+        let label = label.trim().to_lowercase();
+        if label.starts_with("good") {
+            Quality::Good
+        } else if ["uncertain", "questionable", "substituted", "annotated"]
+            .iter()
+            .any(|prefix| label.starts_with(prefix))
+        {
+            Quality::Uncertain
+        } else {
+            Quality::Bad
+        }
+    }
+
+    /// Classify a column of raw codes into quality levels; missing codes are `Bad`
+    pub fn classify(self, codes: &Series) -> Result<Int32Chunked> {
+        let levels: Vec<i32> = if self == QualityScheme::Label {
+            let labels = codes.str().map_err(|_| {
+                IndustrytsError::OperationError(format!(
+                    "Quality column {} must contain text for the label scheme",
+                    codes.name()
+                ))
+            })?;
+            labels
+                .into_iter()
+                .map(|l| l.map_or(Quality::Bad, Self::classify_label) as i32)
+                .collect()
+        } else {
+            let values = codes.cast(&DataType::Int64)?;
+            values
+                .i64()?
+                .into_iter()
+                .map(|c| c.map_or(Quality::Bad, |c| self.classify_code(c)) as i32)
+                .collect()
+        };
+        Ok(Int32Chunked::from_vec(codes.name().clone(), levels))
+    }
+}
+
+/// Name of the quality companion column of `column`
+pub fn quality_column(column: &str, suffix: &str) -> String {
+    format!("{}{}", column, suffix)
+}
+
+/// Aggregation carrying the worst quality of a group
+pub fn worst_quality_expr(column: &str) -> Expr {
+    col(column).min()
+}
+
+/// Whether `column` holds string states rather than measurements
+fn is_state_column(data: &TimeSeriesData, column: &str) -> bool {
+    data.dataframe()
+        .column(column)
+        .is_ok_and(|c| c.dtype() == &DataType::String)
+}
+
+/// Aggregation expressions for `columns` that carry quality companion columns along
+///
+/// Companion columns are aggregated with `worst_quality_expr`, flag columns (see
+/// `flags`) with "any" and the values with `aggregation`, weighted by their column
+/// in `weights` if any; string state columns fall back to `state_aggregation` when
+/// `aggregation` is numeric. With `add_companions`, the quality companions and
+/// flags of the listed value columns are aggregated too (used when the caller
+/// selected columns explicitly).
+pub fn quality_aware_aggs(
+    data: &TimeSeriesData,
+    columns: &[String],
+    add_companions: bool,
+    aggregation: AggMethod,
+    state_aggregation: StateAggregation,
+    weights: &BTreeMap<String, String>,
+) -> Vec<Expr> {
+    let suffix = data.get_tag(QUALITY_SUFFIX_TAG);
+    let mut columns = columns.to_vec();
+    if let (Some(suffix), true) = (suffix, add_companions) {
+        for value in columns.clone() {
+            let companion = quality_column(&value, suffix);
+            if data.dataframe().column(&companion).is_ok() && !columns.contains(&companion) {
+                columns.push(companion);
+            }
+        }
+    }
+    if add_companions {
+        for (flag, value) in flag_columns(data) {
+            if columns.contains(&value) && !columns.contains(&flag) {
+                columns.push(flag);
+            }
+        }
+    }
+    columns
+        .iter()
+        .map(|c| match suffix {
+            _ if is_flag_column(data, c) => any_flag_expr(c),
+            Some(suffix) if c.ends_with(suffix) => worst_quality_expr(c),
+            _ if aggregation.is_numeric() && is_state_column(data, c) => state_aggregation.expr(c),
+            _ => match weights.get(c) {
+                Some(weight) => aggregation.weighted_expr(c, weight),
+                None => aggregation.expr(c),
+            },
+        })
+        .collect()
+}
+
+/// Mark samples filled by `FillNullOperation` as `Uncertain` in the companion column
+pub(crate) fn mark_substituted(
+    data: &mut TimeSeriesData,
+    column: &str,
+    original: &Series,
+    filled: &Series,
+) -> Result<()> {
+    let Some(suffix) = data.get_tag(QUALITY_SUFFIX_TAG).map(|s| s.to_string()) else {
+        return Ok(());
+    };
+    let companion = quality_column(column, &suffix);
+    let df = data.dataframe_mut();
+    let Ok(levels) = df.column(&companion) else {
+        return Ok(());
+    };
+
+    let substituted = original.is_null() & filled.is_not_null();
+    let levels = levels.as_materialized_series().i32()?.clone();
+    let uncertain = Int32Chunked::full(
+        levels.name().clone(),
+        Quality::Uncertain as i32,
+        levels.len(),
+    );
+    let marked = uncertain.zip_with(&substituted, &levels)?;
+    df.replace(&companion, marked.into_series())?;
+    Ok(())
+}
+
+/// Quality filter operation - null out low-quality samples and normalize quality columns
+///
+/// Value columns without a companion quality column are left untouched. After
+/// filtering, companion columns hold `Quality` levels as Int32 (0 bad, 1 uncertain,
+/// 2 good).
+pub struct QualityFilterOperation {
+    scheme: QualityScheme,
+    suffix: String,
+    min_quality: Quality,
+    columns: Option<Vec<String>>,
+}
+
+impl QualityFilterOperation {
+    /// Ignore `Bad` samples of all value columns that have a quality column
+    pub fn new(scheme: QualityScheme) -> Self {
+        Self {
+            scheme,
+            suffix: DEFAULT_QUALITY_SUFFIX.to_string(),
+            min_quality: Quality::Uncertain,
+            columns: None,
+        }
+    }
+
+    /// Suffix of the companion columns (default "_quality")
+    pub fn with_suffix(mut self, suffix: &str) -> Self {
+        self.suffix = suffix.to_string();
+        self
+    }
+
+    /// Lowest quality that is kept (default `Uncertain`)
+    pub fn with_min_quality(mut self, min_quality: Quality) -> Self {
+        self.min_quality = min_quality;
+        self
+    }
+
+    /// Restrict filtering to these value columns
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    fn value_columns(&self, data: &TimeSeriesData) -> Vec<String> {
+        match &self.columns {
+            Some(cols) => cols.clone(),
+            None => {
+                let features = data.feature_columns();
+                features
+                    .iter()
+                    .filter(|c| {
+                        !c.ends_with(&self.suffix)
+                            && features.contains(&quality_column(c, &self.suffix))
+                    })
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Operation for QualityFilterOperation {
+    fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = self.value_columns(&data);
+        let df = data.dataframe_mut();
+
+        for column in &columns {
+            let companion = quality_column(column, &self.suffix);
+            let codes = df
+                .column(&companion)
+                .map_err(|_| IndustrytsError::ColumnNotFound(companion.clone()))?
+                .as_materialized_series()
+                .clone();
+            let levels = self.scheme.classify(&codes)?;
+
+            let keep = levels.gt_eq(self.min_quality as i32);
+            let values = df.column(column)?.as_materialized_series().clone();
+            let nulls = Series::full_null(values.name().clone(), values.len(), values.dtype());
+            df.replace(column, values.zip_with(&keep, &nulls)?)?;
+            df.replace(&companion, levels.into_series())?;
+        }
+
+        data.add_tag(QUALITY_SUFFIX_TAG.to_string(), self.suffix.clone());
+        Ok(data)
+    }
+
+    fn name(&self) -> &str {
+        "quality_filter"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        for column in self.columns.iter().flatten() {
+            columns.push(column.clone());
+            columns.push(quality_column(column, &self.suffix));
+        }
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data() -> TimeSeriesData {
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000, 180_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), &[1.0, 2.0, 3.0, 4.0]).into(),
+            // good, bad (comm failure), uncertain (last usable), good
+            Series::new("temp_quality".into(), &[192i32, 24, 68, 192]).into(),
+            Series::new("flow".into(), &[5.0, 6.0, 7.0, 8.0]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_quality_filter_masks_bad_samples() {
+        let result = QualityFilterOperation::new(QualityScheme::OpcDa)
+            .execute(make_data())
+            .unwrap();
+        let df = result.dataframe();
+
+        let temp: Vec<Option<f64>> = df
+            .column("temp")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(temp, vec![Some(1.0), None, Some(3.0), Some(4.0)]);
+        let levels: Vec<Option<i32>> = df
+            .column("temp_quality")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(levels, vec![Some(2), Some(0), Some(1), Some(2)]);
+        assert_eq!(df.column("flow").unwrap().null_count(), 0);
+        assert_eq!(result.get_tag(QUALITY_SUFFIX_TAG), Some("_quality"));
+    }
+
+    #[test]
+    fn test_quality_carried_through_fill_and_buckets() {
+        use crate::config::{AggMethod, FillMethod};
+        use crate::operations::{CalendarBucketOperation, FillNullOperation};
+
+        let filtered = QualityFilterOperation::new(QualityScheme::OpcDa)
+            .execute(make_data())
+            .unwrap();
+        let filled = FillNullOperation::new(FillMethod::Forward, Some(vec!["temp".to_string()]))
+            .execute(filtered)
+            .unwrap();
+        let levels: Vec<Option<i32>> = filled
+            .dataframe()
+            .column("temp_quality")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(levels, vec![Some(2), Some(1), Some(1), Some(2)]);
+
+        let bucketed =
+            CalendarBucketOperation::new("UTC", AggMethod::Mean, Some(vec!["temp".to_string()]))
+                .unwrap()
+                .execute(filled)
+                .unwrap();
+        let df = bucketed.dataframe();
+        assert_eq!(df.column("temp").unwrap().f64().unwrap().get(0), Some(2.25));
+        assert_eq!(
+            df.column("temp_quality").unwrap().i32().unwrap().get(0),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_classify_schemes() {
+        let ua = Series::new("q".into(), &[0i64, 0x4000_0000, 0x8000_0000]);
+        let levels: Vec<Option<i32>> = QualityScheme::OpcUa
+            .classify(&ua)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(levels, vec![Some(2), Some(1), Some(0)]);
+
+        let pi = Series::new(
+            "q".into(),
+            &[
+                Some("Good"),
+                Some("Questionable"),
+                Some("I/O Timeout"),
+                None,
+            ],
+        );
+        let levels: Vec<Option<i32>> = QualityScheme::Label
+            .classify(&pi)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(levels, vec![Some(2), Some(1), Some(0), Some(0)]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Sentinel value replacement
+//!
+//! Historian exports usually encode missing data as magic values: `-9999`,
+//! `65535` or status text such as `"Bad"` or `"I/O Timeout"` mixed into numeric
+//! columns. `ReplaceSentinelsOperation` turns them into nulls so later cleaning
+//! steps (fill, validate, outlier handling) see real gaps instead of outliers.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Value standing for "missing"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SentinelValue {
+    Number(f64),
+    Text(String),
+}
+
+impl From<f64> for SentinelValue {
+    fn from(value: f64) -> Self {
+        SentinelValue::Number(value)
+    }
+}
+
+impl From<&str> for SentinelValue {
+    fn from(value: &str) -> Self {
+        SentinelValue::Text(value.to_string())
+    }
+}
+
+/// Replace sentinels operation - null out magic "missing" values
+///
+/// Numeric columns match numeric sentinels. String columns match text sentinels
+/// exactly and numeric sentinels by value (`"-9999.0"` matches `-9999`); when every
+/// remaining value of a string column is numeric, the column is cast to Float64.
+pub struct ReplaceSentinelsOperation {
+    default: Vec<SentinelValue>,
+    per_column: BTreeMap<String, Vec<SentinelValue>>,
+    cast_numeric: bool,
+}
+
+impl ReplaceSentinelsOperation {
+    /// Replace `sentinels` in every feature column
+    pub fn new(sentinels: Vec<SentinelValue>) -> Self {
+        Self {
+            default: sentinels,
+            per_column: BTreeMap::new(),
+            cast_numeric: true,
+        }
+    }
+
+    /// Use `sentinels` for `column` instead of the default list
+    pub fn with_column(mut self, column: &str, sentinels: Vec<SentinelValue>) -> Self {
+        self.per_column.insert(column.to_string(), sentinels);
+        self
+    }
+
+    /// Cast string columns to Float64 once only numbers remain (default true)
+    pub fn with_cast_numeric(mut self, cast_numeric: bool) -> Self {
+        self.cast_numeric = cast_numeric;
+        self
+    }
+
+    /// Null out sentinels in one column; returns the new column and the count replaced
+    fn replace(&self, series: &Series, sentinels: &[SentinelValue]) -> Result<(Series, usize)> {
+        let numbers: Vec<f64> = sentinels
+            .iter()
+            .filter_map(|s| match s {
+                SentinelValue::Number(n) => Some(*n),
+                SentinelValue::Text(_) => None,
+            })
+            .collect();
+        let is_sentinel: BooleanChunked = if series.dtype() == &DataType::String {
+            series
+                .str()?
+                .into_iter()
+                .map(|v| {
+                    v.is_some_and(|v| {
+                        sentinels.iter().any(|s| match s {
+                            SentinelValue::Text(text) => v == text,
+                            SentinelValue::Number(n) => v.trim().parse::<f64>() == Ok(*n),
+                        })
+                    })
+                })
+                .collect()
+        } else if series.dtype().is_primitive_numeric() {
+            series
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .map(|v| v.is_some_and(|v| numbers.contains(&v)))
+                .collect()
+        } else {
+            return Ok((series.clone(), 0));
+        };
+
+        let replaced = is_sentinel.sum().unwrap_or(0) as usize;
+        let nulls = Series::full_null(series.name().clone(), series.len(), series.dtype());
+        let mut result = series.zip_with(&!is_sentinel, &nulls)?;
+        if self.cast_numeric
+            && result.dtype() == &DataType::String
+            && let Ok(numeric) = result.strict_cast(&DataType::Float64)
+        {
+            result = numeric;
+        }
+        Ok((result, replaced))
+    }
+
+    fn run(&self, data: TimeSeriesData, mut ctx: Option<&mut OpContext>) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        for col_name in data.feature_columns() {
+            let sentinels = self.per_column.get(col_name).unwrap_or(&self.default);
+            if sentinels.is_empty() {
+                continue;
+            }
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            let (series, replaced) = self.replace(&series, sentinels)?;
+            df.replace(col_name, series)?;
+            if let Some(ctx) = ctx.as_deref_mut() {
+                ctx.record_metric(&format!("sentinels.{}", col_name), replaced as f64);
+            }
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+}
+
+impl Operation for ReplaceSentinelsOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        "replace_sentinels"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.per_column.keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_numeric_and_text_sentinels() {
+        let times: Vec<i64> = (0..4).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), [20.5, -9999.0, 21.0, 65535.0]).into(),
+            Series::new("flow".into(), ["12.5", "Bad", "I/O Timeout", "-9999"]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = ReplaceSentinelsOperation::new(vec![(-9999.0).into(), 65535.0.into()])
+            .with_column(
+                "flow",
+                vec!["Bad".into(), "I/O Timeout".into(), (-9999.0).into()],
+            );
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        let temp: Vec<Option<f64>> = df
+            .column("temp")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(temp, vec![Some(20.5), None, Some(21.0), None]);
+        let flow: Vec<Option<f64>> = df
+            .column("flow")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(flow, vec![Some(12.5), None, None, None]);
+
+        let (_, metrics) = ctx.take_diagnostics();
+        assert_eq!(metrics["sentinels.flow"], 3.0);
+    }
+}
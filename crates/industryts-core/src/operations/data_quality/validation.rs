@@ -0,0 +1,694 @@
+//! Declarative data validation rules
+//!
+//! A `ValidationRules` set describes expectations per column (dtype, value range,
+//! null ratio, sampling gaps, monotonicity, uniqueness, allowed values).
+//! `ValidateOperation` evaluates the whole rule set and produces a
+//! `ValidationReport`, then applies its `ValidationPolicy`: fail the pipeline,
+//! warn through the execution context, or drop the offending rows.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::utils::parse_duration;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+/// Validation rules keyed by column name
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ValidationRules {
+    /// Rules per column (the time column may be listed as well)
+    #[serde(default)]
+    pub columns: BTreeMap<String, ColumnRules>,
+}
+
+/// Expectations for a single column
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ColumnRules {
+    /// Expected dtype ("float64", "int64", "string", "bool", "numeric", "datetime", ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dtype: Option<String>,
+    /// Minimum allowed value (inclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Maximum allowed value (inclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Maximum fraction of null values in [0, 1]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_null_ratio: Option<f64>,
+    /// Maximum time between consecutive non-null observations (e.g. "5min")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_gap: Option<String>,
+    /// Required ordering of non-null values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monotonic: Option<Monotonicity>,
+    /// Exhaustive list of allowed values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_values: Option<Vec<AllowedValue>>,
+    /// Reject repeated non-null values (duplicate timestamps on the time column)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+}
+
+/// What `ValidateOperation` does when rules fail
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationPolicy {
+    /// Fail the pipeline
+    #[default]
+    Fail,
+    /// Pass the data through, recording the failures as warnings and tags
+    Warn,
+    /// Drop rows violating row-level rules (range, allowed values, monotonicity,
+    /// uniqueness); rules still failing on the remaining rows fail the pipeline
+    Drop,
+}
+
+/// Monotonicity requirement
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Monotonicity {
+    Increasing,
+    Decreasing,
+    StrictlyIncreasing,
+    StrictlyDecreasing,
+}
+
+/// A literal value accepted by an `allowed_values` rule
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum AllowedValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl AllowedValue {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            AllowedValue::Int(v) => Some(*v as f64),
+            AllowedValue::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AllowedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AllowedValue::Bool(v) => write!(f, "{}", v),
+            AllowedValue::Int(v) => write!(f, "{}", v),
+            AllowedValue::Float(v) => write!(f, "{}", v),
+            AllowedValue::Str(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Outcome of a single rule check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleResult {
+    /// Column the rule applies to
+    pub column: String,
+    /// Rule name (e.g. "range", "max_null_ratio")
+    pub rule: String,
+    /// Whether the rule passed
+    pub passed: bool,
+    /// Human-readable detail of what was observed
+    pub detail: String,
+}
+
+impl RuleResult {
+    fn new(column: &str, rule: &str, passed: bool, detail: String) -> Self {
+        Self {
+            column: column.to_string(),
+            rule: rule.to_string(),
+            passed,
+            detail,
+        }
+    }
+}
+
+/// Pass/fail report for a full rule set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Results of every evaluated rule
+    pub results: Vec<RuleResult>,
+}
+
+impl ValidationReport {
+    /// Whether all rules passed
+    pub fn is_passed(&self) -> bool {
+        self.results.iter().all(|r| r.passed)
+    }
+
+    /// Failed rules only
+    pub fn failures(&self) -> Vec<&RuleResult> {
+        self.results.iter().filter(|r| !r.passed).collect()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failures = self.failures();
+        write!(
+            f,
+            "{} of {} rules failed",
+            failures.len(),
+            self.results.len()
+        )?;
+        for failure in failures {
+            write!(
+                f,
+                "; {}.{}: {}",
+                failure.column, failure.rule, failure.detail
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Check whether a dtype matches a rule dtype name
+fn dtype_matches(expected: &str, dtype: &DataType) -> bool {
+    match expected.to_lowercase().as_str() {
+        "numeric" => dtype.is_primitive_numeric(),
+        "float" => dtype.is_float(),
+        "integer" | "int" => dtype.is_integer(),
+        "float64" | "f64" => *dtype == DataType::Float64,
+        "float32" | "f32" => *dtype == DataType::Float32,
+        "int64" | "i64" => *dtype == DataType::Int64,
+        "int32" | "i32" => *dtype == DataType::Int32,
+        "bool" | "boolean" => *dtype == DataType::Boolean,
+        "string" | "str" | "utf8" => *dtype == DataType::String,
+        "datetime" => matches!(dtype, DataType::Datetime(_, _)),
+        "date" => *dtype == DataType::Date,
+        other => dtype.to_string() == other,
+    }
+}
+
+/// Values of `series` as f64, timestamps in milliseconds for the time column
+fn numeric_values(data: &TimeSeriesData, series: &Series) -> Result<Vec<Option<f64>>> {
+    if series.name().as_str() == data.time_column() {
+        return Ok(data
+            .timestamps_ms()?
+            .into_iter()
+            .map(|v| v.map(|v| v as f64))
+            .collect());
+    }
+    Ok(series
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .collect())
+}
+
+/// Validate operation - evaluate a set of declarative rules
+///
+/// Feature columns with a plausible range in their `min` and `max` properties
+/// (see `TagMetadata`) are range-checked too, unless their rules set a range.
+pub struct ValidateOperation {
+    rules: ValidationRules,
+    policy: ValidationPolicy,
+}
+
+impl ValidateOperation {
+    pub fn new(rules: ValidationRules) -> Self {
+        Self {
+            rules,
+            policy: ValidationPolicy::Fail,
+        }
+    }
+
+    /// Handling of failed rules (default: fail the pipeline)
+    pub fn with_policy(mut self, policy: ValidationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Record failures in the output tags instead of failing the pipeline
+    pub fn with_warn_only(mut self, warn_only: bool) -> Self {
+        self.policy = if warn_only {
+            ValidationPolicy::Warn
+        } else {
+            ValidationPolicy::Fail
+        };
+        self
+    }
+
+    /// Get the rule set
+    pub fn rules(&self) -> &ValidationRules {
+        &self.rules
+    }
+
+    /// The rule set with the column ranges of `data` added
+    fn effective_rules(&self, data: &TimeSeriesData) -> Cow<'_, ValidationRules> {
+        let mut rules = Cow::Borrowed(&self.rules);
+        for column in data.feature_columns() {
+            let (min, max) = data.column_range(column);
+            if min.is_none() && max.is_none() {
+                continue;
+            }
+            let listed = self.rules.columns.get(column);
+            if listed.is_some_and(|r| r.min.is_some() || r.max.is_some()) {
+                continue;
+            }
+            let entry = rules.to_mut().columns.entry(column.clone()).or_default();
+            entry.min = min;
+            entry.max = max;
+        }
+        rules
+    }
+
+    /// Evaluate all rules against the data
+    pub fn evaluate(&self, data: &TimeSeriesData) -> Result<ValidationReport> {
+        let mut report = ValidationReport::default();
+        let df = data.dataframe();
+
+        for (name, rules) in &self.effective_rules(data).columns {
+            let series = match df.column(name) {
+                Ok(column) => column.as_materialized_series(),
+                Err(_) => {
+                    report.results.push(RuleResult::new(
+                        name,
+                        "exists",
+                        false,
+                        "column is missing".to_string(),
+                    ));
+                    continue;
+                }
+            };
+            let is_time = name == data.time_column();
+
+            if let Some(expected) = &rules.dtype {
+                let passed = dtype_matches(expected, series.dtype());
+                report.results.push(RuleResult::new(
+                    name,
+                    "dtype",
+                    passed,
+                    format!("expected {}, found {}", expected, series.dtype()),
+                ));
+            }
+
+            if rules.min.is_some() || rules.max.is_some() {
+                let values = series.cast(&DataType::Float64)?;
+                let min = rules.min.unwrap_or(f64::NEG_INFINITY);
+                let max = rules.max.unwrap_or(f64::INFINITY);
+                let violations = values
+                    .f64()?
+                    .into_iter()
+                    .flatten()
+                    .filter(|v| *v < min || *v > max)
+                    .count();
+                report.results.push(RuleResult::new(
+                    name,
+                    "range",
+                    violations == 0,
+                    format!("{} values outside [{}, {}]", violations, min, max),
+                ));
+            }
+
+            if let Some(max_ratio) = rules.max_null_ratio {
+                let ratio = if series.is_empty() {
+                    0.0
+                } else {
+                    series.null_count() as f64 / series.len() as f64
+                };
+                report.results.push(RuleResult::new(
+                    name,
+                    "max_null_ratio",
+                    ratio <= max_ratio,
+                    format!("null ratio {:.4} (limit {})", ratio, max_ratio),
+                ));
+            }
+
+            if let Some(max_gap) = &rules.max_gap {
+                let limit_ms = parse_duration(max_gap)?.as_millis() as i64;
+                let times = data.timestamps_ms()?;
+                let observed: Vec<i64> = times
+                    .into_iter()
+                    .zip(&series.is_not_null())
+                    .filter_map(|(t, valid)| if valid == Some(true) { t } else { None })
+                    .collect();
+                let largest = observed.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+                report.results.push(RuleResult::new(
+                    name,
+                    "max_gap",
+                    largest <= limit_ms,
+                    format!("largest gap {} ms (limit {})", largest, max_gap),
+                ));
+            }
+
+            if let Some(monotonic) = rules.monotonic {
+                let values: Vec<f64> = if is_time {
+                    data.timestamps_ms()?
+                        .into_iter()
+                        .flatten()
+                        .map(|v| v as f64)
+                        .collect()
+                } else {
+                    series
+                        .cast(&DataType::Float64)?
+                        .f64()?
+                        .into_iter()
+                        .flatten()
+                        .collect()
+                };
+                let violations = values
+                    .windows(2)
+                    .filter(|w| match monotonic {
+                        Monotonicity::Increasing => w[1] < w[0],
+                        Monotonicity::Decreasing => w[1] > w[0],
+                        Monotonicity::StrictlyIncreasing => w[1] <= w[0],
+                        Monotonicity::StrictlyDecreasing => w[1] >= w[0],
+                    })
+                    .count();
+                report.results.push(RuleResult::new(
+                    name,
+                    "monotonic",
+                    violations == 0,
+                    format!("{} ordering violations", violations),
+                ));
+            }
+
+            if rules.unique {
+                let values = series.cast(&DataType::String)?;
+                let mut seen = HashSet::new();
+                let duplicates = values
+                    .str()?
+                    .into_iter()
+                    .flatten()
+                    .filter(|v| !seen.insert(*v))
+                    .count();
+                report.results.push(RuleResult::new(
+                    name,
+                    "unique",
+                    duplicates == 0,
+                    format!("{} repeated values", duplicates),
+                ));
+            }
+
+            if let Some(allowed) = &rules.allowed_values {
+                let violations = if series.dtype().is_primitive_numeric() {
+                    let allowed: Vec<f64> = allowed.iter().filter_map(|v| v.as_f64()).collect();
+                    series
+                        .cast(&DataType::Float64)?
+                        .f64()?
+                        .into_iter()
+                        .flatten()
+                        .filter(|v| !allowed.contains(v))
+                        .count()
+                } else {
+                    let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                    series
+                        .cast(&DataType::String)?
+                        .str()?
+                        .into_iter()
+                        .flatten()
+                        .filter(|v| !allowed.iter().any(|a| a == v))
+                        .count()
+                };
+                report.results.push(RuleResult::new(
+                    name,
+                    "allowed_values",
+                    violations == 0,
+                    format!("{} values not in allowed set", violations),
+                ));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Rows violating a row-level rule
+    ///
+    /// Monotonicity is judged against the last non-violating value, so the kept
+    /// rows are ordered; for uniqueness the first occurrence is kept.
+    pub fn violating_rows(&self, data: &TimeSeriesData) -> Result<Vec<bool>> {
+        let df = data.dataframe();
+        let mut violating = vec![false; df.height()];
+        for (name, rules) in &self.effective_rules(data).columns {
+            let Ok(column) = df.column(name) else {
+                continue;
+            };
+            let series = column.as_materialized_series();
+
+            if rules.min.is_some() || rules.max.is_some() {
+                let min = rules.min.unwrap_or(f64::NEG_INFINITY);
+                let max = rules.max.unwrap_or(f64::INFINITY);
+                for (row, value) in numeric_values(data, series)?.into_iter().enumerate() {
+                    if value.is_some_and(|v| v < min || v > max) {
+                        violating[row] = true;
+                    }
+                }
+            }
+
+            if let Some(monotonic) = rules.monotonic {
+                let mut last: Option<f64> = None;
+                for (row, value) in numeric_values(data, series)?.into_iter().enumerate() {
+                    let Some(value) = value else {
+                        continue;
+                    };
+                    let ordered = last.is_none_or(|last| match monotonic {
+                        Monotonicity::Increasing => value >= last,
+                        Monotonicity::Decreasing => value <= last,
+                        Monotonicity::StrictlyIncreasing => value > last,
+                        Monotonicity::StrictlyDecreasing => value < last,
+                    });
+                    if ordered {
+                        last = Some(value);
+                    } else {
+                        violating[row] = true;
+                    }
+                }
+            }
+
+            if rules.unique {
+                let values = series.cast(&DataType::String)?;
+                let mut seen = HashSet::new();
+                for (row, value) in values.str()?.into_iter().enumerate() {
+                    if value.is_some_and(|v| !seen.insert(v)) {
+                        violating[row] = true;
+                    }
+                }
+            }
+
+            if let Some(allowed) = &rules.allowed_values {
+                if series.dtype().is_primitive_numeric() {
+                    let allowed: Vec<f64> = allowed.iter().filter_map(|v| v.as_f64()).collect();
+                    for (row, value) in numeric_values(data, series)?.into_iter().enumerate() {
+                        if value.is_some_and(|v| !allowed.contains(&v)) {
+                            violating[row] = true;
+                        }
+                    }
+                } else {
+                    let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                    let values = series.cast(&DataType::String)?;
+                    for (row, value) in values.str()?.into_iter().enumerate() {
+                        if value.is_some_and(|v| !allowed.iter().any(|a| a == v)) {
+                            violating[row] = true;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(violating)
+    }
+
+    fn run(
+        &self,
+        mut data: TimeSeriesData,
+        mut ctx: Option<&mut OpContext>,
+    ) -> Result<TimeSeriesData> {
+        let mut report = self.evaluate(&data)?;
+
+        if !report.is_passed() && self.policy == ValidationPolicy::Drop {
+            let violating = self.violating_rows(&data)?;
+            let dropped = violating.iter().filter(|v| **v).count();
+            if dropped > 0 {
+                let keep: BooleanChunked = violating.into_iter().map(|v| !v).collect();
+                let df = data.dataframe().filter(&keep)?;
+                data = TimeSeriesData::with_metadata(df, data.metadata().clone())?;
+                report = self.evaluate(&data)?;
+            }
+            if let Some(ctx) = ctx.as_deref_mut() {
+                ctx.record_metric("validate.dropped_rows", dropped as f64);
+                if dropped > 0 {
+                    ctx.warn(format!(
+                        "dropped {} rows violating validation rules",
+                        dropped
+                    ));
+                }
+            }
+        }
+
+        if let Some(ctx) = ctx {
+            ctx.record_metric("validate.failures", report.failures().len() as f64);
+            if self.policy == ValidationPolicy::Warn {
+                for failure in report.failures() {
+                    ctx.warn(format!(
+                        "validation failed: {}.{}: {}",
+                        failure.column, failure.rule, failure.detail
+                    ));
+                }
+            }
+        }
+
+        if !report.is_passed() && self.policy != ValidationPolicy::Warn {
+            return Err(IndustrytsError::ValidationError(report.to_string()));
+        }
+
+        data.add_tag(
+            "validation.passed".to_string(),
+            report.is_passed().to_string(),
+        );
+        data.add_tag(
+            "validation.failures".to_string(),
+            report.failures().len().to_string(),
+        );
+        Ok(data)
+    }
+}
+
+impl Operation for ValidateOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        "validate"
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.policy == ValidationPolicy::Drop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..4).map(|i| 1704067200000i64 + i * 60_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), &[Some(20.0), None, Some(22.0), Some(95.0)]).into(),
+            Series::new("state".into(), &["RUN", "RUN", "STOP", "FAULT"]).into(),
+        ])
+        .unwrap();
+
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_validation_rules_report() {
+        let rules: ValidationRules = toml::from_str(
+            r#"
+            [columns.time]
+            monotonic = "strictly_increasing"
+            max_gap = "1min"
+
+            [columns.temp]
+            dtype = "float64"
+            min = 0.0
+            max = 50.0
+            max_null_ratio = 0.5
+
+            [columns.state]
+            allowed_values = ["RUN", "STOP"]
+            "#,
+        )
+        .unwrap();
+
+        let report = ValidateOperation::new(rules)
+            .evaluate(&sample_data())
+            .unwrap();
+
+        let failed: Vec<&str> = report.failures().iter().map(|r| r.rule.as_str()).collect();
+        assert_eq!(failed, vec!["allowed_values", "range"]);
+        assert!(!report.is_passed());
+    }
+
+    #[test]
+    fn test_validate_operation_warn_only() {
+        let mut rules = ValidationRules::default();
+        rules
+            .columns
+            .insert("missing".to_string(), ColumnRules::default());
+
+        let op = ValidateOperation::new(rules.clone());
+        assert!(op.execute(sample_data()).is_err());
+
+        let op = ValidateOperation::new(rules).with_warn_only(true);
+        let result = op.execute(sample_data()).unwrap();
+        assert_eq!(result.get_tag("validation.passed"), Some("false"));
+    }
+
+    #[test]
+    fn test_validation_policies() {
+        // Duplicate timestamp and an out-of-range reading
+        let times: Vec<i64> = [0i64, 1, 1, 2, 3].iter().map(|m| m * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), &[20.0, 21.0, 21.5, 95.0, 22.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+        let rules: ValidationRules = toml::from_str(
+            r#"
+            [columns.time]
+            unique = true
+            monotonic = "increasing"
+
+            [columns.temp]
+            max = 50.0
+            "#,
+        )
+        .unwrap();
+
+        let report = ValidateOperation::new(rules.clone())
+            .evaluate(&data)
+            .unwrap();
+        let failed: Vec<&str> = report.failures().iter().map(|r| r.rule.as_str()).collect();
+        assert_eq!(failed, vec!["range", "unique"]);
+
+        let op = ValidateOperation::new(rules.clone()).with_policy(ValidationPolicy::Warn);
+        let mut ctx = OpContext::new();
+        let warned = op.execute_with_context(data.clone(), &mut ctx).unwrap();
+        assert_eq!(warned.len(), 5);
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(metrics["validate.failures"], 2.0);
+
+        let op = ValidateOperation::new(rules).with_policy(ValidationPolicy::Drop);
+        assert!(op.removes_rows());
+        let mut ctx = OpContext::new();
+        let cleaned = op.execute_with_context(data, &mut ctx).unwrap();
+        let temps: Vec<Option<f64>> = cleaned
+            .dataframe()
+            .column("temp")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(temps, [Some(20.0), Some(21.0), Some(22.0)]);
+        assert_eq!(cleaned.get_tag("validation.passed"), Some("true"));
+        assert_eq!(ctx.take_diagnostics().1["validate.dropped_rows"], 2.0);
+    }
+}
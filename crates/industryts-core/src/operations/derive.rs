@@ -0,0 +1,579 @@
+//! Derived columns from formulas
+//!
+//! `DeriveOperation` evaluates formulas such as `power = voltage * current` or
+//! `t_f = t_c * 1.8 + 32` over existing columns, so unit conversions and simple
+//! KPIs need no operation of their own. A formula assigns an expression of
+//! columns, numbers, `+ - * / ^`, parentheses and the functions `abs`, `sqrt`,
+//! `exp`, `ln`, `log10`, `min(a, b)` and `max(a, b)` to a column. Column names
+//! may contain dots (e.g. `TI101.PV`); other names are quoted in backticks, e.g.
+//! `` `flow rate` ``.
+//!
+//! Formulas are evaluated in order, so later ones may use earlier results, and
+//! assigning to an existing column replaces it. Addition, subtraction,
+//! multiplication and division are checked by an `ArithmeticPolicy`: integer
+//! results keep the operands' type (Int64 when they differ) and overflow at its
+//! range, and division by zero is nulled, clamped or rejected rather than
+//! producing infinities. Powers and functions are computed as floats, with
+//! results outside the function's domain (e.g. the square root of a negative
+//! value) set to null. Nulls propagate.
+
+use crate::core::{
+    ArithmeticPolicy, ArithmeticViolations, BinaryOp, OpContext, Operation, TimeSeriesData,
+};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+
+/// Parsed expression of a formula
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Column(String),
+    Int(i64),
+    Float(f64),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log10" => Function::Log10,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Node {
+    /// Columns read by the expression
+    fn columns(&self, out: &mut Vec<String>) {
+        match self {
+            Node::Column(name) => {
+                if !out.contains(name) {
+                    out.push(name.clone());
+                }
+            }
+            Node::Int(_) | Node::Float(_) => {}
+            Node::Neg(inner) => inner.columns(out),
+            Node::Binary(_, lhs, rhs) | Node::Pow(lhs, rhs) => {
+                lhs.columns(out);
+                rhs.columns(out);
+            }
+            Node::Call(_, args) => args.iter().for_each(|a| a.columns(out)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let invalid = |message: String| IndustrytsError::ConfigError(message);
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut float = false;
+            while let Some(&(i, c)) = chars.peek() {
+                // A sign continues a number only right after its exponent marker
+                let exponent_sign = (c == '+' || c == '-') && text[start..i].ends_with(['e', 'E']);
+                if c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || exponent_sign {
+                    float |= !c.is_ascii_digit();
+                    end = i + 1;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = &text[start..end];
+            let token = if float {
+                number.parse().map(Token::Float).ok()
+            } else {
+                number.parse().map(Token::Int).ok()
+            };
+            tokens.push(token.ok_or_else(|| invalid(format!("Invalid number: {}", number)))?);
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' || c == '.' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(text[start..end].to_string()));
+        } else if c == '`' {
+            chars.next();
+            let name: String = chars
+                .by_ref()
+                .map(|(_, c)| c)
+                .take_while(|&c| c != '`')
+                .collect();
+            if !text[start + 1..].contains('`') {
+                return Err(invalid(format!("Unterminated column name in: {}", text)));
+            }
+            tokens.push(Token::Ident(name));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(invalid(format!("Unexpected '{}' in formula: {}", c, text)));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of one expression
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    text: &'a str,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> IndustrytsError {
+        IndustrytsError::ConfigError(format!("{} in formula: {}", message, self.text))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", symbol)))
+        }
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Node> {
+        let mut expr = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                BinaryOp::Add
+            } else if self.eat('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(expr);
+            };
+            expr = Node::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Node> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                BinaryOp::Mul
+            } else if self.eat('/') {
+                BinaryOp::Div
+            } else {
+                return Ok(expr);
+            };
+            expr = Node::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat('-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    /// power := atom ('^' unary)?, right-associative
+    fn power(&mut self) -> Result<Node> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(Node::Pow(Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    /// atom := number | column | function '(' arguments ')' | '(' expression ')'
+    fn atom(&mut self) -> Result<Node> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("Unexpected end"));
+        };
+        self.position += 1;
+        match token {
+            Token::Int(value) => Ok(Node::Int(value)),
+            Token::Float(value) => Ok(Node::Float(value)),
+            Token::Symbol('(') => {
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::Symbol('(')) => {
+                let function = Function::parse(&name)
+                    .ok_or_else(|| self.error(&format!("Unknown function '{}'", name)))?;
+                self.position += 1;
+                let mut args = vec![self.expression()?];
+                while self.eat(',') {
+                    args.push(self.expression()?);
+                }
+                self.expect(')')?;
+                if args.len() != function.arity() {
+                    return Err(self.error(&format!(
+                        "'{}' takes {} argument(s)",
+                        name,
+                        function.arity()
+                    )));
+                }
+                Ok(Node::Call(function, args))
+            }
+            Token::Ident(name) => Ok(Node::Column(name)),
+            Token::Symbol(c) => Err(self.error(&format!("Unexpected '{}'", c))),
+        }
+    }
+}
+
+/// One parsed `target = expression` formula
+#[derive(Debug, Clone)]
+pub(crate) struct Formula {
+    target: String,
+    expr: Node,
+}
+
+impl Formula {
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        let (target, expression) = text.split_once('=').ok_or_else(|| {
+            IndustrytsError::ConfigError(format!("Formula needs 'name = expression': {}", text))
+        })?;
+        let target = target.trim().trim_matches('`').to_string();
+        if target.is_empty() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Formula needs a target column: {}",
+                text
+            )));
+        }
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            text,
+        };
+        let expr = parser.expression()?;
+        if parser.position < tokens.len() {
+            return Err(parser.error("Unexpected trailing input"));
+        }
+        Ok(Self { target, expr })
+    }
+
+    /// Assigned column
+    pub(crate) fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Columns read by the expression, in order of first use
+    pub(crate) fn inputs(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        self.expr.columns(&mut columns);
+        columns
+    }
+}
+
+/// Evaluation of formulas over a dataframe, counting arithmetic violations
+struct Evaluator<'a> {
+    df: &'a DataFrame,
+    /// Derived column, named in arithmetic errors
+    target: &'a str,
+    policy: ArithmeticPolicy,
+    violations: ArithmeticViolations,
+}
+
+impl Evaluator<'_> {
+    fn eval(&mut self, expr: &Node) -> Result<Series> {
+        let height = self.df.height();
+        Ok(match expr {
+            Node::Column(name) => {
+                let column = self.df.column(name)?;
+                if !column.dtype().is_primitive_numeric() {
+                    return Err(IndustrytsError::InvalidOperation(format!(
+                        "Cannot derive from non-numeric column: {}",
+                        name
+                    )));
+                }
+                column.as_materialized_series().clone()
+            }
+            Node::Int(value) => Series::new("literal".into(), vec![*value; height]),
+            Node::Float(value) => Series::new("literal".into(), vec![*value; height]),
+            Node::Neg(inner) => {
+                let value = self.eval(inner)?;
+                let zero = Series::new("literal".into(), vec![0i64; height]);
+                self.binary(BinaryOp::Sub, &zero, &value)?
+            }
+            Node::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                self.binary(*op, &lhs, &rhs)?
+            }
+            Node::Pow(base, exponent) => {
+                let base = self.eval(base)?;
+                let exponent = self.eval(exponent)?;
+                float_map2(&base, &exponent, f64::powf)?
+            }
+            Node::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| self.eval(a))
+                    .collect::<Result<Vec<_>>>()?;
+                match function {
+                    Function::Min => float_map2(&args[0], &args[1], f64::min)?,
+                    Function::Max => float_map2(&args[0], &args[1], f64::max)?,
+                    Function::Abs => float_map(&args[0], f64::abs)?,
+                    Function::Sqrt => float_map(&args[0], f64::sqrt)?,
+                    Function::Exp => float_map(&args[0], f64::exp)?,
+                    Function::Ln => float_map(&args[0], f64::ln)?,
+                    Function::Log10 => float_map(&args[0], f64::log10)?,
+                }
+            }
+        })
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: &Series, rhs: &Series) -> Result<Series> {
+        let lhs = lhs.clone().with_name(self.target.into());
+        let (result, violations) = self.policy.apply(op, &lhs, rhs)?;
+        self.violations.overflows += violations.overflows;
+        self.violations.divisions_by_zero += violations.divisions_by_zero;
+        Ok(result)
+    }
+}
+
+/// Apply `f` to finite values; non-finite results become null
+fn float_map(values: &Series, f: impl Fn(f64) -> f64) -> Result<Series> {
+    let values = values.cast(&DataType::Float64)?;
+    let result: Float64Chunked = values
+        .f64()?
+        .into_iter()
+        .map(|v| v.map(&f).filter(|r| r.is_finite()))
+        .collect();
+    Ok(result.into_series())
+}
+
+fn float_map2(lhs: &Series, rhs: &Series, f: impl Fn(f64, f64) -> f64) -> Result<Series> {
+    let lhs = lhs.cast(&DataType::Float64)?;
+    let rhs = rhs.cast(&DataType::Float64)?;
+    let result: Float64Chunked = lhs
+        .f64()?
+        .into_iter()
+        .zip(rhs.f64()?)
+        .map(|(a, b)| Some(f(a?, b?)).filter(|r| r.is_finite()))
+        .collect();
+    Ok(result.into_series())
+}
+
+/// Derive operation - add columns computed from formulas
+pub struct DeriveOperation {
+    formulas: Vec<Formula>,
+    policy: ArithmeticPolicy,
+}
+
+impl DeriveOperation {
+    /// Parse formulas such as `"power = voltage * current"`
+    pub fn new(formulas: &[String]) -> Result<Self> {
+        if formulas.is_empty() {
+            return Err(IndustrytsError::ConfigError(
+                "Derive needs at least one formula".to_string(),
+            ));
+        }
+        Ok(Self {
+            formulas: formulas
+                .iter()
+                .map(|f| Formula::parse(f))
+                .collect::<Result<_>>()?,
+            policy: ArithmeticPolicy::Null,
+        })
+    }
+
+    /// Handling of overflow and division by zero (default null)
+    pub fn with_policy(mut self, policy: ArithmeticPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Evaluate the formulas; returns the data and the violations per derived column
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, Vec<(String, usize)>)> {
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        let mut violations = Vec::new();
+        for formula in &self.formulas {
+            let mut evaluator = Evaluator {
+                df: &df,
+                target: &formula.target,
+                policy: self.policy,
+                violations: ArithmeticViolations::default(),
+            };
+            let series = evaluator
+                .eval(&formula.expr)?
+                .with_name(formula.target.as_str().into());
+            let count = evaluator.violations.total();
+            df.with_column(series)?;
+            if count > 0 {
+                violations.push((formula.target.clone(), count));
+            }
+            if formula.target != metadata.time_column
+                && !metadata.group_columns.contains(&formula.target)
+                && !metadata.feature_columns.contains(&formula.target)
+            {
+                metadata.feature_columns.push(formula.target.clone());
+            }
+        }
+        Ok((TimeSeriesData::with_metadata(df, metadata)?, violations))
+    }
+}
+
+impl Operation for DeriveOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, violations) = self.run(data)?;
+        let total: usize = violations.iter().map(|(_, count)| count).sum();
+        ctx.record_metric("derive.violations", total as f64);
+        for (column, count) in violations {
+            ctx.warn(format!(
+                "{} overflow(s) or division(s) by zero in {}",
+                count, column
+            ));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "derive"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut derived: Vec<String> = Vec::new();
+        let mut required = Vec::new();
+        for formula in &self.formulas {
+            let mut columns = Vec::new();
+            formula.expr.columns(&mut columns);
+            for column in columns {
+                if !derived.contains(&column) && !required.contains(&column) {
+                    required.push(column);
+                }
+            }
+            derived.push(formula.target.clone());
+        }
+        required
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let mut added: Vec<String> = Vec::new();
+        for formula in &self.formulas {
+            if !feature_columns.contains(&formula.target) && !added.contains(&formula.target) {
+                added.push(formula.target.clone());
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_derive_formulas() {
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("voltage", [230.0, 231.0, 229.0])
+            .with_column("current", [Some(2.0), None, Some(0.0)])
+            .with_column("count", [i32::MAX, 1, 2])
+            .build()
+            .unwrap();
+        let formulas: Vec<String> = [
+            "power = voltage * current",
+            "`load ratio` = power / (voltage * current) - 1",
+            "t_f = -(2 ^ 3) + sqrt(current) * 1.5e1",
+            "next = count + 1",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let op = DeriveOperation::new(&formulas).unwrap();
+        assert_eq!(op.required_columns(), vec!["voltage", "current", "count"]);
+
+        let result = op.execute(data.clone()).unwrap();
+        let get = |name: &str| {
+            let column = result.dataframe().column(name).unwrap();
+            column
+                .cast(&DataType::Float64)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(get("power").get(0), Some(460.0));
+        assert_eq!(get("power").get(1), None);
+        // Division by zero is nulled by the default policy
+        assert_eq!(get("load ratio").get(0), Some(0.0));
+        assert_eq!(get("load ratio").get(2), None);
+        assert!((get("t_f").get(0).unwrap() - (2f64.sqrt() * 15.0 - 8.0)).abs() < 1e-9);
+        // Mixed integer types are computed as Int64
+        let next = result.dataframe().column("next").unwrap();
+        assert_eq!(next.dtype(), &DataType::Int64);
+        assert_eq!(next.i64().unwrap().get(0), Some(i32::MAX as i64 + 1));
+        assert!(result.feature_columns().contains(&"load ratio".to_string()));
+
+        let strict = DeriveOperation::new(&formulas[..2])
+            .unwrap()
+            .with_policy(ArithmeticPolicy::Error);
+        assert!(strict.execute(data).is_err());
+        assert!(DeriveOperation::new(&["x = voltage *".to_string()]).is_err());
+        assert!(DeriveOperation::new(&["x = foo(voltage)".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Datatype optimization
+//!
+//! Historian exports and CSV loads default to `Int64`, `Float64` and `String`,
+//! which wastes memory on wide datasets of small counters, quantized readings and
+//! repetitive state text. `OptimizeDtypesOperation` downcasts each column to the
+//! smallest dtype that holds its values exactly:
+//!
+//! - integers to the narrowest of `u8`/`u16`/`u32` (non-negative) or `i8`/`i16`/`i32`
+//! - `Float64` to `Float32` when every value survives the round trip (e.g. after
+//!   `QuantizeOperation` with a coarse resolution)
+//! - low-cardinality strings to `Categorical`
+//!
+//! Categorical columns are not treated as state columns by resampling, so the pass
+//! belongs at the end of a pipeline, before export.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+
+/// Default ratio of distinct to non-null values below which strings are categorized
+pub const DEFAULT_MAX_CATEGORY_RATIO: f64 = 0.5;
+
+/// Bit width of an integer dtype
+fn int_bits(dtype: &DataType) -> u32 {
+    match dtype {
+        DataType::Int8 | DataType::UInt8 => 8,
+        DataType::Int16 | DataType::UInt16 => 16,
+        DataType::Int32 | DataType::UInt32 => 32,
+        _ => 64,
+    }
+}
+
+/// Narrowest integer dtype holding `[min, max]`
+fn narrowest_int(min: f64, max: f64) -> Option<DataType> {
+    let candidates: [(DataType, f64, f64); 3] = if min >= 0.0 {
+        [
+            (DataType::UInt8, 0.0, u8::MAX as f64),
+            (DataType::UInt16, 0.0, u16::MAX as f64),
+            (DataType::UInt32, 0.0, u32::MAX as f64),
+        ]
+    } else {
+        [
+            (DataType::Int8, i8::MIN as f64, i8::MAX as f64),
+            (DataType::Int16, i16::MIN as f64, i16::MAX as f64),
+            (DataType::Int32, i32::MIN as f64, i32::MAX as f64),
+        ]
+    };
+    candidates
+        .into_iter()
+        .find(|(_, lo, hi)| min >= *lo && max <= *hi)
+        .map(|(dtype, _, _)| dtype)
+}
+
+/// Smallest safe dtype for `series`, or `None` to keep it as is
+fn optimal_dtype(
+    series: &Series,
+    max_category_ratio: f64,
+    float_downcast: bool,
+) -> Result<Option<DataType>> {
+    let dtype = series.dtype();
+    if dtype.is_integer() {
+        let (Some(min), Some(max)) = (series.min::<f64>()?, series.max::<f64>()?) else {
+            return Ok(None);
+        };
+        Ok(narrowest_int(min, max).filter(|target| int_bits(target) < int_bits(dtype)))
+    } else if dtype == &DataType::Float64 && float_downcast {
+        let values = series.f64()?;
+        let lossless = values.into_no_null_iter().all(|v| {
+            let narrowed = v as f32 as f64;
+            narrowed == v || (v.is_nan() && narrowed.is_nan())
+        });
+        Ok(lossless.then_some(DataType::Float32))
+    } else if dtype == &DataType::String && max_category_ratio > 0.0 {
+        let values = series.drop_nulls();
+        let distinct = values.n_unique()? as f64;
+        let categorize = !values.is_empty() && distinct <= max_category_ratio * values.len() as f64;
+        Ok(categorize.then(|| DataType::from_categories(Categories::global())))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Optimize dtypes operation - downcast columns to the smallest safe dtype
+///
+/// Values are never changed, only their representation. The time column is left
+/// untouched.
+pub struct OptimizeDtypesOperation {
+    columns: Option<Vec<String>>,
+    max_category_ratio: f64,
+    float_downcast: bool,
+}
+
+impl OptimizeDtypesOperation {
+    pub fn new(columns: Option<Vec<String>>) -> Self {
+        Self {
+            columns,
+            max_category_ratio: DEFAULT_MAX_CATEGORY_RATIO,
+            float_downcast: true,
+        }
+    }
+
+    /// Categorize strings with at most this ratio of distinct values (0 disables)
+    pub fn with_max_category_ratio(mut self, ratio: f64) -> Self {
+        self.max_category_ratio = ratio;
+        self
+    }
+
+    /// Allow lossless `Float64` to `Float32` downcasts (default true)
+    pub fn with_float_downcast(mut self, float_downcast: bool) -> Self {
+        self.float_downcast = float_downcast;
+        self
+    }
+}
+
+impl Operation for OptimizeDtypesOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| data.feature_columns().to_vec());
+
+        let mut df = data.dataframe().clone();
+        for col_name in &columns {
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            if let Some(dtype) =
+                optimal_dtype(&series, self.max_category_ratio, self.float_downcast)?
+            {
+                df.replace(col_name, series.cast(&dtype)?)?;
+            }
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let before = data.dataframe().estimated_size();
+        let result = self.execute(data)?;
+        let after = result.dataframe().estimated_size();
+        ctx.record_metric("optimize_dtypes.saved_bytes", before as f64 - after as f64);
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "optimize_dtypes"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrink_dtypes() {
+        let time_series = Series::new(
+            "time".into(),
+            (0..6).map(|i| i * 60_000i64).collect::<Vec<_>>(),
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("count".into(), [0i64, 3, 255, 7, 1, 2]).into(),
+            Series::new("delta".into(), [-5i64, 300, 0, 1, 2, 3]).into(),
+            Series::new("level".into(), [0.5, 1.25, 2.0, -0.75, 3.5, 0.0]).into(),
+            Series::new("flow".into(), [0.1, 1.25, 2.0, -0.75, 3.5, 0.0]).into(),
+            Series::new("state".into(), ["run", "run", "idle", "run", "idle", "run"]).into(),
+            Series::new("batch".into(), ["a", "b", "c", "d", "e", "f"]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut ctx = OpContext::new();
+        let result = OptimizeDtypesOperation::new(None)
+            .execute_with_context(data.clone(), &mut ctx)
+            .unwrap();
+        let dtype = |name: &str| result.dataframe().column(name).unwrap().dtype().clone();
+        assert_eq!(dtype("count"), DataType::UInt8);
+        assert_eq!(dtype("delta"), DataType::Int16);
+        assert_eq!(dtype("level"), DataType::Float32);
+        // 0.1 is not exact in f32
+        assert_eq!(dtype("flow"), DataType::Float64);
+        assert!(matches!(dtype("state"), DataType::Categorical(_, _)));
+        assert_eq!(dtype("batch"), DataType::String);
+        assert!(ctx.take_diagnostics().1["optimize_dtypes.saved_bytes"] > 0.0);
+
+        let shrunk = data.shrink_dtypes().unwrap();
+        assert_eq!(
+            shrunk.dataframe().column("count").unwrap().dtype(),
+            &DataType::UInt8
+        );
+        assert_eq!(
+            shrunk
+                .dataframe()
+                .column("state")
+                .unwrap()
+                .cast(&DataType::String)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(2),
+            Some("idle")
+        );
+    }
+}
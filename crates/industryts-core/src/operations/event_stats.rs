@@ -0,0 +1,342 @@
+//! Statistics of signals around events
+//!
+//! Alarm and trip analysis asks the same questions of every event: where did
+//! the process stand just before it, how far did it swing during it, and how
+//! fast did it recover? `EventWindowStatsOperation` answers them in one table:
+//! for each event of an event-frame table (see `EventFrames`) it aggregates the
+//! selected signals over a window before the event start, over the event itself
+//! and over a window after its end, and outputs one row per event.
+
+use crate::config::AggMethod;
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::labeling::EventFrames;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Name of the event start column of the output
+pub const EVENT_START_COLUMN: &str = "event_start";
+/// Name of the event end column of the output
+pub const EVENT_END_COLUMN: &str = "event_end";
+
+/// Event window statistics operation - one row of signal statistics per event
+///
+/// The output has the event start as time column, `event_end` (null for an
+/// open-ended event), the asset column if events are matched by asset, and
+/// `<column>_<window>_<stat>` for each column, window (`before`: the `before`
+/// duration up to the start; `during`: from start to end; `after`: the `after`
+/// duration from the end) and statistic. Windows without samples give nulls;
+/// open-ended events have no `after` window. Rows are ordered by asset, then
+/// event start.
+pub struct EventWindowStatsOperation {
+    events: EventFrames,
+    before: Option<Duration>,
+    after: Option<Duration>,
+    during: bool,
+    stats: Vec<AggMethod>,
+    columns: Option<Vec<String>>,
+    asset_column: Option<String>,
+}
+
+impl EventWindowStatsOperation {
+    /// Aggregate `columns` (default: all feature columns) during each event
+    /// with `stats`
+    pub fn new(events: EventFrames, stats: Vec<AggMethod>, columns: Option<Vec<String>>) -> Self {
+        Self {
+            events,
+            before: None,
+            after: None,
+            during: true,
+            stats,
+            columns,
+            asset_column: None,
+        }
+    }
+
+    /// Also aggregate over the `before` window up to each event start
+    pub fn with_before(mut self, before: Duration) -> Self {
+        self.before = Some(before);
+        self
+    }
+
+    /// Also aggregate over the `after` window from each event end
+    pub fn with_after(mut self, after: Duration) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    /// Whether to aggregate over the events themselves (default true)
+    pub fn with_during(mut self, during: bool) -> Self {
+        self.during = during;
+        self
+    }
+
+    /// Match events to rows by this column (the events must carry asset IDs)
+    pub fn with_asset_column(mut self, column: &str) -> Self {
+        self.asset_column = Some(column.to_string());
+        self
+    }
+
+    /// Names of the aggregated windows
+    fn windows(&self) -> Vec<&'static str> {
+        let mut windows = Vec::new();
+        if self.before.is_some() {
+            windows.push("before");
+        }
+        if self.during {
+            windows.push("during");
+        }
+        if self.after.is_some() {
+            windows.push("after");
+        }
+        windows
+    }
+
+    /// `[from, to)` bounds of `window` for an event
+    fn bounds(&self, window: &str, start: i64, end: i64) -> Option<(i64, i64)> {
+        let ms = |d: Option<Duration>| d.map_or(0, |d| d.as_millis() as i64);
+        match window {
+            "before" => Some((start - ms(self.before), start)),
+            "during" => Some((start, end.saturating_add(1))),
+            _ if end == i64::MAX => None,
+            _ => Some((
+                end.saturating_add(1),
+                end.saturating_add(1 + ms(self.after)),
+            )),
+        }
+    }
+
+    fn output_name(column: &str, window: &str, stat: AggMethod) -> String {
+        let stat = format!("{:?}", stat).to_lowercase();
+        format!("{}_{}_{}", column, window, stat)
+    }
+}
+
+/// `stat` of the non-null values of `values`
+pub(crate) fn aggregate(values: &Series, stat: AggMethod) -> Result<Option<f64>> {
+    let values = values.drop_nulls();
+    let non_null = |value: Option<f64>| if values.is_empty() { None } else { value };
+    Ok(match stat {
+        AggMethod::Mean => values.mean(),
+        AggMethod::Sum => non_null(Some(values.sum::<f64>()?)),
+        AggMethod::Min => values.min::<f64>()?,
+        AggMethod::Max => values.max::<f64>()?,
+        AggMethod::First => values.f64()?.first(),
+        AggMethod::Last => values.f64()?.last(),
+        AggMethod::Count => Some(values.len() as f64),
+    })
+}
+
+impl Operation for EventWindowStatsOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        if self.asset_column.is_none() && self.events.has_assets() {
+            return Err(IndustrytsError::InvalidOperation(
+                "Event frames carry asset IDs; set an asset column to match them".to_string(),
+            ));
+        }
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data.feature_columns().to_vec(),
+        };
+        let df = data.dataframe();
+
+        // Rows of each asset, ordered by time
+        let times = data.timestamps_ms()?;
+        let assets: Vec<Option<String>> = match &self.asset_column {
+            Some(c) => df
+                .column(c)?
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|a| a.map(str::to_string))
+                .collect(),
+            None => vec![None; df.height()],
+        };
+        let mut rows: HashMap<Option<&str>, Vec<(i64, IdxSize)>> = HashMap::new();
+        for (row, (t, asset)) in times.into_iter().zip(&assets).enumerate() {
+            if let Some(t) = t {
+                rows.entry(asset.as_deref())
+                    .or_default()
+                    .push((t, row as IdxSize));
+            }
+        }
+        let mut series: HashMap<Option<&str>, (Vec<i64>, Vec<Series>)> = HashMap::new();
+        for (asset, mut asset_rows) in rows {
+            asset_rows.sort_unstable();
+            let take = IdxCa::from_vec("".into(), asset_rows.iter().map(|&(_, row)| row).collect());
+            let values = columns
+                .iter()
+                .map(|c| {
+                    let column = df.column(c)?.cast(&DataType::Float64)?;
+                    Ok(column.take(&take)?.as_materialized_series().clone())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            series.insert(
+                asset,
+                (asset_rows.into_iter().map(|(t, _)| t).collect(), values),
+            );
+        }
+
+        let events = self.events.events();
+        let windows = self.windows();
+        let mut outputs: Vec<Vec<Option<f64>>> =
+            vec![
+                Vec::with_capacity(events.len());
+                columns.len() * windows.len() * self.stats.len()
+            ];
+        for &(asset, start, end) in &events {
+            let asset_series = series.get(&asset);
+            let mut output = outputs.iter_mut();
+            for values_index in 0..columns.len() {
+                for window in &windows {
+                    let slice = match (asset_series, self.bounds(window, start, end)) {
+                        (Some((times, values)), Some((from, to))) => {
+                            let first = times.partition_point(|&t| t < from);
+                            let last = times.partition_point(|&t| t < to);
+                            Some(values[values_index].slice(first as i64, last - first))
+                        }
+                        _ => None,
+                    };
+                    for &stat in &self.stats {
+                        let value = match &slice {
+                            Some(slice) => aggregate(slice, stat)?,
+                            None => None,
+                        };
+                        output.next().expect("one output per statistic").push(value);
+                    }
+                }
+            }
+        }
+
+        let datetime = DataType::Datetime(TimeUnit::Milliseconds, None);
+        let starts: Vec<i64> = events.iter().map(|e| e.1).collect();
+        let ends: Vec<Option<i64>> = events
+            .iter()
+            .map(|e| (e.2 != i64::MAX).then_some(e.2))
+            .collect();
+        let mut out = vec![
+            Series::new(EVENT_START_COLUMN.into(), starts)
+                .cast(&datetime)?
+                .into(),
+            Series::new(EVENT_END_COLUMN.into(), ends)
+                .cast(&datetime)?
+                .into(),
+        ];
+        if let Some(asset_column) = &self.asset_column {
+            let ids: Vec<Option<&str>> = events.iter().map(|e| e.0).collect();
+            out.push(Series::new(asset_column.as_str().into(), ids).into());
+        }
+        let names = self.added_columns(&columns);
+        for (name, values) in names.iter().zip(outputs) {
+            out.push(Series::new(name.as_str().into(), values).into());
+        }
+
+        let mut result = TimeSeriesData::new(DataFrame::new(out)?, Some(EVENT_START_COLUMN))?;
+        if let Some(asset_column) = &self.asset_column {
+            result = result.with_group_columns(std::slice::from_ref(asset_column))?;
+        }
+        result.metadata_mut().feature_columns = names;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "event_window_stats"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.asset_column.iter().cloned());
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        let windows = self.windows();
+        columns
+            .iter()
+            .flat_map(|c| {
+                windows.iter().flat_map(move |w| {
+                    self.stats
+                        .iter()
+                        .map(move |&stat| Self::output_name(c, w, stat))
+                })
+            })
+            .collect()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+
+    fn reorders_rows(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    const MIN: i64 = 60_000;
+
+    #[test]
+    fn test_statistics_around_trips() {
+        // Pressure ramps up, trips at 10 min and recovers after 13 min
+        let pressure: Vec<f64> = (0..20)
+            .map(|i| match i {
+                0..10 => i as f64,
+                10..=12 => 20.0,
+                _ => 5.0,
+            })
+            .collect();
+        let data = Fixture::every(Duration::from_secs(60), 20)
+            .with_column("pressure", pressure)
+            .with_column("flow", vec![1.0; 20])
+            .build()
+            .unwrap();
+        let t0 = 1_704_067_200_000;
+        let events = EventFrames::new(vec![
+            (None, t0 + 10 * MIN, t0 + 12 * MIN),
+            (None, t0 + 18 * MIN, i64::MAX),
+        ]);
+
+        let op = EventWindowStatsOperation::new(
+            events,
+            vec![AggMethod::Mean, AggMethod::Max],
+            Some(vec!["pressure".to_string()]),
+        )
+        .with_before(Duration::from_secs(3 * 60))
+        .with_after(Duration::from_secs(2 * 60));
+        let result = op.execute(data).unwrap();
+
+        assert_eq!(result.time_column(), EVENT_START_COLUMN);
+        assert_eq!(
+            result.feature_columns(),
+            [
+                "pressure_before_mean",
+                "pressure_before_max",
+                "pressure_during_mean",
+                "pressure_during_max",
+                "pressure_after_mean",
+                "pressure_after_max",
+            ]
+        );
+        let df = result.dataframe();
+        let column = |name: &str| -> Vec<Option<f64>> {
+            df.column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        // Samples at 7, 8 and 9 minutes precede the first trip
+        assert_eq!(column("pressure_before_mean"), [Some(8.0), Some(5.0)]);
+        assert_eq!(column("pressure_during_max"), [Some(20.0), Some(5.0)]);
+        assert_eq!(column("pressure_after_mean"), [Some(5.0), None]);
+        assert_eq!(df.column(EVENT_END_COLUMN).unwrap().null_count(), 1);
+    }
+}
@@ -1,47 +1,83 @@
 //! Feature engineering operations for time series data
 
-use crate::core::{Operation, TimeSeriesData};
-use crate::error::Result;
+use crate::core::naming::check_collisions;
+use crate::core::{Carry, CarryMode, NamingPolicy, Operation, StatefulOperation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::outlier::{median, quantile};
+use crate::operations::group::{map_partitions, partition_columns, target_columns};
+use crate::utils::parse_duration;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 /// Lag operation - create lagged features
+///
+/// With `partition_by`, each segment (batch, run, machine) is lagged separately
+/// and its first rows get nulls instead of values from the previous segment.
 pub struct LagOperation {
     periods: Vec<i32>,
     columns: Option<Vec<String>>,
+    partition_by: Vec<String>,
+    naming: NamingPolicy,
 }
 
 impl LagOperation {
     pub fn new(periods: Vec<i32>, columns: Option<Vec<String>>) -> Self {
-        Self { periods, columns }
+        Self {
+            periods,
+            columns,
+            partition_by: Vec::new(),
+            naming: NamingPolicy::default(),
+        }
+    }
+
+    /// Lag within segments identified by these columns
+    pub fn with_partition_by(mut self, partition_by: Vec<String>) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
+    fn output_name(&self, column: &str, period: i32) -> String {
+        self.naming
+            .name(column, "lag", Some(&period.abs().to_string()))
     }
 }
 
 impl Operation for LagOperation {
     fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
         // Get columns to create lag features for
-        let columns_to_lag = if let Some(cols) = &self.columns {
-            cols.clone()
-        } else {
-            data.feature_columns().to_vec()
-        };
+        let columns_to_lag = target_columns(
+            self.columns.as_deref(),
+            data.feature_columns(),
+            &self.partition_by,
+        );
 
-        let mut df = data.dataframe().clone();
+        check_collisions(
+            self.name(),
+            data.dataframe().get_column_names(),
+            &self.added_columns(data.feature_columns()),
+        )?;
 
-        // Create lag features for each column and period
-        for col_name in &columns_to_lag {
-            let column = df.column(col_name)?;
-            let series = column.as_materialized_series().clone();
+        let partition_by = partition_columns(&self.partition_by, &data);
+        let df = map_partitions(data.dataframe(), partition_by, |mut df| {
+            // Create lag features for each column and period
+            for col_name in &columns_to_lag {
+                let column = df.column(col_name)?;
+                let series = column.as_materialized_series().clone();
 
-            for &period in &self.periods {
-                // Create lag feature name
-                let lag_name = format!("{}_lag_{}", col_name, period.abs());
+                for &period in &self.periods {
+                    // Create lag feature name
+                    let lag_name = self.output_name(col_name, period);
 
-                // Shift series by period (positive = backward, negative = forward)
-                let lagged = series.shift(period as i64);
+                    // Shift series by period (positive = backward, negative = forward)
+                    let lagged = series.shift(period as i64);
 
-                // Add to dataframe
-                df.with_column(lagged.with_name(lag_name.as_str().into()))?;
+                    // Add to dataframe
+                    df.with_column(lagged.with_name(lag_name.as_str().into()))?;
+                }
             }
-        }
+            Ok(df)
+        })?;
 
         // Create new TimeSeriesData with lagged features
         TimeSeriesData::new(df, Some(data.time_column()))
@@ -50,8 +86,770 @@ impl Operation for LagOperation {
     fn name(&self) -> &str {
         "lag"
     }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.naming = naming.clone();
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        self.periods
+            .iter()
+            .filter(|&&period| period < 0)
+            .map(|period| format!("lag period {} reads {} row(s) ahead", period, -period))
+            .collect()
+    }
+
+    fn apply_lazy(&self, mut lf: LazyFrame, time_column: &str) -> Result<LazyFrame> {
+        let features = TimeSeriesData::lazy_feature_columns(&mut lf, time_column)?;
+        let columns = target_columns(self.columns.as_deref(), &features, &self.partition_by);
+        check_collisions(
+            self.name(),
+            lf.collect_schema()?.iter_names(),
+            &self.added_columns(&features),
+        )?;
+        let partition: Vec<Expr> = self.partition_by.iter().map(|c| col(c.as_str())).collect();
+        let lags: Vec<Expr> = columns
+            .iter()
+            .flat_map(|c| {
+                let partition = &partition;
+                self.periods.iter().map(move |&period| {
+                    let lagged = col(c.as_str()).shift(lit(period as i64));
+                    let lagged = if partition.is_empty() {
+                        lagged
+                    } else {
+                        lagged.over(partition.clone())
+                    };
+                    lagged.alias(self.output_name(c, period))
+                })
+            })
+            .collect();
+        Ok(lf.with_columns(lags))
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.partition_by.iter().cloned());
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        target_columns(self.columns.as_deref(), feature_columns, &self.partition_by)
+            .iter()
+            .flat_map(|c| {
+                self.periods
+                    .iter()
+                    .map(move |&period| self.output_name(c, period))
+            })
+            .collect()
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        // Leads look ahead into the next chunk and are not carried
+        if self.periods.iter().any(|&p| p < 0) {
+            return None;
+        }
+        let rows = self.periods.iter().copied().max().unwrap_or(0) as usize;
+        let carry = Carry::new(self, CarryMode::InputRows(rows)).with_keys(&self.partition_by);
+        Some(Box::new(carry))
+    }
 }
 
-// TODO: Implement RollingOperation using LazyFrame API in future versions
-// Rolling window operations need to be implemented using Polars LazyFrame API
-// which has changed in version 0.51+
+/// Trend slope operation - rolling linear regression against time
+///
+/// For each sample, fits `value = a + b * t` over the trailing time window
+/// `(t - window, t]` and outputs the slope `b` (per `slope_per`, default one hour)
+/// as `<column>_slope` and the fit's R² as `<column>_r2`. A slowly rising slope with
+/// high R² is the signature of gradual fouling or wear; noisy data gives low R².
+/// Null values are skipped; windows with fewer than `min_periods` samples give nulls.
+/// With `partition_by`, windows stop at segment boundaries.
+pub struct TrendSlopeOperation {
+    window: Duration,
+    slope_per: Duration,
+    min_periods: usize,
+    columns: Option<Vec<String>>,
+    partition_by: Vec<String>,
+    naming: NamingPolicy,
+}
+
+impl TrendSlopeOperation {
+    pub fn new(window: Duration, columns: Option<Vec<String>>) -> Self {
+        Self {
+            window,
+            slope_per: Duration::from_secs(3600),
+            min_periods: 3,
+            columns,
+            partition_by: Vec::new(),
+            naming: NamingPolicy::default(),
+        }
+    }
+
+    /// Fit within segments identified by these columns
+    pub fn with_partition_by(mut self, partition_by: Vec<String>) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
+    /// Time unit of the slope (default one hour)
+    pub fn with_slope_per(mut self, slope_per: Duration) -> Self {
+        self.slope_per = slope_per;
+        self
+    }
+
+    /// Minimum number of samples in a window (default 3, at least 2)
+    pub fn with_min_periods(mut self, min_periods: usize) -> Self {
+        self.min_periods = min_periods.max(2);
+        self
+    }
+
+    /// Rolling slope and R² of `values` over `times`
+    fn fit(
+        &self,
+        times: &[Option<i64>],
+        values: &[Option<f64>],
+    ) -> (Vec<Option<f64>>, Vec<Option<f64>>) {
+        let window_ms = self.window.as_millis() as i64;
+        let unit_ms = self.slope_per.as_millis().max(1) as f64;
+        let origin = times.iter().flatten().next().copied().unwrap_or(0);
+
+        // Running sums over the points currently in the window
+        let (mut n, mut sx, mut sy, mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let point = |i: usize| match (times[i], values[i]) {
+            (Some(t), Some(y)) if y.is_finite() => Some(((t - origin) as f64 / unit_ms, y)),
+            _ => None,
+        };
+
+        let mut slopes = Vec::with_capacity(times.len());
+        let mut r2s = Vec::with_capacity(times.len());
+        let mut start = 0;
+        for i in 0..times.len() {
+            if let Some((x, y)) = point(i) {
+                n += 1.0;
+                sx += x;
+                sy += y;
+                sxx += x * x;
+                sxy += x * y;
+                syy += y * y;
+            }
+            let Some(t) = times[i] else {
+                slopes.push(None);
+                r2s.push(None);
+                continue;
+            };
+            while start < i && times[start].is_none_or(|s| s <= t - window_ms) {
+                if let Some((x, y)) = point(start) {
+                    n -= 1.0;
+                    sx -= x;
+                    sy -= y;
+                    sxx -= x * x;
+                    sxy -= x * y;
+                    syy -= y * y;
+                }
+                start += 1;
+            }
+
+            let var_x = n * sxx - sx * sx;
+            let var_y = n * syy - sy * sy;
+            let cov = n * sxy - sx * sy;
+            if (n as usize) < self.min_periods || var_x <= f64::EPSILON * n * sxx.abs() {
+                slopes.push(None);
+                r2s.push(None);
+                continue;
+            }
+            slopes.push(Some(cov / var_x));
+            r2s.push(if var_y > 0.0 {
+                Some((cov * cov / (var_x * var_y)).clamp(0.0, 1.0))
+            } else {
+                None
+            });
+        }
+        (slopes, r2s)
+    }
+}
+
+impl Operation for TrendSlopeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = target_columns(
+            self.columns.as_deref(),
+            data.feature_columns(),
+            &self.partition_by,
+        );
+
+        check_collisions(
+            self.name(),
+            data.dataframe().get_column_names(),
+            &self.added_columns(data.feature_columns()),
+        )?;
+
+        let time_col = data.time_column().to_string();
+        let partition_by = partition_columns(&self.partition_by, &data);
+        let df = map_partitions(data.dataframe(), partition_by, |mut df| {
+            let times: Vec<Option<i64>> = TimeSeriesData::new(df.clone(), Some(&time_col))?
+                .timestamps_ms()?
+                .into_iter()
+                .collect();
+            for col_name in &columns {
+                let values: Vec<Option<f64>> = df
+                    .column(col_name)?
+                    .cast(&DataType::Float64)?
+                    .f64()?
+                    .into_iter()
+                    .collect();
+                let (slopes, r2s) = self.fit(&times, &values);
+                let slope_name = self.naming.name(col_name, "slope", None);
+                let r2_name = self.naming.name(col_name, "r2", None);
+                df.with_column(Series::new(slope_name.into(), slopes))?;
+                df.with_column(Series::new(r2_name.into(), r2s))?;
+            }
+            Ok(df)
+        })?;
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "trend_slope"
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.naming = naming.clone();
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.partition_by.iter().cloned());
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        target_columns(self.columns.as_deref(), feature_columns, &self.partition_by)
+            .iter()
+            .flat_map(|c| {
+                [
+                    self.naming.name(c, "slope", None),
+                    self.naming.name(c, "r2", None),
+                ]
+            })
+            .collect()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.window
+    }
+}
+
+/// Trailing window of a rolling statistic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollingWindow {
+    /// The current row and the `n - 1` rows before it
+    Rows(usize),
+    /// Samples within `(t - window, t]`
+    Time(Duration),
+}
+
+impl RollingWindow {
+    /// Parse "60" or "60 rows" as a row window and anything else (e.g. "15m") as a
+    /// time window
+    pub fn parse(s: &str) -> Result<Self> {
+        let trimmed = s.trim();
+        let count = trimmed
+            .strip_suffix("rows")
+            .or_else(|| trimmed.strip_suffix("row"))
+            .unwrap_or(trimmed)
+            .trim();
+        if let Ok(rows) = count.parse::<usize>() {
+            if rows == 0 {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Rolling window '{}' must contain at least one row",
+                    s
+                )));
+            }
+            return Ok(RollingWindow::Rows(rows));
+        }
+        Ok(RollingWindow::Time(parse_duration(trimmed)?))
+    }
+
+    /// Short name used in output columns: "60" for rows, "15m", "1h", "90s" for time
+    pub fn label(&self) -> String {
+        match self {
+            RollingWindow::Rows(rows) => rows.to_string(),
+            RollingWindow::Time(window) => {
+                let ms = window.as_millis();
+                [
+                    (86_400_000, "d"),
+                    (3_600_000, "h"),
+                    (60_000, "m"),
+                    (1000, "s"),
+                ]
+                .iter()
+                .find(|(unit, _)| ms > 0 && ms % unit == 0)
+                .map(|(unit, suffix)| format!("{}{}", ms / unit, suffix))
+                .unwrap_or_else(|| format!("{}ms", ms))
+            }
+        }
+    }
+}
+
+/// Statistic computed over a rolling window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollingStat {
+    Mean,
+    /// Sample standard deviation
+    Std,
+    Min,
+    Max,
+    Median,
+    /// Linearly interpolated quantile in [0, 1]
+    Quantile(f64),
+}
+
+impl RollingStat {
+    /// Parse "mean", "std", "min", "max" or "median"
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mean" => Ok(RollingStat::Mean),
+            "std" => Ok(RollingStat::Std),
+            "min" => Ok(RollingStat::Min),
+            "max" => Ok(RollingStat::Max),
+            "median" => Ok(RollingStat::Median),
+            other => Err(IndustrytsError::ConfigError(format!(
+                "Unknown rolling statistic '{}'",
+                other
+            ))),
+        }
+    }
+
+    /// Short name used in output columns, e.g. "mean" or "q95" for the 0.95 quantile
+    pub fn label(&self) -> String {
+        match self {
+            RollingStat::Mean => "mean".to_string(),
+            RollingStat::Std => "std".to_string(),
+            RollingStat::Min => "min".to_string(),
+            RollingStat::Max => "max".to_string(),
+            RollingStat::Median => "median".to_string(),
+            RollingStat::Quantile(q) => {
+                format!("q{}", (q * 1000.0).round() / 10.0).replace('.', "_")
+            }
+        }
+    }
+
+    /// Value of the statistic over `sorted`, which is sorted and not empty
+    fn compute(&self, sorted: &[f64]) -> Option<f64> {
+        let n = sorted.len() as f64;
+        match self {
+            RollingStat::Mean => Some(sorted.iter().sum::<f64>() / n),
+            RollingStat::Std => {
+                if sorted.len() < 2 {
+                    return None;
+                }
+                let mean = sorted.iter().sum::<f64>() / n;
+                let ss: f64 = sorted.iter().map(|v| (v - mean) * (v - mean)).sum();
+                Some((ss / (n - 1.0)).sqrt())
+            }
+            RollingStat::Min => sorted.first().copied(),
+            RollingStat::Max => sorted.last().copied(),
+            RollingStat::Median => Some(median(sorted)),
+            RollingStat::Quantile(q) => Some(quantile(sorted, *q)),
+        }
+    }
+}
+
+/// Default output column name: `<column>_roll<stat>_<window>`, e.g. `temp_rollmean_15m`
+pub const DEFAULT_ROLLING_NAME_TEMPLATE: &str = "{column}_roll{stat}_{window}";
+
+/// Rolling features operation - trailing window statistics
+///
+/// For every column, window and statistic, adds a column named after
+/// `name_template`, where `{column}`, `{stat}` and `{window}` are replaced by the
+/// source column, `RollingStat::label` and `RollingWindow::label`. Without a
+/// template, the naming policy names it with op `roll<stat>` and the window as
+/// parameter. Windows can be
+/// overridden per column. Null and non-finite values are skipped; windows with fewer
+/// than `min_periods` samples give nulls. With `partition_by`, windows stop at
+/// segment boundaries.
+pub struct RollingFeaturesOperation {
+    windows: Vec<RollingWindow>,
+    stats: Vec<RollingStat>,
+    columns: Option<Vec<String>>,
+    column_windows: BTreeMap<String, Vec<RollingWindow>>,
+    name_template: Option<String>,
+    naming: NamingPolicy,
+    min_periods: usize,
+    partition_by: Vec<String>,
+}
+
+impl RollingFeaturesOperation {
+    pub fn new(
+        windows: Vec<RollingWindow>,
+        stats: Vec<RollingStat>,
+        columns: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            windows,
+            stats,
+            columns,
+            column_windows: BTreeMap::new(),
+            name_template: None,
+            naming: NamingPolicy::default(),
+            min_periods: 1,
+            partition_by: Vec::new(),
+        }
+    }
+
+    /// Use `windows` instead of the default windows for `column`
+    pub fn with_column_windows(mut self, column: &str, windows: Vec<RollingWindow>) -> Self {
+        self.column_windows.insert(column.to_string(), windows);
+        self
+    }
+
+    /// Name output columns after `template` (see `DEFAULT_ROLLING_NAME_TEMPLATE`)
+    pub fn with_name_template(mut self, template: &str) -> Self {
+        self.name_template = Some(template.to_string());
+        self
+    }
+
+    /// Minimum number of samples in a window (default 1)
+    pub fn with_min_periods(mut self, min_periods: usize) -> Self {
+        self.min_periods = min_periods.max(1);
+        self
+    }
+
+    /// Compute within segments identified by these columns
+    pub fn with_partition_by(mut self, partition_by: Vec<String>) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
+    fn windows_for(&self, column: &str) -> &[RollingWindow] {
+        self.column_windows
+            .get(column)
+            .map_or(&self.windows, |windows| windows)
+    }
+
+    fn output_name(&self, column: &str, stat: &RollingStat, window: &RollingWindow) -> String {
+        match &self.name_template {
+            Some(template) => template
+                .replace("{column}", column)
+                .replace("{stat}", &stat.label())
+                .replace("{window}", &window.label()),
+            None => self.naming.name(
+                column,
+                &format!("roll{}", stat.label()),
+                Some(&window.label()),
+            ),
+        }
+    }
+
+    /// Rolling values of every statistic over `window`
+    fn roll(
+        &self,
+        window: &RollingWindow,
+        times: &[Option<i64>],
+        values: &[Option<f64>],
+    ) -> Vec<Vec<Option<f64>>> {
+        let mut outputs = vec![Vec::with_capacity(values.len()); self.stats.len()];
+        let mut start = 0;
+        for i in 0..values.len() {
+            match window {
+                RollingWindow::Rows(rows) => start = (i + 1).saturating_sub(*rows),
+                RollingWindow::Time(duration) => {
+                    let Some(t) = times[i] else {
+                        outputs.iter_mut().for_each(|output| output.push(None));
+                        continue;
+                    };
+                    let window_ms = duration.as_millis() as i64;
+                    while start < i && times[start].is_none_or(|s| s <= t - window_ms) {
+                        start += 1;
+                    }
+                }
+            }
+            let mut samples: Vec<f64> = values[start..=i]
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|v| v.is_finite())
+                .collect();
+            if samples.len() < self.min_periods || samples.is_empty() {
+                outputs.iter_mut().for_each(|output| output.push(None));
+                continue;
+            }
+            samples.sort_by(f64::total_cmp);
+            for (output, stat) in outputs.iter_mut().zip(&self.stats) {
+                output.push(stat.compute(&samples));
+            }
+        }
+        outputs
+    }
+}
+
+impl Operation for RollingFeaturesOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = target_columns(
+            self.columns.as_deref(),
+            data.feature_columns(),
+            &self.partition_by,
+        );
+
+        check_collisions(
+            self.name(),
+            data.dataframe().get_column_names(),
+            &self.added_columns(data.feature_columns()),
+        )?;
+
+        let time_col = data.time_column().to_string();
+        let partition_by = partition_columns(&self.partition_by, &data);
+        let df = map_partitions(data.dataframe(), partition_by, |mut df| {
+            let times: Vec<Option<i64>> = TimeSeriesData::new(df.clone(), Some(&time_col))?
+                .timestamps_ms()?
+                .into_iter()
+                .collect();
+            for col_name in &columns {
+                let values: Vec<Option<f64>> = df
+                    .column(col_name)?
+                    .cast(&DataType::Float64)?
+                    .f64()?
+                    .into_iter()
+                    .collect();
+                for window in self.windows_for(col_name) {
+                    let outputs = self.roll(window, &times, &values);
+                    for (stat, output) in self.stats.iter().zip(outputs) {
+                        let name = self.output_name(col_name, stat, window);
+                        df.with_column(Series::new(name.into(), output))?;
+                    }
+                }
+            }
+            Ok(df)
+        })?;
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "rolling_features"
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.naming = naming.clone();
+    }
+
+    fn validate(&self, _data: &TimeSeriesData) -> Result<()> {
+        for stat in &self.stats {
+            if let RollingStat::Quantile(q) = stat
+                && !(0.0..=1.0).contains(q)
+            {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "rolling quantile {} is outside [0, 1]",
+                    q
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.column_windows.keys().cloned());
+        columns.extend(self.partition_by.iter().cloned());
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        target_columns(self.columns.as_deref(), feature_columns, &self.partition_by)
+            .iter()
+            .flat_map(|c| {
+                self.windows_for(c).iter().flat_map(move |window| {
+                    self.stats
+                        .iter()
+                        .map(move |stat| self.output_name(c, stat, window))
+                })
+            })
+            .collect()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.windows
+            .iter()
+            .chain(self.column_windows.values().flatten())
+            .filter_map(|window| match window {
+                RollingWindow::Time(duration) => Some(*duration),
+                RollingWindow::Rows(_) => None,
+            })
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lag_respects_partitions() {
+        let time_series = Series::new(
+            "time".into(),
+            (0..6).map(|i| i * 60_000i64).collect::<Vec<_>>(),
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("batch".into(), ["a", "a", "b", "a", "b", "b"]).into(),
+            Series::new("temp".into(), [1.0, 2.0, 10.0, 3.0, 20.0, 30.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = LagOperation::new(vec![1], None).with_partition_by(vec!["batch".to_string()]);
+        assert_eq!(op.added_columns(data.feature_columns()), vec!["temp_lag_1"]);
+        let result = op.execute(data).unwrap();
+        let lagged: Vec<Option<f64>> = result
+            .dataframe()
+            .column("temp_lag_1")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            lagged,
+            vec![None, Some(1.0), None, Some(2.0), Some(10.0), Some(20.0)]
+        );
+        assert_eq!(
+            result
+                .dataframe()
+                .column("batch")
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(2),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn test_trend_slope_on_linear_drift() {
+        // Value rises 0.5 per hour, sampled every 10 minutes, with a gap at index 3
+        let times: Vec<i64> = (0..12).map(|i| i * 600_000).collect();
+        let values: Vec<Option<f64>> = (0..12)
+            .map(|i| {
+                if i == 3 {
+                    None
+                } else {
+                    Some(10.0 + 0.5 * i as f64 / 6.0)
+                }
+            })
+            .collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("dp".into(), values).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = TrendSlopeOperation::new(Duration::from_secs(3600), None);
+        assert_eq!(
+            op.added_columns(&["dp".to_string()]),
+            vec!["dp_slope", "dp_r2"]
+        );
+        let result = op.execute(data).unwrap();
+
+        let slopes = result
+            .dataframe()
+            .column("dp_slope")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .clone();
+        let r2 = result
+            .dataframe()
+            .column("dp_r2")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .clone();
+        assert_eq!(slopes.get(1), None);
+        for i in 4..12 {
+            assert!((slopes.get(i).unwrap() - 0.5).abs() < 1e-9);
+            assert!((r2.get(i).unwrap() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_rolling_features_windows_and_naming() {
+        assert_eq!(RollingWindow::parse("60").unwrap(), RollingWindow::Rows(60));
+        assert_eq!(
+            RollingWindow::parse("60 rows").unwrap(),
+            RollingWindow::Rows(60)
+        );
+        assert_eq!(RollingWindow::parse("15m").unwrap().label(), "15m");
+        assert_eq!(RollingWindow::parse("90s").unwrap().label(), "90s");
+        assert_eq!(RollingWindow::parse("1500ms").unwrap().label(), "1500ms");
+        assert!(RollingWindow::parse("0").is_err());
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000, 240_000, 300_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), &[1.0, 2.0, 3.0, 4.0, 5.0]).into(),
+            Series::new("pressure".into(), &[1.0, 2.0, 3.0, 4.0, 5.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = RollingFeaturesOperation::new(
+            vec![RollingWindow::Time(Duration::from_secs(120))],
+            vec![
+                RollingStat::Mean,
+                RollingStat::Max,
+                RollingStat::Quantile(0.5),
+            ],
+            None,
+        )
+        .with_column_windows("pressure", vec![RollingWindow::Rows(2)]);
+        let columns = ["temp".to_string(), "pressure".to_string()];
+        assert_eq!(
+            op.added_columns(&columns),
+            [
+                "temp_rollmean_2m",
+                "temp_rollmax_2m",
+                "temp_rollq50_2m",
+                "pressure_rollmean_2",
+                "pressure_rollmax_2",
+                "pressure_rollq50_2",
+            ]
+        );
+        assert_eq!(op.warmup(), Duration::from_secs(120));
+
+        let result = op.execute(data).unwrap();
+        let column = |name: &str| -> Vec<Option<f64>> {
+            result
+                .dataframe()
+                .column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        // The 2m window at 240s only holds the sample at 240s
+        assert_eq!(
+            column("temp_rollmean_2m"),
+            [Some(1.0), Some(1.5), Some(2.5), Some(4.0), Some(4.5)]
+        );
+        assert_eq!(
+            column("pressure_rollmean_2"),
+            [Some(1.0), Some(1.5), Some(2.5), Some(3.5), Some(4.5)]
+        );
+        assert_eq!(column("temp_rollq50_2m"), column("temp_rollmean_2m"));
+
+        let renamed = op.with_name_template("{stat}_{window}_{column}");
+        assert_eq!(renamed.added_columns(&columns)[0], "mean_2m_temp");
+    }
+}
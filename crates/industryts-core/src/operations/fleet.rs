@@ -0,0 +1,253 @@
+//! Aggregation of one measurement across many assets
+//!
+//! Fleet KPIs compare or summarize the same measurement of many assets, e.g. the
+//! mean, worst and reporting count of the vibration of every pump. The asset of a
+//! column is a path in the asset hierarchy such as `plant_a/line_1/pump_3`, read
+//! from its `asset` column property (set by tag dictionaries, see
+//! `MapColumnsOperation`) or given explicitly per column.
+//!
+//! At `level` 0 all selected columns are aggregated into `<name>_<stat>` columns.
+//! At level `n` they are aggregated per node `n` levels deep, into
+//! `<name>_<node>_<stat>` columns where the node path is joined with `_`, e.g.
+//! `vibration_plant_a_line_1_max` at level 2. Columns whose asset is not known
+//! that deep are left out. Nulls are skipped; a row with no value in a node
+//! aggregates to null, with a count of 0.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Statistic computed across the columns of a fleet node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FleetStat {
+    Mean,
+    Min,
+    Max,
+    Sum,
+    /// Number of non-null values
+    Count,
+}
+
+impl FleetStat {
+    fn label(&self) -> &'static str {
+        match self {
+            FleetStat::Mean => "mean",
+            FleetStat::Min => "min",
+            FleetStat::Max => "max",
+            FleetStat::Sum => "sum",
+            FleetStat::Count => "count",
+        }
+    }
+}
+
+/// Fleet aggregate operation - per-row statistics across assets
+pub struct FleetAggregateOperation {
+    name: String,
+    stats: Vec<FleetStat>,
+    level: usize,
+    /// Asset paths overriding the `asset` column property
+    assets: BTreeMap<String, String>,
+    columns: Option<Vec<String>>,
+}
+
+impl FleetAggregateOperation {
+    pub fn new(name: &str, stats: Vec<FleetStat>, columns: Option<Vec<String>>) -> Result<Self> {
+        if stats.is_empty() {
+            return Err(IndustrytsError::ConfigError(
+                "Fleet aggregation needs at least one statistic".to_string(),
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            stats,
+            level: 0,
+            assets: BTreeMap::new(),
+            columns,
+        })
+    }
+
+    /// Aggregate per hierarchy node `level` levels deep (0 aggregates everything)
+    pub fn with_level(mut self, level: usize) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Asset path of a column, e.g. `plant_a/line_1/pump_3`
+    pub fn with_asset(mut self, column: &str, path: &str) -> Self {
+        self.assets.insert(column.to_string(), path.to_string());
+        self
+    }
+
+    /// Node of `path` at the operation's level, `None` if the path is too short
+    fn node(&self, path: Option<&str>) -> Option<String> {
+        if self.level == 0 {
+            return Some(String::new());
+        }
+        let segments: Vec<&str> = path?
+            .split('/')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        (segments.len() >= self.level).then(|| segments[..self.level].join("/"))
+    }
+
+    fn output_name(&self, node: &str, stat: FleetStat) -> String {
+        if node.is_empty() {
+            format!("{}_{}", self.name, stat.label())
+        } else {
+            format!("{}_{}_{}", self.name, node.replace('/', "_"), stat.label())
+        }
+    }
+}
+
+impl Operation for FleetAggregateOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let df = data.dataframe();
+        let columns = match &self.columns {
+            Some(cols) => cols.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| df.column(c).is_ok_and(|c| c.dtype().is_primitive_numeric()))
+                .cloned()
+                .collect(),
+        };
+
+        let mut nodes: BTreeMap<String, Vec<Float64Chunked>> = BTreeMap::new();
+        for col_name in &columns {
+            let column = df.column(col_name)?;
+            if !column.dtype().is_primitive_numeric() {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "Cannot aggregate non-numeric column: {}",
+                    col_name
+                )));
+            }
+            let path = self
+                .assets
+                .get(col_name)
+                .map(String::as_str)
+                .or_else(|| data.column_asset(col_name));
+            if let Some(node) = self.node(path) {
+                let values = column.cast(&DataType::Float64)?;
+                nodes.entry(node).or_default().push(values.f64()?.clone());
+            }
+        }
+        if nodes.is_empty() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "No columns to aggregate for fleet '{}'",
+                self.name
+            )));
+        }
+
+        let mut df = df.clone();
+        let mut metadata = data.metadata().clone();
+        for (node, members) in &nodes {
+            let rows: Vec<Vec<f64>> = (0..df.height())
+                .map(|row| members.iter().filter_map(|m| m.get(row)).collect())
+                .collect();
+            for &stat in &self.stats {
+                let name = self.output_name(node, stat);
+                let series = match stat {
+                    FleetStat::Count => {
+                        let counts: Vec<u32> = rows.iter().map(|r| r.len() as u32).collect();
+                        Series::new(name.as_str().into(), counts)
+                    }
+                    _ => {
+                        let values: Vec<Option<f64>> = rows
+                            .iter()
+                            .map(|r| {
+                                let first = *r.first()?;
+                                Some(match stat {
+                                    FleetStat::Mean => r.iter().sum::<f64>() / r.len() as f64,
+                                    FleetStat::Min => r.iter().fold(first, |a, &b| a.min(b)),
+                                    FleetStat::Max => r.iter().fold(first, |a, &b| a.max(b)),
+                                    _ => r.iter().sum::<f64>(),
+                                })
+                            })
+                            .collect();
+                        Series::new(name.as_str().into(), values)
+                    }
+                };
+                df.with_column(series)?;
+                if !metadata.feature_columns.contains(&name) {
+                    metadata.feature_columns.push(name);
+                }
+            }
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn name(&self) -> &str {
+        "fleet_aggregate"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        // Nodes of paths held in column properties are only known at run time
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        let nodes: BTreeSet<String> = columns
+            .iter()
+            .filter_map(|c| self.node(self.assets.get(c).map(String::as_str)))
+            .collect();
+        nodes
+            .iter()
+            .flat_map(|node| self.stats.iter().map(|&s| self.output_name(node, s)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fleet_aggregate_by_hierarchy_level() {
+        let mut data = Fixture::every(Duration::from_secs(60), 2)
+            .with_column("pump_1_vibration", [Some(1.0), None])
+            .with_column("pump_2_vibration", [Some(3.0), None])
+            .with_column("pump_3_vibration", [5.0, 6.0])
+            .build()
+            .unwrap();
+        data.set_column_property("pump_1_vibration", "asset", "plant_a/line_1/pump_1");
+        data.set_column_property("pump_2_vibration", "asset", "plant_a/line_1/pump_2");
+        let columns = Some(data.feature_columns().to_vec());
+
+        let fleet = FleetAggregateOperation::new(
+            "vibration",
+            vec![FleetStat::Mean, FleetStat::Max, FleetStat::Count],
+            columns.clone(),
+        )
+        .unwrap();
+        let result = fleet.execute(data.clone()).unwrap();
+        let get = |name: &str| result.dataframe().column(name).unwrap().clone();
+        assert_eq!(get("vibration_mean").f64().unwrap().get(0), Some(3.0));
+        assert_eq!(get("vibration_max").f64().unwrap().get(1), Some(6.0));
+        assert_eq!(get("vibration_count").u32().unwrap().get(1), Some(1));
+        assert!(
+            result
+                .feature_columns()
+                .contains(&"vibration_mean".to_string())
+        );
+
+        let lines = FleetAggregateOperation::new("vibration", vec![FleetStat::Sum], columns)
+            .unwrap()
+            .with_level(2)
+            .with_asset("pump_3_vibration", "plant_b/line_1/pump_3");
+        let result = lines.execute(data).unwrap();
+        let sum = |name: &str| {
+            let column = result.dataframe().column(name).unwrap();
+            let values = column.f64().unwrap();
+            (values.get(0), values.get(1))
+        };
+        assert_eq!(sum("vibration_plant_a_line_1_sum"), (Some(4.0), None));
+        assert_eq!(sum("vibration_plant_b_line_1_sum"), (Some(5.0), Some(6.0)));
+    }
+}
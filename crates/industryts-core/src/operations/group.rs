@@ -0,0 +1,396 @@
+//! Per-entity execution for panel data
+//!
+//! `PerGroupOperation` splits a long-format frame (one row per entity and
+//! timestamp) by an ID column, runs an inner operation on each entity's series in
+//! parallel and stacks the results. This is a stopgap until operations support
+//! groups natively.
+//!
+//! Row-wise feature operations (lag, difference, trend slope, rolling features) and
+//! null filling instead take a `partition_by` list and use `map_partitions`, which
+//! keeps the original row order so windows never reach across batches, runs or
+//! machines. Without `partition_by` they partition by the group columns of the data
+//! (`TimeSeriesData::with_group_columns`).
+
+use crate::core::{NamingPolicy, OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::time::Duration;
+
+/// Apply an operation separately to each entity of a panel
+pub struct PerGroupOperation {
+    id_column: String,
+    inner: Box<dyn Operation>,
+    name: String,
+}
+
+impl PerGroupOperation {
+    /// Wrap `inner`, running it once per distinct value of `id_column`
+    ///
+    /// Use `OperationExt::then` to run several steps per group.
+    pub fn new(id_column: &str, inner: Box<dyn Operation>) -> Self {
+        let name = format!("per_group({})", inner.name());
+        Self {
+            id_column: id_column.to_string(),
+            inner,
+            name,
+        }
+    }
+
+    /// Partition by the ID column, run `per_group` on each partition and restack
+    ///
+    /// The ID column is removed before the inner operation runs (so it is not
+    /// treated as a feature) and restored afterwards at its original position.
+    /// Groups keep the order in which they first appear.
+    fn run<T: Send>(
+        &self,
+        data: TimeSeriesData,
+        per_group: impl Fn(TimeSeriesData) -> Result<(TimeSeriesData, T)> + Sync,
+    ) -> Result<(TimeSeriesData, Vec<T>)> {
+        let time_col = data.time_column().to_string();
+        let id_index = data
+            .dataframe()
+            .get_column_index(&self.id_column)
+            .ok_or_else(|| IndustrytsError::ColumnNotFound(self.id_column.clone()))?;
+
+        let partitions = data
+            .dataframe()
+            .partition_by_stable([self.id_column.as_str()], true)?;
+        if partitions.is_empty() {
+            return Ok((data, Vec::new()));
+        }
+
+        let results = partitions
+            .into_par_iter()
+            .map(|mut partition| {
+                let id = partition.drop_in_place(&self.id_column)?;
+                let (result, extra) = per_group(TimeSeriesData::new(partition, Some(&time_col))?)?;
+                let tags = result.metadata().tags.clone();
+                let mut df = result.into_dataframe();
+                let id = id.new_from_index(0, df.height());
+                df.insert_column(id_index.min(df.width()), id)?;
+                Ok((df, tags, extra))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut results = results.into_iter();
+        let (mut df, group_tags, first_extra) = results.next().expect("at least one group");
+        let mut extras = vec![first_extra];
+        for (group_df, _, extra) in results {
+            df.vstack_mut(&group_df).map_err(|e| {
+                IndustrytsError::OperationError(format!(
+                    "{} produced different schemas across groups: {}",
+                    self.inner.name(),
+                    e
+                ))
+            })?;
+            extras.push(extra);
+        }
+        df.as_single_chunk_par();
+
+        let mut output = TimeSeriesData::new(df, Some(&time_col))?;
+        output.metadata_mut().tags = data.metadata().tags.clone();
+        for (key, value) in group_tags {
+            output.add_tag(key, value);
+        }
+        Ok((output, extras))
+    }
+}
+
+impl Operation for PerGroupOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, |group| Ok((self.inner.execute(group)?, ())))
+            .map(|(output, _)| output)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (output, group_contexts) = {
+            let parent = &*ctx;
+            self.run(data, |group| {
+                let mut group_ctx = parent.fork();
+                let result = self.inner.execute_with_context(group, &mut group_ctx)?;
+                Ok((result, group_ctx))
+            })?
+        };
+        for group_ctx in group_contexts {
+            ctx.join(group_ctx)?;
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.inner.set_seed(seed)
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.inner.set_naming(naming)
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = vec![self.id_column.clone()];
+        for column in self.inner.required_columns() {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let features: Vec<String> = feature_columns
+            .iter()
+            .filter(|c| **c != self.id_column)
+            .cloned()
+            .collect();
+        self.inner.added_columns(&features)
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.inner.removes_rows()
+    }
+
+    // Rows come back grouped by id
+    fn reorders_rows(&self) -> bool {
+        true
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        self.inner.lookahead()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.inner.warmup()
+    }
+
+    fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+        if data.dataframe().column(&self.id_column).is_err() {
+            return Err(IndustrytsError::ColumnNotFound(self.id_column.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Apply `f` to each partition of `df` by the values of `partition_by`
+///
+/// `f` receives each partition in its original row order and must return as many
+/// rows as it received; the results are put back in the row order of `df`. Null
+/// keys form their own partition. With no partition columns, `f` runs on `df`.
+pub fn map_partitions<F>(df: &DataFrame, partition_by: &[String], f: F) -> Result<DataFrame>
+where
+    F: Fn(DataFrame) -> Result<DataFrame>,
+{
+    if partition_by.is_empty() {
+        return f(df.clone());
+    }
+    const ROW_INDEX: &str = "__industryts_row";
+
+    let partitions = df
+        .with_row_index(ROW_INDEX.into(), None)?
+        .partition_by_stable(partition_by.iter().map(String::as_str), true)?;
+    let mut stacked: Option<DataFrame> = None;
+    for mut partition in partitions {
+        let rows = partition.drop_in_place(ROW_INDEX)?;
+        let mut result = f(partition)?;
+        if result.height() != rows.len() {
+            return Err(IndustrytsError::OperationError(format!(
+                "partition of {} rows became {} rows",
+                rows.len(),
+                result.height()
+            )));
+        }
+        result.with_column(rows)?;
+        match stacked.as_mut() {
+            Some(stacked) => {
+                stacked.vstack_mut(&result)?;
+            }
+            None => stacked = Some(result),
+        }
+    }
+
+    let Some(stacked) = stacked else {
+        return f(df.clone());
+    };
+    let mut result = stacked.sort([ROW_INDEX], SortMultipleOptions::default())?;
+    result.drop_in_place(ROW_INDEX)?;
+    Ok(result)
+}
+
+/// Explicit `partition_by` columns, or the group columns of `data`
+pub(crate) fn partition_columns<'a>(
+    partition_by: &'a [String],
+    data: &'a TimeSeriesData,
+) -> &'a [String] {
+    if partition_by.is_empty() {
+        data.group_columns()
+    } else {
+        partition_by
+    }
+}
+
+/// Explicit columns, or all feature columns except the partition columns
+pub(crate) fn target_columns(
+    columns: Option<&[String]>,
+    feature_columns: &[String],
+    partition_by: &[String],
+) -> Vec<String> {
+    match columns {
+        Some(columns) => columns.to_vec(),
+        None => feature_columns
+            .iter()
+            .filter(|c| !partition_by.contains(c))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::StandardizeOperation;
+
+    fn panel() -> TimeSeriesData {
+        let time_series = Series::new(
+            "timestamp".into(),
+            &[0i64, 0, 60_000, 60_000, 120_000, 120_000],
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("unit".into(), &["a", "b", "a", "b", "a", "b"]).into(),
+            Series::new("value".into(), &[1.0, 100.0, 2.0, 200.0, 3.0, 300.0]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("timestamp")).unwrap()
+    }
+
+    #[test]
+    fn test_per_group_standardizes_each_entity() {
+        let op = PerGroupOperation::new("unit", Box::new(StandardizeOperation::new(None)));
+        let result = op.execute(panel()).unwrap();
+
+        let df = result.dataframe();
+        let names: Vec<&str> = df.get_column_names().iter().map(|n| n.as_str()).collect();
+        assert_eq!(names, vec!["timestamp", "unit", "value"]);
+
+        let units: Vec<Option<&str>> = df
+            .column("unit")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            units,
+            vec![
+                Some("a"),
+                Some("a"),
+                Some("a"),
+                Some("b"),
+                Some("b"),
+                Some("b")
+            ]
+        );
+        let values: Vec<f64> = df
+            .column("value")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(values, vec![-1.0, 0.0, 1.0, -1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_per_group_requires_id_column() {
+        let op = PerGroupOperation::new("site", Box::new(StandardizeOperation::new(None)));
+        assert_eq!(op.required_columns(), vec!["site".to_string()]);
+        assert!(op.execute(panel()).is_err());
+    }
+
+    #[test]
+    fn test_group_columns_partition_pipeline_steps() {
+        use crate::config::{AggMethod, FillMethod};
+        use crate::operations::{FillNullOperation, LagOperation, ResampleOperation};
+        use crate::pipeline::Pipeline;
+
+        let time_series = Series::new(
+            "timestamp".into(),
+            &[0i64, 0, 60_000, 60_000, 120_000, 120_000],
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("unit".into(), &["a", "b", "a", "b", "a", "b"]).into(),
+            Series::new(
+                "value".into(),
+                &[Some(1.0), Some(10.0), None, Some(20.0), Some(3.0), None],
+            )
+            .into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("timestamp"))
+            .unwrap()
+            .with_group_columns(&["unit".to_string()])
+            .unwrap();
+        assert_eq!(data.feature_columns(), ["value"]);
+        assert!(
+            TimeSeriesData::new(data.dataframe().clone(), Some("timestamp"))
+                .unwrap()
+                .with_group_columns(&["line".to_string()])
+                .is_err()
+        );
+
+        let column = |data: &TimeSeriesData, name: &str| -> Vec<Option<f64>> {
+            data.dataframe()
+                .column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(FillNullOperation::new(FillMethod::Forward, None)));
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        let features = pipeline.process(data).unwrap();
+        assert_eq!(features.group_columns(), ["unit"]);
+        // The forward fill of b's last row does not borrow a's value, and lags
+        // start over for every unit
+        assert_eq!(
+            column(&features, "value"),
+            [
+                Some(1.0),
+                Some(10.0),
+                Some(1.0),
+                Some(20.0),
+                Some(3.0),
+                Some(20.0)
+            ]
+        );
+        assert_eq!(
+            column(&features, "value_lag_1"),
+            [None, None, Some(1.0), Some(10.0), Some(1.0), Some(20.0)]
+        );
+
+        let resampled = ResampleOperation::new("2m", AggMethod::Mean, Some(vec!["value".into()]))
+            .unwrap()
+            .execute(features)
+            .unwrap();
+        assert_eq!(resampled.group_columns(), ["unit"]);
+        assert_eq!(
+            column(&resampled, "value"),
+            [Some(1.0), Some(15.0), Some(3.0), Some(20.0)]
+        );
+    }
+}
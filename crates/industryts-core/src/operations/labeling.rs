@@ -0,0 +1,381 @@
+//! Supervised labels from event frames
+//!
+//! Predictive-maintenance datasets pair sensor history with an event-frame table
+//! (failures, trips, alarms) holding a start and end time per event, optionally per
+//! asset. `EventLabelOperation` labels each sample window as `pre_event` when an
+//! event starts within the prediction horizon after the window, `in_event` while it
+//! overlaps an event and `normal` otherwise.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+/// Window far from any event
+pub const LABEL_NORMAL: &str = "normal";
+/// Window ending within the prediction horizon before an event
+pub const LABEL_PRE_EVENT: &str = "pre_event";
+/// Window overlapping an event
+pub const LABEL_IN_EVENT: &str = "in_event";
+
+/// Event frames of one asset, sorted by start
+#[derive(Debug, Clone, Default)]
+struct Frames {
+    starts: Vec<i64>,
+    ends: Vec<i64>,
+    /// Running maximum of the end times, aligned with `starts`
+    max_ends: Vec<i64>,
+}
+
+impl Frames {
+    fn new(mut frames: Vec<(i64, i64)>) -> Self {
+        frames.sort_unstable();
+        let starts = frames.iter().map(|f| f.0).collect();
+        let ends = frames.iter().map(|f| f.1).collect();
+        let max_ends = frames
+            .iter()
+            .scan(i64::MIN, |max, f| {
+                *max = (*max).max(f.1);
+                Some(*max)
+            })
+            .collect();
+        Self {
+            starts,
+            ends,
+            max_ends,
+        }
+    }
+}
+
+/// Table of events with start/end times (UTC milliseconds) and optional asset IDs
+#[derive(Debug, Clone, Default)]
+pub struct EventFrames {
+    frames: HashMap<Option<String>, Frames>,
+    len: usize,
+}
+
+impl EventFrames {
+    /// Build from `(asset, start_ms, end_ms)` records
+    pub fn new(records: Vec<(Option<String>, i64, i64)>) -> Self {
+        let len = records.len();
+        let mut grouped: HashMap<Option<String>, Vec<(i64, i64)>> = HashMap::new();
+        for (asset, start, end) in records {
+            grouped
+                .entry(asset)
+                .or_default()
+                .push((start, end.max(start)));
+        }
+        let frames = grouped
+            .into_iter()
+            .map(|(asset, frames)| (asset, Frames::new(frames)))
+            .collect();
+        Self { frames, len }
+    }
+
+    /// Read event frames from a dataframe with datetime start and end columns
+    pub fn from_dataframe(
+        df: &DataFrame,
+        start_column: &str,
+        end_column: &str,
+        asset_column: Option<&str>,
+    ) -> Result<Self> {
+        let starts = TimeSeriesData::new(df.clone(), Some(start_column))?.timestamps_ms()?;
+        let ends = TimeSeriesData::new(df.clone(), Some(end_column))?.timestamps_ms()?;
+        let assets: Vec<Option<String>> = match asset_column {
+            Some(c) => df
+                .column(c)?
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|a| a.map(str::to_string))
+                .collect(),
+            None => vec![None; df.height()],
+        };
+
+        let mut records = Vec::with_capacity(df.height());
+        for ((start, end), asset) in starts.into_iter().zip(&ends).zip(assets) {
+            match (start, end) {
+                (Some(start), Some(end)) => records.push((asset, start, end)),
+                (Some(start), None) => records.push((asset, start, i64::MAX)),
+                _ => {
+                    return Err(IndustrytsError::InvalidOperation(format!(
+                        "Event frame without a start time in column: {}",
+                        start_column
+                    )));
+                }
+            }
+        }
+        Ok(Self::new(records))
+    }
+
+    /// Load event frames from an Arrow IPC file
+    pub fn load(
+        path: &Path,
+        start_column: &str,
+        end_column: &str,
+        asset_column: Option<&str>,
+    ) -> Result<Self> {
+        let df = IpcReader::new(File::open(path)?).finish()?;
+        Self::from_dataframe(&df, start_column, end_column, asset_column)
+    }
+
+    /// Number of events
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the events carry asset IDs
+    pub fn has_assets(&self) -> bool {
+        self.frames.keys().any(Option::is_some)
+    }
+
+    /// `(asset, start_ms, end_ms)` of every event, by asset then start; open-ended
+    /// events end at `i64::MAX`
+    pub fn events(&self) -> Vec<(Option<&str>, i64, i64)> {
+        let mut assets: Vec<&Option<String>> = self.frames.keys().collect();
+        assets.sort();
+        assets
+            .into_iter()
+            .flat_map(|asset| {
+                let frames = &self.frames[asset];
+                frames
+                    .starts
+                    .iter()
+                    .zip(&frames.ends)
+                    .map(move |(&start, &end)| (asset.as_deref(), start, end))
+            })
+            .collect()
+    }
+}
+
+/// Event label operation - label windows as normal, pre-event or in-event
+///
+/// A sample at `t` stands for the window `[t, t + window]` (default: the instant
+/// `t`). Appends the label column (default `event_label`) and
+/// `<label>_time_to_event`, the seconds from `t` until the next event starts (0
+/// inside an event, null when no event follows). An open-ended event (null end)
+/// lasts forever.
+pub struct EventLabelOperation {
+    events: EventFrames,
+    pre_horizon: Duration,
+    window: Duration,
+    asset_column: Option<String>,
+    output: String,
+}
+
+impl EventLabelOperation {
+    /// Label windows ending at most `pre_horizon` before an event as `pre_event`
+    pub fn new(events: EventFrames, pre_horizon: Duration) -> Self {
+        Self {
+            events,
+            pre_horizon,
+            window: Duration::ZERO,
+            asset_column: None,
+            output: "event_label".to_string(),
+        }
+    }
+
+    /// Length of the window each sample stands for
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Match events to rows by this column (the events must carry asset IDs)
+    pub fn with_asset_column(mut self, column: &str) -> Self {
+        self.asset_column = Some(column.to_string());
+        self
+    }
+
+    /// Name of the label column
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output = output.to_string();
+        self
+    }
+
+    fn time_to_event_column(&self) -> String {
+        format!("{}_time_to_event", self.output)
+    }
+
+    fn label(&self, frames: &Frames, t: i64) -> (&'static str, Option<f64>) {
+        let window_end = t.saturating_add(self.window.as_millis() as i64);
+        let horizon = self.pre_horizon.as_millis() as i64;
+
+        // Events starting no later than the window end; do any reach into it?
+        let started = frames.starts.partition_point(|&s| s <= window_end);
+        if started > 0 && frames.max_ends[started - 1] >= t {
+            let in_event_at_t = frames.starts.partition_point(|&s| s <= t);
+            let tte = if in_event_at_t > 0 && frames.max_ends[in_event_at_t - 1] >= t {
+                0.0
+            } else {
+                (frames.starts[in_event_at_t] - t) as f64 / 1000.0
+            };
+            return (LABEL_IN_EVENT, Some(tte));
+        }
+        match frames.starts.get(started) {
+            Some(&next) => {
+                let tte = Some((next - t) as f64 / 1000.0);
+                if next - window_end <= horizon {
+                    (LABEL_PRE_EVENT, tte)
+                } else {
+                    (LABEL_NORMAL, tte)
+                }
+            }
+            None => (LABEL_NORMAL, None),
+        }
+    }
+}
+
+impl Operation for EventLabelOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        if self.asset_column.is_none() && self.events.has_assets() {
+            return Err(IndustrytsError::InvalidOperation(
+                "Event frames carry asset IDs; set an asset column to match them".to_string(),
+            ));
+        }
+        let timestamps = data.timestamps_ms()?;
+        let assets: Vec<Option<String>> = match &self.asset_column {
+            Some(c) => data
+                .dataframe()
+                .column(c)?
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|a| a.map(str::to_string))
+                .collect(),
+            None => vec![None; data.len()],
+        };
+
+        let empty = Frames::default();
+        let mut labels = Vec::with_capacity(data.len());
+        let mut tte = Vec::with_capacity(data.len());
+        for (t, asset) in timestamps.into_iter().zip(&assets) {
+            let frames = self.events.frames.get(asset).unwrap_or(&empty);
+            let (label, seconds) = match t {
+                Some(t) => self.label(frames, t),
+                None => (LABEL_NORMAL, None),
+            };
+            labels.push(label);
+            tte.push(seconds);
+        }
+
+        let mut df = data.dataframe().clone();
+        df.with_column(Series::new(self.output.as_str().into(), labels))?;
+        df.with_column(Series::new(self.time_to_event_column().into(), tte))?;
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "event_label"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.asset_column.iter().cloned().collect()
+    }
+
+    fn added_columns(&self, _feature_columns: &[String]) -> Vec<String> {
+        vec![self.output.clone(), self.time_to_event_column()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: i64 = 60_000;
+
+    fn make_data(assets: &[&str]) -> TimeSeriesData {
+        // 20 one-minute samples per asset
+        let mut times = Vec::new();
+        let mut ids = Vec::new();
+        for asset in assets {
+            for i in 0..20 {
+                times.push(i * MIN);
+                ids.push(asset.to_string());
+            }
+        }
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("pump".into(), ids).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn labels(data: &TimeSeriesData) -> Vec<String> {
+        data.dataframe()
+            .column("event_label")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_labels_pre_and_in_event() {
+        let events = EventFrames::new(vec![(None, 10 * MIN, 12 * MIN)]);
+        let op = EventLabelOperation::new(events, Duration::from_secs(180));
+        let result = op.execute(make_data(&["p1"])).unwrap();
+        let labels = labels(&result);
+
+        assert_eq!(labels[6], LABEL_NORMAL);
+        assert_eq!(labels[7], LABEL_PRE_EVENT);
+        assert_eq!(labels[9], LABEL_PRE_EVENT);
+        assert_eq!(labels[10], LABEL_IN_EVENT);
+        assert_eq!(labels[12], LABEL_IN_EVENT);
+        assert_eq!(labels[13], LABEL_NORMAL);
+
+        let tte = result
+            .dataframe()
+            .column("event_label_time_to_event")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .clone();
+        assert_eq!(tte.get(7), Some(180.0));
+        assert_eq!(tte.get(11), Some(0.0));
+        assert_eq!(tte.get(13), None);
+    }
+
+    #[test]
+    fn test_windows_and_assets() {
+        let start = Series::new("start".into(), [5 * MIN])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let end = Series::new("end".into(), [6 * MIN])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let table = DataFrame::new(vec![
+            start.into(),
+            end.into(),
+            Series::new("asset".into(), ["p2"]).into(),
+        ])
+        .unwrap();
+        let events = EventFrames::from_dataframe(&table, "start", "end", Some("asset")).unwrap();
+
+        let op = EventLabelOperation::new(events, Duration::ZERO)
+            .with_window(Duration::from_secs(120))
+            .with_asset_column("pump");
+        let labels = labels(&op.execute(make_data(&["p1", "p2"])).unwrap());
+
+        assert!(labels[..20].iter().all(|l| l == LABEL_NORMAL));
+        // The window [3m, 5m] touches the event start
+        assert_eq!(labels[20 + 3], LABEL_IN_EVENT);
+        assert_eq!(labels[20 + 2], LABEL_NORMAL);
+    }
+}
@@ -0,0 +1,273 @@
+//! Column mapping from external tag dictionaries
+//!
+//! Historian exports name columns after raw tags such as `45TI1234.PV`. A tag
+//! dictionary maps each tag to a friendly name and optionally a unit and a
+//! description. It can be a TOML file:
+//!
+//! ```toml
+//! [[columns]]
+//! tag = "45TI1234.PV"
+//! name = "reactor_inlet_temp"
+//! unit = "degC"
+//! description = "Reactor inlet temperature"
+//! asset = "plant_a/reactor_1"
+//! ```
+//!
+//! or a CSV file with a header row naming the `tag`, `name`, `unit`,
+//! `description` and `asset` columns (the last three optional, in any order).
+//! The asset is the column's path in the asset hierarchy, used by fleet
+//! aggregation.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Mapping of one raw tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub tag: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Path in the asset hierarchy, e.g. `plant_a/line_1/pump_3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MappingFile {
+    columns: Vec<ColumnMapping>,
+}
+
+/// Split a CSV line, honouring double-quoted fields with `""` escapes
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// Parse a CSV tag dictionary
+fn parse_csv(contents: &str) -> Result<Vec<ColumnMapping>> {
+    let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+    let header = split_csv_line(lines.next().unwrap_or_default());
+    let position = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(tag), Some(name)) = (position("tag"), position("name")) else {
+        return Err(IndustrytsError::ConfigError(
+            "Column mapping CSV needs 'tag' and 'name' header columns".to_string(),
+        ));
+    };
+    let (unit, description, asset) = (position("unit"), position("description"), position("asset"));
+
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let fields = split_csv_line(line);
+            let get = |index: Option<usize>| {
+                index
+                    .and_then(|i| fields.get(i))
+                    .filter(|f| !f.is_empty())
+                    .cloned()
+            };
+            match (get(Some(tag)), get(Some(name))) {
+                (Some(tag), Some(name)) => Ok(ColumnMapping {
+                    tag,
+                    name,
+                    unit: get(unit),
+                    description: get(description),
+                    asset: get(asset),
+                }),
+                _ => Err(IndustrytsError::ConfigError(format!(
+                    "Column mapping CSV line {} lacks a tag or name",
+                    i + 2
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Map columns operation - rename raw tags and record units and descriptions
+///
+/// Renamed columns get the column properties `unit`, `description` and `source`
+/// (the raw tag), see `TimeSeriesData::column_property`. Tags missing from the data
+/// are skipped unless the operation is strict.
+pub struct MapColumnsOperation {
+    mappings: Vec<ColumnMapping>,
+    strict: bool,
+    drop_unmapped: bool,
+}
+
+impl MapColumnsOperation {
+    pub fn new(mappings: Vec<ColumnMapping>) -> Result<Self> {
+        for (i, mapping) in mappings.iter().enumerate() {
+            if mappings[..i].iter().any(|m| m.name == mapping.name) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Column mapping assigns the name '{}' twice",
+                    mapping.name
+                )));
+            }
+        }
+        Ok(Self {
+            mappings,
+            strict: false,
+            drop_unmapped: false,
+        })
+    }
+
+    /// Load a tag dictionary from a `.toml` or `.csv` file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let is_csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let mappings = if is_csv {
+            parse_csv(&contents)?
+        } else {
+            toml::from_str::<MappingFile>(&contents)?.columns
+        };
+        Self::new(mappings)
+    }
+
+    /// Fail when a mapped tag is missing from the data
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Drop feature columns that have no mapping
+    pub fn with_drop_unmapped(mut self, drop_unmapped: bool) -> Self {
+        self.drop_unmapped = drop_unmapped;
+        self
+    }
+}
+
+impl Operation for MapColumnsOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        let mut time_col = data.time_column().to_string();
+        let mut mapped = Vec::new();
+
+        for mapping in &self.mappings {
+            if df.column(&mapping.tag).is_err() {
+                if self.strict {
+                    return Err(IndustrytsError::ColumnNotFound(mapping.tag.clone()));
+                }
+                continue;
+            }
+            df.rename(&mapping.tag, mapping.name.as_str().into())?;
+            if mapping.tag == time_col {
+                time_col = mapping.name.clone();
+            }
+            mapped.push(mapping);
+        }
+
+        if self.drop_unmapped {
+            let unmapped: Vec<String> = data
+                .feature_columns()
+                .iter()
+                .filter(|c| !self.mappings.iter().any(|m| &m.tag == *c))
+                .cloned()
+                .collect();
+            df = df.drop_many(unmapped);
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(&time_col))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        for mapping in mapped {
+            result.set_column_property(&mapping.name, "source", &mapping.tag);
+            if let Some(unit) = &mapping.unit {
+                result.set_column_property(&mapping.name, "unit", unit);
+            }
+            if let Some(description) = &mapping.description {
+                result.set_column_property(&mapping.name, "description", description);
+            }
+            if let Some(asset) = &mapping.asset {
+                result.set_column_property(&mapping.name, "asset", asset);
+            }
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "map_columns"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        if self.strict {
+            self.mappings.iter().map(|m| m.tag.clone()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        self.mappings
+            .iter()
+            .filter(|m| feature_columns.contains(&m.tag))
+            .map(|m| m.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    fn make_data() -> TimeSeriesData {
+        let times: Vec<i64> = (0..3).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("45TI1234.PV".into(), [350.1, 350.4, 351.0]).into(),
+            Series::new("45FI0101.PV".into(), [12.0, 12.5, 12.2]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_map_columns_from_csv() {
+        let csv = "tag,name,unit,description\n\
+                   45TI1234.PV,reactor_temp,degC,\"Reactor inlet temperature, bed 1\"\n\
+                   99XX0000.PV,unused,,\n";
+        let op = MapColumnsOperation::new(parse_csv(csv).unwrap())
+            .unwrap()
+            .with_drop_unmapped(true);
+        let result = op.execute(make_data()).unwrap();
+
+        assert_eq!(result.feature_columns(), ["reactor_temp"]);
+        assert_eq!(result.column_unit("reactor_temp"), Some("degC"));
+        assert_eq!(
+            result.column_description("reactor_temp"),
+            Some("Reactor inlet temperature, bed 1")
+        );
+        assert_eq!(
+            result.column_property("reactor_temp", "source"),
+            Some("45TI1234.PV")
+        );
+
+        let strict = MapColumnsOperation::new(parse_csv(csv).unwrap())
+            .unwrap()
+            .with_strict(true);
+        assert!(strict.execute(make_data()).is_err());
+    }
+}
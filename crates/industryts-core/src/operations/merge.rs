@@ -0,0 +1,580 @@
+//! Combining time series from several sources
+//!
+//! Plants spread their data over systems sampled at different rates: the DCS
+//! every second, the LIMS a few times a day, the MES per batch. Two ways bring
+//! them together:
+//! - `TimeSeriesData::join_asof` adds to every row the columns of the nearest
+//!   row of another series (backward, forward or nearest in time), optionally
+//!   within a tolerance and matched by group columns present in both
+//! - `TimeSeriesData::concat` stacks series with the same kind of rows, filling
+//!   the columns a part lacks with nulls and sorting the rows by time
+//!
+//! Exports of the same source drift over time as tags are added and retired.
+//! `TimeSeriesData::concat_with` reconciles the schemas of the parts by taking the
+//! union (nulls where a part lacks a column), the intersection, or failing on any
+//! difference, and reports the columns missing from some parts or stored with
+//! another dtype.
+//!
+//! Time columns may have different names and units; the first (left) series
+//! keeps its own. Neither input needs to be sorted. Tags of all inputs are
+//! merged, the first input's winning on conflicts. `MergeOperation` runs either
+//! as a pipeline step, with the other input read from a file:
+//!
+//! ```toml
+//! [[operations]]
+//! type = "merge"
+//! how = "asof"
+//! tolerance = "8h"
+//! source = { path = "lims.csv", time_column = "sample_time" }
+//! ```
+//!
+//! or taken from the named datasets of the run (`input = "weather"`), supplied
+//! by `Pipeline::process_with_inputs` or by the other pipelines of a
+//! `PipelineSet`.
+
+use crate::core::data::column_tag;
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::io::SourceConfig;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+/// Row of the other series matched by an as-of join
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsofStrategy {
+    /// Latest row at or before the row's time
+    #[default]
+    Backward,
+    /// Earliest row at or after the row's time
+    Forward,
+    /// Closest row in time, the earlier one on ties
+    Nearest,
+}
+
+/// How `MergeOperation` combines its inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeHow {
+    /// Add the other input's columns with an as-of join
+    #[default]
+    Asof,
+    /// Append the other input's rows
+    Concat,
+}
+
+/// How `TimeSeriesData::concat_with` handles parts with different columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaReconciliation {
+    /// Keep every column, null in the rows of parts without it
+    #[default]
+    Union,
+    /// Keep only the columns of every part
+    Intersection,
+    /// Fail unless all parts have the same columns and dtypes
+    Strict,
+}
+
+/// Differences between the schemas of concatenated parts
+///
+/// Parts are identified by their index in the concatenated slice.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaDiff {
+    /// Columns absent from some parts, with the indices of those parts
+    pub missing: BTreeMap<String, Vec<usize>>,
+    /// Columns stored with another dtype than in the first part having them
+    pub retyped: BTreeMap<String, Vec<(usize, DataType)>>,
+    /// Columns left out of the result
+    pub dropped: Vec<String>,
+}
+
+impl SchemaDiff {
+    /// Whether all parts had the same schema
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.retyped.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut differences = Vec::new();
+        for (column, parts) in &self.missing {
+            differences.push(format!("'{}' missing from part(s) {:?}", column, parts));
+        }
+        for (column, parts) in &self.retyped {
+            for (part, dtype) in parts {
+                differences.push(format!("'{}' is {} in part {}", column, dtype, part));
+            }
+        }
+        write!(f, "{}", differences.join(", "))
+    }
+}
+
+/// Suffix of joined columns whose name is already taken
+const CLASH_SUFFIX: &str = "_right";
+
+/// Key of every row from the values of `columns`
+pub(crate) fn row_keys(df: &DataFrame, columns: &[String]) -> Result<Vec<String>> {
+    let mut keys = vec![String::new(); df.height()];
+    for name in columns {
+        let column = df.column(name)?.as_materialized_series();
+        for (key, value) in keys.iter_mut().zip(column.iter()) {
+            key.push_str(&value.to_string());
+            key.push('\u{1f}');
+        }
+    }
+    Ok(keys)
+}
+
+/// Tags of `other` missing from `tags`, with column properties renamed
+fn merge_tags(
+    tags: &mut HashMap<String, String>,
+    other: &TimeSeriesData,
+    renamed: &HashMap<String, String>,
+) {
+    for (key, value) in &other.metadata().tags {
+        let key = renamed
+            .iter()
+            .find_map(|(from, to)| {
+                let field = key.strip_prefix(&format!("column.{}.", from))?;
+                Some(column_tag(to, field))
+            })
+            .unwrap_or_else(|| key.clone());
+        tags.entry(key).or_insert_with(|| value.clone());
+    }
+}
+
+impl TimeSeriesData {
+    /// Add the feature columns of `other` at the matching rows of `other`
+    ///
+    /// Each row is matched with the row of `other` chosen by `strategy`, no more
+    /// than `tolerance` away; rows without a match get nulls. Group columns of
+    /// this series that `other` also has must match too. Columns of `other` whose
+    /// name is taken get the suffix `_right`. The rows keep their order.
+    pub fn join_asof(
+        &self,
+        other: &TimeSeriesData,
+        tolerance: Option<Duration>,
+        strategy: AsofStrategy,
+    ) -> Result<TimeSeriesData> {
+        let by: Vec<String> = self
+            .group_columns()
+            .iter()
+            .filter(|c| other.dataframe().column(c).is_ok())
+            .cloned()
+            .collect();
+        let tolerance = tolerance.map(|t| t.as_millis() as i64);
+
+        // Rows of `other` per key, ordered by time
+        let mut index: HashMap<String, Vec<(i64, usize)>> = HashMap::new();
+        let other_times = other.timestamps_ms()?;
+        for (row, (key, time)) in row_keys(other.dataframe(), &by)?
+            .into_iter()
+            .zip(other_times.iter())
+            .enumerate()
+        {
+            if let Some(time) = time {
+                index.entry(key).or_default().push((time, row));
+            }
+        }
+        for rows in index.values_mut() {
+            rows.sort_unstable();
+        }
+
+        let times = self.timestamps_ms()?;
+        let matches: IdxCa = row_keys(self.dataframe(), &by)?
+            .iter()
+            .zip(times.iter())
+            .map(|(key, time)| {
+                let (rows, time) = (index.get(key)?, time?);
+                let before = rows.partition_point(|&(t, _)| t <= time);
+                let after = rows.partition_point(|&(t, _)| t < time);
+                let backward = before.checked_sub(1).map(|i| rows[i]);
+                let forward = rows.get(after).copied();
+                let (t, row) = match strategy {
+                    AsofStrategy::Backward => backward?,
+                    AsofStrategy::Forward => forward?,
+                    AsofStrategy::Nearest => match (backward, forward) {
+                        (Some(b), Some(f)) if f.0 - time < time - b.0 => f,
+                        (Some(b), _) => b,
+                        (None, f) => f?,
+                    },
+                };
+                match tolerance {
+                    Some(tolerance) if (t - time).abs() > tolerance => None,
+                    _ => Some(row as IdxSize),
+                }
+            })
+            .collect();
+
+        let mut df = self.dataframe().clone();
+        let mut metadata = self.metadata().clone();
+        let mut renamed = HashMap::new();
+        let joined: Vec<&String> = other
+            .feature_columns()
+            .iter()
+            .filter(|c| !by.contains(c) && !other.group_columns().contains(c))
+            .collect();
+        for name in joined {
+            let mut column = other.dataframe().column(name)?.take(&matches)?;
+            if df.column(name).is_ok() {
+                let new_name = format!("{}{}", name, CLASH_SUFFIX);
+                if df.column(&new_name).is_ok() {
+                    return Err(IndustrytsError::InvalidOperation(format!(
+                        "Joined column '{}' clashes with '{}'",
+                        name, new_name
+                    )));
+                }
+                column.rename(new_name.as_str().into());
+                renamed.insert(name.clone(), new_name);
+            }
+            metadata.feature_columns.push(column.name().to_string());
+            df.with_column(column)?;
+        }
+        merge_tags(&mut metadata.tags, other, &renamed);
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    /// Stack the rows of `parts`, sorted by time
+    ///
+    /// The first part gives the time column, group columns and the dtype of each
+    /// column; other parts are renamed and cast to match. Columns missing from a
+    /// part are null in its rows.
+    pub fn concat(parts: &[TimeSeriesData]) -> Result<TimeSeriesData> {
+        Self::concat_with(parts, SchemaReconciliation::Union).map(|(data, _)| data)
+    }
+
+    /// Stack the rows of `parts`, reconciling their columns
+    ///
+    /// Like `concat`, but columns missing from some parts are kept or dropped
+    /// according to `reconciliation`. Returns the result and the differences
+    /// between the parts' schemas.
+    pub fn concat_with(
+        parts: &[TimeSeriesData],
+        reconciliation: SchemaReconciliation,
+    ) -> Result<(TimeSeriesData, SchemaDiff)> {
+        let Some(first) = parts.first() else {
+            return Err(IndustrytsError::InvalidOperation(
+                "Nothing to concatenate".to_string(),
+            ));
+        };
+        let time_col = first.time_column();
+        let name_in = |part: &TimeSeriesData, name: &str| -> Option<String> {
+            if name == time_col {
+                return Some(part.time_column().to_string());
+            }
+            let present = name != part.time_column() && part.dataframe().column(name).is_ok();
+            present.then(|| name.to_string())
+        };
+
+        // Union of the columns in order of appearance, dtype of the first occurrence
+        let mut schema: Vec<(String, DataType)> = Vec::new();
+        for part in parts {
+            for column in part.dataframe().get_columns() {
+                let name = if column.name() == part.time_column() {
+                    time_col
+                } else {
+                    column.name().as_str()
+                };
+                if !schema.iter().any(|(n, _)| n == name) {
+                    schema.push((name.to_string(), column.dtype().clone()));
+                }
+            }
+        }
+
+        let mut diff = SchemaDiff::default();
+        for (name, dtype) in &schema {
+            for (index, part) in parts.iter().enumerate() {
+                match name_in(part, name) {
+                    None => diff.missing.entry(name.clone()).or_default().push(index),
+                    Some(source) => {
+                        let found = part.dataframe().column(&source)?.dtype();
+                        if found != dtype {
+                            let retyped = diff.retyped.entry(name.clone()).or_default();
+                            retyped.push((index, found.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        match reconciliation {
+            SchemaReconciliation::Strict if !diff.is_empty() => {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "Schemas of the concatenated parts differ: {}",
+                    diff
+                )));
+            }
+            SchemaReconciliation::Intersection => {
+                schema.retain(|(name, _)| !diff.missing.contains_key(name));
+                diff.dropped = diff.missing.keys().cloned().collect();
+            }
+            _ => {}
+        }
+
+        let mut stacked: Option<DataFrame> = None;
+        let mut metadata = first.metadata().clone();
+        for part in parts {
+            let df = part.dataframe();
+            let mut columns = Vec::with_capacity(schema.len());
+            for (name, dtype) in &schema {
+                let column = match name_in(part, name) {
+                    Some(source) => df
+                        .column(&source)?
+                        .cast(dtype)?
+                        .with_name(name.as_str().into()),
+                    None => Column::full_null(name.as_str().into(), df.height(), dtype),
+                };
+                columns.push(column);
+            }
+            let df = DataFrame::new(columns)?;
+            match &mut stacked {
+                Some(stacked) => {
+                    stacked.vstack_mut(&df)?;
+                }
+                None => stacked = Some(df),
+            }
+            merge_tags(&mut metadata.tags, part, &HashMap::new());
+        }
+
+        let df = stacked.unwrap_or_default().sort(
+            [time_col],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?;
+        metadata.group_columns.retain(|c| df.column(c).is_ok());
+        metadata.feature_columns = schema
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name != time_col && !metadata.group_columns.contains(name))
+            .collect();
+        Ok((TimeSeriesData::with_metadata(df, metadata)?, diff))
+    }
+}
+
+/// The other input of a `MergeOperation`
+enum MergeInput {
+    Data(TimeSeriesData),
+    Source(SourceConfig),
+    Catalog(String),
+}
+
+/// Merge operation - join or append a second input
+pub struct MergeOperation {
+    input: MergeInput,
+    how: MergeHow,
+    tolerance: Option<Duration>,
+    strategy: AsofStrategy,
+    schema: SchemaReconciliation,
+}
+
+impl MergeOperation {
+    /// Merge with `other`
+    pub fn new(other: TimeSeriesData, how: MergeHow) -> Self {
+        Self::with_input(MergeInput::Data(other), how)
+    }
+
+    /// Merge with the file of `source`, read on every execution
+    pub fn from_source(source: SourceConfig, how: MergeHow) -> Self {
+        Self::with_input(MergeInput::Source(source), how)
+    }
+
+    /// Merge with the dataset `name` of the run's catalog (see `OpContext::input`)
+    pub fn from_catalog(name: &str, how: MergeHow) -> Self {
+        Self::with_input(MergeInput::Catalog(name.to_string()), how)
+    }
+
+    fn with_input(input: MergeInput, how: MergeHow) -> Self {
+        Self {
+            input,
+            how,
+            tolerance: None,
+            strategy: AsofStrategy::default(),
+            schema: SchemaReconciliation::default(),
+        }
+    }
+
+    /// Largest time distance of an as-of match
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Row matched by an as-of join (default backward)
+    pub fn with_strategy(mut self, strategy: AsofStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Handling of differing columns when appending (default union)
+    pub fn with_schema_reconciliation(mut self, schema: SchemaReconciliation) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Merge `data` with the other input; returns the result and, when
+    /// appending, the schema differences
+    fn run(
+        &self,
+        data: TimeSeriesData,
+        ctx: Option<&OpContext>,
+    ) -> Result<(TimeSeriesData, SchemaDiff)> {
+        let other = match &self.input {
+            MergeInput::Data(other) => other.clone(),
+            MergeInput::Source(source) => source.read(None)?,
+            MergeInput::Catalog(name) => {
+                ctx.and_then(|ctx| ctx.input(name))
+                    .cloned()
+                    .ok_or_else(|| {
+                        IndustrytsError::InvalidOperation(format!(
+                            "merge input '{}' is not among the datasets of the run",
+                            name
+                        ))
+                    })?
+            }
+        };
+        match self.how {
+            MergeHow::Asof => Ok((
+                data.join_asof(&other, self.tolerance, self.strategy)?,
+                SchemaDiff::default(),
+            )),
+            MergeHow::Concat => TimeSeriesData::concat_with(&[data, other], self.schema),
+        }
+    }
+}
+
+impl Operation for MergeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, diff) = self.run(data, Some(ctx))?;
+        if !diff.is_empty() {
+            ctx.warn(format!("Appended schema differs: {}", diff));
+        }
+        if !diff.dropped.is_empty() {
+            ctx.warn(format!("Dropped columns: {}", diff.dropped.join(", ")));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "merge"
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        // Columns of a file or catalog input are only known once it is read
+        match (&self.input, self.how) {
+            (MergeInput::Data(other), MergeHow::Asof) => other
+                .feature_columns()
+                .iter()
+                .filter(|c| !other.group_columns().contains(c))
+                .map(|c| match feature_columns.contains(c) {
+                    true => format!("{}{}", c, CLASH_SUFFIX),
+                    false => c.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn catalog_inputs(&self) -> Vec<String> {
+        match &self.input {
+            MergeInput::Catalog(name) => vec![name.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        match (self.how, self.strategy) {
+            (MergeHow::Asof, AsofStrategy::Forward | AsofStrategy::Nearest) => {
+                vec![format!("as-of join with the {:?} strategy", self.strategy).to_lowercase()]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        match self.how {
+            MergeHow::Asof => (input_rows, Some(input_rows)),
+            MergeHow::Concat => (input_rows, None),
+        }
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.how == MergeHow::Concat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_join_asof_and_concat() {
+        let start = 1_704_067_200_000;
+        let dcs = Fixture::every(Duration::from_secs(3600), 4)
+            .with_column("temp", [80.0, 81.0, 82.0, 83.0])
+            .build()
+            .unwrap();
+        // Lab samples out of order, under another time column name
+        let lab = Fixture::at_times_ms(vec![start + 5_400_000, start - 600_000])
+            .with_column("temp", [79.5, 80.5])
+            .with_column("purity", [0.97, 0.95])
+            .build()
+            .unwrap();
+        let mut lab_df = lab.into_dataframe();
+        lab_df.rename("time", "sample_time".into()).unwrap();
+        let mut lab = TimeSeriesData::new(lab_df, Some("sample_time")).unwrap();
+        lab.set_column_property("purity", "unit", "%");
+
+        let joined = dcs
+            .join_asof(
+                &lab,
+                Some(Duration::from_secs(3600)),
+                AsofStrategy::Backward,
+            )
+            .unwrap();
+        let purity = joined.dataframe().column("purity").unwrap();
+        let purity: Vec<Option<f64>> = purity.f64().unwrap().into_iter().collect();
+        assert_eq!(purity, [Some(0.95), None, Some(0.97), None]);
+        assert!(joined.feature_columns().contains(&"temp_right".to_string()));
+        assert_eq!(joined.column_unit("purity"), Some("%"));
+
+        let nearest = dcs.join_asof(&lab, None, AsofStrategy::Nearest).unwrap();
+        let purity = nearest.dataframe().column("purity").unwrap();
+        assert_eq!(purity.f64().unwrap().get(3), Some(0.97));
+
+        let stacked = TimeSeriesData::concat(&[dcs.clone(), lab.clone()]).unwrap();
+        assert_eq!(stacked.len(), 6);
+        assert_eq!(stacked.time_column(), "time");
+        let times: Vec<Option<i64>> = stacked.timestamps_ms().unwrap().into_iter().collect();
+        assert!(times.is_sorted());
+        assert_eq!(
+            stacked.dataframe().column("purity").unwrap().null_count(),
+            4
+        );
+
+        let (common, diff) = TimeSeriesData::concat_with(
+            &[dcs.clone(), lab.clone()],
+            SchemaReconciliation::Intersection,
+        )
+        .unwrap();
+        assert_eq!(common.feature_columns(), ["temp"]);
+        assert_eq!(diff.missing.get("purity"), Some(&vec![0]));
+        assert_eq!(diff.dropped, ["purity"]);
+        let Err(strict) = TimeSeriesData::concat_with(&[dcs, lab], SchemaReconciliation::Strict)
+        else {
+            panic!("differing schemas must fail in strict mode");
+        };
+        assert!(strict.to_string().contains("'purity' missing"));
+    }
+}
@@ -1,17 +1,77 @@
 //! Time series operations module
 //!
 //! This module provides various operations for time series data processing organized by category:
+//! - anomaly: anomaly detection (control charts, rate-of-change, stuck sensors)
+//! - anonymize: reversible masking of names, timestamps and values for sharing
 //! - data_quality: data cleaning and validation
+//! - derive: computed columns from arithmetic formulas
+//! - event_stats: statistics of signals before, during and after events
 //! - temporal: time-based operations
 //! - features: feature engineering operations
+//! - fleet: aggregation of a measurement across assets of the hierarchy
 //! - transform: data transformation operations
+//! - dtypes: memory-saving dtype optimization
+//! - group: per-entity execution for panel data
+//! - labeling: supervised labels from event frames
+//! - mapping: renaming raw tags from external dictionaries
+//! - recipe: step chains applied to column families
+//! - merge: as-of joins and concatenation of several sources
+//! - monitoring: process monitoring and drift detection
+//! - privacy: calibrated noise on aggregates for differentially private sharing
+//! - selection: pruning of empty, flat and collinear feature columns
+//! - text: cleanup of and pattern extraction from text columns
+//! - units: conversion of columns to one engineering unit
 
+pub mod anomaly;
+pub mod anonymize;
 pub mod data_quality;
+pub mod derive;
+pub mod dtypes;
+pub mod event_stats;
 pub mod features;
+pub mod fleet;
+pub mod group;
+pub mod labeling;
+pub mod mapping;
+pub mod merge;
+pub mod monitoring;
+pub mod privacy;
+pub mod recipe;
+pub mod selection;
 pub mod temporal;
+pub mod text;
 pub mod transform;
+pub mod units;
 
 // Re-export all operations for backward compatibility
-pub use data_quality::FillNullOperation;
-pub use features::LagOperation;
+pub use anomaly::{EwmaOperation, FlatlineOperation, QuantileBandOperation, RateOfChangeOperation};
+pub use anonymize::{AnonymizationKey, AnonymizeOperation, MaskedColumn};
+pub use data_quality::{
+    ApplyFlagsOperation, DeadbandOperation, DeduplicateOperation, DuplicateKeep,
+    ExpectationOperation, FillNullOperation, NormalizeNansOperation, OutlierOperation,
+    QualityFilterOperation, ReplaceSentinelsOperation, ValidateOperation,
+};
+pub use derive::DeriveOperation;
+pub use dtypes::OptimizeDtypesOperation;
+pub use event_stats::EventWindowStatsOperation;
+pub use features::{
+    LagOperation, RollingFeaturesOperation, RollingStat, RollingWindow, TrendSlopeOperation,
+};
+pub use fleet::{FleetAggregateOperation, FleetStat};
+pub use group::PerGroupOperation;
+pub use labeling::{EventFrames, EventLabelOperation};
+pub use mapping::MapColumnsOperation;
+pub use merge::{AsofStrategy, MergeHow, MergeOperation, SchemaDiff, SchemaReconciliation};
+pub use monitoring::{CusumOperation, EwCorrelationOperation, SeasonalBaselineOperation};
+pub use privacy::{NoiseMechanism, PrivacyNoiseOperation};
+pub use recipe::{RecipeBuilder, RecipeOperation};
+pub use selection::{DropReason, DroppedFeature, FeatureSelectionOperation};
+pub use temporal::{
+    CalendarBucketOperation, CalendarFilterOperation, ClockSkewOperation, ConvertTimezoneOperation,
+    DownsampleOperation, DownsampleTarget, HolidayCalendar, Interpolation, ParseTimestampOperation,
+    ReconstructOperation, RegularizeOperation, ResampleOperation, SortByTimeOperation,
+    StateDwellTimeOperation,
+};
+pub use text::{CleanTextOperation, ExtractPatternOperation, TextCase};
 pub use transform::*;
+pub use units::ConvertUnitsOperation;
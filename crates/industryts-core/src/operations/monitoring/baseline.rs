@@ -0,0 +1,383 @@
+//! Robust seasonal baseline
+//!
+//! Process values in plants often follow the daily or weekly schedule (shifts,
+//! weekend shutdowns), so a fixed limit either misses drift on a quiet night or
+//! alarms every Monday morning. A seasonal profile keeps the median and the median
+//! absolute deviation (MAD) of each time-of-day or time-of-week bucket; samples are
+//! scored against the profile of their own bucket.
+
+use super::explain::{DEFAULT_CONTEXT_ROWS, ExplanationOutput, Flag, explanation_table};
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::temporal::timezone::{parse_time_zone, utc_to_local};
+use chrono::{Datelike, Timelike};
+use chrono_tz::Tz;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Scale factor making the MAD a consistent estimator of the standard deviation
+const MAD_SCALE: f64 = 1.4826;
+
+/// Length of the seasonal cycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeasonalPeriod {
+    Daily,
+    #[default]
+    Weekly,
+}
+
+impl SeasonalPeriod {
+    fn millis(self) -> i64 {
+        match self {
+            SeasonalPeriod::Daily => 86_400_000,
+            SeasonalPeriod::Weekly => 7 * 86_400_000,
+        }
+    }
+}
+
+/// Median and scaled MAD of one bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketStats {
+    pub median: f64,
+    pub mad: f64,
+}
+
+/// Fitted seasonal profile: bucket statistics per column
+#[derive(Debug, Clone)]
+pub struct SeasonalProfile {
+    period: SeasonalPeriod,
+    bucket_ms: i64,
+    time_zone: Tz,
+    buckets: HashMap<String, Vec<Option<BucketStats>>>,
+}
+
+impl SeasonalProfile {
+    /// Number of buckets in one period
+    pub fn bucket_count(&self) -> usize {
+        (self.period.millis() / self.bucket_ms) as usize
+    }
+
+    /// Bucket of a UTC timestamp, judged by local time
+    pub fn bucket_of(&self, utc_ms: i64) -> Option<usize> {
+        let local = utc_to_local(&self.time_zone, utc_ms)?;
+        let day_ms = local.num_seconds_from_midnight() as i64 * 1000;
+        let offset_ms = match self.period {
+            SeasonalPeriod::Daily => day_ms,
+            SeasonalPeriod::Weekly => {
+                local.weekday().num_days_from_monday() as i64 * 86_400_000 + day_ms
+            }
+        };
+        Some((offset_ms / self.bucket_ms) as usize)
+    }
+
+    /// Statistics of `column` in `bucket`, if enough samples were seen
+    pub fn stats(&self, column: &str, bucket: usize) -> Option<BucketStats> {
+        self.buckets.get(column)?.get(bucket).copied().flatten()
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Seasonal baseline operation - score samples against a time-of-week profile
+///
+/// Appends `<column>_baseline` (the bucket median) and `<column>_deviation`, the
+/// distance from the median in scaled MAD units. Buckets with fewer than
+/// `min_samples` values, or with zero MAD, score null. The profile is fitted on the
+/// input unless one was supplied with `with_profile`.
+pub struct SeasonalBaselineOperation {
+    period: SeasonalPeriod,
+    bucket: Duration,
+    time_zone: Tz,
+    min_samples: usize,
+    columns: Option<Vec<String>>,
+    profile: Option<SeasonalProfile>,
+    explanations: Option<(ExplanationOutput, f64)>,
+}
+
+impl SeasonalBaselineOperation {
+    /// Profile over `period` in buckets of `bucket` (must divide the period)
+    pub fn new(
+        period: SeasonalPeriod,
+        bucket: Duration,
+        columns: Option<Vec<String>>,
+    ) -> Result<Self> {
+        let bucket_ms = bucket.as_millis() as i64;
+        if bucket_ms == 0 || period.millis() % bucket_ms != 0 {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Seasonal bucket {:?} must evenly divide the {:?} period",
+                bucket, period
+            )));
+        }
+        Ok(Self {
+            period,
+            bucket,
+            time_zone: Tz::UTC,
+            min_samples: 3,
+            columns,
+            profile: None,
+            explanations: None,
+        })
+    }
+
+    /// Zone whose local clock defines the buckets (default UTC)
+    pub fn with_time_zone(mut self, time_zone: &str) -> Result<Self> {
+        self.time_zone = parse_time_zone(time_zone)?;
+        Ok(self)
+    }
+
+    /// Samples a bucket needs before it is scored (default 3)
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples.max(1);
+        self
+    }
+
+    /// Score against a previously fitted profile instead of the input itself
+    pub fn with_profile(mut self, profile: SeasonalProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Explain samples deviating by at least `threshold` as the secondary output `output`
+    pub fn with_explanations(
+        mut self,
+        output: &str,
+        threshold: f64,
+        context_rows: Option<usize>,
+    ) -> Self {
+        let output = ExplanationOutput {
+            name: output.to_string(),
+            context_rows: context_rows.unwrap_or(DEFAULT_CONTEXT_ROWS),
+        };
+        self.explanations = Some((output, threshold));
+        self
+    }
+
+    fn columns_of(&self, data: &TimeSeriesData) -> Vec<String> {
+        self.columns
+            .clone()
+            .unwrap_or_else(|| data.feature_columns().to_vec())
+    }
+
+    /// Fit the seasonal profile of `data`
+    pub fn fit(&self, data: &TimeSeriesData) -> Result<SeasonalProfile> {
+        let mut profile = SeasonalProfile {
+            period: self.period,
+            bucket_ms: self.bucket.as_millis() as i64,
+            time_zone: self.time_zone,
+            buckets: HashMap::new(),
+        };
+        let buckets: Vec<Option<usize>> = data
+            .timestamps_ms()?
+            .into_iter()
+            .map(|t| t.and_then(|t| profile.bucket_of(t)))
+            .collect();
+
+        for col_name in self.columns_of(data) {
+            let values = data
+                .dataframe()
+                .column(&col_name)?
+                .cast(&DataType::Float64)?;
+            let mut samples = vec![Vec::new(); profile.bucket_count()];
+            for (bucket, value) in buckets.iter().zip(values.f64()?) {
+                if let (Some(bucket), Some(value)) = (bucket, value) {
+                    samples[*bucket].push(value);
+                }
+            }
+            let stats = samples
+                .into_iter()
+                .map(|mut values| {
+                    if values.len() < self.min_samples {
+                        return None;
+                    }
+                    let median = median(&mut values);
+                    let mut deviations: Vec<f64> =
+                        values.iter().map(|v| (v - median).abs()).collect();
+                    Some(BucketStats {
+                        median,
+                        mad: MAD_SCALE * self::median(&mut deviations),
+                    })
+                })
+                .collect();
+            profile.buckets.insert(col_name, stats);
+        }
+        Ok(profile)
+    }
+
+    /// Score `data`; returns the augmented data and the samples beyond the
+    /// explanation threshold
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, Vec<Flag>)> {
+        let fitted;
+        let profile = match &self.profile {
+            Some(profile) => profile,
+            None => {
+                fitted = self.fit(&data)?;
+                &fitted
+            }
+        };
+        let buckets: Vec<Option<usize>> = data
+            .timestamps_ms()?
+            .into_iter()
+            .map(|t| t.and_then(|t| profile.bucket_of(t)))
+            .collect();
+
+        let mut df = data.dataframe().clone();
+        let mut flags = Vec::new();
+        for col_name in self.columns_of(&data) {
+            if !profile.buckets.contains_key(&col_name) {
+                return Err(IndustrytsError::ColumnNotFound(format!(
+                    "{} (not in seasonal profile)",
+                    col_name
+                )));
+            }
+            let values = df.column(&col_name)?.cast(&DataType::Float64)?;
+            let mut baseline = Vec::with_capacity(buckets.len());
+            let mut deviation = Vec::with_capacity(buckets.len());
+            for (bucket, value) in buckets.iter().zip(values.f64()?) {
+                let stats = bucket.and_then(|b| profile.stats(&col_name, b));
+                baseline.push(stats.map(|s| s.median));
+                deviation.push(match (stats, value) {
+                    (Some(s), Some(v)) if s.mad > 0.0 => Some((v - s.median) / s.mad),
+                    _ => None,
+                });
+            }
+            if let Some((_, threshold)) = &self.explanations {
+                for (row, score) in deviation.iter().enumerate() {
+                    if let Some(score) = score.filter(|d| d.abs() >= *threshold) {
+                        flags.push(Flag {
+                            row,
+                            column: col_name.clone(),
+                            method: "seasonal_deviation".to_string(),
+                            score,
+                            threshold: *threshold,
+                        });
+                    }
+                }
+            }
+            df.with_column(Series::new(
+                format!("{}_baseline", col_name).into(),
+                baseline,
+            ))?;
+            df.with_column(Series::new(
+                format!("{}_deviation", col_name).into(),
+                deviation,
+            ))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok((result, flags))
+    }
+}
+
+impl Operation for SeasonalBaselineOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, flags) = self.run(data)?;
+        if let Some((output, _)) = &self.explanations {
+            let table = explanation_table(&result, &flags, output.context_rows)?;
+            ctx.outputs_mut().insert(&output.name, table)?;
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "seasonal_baseline"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        columns
+            .iter()
+            .flat_map(|c| [format!("{}_baseline", c), format!("{}_deviation", c)])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data(days: i64, spike_at: Option<usize>) -> TimeSeriesData {
+        // Hourly samples following a day/night pattern with small noise
+        let n = days * 24;
+        let times: Vec<i64> = (0..n).map(|i| 1704067200000i64 + i * 3_600_000).collect();
+        let mut values: Vec<f64> = (0..n)
+            .map(|i| {
+                let level = if (8..18).contains(&(i % 24)) {
+                    80.0
+                } else {
+                    20.0
+                };
+                level + [0.5, -0.5, 1.0, -1.0, 0.0][(i / 24 % 5) as usize]
+            })
+            .collect();
+        if let Some(i) = spike_at {
+            values[i] = 50.0;
+        }
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("load".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_daily_baseline_scores_deviation() {
+        let op =
+            SeasonalBaselineOperation::new(SeasonalPeriod::Daily, Duration::from_secs(3600), None)
+                .unwrap();
+        let profile = op.fit(&make_data(10, None)).unwrap();
+        assert_eq!(profile.bucket_count(), 24);
+        assert_eq!(profile.stats("load", 10).unwrap().median, 80.0);
+
+        // Score new data: a 50 at 03:00 is far from the night baseline of 20
+        let result = op
+            .with_profile(profile)
+            .execute(make_data(2, Some(27)))
+            .unwrap();
+        let df = result.dataframe();
+        let baseline = df.column("load_baseline").unwrap().f64().unwrap().clone();
+        let deviation = df.column("load_deviation").unwrap().f64().unwrap().clone();
+        assert_eq!(baseline.get(27), Some(20.0));
+        assert!(deviation.get(27).unwrap() > 10.0);
+        assert!(deviation.get(28).unwrap().abs() < 2.0);
+    }
+
+    #[test]
+    fn test_bucket_must_divide_period() {
+        assert!(
+            SeasonalBaselineOperation::new(
+                SeasonalPeriod::Weekly,
+                Duration::from_secs(11 * 60),
+                None
+            )
+            .is_err()
+        );
+    }
+}
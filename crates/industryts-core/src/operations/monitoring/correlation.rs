@@ -0,0 +1,279 @@
+//! Exponentially weighted covariance and correlation
+//!
+//! Tracks the relationship between pairs of process variables (e.g. pump speed and
+//! flow) with a time-aware exponential decay: a sample's weight halves every
+//! `half_life` of elapsed time, so irregular sampling and gaps are handled without
+//! resampling first.
+
+use crate::core::{Operation, StatefulOperation, StepState, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Incremental state of an exponentially weighted covariance
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EwCovarianceState {
+    last_ms: Option<i64>,
+    weight: f64,
+    mean_x: f64,
+    mean_y: f64,
+    cxx: f64,
+    cyy: f64,
+    cxy: f64,
+    count: usize,
+}
+
+impl EwCovarianceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the observation `(x, y)` taken at `t_ms`
+    pub fn update(&mut self, t_ms: i64, x: f64, y: f64, half_life: Duration) {
+        let decay = match self.last_ms {
+            Some(last) => {
+                let elapsed = (t_ms - last).max(0) as f64;
+                0.5f64.powf(elapsed / half_life.as_millis().max(1) as f64)
+            }
+            None => 0.0,
+        };
+        self.last_ms = Some(t_ms);
+        self.count += 1;
+
+        // Weighted incremental update (West, 1979) with old weights scaled by `decay`
+        self.weight = decay * self.weight + 1.0;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / self.weight;
+        self.mean_y += dy / self.weight;
+        self.cxx = decay * self.cxx + dx * (x - self.mean_x);
+        self.cyy = decay * self.cyy + dy * (y - self.mean_y);
+        self.cxy = decay * self.cxy + dx * (y - self.mean_y);
+    }
+
+    /// Number of observations seen
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Current covariance
+    pub fn covariance(&self) -> Option<f64> {
+        (self.count >= 2).then(|| self.cxy / self.weight)
+    }
+
+    /// Current correlation, `None` while either variable is constant
+    pub fn correlation(&self) -> Option<f64> {
+        let denom = (self.cxx * self.cyy).sqrt();
+        (self.count >= 2 && denom > 0.0).then(|| (self.cxy / denom).clamp(-1.0, 1.0))
+    }
+}
+
+/// EW correlation operation - rolling covariance/correlation of column pairs
+///
+/// Appends `<a>_<b>_ewcov` and `<a>_<b>_ewcorr` for each pair. Rows where either
+/// value is null do not update the state. Chunk by chunk (see
+/// `Pipeline::process_stream`) and across warm runs the state carries over, so
+/// consecutive chunks give the same result as one call on the whole series.
+pub struct EwCorrelationOperation {
+    pairs: Vec<(String, String)>,
+    half_life: Duration,
+    min_periods: usize,
+}
+
+impl EwCorrelationOperation {
+    pub fn new(pairs: Vec<(String, String)>, half_life: Duration) -> Self {
+        Self {
+            pairs,
+            half_life,
+            min_periods: 5,
+        }
+    }
+
+    /// Observations required before values are emitted (default 5)
+    pub fn with_min_periods(mut self, min_periods: usize) -> Self {
+        self.min_periods = min_periods.max(2);
+        self
+    }
+
+    fn output_names(a: &str, b: &str) -> (String, String) {
+        (format!("{}_{}_ewcov", a, b), format!("{}_{}_ewcorr", a, b))
+    }
+
+    /// Append the outputs, continuing from and updating `states`
+    fn run(&self, data: TimeSeriesData, states: &mut PairStates) -> Result<TimeSeriesData> {
+        let times: Vec<Option<i64>> = data.timestamps_ms()?.into_iter().collect();
+        let mut df = data.dataframe().clone();
+
+        for (a, b) in &self.pairs {
+            let xs = df.column(a)?.cast(&DataType::Float64)?;
+            let ys = df.column(b)?.cast(&DataType::Float64)?;
+            let state = states.entry((a.clone(), b.clone())).or_default();
+
+            let mut covs = Vec::with_capacity(times.len());
+            let mut corrs = Vec::with_capacity(times.len());
+            for ((t, x), y) in times.iter().zip(xs.f64()?).zip(ys.f64()?) {
+                if let (Some(t), Some(x), Some(y)) = (*t, x, y) {
+                    state.update(t, x, y, self.half_life);
+                }
+                let ready = state.count() >= self.min_periods;
+                covs.push(state.covariance().filter(|_| ready));
+                corrs.push(state.correlation().filter(|_| ready));
+            }
+
+            let (cov_name, corr_name) = Self::output_names(a, b);
+            df.with_column(Series::new(cov_name.into(), covs))?;
+            df.with_column(Series::new(corr_name.into(), corrs))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+}
+
+/// Covariance state of each column pair
+type PairStates = BTreeMap<(String, String), EwCovarianceState>;
+
+impl Operation for EwCorrelationOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, &mut PairStates::new())
+    }
+
+    fn name(&self) -> &str {
+        "ew_correlation"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        for (a, b) in &self.pairs {
+            for c in [a, b] {
+                if !columns.contains(c) {
+                    columns.push(c.clone());
+                }
+            }
+        }
+        columns
+    }
+
+    fn added_columns(&self, _feature_columns: &[String]) -> Vec<String> {
+        self.pairs
+            .iter()
+            .flat_map(|(a, b)| {
+                let (cov, corr) = Self::output_names(a, b);
+                [cov, corr]
+            })
+            .collect()
+    }
+
+    /// Ten half-lives, after which earlier samples weigh less than 0.1%
+    fn warmup(&self) -> Duration {
+        self.half_life * 10
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        Some(Box::new(EwCorrelationState {
+            operation: self,
+            states: PairStates::new(),
+        }))
+    }
+}
+
+/// Covariance states of all pairs, kept between chunks
+struct EwCorrelationState<'a> {
+    operation: &'a EwCorrelationOperation,
+    states: PairStates,
+}
+
+impl StatefulOperation for EwCorrelationState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.operation.run(chunk, &mut self.states)
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        // Pairs are not valid JSON object keys
+        let states: Vec<_> = self.states.iter().collect();
+        Ok(StepState {
+            values: serde_json::to_value(states)?,
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        let states: Vec<((String, String), EwCovarianceState)> =
+            serde_json::from_value(state.values)?;
+        self.states = states.into_iter().collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data(start: i64, n: i64) -> TimeSeriesData {
+        let times: Vec<i64> = (start..start + n).map(|i| i * 60_000).collect();
+        let speed: Vec<f64> = (start..start + n).map(|i| (i as f64 * 0.3).sin()).collect();
+        let flow: Vec<f64> = speed.iter().map(|s| 2.0 * s + 1.0).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("speed".into(), speed).into(),
+            Series::new("flow".into(), flow).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn corr(data: &TimeSeriesData) -> Vec<Option<f64>> {
+        data.dataframe()
+            .column("speed_flow_ewcorr")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_ew_correlation_of_linear_pair() {
+        let op = EwCorrelationOperation::new(
+            vec![("speed".to_string(), "flow".to_string())],
+            Duration::from_secs(600),
+        );
+        let result = op.execute(make_data(0, 40)).unwrap();
+        let values = corr(&result);
+        assert_eq!(values[3], None);
+        assert!((values[39].unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let pairs = vec![("speed".to_string(), "flow".to_string())];
+        let batch = EwCorrelationOperation::new(pairs.clone(), Duration::from_secs(600))
+            .execute(make_data(0, 40))
+            .unwrap();
+
+        // Continued from a saved state, as by a later warm run
+        let op = EwCorrelationOperation::new(pairs, Duration::from_secs(600));
+        let mut first = op.stateful().unwrap();
+        first.process_chunk(make_data(0, 25)).unwrap();
+        let saved = first.save_state().unwrap();
+        let mut second = op.stateful().unwrap();
+        second.restore_state(saved).unwrap();
+        let tail = second.process_chunk(make_data(25, 15)).unwrap();
+
+        let cov = |d: &TimeSeriesData, i: usize| {
+            d.dataframe()
+                .column("speed_flow_ewcov")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(i)
+        };
+        assert!((cov(&batch, 39).unwrap() - cov(&tail, 14).unwrap()).abs() < 1e-12);
+    }
+}
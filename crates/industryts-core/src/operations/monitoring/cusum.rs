@@ -0,0 +1,407 @@
+//! CUSUM drift detection
+//!
+//! Two-sided tabular CUSUM on standardized values `z = (x - target) / sigma`:
+//!
+//! ```text
+//! S+ = max(0, S+ + z - k)      S- = max(0, S- - z - k)
+//! ```
+//!
+//! An alarm is raised when either sum exceeds `h`; both sums then restart from zero.
+//! `k` (allowance) and `h` (decision interval) are in sigma units; the classic
+//! choice k = 0.5, h = 5 detects a one-sigma shift quickly with few false alarms.
+//!
+//! In chunked runs the sums continue across chunks; self-tuned references are
+//! then learned from the first chunk, and the onset of a drift that began in an
+//! earlier chunk is not marked.
+//!
+//! A self-tuned reference needs two samples; with fewer (e.g. an empty or
+//! single-row chunk) the sums are null and no alarm is raised.
+
+use super::explain::{DEFAULT_CONTEXT_ROWS, ExplanationOutput, Flag, explanation_table};
+use crate::core::{OpContext, Operation, StatefulOperation, StepState, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// In-control level and spread of a column
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Reference {
+    target: f64,
+    sigma: f64,
+}
+
+/// Sums of a column, carried across chunks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct SumState {
+    reference: Reference,
+    pos: f64,
+    neg: f64,
+}
+
+/// CUSUM operation - flag sustained drifts away from an in-control level
+///
+/// For each column appends `<column>_cusum_pos` and `<column>_cusum_neg` (the two
+/// sums), `<column>_cusum_alarm` (1 upward alarm, -1 downward alarm, 0 otherwise)
+/// and `<column>_cusum_change`, true at the estimated drift onset of each alarm
+/// (the sample after the triggering sum last left zero).
+///
+/// Without an explicit target the in-control level is self-tuned from the mean and
+/// standard deviation of the first `reference_window` samples (default: all).
+pub struct CusumOperation {
+    k: f64,
+    h: f64,
+    columns: Option<Vec<String>>,
+    reference: Option<Reference>,
+    reference_window: Option<usize>,
+    explanations: Option<ExplanationOutput>,
+}
+
+impl CusumOperation {
+    pub fn new(k: f64, h: f64, columns: Option<Vec<String>>) -> Self {
+        Self {
+            k,
+            h,
+            columns,
+            reference: None,
+            reference_window: None,
+            explanations: None,
+        }
+    }
+
+    /// Use a known in-control mean and standard deviation
+    pub fn with_target(mut self, target: f64, sigma: f64) -> Self {
+        self.reference = Some(Reference { target, sigma });
+        self
+    }
+
+    /// Self-tune target and sigma from the first `rows` non-null samples
+    pub fn with_reference_window(mut self, rows: usize) -> Self {
+        self.reference_window = Some(rows);
+        self
+    }
+
+    /// Emit an explanation of every alarm as the secondary output `output`
+    ///
+    /// The score is the CUSUM sum that crossed `h`; see `monitoring::explain`.
+    pub fn with_explanations(mut self, output: &str, context_rows: Option<usize>) -> Self {
+        self.explanations = Some(ExplanationOutput {
+            name: output.to_string(),
+            context_rows: context_rows.unwrap_or(DEFAULT_CONTEXT_ROWS),
+        });
+        self
+    }
+
+    /// Reference of `column`, or `None` if it is self-tuned from fewer than two samples
+    fn reference_for(&self, column: &str, values: &[Option<f64>]) -> Result<Option<Reference>> {
+        let reference = match self.reference {
+            Some(reference) => reference,
+            None => {
+                let window: Vec<f64> = values
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .take(self.reference_window.unwrap_or(usize::MAX))
+                    .collect();
+                if window.len() < 2 {
+                    return Ok(None);
+                }
+                let n = window.len() as f64;
+                let target = window.iter().sum::<f64>() / n;
+                let var = window.iter().map(|v| (v - target).powi(2)).sum::<f64>() / (n - 1.0);
+                Reference {
+                    target,
+                    sigma: var.sqrt(),
+                }
+            }
+        };
+        if !(reference.sigma.is_finite() && reference.sigma > 0.0) {
+            return Err(IndustrytsError::OperationError(format!(
+                "CUSUM needs a positive sigma for column: {}",
+                column
+            )));
+        }
+        Ok(Some(reference))
+    }
+
+    /// Run the detector from the sums in `sums`, which are advanced; columns
+    /// without them start from zero. Returns the augmented data and the alarms
+    /// raised.
+    fn run(
+        &self,
+        data: TimeSeriesData,
+        sums: &mut BTreeMap<String, SumState>,
+    ) -> Result<(TimeSeriesData, Vec<Flag>)> {
+        let columns = if let Some(cols) = &self.columns {
+            cols.clone()
+        } else {
+            data.feature_columns().to_vec()
+        };
+
+        let mut df = data.dataframe().clone();
+        let mut flags = Vec::new();
+        for col_name in &columns {
+            let values: Vec<Option<f64>> = df
+                .column(col_name)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .collect();
+            let SumState {
+                reference,
+                pos: mut s_pos,
+                neg: mut s_neg,
+            } = match sums.get(col_name) {
+                Some(state) => *state,
+                None => match self.reference_for(col_name, &values)? {
+                    Some(reference) => SumState {
+                        reference,
+                        pos: 0.0,
+                        neg: 0.0,
+                    },
+                    None => {
+                        let n = values.len();
+                        let nulls = vec![None::<f64>; n];
+                        df.with_column(Series::new(
+                            format!("{}_cusum_pos", col_name).into(),
+                            nulls.clone(),
+                        ))?;
+                        df.with_column(Series::new(
+                            format!("{}_cusum_neg", col_name).into(),
+                            nulls,
+                        ))?;
+                        df.with_column(Series::new(
+                            format!("{}_cusum_alarm", col_name).into(),
+                            vec![0i32; n],
+                        ))?;
+                        df.with_column(Series::new(
+                            format!("{}_cusum_change", col_name).into(),
+                            vec![false; n],
+                        ))?;
+                        continue;
+                    }
+                },
+            };
+
+            let n = values.len();
+            let mut pos = Vec::with_capacity(n);
+            let mut neg = Vec::with_capacity(n);
+            let mut alarm = vec![0i32; n];
+            let mut change = vec![false; n];
+            // Onsets of drifts begun in an earlier chunk are unknown
+            let (mut pos_start, mut neg_start) = (None, None);
+
+            for (i, value) in values.iter().enumerate() {
+                if let Some(x) = value {
+                    let z = (x - reference.target) / reference.sigma;
+                    if s_pos == 0.0 {
+                        pos_start = Some(i);
+                    }
+                    if s_neg == 0.0 {
+                        neg_start = Some(i);
+                    }
+                    s_pos = (s_pos + z - self.k).max(0.0);
+                    s_neg = (s_neg - z - self.k).max(0.0);
+                }
+                pos.push(value.map(|_| s_pos));
+                neg.push(value.map(|_| s_neg));
+
+                let triggered = if s_pos > self.h {
+                    Some((1, pos_start, "cusum_pos", s_pos))
+                } else if s_neg > self.h {
+                    Some((-1, neg_start, "cusum_neg", s_neg))
+                } else {
+                    None
+                };
+                if let Some((direction, onset, method, score)) = triggered {
+                    alarm[i] = direction;
+                    if let Some(onset) = onset {
+                        change[onset] = true;
+                    }
+                    flags.push(Flag {
+                        row: i,
+                        column: col_name.clone(),
+                        method: method.to_string(),
+                        score,
+                        threshold: self.h,
+                    });
+                    s_pos = 0.0;
+                    s_neg = 0.0;
+                }
+            }
+            sums.insert(
+                col_name.clone(),
+                SumState {
+                    reference,
+                    pos: s_pos,
+                    neg: s_neg,
+                },
+            );
+
+            df.with_column(Series::new(format!("{}_cusum_pos", col_name).into(), pos))?;
+            df.with_column(Series::new(format!("{}_cusum_neg", col_name).into(), neg))?;
+            df.with_column(Series::new(
+                format!("{}_cusum_alarm", col_name).into(),
+                alarm,
+            ))?;
+            df.with_column(Series::new(
+                format!("{}_cusum_change", col_name).into(),
+                change,
+            ))?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok((result, flags))
+    }
+}
+
+/// Sums of all columns, kept between chunks
+struct CusumState<'a> {
+    operation: &'a CusumOperation,
+    sums: BTreeMap<String, SumState>,
+}
+
+impl StatefulOperation for CusumState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.operation
+            .run(chunk, &mut self.sums)
+            .map(|(result, _)| result)
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(StepState {
+            values: serde_json::to_value(&self.sums)?,
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        self.sums = serde_json::from_value(state.values)?;
+        Ok(())
+    }
+}
+
+impl Operation for CusumOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, &mut BTreeMap::new())
+            .map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, flags) = self.run(data, &mut BTreeMap::new())?;
+        if let Some(output) = &self.explanations {
+            let table = explanation_table(&result, &flags, output.context_rows)?;
+            ctx.outputs_mut().insert(&output.name, table)?;
+        }
+        let alarms = flags.len();
+        ctx.record_metric("cusum.alarms", alarms as f64);
+        if alarms > 0 {
+            ctx.warn(format!("CUSUM raised {} drift alarm(s)", alarms));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "cusum"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let columns = self.columns.as_deref().unwrap_or(feature_columns);
+        columns
+            .iter()
+            .flat_map(|c| {
+                ["pos", "neg", "alarm", "change"].map(|suffix| format!("{}_cusum_{}", c, suffix))
+            })
+            .collect()
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        Some(Box::new(CusumState {
+            operation: self,
+            sums: BTreeMap::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cusum_detects_upward_shift() {
+        // In control around 10 for 50 samples, then a one-sigma upward shift
+        let values: Vec<f64> = (0..100)
+            .map(|i| {
+                let noise = [0.5, -0.5, 1.0, -1.0, 0.0][i % 5];
+                if i < 50 {
+                    10.0 + noise
+                } else {
+                    10.0 + noise + 0.8
+                }
+            })
+            .collect();
+        let times: Vec<i64> = (0..100).map(|i| i * 60_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), values).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = CusumOperation::new(0.5, 5.0, None)
+            .with_reference_window(50)
+            .with_explanations("cusum_alarms", Some(10));
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+        let df = result.dataframe();
+
+        let alarms: Vec<i32> = df
+            .column("temp_cusum_alarm")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let first = alarms.iter().position(|&a| a != 0).unwrap();
+        assert!((50..70).contains(&first));
+        assert_eq!(alarms[first], 1);
+        assert!(alarms[..50].iter().all(|&a| a == 0));
+
+        let change = df
+            .column("temp_cusum_change")
+            .unwrap()
+            .bool()
+            .unwrap()
+            .clone();
+        let onset = change.into_iter().position(|c| c == Some(true)).unwrap();
+        assert!((45..=first).contains(&onset));
+
+        let explanations = ctx.outputs().get("cusum_alarms").unwrap();
+        assert_eq!(
+            explanations.height(),
+            alarms.iter().filter(|&&a| a != 0).count()
+        );
+        let method = explanations.column("method").unwrap().str().unwrap().get(0);
+        assert_eq!(method, Some("cusum_pos"));
+        let score = explanations.column("score").unwrap().f64().unwrap().get(0);
+        assert!(score.unwrap() > 5.0);
+        let context_rows = explanations.column("context_rows").unwrap().u32().unwrap();
+        assert_eq!(context_rows.get(0), Some(10));
+
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(warnings.len(), 1);
+        assert!(metrics["cusum.alarms"] >= 1.0);
+    }
+}
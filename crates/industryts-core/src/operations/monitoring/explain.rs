@@ -0,0 +1,109 @@
+//! Explanations of flagged points
+//!
+//! Detectors that flag points (CUSUM alarms, seasonal baseline deviations) can emit
+//! an explanation table as a secondary output, one row per flag:
+//!
+//! | column | meaning |
+//! |--------|---------|
+//! | time column | timestamp of the flagged sample |
+//! | `column` | flagged column |
+//! | `method` | detector and rule that fired, e.g. `cusum_pos` |
+//! | `value` | sample value |
+//! | `score`, `threshold` | detector score and the limit it crossed |
+//! | `context_mean`, `context_std`, `context_min`, `context_max` | statistics of the preceding samples |
+//! | `context_rows` | non-null samples behind the context statistics |
+//!
+//! The context covers the `context_rows` rows before the flag, so reviewers can see
+//! what "normal" looked like right before it.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use polars::prelude::*;
+
+/// Default number of preceding rows summarized for each flag
+pub const DEFAULT_CONTEXT_ROWS: usize = 30;
+
+/// One flagged sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flag {
+    pub row: usize,
+    pub column: String,
+    pub method: String,
+    pub score: f64,
+    pub threshold: f64,
+}
+
+/// Where and how to emit explanations
+#[derive(Debug, Clone)]
+pub(crate) struct ExplanationOutput {
+    pub(crate) name: String,
+    pub(crate) context_rows: usize,
+}
+
+/// Build the explanation table of `flags` raised on `data`
+pub fn explanation_table(
+    data: &TimeSeriesData,
+    flags: &[Flag],
+    context_rows: usize,
+) -> Result<DataFrame> {
+    let df = data.dataframe();
+    let rows: Vec<IdxSize> = flags.iter().map(|f| f.row as IdxSize).collect();
+    let times = df
+        .column(data.time_column())?
+        .as_materialized_series()
+        .take(&IdxCa::from_vec("idx".into(), rows))?;
+
+    let n = flags.len();
+    let mut value = Vec::with_capacity(n);
+    let mut stats: [Vec<Option<f64>>; 4] = std::array::from_fn(|_| Vec::with_capacity(n));
+    let mut context_count = Vec::with_capacity(n);
+    for flag in flags {
+        let column = df.column(&flag.column)?.cast(&DataType::Float64)?;
+        let values = column.f64()?;
+        value.push(values.get(flag.row));
+
+        let start = flag.row.saturating_sub(context_rows);
+        let context: Vec<f64> = (start..flag.row).filter_map(|i| values.get(i)).collect();
+        let count = context.len();
+        let mean = (count > 0).then(|| context.iter().sum::<f64>() / count as f64);
+        let std = mean.filter(|_| count > 1).map(|m| {
+            (context.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        });
+        stats[0].push(mean);
+        stats[1].push(std);
+        stats[2].push(context.iter().copied().reduce(f64::min));
+        stats[3].push(context.iter().copied().reduce(f64::max));
+        context_count.push(count as u32);
+    }
+
+    let [mean, std, min, max] = stats;
+    Ok(DataFrame::new(vec![
+        times.into(),
+        Series::new(
+            "column".into(),
+            flags.iter().map(|f| f.column.as_str()).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "method".into(),
+            flags.iter().map(|f| f.method.as_str()).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new("value".into(), value).into(),
+        Series::new(
+            "score".into(),
+            flags.iter().map(|f| f.score).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new(
+            "threshold".into(),
+            flags.iter().map(|f| f.threshold).collect::<Vec<_>>(),
+        )
+        .into(),
+        Series::new("context_mean".into(), mean).into(),
+        Series::new("context_std".into(), std).into(),
+        Series::new("context_min".into(), min).into(),
+        Series::new("context_max".into(), max).into(),
+        Series::new("context_rows".into(), context_count).into(),
+    ])?)
+}
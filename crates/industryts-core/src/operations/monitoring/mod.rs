@@ -0,0 +1,17 @@
+//! Process monitoring operations
+//!
+//! This module provides operations for watching process behaviour over time:
+//! - baseline: robust seasonal (time-of-day/week) baseline and deviation scoring
+//! - correlation: exponentially weighted covariance/correlation between variables
+//! - cusum: CUSUM drift detection
+//! - explain: explanation tables of flagged points
+
+pub mod baseline;
+pub mod correlation;
+pub mod cusum;
+pub mod explain;
+
+pub use baseline::{SeasonalBaselineOperation, SeasonalPeriod, SeasonalProfile};
+pub use correlation::{EwCorrelationOperation, EwCovarianceState};
+pub use cusum::CusumOperation;
+pub use explain::{Flag, explanation_table};
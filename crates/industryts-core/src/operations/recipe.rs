@@ -0,0 +1,233 @@
+//! Per-column processing recipes
+//!
+//! Engineers treat tags by family: temperatures are despiked and smoothed,
+//! totalizer counters are differenced and clipped, valve positions are left
+//! alone. `RecipeOperation` maps column selectors to such step chains in one
+//! step. Each feature column is claimed by the first recipe whose selector
+//! matches it; the recipes run in parallel, each on the time and group columns
+//! plus its own columns, and their outputs are put back side by side. Columns no
+//! recipe claims pass through unchanged.
+//!
+//! Recipe steps must keep the rows of their input; a step that resamples, filters
+//! or reorders rows fails the operation.
+
+use crate::core::{ColumnSelector, OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use rayon::prelude::*;
+
+/// Builds the steps of a recipe for the columns its selector resolved to
+pub type RecipeBuilder = Box<dyn Fn(Vec<String>) -> Result<Box<dyn Operation>> + Send + Sync>;
+
+struct Recipe {
+    selector: ColumnSelector,
+    build: RecipeBuilder,
+}
+
+/// Recipe operation - step chains applied to column families
+#[derive(Default)]
+pub struct RecipeOperation {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeOperation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the steps built by `build` on the columns matched by `selector`
+    ///
+    /// Columns already claimed by an earlier recipe are not passed again.
+    pub fn with_recipe<F>(mut self, selector: impl Into<ColumnSelector>, build: F) -> Self
+    where
+        F: Fn(Vec<String>) -> Result<Box<dyn Operation>> + Send + Sync + 'static,
+    {
+        self.recipes.push(Recipe {
+            selector: selector.into(),
+            build: Box::new(build),
+        });
+        self
+    }
+
+    /// Columns of each recipe, first match winning
+    fn assign(
+        &self,
+        resolve: impl Fn(&ColumnSelector) -> Result<Vec<String>>,
+    ) -> Result<Vec<Vec<String>>> {
+        let mut claimed: Vec<String> = Vec::new();
+        self.recipes
+            .iter()
+            .map(|recipe| {
+                let columns: Vec<String> = resolve(&recipe.selector)?
+                    .into_iter()
+                    .filter(|c| !claimed.contains(c))
+                    .collect();
+                claimed.extend(columns.iter().cloned());
+                Ok(columns)
+            })
+            .collect()
+    }
+
+    /// Run every recipe with matching columns through `step` and merge the outputs
+    fn run<T: Send>(
+        &self,
+        data: TimeSeriesData,
+        step: impl Fn(&dyn Operation, TimeSeriesData) -> Result<(TimeSeriesData, T)> + Sync,
+    ) -> Result<(TimeSeriesData, Vec<T>)> {
+        let assignments = self.assign(|selector| selector.resolve(&data))?;
+        let mut keys = vec![data.time_column().to_string()];
+        keys.extend(data.group_columns().iter().cloned());
+
+        let results = self
+            .recipes
+            .par_iter()
+            .zip(assignments)
+            .filter(|(_, columns)| !columns.is_empty())
+            .map(|(recipe, columns)| {
+                let operation = (recipe.build)(columns.clone())?;
+                let selected: Vec<&String> = keys.iter().chain(&columns).collect();
+                let mut metadata = data.metadata().clone();
+                metadata.feature_columns = columns.clone();
+                let input =
+                    TimeSeriesData::with_metadata(data.dataframe().select(selected)?, metadata)?;
+                let (output, extra) = step(operation.as_ref(), input)?;
+                let rows_kept = output.len() == data.len()
+                    && output
+                        .dataframe()
+                        .column(data.time_column())?
+                        .equals_missing(data.dataframe().column(data.time_column())?);
+                if !rows_kept {
+                    return Err(IndustrytsError::OperationError(format!(
+                        "recipe step {} changed the rows of {:?}",
+                        operation.name(),
+                        columns
+                    )));
+                }
+                Ok((columns, output, extra))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        let mut extras = Vec::new();
+        for (columns, output, extra) in results {
+            for column in &columns {
+                if output.dataframe().column(column).is_err() {
+                    df.drop_in_place(column)?;
+                    metadata.feature_columns.retain(|c| c != column);
+                }
+            }
+            for column in output.dataframe().get_columns() {
+                let name = column.name().to_string();
+                if keys.contains(&name) {
+                    continue;
+                }
+                if !metadata.feature_columns.contains(&name) {
+                    metadata.feature_columns.push(name);
+                }
+                df.with_column(column.clone())?;
+            }
+            metadata.tags.extend(output.metadata().tags.clone());
+            extras.push(extra);
+        }
+        Ok((TimeSeriesData::with_metadata(df, metadata)?, extras))
+    }
+}
+
+impl Operation for RecipeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, |operation, input| Ok((operation.execute(input)?, ())))
+            .map(|(output, _)| output)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (output, recipe_contexts) = {
+            let parent = &*ctx;
+            self.run(data, |operation, input| {
+                let mut recipe_ctx = parent.fork();
+                let result = operation.execute_with_context(input, &mut recipe_ctx)?;
+                Ok((result, recipe_ctx))
+            })?
+        };
+        for recipe_ctx in recipe_contexts {
+            ctx.join(recipe_ctx)?;
+        }
+        Ok(output)
+    }
+
+    fn name(&self) -> &str {
+        "recipe"
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        // Dtype selectors cannot be checked without data and are assumed to match
+        let Ok(assignments) = self.assign(|selector| selector.resolve_names(feature_columns))
+        else {
+            return Vec::new();
+        };
+        let mut added = Vec::new();
+        for (recipe, columns) in self.recipes.iter().zip(assignments) {
+            if columns.is_empty() {
+                continue;
+            }
+            if let Ok(operation) = (recipe.build)(columns.clone()) {
+                added.extend(operation.added_columns(&columns));
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{DifferenceOperation, StandardizeOperation};
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_recipes_by_column_family() {
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("TI_101", [1.0, 2.0, 3.0])
+            .with_column("FQ_101", [10.0, 15.0, 25.0])
+            .with_column("TI_SP", [5.0, 5.0, 5.0])
+            .with_column("ZV_101", [0.5, 0.5, 1.0])
+            .build()
+            .unwrap();
+        let op = RecipeOperation::new()
+            .with_recipe(ColumnSelector::pattern("TI_SP"), |_| {
+                Ok(Box::new(DifferenceOperation::new(1, Some(vec![]))) as Box<dyn Operation>)
+            })
+            .with_recipe(ColumnSelector::pattern("TI_*"), |columns| {
+                Ok(Box::new(StandardizeOperation::new(Some(columns))) as Box<dyn Operation>)
+            })
+            .with_recipe(ColumnSelector::pattern("FQ_*"), |columns| {
+                Ok(Box::new(DifferenceOperation::new(1, Some(columns))) as Box<dyn Operation>)
+            });
+        let features = data.feature_columns().to_vec();
+        assert_eq!(op.added_columns(&features), ["FQ_101_diff_1"]);
+
+        let result = op.execute(data).unwrap();
+        let df = result.dataframe();
+        let values = |name: &str| -> Vec<Option<f64>> {
+            df.column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(values("TI_101"), [Some(-1.0), Some(0.0), Some(1.0)]);
+        // Claimed by the first recipe, so not standardized
+        assert_eq!(values("TI_SP"), [Some(5.0); 3]);
+        assert_eq!(values("FQ_101_diff_1"), [None, Some(5.0), Some(10.0)]);
+        assert_eq!(values("ZV_101"), [Some(0.5), Some(0.5), Some(1.0)]);
+        assert_eq!(
+            result.feature_columns(),
+            ["TI_101", "FQ_101", "TI_SP", "ZV_101", "FQ_101_diff_1"]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Feature selection
+//!
+//! Bulk feature extraction (rolling statistics over several windows, lags, trend
+//! slopes) easily yields hundreds of columns, many of them empty, flat or copies
+//! of each other. `FeatureSelectionOperation` drops three kinds of columns:
+//!
+//! - mostly null: the share of null and NaN values exceeds `max_null_ratio`
+//! - near constant: the most frequent value makes up more than
+//!   `max_dominant_ratio` of the non-null values
+//! - collinear: the absolute Pearson correlation with an earlier kept column
+//!   exceeds `max_correlation`; of each such pair the earlier column is kept
+//!
+//! The checks run in that order and a column is reported with the first one that
+//! applies. A ratio of 1 disables a check. Each dropped column is listed with its
+//! reason in an optional secondary output.
+
+use crate::core::{
+    FittableOperation, OpContext, Operation, StatefulOperation, StepState, TimeSeriesData,
+};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default share of nulls above which a column is dropped
+pub const DEFAULT_MAX_NULL_RATIO: f64 = 0.5;
+/// Default share of the most frequent value above which a column is dropped
+pub const DEFAULT_MAX_DOMINANT_RATIO: f64 = 0.99;
+/// Default absolute correlation above which the later column of a pair is dropped
+pub const DEFAULT_MAX_CORRELATION: f64 = 0.95;
+
+/// Check that removed a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    MostlyNull,
+    NearConstant,
+    Collinear,
+}
+
+impl DropReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::MostlyNull => "mostly_null",
+            DropReason::NearConstant => "near_constant",
+            DropReason::Collinear => "collinear",
+        }
+    }
+}
+
+/// Column removed by `FeatureSelectionOperation`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroppedFeature {
+    pub column: String,
+    pub reason: DropReason,
+    /// Null ratio, share of the most frequent value or absolute correlation
+    pub value: f64,
+    /// Kept column a collinear column correlates with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlated_with: Option<String>,
+}
+
+/// Feature selection operation - drop uninformative or redundant columns
+///
+/// Unfitted, the columns are selected on the data the operation is applied to
+/// (on the first chunk when streaming). Once fitted (see `FittableOperation`),
+/// the columns dropped from the training data are dropped from any later data,
+/// so training and scoring sets keep the same features. Data of fewer than two
+/// rows keeps every column.
+pub struct FeatureSelectionOperation {
+    columns: Option<Vec<String>>,
+    max_null_ratio: f64,
+    max_dominant_ratio: f64,
+    max_correlation: f64,
+    report: Option<String>,
+    fitted: Option<Vec<DroppedFeature>>,
+}
+
+impl FeatureSelectionOperation {
+    pub fn new(columns: Option<Vec<String>>) -> Self {
+        Self {
+            columns,
+            max_null_ratio: DEFAULT_MAX_NULL_RATIO,
+            max_dominant_ratio: DEFAULT_MAX_DOMINANT_RATIO,
+            max_correlation: DEFAULT_MAX_CORRELATION,
+            report: None,
+            fitted: None,
+        }
+    }
+
+    /// Drop columns with a larger share of null or NaN values
+    pub fn with_max_null_ratio(mut self, ratio: f64) -> Result<Self> {
+        self.max_null_ratio = check_ratio("max_null_ratio", ratio)?;
+        Ok(self)
+    }
+
+    /// Drop columns whose most frequent value has a larger share of the non-null values
+    pub fn with_max_dominant_ratio(mut self, ratio: f64) -> Result<Self> {
+        self.max_dominant_ratio = check_ratio("max_dominant_ratio", ratio)?;
+        Ok(self)
+    }
+
+    /// Drop numeric columns correlated more strongly with an earlier kept column
+    pub fn with_max_correlation(mut self, correlation: f64) -> Result<Self> {
+        self.max_correlation = check_ratio("max_correlation", correlation)?;
+        Ok(self)
+    }
+
+    /// Store the dropped columns and reasons as the secondary output `name`
+    pub fn with_report(mut self, name: &str) -> Self {
+        self.report = Some(name.to_string());
+        self
+    }
+
+    /// Columns of `data` the operation drops, in column order
+    pub fn select(&self, data: &TimeSeriesData) -> Result<Vec<DroppedFeature>> {
+        let columns = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| data.feature_columns().to_vec());
+        let df = data.dataframe();
+        let mut dropped = Vec::new();
+        let mut kept: Vec<(String, Vec<Option<f64>>)> = Vec::new();
+
+        for col_name in columns {
+            let column = df
+                .column(&col_name)
+                .map_err(|_| IndustrytsError::ColumnNotFound(col_name.clone()))?;
+            // Fewer than two rows tell nothing about a column; keep it
+            if column.len() < 2 {
+                continue;
+            }
+            let values = if column.dtype().is_primitive_numeric() {
+                let values = column.cast(&DataType::Float64)?;
+                let values: Vec<Option<f64>> = values
+                    .f64()?
+                    .into_iter()
+                    .map(|v| v.filter(|v| !v.is_nan()))
+                    .collect();
+                Some(values)
+            } else {
+                None
+            };
+
+            let (nulls, dominant) = match &values {
+                Some(values) => {
+                    let mut counts: HashMap<u64, usize> = HashMap::new();
+                    for v in values.iter().flatten() {
+                        // -0.0 and 0.0 are the same value
+                        *counts.entry((v + 0.0).to_bits()).or_default() += 1;
+                    }
+                    let non_null: usize = counts.values().sum();
+                    (values.len() - non_null, counts.into_values().max())
+                }
+                None => {
+                    let strings = column.cast(&DataType::String)?;
+                    let mut counts: HashMap<&str, usize> = HashMap::new();
+                    for v in strings.str()?.into_iter().flatten() {
+                        *counts.entry(v).or_default() += 1;
+                    }
+                    (column.null_count(), counts.into_values().max())
+                }
+            };
+
+            let null_ratio = nulls as f64 / column.len() as f64;
+            if null_ratio > self.max_null_ratio {
+                dropped.push(DroppedFeature {
+                    column: col_name,
+                    reason: DropReason::MostlyNull,
+                    value: null_ratio,
+                    correlated_with: None,
+                });
+                continue;
+            }
+            let non_null = column.len() - nulls;
+            let dominant_ratio = dominant.map_or(1.0, |n| n as f64 / non_null as f64);
+            if dominant_ratio > self.max_dominant_ratio {
+                dropped.push(DroppedFeature {
+                    column: col_name,
+                    reason: DropReason::NearConstant,
+                    value: dominant_ratio,
+                    correlated_with: None,
+                });
+                continue;
+            }
+
+            let Some(values) = values else {
+                continue;
+            };
+            if self.max_correlation < 1.0 {
+                let correlated = kept.par_iter().find_map_first(|(other, other_values)| {
+                    let r = pearson(other_values, &values)?.abs();
+                    (r > self.max_correlation).then(|| (other.clone(), r))
+                });
+                if let Some((other, r)) = correlated {
+                    dropped.push(DroppedFeature {
+                        column: col_name,
+                        reason: DropReason::Collinear,
+                        value: r,
+                        correlated_with: Some(other),
+                    });
+                    continue;
+                }
+            }
+            kept.push((col_name, values));
+        }
+        Ok(dropped)
+    }
+
+    /// Data without the `dropped` columns that are present
+    fn drop_columns(data: TimeSeriesData, dropped: &[DroppedFeature]) -> Result<TimeSeriesData> {
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for feature in dropped {
+            if df.get_column_index(&feature.column).is_some() {
+                df.drop_in_place(&feature.column)?;
+            }
+        }
+        metadata
+            .feature_columns
+            .retain(|c| !dropped.iter().any(|d| &d.column == c));
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, Vec<DroppedFeature>)> {
+        let dropped = match &self.fitted {
+            Some(dropped) => dropped.clone(),
+            None => self.select(&data)?,
+        };
+        Ok((Self::drop_columns(data, &dropped)?, dropped))
+    }
+}
+
+fn check_ratio(name: &str, value: f64) -> Result<f64> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(IndustrytsError::ConfigError(format!(
+            "{} must be between 0 and 1, got {}",
+            name, value
+        )));
+    }
+    Ok(value)
+}
+
+/// Pearson correlation over the rows where both values are present
+fn pearson(x: &[Option<f64>], y: &[Option<f64>]) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = x
+        .iter()
+        .zip(y)
+        .filter_map(|(x, y)| Some(((*x)?, (*y)?)))
+        .collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxy += dx * dy;
+        sxx += dx * dx;
+        syy += dy * dy;
+    }
+    if sxx == 0.0 || syy == 0.0 {
+        return None;
+    }
+    Some((sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0))
+}
+
+/// Dropped columns as a table of column, reason, value and correlated column
+fn report_table(dropped: &[DroppedFeature]) -> Result<DataFrame> {
+    let column: Vec<&str> = dropped.iter().map(|d| d.column.as_str()).collect();
+    let reason: Vec<&str> = dropped.iter().map(|d| d.reason.as_str()).collect();
+    let value: Vec<f64> = dropped.iter().map(|d| d.value).collect();
+    let correlated_with: Vec<Option<&str>> = dropped
+        .iter()
+        .map(|d| d.correlated_with.as_deref())
+        .collect();
+    Ok(df!(
+        "column" => column,
+        "reason" => reason,
+        "value" => value,
+        "correlated_with" => correlated_with,
+    )?)
+}
+
+impl FittableOperation for FeatureSelectionOperation {
+    fn fit(&mut self, data: &TimeSeriesData) -> Result<()> {
+        self.fitted = Some(self.select(data)?);
+        Ok(())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.fitted
+            .as_ref()
+            .and_then(|dropped| serde_json::to_value(dropped).ok())
+    }
+
+    fn load_params(&mut self, params: serde_json::Value) -> Result<()> {
+        self.fitted = Some(serde_json::from_value(params)?);
+        Ok(())
+    }
+}
+
+impl Operation for FeatureSelectionOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, dropped) = self.run(data)?;
+        if let Some(name) = &self.report {
+            ctx.outputs_mut().insert(name, report_table(&dropped)?)?;
+        }
+        ctx.record_metric("feature_selection.dropped", dropped.len() as f64);
+        if !dropped.is_empty() {
+            let count = |reason| dropped.iter().filter(|d| d.reason == reason).count();
+            ctx.warn(format!(
+                "Feature selection dropped {} column(s): {} mostly null, {} near constant, {} collinear",
+                dropped.len(),
+                count(DropReason::MostlyNull),
+                count(DropReason::NearConstant),
+                count(DropReason::Collinear)
+            ));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "feature_selection"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn fittable(&self) -> Option<&dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn fittable_mut(&mut self) -> Option<&mut dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        if self.fitted.is_some() {
+            return None;
+        }
+        Some(Box::new(SelectionState {
+            operation: self,
+            dropped: None,
+        }))
+    }
+}
+
+/// Selection made on the first chunk and kept for the rest, so chunks share a schema
+struct SelectionState<'a> {
+    operation: &'a FeatureSelectionOperation,
+    dropped: Option<Vec<DroppedFeature>>,
+}
+
+impl StatefulOperation for SelectionState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        let dropped = match &self.dropped {
+            Some(dropped) => dropped,
+            None => self.dropped.insert(self.operation.select(&chunk)?),
+        };
+        FeatureSelectionOperation::drop_columns(chunk, dropped)
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(StepState {
+            values: serde_json::to_value(&self.dropped)?,
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        self.dropped = serde_json::from_value(state.values)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_uninformative_columns_are_dropped() {
+        let n = 20;
+        let x: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64).collect();
+        let data = Fixture::every(Duration::from_secs(60), n)
+            .with_column("x", x.clone())
+            .with_column(
+                "sparse",
+                (0..n)
+                    .map(|i| (i % 4 == 0).then_some(i as f64))
+                    .collect::<Vec<_>>(),
+            )
+            .with_column("flat", vec![1.0; n])
+            .with_column(
+                "x_scaled",
+                x.iter().map(|v| 2.0 * v + 1.0).collect::<Vec<_>>(),
+            )
+            .with_column("y", (0..n).map(|i| (i % 3) as f64).collect::<Vec<_>>())
+            .with_column("state", ["run", "idle"].repeat(n / 2))
+            .build()
+            .unwrap();
+
+        let mut op = FeatureSelectionOperation::new(None).with_report("dropped");
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data.clone(), &mut ctx).unwrap();
+        assert_eq!(result.feature_columns(), ["x", "y", "state"]);
+        assert!(result.dataframe().column("flat").is_err());
+
+        let report = ctx.outputs().get("dropped").unwrap();
+        let reasons: Vec<&str> = report
+            .column("reason")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(reasons, ["mostly_null", "near_constant", "collinear"]);
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(metrics["feature_selection.dropped"], 3.0);
+        assert_eq!(warnings.len(), 1);
+
+        // The fitted selection is reused on data where `flat` varies
+        op.fit(&data).unwrap();
+        let params = op.params().unwrap();
+        let mut loaded = FeatureSelectionOperation::new(None);
+        loaded.load_params(params).unwrap();
+        let later = Fixture::every(Duration::from_secs(60), 4)
+            .with_column("x", [1.0, 2.0, 3.0, 4.0])
+            .with_column("flat", [1.0, 2.0, 1.0, 3.0])
+            .with_column("y", [0.0, 1.0, 0.0, 2.0])
+            .build()
+            .unwrap();
+        let result = loaded.execute(later).unwrap();
+        assert_eq!(result.feature_columns(), ["x", "y"]);
+        assert!(
+            FeatureSelectionOperation::new(None)
+                .with_max_correlation(1.5)
+                .is_err()
+        );
+    }
+}
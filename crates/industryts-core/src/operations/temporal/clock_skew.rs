@@ -0,0 +1,97 @@
+//! Clock skew correction
+//!
+//! PLC, DCS and historian clocks are set independently and disagree, often by
+//! minutes, and cheap clocks drift further apart over months. Joining data from
+//! such sources pairs each cause with the wrong effect. `ClockSkewOperation`
+//! moves the timestamps of a source onto the reference clock: by a constant
+//! offset, plus a drift in parts per million of the time elapsed since a
+//! reference instant. The offset and drift are typically estimated with
+//! `analysis::estimate_skew`.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::Result;
+
+/// Clock skew operation - shift timestamps onto a reference clock
+///
+/// Each timestamp `t` becomes `t + offset + drift_ppm * 1e-6 * (t - reference)`.
+/// Row order is kept; a drift of a few hundred ppm cannot reorder rows.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSkewOperation {
+    offset_ms: i64,
+    drift_ppm: f64,
+    reference_ms: i64,
+}
+
+impl ClockSkewOperation {
+    /// Add `offset_ms` to every timestamp
+    pub fn new(offset_ms: i64) -> Self {
+        Self {
+            offset_ms,
+            drift_ppm: 0.0,
+            reference_ms: 0,
+        }
+    }
+
+    /// Also correct a drift of `drift_ppm`, with the offset measured at
+    /// `reference_ms` (ms since epoch)
+    pub fn with_drift(mut self, drift_ppm: f64, reference_ms: i64) -> Self {
+        self.drift_ppm = drift_ppm;
+        self.reference_ms = reference_ms;
+        self
+    }
+
+    /// Correction applied to a timestamp, in milliseconds
+    pub fn correction_at(&self, t_ms: i64) -> i64 {
+        let drift = self.drift_ppm * 1e-6 * (t_ms - self.reference_ms) as f64;
+        self.offset_ms + drift.round() as i64
+    }
+}
+
+impl Operation for ClockSkewOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        if self.offset_ms == 0 && self.drift_ppm == 0.0 {
+            return Ok(data);
+        }
+        data.shift_time_column(|t| self.correction_at(t))
+    }
+
+    fn name(&self) -> &str {
+        "clock_skew"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipelineConfig;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_configured_clock_skew() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "plc"
+
+            [[operations]]
+            type = "clock_skew"
+            offset = "-2min"
+            drift_ppm = 100.0
+            reference_time = "2024-01-01 00:00:00"
+            "#,
+        )
+        .unwrap();
+        let t0 = 1_704_067_200_000;
+        let data = Fixture::at_times_ms(vec![t0, t0 + 10_000_000])
+            .with_column("flow", [1.0, 2.0])
+            .build()
+            .unwrap();
+        let result = Pipeline::from_config(config)
+            .unwrap()
+            .process(data)
+            .unwrap();
+        let times: Vec<Option<i64>> = result.timestamps_ms().unwrap().into_iter().collect();
+        // 100 ppm of 10,000 s is 1 s
+        assert_eq!(times, [Some(t0 - 120_000), Some(t0 + 10_000_000 - 119_000)]);
+    }
+}
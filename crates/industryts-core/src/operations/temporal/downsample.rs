@@ -0,0 +1,288 @@
+//! Downsampling for visualization
+//!
+//! A plot cannot show more points than it has pixels, so long traces are reduced
+//! before they are served. Unlike resampling, downsampling keeps original samples:
+//! - `lttb` (largest triangle three buckets) keeps in each bucket the sample
+//!   spanning the largest triangle with the previously kept sample and the mean of
+//!   the next bucket, preserving the visual shape
+//! - `minmax` keeps the lowest and highest sample of each bucket, so no spike is
+//!   lost
+//!
+//! Buckets hold an equal number of samples (for a target point count) or span an
+//! equal duration. The first and last sample of each series are always kept.
+//! Every column is reduced on its own and a row is kept if any column selects it,
+//! so the output holds up to the target times the number of columns.
+
+use crate::config::DownsampleMethod;
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Size of the buckets of a `DownsampleOperation`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownsampleTarget {
+    /// About this many points per column and series
+    Points(usize),
+    /// Buckets of this duration
+    Bucket(Duration),
+}
+
+/// Downsample operation - keep the samples that preserve a trace's shape
+pub struct DownsampleOperation {
+    method: DownsampleMethod,
+    target: DownsampleTarget,
+    columns: Option<Vec<String>>,
+}
+
+/// Non-null sample of a column: row, time relative to the first sample, value
+type Point = (usize, f64, f64);
+
+impl DownsampleOperation {
+    pub fn new(
+        method: DownsampleMethod,
+        target: DownsampleTarget,
+        columns: Option<Vec<String>>,
+    ) -> Result<Self> {
+        match target {
+            DownsampleTarget::Points(points) if points < 3 => {
+                Err(IndustrytsError::InvalidOperation(format!(
+                    "Downsampling needs a target of at least 3 points, got {}",
+                    points
+                )))
+            }
+            DownsampleTarget::Bucket(bucket) if bucket.is_zero() => Err(
+                IndustrytsError::InvalidOperation("Downsample bucket must be positive".to_string()),
+            ),
+            _ => Ok(Self {
+                method,
+                target,
+                columns,
+            }),
+        }
+    }
+
+    /// Buckets of the inner points (all but the first and last)
+    fn buckets(&self, points: &[Point]) -> Vec<Range<usize>> {
+        let inner = points.len() - 2;
+        match self.target {
+            DownsampleTarget::Points(target) => {
+                let count = match self.method {
+                    DownsampleMethod::Lttb => target - 2,
+                    DownsampleMethod::MinMax => ((target - 2) / 2).max(1),
+                };
+                (0..count)
+                    .map(|i| 1 + i * inner / count..1 + (i + 1) * inner / count)
+                    .filter(|range| !range.is_empty())
+                    .collect()
+            }
+            DownsampleTarget::Bucket(bucket) => {
+                let width = bucket.as_millis() as f64;
+                let mut buckets: Vec<Range<usize>> = Vec::new();
+                let mut current = None;
+                for (i, &(_, t, _)) in points.iter().enumerate().take(inner + 1).skip(1) {
+                    let index = (t / width).floor() as i64;
+                    match buckets.last_mut() {
+                        Some(range) if current == Some(index) => range.end = i + 1,
+                        _ => {
+                            buckets.push(i..i + 1);
+                            current = Some(index);
+                        }
+                    }
+                }
+                buckets
+            }
+        }
+    }
+
+    /// Mark the rows to keep of one column's `points`, ordered by time
+    fn select(&self, points: &[Point], keep: &mut [bool]) {
+        let few = match self.target {
+            DownsampleTarget::Points(target) => points.len() <= target,
+            DownsampleTarget::Bucket(_) => false,
+        };
+        if points.len() <= 2 || few {
+            points.iter().for_each(|&(row, _, _)| keep[row] = true);
+            return;
+        }
+        keep[points[0].0] = true;
+        keep[points[points.len() - 1].0] = true;
+
+        let buckets = self.buckets(points);
+        match self.method {
+            DownsampleMethod::MinMax => {
+                for range in &buckets {
+                    let bucket = &points[range.clone()];
+                    let lowest = bucket.iter().min_by(|a, b| a.2.total_cmp(&b.2));
+                    let highest = bucket.iter().max_by(|a, b| a.2.total_cmp(&b.2));
+                    for &(row, _, _) in lowest.into_iter().chain(highest) {
+                        keep[row] = true;
+                    }
+                }
+            }
+            DownsampleMethod::Lttb => {
+                let mut anchor = points[0];
+                for (i, range) in buckets.iter().enumerate() {
+                    // Mean of the next bucket, or the last point after the last bucket
+                    let next = match buckets.get(i + 1) {
+                        Some(next) => &points[next.clone()],
+                        None => &points[points.len() - 1..],
+                    };
+                    let n = next.len() as f64;
+                    let cx = next.iter().map(|p| p.1).sum::<f64>() / n;
+                    let cy = next.iter().map(|p| p.2).sum::<f64>() / n;
+
+                    let (_, ax, ay) = anchor;
+                    let area = |p: &Point| ((ax - cx) * (p.2 - ay) - (ax - p.1) * (cy - ay)).abs();
+                    if let Some(&chosen) = points[range.clone()]
+                        .iter()
+                        .max_by(|a, b| area(a).total_cmp(&area(b)))
+                    {
+                        keep[chosen.0] = true;
+                        anchor = chosen;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Operation for DownsampleOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = match &self.columns {
+            Some(cols) => cols.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| {
+                    data.dataframe()
+                        .column(c)
+                        .is_ok_and(|c| c.dtype().is_primitive_numeric())
+                })
+                .cloned()
+                .collect(),
+        };
+        let df = data.dataframe();
+        let times: Vec<Option<i64>> = data.timestamps_ms()?.into_iter().collect();
+
+        // Row indices of each series
+        let series_rows: Vec<Vec<usize>> = if data.group_columns().is_empty() {
+            vec![(0..df.height()).collect()]
+        } else {
+            const ROW_INDEX: &str = "__downsample_row";
+            df.select(data.group_columns())?
+                .with_row_index(ROW_INDEX.into(), None)?
+                .partition_by_stable(data.group_columns(), true)?
+                .iter()
+                .map(|partition| {
+                    let rows = partition.column(ROW_INDEX)?.idx()?;
+                    Ok(rows.into_no_null_iter().map(|i| i as usize).collect())
+                })
+                .collect::<Result<_>>()?
+        };
+
+        let mut keep = vec![false; df.height()];
+        for col_name in &columns {
+            let column = df.column(col_name)?;
+            if !column.dtype().is_primitive_numeric() {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "Cannot downsample non-numeric column: {}",
+                    col_name
+                )));
+            }
+            let values = column.cast(&DataType::Float64)?;
+            let values = values.f64()?;
+            for rows in &series_rows {
+                let mut points: Vec<(usize, i64, f64)> = rows
+                    .iter()
+                    .filter_map(|&row| Some((row, times[row]?, values.get(row)?)))
+                    .filter(|point| point.2.is_finite())
+                    .collect();
+                points.sort_by_key(|&(row, t, _)| (t, row));
+                let Some(&(_, origin, _)) = points.first() else {
+                    continue;
+                };
+                let points: Vec<Point> = points
+                    .into_iter()
+                    .map(|(row, t, v)| (row, (t - origin) as f64, v))
+                    .collect();
+                self.select(&points, &mut keep);
+            }
+        }
+
+        let mask = BooleanChunked::from_slice("keep".into(), &keep);
+        TimeSeriesData::with_metadata(df.filter(&mask)?, data.metadata().clone())
+    }
+
+    fn name(&self) -> &str {
+        "downsample"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+}
+
+impl TimeSeriesData {
+    /// Reduce all numeric feature columns to about `target` for plotting
+    ///
+    /// See `DownsampleOperation`.
+    pub fn downsample(self, method: DownsampleMethod, target: DownsampleTarget) -> Result<Self> {
+        DownsampleOperation::new(method, target, None)?.execute(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_downsample_keeps_shape_and_extremes() {
+        // A slow ramp with a single spike at row 500
+        let values: Vec<f64> = (0..1000)
+            .map(|i| if i == 500 { 100.0 } else { i as f64 / 100.0 })
+            .collect();
+        let data = Fixture::every(Duration::from_secs(1), 1000)
+            .with_column("flow", values)
+            .build()
+            .unwrap();
+
+        for method in [DownsampleMethod::Lttb, DownsampleMethod::MinMax] {
+            let result = data
+                .clone()
+                .downsample(method, DownsampleTarget::Points(50))
+                .unwrap();
+            assert!(result.len() <= 50);
+            let flow = result.dataframe().column("flow").unwrap().f64().unwrap();
+            assert_eq!(flow.max(), Some(100.0));
+            assert_eq!(flow.get(0), Some(0.0));
+            assert_eq!(flow.get(result.len() - 1), Some(9.99));
+        }
+
+        let op = DownsampleOperation::new(
+            DownsampleMethod::MinMax,
+            DownsampleTarget::Bucket(Duration::from_secs(60)),
+            None,
+        )
+        .unwrap();
+        let result = op.execute(data).unwrap();
+        // Up to two samples per minute plus the first and last sample
+        assert!(result.len() <= 2 * 17 + 2);
+        assert_eq!(
+            result
+                .dataframe()
+                .column("flow")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .max(),
+            Some(100.0)
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Holiday calendars and working-day filtering
+//!
+//! A `HolidayCalendar` decides whether a local date is a working day. It combines
+//! weekend days, rule-based public holidays of a country and plant-specific dates
+//! such as shutdowns. Pipelines define one calendar (the `[calendar]` section of the
+//! TOML config) and share it between `CalendarFilterOperation` and
+//! `CalendarBucketOperation` so "working day" means the same thing everywhere.
+//!
+//! Built-in country calendars: "US" (federal), "DE" (nationwide) and "FR".
+
+use crate::config::CalendarConfig;
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::temporal::timezone::{parse_time_zone, utc_to_local};
+use chrono::{Datelike, NaiveDate, Weekday};
+use chrono_tz::Tz;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Rule giving the date of a recurring holiday in a year
+#[derive(Debug, Clone, Copy)]
+enum HolidayRule {
+    Fixed {
+        month: u32,
+        day: u32,
+    },
+    /// Fixed date moved to Friday/Monday when it falls on Saturday/Sunday
+    FixedObserved {
+        month: u32,
+        day: u32,
+    },
+    /// `n`-th weekday of a month; negative `n` counts from the end
+    NthWeekday {
+        month: u32,
+        weekday: Weekday,
+        n: i32,
+    },
+    /// Days relative to Easter Sunday
+    Easter(i64),
+}
+
+impl HolidayRule {
+    fn date_in(self, year: i32) -> Option<NaiveDate> {
+        match self {
+            HolidayRule::Fixed { month, day } => NaiveDate::from_ymd_opt(year, month, day),
+            HolidayRule::FixedObserved { month, day } => {
+                let date = NaiveDate::from_ymd_opt(year, month, day)?;
+                match date.weekday() {
+                    Weekday::Sat => date.pred_opt(),
+                    Weekday::Sun => date.succ_opt(),
+                    _ => Some(date),
+                }
+            }
+            HolidayRule::NthWeekday { month, weekday, n } if n > 0 => {
+                NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8)
+            }
+            HolidayRule::NthWeekday { month, weekday, .. } => {
+                let (next_year, next_month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                let mut date = NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()?;
+                while date.weekday() != weekday {
+                    date = date.pred_opt()?;
+                }
+                Some(date)
+            }
+            HolidayRule::Easter(offset) => {
+                let easter = easter_sunday(year)?;
+                easter.checked_add_signed(chrono::Duration::days(offset))
+            }
+        }
+    }
+}
+
+/// Date of Easter Sunday (anonymous Gregorian algorithm)
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+fn country_rules(country: &str) -> Option<Vec<HolidayRule>> {
+    use HolidayRule::*;
+    let rules = match country.to_uppercase().as_str() {
+        "US" => vec![
+            FixedObserved { month: 1, day: 1 },
+            NthWeekday {
+                month: 1,
+                weekday: Weekday::Mon,
+                n: 3,
+            },
+            NthWeekday {
+                month: 2,
+                weekday: Weekday::Mon,
+                n: 3,
+            },
+            NthWeekday {
+                month: 5,
+                weekday: Weekday::Mon,
+                n: -1,
+            },
+            FixedObserved { month: 6, day: 19 },
+            FixedObserved { month: 7, day: 4 },
+            NthWeekday {
+                month: 9,
+                weekday: Weekday::Mon,
+                n: 1,
+            },
+            NthWeekday {
+                month: 10,
+                weekday: Weekday::Mon,
+                n: 2,
+            },
+            FixedObserved { month: 11, day: 11 },
+            NthWeekday {
+                month: 11,
+                weekday: Weekday::Thu,
+                n: 4,
+            },
+            FixedObserved { month: 12, day: 25 },
+        ],
+        "DE" => vec![
+            Fixed { month: 1, day: 1 },
+            Easter(-2),
+            Easter(1),
+            Fixed { month: 5, day: 1 },
+            Easter(39),
+            Easter(50),
+            Fixed { month: 10, day: 3 },
+            Fixed { month: 12, day: 25 },
+            Fixed { month: 12, day: 26 },
+        ],
+        "FR" => vec![
+            Fixed { month: 1, day: 1 },
+            Easter(1),
+            Fixed { month: 5, day: 1 },
+            Fixed { month: 5, day: 8 },
+            Easter(39),
+            Easter(50),
+            Fixed { month: 7, day: 14 },
+            Fixed { month: 8, day: 15 },
+            Fixed { month: 11, day: 1 },
+            Fixed { month: 11, day: 11 },
+            Fixed { month: 12, day: 25 },
+        ],
+        _ => return None,
+    };
+    Some(rules)
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").map_err(|_| {
+        IndustrytsError::ConfigError(format!("Invalid date (expected YYYY-MM-DD): {}", s))
+    })
+}
+
+/// Working-day calendar of a plant
+#[derive(Debug, Clone)]
+pub struct HolidayCalendar {
+    weekend: Vec<Weekday>,
+    rules: Vec<HolidayRule>,
+    dates: BTreeSet<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    /// Calendar with Saturday/Sunday weekends and no holidays
+    pub fn new() -> Self {
+        Self {
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+            rules: Vec::new(),
+            dates: BTreeSet::new(),
+        }
+    }
+
+    /// Calendar with the public holidays of `country` (ISO code, e.g. "DE")
+    pub fn country(country: &str) -> Result<Self> {
+        let rules = country_rules(country).ok_or_else(|| {
+            IndustrytsError::ConfigError(format!("No holiday calendar for country: {}", country))
+        })?;
+        Ok(Self {
+            rules,
+            ..Self::new()
+        })
+    }
+
+    /// Build a calendar from its configuration
+    pub fn from_config(config: &CalendarConfig) -> Result<Self> {
+        let mut calendar = match &config.country {
+            Some(country) => Self::country(country)?,
+            None => Self::new(),
+        };
+        if let Some(weekend) = &config.weekend {
+            let days = weekend
+                .iter()
+                .map(|d| {
+                    d.parse::<Weekday>().map_err(|_| {
+                        IndustrytsError::ConfigError(format!("Invalid weekday: {}", d))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            calendar = calendar.with_weekend(days);
+        }
+        for date in &config.holidays {
+            calendar = calendar.with_holiday(parse_date(date)?);
+        }
+        for shutdown in &config.shutdowns {
+            calendar =
+                calendar.with_shutdown(parse_date(&shutdown.start)?, parse_date(&shutdown.end)?)?;
+        }
+        Ok(calendar)
+    }
+
+    /// Replace the weekend days
+    pub fn with_weekend(mut self, weekend: Vec<Weekday>) -> Self {
+        self.weekend = weekend;
+        self
+    }
+
+    /// Add a single non-working date
+    pub fn with_holiday(mut self, date: NaiveDate) -> Self {
+        self.dates.insert(date);
+        self
+    }
+
+    /// Add a plant shutdown from `start` to `end` (inclusive)
+    pub fn with_shutdown(mut self, start: NaiveDate, end: NaiveDate) -> Result<Self> {
+        if end < start {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Shutdown ends before it starts: {} to {}",
+                start, end
+            )));
+        }
+        self.dates
+            .extend(start.iter_days().take_while(|d| *d <= end));
+        Ok(self)
+    }
+
+    /// Check whether `date` is a public holiday or plant-specific non-working date
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        if self.dates.contains(&date) {
+            return true;
+        }
+        // Observed dates can fall into the neighbouring year (Jan 1 on a Saturday)
+        let year = date.year();
+        self.rules
+            .iter()
+            .any(|rule| rule.date_in(year) == Some(date) || rule.date_in(year + 1) == Some(date))
+    }
+
+    /// Check whether `date` is a working day
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday()) && !self.is_holiday(date)
+    }
+
+    /// Holidays (excluding weekends) falling in `year`, sorted
+    ///
+    /// Empty for years outside the supported date range.
+    pub fn holidays(&self, year: i32) -> Vec<NaiveDate> {
+        let Some(start) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+            return Vec::new();
+        };
+        start
+            .iter_days()
+            .take_while(|d| d.year() == year)
+            .filter(|d| self.is_holiday(*d))
+            .collect()
+    }
+
+    /// Working-day flag for each UTC timestamp (ms), using local dates in `tz`
+    ///
+    /// `day_start` shifts the date boundary, so with a 06:00 production-day start
+    /// 05:00 local time still belongs to the previous day.
+    pub fn working_day_mask(
+        &self,
+        timestamps_ms: &Int64Chunked,
+        tz: &Tz,
+        day_start: chrono::NaiveTime,
+    ) -> BooleanChunked {
+        let since_midnight = day_start - chrono::NaiveTime::MIN;
+        let mut cache: HashMap<NaiveDate, bool> = HashMap::new();
+        timestamps_ms
+            .into_iter()
+            .map(|t| {
+                let local = utc_to_local(tz, t?)?;
+                let date = (local - since_midnight).date();
+                Some(
+                    *cache
+                        .entry(date)
+                        .or_insert_with(|| self.is_working_day(date)),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Default for HolidayCalendar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which days `CalendarFilterOperation` keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayFilter {
+    #[default]
+    Working,
+    NonWorking,
+}
+
+/// Calendar filter operation - keep rows on working (or non-working) days
+pub struct CalendarFilterOperation {
+    calendar: Arc<HolidayCalendar>,
+    time_zone: Tz,
+    keep: DayFilter,
+}
+
+impl CalendarFilterOperation {
+    /// Keep working days, judged by local dates in `time_zone`
+    pub fn new(calendar: Arc<HolidayCalendar>, time_zone: &str) -> Result<Self> {
+        Ok(Self {
+            calendar,
+            time_zone: parse_time_zone(time_zone)?,
+            keep: DayFilter::Working,
+        })
+    }
+
+    /// Choose whether working or non-working days are kept
+    pub fn with_keep(mut self, keep: DayFilter) -> Self {
+        self.keep = keep;
+        self
+    }
+}
+
+impl Operation for CalendarFilterOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let time_col = data.time_column().to_string();
+        let working = self.calendar.working_day_mask(
+            &data.timestamps_ms()?,
+            &self.time_zone,
+            chrono::NaiveTime::MIN,
+        );
+        let mask = match self.keep {
+            DayFilter::Working => working.fill_null_with_values(false)?,
+            DayFilter::NonWorking => (!working).fill_null_with_values(false)?,
+        };
+
+        let df = data.dataframe().filter(&mask)?;
+        let mut result = TimeSeriesData::new(df, Some(&time_col))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "calendar_filter"
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_country_holidays() {
+        let us = HolidayCalendar::country("US").unwrap();
+        assert!(us.is_holiday(date(2024, 11, 28))); // Thanksgiving
+        assert!(us.is_holiday(date(2024, 5, 27))); // Memorial Day
+        assert!(us.is_holiday(date(2026, 7, 3))); // July 4th observed on Friday
+        assert!(us.is_holiday(date(2021, 12, 31))); // New Year 2022 observed
+        assert_eq!(us.holidays(2024).len(), 11);
+        assert!(us.holidays(i32::MAX).is_empty());
+        assert!(us.holidays(i32::MIN).is_empty());
+
+        let de = HolidayCalendar::country("DE").unwrap();
+        assert!(de.is_holiday(date(2024, 3, 29))); // Good Friday
+        assert!(de.is_holiday(date(2024, 4, 1))); // Easter Monday
+        assert!(!de.is_working_day(date(2024, 3, 30))); // Saturday
+        assert!(de.is_working_day(date(2024, 4, 2)));
+        assert!(HolidayCalendar::country("XX").is_err());
+    }
+
+    #[test]
+    fn test_calendar_filter_with_shutdown() {
+        let calendar = HolidayCalendar::new()
+            .with_shutdown(date(2024, 1, 2), date(2024, 1, 3))
+            .unwrap();
+        // 2024-01-01 (Mon) to 2024-01-07 (Sun), one sample per day at noon UTC
+        let times: Vec<i64> = (0..7).map(|i| 1704110400000 + i * 86_400_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = CalendarFilterOperation::new(Arc::new(calendar), "UTC").unwrap();
+        let result = op.execute(data).unwrap();
+        let values: Vec<f64> = result
+            .dataframe()
+            .column("value")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(values, vec![1.0, 4.0, 5.0]);
+    }
+}
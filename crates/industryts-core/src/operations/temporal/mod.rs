@@ -1,9 +1,37 @@
 //! Temporal operations
 //!
 //! This module provides time-based operations:
+//! - clock_skew: correction of clock offsets and drift between systems
+//! - downsample: LTTB and min/max reduction of traces for plotting
+//! - parse: timestamp parsing from strings and epoch numbers
+//! - reconstruct: continuous signals from compressed historian samples
+//! - regularize: gap detection and insertion of missing timestamps
 //! - resample: resampling time series data
+//! - sort: sorting rows by time
 //! - shift: time-based shifting
 //! - aggregation: time-based aggregation
+//! - timezone: time zone conversion and DST-aware calendar buckets
+//! - holidays: holiday calendars and working-day filtering
+//! - state: time-in-state of string state columns
 
-// Placeholder for future temporal operations
-// TODO: Implement resample operation with Polars 0.51+ API
+pub mod clock_skew;
+pub mod downsample;
+pub mod holidays;
+pub mod parse;
+pub mod reconstruct;
+pub mod regularize;
+pub mod resample;
+pub mod sort;
+pub mod state;
+pub mod timezone;
+
+pub use clock_skew::ClockSkewOperation;
+pub use downsample::{DownsampleOperation, DownsampleTarget};
+pub use holidays::{CalendarFilterOperation, DayFilter, HolidayCalendar};
+pub use parse::ParseTimestampOperation;
+pub use reconstruct::{Interpolation, ReconstructOperation};
+pub use regularize::RegularizeOperation;
+pub use resample::ResampleOperation;
+pub use sort::SortByTimeOperation;
+pub use state::StateDwellTimeOperation;
+pub use timezone::{CalendarBucketOperation, ConvertTimezoneOperation};
@@ -0,0 +1,376 @@
+//! Timestamp parsing for string and epoch-encoded time columns
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use polars::prelude::*;
+
+/// Candidate formats tried in order when none are configured
+///
+/// Besides chrono format strings, the special tokens `rfc3339`, `epoch_s` and
+/// `epoch_ms` are recognized.
+pub const DEFAULT_FORMATS: &[&str] = &[
+    "rfc3339",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y %H:%M",
+    "%d/%m/%Y %H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y-%m-%d",
+    "%d.%m.%Y",
+];
+
+/// Ambiguous date formats of `DEFAULT_FORMATS`, replaced when the date order is known
+const AMBIGUOUS_FORMATS: &[&str] = &["%d/%m/%Y %H:%M:%S"];
+
+/// Slash and dash dates written day first, e.g. 31/12/2024
+const DAY_FIRST_FORMATS: &[&str] = &[
+    "%d/%m/%Y %H:%M:%S%.f",
+    "%d/%m/%Y %H:%M",
+    "%d-%m-%Y %H:%M:%S%.f",
+    "%d-%m-%Y %H:%M",
+    "%d/%m/%Y",
+    "%d-%m-%Y",
+];
+
+/// Slash and dash dates written month first, e.g. 12/31/2024
+const MONTH_FIRST_FORMATS: &[&str] = &[
+    "%m/%d/%Y %H:%M:%S%.f",
+    "%m/%d/%Y %H:%M",
+    "%m-%d-%Y %H:%M:%S%.f",
+    "%m-%d-%Y %H:%M",
+    "%m/%d/%Y",
+    "%m-%d-%Y",
+];
+
+/// `DEFAULT_FORMATS` with slash and dash dates read day first or month first
+pub fn formats_with_date_order(day_first: bool) -> Vec<String> {
+    let ordered = if day_first {
+        DAY_FIRST_FORMATS
+    } else {
+        MONTH_FIRST_FORMATS
+    };
+    DEFAULT_FORMATS
+        .iter()
+        .filter(|f| !AMBIGUOUS_FORMATS.contains(f))
+        .chain(ordered)
+        .map(|f| f.to_string())
+        .collect()
+}
+
+/// Parse a single value with one format, returning milliseconds since epoch
+fn parse_with_format(value: &str, format: &str) -> Option<i64> {
+    match format {
+        "rfc3339" => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|dt| dt.timestamp_millis()),
+        "epoch_s" => value.parse::<f64>().ok().map(|v| (v * 1000.0) as i64),
+        "epoch_ms" => value.parse::<f64>().ok().map(|v| v as i64),
+        fmt => NaiveDateTime::parse_from_str(value, fmt)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(value, fmt)
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+            })
+            .map(|dt| dt.and_utc().timestamp_millis()),
+    }
+}
+
+/// Parse a single timestamp with the default formats, in milliseconds since epoch
+pub(crate) fn parse_instant_ms(value: &str) -> Result<i64> {
+    DEFAULT_FORMATS
+        .iter()
+        .find_map(|format| parse_with_format(value.trim(), format))
+        .ok_or_else(|| IndustrytsError::ConfigError(format!("Cannot parse timestamp '{}'", value)))
+}
+
+/// Parse a string or integer series into milliseconds since epoch
+///
+/// Returns the parsed values and the positions of non-empty strings no format
+/// could parse; those become nulls.
+fn parse_millis(series: &Series, formats: &[String]) -> Result<(Vec<Option<i64>>, Vec<usize>)> {
+    let dtype = series.dtype();
+    let mut failed = Vec::new();
+
+    let millis: Vec<Option<i64>> = if dtype.is_integer() {
+        let values = series.cast(&DataType::Int64)?;
+        let seconds = if formats.iter().any(|f| f == "epoch_s") {
+            true
+        } else if formats.iter().any(|f| f == "epoch_ms") {
+            false
+        } else {
+            values
+                .i64()?
+                .into_iter()
+                .flatten()
+                .all(|v| v.abs() < 100_000_000_000)
+        };
+        values
+            .i64()?
+            .into_iter()
+            .map(|v| v.map(|v| if seconds { v * 1000 } else { v }))
+            .collect()
+    } else if *dtype == DataType::String {
+        let mut out = Vec::with_capacity(series.len());
+        for (i, value) in series.str()?.into_iter().enumerate() {
+            let parsed = match value.map(str::trim) {
+                None | Some("") => None,
+                Some(v) => {
+                    let parsed = formats.iter().find_map(|fmt| parse_with_format(v, fmt));
+                    if parsed.is_none() {
+                        failed.push(i);
+                    }
+                    parsed
+                }
+            };
+            out.push(parsed);
+        }
+        out
+    } else {
+        return Err(IndustrytsError::InvalidTimeColumnType(format!(
+            "{:?} (expected string or integer)",
+            dtype
+        )));
+    };
+
+    Ok((millis, failed))
+}
+
+/// Parse a string or integer series into a millisecond Datetime series
+///
+/// Integer columns are interpreted as epoch values: `epoch_s`/`epoch_ms` in
+/// `formats` select the unit, otherwise values above 1e11 are taken as
+/// milliseconds. String values are tried against each format in order. With
+/// `strict`, the first unparsable value is an error; otherwise it becomes null.
+pub fn parse_timestamp_series(series: &Series, formats: &[String], strict: bool) -> Result<Series> {
+    let name = series.name().clone();
+    let (millis, failed) = parse_millis(series, formats)?;
+    if strict && let Some(&first) = failed.first() {
+        let value = series
+            .str()?
+            .get(first)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        return Err(IndustrytsError::OperationError(format!(
+            "Cannot parse timestamp '{}' in column {} with formats {:?}",
+            value, name, formats
+        )));
+    }
+
+    Ok(Series::new(name, millis).cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?)
+}
+
+/// Column of dead-letter rows explaining why they were rejected
+pub const DEAD_LETTER_REASON_COLUMN: &str = "dead_letter_reason";
+
+/// Parse timestamp operation - convert string/epoch columns into Datetime
+///
+/// With a dead-letter output, rows whose value cannot be parsed are removed and
+/// routed, unchanged, to that secondary output with a `dead_letter_reason` column,
+/// so they can be written to a file or topic sink instead of failing the run or
+/// silently becoming nulls.
+pub struct ParseTimestampOperation {
+    column: String,
+    formats: Vec<String>,
+    strict: bool,
+    dead_letter: Option<String>,
+}
+
+impl ParseTimestampOperation {
+    pub fn new(column: String, formats: Option<Vec<String>>) -> Self {
+        let formats =
+            formats.unwrap_or_else(|| DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect());
+        Self {
+            column,
+            formats,
+            strict: true,
+            dead_letter: None,
+        }
+    }
+
+    /// Turn unparsable values into nulls instead of failing
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Route rows with unparsable values to the secondary output `output`
+    pub fn with_dead_letter(mut self, output: &str) -> Self {
+        self.dead_letter = Some(output.to_string());
+        self
+    }
+
+    /// Parse the column in a raw DataFrame
+    ///
+    /// Use this before `TimeSeriesData::new` when the time column itself is stored
+    /// as strings or epoch numbers.
+    pub fn apply_to_frame(&self, df: DataFrame) -> Result<DataFrame> {
+        if self.dead_letter.is_some() {
+            return Ok(self.apply_to_frame_with_rejects(df)?.0);
+        }
+        let series = self.series(&df)?;
+        let parsed = parse_timestamp_series(&series, &self.formats, self.strict)?;
+        let mut df = df;
+        df.replace(&self.column, parsed)?;
+        Ok(df)
+    }
+
+    /// Parse the column in a raw DataFrame, splitting off unparsable rows
+    ///
+    /// Returns the parsed rows and the rejected rows in their original form, with
+    /// a `dead_letter_reason` column.
+    pub fn apply_to_frame_with_rejects(&self, df: DataFrame) -> Result<(DataFrame, DataFrame)> {
+        let series = self.series(&df)?;
+        let (millis, failed) = parse_millis(&series, &self.formats)?;
+        let mut rejected_mask = vec![false; df.height()];
+        for &i in &failed {
+            rejected_mask[i] = true;
+        }
+        let rejected_mask = BooleanChunked::from_slice("rejected".into(), &rejected_mask);
+
+        let mut rejected = df.filter(&rejected_mask)?;
+        let reason = format!("unparsable timestamp in {}", self.column);
+        rejected.with_column(Series::new(
+            DEAD_LETTER_REASON_COLUMN.into(),
+            vec![reason; rejected.height()],
+        ))?;
+
+        let parsed = Series::new(self.column.as_str().into(), millis)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+        let mut df = df;
+        df.replace(&self.column, parsed)?;
+        Ok((df.filter(&!rejected_mask)?, rejected))
+    }
+
+    fn series(&self, df: &DataFrame) -> Result<Series> {
+        Ok(df
+            .column(&self.column)
+            .map_err(|_| IndustrytsError::ColumnNotFound(self.column.clone()))?
+            .as_materialized_series()
+            .clone())
+    }
+}
+
+impl Operation for ParseTimestampOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.execute_with_context(data, &mut OpContext::new())
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let time_column = data.time_column().to_string();
+        let metadata = data.metadata().clone();
+        let df = match &self.dead_letter {
+            Some(output) => {
+                let (df, rejected) = self.apply_to_frame_with_rejects(data.into_dataframe())?;
+                ctx.record_metric(
+                    &format!("{}.dead_letter", self.name()),
+                    rejected.height() as f64,
+                );
+                if rejected.height() > 0 {
+                    ctx.warn(format!(
+                        "{} rows with unparsable {} sent to {}",
+                        rejected.height(),
+                        self.column,
+                        output
+                    ));
+                }
+                ctx.outputs_mut().insert(output, rejected)?;
+                df
+            }
+            None => self.apply_to_frame(data.into_dataframe())?,
+        };
+        let mut result = TimeSeriesData::new(df, Some(&time_column))?;
+        result.metadata_mut().tags = metadata.tags;
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "parse_timestamp"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        vec![self.column.clone()]
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.dead_letter.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_day_first_format() {
+        let df = DataFrame::new(vec![
+            Series::new(
+                "tagTime".into(),
+                &["01.02.2024 08:00:00", "01.02.2024 08:01:00"],
+            )
+            .into(),
+            Series::new("value".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+
+        let op = ParseTimestampOperation::new("tagTime".to_string(), None);
+        let df = op.apply_to_frame(df).unwrap();
+        let ts = TimeSeriesData::new(df, Some("tagTime")).unwrap();
+
+        let millis: Vec<Option<i64>> = ts.timestamps_ms().unwrap().into_iter().collect();
+        assert_eq!(millis, vec![Some(1706774400000), Some(1706774460000)]);
+    }
+
+    #[test]
+    fn test_parse_epoch_seconds() {
+        let series = Series::new("t".into(), &[1704067200i64, 1704067260]);
+        let parsed = parse_timestamp_series(&series, &[], true).unwrap();
+        assert_eq!(
+            parsed.dtype(),
+            &DataType::Datetime(TimeUnit::Milliseconds, None)
+        );
+
+        let bad = Series::new("t".into(), &["not a time"]);
+        assert!(parse_timestamp_series(&bad, &["%Y-%m-%d".to_string()], true).is_err());
+    }
+
+    #[test]
+    fn test_dead_letter_rows() {
+        let df = DataFrame::new(vec![
+            Series::new(
+                "tagTime".into(),
+                &["2024-02-01 08:00:00", "garbage", "2024-02-01 08:02:00"],
+            )
+            .into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0]).into(),
+        ])
+        .unwrap();
+
+        let op = ParseTimestampOperation::new("tagTime".to_string(), None)
+            .with_dead_letter("dead_letter");
+        let (parsed, rejected) = op.apply_to_frame_with_rejects(df).unwrap();
+        assert_eq!(parsed.height(), 2);
+        assert_eq!(rejected.height(), 1);
+        assert_eq!(
+            rejected.column("tagTime").unwrap().str().unwrap().get(0),
+            Some("garbage")
+        );
+        assert_eq!(
+            rejected
+                .column(DEAD_LETTER_REASON_COLUMN)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(0),
+            Some("unparsable timestamp in tagTime")
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Signal reconstruction from compressed historian data
+//!
+//! Historians store a sample only when the value leaves a deadband or a
+//! swinging-door corridor, so stored samples cluster around changes and a mean
+//! over rows overweights them. `ReconstructOperation` rebuilds the signal on a
+//! regular grid from the stored samples: step tags (setpoints, states, counters)
+//! hold each sample until the next, continuous tags are interpolated linearly
+//! between them, as the historian itself would return them. Each column follows
+//! its `interpolation` property (see `TagMetadata`), or the operation's default.
+
+use super::regularize::timestamps_like;
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Column property declaring how a tag's value evolves between stored samples
+pub const INTERPOLATION: &str = "interpolation";
+
+/// How a value evolves between two stored samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    /// Linearly towards the next sample
+    #[default]
+    Linear,
+    /// Held until the next sample
+    Step,
+}
+
+impl Interpolation {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "linear" => Ok(Interpolation::Linear),
+            "step" => Ok(Interpolation::Step),
+            _ => Err(IndustrytsError::ConfigError(format!(
+                "Unknown interpolation '{}', expected 'linear' or 'step'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Reconstruct operation - resample compressed samples onto a regular grid
+///
+/// Returns one row per grid timestamp (multiples of `every` since the epoch)
+/// between the first and last stored sample of each series. Selected numeric
+/// columns (default: all numeric feature columns) are interpolated as declared;
+/// other columns hold their last stored value. A null sample stays null until
+/// the next one, and a linear value is null if either neighbour is.
+pub struct ReconstructOperation {
+    every: Duration,
+    interpolation: Interpolation,
+    max_gap: Option<Duration>,
+    columns: Option<Vec<String>>,
+}
+
+impl ReconstructOperation {
+    pub fn new(every: Duration, columns: Option<Vec<String>>) -> Result<Self> {
+        if every.as_millis() == 0 {
+            return Err(IndustrytsError::ConfigError(
+                "Reconstruction grid interval must be at least 1ms".to_string(),
+            ));
+        }
+        Ok(Self {
+            every,
+            interpolation: Interpolation::Linear,
+            max_gap: None,
+            columns,
+        })
+    }
+
+    /// Interpolation of columns without an `interpolation` property (default linear)
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Leave grid timestamps null between stored samples further apart than
+    /// `max_gap`, e.g. a multiple of the historian's heartbeat interval
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = Some(max_gap);
+        self
+    }
+
+    /// Interpolation of each selected column of `data`
+    fn interpolations(&self, data: &TimeSeriesData) -> Result<Vec<(String, Interpolation)>> {
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| {
+                    data.dataframe()
+                        .column(c)
+                        .is_ok_and(|c| c.dtype().is_primitive_numeric())
+                })
+                .cloned()
+                .collect(),
+        };
+        columns
+            .into_iter()
+            .map(|column| {
+                let interpolation = match data.column_property(&column, INTERPOLATION) {
+                    Some(declared) => Interpolation::parse(declared)?,
+                    None => self.interpolation,
+                };
+                Ok((column, interpolation))
+            })
+            .collect()
+    }
+
+    /// Reconstruct one series, sorted by time
+    fn reconstruct(
+        &self,
+        df: &DataFrame,
+        time_col: &str,
+        interpolations: &[(String, Interpolation)],
+    ) -> Result<DataFrame> {
+        let times: Vec<i64> = TimeSeriesData::new(df.clone(), Some(time_col))?
+            .timestamps_ms()?
+            .into_iter()
+            .flatten()
+            .collect();
+        let (Some(&first), Some(&last)) = (times.first(), times.last()) else {
+            return Ok(df.clear());
+        };
+        let every = self.every.as_millis() as i64;
+        let max_gap = self.max_gap.map(|g| g.as_millis() as i64);
+
+        // Last stored sample at or before each grid timestamp
+        let mut grid = Vec::new();
+        let mut at = Vec::new();
+        let mut sample = 0;
+        let mut t = -(-first).div_euclid(every) * every;
+        while t <= last {
+            while sample + 1 < times.len() && times[sample + 1] <= t {
+                sample += 1;
+            }
+            grid.push(t);
+            at.push(sample as IdxSize);
+            t += every;
+        }
+        // Whether the interval from each sample to the next is bridged
+        let bridged: Vec<bool> = grid
+            .iter()
+            .zip(&at)
+            .map(|(&t, &i)| {
+                let i = i as usize;
+                times[i] == t
+                    || max_gap
+                        .is_none_or(|gap| times.get(i + 1).is_none_or(|&n| n - times[i] <= gap))
+            })
+            .collect();
+        let at = IdxCa::from_vec("at".into(), at);
+
+        let mut columns = Vec::with_capacity(df.width());
+        for column in df.get_columns() {
+            let name = column.name().as_str();
+            if name == time_col {
+                columns.push(timestamps_like(name, &grid, column.dtype())?.into());
+                continue;
+            }
+            let interpolation = interpolations
+                .iter()
+                .find(|(c, _)| c == name)
+                .map(|(_, i)| *i);
+            let Some(interpolation) = interpolation else {
+                columns.push(column.take(&at)?);
+                continue;
+            };
+            let values = column.cast(&DataType::Float64)?;
+            let values = values.f64()?;
+            let rebuilt: Float64Chunked = grid
+                .iter()
+                .zip(at.into_no_null_iter())
+                .zip(&bridged)
+                .map(|((&t, i), &bridged)| {
+                    let i = i as usize;
+                    if !bridged {
+                        return None;
+                    }
+                    let value = values.get(i)?;
+                    match interpolation {
+                        Interpolation::Step => Some(value),
+                        Interpolation::Linear if times[i] == t => Some(value),
+                        Interpolation::Linear => {
+                            let next = values.get(i + 1)?;
+                            let share = (t - times[i]) as f64 / (times[i + 1] - times[i]) as f64;
+                            Some(value + (next - value) * share)
+                        }
+                    }
+                })
+                .collect();
+            let rebuilt = rebuilt.into_series().with_name(column.name().clone());
+            // Step columns keep their type, e.g. integer counters
+            let rebuilt = match interpolation {
+                Interpolation::Step => rebuilt.cast(column.dtype())?,
+                Interpolation::Linear => rebuilt,
+            };
+            columns.push(rebuilt.into());
+        }
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+impl Operation for ReconstructOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let interpolations = self.interpolations(&data)?;
+        let metadata = data.metadata().clone();
+        let time_col = metadata.time_column.clone();
+        let sorted = data.dataframe().sort(
+            [time_col.as_str()],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?;
+        let sorted = sorted.filter(&sorted.column(&time_col)?.is_not_null())?;
+        let partitions = if metadata.group_columns.is_empty() {
+            vec![sorted]
+        } else {
+            sorted.partition_by_stable(&metadata.group_columns, true)?
+        };
+
+        let mut df = data.dataframe().clear();
+        for partition in &partitions {
+            df.vstack_mut(&self.reconstruct(partition, &time_col, &interpolations)?)?;
+        }
+        let df = df.sort(
+            [time_col.as_str()],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?;
+        let mut result = TimeSeriesData::new(df, Some(&time_col))?
+            .with_group_columns(&metadata.group_columns)?;
+        result.metadata_mut().tags = metadata.tags;
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "reconstruct"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+
+    fn reorders_rows(&self) -> bool {
+        true
+    }
+
+    fn warmup(&self) -> Duration {
+        // A chunk starts with the last sample stored before it
+        self.max_gap.unwrap_or(self.every)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: i64 = 60_000;
+
+    #[test]
+    fn test_reconstruct_step_and_linear_tags() {
+        // Swinging-door samples of a ramp and a setpoint logged on change
+        let times = [0i64, 4, 5, 10].map(|m| m * MIN);
+        let time_series = Series::new("time".into(), &times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), &[0.0, 40.0, 40.0, 90.0]).into(),
+            Series::new("setpoint".into(), &[10i64, 20, 20, 30]).into(),
+        ])
+        .unwrap();
+        let mut data = TimeSeriesData::new(df, Some("time")).unwrap();
+        data.set_column_property("setpoint", INTERPOLATION, "step");
+
+        let op = ReconstructOperation::new(Duration::from_secs(60), None).unwrap();
+        let result = op.execute(data.clone()).unwrap();
+        assert_eq!(result.len(), 11);
+        let temp: Vec<f64> = result
+            .dataframe()
+            .column("temp")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(&temp[..6], [0.0, 10.0, 20.0, 30.0, 40.0, 40.0]);
+        assert_eq!(temp[8], 70.0);
+        // The mean of the four samples (42.5) overweights the change points
+        let mean = temp.iter().sum::<f64>() / temp.len() as f64;
+        assert!((mean - 490.0 / 11.0).abs() < 1e-9, "{}", mean);
+
+        let setpoint = result.dataframe().column("setpoint").unwrap();
+        assert_eq!(setpoint.dtype(), &DataType::Int64);
+        let setpoint: Vec<i64> = setpoint.i64().unwrap().into_no_null_iter().collect();
+        assert_eq!(setpoint, [10, 10, 10, 10, 20, 20, 20, 20, 20, 20, 30]);
+
+        // Samples 5 minutes apart are not bridged with a 2-minute maximum gap
+        let gapped = op
+            .with_max_gap(Duration::from_secs(120))
+            .execute(data)
+            .unwrap();
+        let temp = gapped.dataframe().column("temp").unwrap();
+        assert_eq!(temp.null_count(), 7);
+        assert_eq!(temp.f64().unwrap().get(5), Some(40.0));
+    }
+}
@@ -0,0 +1,355 @@
+//! Gap detection and time-axis regularization
+//!
+//! Sensor dropouts leave holes in the time axis that row-based steps (lags, fills)
+//! silently bridge. `RegularizeOperation` takes the sampling interval (or infers it
+//! with `TimeSeriesData::infer_frequency`), reports every interval longer than the
+//! tolerance and, unless disabled, inserts the missing grid timestamps inside each
+//! gap as rows of nulls so downstream fill and interpolation steps have rows to work
+//! with. Existing rows are kept unchanged. Grouped data is regularized per entity.
+//! Grids coarser than an hour follow the local clock of data with a time zone, so
+//! a daily grid stays at local midnight across DST transitions.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::temporal::timezone::{local_offset_ms, parse_time_zone, utc_offset_ms};
+use crate::utils::duration::format_duration;
+use chrono_tz::Tz;
+use polars::prelude::*;
+use std::time::Duration;
+
+/// Regularize operation - detect gaps and insert missing timestamps
+pub struct RegularizeOperation {
+    interval: Option<Duration>,
+    tolerance: Option<Duration>,
+    insert_missing: bool,
+    gap_output: Option<String>,
+}
+
+/// A gap of one series
+struct Gap {
+    start: i64,
+    end: i64,
+    missing: usize,
+}
+
+impl RegularizeOperation {
+    /// Regularize to `interval`, or to the inferred sampling interval when `None`
+    pub fn new(interval: Option<Duration>) -> Self {
+        Self {
+            interval,
+            tolerance: None,
+            insert_missing: true,
+            gap_output: None,
+        }
+    }
+
+    /// Report intervals longer than `tolerance` as gaps (default 1.5 grid intervals)
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Insert null rows at missing grid timestamps (default true)
+    pub fn with_insert_missing(mut self, insert_missing: bool) -> Self {
+        self.insert_missing = insert_missing;
+        self
+    }
+
+    /// Route a table of the detected gaps to the secondary output `name`
+    ///
+    /// The table holds the group columns, `gap_start` and `gap_end` (the samples
+    /// around the gap), `gap_ms` and `missing_rows`.
+    pub fn with_gap_output(mut self, name: &str) -> Self {
+        self.gap_output = Some(name.to_string());
+        self
+    }
+
+    fn tolerance_ms(&self, interval_ms: i64) -> i64 {
+        self.tolerance
+            .map_or(interval_ms * 3 / 2, |t| t.as_millis() as i64)
+    }
+
+    /// Gaps of one series and the grid timestamps missing inside them
+    ///
+    /// With `zone`, grid timestamps are `interval_ms` apart on its local clock.
+    fn gaps(
+        &self,
+        data: &TimeSeriesData,
+        interval_ms: i64,
+        zone: Option<&Tz>,
+    ) -> Result<(Vec<Gap>, Vec<i64>)> {
+        let next = |t: i64| match zone {
+            Some(tz) => {
+                let local = t + utc_offset_ms(tz, t) + interval_ms;
+                local - local_offset_ms(tz, local)
+            }
+            None => t + interval_ms,
+        };
+        let tolerance_ms = self.tolerance_ms(interval_ms);
+        let mut times: Vec<i64> = data.timestamps_ms()?.into_iter().flatten().collect();
+        times.sort_unstable();
+        times.dedup();
+
+        let mut gaps = Vec::new();
+        let mut missing = Vec::new();
+        for pair in times.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if end - start <= tolerance_ms {
+                continue;
+            }
+            let before = missing.len();
+            let mut t = next(start);
+            while end - t > interval_ms / 2 {
+                missing.push(t);
+                t = next(t);
+            }
+            gaps.push(Gap {
+                start,
+                end,
+                missing: missing.len() - before,
+            });
+        }
+        Ok((gaps, missing))
+    }
+
+    fn run(&self, data: TimeSeriesData, ctx: Option<&mut OpContext>) -> Result<TimeSeriesData> {
+        let interval = match self.interval {
+            Some(interval) => Some(interval),
+            None => data.infer_frequency()?,
+        };
+        let Some(interval) = interval else {
+            return Ok(data);
+        };
+        let interval_ms = (interval.as_millis() as i64).max(1);
+        let zone = match data.time_zone() {
+            Some(zone) if interval_ms > 3_600_000 => Some(parse_time_zone(zone)?),
+            _ => None,
+        };
+
+        let time_col = data.time_column().to_string();
+        let groups = data.group_columns().to_vec();
+        let partitions = if groups.is_empty() {
+            vec![data.dataframe().clone()]
+        } else {
+            data.dataframe().partition_by_stable(&groups, true)?
+        };
+
+        let time_dtype = data.dataframe().column(&time_col)?.dtype().clone();
+        let mut inserted: Vec<DataFrame> = Vec::new();
+        let mut gap_tables: Vec<DataFrame> = Vec::new();
+        let (mut gap_count, mut missing_count, mut longest) = (0, 0, 0);
+        for partition in partitions {
+            let series = TimeSeriesData::new(partition, Some(&time_col))?;
+            let (gaps, missing) = self.gaps(&series, interval_ms, zone.as_ref())?;
+            if gaps.is_empty() {
+                continue;
+            }
+            gap_count += gaps.len();
+            missing_count += missing.len();
+            longest = longest.max(gaps.iter().map(|g| g.end - g.start).max().unwrap_or(0));
+
+            if self.gap_output.is_some() {
+                let keys = series.dataframe().select(&groups)?.head(Some(1));
+                let mut table = DataFrame::new(
+                    keys.get_columns()
+                        .iter()
+                        .map(|c| c.new_from_index(0, gaps.len()))
+                        .collect(),
+                )?;
+                let starts: Vec<i64> = gaps.iter().map(|g| g.start).collect();
+                let ends: Vec<i64> = gaps.iter().map(|g| g.end).collect();
+                table.with_column(timestamps_like("gap_start", &starts, &time_dtype)?)?;
+                table.with_column(timestamps_like("gap_end", &ends, &time_dtype)?)?;
+                table.with_column(Series::new(
+                    "gap_ms".into(),
+                    gaps.iter().map(|g| g.end - g.start).collect::<Vec<_>>(),
+                ))?;
+                table.with_column(Series::new(
+                    "missing_rows".into(),
+                    gaps.iter().map(|g| g.missing as u64).collect::<Vec<_>>(),
+                ))?;
+                gap_tables.push(table);
+            }
+            if self.insert_missing && !missing.is_empty() {
+                inserted.push(null_rows(series.dataframe(), &time_col, &groups, &missing)?);
+            }
+        }
+
+        if let Some(ctx) = ctx {
+            ctx.record_metric("regularize.interval_ms", interval_ms as f64);
+            ctx.record_metric("regularize.gaps", gap_count as f64);
+            ctx.record_metric("regularize.missing_rows", missing_count as f64);
+            ctx.record_metric("regularize.max_gap_ms", longest as f64);
+            if gap_count > 0 {
+                ctx.warn(format!(
+                    "{} gaps longer than {} (longest {}), {} grid timestamps missing",
+                    gap_count,
+                    format_duration(Duration::from_millis(self.tolerance_ms(interval_ms) as u64)),
+                    format_duration(Duration::from_millis(longest as u64)),
+                    missing_count
+                ));
+            }
+            if let Some(name) = &self.gap_output {
+                for table in gap_tables {
+                    ctx.outputs_mut().insert(name, table)?;
+                }
+            }
+        }
+
+        if inserted.is_empty() {
+            return Ok(data);
+        }
+        let metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for rows in &inserted {
+            df.vstack_mut(rows)?;
+        }
+        let df = df.sort(
+            [time_col.as_str()],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?;
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+}
+
+/// Time column `name` of dtype `dtype` holding `millis`
+pub(crate) fn timestamps_like(name: &str, millis: &[i64], dtype: &DataType) -> Result<Series> {
+    let series = match dtype {
+        DataType::Date => Series::new(
+            name.into(),
+            millis
+                .iter()
+                .map(|ms| ms.div_euclid(86_400_000) as i32)
+                .collect::<Vec<_>>(),
+        ),
+        DataType::Datetime(TimeUnit::Nanoseconds, _)
+        | DataType::Duration(TimeUnit::Nanoseconds) => Series::new(
+            name.into(),
+            millis.iter().map(|ms| ms * 1_000_000).collect::<Vec<_>>(),
+        ),
+        DataType::Datetime(TimeUnit::Microseconds, _)
+        | DataType::Duration(TimeUnit::Microseconds) => Series::new(
+            name.into(),
+            millis.iter().map(|ms| ms * 1_000).collect::<Vec<_>>(),
+        ),
+        _ => Series::new(name.into(), millis),
+    };
+    Ok(series.cast(dtype)?)
+}
+
+/// Rows at `millis` with the group keys of `partition` and nulls elsewhere
+pub(crate) fn null_rows(
+    partition: &DataFrame,
+    time_col: &str,
+    groups: &[String],
+    millis: &[i64],
+) -> Result<DataFrame> {
+    let columns = partition
+        .get_columns()
+        .iter()
+        .map(|column| {
+            let name = column.name().as_str();
+            Ok(if name == time_col {
+                timestamps_like(name, millis, column.dtype())?.into()
+            } else if groups.iter().any(|g| g == name) {
+                column.new_from_index(0, millis.len())
+            } else {
+                Series::full_null(name.into(), millis.len(), column.dtype()).into()
+            })
+        })
+        .collect::<Result<Vec<Column>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+impl Operation for RegularizeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        "regularize"
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        if self.insert_missing {
+            (input_rows, None)
+        } else {
+            (input_rows, Some(input_rows))
+        }
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.insert_missing
+    }
+
+    fn warmup(&self) -> Duration {
+        // Detecting a gap at the start of a chunk needs the sample before it
+        match (self.tolerance, self.interval) {
+            (Some(tolerance), _) => tolerance,
+            (None, Some(interval)) => interval * 3 / 2,
+            (None, None) => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regularize_reports_and_fills_gaps() {
+        // One-minute samples with 3 and 5 minute dropouts
+        let times = [0i64, 1, 2, 5, 6, 11, 12].map(|m| m * 60_000);
+        let time_series = Series::new("time".into(), &times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+        assert_eq!(
+            data.infer_frequency().unwrap(),
+            Some(Duration::from_secs(60))
+        );
+
+        let op = RegularizeOperation::new(None).with_gap_output("gaps");
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data, &mut ctx).unwrap();
+
+        let minutes: Vec<i64> = result
+            .timestamps_ms()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.unwrap() / 60_000)
+            .collect();
+        assert_eq!(minutes, (0..=12).collect::<Vec<_>>());
+        let values = result.dataframe().column("value").unwrap();
+        assert_eq!(values.null_count(), 6);
+        assert_eq!(values.f64().unwrap().get(5), Some(4.0));
+
+        let gaps = ctx.outputs().get("gaps").unwrap();
+        assert_eq!(gaps.height(), 2);
+        let missing: Vec<Option<u64>> = gaps
+            .column("missing_rows")
+            .unwrap()
+            .u64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(missing, [Some(2), Some(4)]);
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(metrics["regularize.gaps"], 2.0);
+        assert_eq!(metrics["regularize.max_gap_ms"], 300_000.0);
+    }
+}
@@ -0,0 +1,750 @@
+//! Resample operation
+//!
+//! Aggregates a series into fixed or calendar-length buckets. The bucket grid is
+//! anchored at the epoch and shifted by `offset`, so "1d" buckets with an offset of
+//! "6h" run from 06:00 to 06:00. For data with a time zone (see
+//! `TimeSeriesData::set_time_zone`), buckets longer than an hour follow the local
+//! clock, so daily buckets start at local midnight and DST days are 23 or 25 hours
+//! long; shorter buckets are laid on the UTC instants, which matches local
+//! boundaries for zones with whole-hour offsets. `label` chooses whether a bucket
+//! is stamped with its start or end and `closed` which edge includes its boundary
+//! sample; shift reports commonly need right-closed, right-labelled buckets
+//! ("hour ending") instead of the left/left default. Buckets without samples are
+//! left out unless `empty_buckets` asks for a dense grid from the first to the
+//! last bucket of each series.
+//! Elapsed-time data (see `TimeSeriesData::to_elapsed`) is bucketed from its origin.
+//! Columns given a weight column are averaged or summed weighted by it, e.g. a
+//! flow-weighted mean concentration, which a plain mean gets wrong when the flow
+//! varies within a bucket.
+//!
+//! With `min_coverage`, each aggregated column gets a `<column>_coverage` column:
+//! its non-null samples in the bucket over the samples expected from the bucket
+//! length and the sampling interval (capped at 1). Buckets below the minimum are
+//! null, so a daily mean of two hours of data is not reported as a valid KPI.
+//!
+//! When streaming, the rows of the last bucket of each chunk are held back until
+//! a later chunk completes it, so every bucket is aggregated once from all of its
+//! samples. The dense grid is completed within each emitted batch, and buckets
+//! closed on both sides may miss a boundary sample that arrives in the next batch.
+
+use crate::config::{AggMethod, EmptyBuckets, StateAggregation, WindowClosed, WindowLabel};
+use crate::core::stateful::concat_chunks;
+use crate::core::{Operation, StatefulOperation, StepState, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::quality::quality_aware_aggs;
+use crate::operations::temporal::timezone::{local_physical_to_utc, parse_time_zone};
+use crate::utils::parse_frequency;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// Resample operation - aggregate into regular time buckets
+pub struct ResampleOperation {
+    every: Duration,
+    offset: Duration,
+    label: WindowLabel,
+    closed: WindowClosed,
+    aggregation: AggMethod,
+    state_aggregation: StateAggregation,
+    empty_buckets: EmptyBuckets,
+    columns: Option<Vec<String>>,
+    /// Weight column per value column
+    weights: BTreeMap<String, String>,
+    /// Minimum share of expected samples per bucket
+    min_coverage: Option<f64>,
+    /// Sampling interval the expected samples are derived from
+    sample_interval: Option<std::time::Duration>,
+}
+
+/// Suffix of the coverage column added per aggregated column
+const COVERAGE_SUFFIX: &str = "_coverage";
+
+impl ResampleOperation {
+    /// Resample to `rule` (e.g. "15min", "1h", "1d", "1mo")
+    pub fn new(rule: &str, aggregation: AggMethod, columns: Option<Vec<String>>) -> Result<Self> {
+        let every = parse_frequency(rule)?;
+        if every.is_zero() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Resample rule must be positive: {}",
+                rule
+            )));
+        }
+        Ok(Self {
+            every,
+            offset: Duration::parse("0ns"),
+            label: WindowLabel::Left,
+            closed: WindowClosed::Left,
+            aggregation,
+            state_aggregation: StateAggregation::Mode,
+            empty_buckets: EmptyBuckets::Drop,
+            columns,
+            weights: BTreeMap::new(),
+            min_coverage: None,
+            sample_interval: None,
+        })
+    }
+
+    /// Shift bucket boundaries, e.g. "6h" for daily buckets starting at 06:00
+    pub fn with_offset(mut self, offset: &str) -> Result<Self> {
+        self.offset = parse_frequency(offset)?;
+        Ok(self)
+    }
+
+    /// Stamp buckets with their start (default) or end
+    pub fn with_label(mut self, label: WindowLabel) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// Aggregation of string state columns when the aggregation is numeric (default mode)
+    pub fn with_state_aggregation(mut self, state_aggregation: StateAggregation) -> Self {
+        self.state_aggregation = state_aggregation;
+        self
+    }
+
+    /// Which bucket edges include their boundary (default left)
+    pub fn with_closed(mut self, closed: WindowClosed) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    /// Emit buckets without samples as nulls or zeros (default: drop them)
+    pub fn with_empty_buckets(mut self, empty_buckets: EmptyBuckets) -> Self {
+        self.empty_buckets = empty_buckets;
+        self
+    }
+
+    /// Aggregate `column` weighted by `weight` (e.g. concentration by flow)
+    ///
+    /// Only `mean` and `sum` aggregations take weights.
+    pub fn with_weight(mut self, column: &str, weight: &str) -> Result<Self> {
+        if !self.aggregation.supports_weights() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Weighted aggregation needs mean or sum, got {:?}",
+                self.aggregation
+            )));
+        }
+        self.weights.insert(column.to_string(), weight.to_string());
+        Ok(self)
+    }
+
+    /// Null buckets holding less than `min_coverage` (0 to 1) of the expected samples
+    ///
+    /// Adds a `<column>_coverage` column per aggregated column. Expected samples are
+    /// the bucket length over `sample_interval`, or over the typical interval of
+    /// the input (`TimeSeriesData::infer_frequency`) when not given; give it when
+    /// streaming, so every chunk expects the same. Inputs of fewer than two samples
+    /// have no inferable interval; their coverage is null and their buckets nulled.
+    pub fn with_min_coverage(
+        mut self,
+        min_coverage: f64,
+        sample_interval: Option<std::time::Duration>,
+    ) -> Result<Self> {
+        if !(0.0..=1.0).contains(&min_coverage) {
+            return Err(IndustrytsError::ConfigError(format!(
+                "min_coverage must be between 0 and 1, got {}",
+                min_coverage
+            )));
+        }
+        if sample_interval.is_some_and(|interval| interval.is_zero()) {
+            return Err(IndustrytsError::ConfigError(
+                "sample_interval must be positive".to_string(),
+            ));
+        }
+        self.min_coverage = Some(min_coverage);
+        self.sample_interval = sample_interval;
+        Ok(self)
+    }
+
+    /// Add the coverage of each of `columns` and null the buckets below the minimum
+    ///
+    /// `df` holds the bucket boundaries and the count of each column under its
+    /// coverage name; the boundaries are dropped. Without a sampling interval the
+    /// coverage is unknown (null) and every bucket is nulled.
+    fn apply_coverage(
+        &self,
+        df: DataFrame,
+        columns: &[String],
+        min_coverage: f64,
+        interval_ms: Option<f64>,
+    ) -> Result<DataFrame> {
+        // Without buckets there are no boundary columns either
+        if df.height() == 0 {
+            let coverages: Vec<Expr> = columns
+                .iter()
+                .map(|c| {
+                    let name = format!("{}{}", c, COVERAGE_SUFFIX);
+                    col(name.as_str()).cast(DataType::Float64)
+                })
+                .collect();
+            return Ok(df.lazy().with_columns(coverages).collect()?);
+        }
+        let bucket_ms = (col("_upper_boundary") - col("_lower_boundary"))
+            .cast(DataType::Duration(TimeUnit::Milliseconds))
+            .cast(DataType::Float64);
+        let expected = match interval_ms {
+            Some(interval_ms) => bucket_ms / lit(interval_ms),
+            None => lit(NULL).cast(DataType::Float64),
+        };
+        let mut coverages = Vec::new();
+        let mut nulled = Vec::new();
+        for column in columns {
+            let coverage_column = format!("{}{}", column, COVERAGE_SUFFIX);
+            let coverage = col(coverage_column.as_str()).cast(DataType::Float64) / expected.clone();
+            coverages.push(
+                when(coverage.clone().gt(lit(1.0)))
+                    .then(lit(1.0))
+                    .otherwise(coverage)
+                    .alias(coverage_column.as_str()),
+            );
+            nulled.push(
+                when(
+                    col(coverage_column.as_str())
+                        .lt(lit(min_coverage))
+                        .or(col(coverage_column.as_str()).is_null()),
+                )
+                .then(lit(NULL))
+                .otherwise(col(column.as_str()))
+                .alias(column.as_str()),
+            );
+        }
+        let df = df
+            .lazy()
+            .with_columns(coverages)
+            .with_columns(nulled)
+            .drop(cols(["_lower_boundary", "_upper_boundary"]))
+            .collect()?;
+        Ok(df)
+    }
+
+    /// Insert the empty buckets between the first and last bucket of each series
+    fn complete_grid(
+        &self,
+        mut df: DataFrame,
+        time_col: &str,
+        groups: &[String],
+    ) -> Result<DataFrame> {
+        const PRESENT: &str = "__resample_present";
+        if df.height() == 0 {
+            return Ok(df);
+        }
+        let height = df.height();
+        df.with_column(Series::new(PRESENT.into(), vec![true; height]))?;
+        let dense = df.upsample_stable(groups.to_vec(), time_col, self.every)?;
+
+        // Each series starts with a sampled bucket, so forward filling restores the keys
+        let mut fills: Vec<Expr> = groups
+            .iter()
+            .map(|g| col(g.as_str()).fill_null_with_strategy(FillNullStrategy::Forward(None)))
+            .collect();
+        if self.empty_buckets == EmptyBuckets::Zero {
+            for (name, dtype) in dense.schema().iter() {
+                let is_key = name == time_col || groups.iter().any(|g| g == name.as_str());
+                if is_key || name == PRESENT || !dtype.is_primitive_numeric() {
+                    continue;
+                }
+                fills.push(
+                    when(col(PRESENT).is_null())
+                        .then(lit(0).cast(dtype.clone()))
+                        .otherwise(col(name.clone()))
+                        .alias(name.clone()),
+                );
+            }
+        }
+        let mut order = vec![time_col.to_string()];
+        order.extend(groups.iter().cloned());
+        let dense = dense
+            .lazy()
+            .with_columns(fills)
+            .sort(
+                order,
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .collect()?;
+        Ok(dense.drop(PRESENT)?)
+    }
+
+    /// Time zone whose clock the buckets of `data` follow, if any
+    fn local_zone<'a>(&self, data: &'a TimeSeriesData) -> Option<&'a str> {
+        let every = &self.every;
+        let calendar = every.months() > 0
+            || every.weeks() > 0
+            || every.days() > 0
+            || every.duration_ns() > 3_600_000_000_000;
+        data.time_zone().filter(|_| calendar)
+    }
+
+    fn options(&self) -> DynamicGroupOptions {
+        DynamicGroupOptions {
+            every: self.every,
+            period: self.every,
+            offset: self.offset,
+            label: match self.label {
+                WindowLabel::Left => Label::Left,
+                WindowLabel::Right => Label::Right,
+            },
+            include_boundaries: false,
+            closed_window: match self.closed {
+                WindowClosed::Left => ClosedWindow::Left,
+                WindowClosed::Right => ClosedWindow::Right,
+                WindowClosed::Both => ClosedWindow::Both,
+                WindowClosed::None => ClosedWindow::None,
+            },
+            start_by: StartBy::WindowBound,
+            ..Default::default()
+        }
+    }
+
+    /// Aggregate `data` on the grid of its time column
+    fn resample(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let time_col = data.time_column().to_string();
+        let columns = if let Some(cols) = &self.columns {
+            cols.clone()
+        } else {
+            data.feature_columns().to_vec()
+        };
+
+        let mut agg_exprs = quality_aware_aggs(
+            &data,
+            &columns,
+            self.columns.is_some(),
+            self.aggregation,
+            self.state_aggregation,
+            &self.weights,
+        );
+        let mut options = self.options();
+        let coverage = match self.min_coverage {
+            Some(min_coverage) => {
+                let interval = match self.sample_interval {
+                    Some(interval) => Some(interval),
+                    // Fewer than two samples have no interval, and no coverage
+                    None if data.len() < 2 => None,
+                    None => Some(data.infer_frequency()?.ok_or_else(|| {
+                        IndustrytsError::InvalidOperation(
+                            "cannot infer the sampling interval for bucket coverage; \
+                             set sample_interval"
+                                .to_string(),
+                        )
+                    })?),
+                };
+                options.include_boundaries = true;
+                for column in &columns {
+                    let name = format!("{}{}", column, COVERAGE_SUFFIX);
+                    agg_exprs.push(col(column.as_str()).count().alias(name.as_str()));
+                }
+                Some((min_coverage, interval.map(|i| i.as_secs_f64() * 1000.0)))
+            }
+            None => None,
+        };
+
+        // Grouped data gets buckets per entity, interleaved by time like the input
+        let groups = data.group_columns();
+        let mut order = vec![time_col.clone()];
+        order.extend(groups.iter().cloned());
+        let result_df = data
+            .dataframe()
+            .clone()
+            .lazy()
+            .sort([time_col.as_str()], SortMultipleOptions::default())
+            .group_by_dynamic(
+                col(time_col.as_str()),
+                groups.iter().map(|c| col(c.as_str())).collect::<Vec<_>>(),
+                options,
+            )
+            .agg(agg_exprs)
+            .sort(
+                order,
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .collect()?;
+        let result_df = match coverage {
+            Some((min_coverage, interval_ms)) => {
+                self.apply_coverage(result_df, &columns, min_coverage, interval_ms)?
+            }
+            None => result_df,
+        };
+        let result_df = match self.empty_buckets {
+            EmptyBuckets::Drop => result_df,
+            EmptyBuckets::Null | EmptyBuckets::Zero => {
+                self.complete_grid(result_df, &time_col, groups)?
+            }
+        };
+
+        let mut result =
+            TimeSeriesData::new(result_df, Some(&time_col))?.with_group_columns(groups)?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+}
+
+impl Operation for ResampleOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        if data.is_elapsed() {
+            // Buckets of elapsed time start at multiples of `every` since the origin
+            return self
+                .resample(data.elapsed_as_datetime()?)?
+                .datetime_as_elapsed();
+        }
+        let Some(zone) = data.time_zone().map(str::to_string) else {
+            return self.resample(data);
+        };
+        if self.local_zone(&data).is_some() {
+            return self.resample(data.to_wall_clock()?)?.localize(&zone);
+        }
+        let mut result = self.resample(data)?;
+        result.set_time_zone(&zone)?;
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "resample"
+    }
+
+    fn lookahead(&self) -> Vec<String> {
+        match self.label {
+            WindowLabel::Left => vec!["buckets labelled by their start hold later samples".into()],
+            WindowLabel::Right => Vec::new(),
+        }
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.weights.values().cloned());
+        columns
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        if self.min_coverage.is_none() {
+            return Vec::new();
+        }
+        self.columns
+            .as_deref()
+            .unwrap_or(feature_columns)
+            .iter()
+            .map(|c| format!("{}{}", c, COVERAGE_SUFFIX))
+            .collect()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        Some(Box::new(ResampleState {
+            operation: self,
+            pending: None,
+        }))
+    }
+}
+
+/// Streaming resampler holding back the samples of the open bucket
+struct ResampleState<'a> {
+    operation: &'a ResampleOperation,
+    pending: Option<TimeSeriesData>,
+}
+
+impl ResampleState<'_> {
+    /// Physical start of the bucket containing the latest sample of `data`
+    fn last_bucket_start(&self, data: &TimeSeriesData) -> Result<Option<i64>> {
+        let zone = match self.operation.local_zone(data) {
+            Some(zone) => Some(parse_time_zone(zone)?),
+            None => None,
+        };
+        let local;
+        let data = match zone {
+            Some(_) => {
+                local = data.clone().to_wall_clock()?;
+                &local
+            }
+            None if data.is_elapsed() => {
+                local = data.clone().elapsed_as_datetime()?;
+                &local
+            }
+            None => data,
+        };
+        let time_col = data.time_column();
+        let latest = data
+            .dataframe()
+            .select([time_col])?
+            .lazy()
+            .select([col(time_col).max()])
+            .drop_nulls(None);
+        let options = DynamicGroupOptions {
+            include_boundaries: true,
+            ..self.operation.options()
+        };
+        let bounds = latest
+            .group_by_dynamic(col(time_col), Vec::<Expr>::new(), options)
+            .agg([len()])
+            .collect()?;
+        let lower = bounds
+            .column("_lower_boundary")?
+            .as_materialized_series()
+            .to_physical_repr()
+            .cast(&DataType::Int64)?;
+        let start = lower.i64()?.max();
+        Ok(match zone {
+            // Bounds were computed on the local clock
+            Some(tz) => {
+                let dtype = data.dataframe().column(time_col)?.dtype().clone();
+                start.map(|start| local_physical_to_utc(&tz, start, &dtype))
+            }
+            None => start,
+        })
+    }
+}
+
+impl StatefulOperation for ResampleState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        let data = match self.pending.take() {
+            Some(pending) => concat_chunks(pending, &chunk)?,
+            None => chunk,
+        };
+        let Some(start) = self.last_bucket_start(&data)? else {
+            return self.operation.execute(data);
+        };
+
+        let times = data
+            .dataframe()
+            .column(data.time_column())?
+            .as_materialized_series()
+            .to_physical_repr()
+            .cast(&DataType::Int64)?;
+        let open = match self.operation.closed {
+            WindowClosed::Left | WindowClosed::Both => times.i64()?.gt_eq(start),
+            WindowClosed::Right | WindowClosed::None => times.i64()?.gt(start),
+        };
+        let metadata = data.metadata().clone();
+        let complete = data.dataframe().filter(&!&open)?;
+        self.pending = Some(TimeSeriesData::with_metadata(
+            data.dataframe().filter(&open)?,
+            metadata.clone(),
+        )?);
+        self.operation
+            .execute(TimeSeriesData::with_metadata(complete, metadata)?)
+    }
+
+    fn finish(&mut self) -> Result<Option<TimeSeriesData>> {
+        self.pending
+            .take()
+            .map(|pending| self.operation.execute(pending))
+            .transpose()
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(match &self.pending {
+            Some(pending) => StepState {
+                rows: Some(pending.dataframe().clone()),
+                values: serde_json::to_value(pending.metadata())?,
+            },
+            None => StepState::default(),
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        self.pending = match state.rows {
+            Some(rows) => Some(TimeSeriesData::with_metadata(
+                rows,
+                serde_json::from_value(state.values)?,
+            )?),
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hourly_data(start_ms: i64, hours: i64) -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..hours).map(|i| start_ms + i * 3_600_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let values: Vec<f64> = (0..hours).map(|i| i as f64).collect();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn column_f64(data: &TimeSeriesData, name: &str) -> Vec<Option<f64>> {
+        data.dataframe()
+            .column(name)
+            .unwrap()
+            .cast(&DataType::Float64)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_buckets_with_offset() {
+        // 2024-01-01 00:00 UTC, 48 hourly samples
+        let data = hourly_data(1704067200000, 48);
+        let op = ResampleOperation::new("1d", AggMethod::Count, None)
+            .unwrap()
+            .with_offset("6h")
+            .unwrap();
+        let result = op.execute(data).unwrap();
+
+        assert_eq!(
+            column_f64(&result, "value"),
+            vec![Some(6.0), Some(24.0), Some(18.0)]
+        );
+        // First bucket starts 2023-12-31 06:00 UTC
+        assert_eq!(result.timestamps_ms().unwrap().get(0), Some(1704002400000));
+    }
+
+    #[test]
+    fn test_right_closed_right_labelled() {
+        let data = hourly_data(0, 4);
+        let op = ResampleOperation::new("2h", AggMethod::Sum, None)
+            .unwrap()
+            .with_closed(WindowClosed::Right)
+            .with_label(WindowLabel::Right);
+        let result = op.execute(data).unwrap();
+
+        assert_eq!(
+            column_f64(&result, "value"),
+            vec![Some(0.0), Some(3.0), Some(3.0)]
+        );
+        let labels: Vec<Option<i64>> = result.timestamps_ms().unwrap().into_iter().collect();
+        assert_eq!(labels, vec![Some(0), Some(7_200_000), Some(14_400_000)]);
+    }
+
+    #[test]
+    fn test_flow_weighted_mean() {
+        let mut data = hourly_data(0, 4);
+        let df = data.dataframe_mut();
+        df.with_column(Series::new("flow".into(), &[1.0, 3.0, 0.0, 0.0]))
+            .unwrap();
+        df.with_column(Series::new(
+            "conc".into(),
+            &[Some(10.0), Some(20.0), None, Some(5.0)],
+        ))
+        .unwrap();
+        let columns = Some(vec!["conc".to_string(), "flow".to_string()]);
+
+        let mean = ResampleOperation::new("2h", AggMethod::Mean, columns.clone())
+            .unwrap()
+            .with_weight("conc", "flow")
+            .unwrap();
+        let result = mean.execute(data.clone()).unwrap();
+        // No flow in the second bucket, so no weighted mean
+        assert_eq!(column_f64(&result, "conc"), vec![Some(17.5), None]);
+        assert_eq!(column_f64(&result, "flow"), vec![Some(2.0), Some(0.0)]);
+
+        let mass = ResampleOperation::new("2h", AggMethod::Sum, columns.clone())
+            .unwrap()
+            .with_weight("conc", "flow")
+            .unwrap();
+        let result = mass.execute(data).unwrap();
+        assert_eq!(column_f64(&result, "conc"), vec![Some(70.0), Some(0.0)]);
+
+        let last = ResampleOperation::new("2h", AggMethod::Last, columns).unwrap();
+        assert!(last.with_weight("conc", "flow").is_err());
+    }
+
+    #[test]
+    fn test_empty_buckets_complete_the_grid() {
+        // Hours 0, 1, 4 and 5 for entity "a", hours 0 and 2 for entity "b"
+        let hours = [0i64, 1, 4, 5, 0, 2];
+        let time_series = Series::new(
+            "time".into(),
+            hours.iter().map(|h| h * 3_600_000).collect::<Vec<_>>(),
+        )
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("unit".into(), &["a", "a", "a", "a", "b", "b"]).into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time"))
+            .unwrap()
+            .with_group_columns(&["unit".to_string()])
+            .unwrap();
+
+        let sparse = ResampleOperation::new("1h", AggMethod::Count, None)
+            .unwrap()
+            .execute(data.clone())
+            .unwrap();
+        assert_eq!(sparse.len(), 6);
+        assert_eq!(sparse.dataframe().column("unit").unwrap().null_count(), 0);
+
+        let zeros = ResampleOperation::new("1h", AggMethod::Count, None)
+            .unwrap()
+            .with_empty_buckets(EmptyBuckets::Zero)
+            .execute(data.clone())
+            .unwrap();
+        let units: Vec<Option<&str>> = zeros
+            .dataframe()
+            .column("unit")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            units,
+            ["a", "b", "a", "b", "a", "b", "a", "a", "a"]
+                .map(Some)
+                .to_vec()
+        );
+        assert_eq!(
+            column_f64(&zeros, "value"),
+            [1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0]
+                .map(Some)
+                .to_vec()
+        );
+
+        let nulls = ResampleOperation::new("1h", AggMethod::Mean, None)
+            .unwrap()
+            .with_empty_buckets(EmptyBuckets::Null)
+            .execute(data)
+            .unwrap();
+        assert_eq!(nulls.len(), 9);
+        assert_eq!(nulls.dataframe().column("value").unwrap().null_count(), 3);
+    }
+
+    #[test]
+    fn test_sparse_buckets_are_nulled() {
+        // Ten-minute samples: a full first hour, two samples in the second
+        let times: Vec<i64> = (0..6).chain([7, 8]).map(|i| i * 600_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 10.0, 20.0];
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let op = ResampleOperation::new("1h", AggMethod::Mean, None)
+            .unwrap()
+            .with_min_coverage(0.5, None)
+            .unwrap();
+        assert_eq!(op.added_columns(&["value".to_string()]), ["value_coverage"]);
+        let result = op.execute(data).unwrap();
+        assert_eq!(column_f64(&result, "value"), vec![Some(3.5), None]);
+        let coverage = column_f64(&result, "value_coverage");
+        assert_eq!(coverage[0], Some(1.0));
+        assert!((coverage[1].unwrap() - 2.0 / 6.0).abs() < 1e-9);
+        assert!(result.dataframe().column("_lower_boundary").is_err());
+        assert!(
+            ResampleOperation::new("1h", AggMethod::Mean, None)
+                .unwrap()
+                .with_min_coverage(1.5, None)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Sorting by time
+//!
+//! Historian exports and merged archives are often out of order, and steps that
+//! look at neighbouring rows (lags, differences, as-of joins, resampling) assume
+//! they are not. `SortByTimeOperation` sorts the rows by time, then by the group
+//! columns, keeping the input order of equal keys and putting null timestamps
+//! last. Each chunk is sorted on its own when streaming.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+
+/// Sort by time operation - guarantee a non-decreasing time column
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SortByTimeOperation;
+
+impl SortByTimeOperation {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Sort `data`; returns it and the number of rows that moved
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, usize)> {
+        const ROW_INDEX: &str = "__sort_row";
+        let mut keys = vec![data.time_column().to_string()];
+        keys.extend(data.group_columns().iter().cloned());
+        let sorted = data
+            .dataframe()
+            .with_row_index(ROW_INDEX.into(), None)?
+            .sort(
+                keys,
+                SortMultipleOptions::default()
+                    .with_maintain_order(true)
+                    .with_nulls_last(true),
+            )?;
+        let moved = sorted
+            .column(ROW_INDEX)?
+            .idx()?
+            .into_no_null_iter()
+            .enumerate()
+            .filter(|&(position, row)| position != row as usize)
+            .count();
+        let sorted = sorted.drop(ROW_INDEX)?;
+        Ok((
+            TimeSeriesData::with_metadata(sorted, data.metadata().clone())?,
+            moved,
+        ))
+    }
+}
+
+impl Operation for SortByTimeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data).map(|(result, _)| result)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, moved) = self.run(data)?;
+        ctx.record_metric("sort_by_time.moved_rows", moved as f64);
+        if moved > 0 {
+            ctx.warn(format!("{} row(s) were out of time order", moved));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "sort_by_time"
+    }
+
+    fn reorders_rows(&self) -> bool {
+        true
+    }
+}
+
+impl TimeSeriesData {
+    /// Sort the rows by time (then group columns); see `SortByTimeOperation`
+    pub fn sort_by_time(self) -> Result<Self> {
+        SortByTimeOperation::new().execute(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_sort_by_time_counts_moved_rows() {
+        let data = Fixture::at_times_ms(vec![0, 2_000, 1_000, 3_000])
+            .with_column("flow", [0.0, 2.0, 1.0, 3.0])
+            .build()
+            .unwrap();
+        let mut ctx = OpContext::new();
+        let result = SortByTimeOperation::new()
+            .execute_with_context(data, &mut ctx)
+            .unwrap();
+
+        let flow: Vec<f64> = result
+            .dataframe()
+            .column("flow")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(flow, vec![0.0, 1.0, 2.0, 3.0]);
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(metrics.get("sort_by_time.moved_rows"), Some(&2.0));
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,218 @@
+//! Time-in-state of string state columns
+//!
+//! Equipment states (running, idle, fault, ...) are usually logged on change, so a
+//! count or mode per bucket says little about how long the equipment spent in each
+//! state. `StateDwellTimeOperation` holds each sample's state until the next sample
+//! and splits these intervals over fixed-length buckets.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+/// State dwell time operation - seconds spent in each state per bucket
+///
+/// Returns one row per bucket (stamped with its start, anchored at the epoch) and a
+/// `<column>_<state>_dwell_s` column per state; all other columns are dropped. The
+/// last sample has no successor, so its state contributes no time.
+pub struct StateDwellTimeOperation {
+    column: String,
+    every: Duration,
+    states: Option<Vec<String>>,
+    max_hold: Option<Duration>,
+}
+
+impl StateDwellTimeOperation {
+    pub fn new(column: &str, every: Duration) -> Result<Self> {
+        if every.as_millis() == 0 {
+            return Err(IndustrytsError::ConfigError(
+                "State dwell bucket must be at least 1ms".to_string(),
+            ));
+        }
+        Ok(Self {
+            column: column.to_string(),
+            every,
+            states: None,
+            max_hold: None,
+        })
+    }
+
+    /// Report these states, in order (default: all states seen, sorted)
+    pub fn with_states(mut self, states: Vec<String>) -> Self {
+        self.states = Some(states);
+        self
+    }
+
+    /// Hold a state at most this long; longer gaps count as unknown
+    pub fn with_max_hold(mut self, max_hold: Duration) -> Self {
+        self.max_hold = Some(max_hold);
+        self
+    }
+
+    fn output_name(&self, state: &str) -> String {
+        format!("{}_{}_dwell_s", self.column, state)
+    }
+}
+
+impl Operation for StateDwellTimeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let time_col = data.time_column().to_string();
+        let states = data
+            .dataframe()
+            .column(&self.column)?
+            .cast(&DataType::String)?;
+        let mut samples: Vec<(i64, Option<&str>)> = data
+            .timestamps_ms()?
+            .into_iter()
+            .zip(states.str()?)
+            .filter_map(|(t, state)| t.map(|t| (t, state)))
+            .collect();
+        samples.sort_by_key(|s| s.0);
+
+        let names: Vec<String> = match &self.states {
+            Some(states) => states.clone(),
+            None => samples
+                .iter()
+                .filter_map(|s| s.1)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        };
+        let index: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+
+        let every = self.every.as_millis() as i64;
+        let max_hold = self.max_hold.map_or(i64::MAX, |d| d.as_millis() as i64);
+        let (first, last) = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => (first.0.div_euclid(every), last.0.div_euclid(every)),
+            _ => (0, -1),
+        };
+        let bucket_count = (last - first + 1) as usize;
+        let mut dwell = vec![vec![0.0f64; bucket_count]; names.len()];
+
+        for pair in samples.windows(2) {
+            let ((start, state), (next, _)) = (pair[0], pair[1]);
+            let Some(&state) = state.and_then(|s| index.get(s)) else {
+                continue;
+            };
+            let end = next.min(start.saturating_add(max_hold));
+            let mut t = start;
+            while t < end {
+                let bucket = t.div_euclid(every);
+                let bucket_end = ((bucket + 1) * every).min(end);
+                dwell[state][(bucket - first) as usize] += (bucket_end - t) as f64 / 1000.0;
+                t = bucket_end;
+            }
+        }
+
+        let bucket_starts: Vec<i64> = (first..=last).map(|b| b * every).collect();
+        let mut columns: Vec<Column> = vec![
+            Series::new(time_col.as_str().into(), bucket_starts)
+                .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+                .into(),
+        ];
+        for (name, values) in names.iter().zip(dwell) {
+            columns.push(Series::new(self.output_name(name).into(), values).into());
+        }
+
+        let mut result = TimeSeriesData::new(DataFrame::new(columns)?, Some(&time_col))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "state_dwell_time"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        vec![self.column.clone()]
+    }
+
+    fn added_columns(&self, _feature_columns: &[String]) -> Vec<String> {
+        self.states
+            .iter()
+            .flatten()
+            .map(|state| self.output_name(state))
+            .collect()
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AggMethod;
+    use crate::operations::ResampleOperation;
+
+    const MIN: i64 = 60_000;
+
+    fn make_data() -> TimeSeriesData {
+        // Logged on change: run 00:00, idle 00:40, fault 01:10, run 01:20, end 02:00
+        let times: Vec<i64> = vec![0, 40 * MIN, 70 * MIN, 80 * MIN, 120 * MIN];
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("state".into(), ["run", "idle", "fault", "run", "run"]).into(),
+            Series::new("speed".into(), [1500.0, 0.0, 0.0, 1450.0, 1480.0]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn column_f64(data: &TimeSeriesData, name: &str) -> Vec<f64> {
+        data.dataframe()
+            .column(name)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_dwell_time_per_bucket() {
+        let op = StateDwellTimeOperation::new("state", Duration::from_secs(3600)).unwrap();
+        let result = op.execute(make_data()).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            column_f64(&result, "state_run_dwell_s"),
+            vec![2400.0, 2400.0, 0.0]
+        );
+        assert_eq!(
+            column_f64(&result, "state_idle_dwell_s"),
+            vec![1200.0, 600.0, 0.0]
+        );
+        assert_eq!(
+            column_f64(&result, "state_fault_dwell_s"),
+            vec![0.0, 600.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_resample_keeps_state_columns() {
+        let op = ResampleOperation::new("1h", AggMethod::Mean, None).unwrap();
+        let result = op.execute(make_data()).unwrap();
+        let states: Vec<&str> = result
+            .dataframe()
+            .column("state")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        // 00:00-01:00 holds run and idle once each; ties go to the smaller name
+        assert_eq!(states, vec!["idle", "fault", "run"]);
+        assert_eq!(column_f64(&result, "speed"), vec![750.0, 725.0, 1480.0]);
+    }
+}
@@ -0,0 +1,543 @@
+//! Time zone conversion and DST-aware calendar bucketing
+//!
+//! Time columns are stored as naive timestamps. `TimeSeriesData::localize` reads
+//! them as wall-clock times of a plant's zone, converts them to UTC and records the
+//! zone in the metadata; `set_time_zone` declares the zone of data that is already
+//! UTC. Zone-aware data keeps unambiguous instants across DST transitions, while
+//! resampling to buckets longer than an hour and regularizing to such grids follow
+//! the local clock, so daily and shift aggregates start at local midnight or shift
+//! change all year round. `to_wall_clock` turns the instants back into local times
+//! for reports.
+//!
+//! `ConvertTimezoneOperation` reinterprets naive timestamps from one zone's wall
+//! clock into another's. `CalendarBucketOperation` aggregates UTC data into local
+//! production days or shifts whose boundaries follow the local wall clock, so DST
+//! transition days are 23 or 25 hours long.
+
+use crate::config::{AggMethod, StateAggregation};
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::quality::quality_aware_aggs;
+use crate::operations::temporal::holidays::HolidayCalendar;
+use chrono::{
+    DateTime, Duration as ChronoDuration, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone,
+};
+use chrono_tz::Tz;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Parse an IANA time zone name such as "Europe/Berlin"
+pub fn parse_time_zone(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|_| IndustrytsError::ConfigError(format!("Unknown time zone: {}", name)))
+}
+
+/// Convert a UTC instant (ms since epoch) into the wall-clock time of `tz`
+pub fn utc_to_local(tz: &Tz, utc_ms: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_millis(utc_ms).map(|dt| dt.with_timezone(tz).naive_local())
+}
+
+/// Convert a wall-clock time of `tz` into a UTC instant (ms since epoch)
+///
+/// Ambiguous times (fall-back hour) resolve to the earlier instant; non-existent
+/// times (spring-forward gap) use the offset in effect before the transition, so
+/// they move forward by the length of the gap (02:30 becomes 03:30).
+pub fn local_to_utc(tz: &Tz, local: NaiveDateTime) -> i64 {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => dt.timestamp_millis(),
+        LocalResult::Ambiguous(earliest, _) => earliest.timestamp_millis(),
+        LocalResult::None => {
+            // A day earlier the clock has not yet jumped
+            let offset = tz
+                .from_local_datetime(&(local - ChronoDuration::days(1)))
+                .earliest()
+                .map_or(0, |dt| dt.offset().fix().local_minus_utc());
+            (local - ChronoDuration::seconds(offset as i64))
+                .and_utc()
+                .timestamp_millis()
+        }
+    }
+}
+
+/// Physical units of the time column per millisecond
+fn units_per_ms(dtype: &DataType) -> i64 {
+    match dtype {
+        DataType::Datetime(TimeUnit::Nanoseconds, _) => 1_000_000,
+        DataType::Datetime(TimeUnit::Microseconds, _) => 1_000,
+        _ => 1,
+    }
+}
+
+/// Offset of local time from UTC in milliseconds at the UTC instant `utc_ms`
+pub(crate) fn utc_offset_ms(tz: &Tz, utc_ms: i64) -> i64 {
+    DateTime::from_timestamp_millis(utc_ms).map_or(0, |dt| {
+        tz.offset_from_utc_datetime(&dt.naive_utc())
+            .fix()
+            .local_minus_utc() as i64
+            * 1000
+    })
+}
+
+/// Offset of local time from UTC in milliseconds at the wall-clock time `local_ms`
+pub(crate) fn local_offset_ms(tz: &Tz, local_ms: i64) -> i64 {
+    DateTime::from_timestamp_millis(local_ms)
+        .map_or(0, |dt| local_ms - local_to_utc(tz, dt.naive_utc()))
+}
+
+/// Physical wall-clock time `local` of a column of `dtype` as a UTC instant
+pub(crate) fn local_physical_to_utc(tz: &Tz, local: i64, dtype: &DataType) -> i64 {
+    let factor = units_per_ms(dtype);
+    local - local_offset_ms(tz, local.div_euclid(factor)) * factor
+}
+
+impl TimeSeriesData {
+    /// Shift every timestamp by `offset_ms(timestamp in ms)` milliseconds
+    ///
+    /// Date columns become millisecond Datetime columns.
+    pub(crate) fn shift_time_column(self, offset_ms: impl Fn(i64) -> i64) -> Result<Self> {
+        let time_col = self.time_column().to_string();
+        let mut series = self
+            .dataframe()
+            .column(&time_col)?
+            .as_materialized_series()
+            .clone();
+        if series.dtype() == &DataType::Date {
+            series = series.cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+        }
+        let dtype = series.dtype().clone();
+        let factor = units_per_ms(&dtype);
+        let shifted: Vec<Option<i64>> = series
+            .to_physical_repr()
+            .i64()?
+            .into_iter()
+            .map(|t| t.map(|t| t + offset_ms(t.div_euclid(factor)) * factor))
+            .collect();
+        let shifted = Series::new(time_col.as_str().into(), shifted).cast(&dtype)?;
+
+        let metadata = self.metadata().clone();
+        let mut df = self.into_dataframe();
+        df.replace(&time_col, shifted)?;
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    /// Declare that the UTC timestamps of the data belong to the local time zone
+    /// `zone`; the timestamps are unchanged
+    pub fn set_time_zone(&mut self, zone: &str) -> Result<()> {
+        parse_time_zone(zone)?;
+        self.metadata_mut().time_zone = Some(zone.to_string());
+        Ok(())
+    }
+
+    /// Read naive timestamps as wall-clock times of `zone` and convert them to UTC
+    ///
+    /// Ambiguous times of the fall-back hour resolve to the earlier instant;
+    /// times in the spring-forward gap use the offset before the transition.
+    /// Fails if the data already has a time zone.
+    pub fn localize(self, zone: &str) -> Result<Self> {
+        if let Some(current) = self.time_zone() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "data is already localized to {}; use convert_time_zone",
+                current
+            )));
+        }
+        let tz = parse_time_zone(zone)?;
+        let mut data = self.shift_time_column(|local| -local_offset_ms(&tz, local))?;
+        data.metadata_mut().time_zone = Some(zone.to_string());
+        Ok(data)
+    }
+
+    /// Move localized data to another time zone; the UTC instants are unchanged
+    pub fn convert_time_zone(mut self, zone: &str) -> Result<Self> {
+        if self.time_zone().is_none() {
+            return Err(IndustrytsError::InvalidOperation(
+                "data has no time zone; use localize or set_time_zone first".to_string(),
+            ));
+        }
+        self.set_time_zone(zone)?;
+        Ok(self)
+    }
+
+    /// Naive wall-clock timestamps of the data's time zone, e.g. for reports
+    ///
+    /// Clears the time zone. Data without a time zone is returned unchanged.
+    pub fn to_wall_clock(self) -> Result<Self> {
+        let Some(zone) = self.time_zone() else {
+            return Ok(self);
+        };
+        let tz = parse_time_zone(zone)?;
+        let mut data = self.shift_time_column(|utc| utc_offset_ms(&tz, utc))?;
+        data.metadata_mut().time_zone = None;
+        Ok(data)
+    }
+}
+
+/// Convert timezone operation - reinterpret wall-clock timestamps in another zone
+pub struct ConvertTimezoneOperation {
+    from: Tz,
+    to: Tz,
+}
+
+impl ConvertTimezoneOperation {
+    pub fn new(from: &str, to: &str) -> Result<Self> {
+        Ok(Self {
+            from: parse_time_zone(from)?,
+            to: parse_time_zone(to)?,
+        })
+    }
+}
+
+impl Operation for ConvertTimezoneOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let time_col = data.time_column().to_string();
+        let converted: Vec<Option<i64>> = data
+            .timestamps_ms()?
+            .into_iter()
+            .map(|t| {
+                let local = DateTime::from_timestamp_millis(t?)?.naive_utc();
+                let utc = local_to_utc(&self.from, local);
+                utc_to_local(&self.to, utc).map(|dt| dt.and_utc().timestamp_millis())
+            })
+            .collect();
+
+        let series = Series::new(time_col.as_str().into(), converted)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+
+        // The output holds naive wall-clock times, not UTC instants of a zone
+        let mut metadata = data.metadata().clone();
+        metadata.time_zone = None;
+        let mut df = data.into_dataframe();
+        df.replace(&time_col, series)?;
+
+        let mut result = TimeSeriesData::with_metadata(df, metadata)?;
+        result.add_tag("time_zone".to_string(), self.to.name().to_string());
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "convert_timezone"
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        true
+    }
+}
+
+/// Calendar bucket operation - aggregate into local days or shifts
+///
+/// Buckets start at `day_start` local time and each day is split into
+/// `shifts_per_day` equal wall-clock shifts. The input time column is taken as UTC;
+/// output timestamps are the UTC instants of the bucket starts.
+pub struct CalendarBucketOperation {
+    time_zone: Tz,
+    day_start: NaiveTime,
+    shifts_per_day: u32,
+    aggregation: AggMethod,
+    state_aggregation: StateAggregation,
+    columns: Option<Vec<String>>,
+    holidays: Option<Arc<HolidayCalendar>>,
+    /// Weight column per value column
+    weights: BTreeMap<String, String>,
+}
+
+impl CalendarBucketOperation {
+    pub fn new(
+        time_zone: &str,
+        aggregation: AggMethod,
+        columns: Option<Vec<String>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            time_zone: parse_time_zone(time_zone)?,
+            day_start: NaiveTime::MIN,
+            shifts_per_day: 1,
+            aggregation,
+            state_aggregation: StateAggregation::Mode,
+            columns,
+            holidays: None,
+            weights: BTreeMap::new(),
+        })
+    }
+
+    /// Drop samples whose production day is not a working day of `calendar`
+    pub fn with_holidays(mut self, calendar: Arc<HolidayCalendar>) -> Self {
+        self.holidays = Some(calendar);
+        self
+    }
+
+    /// Aggregation of string state columns when the aggregation is numeric (default mode)
+    pub fn with_state_aggregation(mut self, state_aggregation: StateAggregation) -> Self {
+        self.state_aggregation = state_aggregation;
+        self
+    }
+
+    /// Aggregate `column` weighted by `weight` (e.g. concentration by flow)
+    ///
+    /// Only `mean` and `sum` aggregations take weights.
+    pub fn with_weight(mut self, column: &str, weight: &str) -> Result<Self> {
+        if !self.aggregation.supports_weights() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Weighted aggregation needs mean or sum, got {:?}",
+                self.aggregation
+            )));
+        }
+        self.weights.insert(column.to_string(), weight.to_string());
+        Ok(self)
+    }
+
+    /// Local time at which the production day starts (e.g. "06:00")
+    pub fn with_day_start(mut self, day_start: &str) -> Result<Self> {
+        self.day_start = NaiveTime::parse_from_str(day_start, "%H:%M").map_err(|_| {
+            IndustrytsError::ConfigError(format!(
+                "Invalid day start (expected HH:MM): {}",
+                day_start
+            ))
+        })?;
+        Ok(self)
+    }
+
+    /// Number of equal shifts per day (1 = daily buckets)
+    pub fn with_shifts_per_day(mut self, shifts_per_day: u32) -> Result<Self> {
+        if shifts_per_day == 0 || 24 % shifts_per_day != 0 {
+            return Err(IndustrytsError::ConfigError(format!(
+                "shifts_per_day must divide 24, got {}",
+                shifts_per_day
+            )));
+        }
+        self.shifts_per_day = shifts_per_day;
+        Ok(self)
+    }
+
+    /// UTC instant (ms) of the start of the bucket containing `utc_ms`
+    fn bucket_start(&self, utc_ms: i64) -> Option<i64> {
+        let local = utc_to_local(&self.time_zone, utc_ms)?;
+        let since_midnight = self.day_start - NaiveTime::MIN;
+        let shifted = local - since_midnight;
+
+        let shift_secs = 86_400 / self.shifts_per_day as i64;
+        let index = (shifted.time() - NaiveTime::MIN).num_seconds() / shift_secs;
+
+        let start =
+            shifted.date().and_time(self.day_start) + ChronoDuration::seconds(index * shift_secs);
+        Some(local_to_utc(&self.time_zone, start))
+    }
+}
+
+impl Operation for CalendarBucketOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let time_col = data.time_column().to_string();
+        let columns = if let Some(cols) = &self.columns {
+            cols.clone()
+        } else {
+            data.feature_columns().to_vec()
+        };
+
+        let timestamps = data.timestamps_ms()?;
+        let buckets: Vec<Option<i64>> = timestamps
+            .into_iter()
+            .map(|t| t.and_then(|t| self.bucket_start(t)))
+            .collect();
+        let bucket_series = Series::new(time_col.as_str().into(), buckets)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+
+        let mut df = data.dataframe().clone();
+        df.replace(&time_col, bucket_series)?;
+        if let Some(calendar) = &self.holidays {
+            let working = calendar.working_day_mask(&timestamps, &self.time_zone, self.day_start);
+            df = df.filter(&working.fill_null_with_values(false)?)?;
+        }
+
+        // Quality companion columns carry the worst quality of each bucket
+        let agg_exprs = quality_aware_aggs(
+            &data,
+            &columns,
+            self.columns.is_some(),
+            self.aggregation,
+            self.state_aggregation,
+            &self.weights,
+        );
+
+        // Grouped data gets buckets per entity, interleaved by time like the input
+        let mut keys = vec![time_col.clone()];
+        keys.extend(data.group_columns().iter().cloned());
+        let result_df = df
+            .lazy()
+            .group_by(keys.iter().map(|c| col(c.as_str())).collect::<Vec<_>>())
+            .agg(agg_exprs)
+            .sort(keys, SortMultipleOptions::default())
+            .collect()?;
+
+        let mut metadata = data.metadata().clone();
+        metadata
+            .feature_columns
+            .retain(|c| result_df.column(c).is_ok());
+        metadata.time_zone = Some(self.time_zone.name().to_string());
+        let mut result = TimeSeriesData::with_metadata(result_df, metadata)?;
+        result.add_tag("time_zone".to_string(), self.time_zone.name().to_string());
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "calendar_bucket"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.weights.values().cloned());
+        columns
+    }
+
+    fn removes_rows(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hourly_data(start_ms: i64, hours: i64) -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..hours).map(|i| start_ms + i * 3_600_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let values: Vec<f64> = (0..hours).map(|i| i as f64).collect();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_daily_buckets_across_dst() {
+        // 2024-03-30 00:00 CET; Berlin switches to CEST on 2024-03-31
+        let data = hourly_data(1711753200000, 49);
+        let op = CalendarBucketOperation::new("Europe/Berlin", AggMethod::Count, None).unwrap();
+        let result = op.execute(data).unwrap();
+
+        let counts: Vec<Option<i64>> = result
+            .dataframe()
+            .column("value")
+            .unwrap()
+            .cast(&DataType::Int64)
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(counts, vec![Some(24), Some(23), Some(2)]);
+    }
+
+    #[test]
+    fn test_calendar_buckets_per_group() {
+        let data = hourly_data(1711753200000, 48);
+        let units: Vec<&str> = (0..48)
+            .map(|i| if i % 2 == 0 { "a" } else { "b" })
+            .collect();
+        let mut df = data.into_dataframe();
+        df.with_column(Series::new("unit".into(), units)).unwrap();
+        let data = TimeSeriesData::new(df, Some("time"))
+            .unwrap()
+            .with_group_columns(&["unit".to_string()])
+            .unwrap();
+
+        let op = CalendarBucketOperation::new("Europe/Berlin", AggMethod::Count, None).unwrap();
+        let result = op.execute(data).unwrap();
+        assert_eq!(result.group_columns(), ["unit".to_string()]);
+        assert_eq!(result.feature_columns(), ["value".to_string()]);
+        assert_eq!(result.time_zone(), Some("Europe/Berlin"));
+
+        // One bucket per day and unit; the DST day has 23 hours, the last day one row of b
+        let counts: Vec<Option<i64>> = result
+            .dataframe()
+            .column("value")
+            .unwrap()
+            .cast(&DataType::Int64)
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            counts,
+            vec![Some(12), Some(12), Some(12), Some(11), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_localized_resample_across_dst() {
+        let mut data = hourly_data(1711753200000, 49);
+        data.set_time_zone("Europe/Berlin").unwrap();
+        let op = crate::operations::ResampleOperation::new("1d", AggMethod::Count, None).unwrap();
+        let result = op.execute(data).unwrap();
+        assert_eq!(result.time_zone(), Some("Europe/Berlin"));
+
+        // Buckets start at local midnight, the DST day is 23 hours long
+        let starts: Vec<Option<i64>> = result.timestamps_ms().unwrap().into_iter().collect();
+        assert_eq!(
+            starts,
+            vec![
+                Some(1711753200000),
+                Some(1711839600000),
+                Some(1711922400000)
+            ]
+        );
+        let counts = result.dataframe().column("value").unwrap();
+        assert_eq!(
+            counts.cast(&DataType::Int64).unwrap().i64().unwrap().get(1),
+            Some(23)
+        );
+
+        let wall = result.to_wall_clock().unwrap();
+        assert_eq!(wall.time_zone(), None);
+        let wall_ms = wall.timestamps_ms().unwrap();
+        assert_eq!(wall_ms.get(2), Some(1711929600000));
+        let back = wall.localize("Europe/Berlin").unwrap();
+        assert_eq!(back.timestamps_ms().unwrap().get(2), Some(1711922400000));
+    }
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_spring_forward_gap_east_of_utc() {
+        // Berlin skips 02:00-03:00 CET on 2024-03-31; 02:30 is read as CET,
+        // i.e. moved forward to 03:30 CEST
+        let tz = parse_time_zone("Europe/Berlin").unwrap();
+        let before = local_to_utc(&tz, local("2024-03-31 01:59"));
+        let gap = local_to_utc(&tz, local("2024-03-31 02:30"));
+        let shifted = local_to_utc(&tz, local("2024-03-31 03:30"));
+        assert_eq!(gap, 1711848600000, "01:30Z");
+        assert!(before < gap);
+        assert_eq!(gap, shifted);
+    }
+
+    #[test]
+    fn test_spring_forward_gap_west_of_utc() {
+        // New York skips 02:00-03:00 EST on 2024-03-10; 02:30 is read as EST,
+        // i.e. moved forward to 03:30 EDT
+        let tz = parse_time_zone("America/New_York").unwrap();
+        let before = local_to_utc(&tz, local("2024-03-10 01:59"));
+        let gap = local_to_utc(&tz, local("2024-03-10 02:30"));
+        let shifted = local_to_utc(&tz, local("2024-03-10 03:30"));
+        assert_eq!(gap, 1710055800000, "07:30Z");
+        assert!(before < gap);
+        assert_eq!(gap, shifted);
+    }
+
+    #[test]
+    fn test_convert_timezone() {
+        // 2024-01-01 12:00 UTC is 13:00 in Berlin (CET)
+        let data = hourly_data(1704110400000, 1);
+        let op = ConvertTimezoneOperation::new("UTC", "Europe/Berlin").unwrap();
+        let result = op.execute(data).unwrap();
+
+        assert_eq!(result.timestamps_ms().unwrap().get(0), Some(1704114000000));
+        assert_eq!(result.get_tag("time_zone"), Some("Europe/Berlin"));
+        assert_eq!(result.time_zone(), None);
+        assert_eq!(result.feature_columns(), ["value".to_string()]);
+    }
+}
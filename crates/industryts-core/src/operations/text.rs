@@ -0,0 +1,274 @@
+//! Cleanup of text columns
+//!
+//! Status columns and event messages come from operators and PLC alarm texts:
+//! padded with blanks, in inconsistent case, with codes buried in free text.
+//! `CleanTextOperation` trims, normalizes the case of and rewrites such columns
+//! with regex replacements; `ExtractPatternOperation` pulls a pattern, e.g. an
+//! error code such as `E-1042`, out of a message into its own column, ready for
+//! encoding or event logic.
+
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Case of cleaned text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextCase {
+    Lower,
+    Upper,
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| {
+        IndustrytsError::ConfigError(format!("Invalid text pattern '{}': {}", pattern, e))
+    })
+}
+
+/// String column `name` of `df`, failing for other types
+fn text_column<'a>(df: &'a DataFrame, name: &str) -> Result<&'a StringChunked> {
+    let column = df.column(name)?;
+    column.str().map_err(|_| {
+        IndustrytsError::InvalidOperation(format!(
+            "{} is a {} column, expected text",
+            name,
+            column.dtype()
+        ))
+    })
+}
+
+/// Clean text operation - trim, case-normalize and rewrite string columns
+///
+/// Applied in that order; replacements run in the order added and may refer to
+/// capture groups (`$1`). Nulls stay null.
+pub struct CleanTextOperation {
+    trim: bool,
+    case: Option<TextCase>,
+    replacements: Vec<(Regex, String)>,
+    columns: Option<Vec<String>>,
+}
+
+impl CleanTextOperation {
+    /// Clean `columns` (default: all string feature columns)
+    pub fn new(columns: Option<Vec<String>>) -> Self {
+        Self {
+            trim: false,
+            case: None,
+            replacements: Vec::new(),
+            columns,
+        }
+    }
+
+    /// Strip leading and trailing whitespace
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    pub fn with_case(mut self, case: TextCase) -> Self {
+        self.case = Some(case);
+        self
+    }
+
+    /// Replace all matches of the regex `pattern` with `replacement`
+    pub fn with_replacement(mut self, pattern: &str, replacement: &str) -> Result<Self> {
+        self.replacements
+            .push((compile(pattern)?, replacement.to_string()));
+        Ok(self)
+    }
+
+    fn clean<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(if self.trim { value.trim() } else { value });
+        match self.case {
+            Some(TextCase::Lower) => value = Cow::Owned(value.to_lowercase()),
+            Some(TextCase::Upper) => value = Cow::Owned(value.to_uppercase()),
+            None => {}
+        }
+        for (regex, replacement) in &self.replacements {
+            if let Cow::Owned(replaced) = regex.replace_all(&value, replacement.as_str()) {
+                value = Cow::Owned(replaced);
+            }
+        }
+        value
+    }
+}
+
+impl Operation for CleanTextOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| {
+                    data.dataframe()
+                        .column(c)
+                        .is_ok_and(|c| c.dtype() == &DataType::String)
+                })
+                .cloned()
+                .collect(),
+        };
+        let metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for name in &columns {
+            let cleaned: StringChunked = text_column(&df, name)?
+                .into_iter()
+                .map(|value| value.map(|v| self.clean(v)))
+                .collect();
+            df.replace(name, cleaned.with_name(name.as_str().into()).into_series())?;
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn name(&self) -> &str {
+        "clean_text"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+}
+
+/// Extract pattern operation - copy a regex match out of a text column
+///
+/// The output is a string column holding the capture group of the first match
+/// per row, null where the pattern does not match.
+pub struct ExtractPatternOperation {
+    column: String,
+    regex: Regex,
+    group: usize,
+    output: String,
+}
+
+impl ExtractPatternOperation {
+    /// Extract `pattern` from `column` into `<column>_match`
+    ///
+    /// The first capture group is extracted if the pattern has one, otherwise
+    /// the whole match.
+    pub fn new(column: &str, pattern: &str) -> Result<Self> {
+        let regex = compile(pattern)?;
+        Ok(Self {
+            column: column.to_string(),
+            group: usize::from(regex.captures_len() > 1),
+            regex,
+            output: format!("{}_match", column),
+        })
+    }
+
+    /// Extract capture group `group` (0 for the whole match)
+    pub fn with_group(mut self, group: usize) -> Result<Self> {
+        if group >= self.regex.captures_len() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Pattern '{}' has no capture group {}",
+                self.regex.as_str(),
+                group
+            )));
+        }
+        self.group = group;
+        Ok(self)
+    }
+
+    /// Name of the output column
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output = output.to_string();
+        self
+    }
+}
+
+impl Operation for ExtractPatternOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        let extracted: StringChunked = text_column(&df, &self.column)?
+            .into_iter()
+            .map(|value| {
+                let captures = self.regex.captures(value?)?;
+                Some(captures.get(self.group)?.as_str())
+            })
+            .collect();
+        df.with_column(extracted.with_name(self.output.as_str().into()))?;
+        if !metadata.feature_columns.contains(&self.output) {
+            metadata.feature_columns.push(self.output.clone());
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn name(&self) -> &str {
+        "extract_pattern"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        vec![self.column.clone()]
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        if feature_columns.contains(&self.output) {
+            Vec::new()
+        } else {
+            vec![self.output.clone()]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clean_and_extract_error_codes() {
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("flow", [1.0, 2.0, 3.0])
+            .with_column(
+                "message",
+                [
+                    Some("  Pump P-101 tripped: err e-1042  "),
+                    None,
+                    Some("Running   normally"),
+                ],
+            )
+            .build()
+            .unwrap();
+
+        let clean = CleanTextOperation::new(None)
+            .with_trim(true)
+            .with_case(TextCase::Upper)
+            .with_replacement(r"\s+", " ")
+            .unwrap();
+        let extract = ExtractPatternOperation::new("message", r"ERR (E-\d+)")
+            .unwrap()
+            .with_output("error_code");
+        let result = extract.execute(clean.execute(data).unwrap()).unwrap();
+
+        let df = result.dataframe();
+        let text = |name: &str| -> Vec<Option<String>> {
+            df.column(name)
+                .unwrap()
+                .str()
+                .unwrap()
+                .into_iter()
+                .map(|v| v.map(str::to_string))
+                .collect()
+        };
+        assert_eq!(
+            text("message"),
+            [
+                Some("PUMP P-101 TRIPPED: ERR E-1042".to_string()),
+                None,
+                Some("RUNNING NORMALLY".to_string()),
+            ]
+        );
+        assert_eq!(text("error_code"), [Some("E-1042".to_string()), None, None]);
+        assert_eq!(result.feature_columns(), ["flow", "message", "error_code"]);
+        assert!(ExtractPatternOperation::new("message", "(").is_err());
+        assert!(
+            CleanTextOperation::new(Some(vec!["flow".to_string()]))
+                .execute(result)
+                .is_err()
+        );
+    }
+}
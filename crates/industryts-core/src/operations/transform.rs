@@ -1,23 +1,237 @@
 //! Data transformation operations
 
-use crate::core::{Operation, TimeSeriesData};
-use crate::error::Result;
+use crate::config::ScaleMethod;
+use crate::core::naming::check_collisions;
+use crate::core::{
+    ArithmeticPolicy, BinaryOp, Carry, CarryMode, FittableOperation, NamingPolicy, OpContext,
+    Operation, StatefulOperation, StepState, TimeSeriesData,
+};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::outlier::{MAD_SCALE, median, quantile};
+use crate::operations::group::{map_partitions, partition_columns, target_columns};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+/// Center and scale of a column learned by `StandardizeOperation`
+///
+/// `mean` and `std` hold the median and scaled MAD for robust scaling. Values
+/// are clipped to `lower` and `upper` before scaling when quantile clipping is
+/// enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StandardScale {
+    pub mean: f64,
+    pub std: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lower: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upper: Option<f64>,
+}
+
+impl StandardScale {
+    /// Clip and scale `series`
+    fn apply(&self, series: &Series) -> Result<Series> {
+        if self.lower.is_none() && self.upper.is_none() {
+            return Ok(((series - self.mean) / self.std).with_name(series.name().clone()));
+        }
+        let (lower, upper) = (
+            self.lower.unwrap_or(f64::NEG_INFINITY),
+            self.upper.unwrap_or(f64::INFINITY),
+        );
+        let values = series.cast(&DataType::Float64)?;
+        let scaled: Float64Chunked = values
+            .f64()?
+            .into_iter()
+            .map(|v| v.map(|v| (v.clamp(lower, upper) - self.mean) / self.std))
+            .collect();
+        Ok(scaled.into_series().with_name(series.name().clone()))
+    }
+}
 
 /// Standardize operation - z-score normalization
+///
+/// Unfitted, each column is scaled with the statistics of the data it is
+/// applied to. Once fitted (see `FittableOperation`), the learned columns are
+/// scaled with the training statistics.
+///
+/// A few spikes inflate the standard deviation and squash every other value
+/// towards zero. Robust scaling uses the median and MAD instead, and quantile
+/// clipping limits values to a central range before the statistics are taken
+/// and the values scaled. With a reference window, the statistics come from the
+/// first rows (e.g. a known-good period) and are kept for the rest of the data,
+/// including later chunks when streaming.
 pub struct StandardizeOperation {
     columns: Option<Vec<String>>,
+    method: ScaleMethod,
+    clip_quantiles: Option<(f64, f64)>,
+    reference_window: Option<usize>,
+    fitted: Option<BTreeMap<String, StandardScale>>,
 }
 
 impl StandardizeOperation {
     pub fn new(columns: Option<Vec<String>>) -> Self {
-        Self { columns }
+        Self {
+            columns,
+            method: ScaleMethod::Standard,
+            clip_quantiles: None,
+            reference_window: None,
+            fitted: None,
+        }
+    }
+
+    /// Center and scale with mean and std (default) or median and MAD
+    pub fn with_method(mut self, method: ScaleMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Clip values to their `lower` and `upper` quantiles before scaling
+    pub fn with_clip_quantiles(mut self, lower: f64, upper: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&lower) || !(0.0..=1.0).contains(&upper) || lower >= upper {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Clip quantiles must satisfy 0 <= lower < upper <= 1, got [{}, {}]",
+                lower, upper
+            )));
+        }
+        self.clip_quantiles = Some((lower, upper));
+        Ok(self)
+    }
+
+    /// Learn the statistics from the first `rows` non-null values of each column
+    pub fn with_reference_window(mut self, rows: usize) -> Self {
+        self.reference_window = Some(rows);
+        self
+    }
+
+    /// Whether plain running moments give the statistics when streaming
+    fn is_plain(&self) -> bool {
+        self.method == ScaleMethod::Standard
+            && self.clip_quantiles.is_none()
+            && self.reference_window.is_none()
+    }
+
+    /// Non-null values of `col_name` the statistics are learned from
+    fn reference_values(&self, df: &DataFrame, col_name: &str) -> Result<Vec<f64>> {
+        let values = df.column(col_name)?.cast(&DataType::Float64)?;
+        Ok(values
+            .f64()?
+            .into_iter()
+            .flatten()
+            .filter(|v| !v.is_nan())
+            .take(self.reference_window.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Statistics of `values` of `col_name`
+    fn scale_from(&self, col_name: &str, mut values: Vec<f64>) -> Result<StandardScale> {
+        if values.len() < 2 {
+            return Err(IndustrytsError::OperationError(format!(
+                "Cannot calculate std for column: {}",
+                col_name
+            )));
+        }
+        values.sort_by(f64::total_cmp);
+        let (lower, upper) = match self.clip_quantiles {
+            Some((lower, upper)) => {
+                let (lower, upper) = (quantile(&values, lower), quantile(&values, upper));
+                values.iter_mut().for_each(|v| *v = v.clamp(lower, upper));
+                (Some(lower), Some(upper))
+            }
+            None => (None, None),
+        };
+        let (mean, std) = match self.method {
+            ScaleMethod::Standard => {
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+                (mean, var.sqrt())
+            }
+            ScaleMethod::Robust => {
+                let center = median(&values);
+                let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+                deviations.sort_by(f64::total_cmp);
+                (center, median(&deviations) * MAD_SCALE)
+            }
+        };
+
+        // Avoid division by zero
+        if std == 0.0 {
+            return Err(IndustrytsError::OperationError(format!(
+                "Standard deviation is zero for column: {}",
+                col_name
+            )));
+        }
+        Ok(StandardScale {
+            mean,
+            std,
+            lower,
+            upper,
+        })
+    }
+
+    /// Statistics of `col_name` in `df`
+    fn scale_of(&self, df: &DataFrame, col_name: &str) -> Result<StandardScale> {
+        self.scale_from(col_name, self.reference_values(df, col_name)?)
+    }
+
+    /// Statistics of `values`, or `None` if there are too few to estimate them
+    fn scale_if_estimable(
+        &self,
+        col_name: &str,
+        values: Vec<f64>,
+    ) -> Result<Option<StandardScale>> {
+        if values.len() < 2 {
+            return Ok(None);
+        }
+        self.scale_from(col_name, values).map(Some)
+    }
+}
+
+/// Scale `series` with `scale`, or null it when the statistics are unknown
+fn apply_scale(scale: Option<StandardScale>, series: &Series) -> Result<Series> {
+    match scale {
+        Some(scale) => scale.apply(series),
+        None => Ok(Series::full_null(
+            series.name().clone(),
+            series.len(),
+            &DataType::Float64,
+        )),
+    }
+}
+
+impl FittableOperation for StandardizeOperation {
+    fn fit(&mut self, data: &TimeSeriesData) -> Result<()> {
+        let columns = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| data.feature_columns().to_vec());
+        let scales = columns
+            .into_iter()
+            .map(|c| Ok((c.clone(), self.scale_of(data.dataframe(), &c)?)))
+            .collect::<Result<_>>()?;
+        self.fitted = Some(scales);
+        Ok(())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.fitted
+            .as_ref()
+            .and_then(|scales| serde_json::to_value(scales).ok())
+    }
+
+    fn load_params(&mut self, params: serde_json::Value) -> Result<()> {
+        self.fitted = Some(serde_json::from_value(params)?);
+        Ok(())
     }
 }
 
 impl Operation for StandardizeOperation {
     fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
         // Get columns to standardize
-        let columns_to_std = if let Some(cols) = &self.columns {
+        let columns_to_std = if let Some(scales) = &self.fitted {
+            scales.keys().cloned().collect()
+        } else if let Some(cols) = &self.columns {
             cols.clone()
         } else {
             data.feature_columns().to_vec()
@@ -27,63 +241,243 @@ impl Operation for StandardizeOperation {
 
         // Standardize each column: (x - mean) / std
         for col_name in &columns_to_std {
-            let column = df.column(col_name)?;
-            let series = column.as_materialized_series().clone();
+            // Fewer than two values give no statistics and null output
+            let scale = match self.fitted.as_ref().and_then(|s| s.get(col_name)) {
+                Some(scale) => Some(*scale),
+                None => self.scale_if_estimable(col_name, self.reference_values(&df, col_name)?)?,
+            };
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            df.replace(col_name, apply_scale(scale, &series)?)?;
+        }
 
-            // Calculate mean and std
-            let mean = series.mean().ok_or_else(|| {
-                crate::IndustrytsError::OperationError(format!(
-                    "Cannot calculate mean for column: {}",
-                    col_name
-                ))
-            })?;
+        // Create new TimeSeriesData with standardized data
+        TimeSeriesData::new(df, Some(data.time_column()))
+    }
 
-            let std = series.std(1).ok_or_else(|| {
-                crate::IndustrytsError::OperationError(format!(
-                    "Cannot calculate std for column: {}",
-                    col_name
-                ))
-            })?;
+    fn name(&self) -> &str {
+        "standardize"
+    }
 
-            // Avoid division by zero
-            if std == 0.0 {
-                return Err(crate::IndustrytsError::OperationError(format!(
-                    "Standard deviation is zero for column: {}",
-                    col_name
-                )));
-            }
+    fn lookahead(&self) -> Vec<String> {
+        match self.fitted {
+            Some(_) => Vec::new(),
+            None => vec!["scaling with statistics of all rows; fit the step first".to_string()],
+        }
+    }
 
-            // Standardize: (x - mean) / std
-            let standardized = (&series - mean) / std;
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
 
-            // Replace the column
-            df.replace(col_name, standardized)?;
+    fn fittable(&self) -> Option<&dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn fittable_mut(&mut self) -> Option<&mut dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        // Fitted statistics apply to every chunk unchanged
+        if self.fitted.is_some() {
+            return None;
         }
+        Some(Box::new(StandardizeState {
+            operation: self,
+            stats: BTreeMap::new(),
+            samples: BTreeMap::new(),
+        }))
+    }
+}
 
-        // Create new TimeSeriesData with standardized data
-        TimeSeriesData::new(df, Some(data.time_column()))
+/// Running count, mean and sum of squared deviations of a column
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct RunningMoments {
+    count: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningMoments {
+    /// Merge in the non-null values of `series`
+    fn update(&mut self, series: &Series) {
+        let count = (series.len() - series.null_count()) as f64;
+        let (Some(mean), Some(var)) = (series.mean(), series.var(0)) else {
+            return;
+        };
+        let total = self.count + count;
+        let delta = mean - self.mean;
+        self.m2 += var * count + delta * delta * self.count * count / total;
+        self.mean += delta * count / total;
+        self.count = total;
     }
 
-    fn name(&self) -> &str {
-        "standardize"
+    fn std(&self) -> Option<f64> {
+        (self.count > 1.0).then(|| (self.m2 / (self.count - 1.0)).sqrt())
     }
 }
 
+/// Standardization with the statistics of all chunks seen so far
+///
+/// Early chunks are scaled with the statistics available when they are processed,
+/// so the output converges to the batch result as the stream goes on. Plain
+/// standardization keeps running moments; robust or clipped scaling keeps the
+/// values seen so far, up to the reference window once it is full.
+struct StandardizeState<'a> {
+    operation: &'a StandardizeOperation,
+    stats: BTreeMap<String, RunningMoments>,
+    samples: BTreeMap<String, Vec<f64>>,
+}
+
+impl StandardizeState<'_> {
+    fn plain_scale(&mut self, col_name: &str, series: &Series) -> Result<Option<StandardScale>> {
+        let moments = self.stats.entry(col_name.to_string()).or_default();
+        moments.update(series);
+        let Some(std) = moments.std() else {
+            return Ok(None);
+        };
+        if std == 0.0 {
+            return Err(IndustrytsError::OperationError(format!(
+                "Standard deviation is zero for column: {}",
+                col_name
+            )));
+        }
+        Ok(Some(StandardScale {
+            mean: moments.mean,
+            std,
+            lower: None,
+            upper: None,
+        }))
+    }
+
+    fn sampled_scale(&mut self, df: &DataFrame, col_name: &str) -> Result<Option<StandardScale>> {
+        let operation = self.operation;
+        let samples = self.samples.entry(col_name.to_string()).or_default();
+        let room = operation.reference_window.unwrap_or(usize::MAX) - samples.len();
+        if room > 0 {
+            let values = operation.reference_values(df, col_name)?;
+            samples.extend(values.into_iter().take(room));
+        }
+        operation.scale_if_estimable(col_name, samples.clone())
+    }
+}
+
+impl StatefulOperation for StandardizeState<'_> {
+    fn process_chunk(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        let columns = match &self.operation.columns {
+            Some(columns) => columns.clone(),
+            None => chunk.feature_columns().to_vec(),
+        };
+        let metadata = chunk.metadata().clone();
+        let mut df = chunk.into_dataframe();
+        for col_name in &columns {
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            let scale = if self.operation.is_plain() {
+                self.plain_scale(col_name, &series)?
+            } else {
+                self.sampled_scale(&df, col_name)?
+            };
+            df.replace(col_name, apply_scale(scale, &series)?)?;
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn save_state(&self) -> Result<StepState> {
+        Ok(StepState {
+            values: serde_json::to_value((&self.stats, &self.samples))?,
+            ..Default::default()
+        })
+    }
+
+    fn restore_state(&mut self, state: StepState) -> Result<()> {
+        (self.stats, self.samples) = serde_json::from_value(state.values)?;
+        Ok(())
+    }
+}
+
+/// Range of a column learned by `NormalizeOperation`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinMaxScale {
+    pub min: f64,
+    pub max: f64,
+}
+
 /// Normalize operation - min-max normalization to [0, 1]
+///
+/// Once fitted, the learned columns are scaled with the training range, so new
+/// values outside it fall outside [0, 1].
 pub struct NormalizeOperation {
     columns: Option<Vec<String>>,
+    fitted: Option<BTreeMap<String, MinMaxScale>>,
 }
 
 impl NormalizeOperation {
     pub fn new(columns: Option<Vec<String>>) -> Self {
-        Self { columns }
+        Self {
+            columns,
+            fitted: None,
+        }
+    }
+
+    /// Min and max of `col_name` in `df`
+    fn scale_of(df: &DataFrame, col_name: &str) -> Result<MinMaxScale> {
+        let series = df.column(col_name)?.as_materialized_series();
+        let min = series.min::<f64>()?.ok_or_else(|| {
+            IndustrytsError::OperationError(format!(
+                "Cannot calculate min for column: {}",
+                col_name
+            ))
+        })?;
+        let max = series.max::<f64>()?.ok_or_else(|| {
+            IndustrytsError::OperationError(format!(
+                "Cannot calculate max for column: {}",
+                col_name
+            ))
+        })?;
+
+        // Avoid division by zero
+        if max - min == 0.0 {
+            return Err(IndustrytsError::OperationError(format!(
+                "Range is zero for column: {}",
+                col_name
+            )));
+        }
+        Ok(MinMaxScale { min, max })
+    }
+}
+
+impl FittableOperation for NormalizeOperation {
+    fn fit(&mut self, data: &TimeSeriesData) -> Result<()> {
+        let columns = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| data.feature_columns().to_vec());
+        let scales = columns
+            .into_iter()
+            .map(|c| Ok((c.clone(), Self::scale_of(data.dataframe(), &c)?)))
+            .collect::<Result<_>>()?;
+        self.fitted = Some(scales);
+        Ok(())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        self.fitted
+            .as_ref()
+            .and_then(|scales| serde_json::to_value(scales).ok())
+    }
+
+    fn load_params(&mut self, params: serde_json::Value) -> Result<()> {
+        self.fitted = Some(serde_json::from_value(params)?);
+        Ok(())
     }
 }
 
 impl Operation for NormalizeOperation {
     fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
         // Get columns to normalize
-        let columns_to_norm = if let Some(cols) = &self.columns {
+        let columns_to_norm = if let Some(scales) = &self.fitted {
+            scales.keys().cloned().collect()
+        } else if let Some(cols) = &self.columns {
             cols.clone()
         } else {
             data.feature_columns().to_vec()
@@ -93,38 +487,19 @@ impl Operation for NormalizeOperation {
 
         // Normalize each column: (x - min) / (max - min)
         for col_name in &columns_to_norm {
-            let column = df.column(col_name)?;
-            let series = column.as_materialized_series().clone();
-
-            // Calculate min and max
-            let min_val = series.min::<f64>()?.ok_or_else(|| {
-                crate::IndustrytsError::OperationError(format!(
-                    "Cannot calculate min for column: {}",
-                    col_name
-                ))
-            })?;
-
-            let max_val = series.max::<f64>()?.ok_or_else(|| {
-                crate::IndustrytsError::OperationError(format!(
-                    "Cannot calculate max for column: {}",
-                    col_name
-                ))
-            })?;
-
-            // Avoid division by zero
-            let range = max_val - min_val;
-            if range == 0.0 {
-                return Err(crate::IndustrytsError::OperationError(format!(
-                    "Range is zero for column: {}",
-                    col_name
-                )));
-            }
-
-            // Normalize: (x - min) / (max - min)
-            let normalized = (&series - min_val) / range;
-
-            // Replace the column
-            df.replace(col_name, normalized)?;
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            let scale = match self.fitted.as_ref().and_then(|s| s.get(col_name)) {
+                Some(scale) => *scale,
+                // Fewer than two values give no range and null output
+                None if series.len() - series.null_count() < 2 => {
+                    let nulls =
+                        Series::full_null(series.name().clone(), series.len(), &DataType::Float64);
+                    df.replace(col_name, nulls)?;
+                    continue;
+                }
+                None => Self::scale_of(&df, col_name)?,
+            };
+            df.replace(col_name, (&series - scale.min) / (scale.max - scale.min))?;
         }
 
         // Create new TimeSeriesData with normalized data
@@ -134,52 +509,437 @@ impl Operation for NormalizeOperation {
     fn name(&self) -> &str {
         "normalize"
     }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+
+    fn fittable(&self) -> Option<&dyn FittableOperation> {
+        Some(self)
+    }
+
+    fn fittable_mut(&mut self) -> Option<&mut dyn FittableOperation> {
+        Some(self)
+    }
 }
 
 /// Difference operation - calculate differences between consecutive values
+///
+/// With `partition_by`, each segment is differenced separately so the first rows
+/// of a batch are null rather than a jump from the previous batch. With an
+/// overflow policy, integer differences outside their type's range (e.g. of
+/// unsigned counters that reset) are nulled, clamped or rejected instead of
+/// wrapping.
 pub struct DifferenceOperation {
     lag: usize,
     columns: Option<Vec<String>>,
+    partition_by: Vec<String>,
+    overflow: Option<ArithmeticPolicy>,
+    naming: NamingPolicy,
 }
 
 impl DifferenceOperation {
     pub fn new(lag: usize, columns: Option<Vec<String>>) -> Self {
-        Self { lag, columns }
+        Self {
+            lag,
+            columns,
+            partition_by: Vec::new(),
+            overflow: None,
+            naming: NamingPolicy::default(),
+        }
+    }
+
+    /// Difference within segments identified by these columns
+    pub fn with_partition_by(mut self, partition_by: Vec<String>) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
+    /// Check differences for overflow and handle them with `policy`
+    pub fn with_overflow_policy(mut self, policy: ArithmeticPolicy) -> Self {
+        self.overflow = Some(policy);
+        self
+    }
+
+    fn output_name(&self, column: &str) -> String {
+        self.naming
+            .name(column, "diff", Some(&self.lag.to_string()))
+    }
+
+    /// Difference the data; returns it and the overflows per derived column
+    fn run(&self, data: TimeSeriesData) -> Result<(TimeSeriesData, Vec<(String, usize)>)> {
+        // Get columns to difference
+        let columns_to_diff = target_columns(
+            self.columns.as_deref(),
+            data.feature_columns(),
+            &self.partition_by,
+        );
+
+        check_collisions(
+            self.name(),
+            data.dataframe().get_column_names(),
+            &self.added_columns(data.feature_columns()),
+        )?;
+
+        let overflows: Vec<Cell<usize>> = columns_to_diff.iter().map(|_| Cell::new(0)).collect();
+        let partition_by = partition_columns(&self.partition_by, &data);
+        let df = map_partitions(data.dataframe(), partition_by, |mut df| {
+            // Calculate differences for each column
+            for (col_name, count) in columns_to_diff.iter().zip(&overflows) {
+                let column = df.column(col_name)?;
+                let series = column.as_materialized_series().clone();
+
+                // Calculate difference: x(t) - x(t-lag)
+                let shifted = series.shift(self.lag as i64);
+                let diff = match self.overflow {
+                    Some(policy) => {
+                        let (diff, violations) = policy.apply(BinaryOp::Sub, &series, &shifted)?;
+                        count.set(count.get() + violations.overflows);
+                        diff
+                    }
+                    None => (&series - &shifted)?,
+                };
+
+                // Create new column name
+                let diff_name = self.output_name(col_name);
+
+                // Add to dataframe
+                df.with_column(diff.with_name(diff_name.as_str().into()))?;
+            }
+            Ok(df)
+        })?;
+
+        // Create new TimeSeriesData with difference features
+        let overflows = columns_to_diff
+            .iter()
+            .zip(overflows)
+            .map(|(c, count)| (self.output_name(c), count.get()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        Ok((
+            TimeSeriesData::new(df, Some(data.time_column()))?,
+            overflows,
+        ))
     }
 }
 
 impl Operation for DifferenceOperation {
     fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
-        // Get columns to difference
-        let columns_to_diff = if let Some(cols) = &self.columns {
-            cols.clone()
-        } else {
-            data.feature_columns().to_vec()
-        };
+        self.run(data).map(|(result, _)| result)
+    }
 
-        let mut df = data.dataframe().clone();
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let (result, overflows) = self.run(data)?;
+        if self.overflow.is_some() {
+            let total: usize = overflows.iter().map(|(_, count)| count).sum();
+            ctx.record_metric("difference.overflows", total as f64);
+        }
+        for (column, count) in overflows {
+            ctx.warn(format!("{} overflow(s) in {}", count, column));
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "difference"
+    }
 
-        // Calculate differences for each column
-        for col_name in &columns_to_diff {
-            let column = df.column(col_name)?;
-            let series = column.as_materialized_series().clone();
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.naming = naming.clone();
+    }
 
-            // Calculate difference: x(t) - x(t-lag)
-            let shifted = series.shift(self.lag as i64);
-            let diff = (&series - &shifted)?;
+    fn apply_lazy(&self, mut lf: LazyFrame, time_column: &str) -> Result<LazyFrame> {
+        if self.overflow.is_some() {
+            let data = TimeSeriesData::new(lf.collect()?, Some(time_column))?;
+            return Ok(self.execute(data)?.into_dataframe().lazy());
+        }
+        let features = TimeSeriesData::lazy_feature_columns(&mut lf, time_column)?;
+        let columns = target_columns(self.columns.as_deref(), &features, &self.partition_by);
+        check_collisions(
+            self.name(),
+            lf.collect_schema()?.iter_names(),
+            &self.added_columns(&features),
+        )?;
+        let partition: Vec<Expr> = self.partition_by.iter().map(|c| col(c.as_str())).collect();
+        let diffs: Vec<Expr> = columns
+            .iter()
+            .map(|c| {
+                let diff = col(c.as_str()) - col(c.as_str()).shift(lit(self.lag as i64));
+                let diff = if partition.is_empty() {
+                    diff
+                } else {
+                    diff.over(partition.clone())
+                };
+                diff.alias(self.output_name(c))
+            })
+            .collect();
+        Ok(lf.with_columns(diffs))
+    }
 
-            // Create new column name
-            let diff_name = format!("{}_diff_{}", col_name, self.lag);
+    fn required_columns(&self) -> Vec<String> {
+        let mut columns = self.columns.clone().unwrap_or_default();
+        columns.extend(self.partition_by.iter().cloned());
+        columns
+    }
 
-            // Add to dataframe
-            df.with_column(diff.with_name(diff_name.as_str().into()))?;
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        target_columns(self.columns.as_deref(), feature_columns, &self.partition_by)
+            .iter()
+            .map(|c| self.output_name(c))
+            .collect()
+    }
+
+    fn stateful(&self) -> Option<Box<dyn StatefulOperation + '_>> {
+        let carry = Carry::new(self, CarryMode::InputRows(self.lag)).with_keys(&self.partition_by);
+        Some(Box::new(carry))
+    }
+}
+
+/// How quantized values are rounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantization {
+    /// Keep this many significant digits
+    SignificantDigits(u32),
+    /// Round to a multiple of this step (e.g. 0.1 for a 0.1 degC transmitter)
+    Resolution(f64),
+}
+
+impl Quantization {
+    fn apply(self, value: f64) -> f64 {
+        if !value.is_finite() || value == 0.0 {
+            return value;
         }
+        match self {
+            Quantization::SignificantDigits(digits) => {
+                let magnitude = value.abs().log10().floor() as i32;
+                round_to_step(value, 10f64.powi(magnitude + 1 - digits as i32))
+            }
+            Quantization::Resolution(step) => round_to_step(value, step),
+        }
+    }
+}
 
-        // Create new TimeSeriesData with difference features
-        TimeSeriesData::new(df, Some(data.time_column()))
+/// Round to a multiple of `step`, dividing by the reciprocal of fractional steps
+/// so that e.g. 0.3 at step 0.1 stays 0.3 rather than 0.30000000000000004
+fn round_to_step(value: f64, step: f64) -> f64 {
+    if step < 1.0 {
+        let recip = step.recip();
+        (value * recip).round() / recip
+    } else {
+        (value / step).round() * step
+    }
+}
+
+/// Quantize operation - round float columns to a precision and optionally store f32
+///
+/// Meant for archiving pipelines: rounding to the sensor's real resolution drops
+/// noise digits that compress badly, and f32 halves the size of the stored values.
+/// Without explicit columns only float feature columns are quantized.
+pub struct QuantizeOperation {
+    quantization: Quantization,
+    columns: Option<Vec<String>>,
+    downcast: bool,
+}
+
+impl QuantizeOperation {
+    pub fn new(quantization: Quantization, columns: Option<Vec<String>>) -> Result<Self> {
+        let valid = match quantization {
+            Quantization::SignificantDigits(digits) => digits > 0,
+            Quantization::Resolution(step) => step.is_finite() && step > 0.0,
+        };
+        if !valid {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Invalid quantization: {:?}",
+                quantization
+            )));
+        }
+        Ok(Self {
+            quantization,
+            columns,
+            downcast: false,
+        })
+    }
+
+    /// Store quantized columns as Float32
+    pub fn with_downcast(mut self, downcast: bool) -> Self {
+        self.downcast = downcast;
+        self
+    }
+}
+
+impl Operation for QuantizeOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        let columns: Vec<String> = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| df.column(c).is_ok_and(|c| c.dtype().is_float()))
+                .cloned()
+                .collect(),
+        };
+
+        for col_name in &columns {
+            let series = df.column(col_name)?.as_materialized_series().clone();
+            if !series.dtype().is_primitive_numeric() {
+                return Err(IndustrytsError::OperationError(format!(
+                    "Cannot quantize non-numeric column: {}",
+                    col_name
+                )));
+            }
+            let quantized: Float64Chunked = series
+                .cast(&DataType::Float64)?
+                .f64()?
+                .apply_values(|v| self.quantization.apply(v));
+            let dtype = if self.downcast {
+                DataType::Float32
+            } else {
+                DataType::Float64
+            };
+            df.replace(col_name, quantized.into_series().cast(&dtype)?)?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+        result.metadata_mut().tags = data.metadata().tags.clone();
+        Ok(result)
     }
 
     fn name(&self) -> &str {
-        "difference"
+        "quantize"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data() -> TimeSeriesData {
+        let time_series = Series::new("time".into(), [0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("temp".into(), [20.04999, 0.3000001, -123.456]).into(),
+            Series::new("count".into(), [1i64, 2, 3]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn values(data: &TimeSeriesData, name: &str) -> Vec<f64> {
+        let column = data.dataframe().column(name).unwrap();
+        column
+            .cast(&DataType::Float64)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_robust_clipped_and_reference_standardization() {
+        let data = crate::testing::Fixture::every(std::time::Duration::from_secs(60), 6)
+            .with_column("flow", [1.0, 2.0, 3.0, 4.0, 5.0, 100.0])
+            .build()
+            .unwrap();
+
+        // Median 3.5 and MAD 1.5, untouched by the spike
+        let robust = StandardizeOperation::new(None).with_method(ScaleMethod::Robust);
+        let flow = values(&robust.execute(data.clone()).unwrap(), "flow");
+        assert!((flow[4] - 1.0 / MAD_SCALE).abs() < 1e-9);
+
+        // The spike is clipped to the 80% quantile before scaling
+        let clipped = StandardizeOperation::new(None)
+            .with_clip_quantiles(0.0, 0.8)
+            .unwrap();
+        let flow = values(&clipped.execute(data.clone()).unwrap(), "flow");
+        assert_eq!(flow[5], flow[4]);
+        assert!(
+            StandardizeOperation::new(None)
+                .with_clip_quantiles(0.9, 0.1)
+                .is_err()
+        );
+
+        // Statistics frozen from the first three rows, also across chunks
+        let reference = StandardizeOperation::new(None).with_reference_window(3);
+        assert_eq!(
+            values(&reference.execute(data.clone()).unwrap(), "flow")[5],
+            98.0
+        );
+        let mut state = reference.stateful().unwrap();
+        let chunk = |offset, len| {
+            TimeSeriesData::with_metadata(
+                data.dataframe().slice(offset, len),
+                data.metadata().clone(),
+            )
+            .unwrap()
+        };
+        state.process_chunk(chunk(0, 3)).unwrap();
+        let tail = state.process_chunk(chunk(3, 3)).unwrap();
+        assert_eq!(values(&tail, "flow"), vec![2.0, 3.0, 98.0]);
+    }
+
+    #[test]
+    fn test_quantize_resolution_and_digits() {
+        let op = QuantizeOperation::new(Quantization::Resolution(0.1), None).unwrap();
+        let result = op.execute(make_data()).unwrap();
+        assert_eq!(values(&result, "temp"), vec![20.0, 0.3, -123.5]);
+        assert_eq!(
+            result.dataframe().column("count").unwrap().dtype(),
+            &DataType::Int64
+        );
+
+        let op = QuantizeOperation::new(Quantization::SignificantDigits(2), None)
+            .unwrap()
+            .with_downcast(true);
+        let result = op.execute(make_data()).unwrap();
+        assert_eq!(
+            result.dataframe().column("temp").unwrap().dtype(),
+            &DataType::Float32
+        );
+        assert_eq!(
+            values(&result, "temp"),
+            vec![20.0, 0.30000001192092896, -120.0]
+        );
+
+        assert!(QuantizeOperation::new(Quantization::Resolution(0.0), None).is_err());
+    }
+
+    #[test]
+    fn test_difference_overflow_policy() {
+        // An unsigned counter that resets between the second and third sample
+        let mut data = make_data();
+        data.dataframe_mut()
+            .with_column(Series::new("counter".into(), [10u32, 20, 5]))
+            .unwrap();
+        let data = TimeSeriesData::new(data.into_dataframe(), Some("time")).unwrap();
+        let op = DifferenceOperation::new(1, Some(vec!["counter".into()]))
+            .with_overflow_policy(ArithmeticPolicy::Null);
+        let mut ctx = OpContext::new();
+        let result = op.execute_with_context(data.clone(), &mut ctx).unwrap();
+        let diff = result.dataframe().column("counter_diff_1").unwrap();
+        assert_eq!(diff.dtype(), &DataType::UInt32);
+        assert_eq!(diff.u32().unwrap().get(1), Some(10));
+        assert_eq!(diff.null_count(), 2);
+        let (warnings, metrics) = ctx.take_diagnostics();
+        assert_eq!(
+            warnings,
+            vec!["1 overflow(s) in counter_diff_1".to_string()]
+        );
+        assert_eq!(metrics["difference.overflows"], 1.0);
+
+        let op = DifferenceOperation::new(1, Some(vec!["counter".into()]))
+            .with_overflow_policy(ArithmeticPolicy::Error);
+        assert!(op.execute(data).is_err());
     }
 }
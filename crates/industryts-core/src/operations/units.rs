@@ -0,0 +1,156 @@
+//! Conversion of columns to one engineering unit
+//!
+//! Sites report the same quantity in different units: a retrofitted line in degF,
+//! the rest in degC. `ConvertUnitsOperation` converts columns from the unit of
+//! each column to a target unit, so a group selected with
+//! `columns = "quantity:temperature"` ends up in one unit; see `core::units`.
+
+use crate::core::units::{convert_units, unit_info};
+use crate::core::{Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+
+/// Convert units operation - rescale columns to a target unit
+///
+/// Each column is converted from its `unit` property, which is then set to the
+/// target unit. Columns must have a known unit of the target's quantity.
+pub struct ConvertUnitsOperation {
+    unit: String,
+    columns: Option<Vec<String>>,
+}
+
+impl ConvertUnitsOperation {
+    /// Convert `columns` (default: every feature column whose unit measures the
+    /// quantity of `unit`) to `unit`
+    pub fn new(unit: &str, columns: Option<Vec<String>>) -> Result<Self> {
+        if unit_info(unit).is_none() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Unknown unit '{}'",
+                unit
+            )));
+        }
+        Ok(Self {
+            unit: unit.to_string(),
+            columns,
+        })
+    }
+}
+
+impl Operation for ConvertUnitsOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let quantity = unit_info(&self.unit).map(|info| info.quantity);
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => data
+                .feature_columns()
+                .iter()
+                .filter(|c| data.column_unit(c).and_then(unit_info).map(|i| i.quantity) == quantity)
+                .cloned()
+                .collect(),
+        };
+        let mut conversions = Vec::new();
+        for column in &columns {
+            let Some(unit) = data.column_unit(column) else {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "{} has no unit to convert to {}",
+                    column, self.unit
+                )));
+            };
+            conversions.push((column.clone(), convert_units(unit, &self.unit)?));
+        }
+
+        let metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for (column, (scale, offset)) in conversions {
+            let values = df.column(&column)?.cast(&DataType::Float64)?;
+            let converted = values.f64()?.apply_values(|v| v * scale + offset);
+            df.replace(&column, converted.into_series())?;
+        }
+        let mut result = TimeSeriesData::with_metadata(df, metadata)?;
+        for column in &columns {
+            result.set_column_property(column, "unit", &self.unit);
+        }
+        Ok(result)
+    }
+
+    fn name(&self) -> &str {
+        "convert_units"
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        self.columns.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PipelineConfig;
+    use crate::core::{TagInfo, TagMetadata};
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_convert_quantity_group() {
+        let tag = |tag: &str, unit: &str, quantity: Option<&str>| TagInfo {
+            tag: tag.to_string(),
+            unit: Some(unit.to_string()),
+            quantity: quantity.map(str::to_string),
+            ..Default::default()
+        };
+        let metadata = TagMetadata::new(vec![
+            tag("TI_101", "degC", None),
+            tag("TI_201", "degF", None),
+            tag("skin", "K", Some("temperature")),
+            tag("PI_101", "bar", None),
+        ])
+        .unwrap();
+        let data = Fixture::every(Duration::from_secs(60), 2)
+            .with_column("TI_101", [20.0, 100.0])
+            .with_column("TI_201", [68.0, 212.0])
+            .with_column("skin", [293.15, 373.15])
+            .with_column("PI_101", [1.0, 2.0])
+            .build()
+            .unwrap();
+
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "units"
+
+            [[operations]]
+            type = "convert_units"
+            unit = "degC"
+            columns = "quantity:temperature"
+            "#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::from_config(config).unwrap();
+        pipeline.set_tag_metadata(metadata);
+        let result = pipeline.process(data).unwrap();
+
+        let values = |name: &str| -> Vec<f64> {
+            result
+                .dataframe()
+                .column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_no_null_iter()
+                .collect()
+        };
+        for column in ["TI_101", "TI_201", "skin"] {
+            let converted = values(column);
+            assert!((converted[0] - 20.0).abs() < 1e-9, "{}", column);
+            assert!((converted[1] - 100.0).abs() < 1e-9, "{}", column);
+            assert_eq!(result.column_unit(column), Some("degC"));
+        }
+        assert_eq!(values("PI_101"), [1.0, 2.0]);
+        assert_eq!(result.column_unit("PI_101"), Some("bar"));
+
+        let op = ConvertUnitsOperation::new("degC", Some(vec!["PI_101".to_string()])).unwrap();
+        assert!(op.execute(result).is_err());
+        assert!(ConvertUnitsOperation::new("furlong", None).is_err());
+    }
+}
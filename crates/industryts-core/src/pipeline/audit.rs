@@ -0,0 +1,381 @@
+//! Audit trail of dropped and modified rows
+//!
+//! Traceable data cleaning requires showing what each step removed or changed.
+//! With a `RowAudit` set (`Pipeline::set_audit` or an `[audit]` section), every
+//! step's input is compared with its output: input rows missing from the output
+//! are reported as `dropped`, and rows whose values changed as `modified`, with
+//! the values before and after. The first `max_rows` affected rows of each kind
+//! per step are appended to a secondary output (default `audit`), which an
+//! `[audit] dir` or `Pipeline::add_sink` writes to a side file. The full counts
+//! are recorded as the step metrics `audit.dropped_rows` and `audit.modified_rows`.
+//!
+//! Rows are matched by timestamp and group key (repeated keys in order of
+//! appearance). Columns added by a step are not compared, and rows of steps that
+//! create new timestamps, such as resampling, are reported as dropped; restrict
+//! the audit to cleaning steps with `with_operations`. The audit table has one
+//! row per affected value: `step`, `operation`, `change`, `time`, `key` (group
+//! values joined by `|`, null without groups), `column`, `before` and `after`,
+//! values rendered as strings.
+
+use crate::config::AuditConfig;
+use crate::core::{OpContext, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Default number of sampled rows per step and kind of change
+const DEFAULT_MAX_ROWS: usize = 100;
+
+/// Sampling of rows dropped or modified by pipeline steps
+#[derive(Debug, Clone)]
+pub struct RowAudit {
+    output: String,
+    max_rows: usize,
+    operations: Option<Vec<String>>,
+}
+
+impl RowAudit {
+    /// Audit every step into the secondary output `output`
+    pub fn new(output: &str) -> Self {
+        Self {
+            output: output.to_string(),
+            max_rows: DEFAULT_MAX_ROWS,
+            operations: None,
+        }
+    }
+
+    /// Sample at most `rows` dropped and `rows` modified rows per step
+    pub fn with_max_rows(mut self, rows: usize) -> Self {
+        self.max_rows = rows;
+        self
+    }
+
+    /// Only audit steps with these operation names (e.g. `filter`, `fill_null`)
+    pub fn with_operations(mut self, operations: Vec<String>) -> Self {
+        self.operations = Some(operations);
+        self
+    }
+
+    /// Build the audit from the `[audit]` table
+    pub fn from_config(config: &AuditConfig) -> Self {
+        let audit = Self::new(&config.output).with_max_rows(config.max_rows);
+        match &config.operations {
+            Some(operations) => audit.with_operations(operations.clone()),
+            None => audit,
+        }
+    }
+
+    /// Name of the secondary output
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Compare the input and output of step `index` and record the affected rows
+    pub(crate) fn record(
+        &self,
+        index: usize,
+        operation: &str,
+        input: &TimeSeriesData,
+        output: &TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<()> {
+        if self
+            .operations
+            .as_ref()
+            .is_some_and(|names| !names.iter().any(|n| n == operation))
+        {
+            return Ok(());
+        }
+        let (input_keys, output_keys) = (RowKeys::of(input)?, RowKeys::of(output)?);
+        let positions: HashMap<(Option<i64>, Option<&str>, usize), IdxSize> = output_keys
+            .iter()
+            .enumerate()
+            .map(|(row, key)| (key, row as IdxSize))
+            .collect();
+        let (mut matched_in, mut matched_out, mut dropped) = (Vec::new(), Vec::new(), Vec::new());
+        for (row, key) in input_keys.iter().enumerate() {
+            match positions.get(&key) {
+                Some(&out) => {
+                    matched_in.push(row as IdxSize);
+                    matched_out.push(out);
+                }
+                None => dropped.push(row as IdxSize),
+            }
+        }
+
+        let mut entries = AuditEntries::new(index, operation);
+        for &row in dropped.iter().take(self.max_rows) {
+            for column in input.feature_columns() {
+                let before = render(input, column, row)?;
+                entries.push("dropped", &input_keys, row, column, before, None);
+            }
+        }
+        let modified = modified_values(input, output, &matched_in, &matched_out)?;
+        let mut modified_rows: Vec<IdxSize> = modified.iter().map(|m| m.0).collect();
+        modified_rows.dedup();
+        let sampled = modified_rows.get(..self.max_rows).unwrap_or(&modified_rows);
+        let last_sampled = sampled.last().copied();
+        for (row, column, before, after) in modified {
+            if last_sampled.is_none_or(|last| row > last) {
+                break;
+            }
+            entries.push("modified", &input_keys, row, &column, before, after);
+        }
+
+        ctx.record_metric("audit.dropped_rows", dropped.len() as f64);
+        ctx.record_metric("audit.modified_rows", modified_rows.len() as f64);
+        if !entries.is_empty() {
+            ctx.outputs_mut().insert(&self.output, entries.finish()?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Timestamps and group keys of the rows of a dataset
+struct RowKeys {
+    times: Vec<Option<i64>>,
+    groups: Option<Vec<String>>,
+    occurrences: Vec<usize>,
+}
+
+impl RowKeys {
+    fn of(data: &TimeSeriesData) -> Result<Self> {
+        let times: Vec<Option<i64>> = data.timestamps_ms()?.into_iter().collect();
+        let groups = if data.group_columns().is_empty() {
+            None
+        } else {
+            let mut parts = Vec::new();
+            for column in data.group_columns() {
+                let values = data.dataframe().column(column)?.cast(&DataType::String)?;
+                parts.push(values.str()?.clone());
+            }
+            let keys: Vec<String> = (0..data.len())
+                .map(|row| {
+                    let values: Vec<&str> =
+                        parts.iter().map(|p| p.get(row).unwrap_or("")).collect();
+                    values.join("|")
+                })
+                .collect();
+            Some(keys)
+        };
+        let mut seen: HashMap<(Option<i64>, Option<&str>), usize> = HashMap::new();
+        let mut occurrences = Vec::with_capacity(times.len());
+        for (row, time) in times.iter().enumerate() {
+            let group = groups.as_ref().map(|g| g[row].as_str());
+            let count = seen.entry((*time, group)).or_default();
+            occurrences.push(*count);
+            *count += 1;
+        }
+        Ok(Self {
+            times,
+            groups,
+            occurrences,
+        })
+    }
+
+    fn group(&self, row: usize) -> Option<&str> {
+        self.groups.as_ref().map(|g| g[row].as_str())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Option<i64>, Option<&str>, usize)> {
+        (0..self.times.len()).map(|row| (self.times[row], self.group(row), self.occurrences[row]))
+    }
+}
+
+/// Input row, column, and value before and after a change
+type ValueChange = (IdxSize, String, Option<String>, Option<String>);
+
+/// Every changed value of the matched rows, in input row order
+fn modified_values(
+    input: &TimeSeriesData,
+    output: &TimeSeriesData,
+    matched_in: &[IdxSize],
+    matched_out: &[IdxSize],
+) -> Result<Vec<ValueChange>> {
+    let rows_in = IdxCa::from_vec("rows".into(), matched_in.to_vec());
+    let rows_out = IdxCa::from_vec("rows".into(), matched_out.to_vec());
+    let mut changes = Vec::new();
+    for column in input.feature_columns() {
+        let Ok(after) = output.dataframe().column(column) else {
+            continue;
+        };
+        let before = input.dataframe().column(column)?.as_materialized_series();
+        let before = before.take(&rows_in)?;
+        // Values are compared at the input type; a failed cast is a change of kind
+        let Ok(after) = after
+            .as_materialized_series()
+            .take(&rows_out)?
+            .cast(before.dtype())
+        else {
+            continue;
+        };
+        let mut changed = !before.equal_missing(&after)?;
+        if before.dtype().is_float() {
+            let both_nan = before.is_nan()? & after.is_nan()?;
+            changed = changed & !both_nan.fill_null_with_values(false)?;
+        }
+        if !changed.any() {
+            continue;
+        }
+        let (before, after) = (
+            before.cast(&DataType::String)?,
+            after.cast(&DataType::String)?,
+        );
+        let (before, after) = (before.str()?, after.str()?);
+        for (i, is_changed) in changed.into_iter().enumerate() {
+            if is_changed == Some(true) {
+                changes.push((
+                    matched_in[i],
+                    column.clone(),
+                    before.get(i).map(str::to_string),
+                    after.get(i).map(str::to_string),
+                ));
+            }
+        }
+    }
+    changes.sort_by_key(|change| change.0);
+    Ok(changes)
+}
+
+/// Value of `column` at `row` as a string
+fn render(data: &TimeSeriesData, column: &str, row: IdxSize) -> Result<Option<String>> {
+    let values = data.dataframe().column(column)?.cast(&DataType::String)?;
+    Ok(values.str()?.get(row as usize).map(str::to_string))
+}
+
+/// Audit table rows of one step
+struct AuditEntries {
+    step: u32,
+    operation: String,
+    change: Vec<&'static str>,
+    time: Vec<Option<i64>>,
+    key: Vec<Option<String>>,
+    column: Vec<String>,
+    before: Vec<Option<String>>,
+    after: Vec<Option<String>>,
+}
+
+impl AuditEntries {
+    fn new(step: usize, operation: &str) -> Self {
+        Self {
+            step: step as u32,
+            operation: operation.to_string(),
+            change: Vec::new(),
+            time: Vec::new(),
+            key: Vec::new(),
+            column: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    fn push(
+        &mut self,
+        change: &'static str,
+        keys: &RowKeys,
+        row: IdxSize,
+        column: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) {
+        let row = row as usize;
+        self.change.push(change);
+        self.time.push(keys.times[row]);
+        self.key.push(keys.group(row).map(str::to_string));
+        self.column.push(column.to_string());
+        self.before.push(before);
+        self.after.push(after);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.change.is_empty()
+    }
+
+    fn finish(self) -> Result<DataFrame> {
+        let rows = self.change.len();
+        let time = Int64Chunked::from_iter_options("time".into(), self.time.into_iter())
+            .into_datetime(TimeUnit::Milliseconds, None)
+            .into_series();
+        let df = DataFrame::new(vec![
+            Column::new("step".into(), vec![self.step; rows]),
+            Column::new("operation".into(), vec![self.operation; rows]),
+            Column::new("change".into(), self.change),
+            time.into(),
+            Column::new("key".into(), self.key),
+            Column::new("column".into(), self.column),
+            Column::new("before".into(), self.before),
+            Column::new("after".into(), self.after),
+        ])?;
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FillMethod;
+    use crate::operations::FillNullOperation;
+    use crate::operations::data_quality::{Limits, OutlierAction, OutlierMethod, OutlierOperation};
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_audit_samples_dropped_and_modified_rows() {
+        let data = Fixture::every(Duration::from_secs(60), 6)
+            .with_column(
+                "flow",
+                [Some(1.0), None, Some(-5.0), None, Some(4.0), Some(-1.0)],
+            )
+            .build()
+            .unwrap();
+        let mut pipeline = Pipeline::new();
+        let limits = Limits {
+            min: Some(0.0),
+            max: None,
+        };
+        let method = OutlierMethod::Limits([("flow".to_string(), limits)].into());
+        pipeline.add_operation(Box::new(OutlierOperation::new(
+            method,
+            OutlierAction::Drop,
+            None,
+        )));
+        pipeline.add_operation(Box::new(FillNullOperation::new(FillMethod::Forward, None)));
+        pipeline.set_audit(RowAudit::new("audit").with_max_rows(1));
+
+        let (output, outputs) = pipeline.process_with_outputs(data).unwrap();
+        assert_eq!(output.len(), 4);
+        let audit = outputs.get("audit").unwrap();
+        let change: Vec<&str> = audit
+            .column("change")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let before: Vec<Option<&str>> = audit
+            .column("before")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        let after: Vec<Option<&str>> = audit
+            .column("after")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        // Two rows were dropped and two filled, one of each is sampled
+        assert_eq!(change, ["dropped", "modified"]);
+        assert_eq!(before, [Some("-5.0"), None]);
+        assert_eq!(after, [None, Some("1.0")]);
+        assert_eq!(audit.column("step").unwrap().u32().unwrap().get(1), Some(1));
+
+        let (_, context) = pipeline
+            .process_with_context(output, crate::core::ExecutionContext::new())
+            .unwrap();
+        assert_eq!(context.metrics()[0].custom["audit.dropped_rows"], 0.0);
+    }
+}
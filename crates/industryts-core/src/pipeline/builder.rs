@@ -2,12 +2,15 @@
 //!
 //! This module provides a builder pattern for constructing pipelines with a fluent API.
 
-use crate::core::Operation;
+use crate::core::{Operation, OutputSink};
+use crate::error::Result;
+use crate::pipeline::dag::{DagOperation, Stage};
 use crate::pipeline::executor::Pipeline;
 
 /// Builder for constructing pipelines with a fluent API
 pub struct PipelineBuilder {
     operations: Vec<Box<dyn Operation>>,
+    sinks: Vec<(String, Box<dyn OutputSink>)>,
 }
 
 impl PipelineBuilder {
@@ -15,6 +18,7 @@ impl PipelineBuilder {
     pub fn new() -> Self {
         Self {
             operations: Vec::new(),
+            sinks: Vec::new(),
         }
     }
 
@@ -30,12 +34,26 @@ impl PipelineBuilder {
         self
     }
 
+    /// Add a DAG of named stages as the next step (see `DagOperation`)
+    pub fn add_stages(self, stages: Vec<Stage>) -> Result<Self> {
+        Ok(self.add_operation(Box::new(DagOperation::new(stages)?)))
+    }
+
+    /// Route a named secondary output to a sink
+    pub fn add_sink(mut self, output: &str, sink: Box<dyn OutputSink>) -> Self {
+        self.sinks.push((output.to_string(), sink));
+        self
+    }
+
     /// Build the pipeline
     pub fn build(self) -> Pipeline {
         let mut pipeline = Pipeline::new();
         for operation in self.operations {
             pipeline.add_operation(operation);
         }
+        for (output, sink) in self.sinks {
+            pipeline.add_sink(&output, sink);
+        }
         pipeline
     }
 
@@ -0,0 +1,205 @@
+//! Local read cache for remote range sources
+//!
+//! Iterating on a pipeline against a slow historian API reloads the same range on
+//! every run. `CachedSource` wraps a `RangeSource` and keeps each loaded range in
+//! a local directory, keyed by a query string (the tag list, filter or API query
+//! the source stands for) and the requested `[start_ms, end_ms)`. A range loaded
+//! again is read from disk until the entry is older than the time-to-live.
+//!
+//! Entries are Arrow IPC files (`<dir>/<query hash>/<start>_<end>.arrow`) with a
+//! JSON sidecar holding the time and group columns, tags and creation time. They
+//! are written to a temporary file and renamed into place, so concurrent readers
+//! never see partial entries. Only exact ranges are reused; a range overlapping a
+//! cached one is loaded from the source.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use crate::pipeline::backfill::RangeSource;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Sidecar of a cache entry
+#[derive(Serialize, Deserialize)]
+struct EntryInfo {
+    query: String,
+    time_column: String,
+    group_columns: Vec<String>,
+    tags: HashMap<String, String>,
+    created_ms: u64,
+}
+
+/// Cache lookups of a `CachedSource`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Ranges read from the cache
+    pub hits: usize,
+    /// Ranges loaded from the source (missing or expired entries)
+    pub misses: usize,
+}
+
+/// Range source caching loaded ranges on local disk
+pub struct CachedSource<S> {
+    source: S,
+    dir: PathBuf,
+    query: String,
+    ttl: Option<Duration>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<S: RangeSource> CachedSource<S> {
+    /// Cache ranges of `source` under `dir`, keyed by `query` and the range
+    pub fn new<P: Into<PathBuf>>(source: S, dir: P, query: &str) -> Self {
+        Self {
+            source,
+            dir: dir.into(),
+            query: query.to_string(),
+            ttl: None,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reload entries older than `ttl` (default: entries never expire)
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Lookups since the source was created
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Remove every cached range of this query
+    pub fn invalidate(&self) -> Result<()> {
+        let dir = self.query_dir();
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    fn query_dir(&self) -> PathBuf {
+        let hash = blake3::hash(self.query.as_bytes()).to_hex();
+        self.dir.join(&hash.as_str()[..16])
+    }
+
+    fn entry_path(&self, start_ms: i64, end_ms: i64, extension: &str) -> PathBuf {
+        self.query_dir()
+            .join(format!("{}_{}.{}", start_ms, end_ms, extension))
+    }
+
+    /// The cached range, if present and not expired
+    fn read_entry(&self, start_ms: i64, end_ms: i64) -> Result<Option<TimeSeriesData>> {
+        let info_path = self.entry_path(start_ms, end_ms, "json");
+        let Ok(info) = std::fs::read_to_string(&info_path) else {
+            return Ok(None);
+        };
+        let info: EntryInfo = serde_json::from_str(&info)?;
+        // A hash collision would return another query's data
+        if info.query != self.query {
+            return Ok(None);
+        }
+        if let Some(ttl) = self.ttl
+            && now_ms().saturating_sub(info.created_ms) > ttl.as_millis() as u64
+        {
+            return Ok(None);
+        }
+        let file = File::open(self.entry_path(start_ms, end_ms, "arrow"))?;
+        let df = IpcReader::new(file).finish()?;
+        let mut data = TimeSeriesData::new(df, Some(&info.time_column))?
+            .with_group_columns(&info.group_columns)?;
+        data.metadata_mut().tags = info.tags;
+        Ok(Some(data))
+    }
+
+    fn write_entry(&self, start_ms: i64, end_ms: i64, data: &TimeSeriesData) -> Result<()> {
+        std::fs::create_dir_all(self.query_dir())?;
+        let info = EntryInfo {
+            query: self.query.clone(),
+            time_column: data.time_column().to_string(),
+            group_columns: data.group_columns().to_vec(),
+            tags: data.metadata().tags.clone(),
+            created_ms: now_ms(),
+        };
+        // Data first, so a sidecar always points at a complete file
+        let arrow_path = self.entry_path(start_ms, end_ms, "arrow");
+        let tmp_path = arrow_path.with_extension("arrow.tmp");
+        IpcWriter::new(File::create(&tmp_path)?).finish(&mut data.dataframe().clone())?;
+        std::fs::rename(&tmp_path, &arrow_path)?;
+        let info_path = self.entry_path(start_ms, end_ms, "json");
+        let tmp_path = info_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(&info)?)?;
+        std::fs::rename(&tmp_path, &info_path)?;
+        Ok(())
+    }
+}
+
+impl<S: RangeSource> RangeSource for CachedSource<S> {
+    fn load(&self, start_ms: i64, end_ms: i64) -> Result<TimeSeriesData> {
+        if let Some(data) = self.read_entry(start_ms, end_ms)? {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(data);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let data = self.source.load(start_ms, end_ms)?;
+        self.write_entry(start_ms, end_ms, &data)?;
+        Ok(data)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_cached_ranges_are_reused() {
+        let dir = std::env::temp_dir().join(format!("industryts_cache_{}", std::process::id()));
+        let data = Fixture::every(Duration::from_secs(60), 10)
+            .with_column("unit", ["a", "b"].repeat(5))
+            .with_column("flow", (0..10).map(f64::from).collect::<Vec<_>>())
+            .with_group_columns(&["unit"])
+            .build()
+            .unwrap();
+        let cached = CachedSource::new(data, &dir, "flow where unit in (a, b)");
+        cached.invalidate().unwrap();
+
+        let first = cached.load(0, 300_000).unwrap();
+        let again = cached.load(0, 300_000).unwrap();
+        cached.load(0, 600_000).unwrap();
+        assert_eq!(cached.stats(), CacheStats { hits: 1, misses: 2 });
+        assert!(again.dataframe().equals_missing(first.dataframe()));
+        assert_eq!(again.group_columns(), ["unit"]);
+
+        let other = CachedSource::new(
+            Fixture::every(Duration::from_secs(60), 1).build().unwrap(),
+            &dir,
+            "other",
+        )
+        .with_ttl(Duration::ZERO);
+        other.load(0, 60_000).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        other.load(0, 60_000).unwrap();
+        assert_eq!(other.stats().misses, 2);
+
+        cached.invalidate().unwrap();
+        other.invalidate().unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,240 @@
+//! Chunk-by-chunk execution of datasets larger than memory
+//!
+//! `Pipeline::process_stream` consumes an iterator of time-ordered chunks of one
+//! dataset (e.g. files of a historian export read one at a time) and yields the
+//! results incrementally, so only a chunk and the carried state are in memory.
+//! Every step runs through its `Operation::stateful` state, which carries lags,
+//! fills, running statistics and open resample buckets across chunk boundaries.
+//! Steps without a state are applied to each chunk with their warm-up of
+//! preceding rows. Secondary outputs, sinks and run limits are not used.
+//!
+//! `ChunkStream::with_sketch` also summarizes the results as they are yielded
+//! (`DatasetSketch`), so a dataset too large to hold can still be described.
+
+use crate::analysis::DatasetSketch;
+use crate::core::stateful::{concat_chunks, restore_metadata};
+use crate::core::{Carry, CarryMode, Operation, StatefulOperation, TimeSeriesData};
+use crate::error::Result;
+use crate::pipeline::Pipeline;
+
+/// Results of a pipeline run over a sequence of chunks
+///
+/// Yields one result per input chunk with output rows, followed by the rows held
+/// back until the end of the input. Iteration stops after the first error.
+pub struct ChunkStream<'a, I> {
+    pipeline: &'a Pipeline,
+    chunks: I,
+    states: Vec<Box<dyn StatefulOperation + 'a>>,
+    sketch: Option<DatasetSketch>,
+    done: bool,
+}
+
+impl Pipeline {
+    /// Process `chunks` one at a time, carrying state across chunk boundaries
+    ///
+    /// Fails if the pipeline has async steps.
+    pub fn process_stream<I>(&self, chunks: I) -> Result<ChunkStream<'_, I::IntoIter>>
+    where
+        I: IntoIterator<Item = TimeSeriesData>,
+    {
+        let states = self.sync_operations()?.into_iter().map(state_of).collect();
+        Ok(ChunkStream {
+            pipeline: self,
+            chunks: chunks.into_iter(),
+            states,
+            sketch: None,
+            done: false,
+        })
+    }
+}
+
+/// Apply the float precision and NaN policy of `pipeline` to a step output
+fn conform(pipeline: &Pipeline, data: TimeSeriesData) -> Result<TimeSeriesData> {
+    data.with_float_precision(pipeline.float_precision())?
+        .with_nan_policy(pipeline.nan_policy())
+}
+
+/// State of `operation`, or a carry of its warm-up
+pub(crate) fn state_of(operation: &dyn Operation) -> Box<dyn StatefulOperation + '_> {
+    operation.stateful().unwrap_or_else(|| {
+        let warmup = operation.warmup();
+        let mode = if warmup.is_zero() {
+            CarryMode::InputRows(0)
+        } else {
+            CarryMode::InputWindow(warmup)
+        };
+        Box::new(Carry::new(operation, mode))
+    })
+}
+
+/// Pass `chunk` through the step `states` of `pipeline`
+pub(crate) fn process_chunk(
+    pipeline: &Pipeline,
+    states: &mut [Box<dyn StatefulOperation + '_>],
+    chunk: TimeSeriesData,
+) -> Result<TimeSeriesData> {
+    let mut data = pipeline.prepare(chunk)?;
+    for (state, operation) in states.iter_mut().zip(pipeline.sync_operations()?) {
+        let groups = data.group_columns().to_vec();
+        let zone = data
+            .time_zone()
+            .filter(|_| !operation.manages_time_zone())
+            .map(str::to_string);
+        let output = restore_metadata(state.process_chunk(data)?, &groups, zone.as_deref())?;
+        data = conform(pipeline, output)?;
+    }
+    Ok(data)
+}
+
+/// Pass the rows held back by each of `states` through the steps after it
+pub(crate) fn flush_states(
+    pipeline: &Pipeline,
+    states: &mut [Box<dyn StatefulOperation + '_>],
+) -> Result<Option<TimeSeriesData>> {
+    let mut pending: Option<TimeSeriesData> = None;
+    for (state, operation) in states.iter_mut().zip(pipeline.sync_operations()?) {
+        let groups = pending
+            .as_ref()
+            .map(|data| data.group_columns().to_vec())
+            .unwrap_or_default();
+        let zone = pending
+            .as_ref()
+            .and_then(|data| data.time_zone())
+            .filter(|_| !operation.manages_time_zone())
+            .map(str::to_string);
+        let mut output = match pending.take() {
+            Some(data) => Some(state.process_chunk(data)?),
+            None => None,
+        };
+        if let Some(rest) = state.finish()? {
+            output = Some(match output {
+                Some(output) => concat_chunks(output, &rest)?,
+                None => rest,
+            });
+        }
+        pending = output
+            .map(|data| {
+                let data = restore_metadata(data, &groups, zone.as_deref())?;
+                conform(pipeline, data)
+            })
+            .transpose()?;
+    }
+    Ok(pending)
+}
+
+impl<I: Iterator<Item = TimeSeriesData>> ChunkStream<'_, I> {
+    /// Sketch the feature columns of every yielded result
+    pub fn with_sketch(mut self) -> Self {
+        self.sketch = Some(DatasetSketch::new());
+        self
+    }
+
+    /// Sketch of the results yielded so far, if enabled with `with_sketch`
+    pub fn sketch(&self) -> Option<&DatasetSketch> {
+        self.sketch.as_ref()
+    }
+
+    fn process(&mut self, chunk: TimeSeriesData) -> Result<TimeSeriesData> {
+        process_chunk(self.pipeline, &mut self.states, chunk)
+    }
+
+    /// Pass the rows held back by each step through the steps after it
+    fn flush(&mut self) -> Result<Option<TimeSeriesData>> {
+        flush_states(self.pipeline, &mut self.states)
+    }
+}
+
+impl<I: Iterator<Item = TimeSeriesData>> Iterator for ChunkStream<'_, I> {
+    type Item = Result<TimeSeriesData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let result = match self.chunks.next() {
+                Some(chunk) if chunk.is_empty() => continue,
+                Some(chunk) => self.process(chunk).map(Some),
+                None => {
+                    self.done = true;
+                    self.flush()
+                }
+            };
+            let result = match (result, self.sketch.as_mut()) {
+                (Ok(Some(data)), Some(sketch)) if !data.is_empty() => {
+                    sketch.update(&data).map(|_| Some(data))
+                }
+                (result, _) => result,
+            };
+            match result {
+                Ok(Some(data)) if !data.is_empty() => return Some(Ok(data)),
+                Ok(_) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AggMethod, FillMethod};
+    use crate::operations::{
+        DifferenceOperation, FillNullOperation, LagOperation, ResampleOperation,
+    };
+    use polars::prelude::*;
+
+    #[test]
+    fn test_process_stream_matches_whole_run() {
+        // Two interleaved pumps sampled every 30 seconds, with missing values
+        let times: Vec<i64> = (0..40).map(|i| (i / 2) * 30_000).collect();
+        let pumps: Vec<&str> = (0..40).map(|i| ["p1", "p2"][i % 2]).collect();
+        let values: Vec<Option<f64>> = (0..40)
+            .map(|i| (i % 7 != 3).then_some((i * i % 11) as f64))
+            .collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("pump".into(), pumps).into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time"))
+            .unwrap()
+            .with_group_columns(&["pump".to_string()])
+            .unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(FillNullOperation::new(FillMethod::Forward, None)));
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1, 3], None)));
+        pipeline.add_operation(Box::new(DifferenceOperation::new(
+            1,
+            Some(vec!["value".into()]),
+        )));
+        pipeline.add_operation(Box::new(
+            ResampleOperation::new("2min", AggMethod::Mean, None).unwrap(),
+        ));
+        let expected = pipeline.process(data.clone()).unwrap();
+
+        // Chunks split between timestamps; the second lies inside one bucket
+        let mut chunks = Vec::new();
+        for (offset, len) in [(0, 6), (6, 2), (8, 12), (20, 20)] {
+            let chunk = data.dataframe().slice(offset, len);
+            chunks.push(TimeSeriesData::with_metadata(chunk, data.metadata().clone()).unwrap());
+        }
+        let mut output: Option<TimeSeriesData> = None;
+        for result in pipeline.process_stream(chunks).unwrap() {
+            let result = result.unwrap();
+            output = Some(match output {
+                Some(output) => concat_chunks(output, &result).unwrap(),
+                None => result,
+            });
+        }
+        let output = output.unwrap();
+        assert_eq!(output.len(), 10);
+        assert!(output.dataframe().equals_missing(expected.dataframe()));
+    }
+}
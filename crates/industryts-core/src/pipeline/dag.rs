@@ -0,0 +1,530 @@
+//! Branching pipelines as a DAG of named stages
+//!
+//! A linear pipeline cannot compute the same features on raw and on interpolated
+//! data and then combine them. `DagOperation` runs named stages, each a chain of
+//! steps reading either the DAG's input or the outputs of the stages it depends
+//! on, and returns the output of the single stage nothing depends on:
+//!
+//! ```text
+//! input ──► raw_features ───────────────────┐
+//!   └─────► interpolated ──► interp_features ┴─► features
+//! ```
+//!
+//! Stages of the same level run in parallel. A stage with several inputs joins
+//! them on their timestamps (and group columns): the first input's columns are
+//! kept, later inputs only add columns the first lacks. Give branches computing
+//! the same features a `prefix` for the columns they add so both survive the
+//! join. `columns` routes only some feature columns into a stage.
+//!
+//! In a configuration, `[[stages]]` follow the linear `operations`, which feed
+//! the DAG:
+//!
+//! ```toml
+//! [[stages]]
+//! name = "interpolated"
+//! operations = [{ type = "fill_null", method = "time" }]
+//!
+//! [[stages]]
+//! name = "interp_features"
+//! depends_on = ["interpolated"]
+//! prefix = "interp_"
+//! operations = [{ type = "lag", periods = [1] }]
+//!
+//! [[stages]]
+//! name = "features"
+//! depends_on = ["interpolated", "interp_features"]
+//! ```
+
+use crate::config::StageConfig;
+use crate::core::{ColumnSelector, NamingPolicy, OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::{AsofStrategy, HolidayCalendar};
+use crate::pipeline::Pipeline;
+use crate::random::SeedSequence;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Named chain of steps in a `DagOperation`
+pub struct Stage {
+    name: String,
+    depends_on: Vec<String>,
+    columns: Option<ColumnSelector>,
+    prefix: Option<String>,
+    tolerance: Duration,
+    operations: Vec<Box<dyn Operation>>,
+}
+
+impl Stage {
+    /// Stage reading the DAG input, without steps
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            depends_on: Vec::new(),
+            columns: None,
+            prefix: None,
+            tolerance: Duration::ZERO,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Read the outputs of these stages instead of the DAG input
+    pub fn with_dependencies(mut self, stages: &[&str]) -> Self {
+        self.depends_on = stages.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Pass only these feature columns of the input into the stage
+    pub fn with_columns(mut self, columns: ColumnSelector) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Prefix of the columns the stage adds
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Largest time distance of rows joined from several inputs (default exact)
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Append a step
+    pub fn add_operation(mut self, operation: Box<dyn Operation>) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Build a configured stage
+    pub(crate) fn from_config(
+        config: &StageConfig,
+        calendar: Option<&Arc<HolidayCalendar>>,
+    ) -> Result<Self> {
+        let mut stage = Stage::new(&config.name);
+        stage.depends_on = config.depends_on.clone();
+        stage.columns = config.columns.clone();
+        stage.prefix = config.prefix.clone();
+        if let Some(tolerance) = &config.tolerance {
+            stage.tolerance = crate::utils::parse_duration(tolerance)?;
+        }
+        for step in &config.operations {
+            stage = stage.add_operation(Pipeline::create_step(step, calendar)?);
+        }
+        Ok(stage)
+    }
+
+    /// Restrict `data` to the routed feature columns
+    fn route(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let Some(selector) = &self.columns else {
+            return Ok(data);
+        };
+        let selected = selector.resolve(&data)?;
+        let dropped: Vec<String> = data
+            .feature_columns()
+            .iter()
+            .filter(|c| !selected.contains(c) && !data.group_columns().contains(c))
+            .cloned()
+            .collect();
+        let mut metadata = data.metadata().clone();
+        metadata.feature_columns.retain(|c| !dropped.contains(c));
+        TimeSeriesData::with_metadata(data.into_dataframe().drop_many(dropped), metadata)
+    }
+
+    /// Run the steps on `input`, prefixing the columns they add
+    fn run(&self, input: TimeSeriesData, ctx: &mut OpContext) -> Result<TimeSeriesData> {
+        let input = self.route(input)?;
+        let before: BTreeSet<String> = input.feature_columns().iter().cloned().collect();
+        let mut data = input;
+        for operation in &self.operations {
+            ctx.check_cancelled()?;
+            data = operation.execute_with_context(data, ctx)?;
+        }
+        let Some(prefix) = &self.prefix else {
+            return Ok(data);
+        };
+        let mut metadata = data.metadata().clone();
+        let mut df = data.into_dataframe();
+        for name in metadata.feature_columns.iter_mut() {
+            if !before.contains(name) {
+                let renamed = format!("{}{}", prefix, name);
+                df.rename(name, renamed.as_str().into())?;
+                *name = renamed;
+            }
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    /// Join the outputs of the stage's dependencies
+    fn combine(&self, inputs: Vec<&TimeSeriesData>) -> Result<TimeSeriesData> {
+        let mut inputs = inputs.into_iter();
+        let mut combined = inputs.next().expect("at least one input").clone();
+        for other in inputs {
+            let added: Vec<&String> = other
+                .feature_columns()
+                .iter()
+                .filter(|c| combined.dataframe().column(c).is_err())
+                .collect();
+            if added.is_empty() {
+                continue;
+            }
+            let mut keep = vec![other.time_column()];
+            keep.extend(other.group_columns().iter().map(String::as_str));
+            keep.extend(added.iter().map(|c| c.as_str()));
+            let mut part =
+                TimeSeriesData::new(other.dataframe().select(keep)?, Some(other.time_column()))?;
+            if !other.group_columns().is_empty() {
+                part = part.with_group_columns(other.group_columns())?;
+            }
+            part.metadata_mut().tags = other.metadata().tags.clone();
+            combined = combined.join_asof(&part, Some(self.tolerance), AsofStrategy::Backward)?;
+        }
+        Ok(combined)
+    }
+}
+
+/// DAG operation - run named stages with explicit dependencies
+pub struct DagOperation {
+    stages: Vec<Stage>,
+    /// Stage indices by level; a level only depends on earlier levels
+    levels: Vec<Vec<usize>>,
+    /// Index of the stage whose output is returned
+    output: usize,
+}
+
+impl DagOperation {
+    /// Check the stages and order them into levels
+    ///
+    /// Fails on duplicate names, unknown dependencies and cycles, and unless
+    /// exactly one stage is not a dependency of another.
+    pub fn new(stages: Vec<Stage>) -> Result<Self> {
+        let index = |name: &str| stages.iter().position(|s| s.name == name);
+        for (i, stage) in stages.iter().enumerate() {
+            if index(&stage.name) != Some(i) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "stage '{}' is defined twice",
+                    stage.name
+                )));
+            }
+            if let Some(missing) = stage.depends_on.iter().find(|d| index(d).is_none()) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "stage '{}' depends on unknown stage '{}'",
+                    stage.name, missing
+                )));
+            }
+        }
+
+        let finals: Vec<usize> = (0..stages.len())
+            .filter(|&i| {
+                !stages
+                    .iter()
+                    .any(|s| s.depends_on.contains(&stages[i].name))
+            })
+            .collect();
+        let output = match finals[..] {
+            [output] => output,
+            _ => {
+                let names: Vec<&str> = finals.iter().map(|&i| stages[i].name.as_str()).collect();
+                return Err(IndustrytsError::ConfigError(format!(
+                    "a pipeline DAG needs exactly one final stage, found [{}]",
+                    names.join(", ")
+                )));
+            }
+        };
+
+        let mut level_of: Vec<Option<usize>> = vec![None; stages.len()];
+        let mut levels: Vec<Vec<usize>> = Vec::new();
+        while level_of.iter().any(Option::is_none) {
+            let ready: Vec<usize> = (0..stages.len())
+                .filter(|&i| level_of[i].is_none())
+                .filter(|&i| {
+                    stages[i]
+                        .depends_on
+                        .iter()
+                        .all(|d| index(d).is_some_and(|d| level_of[d].is_some()))
+                })
+                .collect();
+            if ready.is_empty() {
+                return Err(IndustrytsError::ConfigError(
+                    "pipeline stages depend on each other in a cycle".to_string(),
+                ));
+            }
+            for &i in &ready {
+                level_of[i] = Some(levels.len());
+            }
+            levels.push(ready);
+        }
+
+        Ok(Self {
+            stages,
+            levels,
+            output,
+        })
+    }
+
+    /// Stage names grouped into levels that run in parallel
+    pub fn levels(&self) -> Vec<Vec<&str>> {
+        self.levels
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .map(|&i| self.stages[i].name.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn position(&self, name: &str) -> usize {
+        self.stages
+            .iter()
+            .position(|s| s.name == name)
+            .expect("dependencies are checked in new")
+    }
+
+    /// Feature columns after each stage, given the DAG input's
+    fn stage_columns(&self, feature_columns: &[String]) -> Vec<Vec<String>> {
+        let mut columns: Vec<Vec<String>> = vec![Vec::new(); self.stages.len()];
+        for &i in self.levels.iter().flatten() {
+            let stage = &self.stages[i];
+            let mut input: Vec<String> = match stage.depends_on.first() {
+                None => feature_columns.to_vec(),
+                Some(_) => Vec::new(),
+            };
+            for dependency in &stage.depends_on {
+                for column in &columns[self.position(dependency)] {
+                    if !input.contains(column) {
+                        input.push(column.clone());
+                    }
+                }
+            }
+            if let Some(selector) = &stage.columns {
+                let selected = selector.resolve_names(&input).unwrap_or_default();
+                input.retain(|c| selected.contains(c));
+            }
+            let mut output = input.clone();
+            for operation in &stage.operations {
+                for column in operation.added_columns(&output) {
+                    let column = match &stage.prefix {
+                        Some(prefix) if !input.contains(&column) => format!("{}{}", prefix, column),
+                        _ => column,
+                    };
+                    if !output.contains(&column) {
+                        output.push(column);
+                    }
+                }
+            }
+            columns[i] = output;
+        }
+        columns
+    }
+
+    fn operations(&self) -> impl Iterator<Item = &dyn Operation> {
+        self.stages
+            .iter()
+            .flat_map(|s| s.operations.iter().map(|op| op.as_ref()))
+    }
+}
+
+impl Operation for DagOperation {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.execute_with_context(data, &mut OpContext::new())
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        let mut outputs: Vec<Option<TimeSeriesData>> =
+            (0..self.stages.len()).map(|_| None).collect();
+        for level in &self.levels {
+            let results = {
+                let parent = &*ctx;
+                let outputs = &outputs;
+                level
+                    .par_iter()
+                    .map(|&i| {
+                        let stage = &self.stages[i];
+                        let input = if stage.depends_on.is_empty() {
+                            data.clone()
+                        } else {
+                            let inputs = stage
+                                .depends_on
+                                .iter()
+                                .map(|d| outputs[self.position(d)].as_ref().expect("earlier level"))
+                                .collect();
+                            stage.combine(inputs)?
+                        };
+                        let mut stage_ctx = parent.fork();
+                        let output = stage.run(input, &mut stage_ctx).map_err(|e| {
+                            IndustrytsError::OperationError(format!(
+                                "stage '{}': {}",
+                                stage.name, e
+                            ))
+                        })?;
+                        Ok((i, output, stage_ctx))
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            };
+            for (i, output, stage_ctx) in results {
+                outputs[i] = Some(output);
+                ctx.join(stage_ctx)?;
+            }
+        }
+        Ok(outputs[self.output].take().expect("every stage ran"))
+    }
+
+    fn name(&self) -> &str {
+        "dag"
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        let seeds = SeedSequence::new(seed);
+        for stage in &mut self.stages {
+            for (i, operation) in stage.operations.iter_mut().enumerate() {
+                let key = format!("{}:{}:{}", stage.name, i, operation.name());
+                operation.set_seed(seeds.derive(&key));
+            }
+        }
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        for stage in &mut self.stages {
+            for operation in &mut stage.operations {
+                operation.set_naming(naming);
+            }
+        }
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        let mut required = Vec::new();
+        for stage in self.stages.iter().filter(|s| s.depends_on.is_empty()) {
+            let mut added: Vec<String> = Vec::new();
+            for operation in &stage.operations {
+                for column in operation.required_columns() {
+                    if !added.contains(&column) && !required.contains(&column) {
+                        required.push(column);
+                    }
+                }
+                added.extend(operation.added_columns(&added));
+            }
+        }
+        required
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        self.stage_columns(feature_columns)
+            .swap_remove(self.output)
+            .into_iter()
+            .filter(|c| !feature_columns.contains(c))
+            .collect()
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.operations().any(|op| op.removes_rows())
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.operations().any(|op| op.reorders_rows())
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        self.operations().any(|op| op.manages_time_zone())
+    }
+
+    fn warmup(&self) -> Duration {
+        // Longest chain of warm-ups from the input to any stage
+        let mut warmups = vec![Duration::ZERO; self.stages.len()];
+        for &i in self.levels.iter().flatten() {
+            let stage = &self.stages[i];
+            let inherited = stage
+                .depends_on
+                .iter()
+                .map(|d| warmups[self.position(d)])
+                .max()
+                .unwrap_or_default();
+            warmups[i] = inherited + stage.operations.iter().map(|op| op.warmup()).sum();
+        }
+        warmups.into_iter().max().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FillMethod, PipelineConfig};
+    use crate::operations::LagOperation;
+    use crate::testing::Fixture;
+
+    #[test]
+    fn test_branches_join_into_final_stage() {
+        let data = Fixture::every(Duration::from_secs(60), 4)
+            .with_column("temp", [Some(1.0), None, Some(3.0), Some(4.0)])
+            .with_column("flow", [5.0, 6.0, 7.0, 8.0])
+            .build()
+            .unwrap();
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "branches"
+
+            [[stages]]
+            name = "raw"
+            columns = ["temp"]
+            operations = [{ type = "lag", periods = [1] }]
+
+            [[stages]]
+            name = "interpolated"
+            operations = [{ type = "fill_null", method = "linear" }]
+
+            [[stages]]
+            name = "interp_features"
+            depends_on = ["interpolated"]
+            columns = ["temp"]
+            prefix = "interp_"
+            operations = [{ type = "lag", periods = [1] }]
+
+            [[stages]]
+            name = "features"
+            depends_on = ["raw", "interp_features", "interpolated"]
+            "#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::from_config(config).unwrap();
+        let result = pipeline.process(data.clone()).unwrap();
+
+        let get = |name: &str| -> Vec<Option<f64>> {
+            let column = result.dataframe().column(name).unwrap();
+            column.f64().unwrap().into_iter().collect()
+        };
+        assert_eq!(get("temp"), [Some(1.0), None, Some(3.0), Some(4.0)]);
+        assert_eq!(get("temp_lag_1"), [None, Some(1.0), None, Some(3.0)]);
+        assert_eq!(
+            get("interp_temp_lag_1"),
+            [None, Some(1.0), Some(2.0), Some(3.0)]
+        );
+        assert_eq!(get("flow"), [Some(5.0), Some(6.0), Some(7.0), Some(8.0)]);
+        let schema = pipeline.propagate_schema(&data).unwrap();
+        assert!(schema.contains(&"interp_temp_lag_1".to_string()));
+
+        let cycle = vec![
+            Stage::new("a").with_dependencies(&["b"]),
+            Stage::new("b")
+                .with_dependencies(&["a"])
+                .add_operation(Box::new(LagOperation::new(vec![1], None))),
+        ];
+        assert!(DagOperation::new(cycle).is_err());
+        let two_finals = vec![
+            Stage::new("a"),
+            Stage::new("b").add_operation(Box::new(crate::operations::FillNullOperation::new(
+                FillMethod::Zero,
+                None,
+            ))),
+        ];
+        assert!(DagOperation::new(two_finals).is_err());
+    }
+}
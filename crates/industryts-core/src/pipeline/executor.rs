@@ -2,81 +2,2146 @@
 //!
 //! This module provides the main Pipeline struct that executes a sequence of operations.
 
-use crate::config::PipelineConfig;
-use crate::core::{ExecutionContext, Operation, TimeSeriesData};
-use crate::error::Result;
-use std::path::Path;
+use crate::config::{EmptyInput, PipelineConfig};
+use crate::core::provenance::ProvenanceStep;
+use crate::core::stateful::restore_metadata;
+use crate::core::{
+    AsyncOperation, ColumnSelector, DirectorySink, ExecutionContext, ExecutionReport, FittedParams,
+    FloatPrecision, MetricsPush, NamingPolicy, NanPolicy, OpContext, Operation, OutputSink,
+    OutputStore, SinkTransaction, StepParams, TagMetadata, TimeSeriesData,
+};
+use crate::error::{IndustrytsError, Result};
+use crate::io::SourceConfig;
+use crate::operations::HolidayCalendar;
+use crate::operations::temporal::timezone::parse_time_zone;
+use crate::pipeline::audit::RowAudit;
+use crate::pipeline::dag::{DagOperation, Stage};
+use crate::pipeline::limits::RunLimits;
+use crate::pipeline::materialize::{Materialization, RunOutput, SpilledResult};
+use crate::pipeline::missing::MissingColumnsStep;
+use crate::pipeline::selected::SelectedColumns;
+use crate::pipeline::set::DataCatalog;
+use crate::pipeline::stats::StatsRegistry;
+use crate::random::SeedSequence;
+use chrono::DateTime;
+use polars::prelude::{DataType, IntoLazy, Series};
+use rayon::prelude::*;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A single pipeline step
+enum PipelineStep {
+    Sync(Arc<dyn Operation>),
+    Async(Arc<dyn AsyncOperation>),
+}
+
+impl PipelineStep {
+    /// Get the synchronous operation, failing for async steps
+    fn as_sync(&self) -> Result<&dyn Operation> {
+        match self {
+            PipelineStep::Sync(operation) => Ok(operation.as_ref()),
+            PipelineStep::Async(operation) => Err(IndustrytsError::InvalidOperation(format!(
+                "Operation '{}' is async; use Pipeline::process_async",
+                operation.name()
+            ))),
+        }
+    }
+}
+
+/// Row contract checked between steps
+///
+/// Captures the input of a synchronous step so its output can be checked: the time
+/// and group columns must survive, a sorted time column must stay sorted unless the
+/// operation reorders rows, and the row count must fall within
+/// `Operation::row_bounds`. Group columns and the time zone are carried over to the
+/// output, the zone only for operations that do not manage it.
+struct RowContract {
+    time_column: String,
+    group_columns: Vec<String>,
+    time_zone: Option<String>,
+    rows: usize,
+    sorted: bool,
+}
+
+impl RowContract {
+    fn of(data: &TimeSeriesData) -> Result<Self> {
+        Ok(Self {
+            time_column: data.time_column().to_string(),
+            group_columns: data.group_columns().to_vec(),
+            time_zone: data.time_zone().map(str::to_string),
+            rows: data.len(),
+            sorted: is_sorted(data)?,
+        })
+    }
+
+    /// Run step `index` on `data`, check its output, audit the rows it changed and
+    /// record the provenance of the columns it changed
+    fn execute(
+        index: usize,
+        operation: &dyn Operation,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+        audit: Option<&RowAudit>,
+        provenance: Option<&ProvenanceStep>,
+    ) -> Result<TimeSeriesData> {
+        let span = tracing::info_span!(
+            "operation",
+            name = operation.name(),
+            step = index,
+            input_rows = data.len(),
+            output_rows = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        let start = Instant::now();
+        let contract = Self::of(&data)?;
+        let input = (audit.is_some() || provenance.is_some()).then(|| data.clone());
+        let output = catch_panic(index, operation.name(), || {
+            operation.execute_with_context(data, ctx)
+        })?;
+        span.record("output_rows", output.len());
+        span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+        contract.check(index, operation, &output)?;
+        let zone = contract
+            .time_zone
+            .as_deref()
+            .filter(|_| !operation.manages_time_zone());
+        let mut output = restore_metadata(output, &contract.group_columns, zone)?;
+        if let (Some(provenance), Some(input)) = (provenance, &input) {
+            provenance.record(index, operation.name(), input, &mut output)?;
+        }
+        if let (Some(audit), Some(input)) = (audit, input) {
+            audit.record(index, operation.name(), &input, &output, ctx)?;
+        }
+        Ok(output)
+    }
+
+    fn check(
+        &self,
+        index: usize,
+        operation: &dyn Operation,
+        output: &TimeSeriesData,
+    ) -> Result<()> {
+        let violation = |message: String| {
+            Err(IndustrytsError::InvalidOperation(format!(
+                "step {} ({}) broke the row contract: {}",
+                index,
+                operation.name(),
+                message
+            )))
+        };
+
+        if output.time_column() != self.time_column
+            || output.dataframe().column(&self.time_column).is_err()
+        {
+            return violation(format!("time column '{}' is missing", self.time_column));
+        }
+        if let Some(group) = self
+            .group_columns
+            .iter()
+            .find(|c| output.dataframe().column(c).is_err())
+        {
+            return violation(format!("group column '{}' is missing", group));
+        }
+        let (min, max) = operation.row_bounds(self.rows);
+        let rows = output.len();
+        if rows < min || max.is_some_and(|max| rows > max) {
+            let expected = match max {
+                Some(max) if max == min => format!("{}", min),
+                Some(max) => format!("{} to {}", min, max),
+                None => format!("at least {}", min),
+            };
+            return violation(format!(
+                "{} input rows became {}, expected {}",
+                self.rows, rows, expected
+            ));
+        }
+        if self.sorted && !operation.reorders_rows() && !is_sorted(output)? {
+            return violation(format!(
+                "time column '{}' is no longer sorted",
+                self.time_column
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Run `f` for step `index`, turning a panic into `OperationPanicked`
+///
+/// A panicking operation (e.g. a plugin with a bug) then fails the run instead of
+/// unwinding into the embedding service. Has no effect when built with
+/// `panic = "abort"`.
+fn catch_panic<T>(index: usize, operation: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        Err(IndustrytsError::OperationPanicked {
+            step: index,
+            operation: operation.to_string(),
+            message: panic_message(payload.as_ref()),
+        })
+    })
+}
+
+/// Message of a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Whether the non-null timestamps of `data` are non-decreasing
+fn is_sorted(data: &TimeSeriesData) -> Result<bool> {
+    let timestamps = data.timestamps_ms()?;
+    let mut previous = i64::MIN;
+    for timestamp in timestamps.into_iter().flatten() {
+        if timestamp < previous {
+            return Ok(false);
+        }
+        previous = timestamp;
+    }
+    Ok(true)
+}
+
+/// Tag holding the name of the pipeline that produced a dataset
+pub const RUN_TAG_PIPELINE: &str = "run.pipeline";
+/// Tag holding the hash of the pipeline configuration
+pub const RUN_TAG_CONFIG_HASH: &str = "run.config_hash";
+/// Tag holding the UTC start time of the run (RFC 3339)
+pub const RUN_TAG_TIMESTAMP: &str = "run.timestamp";
+/// Tag holding the version of this crate
+pub const RUN_TAG_VERSION: &str = "run.version";
+/// Tag holding why a run was skipped (see `Pipeline::set_empty_input`)
+pub const RUN_TAG_SKIPPED: &str = "run.skipped";
 
 /// Pipeline that chains multiple operations
 pub struct Pipeline {
-    operations: Vec<Box<dyn Operation>>,
+    operations: Vec<PipelineStep>,
     config: Option<PipelineConfig>,
+    sinks: Vec<(String, Box<dyn OutputSink>)>,
+    transactional: bool,
+    seed: Option<u64>,
+    min_warmup: Duration,
+    stamp_run_info: bool,
+    track_provenance: bool,
+    lazy: bool,
+    float_precision: FloatPrecision,
+    nan_policy: NanPolicy,
+    group_columns: Vec<String>,
+    time_zone: Option<String>,
+    limits: RunLimits,
+    audit: Option<RowAudit>,
+    tag_metadata: Option<Arc<TagMetadata>>,
+    naming: Option<NamingPolicy>,
+    metrics_push: Option<MetricsPush>,
+    materialization: Materialization,
+    spill_dir: Option<PathBuf>,
+    stats_registry: Option<StatsRegistry>,
+    empty_input: EmptyInput,
 }
 
-impl Pipeline {
-    /// Create a new empty pipeline
-    pub fn new() -> Self {
-        Self {
-            operations: Vec::new(),
-            config: None,
+impl Pipeline {
+    /// Create a new empty pipeline
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+            config: None,
+            sinks: Vec::new(),
+            transactional: false,
+            seed: None,
+            min_warmup: Duration::ZERO,
+            stamp_run_info: false,
+            track_provenance: false,
+            lazy: false,
+            float_precision: FloatPrecision::F64,
+            nan_policy: NanPolicy::Keep,
+            group_columns: Vec::new(),
+            time_zone: None,
+            limits: RunLimits::default(),
+            audit: None,
+            tag_metadata: None,
+            naming: None,
+            metrics_push: None,
+            materialization: Materialization::Memory,
+            spill_dir: None,
+            stats_registry: None,
+            empty_input: EmptyInput::Process,
+        }
+    }
+
+    /// Load pipeline from TOML configuration file
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_config(PipelineConfig::from_toml_file(path.as_ref())?)
+    }
+
+    /// Load pipeline from a TOML or JSON configuration file (chosen by extension)
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_config(PipelineConfig::from_file(path.as_ref())?)
+    }
+
+    /// Build a pipeline from a parsed configuration
+    pub fn from_config(config: PipelineConfig) -> Result<Self> {
+        let mut pipeline = Self::new();
+        pipeline.config = Some(config.clone());
+        if let Some(seed) = config.pipeline.seed {
+            pipeline.set_seed(seed);
+        }
+        pipeline.set_stamp_run_info(config.pipeline.stamp_run_info);
+        pipeline.set_track_provenance(config.pipeline.track_provenance);
+        pipeline.set_lazy(config.execution.lazy);
+        pipeline.set_transactional(config.execution.transactional);
+        pipeline.set_materialization(config.execution.materialize);
+        if let Some(dir) = &config.execution.spill_dir {
+            pipeline.set_spill_dir(dir);
+        }
+        if let Some(path) = &config.execution.stats_registry {
+            pipeline.set_stats_registry(StatsRegistry::new(path));
+        }
+        pipeline.set_empty_input(config.execution.empty_input);
+        pipeline.set_float_precision(config.execution.float);
+        pipeline.set_nan_policy(config.execution.nans);
+        pipeline.set_group_columns(config.pipeline.group_columns.clone());
+        if let Some(zone) = &config.pipeline.time_zone {
+            pipeline.set_time_zone(zone)?;
+        }
+        pipeline.set_limits(RunLimits::from_config(&config.settings)?);
+        if let Some(path) = &config.pipeline.tag_metadata {
+            pipeline.set_tag_metadata(TagMetadata::from_file(Path::new(path))?);
+        }
+        if let Some(template) = &config.pipeline.naming {
+            pipeline.set_naming(NamingPolicy::new(template)?);
+        }
+        if let Some(audit) = &config.audit {
+            pipeline.set_audit(RowAudit::from_config(audit));
+            if let Some(dir) = &audit.dir {
+                pipeline.add_sink(&audit.output, Box::new(DirectorySink::new(dir)));
+            }
+        }
+
+        let calendar = match &config.calendar {
+            Some(calendar) => Some(Arc::new(HolidayCalendar::from_config(calendar)?)),
+            None => None,
+        };
+
+        // Convert StepConfig to Operation instances
+        for step in &config.operations {
+            let operation = Self::create_step(step, calendar.as_ref())?;
+            pipeline.add_operation(operation);
+        }
+        if !config.stages.is_empty() {
+            let stages = config
+                .stages
+                .iter()
+                .map(|stage| Stage::from_config(stage, calendar.as_ref()))
+                .collect::<Result<Vec<_>>>()?;
+            pipeline.add_operation(Box::new(DagOperation::new(stages)?));
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Create a pipeline step from configuration
+    ///
+    /// Steps with a `missing_columns` policy other than `error` are wrapped in
+    /// `MissingColumnsStep`.
+    pub(crate) fn create_step(
+        config: &crate::config::StepConfig,
+        calendar: Option<&Arc<HolidayCalendar>>,
+    ) -> Result<Box<dyn Operation>> {
+        if config.missing_columns.is_error() {
+            return Self::create_operation(config, calendar);
+        }
+        Ok(Box::new(MissingColumnsStep::new(
+            config,
+            config.missing_columns,
+            calendar.cloned(),
+        )?))
+    }
+
+    /// Create an operation from configuration
+    ///
+    /// Steps whose columns are given by a pattern, regex or dtype selector are
+    /// wrapped in `SelectedColumns`, which resolves the selector on each input.
+    pub(crate) fn create_operation(
+        config: &crate::config::OperationConfig,
+        calendar: Option<&Arc<HolidayCalendar>>,
+    ) -> Result<Box<dyn Operation>> {
+        use crate::config::{OperationConfig, OutlierDetector};
+        use crate::core::OperationExt;
+        use crate::operations::data_quality::OutlierMethod;
+        use crate::operations::temporal::parse::parse_instant_ms;
+        use crate::operations::*;
+        use crate::utils::duration::parse_offset_ms;
+
+        if let Some(selector) = config.columns()
+            && selector.exact_names().is_none()
+        {
+            return Ok(Box::new(SelectedColumns::new(
+                config,
+                selector.clone(),
+                calendar.cloned(),
+            )?));
+        }
+
+        match config {
+            OperationConfig::FillNull {
+                method,
+                columns,
+                column_methods,
+                max_gap,
+            } => {
+                let mut op = FillNullOperation::new(*method, Self::column_names(columns));
+                for (column, method) in column_methods {
+                    op = op.with_column_method(column, *method);
+                }
+                if let Some(max_gap) = max_gap {
+                    op = op.with_max_gap(crate::utils::parse_duration(max_gap)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::ReplaceSentinels {
+                values,
+                columns,
+                keep_strings,
+            } => {
+                let mut op = ReplaceSentinelsOperation::new(values.clone())
+                    .with_cast_numeric(!*keep_strings);
+                for (column, sentinels) in columns {
+                    op = op.with_column(column, sentinels.clone());
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::NormalizeNans { policy, columns } => Ok(Box::new(
+                NormalizeNansOperation::new(*policy, Self::column_names(columns)),
+            )),
+            OperationConfig::Deduplicate {
+                keep,
+                aggregation,
+                keys,
+            } => {
+                let mut op = DeduplicateOperation::new(*keep).with_keys(keys.clone());
+                if let Some(aggregation) = aggregation {
+                    op = op.with_aggregation(*aggregation);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Deadband {
+                tolerance,
+                tolerances,
+                max_interval,
+                columns,
+            } => {
+                let mut op = DeadbandOperation::new(*tolerance, Self::column_names(columns))?;
+                for (column, tolerance) in tolerances {
+                    op = op.with_column_tolerance(column, *tolerance)?;
+                }
+                if let Some(interval) = max_interval {
+                    op = op.with_max_interval(crate::utils::parse_duration(interval)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::SortByTime => Ok(Box::new(SortByTimeOperation::new())),
+            OperationConfig::Outlier {
+                method,
+                threshold,
+                window,
+                limits,
+                action,
+                columns,
+            } => {
+                let method = match method {
+                    OutlierDetector::ZScore => OutlierMethod::ZScore {
+                        threshold: threshold.unwrap_or(3.0),
+                    },
+                    OutlierDetector::Iqr => OutlierMethod::Iqr {
+                        factor: threshold.unwrap_or(1.5),
+                    },
+                    OutlierDetector::RollingMad => OutlierMethod::RollingMad {
+                        window: crate::utils::parse_duration(window.as_deref().ok_or_else(
+                            || {
+                                IndustrytsError::ConfigError(
+                                    "rolling_mad outlier detection requires a window".to_string(),
+                                )
+                            },
+                        )?)?,
+                        threshold: threshold.unwrap_or(3.5),
+                    },
+                    OutlierDetector::Limits => OutlierMethod::Limits(limits.clone()),
+                };
+                Ok(Box::new(OutlierOperation::new(
+                    method,
+                    *action,
+                    Self::column_names(columns),
+                )))
+            }
+            OperationConfig::ApplyFlags {
+                action,
+                keep_flags,
+                columns,
+            } => Ok(Box::new(
+                ApplyFlagsOperation::new(*action, Self::column_names(columns))
+                    .with_keep_flags(*keep_flags),
+            )),
+            OperationConfig::Resample {
+                rule,
+                aggregation,
+                columns,
+                offset,
+                label,
+                closed,
+                state_aggregation,
+                empty_buckets,
+                weights,
+                min_coverage,
+                sample_interval,
+            } => {
+                let mut op =
+                    ResampleOperation::new(rule, *aggregation, Self::column_names(columns))?
+                        .with_label(*label)
+                        .with_closed(*closed)
+                        .with_state_aggregation(*state_aggregation)
+                        .with_empty_buckets(*empty_buckets);
+                if let Some(offset) = offset {
+                    op = op.with_offset(offset)?;
+                }
+                for (column, weight) in weights {
+                    op = op.with_weight(column, weight)?;
+                }
+                if let Some(min_coverage) = min_coverage {
+                    let interval = match sample_interval {
+                        Some(interval) => Some(crate::utils::parse_duration(interval)?),
+                        None => None,
+                    };
+                    op = op.with_min_coverage(*min_coverage, interval)?;
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Regularize {
+                interval,
+                tolerance,
+                insert_missing,
+                gap_output,
+            } => {
+                let interval = match interval {
+                    Some(interval) => Some(crate::utils::parse_duration(interval)?),
+                    None => None,
+                };
+                let mut op =
+                    RegularizeOperation::new(interval).with_insert_missing(*insert_missing);
+                if let Some(tolerance) = tolerance {
+                    op = op.with_tolerance(crate::utils::parse_duration(tolerance)?);
+                }
+                if let Some(output) = gap_output {
+                    op = op.with_gap_output(output);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Reconstruct {
+                every,
+                interpolation,
+                max_gap,
+                columns,
+            } => {
+                let mut op = ReconstructOperation::new(
+                    crate::utils::parse_duration(every)?,
+                    Self::column_names(columns),
+                )?
+                .with_interpolation(*interpolation);
+                if let Some(max_gap) = max_gap {
+                    op = op.with_max_gap(crate::utils::parse_duration(max_gap)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Downsample {
+                method,
+                points,
+                bucket,
+                columns,
+            } => {
+                let target = match (points, bucket) {
+                    (Some(points), None) => DownsampleTarget::Points(*points),
+                    (None, Some(bucket)) => {
+                        DownsampleTarget::Bucket(crate::utils::parse_duration(bucket)?)
+                    }
+                    _ => {
+                        return Err(IndustrytsError::ConfigError(
+                            "downsample needs either points or bucket".to_string(),
+                        ));
+                    }
+                };
+                Ok(Box::new(DownsampleOperation::new(
+                    *method,
+                    target,
+                    Self::column_names(columns),
+                )?))
+            }
+            OperationConfig::Lag {
+                periods,
+                columns,
+                partition_by,
+            } => Ok(Box::new(
+                LagOperation::new(periods.clone(), Self::column_names(columns))
+                    .with_partition_by(partition_by.clone()),
+            )),
+            OperationConfig::Difference {
+                lag,
+                columns,
+                partition_by,
+                on_overflow,
+            } => {
+                let mut op = DifferenceOperation::new(*lag, Self::column_names(columns))
+                    .with_partition_by(partition_by.clone());
+                if let Some(policy) = on_overflow {
+                    op = op.with_overflow_policy(*policy);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::TrendSlope {
+                window,
+                slope_per,
+                min_periods,
+                columns,
+                partition_by,
+            } => {
+                let mut op = TrendSlopeOperation::new(
+                    crate::utils::parse_duration(window)?,
+                    Self::column_names(columns),
+                )
+                .with_partition_by(partition_by.clone());
+                if let Some(slope_per) = slope_per {
+                    op = op.with_slope_per(crate::utils::parse_duration(slope_per)?);
+                }
+                if let Some(min_periods) = min_periods {
+                    op = op.with_min_periods(*min_periods);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::RollingFeatures {
+                windows,
+                stats,
+                quantiles,
+                columns,
+                column_windows,
+                name_template,
+                min_periods,
+                partition_by,
+            } => {
+                let parse_windows = |windows: &[String]| -> Result<Vec<RollingWindow>> {
+                    windows.iter().map(|w| RollingWindow::parse(w)).collect()
+                };
+                let mut stats = stats
+                    .iter()
+                    .map(|s| RollingStat::parse(s))
+                    .collect::<Result<Vec<_>>>()?;
+                stats.extend(quantiles.iter().map(|q| RollingStat::Quantile(*q)));
+                let mut op = RollingFeaturesOperation::new(
+                    parse_windows(windows)?,
+                    stats,
+                    Self::column_names(columns),
+                )
+                .with_partition_by(partition_by.clone());
+                for (column, windows) in column_windows {
+                    op = op.with_column_windows(column, parse_windows(windows)?);
+                }
+                if let Some(template) = name_template {
+                    op = op.with_name_template(template);
+                }
+                if let Some(min_periods) = min_periods {
+                    op = op.with_min_periods(*min_periods);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Derive { exprs, on_overflow } => {
+                let op = DeriveOperation::new(exprs)?.with_policy(*on_overflow);
+                Ok(Box::new(op))
+            }
+            OperationConfig::FleetAggregate {
+                name,
+                stats,
+                level,
+                assets,
+                columns,
+            } => {
+                let mut op =
+                    FleetAggregateOperation::new(name, stats.clone(), Self::column_names(columns))?
+                        .with_level(*level);
+                for (column, path) in assets {
+                    op = op.with_asset(column, path);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::EwCorrelation {
+                pairs,
+                half_life,
+                min_periods,
+            } => {
+                let pairs = pairs.iter().map(|[a, b]| (a.clone(), b.clone())).collect();
+                let mut op =
+                    EwCorrelationOperation::new(pairs, crate::utils::parse_duration(half_life)?);
+                if let Some(min_periods) = min_periods {
+                    op = op.with_min_periods(*min_periods);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Cusum {
+                k,
+                h,
+                target,
+                sigma,
+                reference_window,
+                columns,
+                explanations,
+                context_rows,
+            } => {
+                let mut op = CusumOperation::new(*k, *h, Self::column_names(columns));
+                match (target, sigma) {
+                    (Some(target), Some(sigma)) => op = op.with_target(*target, *sigma),
+                    (None, None) => {}
+                    _ => {
+                        return Err(IndustrytsError::ConfigError(
+                            "cusum target and sigma must be given together".to_string(),
+                        ));
+                    }
+                }
+                if let Some(rows) = reference_window {
+                    op = op.with_reference_window(*rows);
+                }
+                if let Some(output) = explanations {
+                    op = op.with_explanations(output, *context_rows);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Ewma {
+                lambda,
+                width,
+                target,
+                sigma,
+                references,
+                reference_window,
+                columns,
+            } => {
+                let mut op = EwmaOperation::new(*lambda, *width, Self::column_names(columns))?;
+                match (target, sigma) {
+                    (Some(target), Some(sigma)) => op = op.with_target(*target, *sigma),
+                    (None, None) => {}
+                    _ => {
+                        return Err(IndustrytsError::ConfigError(
+                            "ewma target and sigma must be given together".to_string(),
+                        ));
+                    }
+                }
+                for (column, reference) in references {
+                    op = op.with_column_reference(column, *reference);
+                }
+                if let Some(rows) = reference_window {
+                    op = op.with_reference_window(*rows);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::RateOfChange {
+                max_rate,
+                per,
+                limits,
+                columns,
+            } => {
+                let per = match per {
+                    Some(per) => Some(crate::utils::parse_duration(per)?),
+                    None => None,
+                };
+                let mut op =
+                    RateOfChangeOperation::new(*max_rate, per, Self::column_names(columns));
+                for (column, limit) in limits {
+                    op = op.with_column_limit(column, *limit);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Flatline {
+                window,
+                tolerance,
+                column_tolerances,
+                columns,
+            } => {
+                let mut op = FlatlineOperation::new(
+                    crate::utils::parse_duration(window)?,
+                    Self::column_names(columns),
+                )
+                .with_tolerance(*tolerance);
+                for (column, tolerance) in column_tolerances {
+                    op = op.with_column_tolerance(column, *tolerance);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::QuantileBand {
+                window,
+                lower,
+                upper,
+                min_periods,
+                columns,
+            } => {
+                let mut op = QuantileBandOperation::new(
+                    crate::utils::parse_duration(window)?,
+                    *lower,
+                    *upper,
+                    Self::column_names(columns),
+                )?;
+                if let Some(min_periods) = min_periods {
+                    op = op.with_min_periods(*min_periods);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::SeasonalBaseline {
+                period,
+                bucket,
+                time_zone,
+                min_samples,
+                columns,
+                explanations,
+                explain_threshold,
+                context_rows,
+            } => {
+                let mut op = SeasonalBaselineOperation::new(
+                    *period,
+                    crate::utils::parse_duration(bucket)?,
+                    Self::column_names(columns),
+                )?
+                .with_time_zone(time_zone)?;
+                if let Some(min_samples) = min_samples {
+                    op = op.with_min_samples(*min_samples);
+                }
+                if let Some(output) = explanations {
+                    op = op.with_explanations(output, *explain_threshold, *context_rows);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Standardize {
+                columns,
+                method,
+                clip_quantiles,
+                reference_window,
+            } => {
+                let mut op =
+                    StandardizeOperation::new(Self::column_names(columns)).with_method(*method);
+                if let Some([lower, upper]) = clip_quantiles {
+                    op = op.with_clip_quantiles(*lower, *upper)?;
+                }
+                if let Some(rows) = reference_window {
+                    op = op.with_reference_window(*rows);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::FeatureSelection {
+                columns,
+                max_null_ratio,
+                max_dominant_ratio,
+                max_correlation,
+                report,
+            } => {
+                let mut op = FeatureSelectionOperation::new(Self::column_names(columns))
+                    .with_max_null_ratio(*max_null_ratio)?
+                    .with_max_dominant_ratio(*max_dominant_ratio)?
+                    .with_max_correlation(*max_correlation)?;
+                if let Some(name) = report {
+                    op = op.with_report(name);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::OptimizeDtypes {
+                columns,
+                max_category_ratio,
+                keep_f64,
+            } => Ok(Box::new(
+                OptimizeDtypesOperation::new(Self::column_names(columns))
+                    .with_max_category_ratio(*max_category_ratio)
+                    .with_float_downcast(!*keep_f64),
+            )),
+            OperationConfig::Quantize {
+                significant_digits,
+                resolution,
+                downcast,
+                columns,
+            } => {
+                let quantization = match (significant_digits, resolution) {
+                    (Some(digits), None) => Quantization::SignificantDigits(*digits),
+                    (None, Some(step)) => Quantization::Resolution(*step),
+                    _ => {
+                        return Err(IndustrytsError::ConfigError(
+                            "quantize needs exactly one of significant_digits and resolution"
+                                .to_string(),
+                        ));
+                    }
+                };
+                Ok(Box::new(
+                    QuantizeOperation::new(quantization, Self::column_names(columns))?
+                        .with_downcast(*downcast),
+                ))
+            }
+            OperationConfig::Validate {
+                rules,
+                warn_only,
+                policy,
+            } => {
+                let op = ValidateOperation::new(rules.clone()).with_warn_only(*warn_only);
+                Ok(Box::new(match policy {
+                    Some(policy) => op.with_policy(*policy),
+                    None => op,
+                }))
+            }
+            OperationConfig::Expect { suite, warn_only } => Ok(Box::new(
+                ExpectationOperation::from_file(Path::new(suite))?.with_warn_only(*warn_only),
+            )),
+            OperationConfig::EventLabel {
+                events,
+                start_column,
+                end_column,
+                asset_column,
+                pre_horizon,
+                window,
+                output,
+            } => {
+                let frames = EventFrames::load(
+                    Path::new(events),
+                    start_column,
+                    end_column,
+                    asset_column.as_deref(),
+                )?;
+                let mut op =
+                    EventLabelOperation::new(frames, crate::utils::parse_duration(pre_horizon)?);
+                if let Some(window) = window {
+                    op = op.with_window(crate::utils::parse_duration(window)?);
+                }
+                if let Some(column) = asset_column {
+                    op = op.with_asset_column(column);
+                }
+                if let Some(output) = output {
+                    op = op.with_output(output);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::EventWindowStats {
+                events,
+                start_column,
+                end_column,
+                asset_column,
+                before,
+                after,
+                during,
+                stats,
+                columns,
+            } => {
+                let frames = EventFrames::load(
+                    Path::new(events),
+                    start_column,
+                    end_column,
+                    asset_column.as_deref(),
+                )?;
+                let mut op = EventWindowStatsOperation::new(
+                    frames,
+                    stats.clone(),
+                    Self::column_names(columns),
+                )
+                .with_during(*during);
+                if let Some(before) = before {
+                    op = op.with_before(crate::utils::parse_duration(before)?);
+                }
+                if let Some(after) = after {
+                    op = op.with_after(crate::utils::parse_duration(after)?);
+                }
+                if let Some(column) = asset_column {
+                    op = op.with_asset_column(column);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::CleanText {
+                trim,
+                case,
+                replace,
+                columns,
+            } => {
+                let mut op = CleanTextOperation::new(Self::column_names(columns)).with_trim(*trim);
+                if let Some(case) = case {
+                    op = op.with_case(*case);
+                }
+                for r in replace {
+                    op = op.with_replacement(&r.pattern, &r.replacement)?;
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::ConvertUnits { unit, columns } => Ok(Box::new(
+                ConvertUnitsOperation::new(unit, Self::column_names(columns))?,
+            )),
+            OperationConfig::ExtractPattern {
+                column,
+                pattern,
+                group,
+                output,
+            } => {
+                let mut op = ExtractPatternOperation::new(column, pattern)?;
+                if let Some(group) = group {
+                    op = op.with_group(*group)?;
+                }
+                if let Some(output) = output {
+                    op = op.with_output(output);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Merge {
+                source,
+                input,
+                how,
+                tolerance,
+                strategy,
+                schema,
+            } => {
+                let op = match (source, input) {
+                    (Some(source), None) => MergeOperation::from_source(source.clone(), *how),
+                    (None, Some(input)) => MergeOperation::from_catalog(input, *how),
+                    _ => {
+                        return Err(IndustrytsError::ConfigError(
+                            "merge needs either source or input".to_string(),
+                        ));
+                    }
+                };
+                let mut op = op
+                    .with_strategy(*strategy)
+                    .with_schema_reconciliation(*schema);
+                if let Some(tolerance) = tolerance {
+                    op = op.with_tolerance(crate::utils::parse_duration(tolerance)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::MapColumns {
+                mapping,
+                strict,
+                drop_unmapped,
+            } => Ok(Box::new(
+                MapColumnsOperation::from_file(Path::new(mapping))?
+                    .with_strict(*strict)
+                    .with_drop_unmapped(*drop_unmapped),
+            )),
+            OperationConfig::ParseTimestamp {
+                column,
+                formats,
+                lenient,
+                dead_letter,
+            } => {
+                let mut op = ParseTimestampOperation::new(column.clone(), formats.clone())
+                    .with_strict(!*lenient);
+                if let Some(output) = dead_letter {
+                    op = op.with_dead_letter(output);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::ConvertTimezone { from, to } => {
+                Ok(Box::new(ConvertTimezoneOperation::new(from, to)?))
+            }
+            OperationConfig::ClockSkew {
+                offset,
+                drift_ppm,
+                reference_time,
+            } => {
+                let op = ClockSkewOperation::new(parse_offset_ms(offset)?);
+                match (drift_ppm, reference_time) {
+                    (None, _) => Ok(Box::new(op)),
+                    (Some(drift), Some(time)) => {
+                        Ok(Box::new(op.with_drift(*drift, parse_instant_ms(time)?)))
+                    }
+                    (Some(_), None) => Err(IndustrytsError::ConfigError(
+                        "clock_skew with drift_ppm needs a reference_time".to_string(),
+                    )),
+                }
+            }
+            OperationConfig::CalendarBucket {
+                time_zone,
+                aggregation,
+                day_start,
+                shifts_per_day,
+                columns,
+                working_days_only,
+                state_aggregation,
+                weights,
+            } => {
+                let mut op = CalendarBucketOperation::new(
+                    time_zone,
+                    *aggregation,
+                    Self::column_names(columns),
+                )?
+                .with_state_aggregation(*state_aggregation);
+                if let Some(day_start) = day_start {
+                    op = op.with_day_start(day_start)?;
+                }
+                if let Some(shifts) = shifts_per_day {
+                    op = op.with_shifts_per_day(*shifts)?;
+                }
+                if *working_days_only {
+                    op = op.with_holidays(Self::require_calendar(calendar)?);
+                }
+                for (column, weight) in weights {
+                    op = op.with_weight(column, weight)?;
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::StateDwellTime {
+                column,
+                every,
+                states,
+                max_hold,
+            } => {
+                let mut op =
+                    StateDwellTimeOperation::new(column, crate::utils::parse_duration(every)?)?;
+                if let Some(states) = states {
+                    op = op.with_states(states.clone());
+                }
+                if let Some(max_hold) = max_hold {
+                    op = op.with_max_hold(crate::utils::parse_duration(max_hold)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::CalendarFilter { time_zone, keep } => Ok(Box::new(
+                CalendarFilterOperation::new(Self::require_calendar(calendar)?, time_zone)?
+                    .with_keep(*keep),
+            )),
+            OperationConfig::QualityFilter {
+                scheme,
+                suffix,
+                min_quality,
+                columns,
+            } => {
+                let mut op = QualityFilterOperation::new(*scheme);
+                if let Some(suffix) = suffix {
+                    op = op.with_suffix(suffix);
+                }
+                if let Some(min_quality) = min_quality {
+                    op = op.with_min_quality(*min_quality);
+                }
+                if let Some(columns) = Self::column_names(columns) {
+                    op = op.with_columns(columns);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Anonymize {
+                key_file,
+                columns,
+                alias_prefix,
+                max_time_shift,
+                jitter,
+            } => {
+                let mut op = AnonymizeOperation::from_key_file(Path::new(key_file))?;
+                if let Some(columns) = Self::column_names(columns) {
+                    op = op.with_columns(columns);
+                }
+                if let Some(prefix) = alias_prefix {
+                    op = op.with_alias_prefix(prefix);
+                }
+                if let Some(max_time_shift) = max_time_shift {
+                    op = op.with_max_time_shift(crate::utils::parse_duration(max_time_shift)?);
+                }
+                if let Some(jitter) = jitter {
+                    op = op.with_jitter(crate::utils::parse_duration(jitter)?);
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::PrivacyNoise {
+                epsilon,
+                sensitivity,
+                mechanism,
+                delta,
+                min,
+                max,
+                columns,
+            } => {
+                let mut op = PrivacyNoiseOperation::new(
+                    *epsilon,
+                    *sensitivity,
+                    Self::column_names(columns),
+                )?
+                .with_bounds(*min, *max);
+                match (mechanism, delta) {
+                    (NoiseMechanism::Laplace, None) => {}
+                    (NoiseMechanism::Gaussian, Some(delta)) => op = op.with_gaussian(*delta)?,
+                    (NoiseMechanism::Laplace, Some(_)) => {
+                        return Err(IndustrytsError::ConfigError(
+                            "delta applies to gaussian privacy noise only".to_string(),
+                        ));
+                    }
+                    (NoiseMechanism::Gaussian, None) => {
+                        return Err(IndustrytsError::ConfigError(
+                            "gaussian privacy noise requires delta".to_string(),
+                        ));
+                    }
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::PerGroup {
+                id_column,
+                operations,
+            } => {
+                let mut steps = operations
+                    .iter()
+                    .map(|step| Self::create_operation(step, calendar));
+                let first = steps.next().ok_or_else(|| {
+                    IndustrytsError::ConfigError(
+                        "per_group requires at least one operation".to_string(),
+                    )
+                })??;
+                let inner = steps.try_fold(first, |acc, step| {
+                    step.map(|step| Box::new(acc.then(step)) as Box<dyn Operation>)
+                })?;
+                Ok(Box::new(PerGroupOperation::new(id_column, inner)))
+            }
+            OperationConfig::Recipe { recipes } => {
+                let mut op = RecipeOperation::new();
+                for recipe in recipes {
+                    if recipe.operations.is_empty() {
+                        return Err(IndustrytsError::ConfigError(
+                            "recipe requires at least one operation".to_string(),
+                        ));
+                    }
+                    let steps = recipe.operations.clone();
+                    let calendar = calendar.cloned();
+                    op = op.with_recipe(recipe.columns.clone(), move |columns| {
+                        let selector = ColumnSelector::Names(columns);
+                        let mut built = steps.iter().map(|step| {
+                            let mut step = step.clone();
+                            step.set_columns(Some(selector.clone()));
+                            Self::create_operation(&step, calendar.as_ref())
+                        });
+                        let first = built.next().expect("recipe steps checked above")?;
+                        built.try_fold(first, |acc, step| {
+                            step.map(|step| Box::new(acc.then(step)) as Box<dyn Operation>)
+                        })
+                    });
+                }
+                Ok(Box::new(op))
+            }
+            OperationConfig::Custom { name, params } => {
+                crate::pipeline::OperationRegistry::create_global(name, params)
+            }
+        }
+    }
+
+    /// Exact column names of a step; selectors were resolved by `SelectedColumns`
+    fn column_names(columns: &Option<ColumnSelector>) -> Option<Vec<String>> {
+        columns
+            .as_ref()
+            .and_then(|selector| selector.exact_names())
+            .map(<[String]>::to_vec)
+    }
+
+    /// Shared pipeline calendar, required by calendar-aware operations
+    fn require_calendar(calendar: Option<&Arc<HolidayCalendar>>) -> Result<Arc<HolidayCalendar>> {
+        calendar.cloned().ok_or_else(|| {
+            IndustrytsError::ConfigError(
+                "Operation requires a [calendar] section in the pipeline config".to_string(),
+            )
+        })
+    }
+
+    /// Add an operation to the pipeline
+    pub fn add_operation(&mut self, mut operation: Box<dyn Operation>) {
+        if let Some(seed) = self.seed {
+            let step_seed = Self::step_seed(seed, self.operations.len(), operation.name());
+            operation.set_seed(step_seed);
+        }
+        if let Some(naming) = &self.naming {
+            operation.set_naming(naming);
+        }
+        self.operations
+            .push(PipelineStep::Sync(Arc::from(operation)));
+    }
+
+    /// Seed all stochastic operations for reproducible runs
+    ///
+    /// Each step receives a sub-seed derived from `seed`, its position and its name;
+    /// operations added later are seeded the same way.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        for (index, step) in self.operations.iter_mut().enumerate() {
+            if let PipelineStep::Sync(operation) = step {
+                let step_seed = Self::step_seed(seed, index, operation.name());
+                if let Some(operation) = Arc::get_mut(operation) {
+                    operation.set_seed(step_seed);
+                }
+            }
+        }
+    }
+
+    /// Pipeline seed, if set
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Name the columns generated by all steps after `naming`
+    ///
+    /// Operations added later follow the same policy.
+    pub fn set_naming(&mut self, naming: NamingPolicy) {
+        for step in &mut self.operations {
+            if let PipelineStep::Sync(operation) = step
+                && let Some(operation) = Arc::get_mut(operation)
+            {
+                operation.set_naming(&naming);
+            }
+        }
+        self.naming = Some(naming);
+    }
+
+    /// Naming policy of generated columns, if set
+    pub fn naming(&self) -> Option<&NamingPolicy> {
+        self.naming.as_ref()
+    }
+
+    /// Learn the parameters of fittable steps (scalers, imputers) from training data
+    ///
+    /// Steps are fitted in order, each on the output of the fitted steps before
+    /// it, and the transformed training data is returned. Afterwards `process`
+    /// and `transform` apply the learned parameters unchanged. Fails for async
+    /// steps.
+    pub fn fit(&mut self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut data = self.prepare(data)?;
+        self.check(&data)?;
+        let mut ctx = OpContext::new();
+        for index in 0..self.operations.len() {
+            self.operations[index].as_sync()?;
+            if let PipelineStep::Sync(operation) = &mut self.operations[index]
+                && operation.fittable().is_some()
+            {
+                let name = operation.name().to_string();
+                let fittable = Arc::get_mut(operation)
+                    .and_then(|operation| operation.fittable_mut())
+                    .ok_or_else(|| {
+                        IndustrytsError::InvalidOperation(format!(
+                            "step {} ({}) is shared and cannot be fitted",
+                            index, name
+                        ))
+                    })?;
+                catch_panic(index, &name, || fittable.fit(&data))?;
+            }
+            let operation = self.operations[index].as_sync()?;
+            let provenance = self.provenance_step(index);
+            data = RowContract::execute(
+                index,
+                operation,
+                data,
+                &mut ctx,
+                self.audit.as_ref(),
+                provenance.as_ref(),
+            )?
+            .with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)?;
+        }
+        Ok(data)
+    }
+
+    /// Execute the pipeline with fitted parameters
+    ///
+    /// Like `process`, but fails if a fittable step has not been fitted or loaded,
+    /// so production data is never scaled with its own statistics.
+    pub fn transform(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        for (index, step) in self.operations.iter().enumerate() {
+            if let PipelineStep::Sync(operation) = step
+                && let Some(fittable) = operation.fittable()
+                && !fittable.is_fitted()
+            {
+                return Err(IndustrytsError::InvalidOperation(format!(
+                    "step {} ({}) is not fitted; call Pipeline::fit or load fitted parameters",
+                    index,
+                    operation.name()
+                )));
+            }
+        }
+        self.process(data)
+    }
+
+    /// Parameters of the fitted steps, e.g. to save with `FittedParams::save`
+    pub fn fitted_params(&self) -> FittedParams {
+        let steps = self
+            .operations
+            .iter()
+            .enumerate()
+            .filter_map(|(step, operation)| match operation {
+                PipelineStep::Sync(operation) => Some(StepParams {
+                    step,
+                    operation: operation.name().to_string(),
+                    params: operation.fittable()?.params()?,
+                }),
+                PipelineStep::Async(_) => None,
+            })
+            .collect();
+        FittedParams { steps }
+    }
+
+    /// Load parameters from `fitted_params` of a pipeline with the same steps
+    pub fn load_fitted_params(&mut self, params: &FittedParams) -> Result<()> {
+        for saved in &params.steps {
+            let mismatch = || {
+                IndustrytsError::ConfigError(format!(
+                    "fitted parameters of step {} ({}) do not match the pipeline",
+                    saved.step, saved.operation
+                ))
+            };
+            let Some(PipelineStep::Sync(operation)) = self.operations.get_mut(saved.step) else {
+                return Err(mismatch());
+            };
+            if operation.name() != saved.operation {
+                return Err(mismatch());
+            }
+            let fittable = Arc::get_mut(operation)
+                .and_then(|operation| operation.fittable_mut())
+                .ok_or_else(mismatch)?;
+            fittable.load_params(saved.params.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Stamp outputs with run information
+    ///
+    /// When enabled, every run adds the `run.*` tags (pipeline name, config hash,
+    /// run timestamp, crate version) to its output, so a dataset records how it was
+    /// produced. Name and config hash are only known for pipelines loaded from a
+    /// configuration.
+    pub fn set_stamp_run_info(&mut self, enabled: bool) {
+        self.stamp_run_info = enabled;
+    }
+
+    /// Record which steps created or modified each column
+    ///
+    /// See `TimeSeriesData::column_provenance`. Comparing each step's input and
+    /// output columns costs a pass over the data per step.
+    pub fn set_track_provenance(&mut self, enabled: bool) {
+        self.track_provenance = enabled;
+    }
+
+    /// Provenance recording of step `index`, if tracked
+    fn provenance_step(&self, index: usize) -> Option<ProvenanceStep> {
+        if !self.track_provenance {
+            return None;
+        }
+        // Steps built from the configuration come first, in order
+        let params = self
+            .config
+            .as_ref()
+            .and_then(|config| config.operations.get(index))
+            .and_then(|step| serde_json::to_value(step).ok());
+        Some(ProvenanceStep { params })
+    }
+
+    /// Hash of the pipeline configuration, if it was loaded from one
+    pub fn config_hash(&self) -> Result<Option<String>> {
+        match &self.config {
+            Some(config) => {
+                let toml = config.to_toml_string()?;
+                Ok(Some(blake3::hash(toml.as_bytes()).to_hex().to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Add the `run.*` tags to `data` if enabled
+    fn stamp(&self, data: &mut TimeSeriesData, started: SystemTime) -> Result<()> {
+        if !self.stamp_run_info {
+            return Ok(());
+        }
+        if let Some(config) = &self.config {
+            data.add_tag(RUN_TAG_PIPELINE.to_string(), config.pipeline.name.clone());
+        }
+        if let Some(hash) = self.config_hash()? {
+            data.add_tag(RUN_TAG_CONFIG_HASH.to_string(), hash);
+        }
+        let started_ms = started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        if let Some(started) = DateTime::from_timestamp_millis(started_ms) {
+            data.add_tag(
+                RUN_TAG_TIMESTAMP.to_string(),
+                started.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            );
+        }
+        data.add_tag(
+            RUN_TAG_VERSION.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        Ok(())
+    }
+
+    /// Set the minimum warm-up used when backfilling
+    ///
+    /// Needed for operations whose lookback is counted in rows (lags, row windows)
+    /// and therefore not reported by `Operation::warmup`.
+    pub fn set_warmup(&mut self, warmup: Duration) {
+        self.min_warmup = warmup;
+    }
+
+    /// History needed before the first exact output row
+    ///
+    /// The sum of the steps' warm-ups (chained windows add up), but at least the
+    /// value given to `set_warmup`.
+    pub fn warmup(&self) -> Duration {
+        let steps: Duration = self
+            .operations
+            .iter()
+            .filter_map(|step| match step {
+                PipelineStep::Sync(operation) => Some(operation.warmup()),
+                PipelineStep::Async(_) => None,
+            })
+            .sum();
+        steps.max(self.min_warmup)
+    }
+
+    /// Sub-seed for the step at `index`
+    fn step_seed(seed: u64, index: usize, name: &str) -> u64 {
+        SeedSequence::new(seed).derive(&format!("{}:{}", index, name))
+    }
+
+    /// Add an async operation to the pipeline
+    ///
+    /// Pipelines containing async operations must be run with `process_async`.
+    pub fn add_async_operation(&mut self, operation: Box<dyn AsyncOperation>) {
+        self.operations
+            .push(PipelineStep::Async(Arc::from(operation)));
+    }
+
+    /// Route a named secondary output to a sink
+    ///
+    /// Sinks are written after all operations have run. Several sinks may be
+    /// registered for the same output.
+    pub fn add_sink(&mut self, output: &str, sink: Box<dyn OutputSink>) {
+        self.sinks.push((output.to_string(), sink));
+    }
+
+    /// Write all sinks as one unit of work
+    ///
+    /// Each sink stages its output and the outputs are committed only once every
+    /// step has run and every sink has staged, including the `[sink]` file of
+    /// `run`. Otherwise sinks are written directly and a failing sink may leave
+    /// others written.
+    pub fn set_transactional(&mut self, transactional: bool) {
+        self.transactional = transactional;
+    }
+
+    /// Push interim metrics during runs, e.g. every few chunks of a backfill
+    ///
+    /// Attached to the contexts the pipeline creates itself (`backfill`,
+    /// `process_with_report`); see `Pipeline::execution_context`.
+    pub fn set_metrics_push(&mut self, push: MetricsPush) {
+        self.metrics_push = Some(push);
+    }
+
+    /// How `run_materialized` returns the output
+    pub fn set_materialization(&mut self, materialization: Materialization) {
+        self.materialization = materialization;
+    }
+
+    /// Directory of spilled outputs (default: the system temp directory)
+    pub fn set_spill_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.spill_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    /// Record the feature statistics of the output of every `run`
+    pub fn set_stats_registry(&mut self, registry: StatsRegistry) {
+        self.stats_registry = Some(registry);
+    }
+
+    pub fn stats_registry(&self) -> Option<&StatsRegistry> {
+        self.stats_registry.as_ref()
+    }
+
+    /// Handling of inputs without rows
+    ///
+    /// Every operation accepts empty input (and single rows): statistics that
+    /// need more rows come out null instead of failing. Scheduled jobs fed empty
+    /// extracts can instead skip the run, returning the input tagged with
+    /// `RUN_TAG_SKIPPED` and logging a warning, without running steps or writing
+    /// sinks, or fail it.
+    pub fn set_empty_input(&mut self, empty_input: EmptyInput) {
+        self.empty_input = empty_input;
+    }
+
+    /// Whether the run on `data` is skipped because it has no rows; tags a
+    /// skipped input
+    fn skip_empty(&self, data: &mut TimeSeriesData) -> Result<bool> {
+        if !data.is_empty() {
+            return Ok(false);
+        }
+        match self.empty_input {
+            EmptyInput::Process => Ok(false),
+            EmptyInput::Skip => {
+                tracing::warn!("input has no rows; skipping the pipeline");
+                data.add_tag(RUN_TAG_SKIPPED.to_string(), "empty input".to_string());
+                Ok(true)
+            }
+            EmptyInput::Error => Err(IndustrytsError::InvalidOperation(
+                "the pipeline input has no rows".to_string(),
+            )),
+        }
+    }
+
+    /// New execution context carrying the metrics push of the pipeline
+    pub fn execution_context(&self) -> ExecutionContext {
+        match &self.metrics_push {
+            Some(push) => ExecutionContext::new().with_metrics_push(push.clone()),
+            None => ExecutionContext::new(),
+        }
+    }
+
+    /// Make `process` run through `process_lazy`
+    pub fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    /// Precision of floating-point columns during a run
+    ///
+    /// With `FloatPrecision::F32`, `Float64` feature columns are cast to `Float32`
+    /// on input and after every step, halving the memory of wide datasets.
+    /// Operations compute in f64 internally and only their results are narrowed.
+    pub fn set_float_precision(&mut self, precision: FloatPrecision) {
+        self.float_precision = precision;
+    }
+
+    pub fn float_precision(&self) -> FloatPrecision {
+        self.float_precision
+    }
+
+    /// Treatment of NaN and null in floating-point feature columns during a run
+    ///
+    /// The policy is applied to the input and after every step, so NaN produced by
+    /// an operation (e.g. 0/0) is normalized before the next step sees it.
+    pub fn set_nan_policy(&mut self, policy: NanPolicy) {
+        self.nan_policy = policy;
+    }
+
+    pub fn nan_policy(&self) -> NanPolicy {
+        self.nan_policy
+    }
+
+    /// Treat these columns as entity keys of inputs that do not declare any
+    ///
+    /// See `TimeSeriesData::with_group_columns`. Runs fail if an input lacks them.
+    pub fn set_group_columns(&mut self, columns: Vec<String>) {
+        self.group_columns = columns;
+    }
+
+    /// Treat inputs without a time zone as UTC data of the plant zone `zone`
+    ///
+    /// Calendar resampling and regularization then follow the local clock (see
+    /// `TimeSeriesData::set_time_zone`).
+    pub fn set_time_zone(&mut self, zone: &str) -> Result<()> {
+        parse_time_zone(zone)?;
+        self.time_zone = Some(zone.to_string());
+        Ok(())
+    }
+
+    /// Limit the time, rows and memory of each run
+    pub fn set_limits(&mut self, limits: RunLimits) {
+        self.limits = limits;
+    }
+
+    /// Resource limits applied to each run
+    pub fn limits(&self) -> &RunLimits {
+        &self.limits
+    }
+
+    /// Record samples of the rows each step drops or modifies (see `RowAudit`)
+    pub fn set_audit(&mut self, audit: RowAudit) {
+        self.audit = Some(audit);
+    }
+
+    /// Row audit of each run, if enabled
+    pub fn audit(&self) -> Option<&RowAudit> {
+        self.audit.as_ref()
+    }
+
+    /// Set column units, ranges and descriptions of every input (see `TagMetadata`)
+    pub fn set_tag_metadata(&mut self, metadata: TagMetadata) {
+        self.tag_metadata = Some(Arc::new(metadata));
+    }
+
+    /// Tag metadata applied to every input, if any
+    pub fn tag_metadata(&self) -> Option<&TagMetadata> {
+        self.tag_metadata.as_deref()
+    }
+
+    /// Execute the pipeline on time series data
+    pub fn process(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        if self.lazy {
+            return self.process_lazy(data);
+        }
+        let (data, _) = self.process_with_outputs(data)?;
+        Ok(data)
+    }
+
+    /// Run a self-contained configured pipeline, from the `[source]` file to the `[sink]` file
+    ///
+    /// The source falls back to the pipeline's time column. Without a sink the
+    /// output is only returned. Relative paths are resolved against the working
+    /// directory.
+    pub fn run(&self) -> Result<TimeSeriesData> {
+        let (config, source) = self.run_source()?;
+        let mut input = source.read(config.pipeline.time_column.as_deref())?;
+        if self.skip_empty(&mut input)? {
+            return Ok(input);
+        }
+        if !self.transactional {
+            let output = self.process(input)?;
+            if let Some(sink) = &config.sink {
+                sink.write(&output)?;
+            }
+            self.record_stats(config, &output)?;
+            return Ok(output);
+        }
+        let (output, outputs) = if self.lazy {
+            (self.process_lazy(input)?, OutputStore::new())
+        } else {
+            self.execute_steps(input, &DataCatalog::new())?
+        };
+        let mut transaction = self.stage_sinks(&outputs)?.0;
+        if let Some(sink) = &config.sink {
+            transaction.push(Box::new(sink.stage(&output)?));
+        }
+        transaction.commit()?;
+        self.record_stats(config, &output)?;
+        Ok(output)
+    }
+
+    /// Run like `run`, returning the output as set by `set_materialization`
+    ///
+    /// `Materialization::Memory` and `Materialization::Spill` also write the
+    /// `[sink]` file, if any, like `run`. `Materialization::Sink` needs a
+    /// `[sink]` section. It runs step by step (see `process_with_context`) to
+    /// report per-step metrics, and writes the `[sink]` file after the routed
+    /// sinks, outside of a transaction.
+    pub fn run_materialized(&self) -> Result<RunOutput> {
+        match self.materialization {
+            Materialization::Memory => self.run().map(RunOutput::Memory),
+            Materialization::Spill => {
+                let dir = self.spill_dir.clone().unwrap_or_else(std::env::temp_dir);
+                SpilledResult::write(&self.run()?, &dir).map(RunOutput::Spilled)
+            }
+            Materialization::Sink => {
+                let (config, source) = self.run_source()?;
+                let Some(sink) = &config.sink else {
+                    return Err(IndustrytsError::ConfigError(
+                        "materialize = \"sink\" needs a [sink] section".to_string(),
+                    ));
+                };
+                let input = source.read(config.pipeline.time_column.as_deref())?;
+                let (output, mut context) =
+                    self.process_with_context(input, self.execution_context())?;
+                if context.get_metadata(RUN_TAG_SKIPPED).is_none() {
+                    sink.write(&output)?;
+                    self.record_stats(config, &output)?;
+                }
+                context.record_bytes_written(output.dataframe().estimated_size() as u64);
+                context.push_metrics();
+                let mut report = ExecutionReport::from_context(&context);
+                report.pipeline = Some(config.pipeline.name.clone());
+                Ok(RunOutput::Written(report))
+            }
+        }
+    }
+
+    /// Record the feature statistics of a configured run's output, if a registry
+    /// is set
+    fn record_stats(&self, config: &PipelineConfig, output: &TimeSeriesData) -> Result<()> {
+        if let Some(registry) = &self.stats_registry {
+            registry.record(Some(&config.pipeline.name), output)?;
+        }
+        Ok(())
+    }
+
+    /// Configuration and `[source]` section of a self-contained pipeline
+    fn run_source(&self) -> Result<(&PipelineConfig, &SourceConfig)> {
+        self.config
+            .as_ref()
+            .and_then(|c| Some((c, c.source.as_ref()?)))
+            .ok_or_else(|| {
+                IndustrytsError::ConfigError(
+                    "Pipeline::run needs a configuration with a [source] section".to_string(),
+                )
+            })
+    }
+
+    /// Execute the pipeline as one lazy Polars query
+    ///
+    /// Steps implementing `Operation::apply_lazy` extend a single query plan, so
+    /// consecutive column-wise steps are optimized and run together without
+    /// copying data in between; other steps collect the plan and run eagerly.
+    /// Secondary outputs, row contracts and tags set by steps are not tracked in
+    /// this mode; the input's tags are kept. Grouped data and data with a time zone
+    /// run eagerly.
+    pub fn process_lazy(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut data = self.prepare(data)?;
+        if self.skip_empty(&mut data)? {
+            return Ok(data);
+        }
+        // Lazy steps see only the frame, not the group columns or the time zone
+        if !data.group_columns().is_empty() || data.time_zone().is_some() {
+            let (data, _) = self.process_with_outputs(data)?;
+            return Ok(data);
+        }
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        self.check(&data)?;
+        let time_column = data.time_column().to_string();
+        let tags = data.metadata().tags.clone();
+        let mut lf = data.into_dataframe().lazy();
+        for (index, step) in self.operations.iter().enumerate() {
+            let operation = step.as_sync()?;
+            lf = catch_panic(index, operation.name(), || {
+                operation.apply_lazy(lf, &time_column)
+            })?;
+        }
+        let mut result = TimeSeriesData::new(lf.collect()?, Some(&time_column))?
+            .with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)?;
+        result.metadata_mut().tags = tags;
+        self.limits.check("the lazy query", clock, &result, None)?;
+        self.stamp(&mut result, started)?;
+        Ok(result)
+    }
+
+    /// Execute the pipeline, returning the secondary outputs of all operations
+    pub fn process_with_outputs(
+        &self,
+        data: TimeSeriesData,
+    ) -> Result<(TimeSeriesData, OutputStore)> {
+        self.process_with_inputs(data, &DataCatalog::new())
+    }
+
+    /// Execute the pipeline with named datasets for steps that read a second input
+    ///
+    /// Steps such as a `merge` with an `input` read the dataset of that name (e.g.
+    /// weather or lab results to join). Fails before running if one of
+    /// `catalog_inputs` is missing. Returns the secondary outputs like
+    /// `process_with_outputs`.
+    pub fn process_with_inputs(
+        &self,
+        data: TimeSeriesData,
+        inputs: &DataCatalog,
+    ) -> Result<(TimeSeriesData, OutputStore)> {
+        let (data, outputs) = self.execute_steps(data, inputs)?;
+        self.write_sinks(&outputs)?;
+        Ok((data, outputs))
+    }
+
+    /// Run the steps of `process_with_inputs` without writing the sinks
+    fn execute_steps(
+        &self,
+        mut data: TimeSeriesData,
+        inputs: &DataCatalog,
+    ) -> Result<(TimeSeriesData, OutputStore)> {
+        if let Some(missing) = self
+            .catalog_inputs()
+            .into_iter()
+            .find(|name| !inputs.contains_key(name))
+        {
+            return Err(IndustrytsError::ConfigError(format!(
+                "the pipeline reads the dataset '{}', which was not supplied",
+                missing
+            )));
+        }
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        data = self.prepare(data)?;
+        if self.skip_empty(&mut data)? {
+            return Ok((data, OutputStore::new()));
         }
+        self.check(&data)?;
+        let mut ctx = OpContext::new().with_catalog(inputs.clone());
+        for (index, step) in self.operations.iter().enumerate() {
+            let operation = step.as_sync()?;
+            let provenance = self.provenance_step(index);
+            data = RowContract::execute(
+                index,
+                operation,
+                data,
+                &mut ctx,
+                self.audit.as_ref(),
+                provenance.as_ref(),
+            )?
+            .with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)?;
+            self.check_limits(index, operation.name(), clock, &data, &ctx)?;
+        }
+        self.stamp(&mut data, started)?;
+        Ok((data, ctx.into_outputs()))
     }
 
-    /// Load pipeline from TOML configuration file
-    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let config = PipelineConfig::from_toml_file(path.as_ref())?;
-        let mut pipeline = Self::new();
-        pipeline.config = Some(config.clone());
+    /// Execute the pipeline on a tokio runtime
+    ///
+    /// Async operations are awaited directly; synchronous operations run on the
+    /// blocking thread pool so CPU-bound work does not stall async worker threads.
+    pub async fn process_async(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        data = self.prepare(data)?;
+        if self.skip_empty(&mut data)? {
+            return Ok(data);
+        }
+        self.check(&data)?;
+        let mut ctx = OpContext::new();
+        for (index, step) in self.operations.iter().enumerate() {
+            let name = match step {
+                PipelineStep::Async(operation) => operation.name().to_string(),
+                PipelineStep::Sync(operation) => operation.name().to_string(),
+            };
+            data = match step {
+                PipelineStep::Async(operation) => {
+                    let operation = operation.clone();
+                    tokio::spawn(async move { operation.execute(data).await })
+                        .await
+                        .map_err(|e| match e.try_into_panic() {
+                            Ok(payload) => IndustrytsError::OperationPanicked {
+                                step: index,
+                                operation: name.clone(),
+                                message: panic_message(payload.as_ref()),
+                            },
+                            Err(e) => IndustrytsError::OperationError(e.to_string()),
+                        })??
+                }
+                PipelineStep::Sync(operation) => {
+                    let operation = operation.clone();
+                    let audit = self.audit.clone();
+                    let provenance = self.provenance_step(index);
+                    let (result, returned) = tokio::task::spawn_blocking(move || {
+                        let result = RowContract::execute(
+                            index,
+                            operation.as_ref(),
+                            data,
+                            &mut ctx,
+                            audit.as_ref(),
+                            provenance.as_ref(),
+                        );
+                        (result, ctx)
+                    })
+                    .await
+                    .map_err(|e| IndustrytsError::OperationError(e.to_string()))?;
+                    ctx = returned;
+                    result?
+                }
+            }
+            .with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)?;
+            self.check_limits(index, &name, clock, &data, &ctx)?;
+        }
+        self.write_sinks(ctx.outputs())?;
+        self.stamp(&mut data, started)?;
+        Ok(data)
+    }
 
-        // Convert OperationConfig to Operation instances
-        for op_config in &config.operations {
-            let operation = Self::create_operation(op_config)?;
-            pipeline.add_operation(operation);
+    /// Named datasets read by the steps besides the pipeline input
+    pub fn catalog_inputs(&self) -> Vec<String> {
+        let mut inputs: Vec<String> = Vec::new();
+        for step in &self.operations {
+            if let PipelineStep::Sync(operation) = step {
+                for input in operation.catalog_inputs() {
+                    if !inputs.contains(&input) {
+                        inputs.push(input);
+                    }
+                }
+            }
         }
+        inputs
+    }
 
-        Ok(pipeline)
+    /// Steps whose output at a row depends on later rows, with the reasons
+    ///
+    /// Each entry reads e.g. "step 2 (lag): lag period -1 reads 1 row(s) ahead".
+    /// Async steps declare nothing and are not listed.
+    pub fn lookahead(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+        for (index, step) in self.operations.iter().enumerate() {
+            if let PipelineStep::Sync(operation) = step {
+                for reason in operation.lookahead() {
+                    reasons.push(format!("step {} ({}): {}", index, operation.name(), reason));
+                }
+            }
+        }
+        reasons
     }
 
-    /// Create an operation from configuration
-    fn create_operation(config: &crate::config::OperationConfig) -> Result<Box<dyn Operation>> {
-        use crate::config::OperationConfig;
-        use crate::operations::*;
+    /// Synchronous operations of all steps, failing if any step is async
+    pub(crate) fn sync_operations(&self) -> Result<Vec<&dyn Operation>> {
+        self.operations.iter().map(PipelineStep::as_sync).collect()
+    }
 
-        match config {
-            OperationConfig::FillNull { method, columns } => {
-                Ok(Box::new(FillNullOperation::new(*method, columns.clone())))
+    /// Apply the configured group columns, time zone, tag metadata, float precision and
+    /// NaN policy to the input of a run
+    pub(crate) fn prepare(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        let mut data = if self.group_columns.is_empty() || !data.group_columns().is_empty() {
+            data
+        } else {
+            data.with_group_columns(&self.group_columns)?
+        };
+        if let Some(zone) = &self.time_zone
+            && data.time_zone().is_none()
+        {
+            data.set_time_zone(zone)?;
+        }
+        if let Some(metadata) = &self.tag_metadata {
+            data.apply_tag_metadata(metadata);
+        }
+        data.with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)
+    }
+
+    /// Check a run on `data` before executing anything
+    ///
+    /// Propagates the schema through all steps, then calls `Operation::validate` of
+    /// every step on a preview of its input: `data` with the columns added by earlier
+    /// steps appended as null Float64 columns. Like `propagate_schema`, checking
+    /// stops at the first async step. Returns the feature columns expected after the
+    /// pipeline has run.
+    pub fn check(&self, data: &TimeSeriesData) -> Result<Vec<String>> {
+        for group in data.group_columns() {
+            if data.dataframe().column(group).is_err() {
+                return Err(IndustrytsError::ColumnNotFound(format!(
+                    "{} (group column)",
+                    group
+                )));
             }
-            OperationConfig::Resample {
-                rule: _,
-                aggregation: _,
-                columns: _,
-            } => {
-                // TODO: Resample operation requires updating to Polars 0.51 API
-                Err(crate::IndustrytsError::InvalidOperation(
-                    "Resample operation is not yet implemented for Polars 0.51+".to_string(),
-                ))
+        }
+        let columns = self.propagate_schema(data)?;
+
+        let mut preview = data.clone();
+        for step in &self.operations {
+            let PipelineStep::Sync(operation) = step else {
+                break;
+            };
+            operation.validate(&preview)?;
+
+            let mut df = preview.dataframe().clone();
+            for added in operation.added_columns(preview.feature_columns()) {
+                if df.column(&added).is_err() {
+                    df.with_column(Series::full_null(
+                        added.as_str().into(),
+                        df.height(),
+                        &DataType::Float64,
+                    ))?;
+                }
             }
-            OperationConfig::Lag { periods, columns } => Ok(Box::new(LagOperation::new(
-                periods.clone(),
-                columns.clone(),
-            ))),
-            OperationConfig::Standardize { columns } => {
-                Ok(Box::new(StandardizeOperation::new(columns.clone())))
+            if df.width() != preview.dataframe().width() {
+                let tags = preview.metadata().tags.clone();
+                preview = TimeSeriesData::new(df, Some(data.time_column()))?
+                    .with_group_columns(data.group_columns())?;
+                preview.metadata_mut().tags = tags;
             }
         }
+        Ok(columns)
     }
 
-    /// Add an operation to the pipeline
-    pub fn add_operation(&mut self, operation: Box<dyn Operation>) {
-        self.operations.push(operation);
+    /// Check declared column requirements and propagate the schema through all steps
+    ///
+    /// Returns the feature columns expected after the pipeline has run. Checking
+    /// stops at the first async step, which does not declare a contract.
+    pub fn propagate_schema(&self, data: &TimeSeriesData) -> Result<Vec<String>> {
+        Ok(self
+            .column_origins(data)?
+            .into_iter()
+            .map(|(column, _)| column)
+            .collect())
     }
 
-    /// Execute the pipeline on time series data
-    pub fn process(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
-        for operation in &self.operations {
-            data = operation.execute(data)?;
+    /// Expected feature columns with the name of the step that adds each one
+    ///
+    /// Input columns have no origin. Like `propagate_schema`, tracing stops at the
+    /// first async step.
+    pub fn column_origins(&self, data: &TimeSeriesData) -> Result<Vec<(String, Option<String>)>> {
+        let time_column = data.time_column().to_string();
+        let mut columns = data.feature_columns().to_vec();
+        let mut origins = vec![None; columns.len()];
+
+        for step in &self.operations {
+            let PipelineStep::Sync(operation) = step else {
+                break;
+            };
+            for required in operation.required_columns() {
+                if required != time_column && !columns.contains(&required) {
+                    return Err(IndustrytsError::ColumnNotFound(format!(
+                        "{} (required by {})",
+                        required,
+                        operation.name()
+                    )));
+                }
+            }
+            for added in operation.added_columns(&columns) {
+                if !columns.contains(&added) {
+                    columns.push(added);
+                    origins.push(Some(operation.name().to_string()));
+                }
+            }
+        }
+
+        Ok(columns.into_iter().zip(origins).collect())
+    }
+
+    /// Fail if the run has exceeded its resource limits after step `index`
+    fn check_limits(
+        &self,
+        index: usize,
+        name: &str,
+        clock: Instant,
+        data: &TimeSeriesData,
+        ctx: &OpContext,
+    ) -> Result<()> {
+        self.limits.check(
+            &format!("step {} ({})", index, name),
+            clock,
+            data,
+            Some(ctx.outputs()),
+        )
+    }
+
+    /// Write routed outputs to their sinks, all sinks concurrently
+    ///
+    /// Transactional pipelines stage all outputs before committing them. Returns
+    /// the estimated size of the tables written.
+    fn write_sinks(&self, outputs: &OutputStore) -> Result<usize> {
+        if self.transactional {
+            let (transaction, size) = self.stage_sinks(outputs)?;
+            transaction.commit()?;
+            return Ok(size);
+        }
+        self.sinks
+            .par_iter()
+            .map(|(name, sink)| match outputs.get(name) {
+                Some(table) => sink.write(name, table).map(|_| table.estimated_size()),
+                None => Ok(0),
+            })
+            .sum()
+    }
+
+    /// Stage routed outputs in their sinks, all sinks concurrently
+    ///
+    /// If a sink fails, the outputs staged so far are rolled back.
+    fn stage_sinks(&self, outputs: &OutputStore) -> Result<(SinkTransaction<'_>, usize)> {
+        let staged: Vec<Result<_>> = self
+            .sinks
+            .par_iter()
+            .filter_map(|(name, sink)| {
+                let table = outputs.get(name)?;
+                Some(sink.stage(name, table).map(|s| (s, table.estimated_size())))
+            })
+            .collect();
+        let mut transaction = SinkTransaction::new();
+        let mut size = 0;
+        let mut error = None;
+        for result in staged {
+            match result {
+                Ok((output, bytes)) => {
+                    transaction.push(output);
+                    size += bytes;
+                }
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok((transaction, size)),
         }
-        Ok(data)
     }
 
     /// Execute the pipeline with execution context tracking
@@ -85,11 +2150,39 @@ impl Pipeline {
         mut data: TimeSeriesData,
         mut context: ExecutionContext,
     ) -> Result<(TimeSeriesData, ExecutionContext)> {
-        for operation in &self.operations {
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        data = self.prepare(data)?;
+        if self.skip_empty(&mut data)? {
+            context.add_metadata(RUN_TAG_SKIPPED.to_string(), "empty input".to_string());
+            return Ok((data, context));
+        }
+        self.check(&data)?;
+        let input_bytes = data.dataframe().estimated_size();
+        context.record_bytes_read(input_bytes as u64);
+        context.record_memory(input_bytes);
+        let mut ctx = OpContext::new()
+            .with_settings(context.metadata().clone())
+            .with_cancellation(context.cancellation_token().clone());
+        for (index, step) in self.operations.iter().enumerate() {
+            ctx.check_cancelled()?;
+            let operation = step.as_sync()?;
             let input_rows = data.len();
             let input_columns = data.feature_columns().len();
+            let input_nulls = data.feature_null_count();
+            let start = std::time::Instant::now();
 
-            data = operation.execute(data)?;
+            let provenance = self.provenance_step(index);
+            data = RowContract::execute(
+                index,
+                operation,
+                data,
+                &mut ctx,
+                self.audit.as_ref(),
+                provenance.as_ref(),
+            )?
+            .with_float_precision(self.float_precision)?
+            .with_nan_policy(self.nan_policy)?;
 
             let output_rows = data.len();
             let output_columns = data.feature_columns().len();
@@ -101,12 +2194,38 @@ impl Pipeline {
             metrics.output_rows = output_rows;
             metrics.input_columns = input_columns;
             metrics.output_columns = output_columns;
+            metrics.output_bytes = data.dataframe().estimated_size();
+            metrics.input_nulls = input_nulls;
+            metrics.output_nulls = data.feature_null_count();
+            metrics.step = Some(index);
+            metrics.duration = start.elapsed();
+            (metrics.warnings, metrics.custom) = ctx.take_diagnostics();
 
             context.record_metrics(metrics);
+            let held = data.dataframe().estimated_size() + ctx.outputs().estimated_size();
+            context.record_memory(held);
+            self.check_limits(index, operation.name(), clock, &data, &ctx)?;
         }
+        let written = self.write_sinks(ctx.outputs())?;
+        context.record_bytes_written(written as u64);
+        self.stamp(&mut data, started)?;
         Ok((data, context))
     }
 
+    /// Execute the pipeline and report per-step metrics of the run
+    ///
+    /// See `ExecutionReport` for the figures collected.
+    pub fn process_with_report(
+        &self,
+        data: TimeSeriesData,
+    ) -> Result<(TimeSeriesData, ExecutionReport)> {
+        let (data, mut context) = self.process_with_context(data, self.execution_context())?;
+        context.push_metrics();
+        let mut report = ExecutionReport::from_context(&context);
+        report.pipeline = self.config.as_ref().map(|c| c.pipeline.name.clone());
+        Ok((data, report))
+    }
+
     /// Get number of operations in the pipeline
     pub fn len(&self) -> usize {
         self.operations.len()
@@ -117,17 +2236,43 @@ impl Pipeline {
         self.operations.is_empty()
     }
 
+    /// Names of all steps, in order
+    pub fn operation_names(&self) -> Vec<String> {
+        self.operations
+            .iter()
+            .map(|step| match step {
+                PipelineStep::Sync(operation) => operation.name().to_string(),
+                PipelineStep::Async(operation) => operation.name().to_string(),
+            })
+            .collect()
+    }
+
+    /// Configuration the pipeline was loaded from, if any
+    pub fn config(&self) -> Option<&PipelineConfig> {
+        self.config.as_ref()
+    }
+
     /// Save pipeline configuration to TOML file
     pub fn to_toml<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         if let Some(config) = &self.config {
             config.to_toml_file(path.as_ref())?;
             Ok(())
         } else {
-            Err(crate::IndustrytsError::ConfigError(
+            Err(IndustrytsError::ConfigError(
                 "Pipeline has no configuration to save".to_string(),
             ))
         }
     }
+
+    /// Save pipeline configuration as TOML or JSON (chosen by extension)
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        match &self.config {
+            Some(config) => config.to_file(path.as_ref()),
+            None => Err(IndustrytsError::ConfigError(
+                "Pipeline has no configuration to save".to_string(),
+            )),
+        }
+    }
 }
 
 impl Default for Pipeline {
@@ -146,4 +2291,795 @@ mod tests {
         assert_eq!(pipeline.len(), 0);
         assert!(pipeline.is_empty());
     }
+
+    #[test]
+    fn test_row_contract_violations() {
+        use polars::prelude::*;
+
+        /// Reverses the rows without declaring it
+        struct Reverse;
+
+        impl Operation for Reverse {
+            fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+                TimeSeriesData::new(data.dataframe().reverse(), Some(data.time_column()))
+            }
+
+            fn name(&self) -> &str {
+                "reverse"
+            }
+        }
+
+        /// Drops the first row without declaring it
+        struct DropFirst;
+
+        impl Operation for DropFirst {
+            fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+                TimeSeriesData::new(
+                    data.dataframe().slice(1, data.len()),
+                    Some(data.time_column()),
+                )
+            }
+
+            fn name(&self) -> &str {
+                "drop_first"
+            }
+        }
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(DropFirst));
+        let err = pipeline.process(data.clone()).err().unwrap().to_string();
+        assert!(err.contains("step 0 (drop_first)"), "{}", err);
+        assert!(err.contains("3 input rows became 2, expected 3"), "{}", err);
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(crate::operations::LagOperation::new(
+            vec![1],
+            None,
+        )));
+        pipeline.add_operation(Box::new(Reverse));
+        let err = pipeline
+            .process_with_context(data, ExecutionContext::new())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("step 1 (reverse)"), "{}", err);
+        assert!(err.contains("no longer sorted"), "{}", err);
+    }
+
+    #[test]
+    fn test_lazy_matches_eager() {
+        use crate::config::PipelineConfig;
+        use polars::prelude::*;
+
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "lazy"
+
+            [execution]
+            lazy = true
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            columns = ["value"]
+            partition_by = ["batch"]
+
+            [[operations]]
+            type = "difference"
+            columns = ["value"]
+
+            [[operations]]
+            type = "standardize"
+            columns = ["value_diff_1"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.execution.lazy);
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000, 180_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("batch".into(), &["a", "a", "b", "b"]).into(),
+            Series::new("value".into(), &[1.0, 3.0, 4.0, 8.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        for operation in &config.operations {
+            pipeline.add_operation(Pipeline::create_operation(operation, None).unwrap());
+        }
+        let eager = pipeline.process(data.clone()).unwrap();
+        let lazy = pipeline.process_lazy(data).unwrap();
+        for column in ["value_lag_1", "value_diff_1"] {
+            let want = eager.dataframe().column(column).unwrap().f64().unwrap();
+            let got = lazy.dataframe().column(column).unwrap().f64().unwrap();
+            for (g, w) in got.into_iter().zip(want) {
+                match (g, w) {
+                    (Some(g), Some(w)) => assert!((g - w).abs() < 1e-12, "{}", column),
+                    _ => assert_eq!(g, w, "{}", column),
+                }
+            }
+        }
+        assert_eq!(
+            lazy.feature_columns(),
+            ["batch", "value", "value_lag_1", "value_diff_1"]
+        );
+    }
+
+    #[test]
+    fn test_f32_precision() {
+        use crate::config::PipelineConfig;
+        use polars::prelude::*;
+
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "narrow"
+
+            [execution]
+            float = "f32"
+
+            [[operations]]
+            type = "fill_null"
+            method = "mean"
+
+            [[operations]]
+            type = "standardize"
+            columns = ["value"]
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.execution.float, FloatPrecision::F32);
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000, 180_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[Some(1.0), None, Some(4.0), Some(7.0)]).into(),
+            Series::new("count".into(), &[1i64, 2, 3, 4]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        for operation in &config.operations {
+            pipeline.add_operation(Pipeline::create_operation(operation, None).unwrap());
+        }
+        let wide = pipeline.process(data.clone()).unwrap();
+        pipeline.set_float_precision(config.execution.float);
+        let narrow = pipeline.process(data).unwrap();
+
+        for column in ["value", "value_lag_1"] {
+            let dtype = narrow.dataframe().column(column).unwrap().dtype().clone();
+            assert_eq!(dtype, DataType::Float32, "{}", column);
+            let want = wide.dataframe().column(column).unwrap().f64().unwrap();
+            let got = narrow
+                .dataframe()
+                .column(column)
+                .unwrap()
+                .cast(&DataType::Float64)
+                .unwrap();
+            for (g, w) in got.f64().unwrap().into_iter().zip(want) {
+                match (g, w) {
+                    (Some(g), Some(w)) => assert!((g - w).abs() < 1e-6, "{}", column),
+                    _ => assert_eq!(g, w, "{}", column),
+                }
+            }
+        }
+        // Only floating-point columns are narrowed
+        assert_eq!(
+            narrow.dataframe().column("count").unwrap().dtype(),
+            wide.dataframe().column("count").unwrap().dtype()
+        );
+    }
+
+    #[test]
+    fn test_secondary_outputs_routed_to_sink() {
+        use polars::prelude::*;
+        use std::sync::{Arc, Mutex};
+
+        /// Splits off negative values as "rejected" rows
+        struct RejectNegative;
+
+        impl Operation for RejectNegative {
+            fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+                self.execute_with_context(data, &mut OpContext::new())
+            }
+
+            fn execute_with_context(
+                &self,
+                data: TimeSeriesData,
+                ctx: &mut OpContext,
+            ) -> Result<TimeSeriesData> {
+                let df = data.dataframe();
+                let negative = df.column("value")?.as_materialized_series().lt(0.0)?;
+                ctx.outputs_mut()
+                    .insert("rejected", df.filter(&negative)?)?;
+                ctx.warn("rejected negative values");
+                ctx.record_metric("rejected_rows", negative.sum().unwrap_or(0) as f64);
+                let clean = df.filter(&!negative)?;
+                TimeSeriesData::new(clean, Some(data.time_column()))
+            }
+
+            fn name(&self) -> &str {
+                "reject_negative"
+            }
+
+            fn removes_rows(&self) -> bool {
+                true
+            }
+        }
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, -2.0, 3.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let sink_written = written.clone();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(RejectNegative));
+        pipeline.add_sink(
+            "rejected",
+            Box::new(move |name: &str, table: &DataFrame| {
+                sink_written
+                    .lock()
+                    .unwrap()
+                    .push((name.to_string(), table.height()));
+                Ok(())
+            }),
+        );
+
+        let (result, outputs) = pipeline.process_with_outputs(data.clone()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(outputs.get("rejected").unwrap().height(), 1);
+        assert_eq!(*written.lock().unwrap(), vec![("rejected".to_string(), 1)]);
+
+        let (_, context) = pipeline
+            .process_with_context(data.clone(), ExecutionContext::new())
+            .unwrap();
+        let metrics = &context.metrics()[0];
+        assert_eq!(metrics.warnings, vec!["rejected negative values"]);
+        assert_eq!(metrics.custom.get("rejected_rows"), Some(&1.0));
+
+        let context = ExecutionContext::new();
+        context.cancellation_token().cancel();
+        assert!(matches!(
+            pipeline.process_with_context(data, context),
+            Err(IndustrytsError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_process_async() {
+        use crate::core::AsyncOperation;
+        use polars::prelude::*;
+
+        struct AddTag;
+
+        #[async_trait::async_trait]
+        impl AsyncOperation for AddTag {
+            async fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+                tokio::task::yield_now().await;
+                data.add_tag("enriched".to_string(), "true".to_string());
+                Ok(data)
+            }
+
+            fn name(&self) -> &str {
+                "add_tag"
+            }
+        }
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(crate::operations::StandardizeOperation::new(None)));
+        pipeline.add_async_operation(Box::new(AddTag));
+
+        assert!(pipeline.process(data.clone()).is_err());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(pipeline.process_async(data)).unwrap();
+        assert_eq!(result.get_tag("enriched"), Some("true"));
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn test_pipeline_seed_reaches_operations() {
+        use polars::prelude::*;
+
+        struct SeededOp(Option<u64>);
+
+        impl Operation for SeededOp {
+            fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+                data.add_tag("seed".to_string(), format!("{:?}", self.0));
+                Ok(data)
+            }
+
+            fn name(&self) -> &str {
+                "seeded"
+            }
+
+            fn set_seed(&mut self, seed: u64) {
+                self.0 = Some(seed);
+            }
+        }
+
+        let time_series = Series::new("time".into(), &[0i64])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![time_series.into()]).unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let run = |seed: u64| {
+            let mut pipeline = Pipeline::new();
+            pipeline.add_operation(Box::new(SeededOp(None)));
+            pipeline.set_seed(seed);
+            let result = pipeline.process(data.clone()).unwrap();
+            result.get_tag("seed").unwrap().to_string()
+        };
+
+        assert_ne!(run(1), "None");
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn test_panicking_operation_becomes_error() {
+        use crate::operations::LagOperation;
+        use crate::testing::Fixture;
+
+        struct PanickingOp;
+
+        impl Operation for PanickingOp {
+            fn execute(&self, _data: TimeSeriesData) -> Result<TimeSeriesData> {
+                panic!("plugin bug");
+            }
+
+            fn name(&self) -> &str {
+                "plugin"
+            }
+        }
+
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("temp", [1.0, 2.0, 3.0])
+            .build()
+            .unwrap();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.add_operation(Box::new(PanickingOp));
+        let Err(IndustrytsError::OperationPanicked {
+            step,
+            operation,
+            message,
+        }) = pipeline.process(data)
+        else {
+            panic!("the panic is reported as an error");
+        };
+        assert_eq!((step, operation.as_str()), (1, "plugin"));
+        assert_eq!(message, "plugin bug");
+    }
+
+    #[test]
+    fn test_propagate_schema() {
+        use crate::operations::{LagOperation, StandardizeOperation};
+        use polars::prelude::*;
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.add_operation(Box::new(StandardizeOperation::new(Some(vec![
+            "value_lag_1".to_string(),
+        ]))));
+        assert_eq!(
+            pipeline.propagate_schema(&data).unwrap(),
+            vec!["value", "value_lag_1"]
+        );
+
+        pipeline.add_operation(Box::new(StandardizeOperation::new(Some(vec![
+            "missing".to_string(),
+        ]))));
+        assert!(matches!(
+            pipeline.process(data),
+            Err(IndustrytsError::ColumnNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_validates_before_execution() {
+        use crate::operations::{LagOperation, PerGroupOperation, StandardizeOperation};
+        use polars::prelude::*;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Validates on a column it does not declare, and counts executions
+        struct NeedsLag(Arc<AtomicUsize>);
+
+        impl Operation for NeedsLag {
+            fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(data)
+            }
+
+            fn name(&self) -> &str {
+                "needs_lag"
+            }
+
+            fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+                data.dataframe().column("value_lag_1")?;
+                Ok(())
+            }
+        }
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let executed = Arc::new(AtomicUsize::new(0));
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.add_operation(Box::new(NeedsLag(executed.clone())));
+        assert_eq!(pipeline.check(&data).unwrap(), vec!["value", "value_lag_1"]);
+
+        pipeline.add_operation(Box::new(PerGroupOperation::new(
+            "asset",
+            Box::new(StandardizeOperation::new(None)),
+        )));
+        assert!(matches!(
+            pipeline.process(data),
+            Err(IndustrytsError::ColumnNotFound(_))
+        ));
+        assert_eq!(executed.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_stamp_run_info() {
+        use polars::prelude::*;
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let path = std::env::temp_dir().join("industryts_stamp_run_info.toml");
+        std::fs::write(
+            &path,
+            "[pipeline]\nname = \"line_3\"\nstamp_run_info = true\n\n\
+             [[operations]]\ntype = \"lag\"\nperiods = [1]\n",
+        )
+        .unwrap();
+        let pipeline = Pipeline::from_toml(&path).unwrap();
+        let result = pipeline.process(data.clone()).unwrap();
+
+        assert_eq!(result.get_tag(RUN_TAG_PIPELINE), Some("line_3"));
+        assert_eq!(
+            result.get_tag(RUN_TAG_CONFIG_HASH),
+            pipeline.config_hash().unwrap().as_deref()
+        );
+        assert_eq!(
+            result.get_tag(RUN_TAG_VERSION),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert!(result.get_tag(RUN_TAG_TIMESTAMP).unwrap().ends_with('Z'));
+
+        let unstamped = Pipeline::new().process(data).unwrap();
+        assert_eq!(unstamped.get_tag(RUN_TAG_VERSION), None);
+    }
+
+    #[test]
+    fn test_run_from_source_to_sink() {
+        let dir = std::env::temp_dir().join(format!("industryts_run_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("input.csv"),
+            "stamp,value\n1704067200,1.0\n1704067260,4.0\n1704067320,9.0\n",
+        )
+        .unwrap();
+        let config = format!(
+            r#"
+            [pipeline]
+            name = "files"
+            time_column = "stamp"
+
+            [source]
+            path = "{dir}/input.csv"
+            epoch_unit = "s"
+
+            [sink]
+            path = "{dir}/output.arrow"
+
+            [[operations]]
+            type = "difference"
+            columns = ["value"]
+            "#,
+            dir = dir.display()
+        );
+        std::fs::write(dir.join("pipeline.toml"), config).unwrap();
+
+        let pipeline = Pipeline::from_toml(dir.join("pipeline.toml")).unwrap();
+        let output = pipeline.run().unwrap();
+        assert_eq!(output.timestamps_ms().unwrap().get(1), Some(1704067260000));
+
+        let written = TimeSeriesData::read_ipc(
+            dir.join("output.arrow"),
+            &crate::io::ReadOptions::new().with_time_column("stamp"),
+        )
+        .unwrap();
+        assert_eq!(written.feature_columns(), ["value", "value_diff_1"]);
+        assert!(Pipeline::new().run().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transactional_run_writes_all_sinks_or_none() {
+        let dir = std::env::temp_dir().join(format!("industryts_tx_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("input.csv"),
+            "stamp,value\n1704067200,1.0\n1704067200,2.0\n1704067260,4.0\n",
+        )
+        .unwrap();
+        let config = |output: &str| {
+            PipelineConfig::from_toml_str(&format!(
+                r#"
+                [pipeline]
+                name = "tx"
+                time_column = "stamp"
+
+                [execution]
+                transactional = true
+
+                [audit]
+                dir = "{dir}/audit"
+
+                [source]
+                path = "{dir}/input.csv"
+                epoch_unit = "s"
+
+                [sink]
+                path = "{output}"
+
+                [[operations]]
+                type = "deduplicate"
+                "#,
+                dir = dir.display()
+            ))
+            .unwrap()
+        };
+        let audit = dir.join("audit").join("audit.arrow");
+
+        // The output directory is missing: the audit table must not be written
+        let missing = dir.join("missing").join("output.csv");
+        let pipeline = Pipeline::from_config(config(&missing.display().to_string())).unwrap();
+        assert!(pipeline.run().is_err());
+        assert!(!audit.exists());
+        assert_eq!(std::fs::read_dir(dir.join("audit")).unwrap().count(), 0);
+
+        let output = dir.join("output.csv");
+        let pipeline = Pipeline::from_config(config(&output.display().to_string())).unwrap();
+        assert_eq!(pipeline.run().unwrap().len(), 2);
+        assert!(audit.exists() && output.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_file_formats() {
+        let dir = std::env::temp_dir().join(format!("industryts_formats_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pipeline = Pipeline::template("sensor_cleanup")
+            .unwrap()
+            .build()
+            .unwrap();
+        let toml = pipeline.config().unwrap().to_toml_string().unwrap();
+
+        for file in ["pipeline.json", "pipeline.toml"] {
+            pipeline.to_file(dir.join(file)).unwrap();
+            let loaded = Pipeline::from_file(dir.join(file)).unwrap();
+            assert_eq!(loaded.config().unwrap().to_toml_string().unwrap(), toml);
+            assert_eq!(loaded.len(), pipeline.len());
+        }
+        let json = std::fs::read_to_string(dir.join("pipeline.json")).unwrap();
+        assert!(json.contains("\"type\": \"replace_sentinels\""));
+        assert!(PipelineConfig::from_json_str(&json).is_ok());
+
+        assert!(pipeline.to_file(dir.join("pipeline.yaml")).is_err());
+        assert!(Pipeline::from_file(dir.join("pipeline.txt")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_empty_and_single_row_inputs() {
+        use crate::config::PipelineConfig;
+        use crate::testing::Fixture;
+
+        let config = |empty_input: &str| {
+            PipelineConfig::from_toml_str(&format!(
+                r#"
+                [pipeline]
+                name = "scheduled"
+
+                [execution]
+                empty_input = "{empty_input}"
+
+                [[operations]]
+                type = "resample"
+                rule = "5m"
+                aggregation = "mean"
+                empty_buckets = "null"
+
+                [[operations]]
+                type = "lag"
+                periods = [1]
+                columns = ["a"]
+
+                [[operations]]
+                type = "rolling_features"
+                windows = ["10m"]
+                stats = ["mean", "std"]
+                columns = ["a"]
+
+                [[operations]]
+                type = "cusum"
+                k = 0.5
+                h = 5.0
+                columns = ["a"]
+
+                [[operations]]
+                type = "ewma"
+                lambda = 0.2
+                width = 3.0
+                columns = ["a"]
+
+                [[operations]]
+                type = "standardize"
+                columns = ["a"]
+                "#
+            ))
+            .unwrap()
+        };
+        let input = |rows: usize| {
+            Fixture::every(Duration::from_secs(60), rows)
+                .with_column("a", (0..rows).map(|i| i as f64).collect::<Vec<_>>())
+                .build()
+                .unwrap()
+        };
+
+        let pipeline = Pipeline::from_config(config("process")).unwrap();
+        let empty = pipeline.process(input(0)).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.dataframe().column("a_cusum_alarm").is_ok());
+        let single = pipeline.process(input(1)).unwrap();
+        assert_eq!(single.len(), 1);
+        for column in ["a", "a_ewma", "a_cusum_pos"] {
+            assert_eq!(single.dataframe().column(column).unwrap().null_count(), 1);
+        }
+
+        let skipping = Pipeline::from_config(config("skip")).unwrap();
+        let skipped = skipping.process(input(0)).unwrap();
+        assert_eq!(skipped.get_tag(RUN_TAG_SKIPPED), Some("empty input"));
+        assert_eq!(skipped.feature_columns(), ["a"]);
+        let (_, context) = skipping
+            .process_with_context(input(0), ExecutionContext::new())
+            .unwrap();
+        assert!(context.get_metadata(RUN_TAG_SKIPPED).is_some());
+        let single = skipping.process(input(1)).unwrap();
+        assert!(single.get_tag(RUN_TAG_SKIPPED).is_none());
+
+        let strict = Pipeline::from_config(config("error")).unwrap();
+        assert!(strict.process(input(0)).is_err());
+        assert!(strict.process(input(1)).is_ok());
+    }
+
+    #[test]
+    fn test_convert_timezone_clears_pipeline_zone() {
+        use crate::operations::ConvertTimezoneOperation;
+        use polars::prelude::*;
+
+        let time_series = Series::new("time".into(), &[1704110400000i64, 1704114000000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("a".into(), &[1.0, 2.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_time_zone("Europe/Berlin").unwrap();
+        pipeline.add_operation(Box::new(
+            ConvertTimezoneOperation::new("Europe/Berlin", "America/New_York").unwrap(),
+        ));
+
+        // The output holds New York wall-clock times, not UTC instants of a zone
+        let result = pipeline.process(data.clone()).unwrap();
+        assert_eq!(result.time_zone(), None);
+        let mut chunks = pipeline.process_stream(vec![data]).unwrap();
+        assert_eq!(chunks.next().unwrap().unwrap().time_zone(), None);
+    }
+
+    #[test]
+    fn test_lazy_matches_eager_with_time_zone() {
+        use crate::config::AggMethod;
+        use crate::operations::ResampleOperation;
+        use polars::prelude::*;
+
+        // Three days of 6-hourly samples from 2024-01-01 00:00 UTC
+        let times: Vec<i64> = (0..12).map(|i| 1704067200000 + i * 21_600_000).collect();
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("a".into(), (0..12).map(f64::from).collect::<Vec<_>>()).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_time_zone("Asia/Tokyo").unwrap();
+        pipeline.add_operation(Box::new(
+            ResampleOperation::new("1d", AggMethod::Mean, None).unwrap(),
+        ));
+
+        // Days start at Tokyo midnight, so the samples span four of them
+        let eager = pipeline.process(data.clone()).unwrap();
+        let lazy = pipeline.process_lazy(data).unwrap();
+        assert_eq!(eager.len(), 4);
+        assert_eq!(lazy.time_zone(), Some("Asia/Tokyo"));
+        assert!(lazy.dataframe().equals_missing(eager.dataframe()));
+    }
 }
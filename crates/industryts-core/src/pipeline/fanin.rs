@@ -0,0 +1,292 @@
+//! Concurrent fan-in from many asynchronous sources
+//!
+//! `FanIn` reads a set of named sources concurrently (e.g. one per asset of a
+//! historian API), runs each result through its own pipeline and stacks the
+//! outputs into one dataset grouped by a source column. At most
+//! `max_concurrency` sources are in flight at once. A source that fails to read,
+//! fails its pipeline or panics is reported in `FanInReport::failures` without
+//! affecting the others. All tasks are owned by the run: dropping the
+//! `FanIn::run` future aborts the sources still in flight.
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use async_trait::async_trait;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+/// Source of time series read asynchronously
+#[async_trait]
+pub trait AsyncSource: Send + Sync {
+    /// Read the data of this source
+    async fn read(&self) -> Result<TimeSeriesData>;
+}
+
+#[async_trait]
+impl AsyncSource for TimeSeriesData {
+    async fn read(&self) -> Result<TimeSeriesData> {
+        Ok(self.clone())
+    }
+}
+
+struct Branch {
+    name: String,
+    source: Arc<dyn AsyncSource>,
+    pipeline: Option<Arc<Pipeline>>,
+}
+
+/// Outcome of a fan-in run
+#[derive(Default)]
+pub struct FanInReport {
+    /// Stacked outputs of the successful sources, `None` if none succeeded
+    pub data: Option<TimeSeriesData>,
+    /// Output rows per successful source
+    pub rows: BTreeMap<String, usize>,
+    /// Errors of the failed sources
+    pub failures: BTreeMap<String, IndustrytsError>,
+}
+
+/// Sources read concurrently and merged into one dataset
+pub struct FanIn {
+    branches: Vec<Branch>,
+    max_concurrency: usize,
+    source_column: String,
+}
+
+impl Default for FanIn {
+    fn default() -> Self {
+        Self {
+            branches: Vec::new(),
+            max_concurrency: 8,
+            source_column: "source".to_string(),
+        }
+    }
+}
+
+impl FanIn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read at most `max_concurrency` sources at once (default 8)
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Name of the column holding the source name (default "source")
+    pub fn with_source_column(mut self, name: &str) -> Self {
+        self.source_column = name.to_string();
+        self
+    }
+
+    /// Add the source `name`, processed by `pipeline` if given
+    ///
+    /// A pipeline can be shared between sources through the `Arc`.
+    pub fn add_source(
+        &mut self,
+        name: &str,
+        source: Arc<dyn AsyncSource>,
+        pipeline: Option<Arc<Pipeline>>,
+    ) -> Result<()> {
+        if self.branches.iter().any(|b| b.name == name) {
+            return Err(IndustrytsError::ConfigError(format!(
+                "source '{}' is already part of the fan-in",
+                name
+            )));
+        }
+        self.branches.push(Branch {
+            name: name.to_string(),
+            source,
+            pipeline,
+        });
+        Ok(())
+    }
+
+    /// Read and process every source, then merge the successful outputs
+    ///
+    /// Must be called within a tokio runtime. Outputs are stacked in the order the
+    /// sources were added, each tagged with its name in the source column, which is
+    /// added in front of the group columns of the outputs. Outputs whose schema
+    /// differs from the first successful one are reported as failures.
+    pub async fn run(&self) -> Result<FanInReport> {
+        let mut results: Vec<Option<Result<TimeSeriesData>>> =
+            self.branches.iter().map(|_| None).collect();
+        let mut tasks = JoinSet::new();
+        let mut running = HashMap::new();
+        let mut pending = self.branches.iter().enumerate();
+        loop {
+            while tasks.len() < self.max_concurrency
+                && let Some((index, branch)) = pending.next()
+            {
+                let source = branch.source.clone();
+                let pipeline = branch.pipeline.clone();
+                let handle = tasks.spawn(async move {
+                    let data = source.read().await?;
+                    match pipeline {
+                        Some(pipeline) => pipeline.process_async(data).await,
+                        None => Ok(data),
+                    }
+                });
+                running.insert(handle.id(), index);
+            }
+            let (id, result) = match tasks.join_next_with_id().await {
+                Some(Ok((id, result))) => (id, result),
+                Some(Err(e)) => (
+                    e.id(),
+                    Err(IndustrytsError::OperationError(format!(
+                        "source task failed: {}",
+                        e
+                    ))),
+                ),
+                None => break,
+            };
+            let index = running.remove(&id).expect("every task is registered");
+            results[index] = Some(result);
+        }
+        self.merge(results)
+    }
+
+    fn merge(&self, results: Vec<Option<Result<TimeSeriesData>>>) -> Result<FanInReport> {
+        let mut report = FanInReport::default();
+        let mut merged: Option<(DataFrame, String, Vec<String>)> = None;
+        for (branch, result) in self.branches.iter().zip(results) {
+            let tagged = result
+                .expect("every source is joined")
+                .and_then(|data| self.tag(&branch.name, data))
+                .and_then(|(df, time_col, groups)| match &mut merged {
+                    None => {
+                        merged = Some((df.clone(), time_col, groups));
+                        Ok(df.height())
+                    }
+                    Some((stacked, stacked_time, _)) => {
+                        if *stacked_time != time_col || stacked.schema() != df.schema() {
+                            return Err(IndustrytsError::ValidationError(format!(
+                                "output schema of source '{}' differs from the other sources",
+                                branch.name
+                            )));
+                        }
+                        stacked.vstack_mut(&df)?;
+                        Ok(df.height())
+                    }
+                });
+            match tagged {
+                Ok(rows) => {
+                    report.rows.insert(branch.name.clone(), rows);
+                }
+                Err(e) => {
+                    report.failures.insert(branch.name.clone(), e);
+                }
+            }
+        }
+
+        if let Some((mut df, time_col, groups)) = merged {
+            df.as_single_chunk_par();
+            let data = TimeSeriesData::new(df, Some(&time_col))?.with_group_columns(&groups)?;
+            report.data = Some(data);
+        }
+        Ok(report)
+    }
+
+    /// Frame of `data` with the source column, its time column and group columns
+    fn tag(&self, name: &str, data: TimeSeriesData) -> Result<(DataFrame, String, Vec<String>)> {
+        let time_col = data.time_column().to_string();
+        let mut groups = vec![self.source_column.clone()];
+        groups.extend(data.group_columns().iter().cloned());
+
+        let mut df = data.into_dataframe();
+        if df.column(&self.source_column).is_ok() {
+            return Err(IndustrytsError::ValidationError(format!(
+                "output of source '{}' already has a column '{}'",
+                name, self.source_column
+            )));
+        }
+        let height = df.height();
+        df.with_column(Series::new(
+            self.source_column.as_str().into(),
+            vec![name; height],
+        ))?;
+        Ok((df, time_col, groups))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::DifferenceOperation;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Source tracking how many reads are in flight
+    struct Probe {
+        data: TimeSeriesData,
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AsyncSource for Probe {
+        async fn read(&self) -> Result<TimeSeriesData> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(self.data.clone())
+        }
+    }
+
+    struct Broken;
+
+    #[async_trait]
+    impl AsyncSource for Broken {
+        async fn read(&self) -> Result<TimeSeriesData> {
+            Err(IndustrytsError::OperationError("historian timeout".into()))
+        }
+    }
+
+    #[test]
+    fn test_fan_in_bounds_concurrency_and_isolates_failures() {
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 3.0, 6.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut diff = Pipeline::new();
+        diff.add_operation(Box::new(DifferenceOperation::new(1, None)));
+        let diff = Arc::new(diff);
+
+        let (in_flight, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut fan_in = FanIn::new().with_max_concurrency(2);
+        for asset in ["pump_1", "pump_2", "pump_3", "pump_4"] {
+            let probe = Probe {
+                data: data.clone(),
+                in_flight: in_flight.clone(),
+                peak: peak.clone(),
+            };
+            fan_in
+                .add_source(asset, Arc::new(probe), Some(diff.clone()))
+                .unwrap();
+        }
+        fan_in.add_source("broken", Arc::new(Broken), None).unwrap();
+        assert!(fan_in.add_source("broken", Arc::new(Broken), None).is_err());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let report = runtime.block_on(fan_in.run()).unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(report.rows.len(), 4);
+        assert!(report.failures.contains_key("broken"));
+        let merged = report.data.unwrap();
+        assert_eq!(merged.len(), 12);
+        assert_eq!(merged.group_columns(), ["source"]);
+        assert_eq!(merged.feature_columns(), ["value", "value_diff_1"]);
+    }
+}
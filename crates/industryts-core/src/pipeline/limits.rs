@@ -0,0 +1,160 @@
+//! Per-run resource limits
+//!
+//! Limits are checked after every step: a run that exceeds its wall-clock
+//! timeout, produces too many rows or holds too much data (main table plus
+//! secondary outputs, as estimated by Polars) stops with
+//! `IndustrytsError::LimitExceeded`. A single long step is not interrupted; the
+//! timeout takes effect once it returns.
+
+use crate::config::SettingsConfig;
+use crate::core::{OutputStore, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::utils::{parse_duration, parse_size};
+use std::time::{Duration, Instant};
+
+/// Resource limits applied to each pipeline run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    /// Maximum wall-clock time of a run
+    pub timeout: Option<Duration>,
+    /// Maximum number of rows of the main table
+    pub max_rows: Option<usize>,
+    /// Maximum estimated memory of the main table and secondary outputs, in bytes
+    pub max_memory_bytes: Option<usize>,
+}
+
+impl RunLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_rows(mut self, rows: usize) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    /// Build limits from the `[settings]` table
+    pub fn from_config(config: &SettingsConfig) -> Result<Self> {
+        Ok(Self {
+            timeout: config.timeout.as_deref().map(parse_duration).transpose()?,
+            max_rows: config.max_output_rows,
+            max_memory_bytes: config.max_memory.as_deref().map(parse_size).transpose()?,
+        })
+    }
+
+    /// Whether any limit is set
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fail if the run has exceeded a limit after the step `step`
+    pub(crate) fn check(
+        &self,
+        step: &str,
+        started: Instant,
+        data: &TimeSeriesData,
+        outputs: Option<&OutputStore>,
+    ) -> Result<()> {
+        if let Some(timeout) = self.timeout {
+            let elapsed = started.elapsed();
+            if elapsed > timeout {
+                return Err(IndustrytsError::LimitExceeded(format!(
+                    "run took {:?} after {}, over the timeout of {:?}",
+                    elapsed, step, timeout
+                )));
+            }
+        }
+        if let Some(max_rows) = self.max_rows
+            && data.len() > max_rows
+        {
+            return Err(IndustrytsError::LimitExceeded(format!(
+                "{} produced {} rows, over the limit of {}",
+                step,
+                data.len(),
+                max_rows
+            )));
+        }
+        if let Some(max_bytes) = self.max_memory_bytes {
+            let bytes =
+                data.dataframe().estimated_size() + outputs.map_or(0, OutputStore::estimated_size);
+            if bytes > max_bytes {
+                return Err(IndustrytsError::LimitExceeded(format!(
+                    "data held after {} is about {} bytes, over the limit of {} bytes",
+                    step, bytes, max_bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PipelineConfig;
+    use crate::operations::LagOperation;
+    use crate::pipeline::Pipeline;
+    use polars::prelude::{DataFrame, DataType, NamedFrom, Series, TimeUnit};
+
+    #[test]
+    fn test_limits_abort_run() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "limited"
+
+            [settings]
+            timeout = "30s"
+            max_output_rows = 2
+            max_memory = "1MB"
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            "#,
+        )
+        .unwrap();
+        let limits = RunLimits::from_config(&config.settings).unwrap();
+        assert_eq!(
+            limits,
+            RunLimits::new()
+                .with_timeout(Duration::from_secs(30))
+                .with_max_rows(2)
+                .with_max_memory(1_000_000)
+        );
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 2.0, 3.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.set_limits(limits);
+        let err = pipeline.process(data.clone()).err().unwrap();
+        assert!(matches!(err, IndustrytsError::LimitExceeded(_)), "{}", err);
+
+        pipeline.set_limits(RunLimits::new().with_max_memory(16));
+        let err = pipeline.process(data.clone()).err().unwrap();
+        assert!(matches!(err, IndustrytsError::LimitExceeded(_)), "{}", err);
+
+        pipeline.set_limits(RunLimits::new().with_max_rows(3));
+        assert_eq!(pipeline.process(data).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,235 @@
+//! Dependency graph of derived columns
+//!
+//! `ColumnLineage` parses the formulas of `derive` steps and records which
+//! columns each derived column is computed from, so engineers can trace the raw
+//! sensors feeding a KPI (`raw_inputs`) or the features affected by a faulty tag
+//! (`downstream`). `to_dot` renders the graph for Graphviz, raw tags as boxes and
+//! derived columns as ellipses.
+//!
+//! Formulas are read in pipeline order. A column read before it is first
+//! assigned is raw; one assigned more than once depends on the inputs of all its
+//! formulas. Stage steps are read after the main steps, with columns named as
+//! within their stage.
+
+use crate::config::{OperationConfig, PipelineConfig};
+use crate::error::Result;
+use crate::operations::derive::Formula;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Dependencies between raw tags and derived columns
+#[derive(Debug, Clone, Default)]
+pub struct ColumnLineage {
+    /// Columns in order of first appearance
+    columns: Vec<String>,
+    /// Direct inputs of each derived column
+    inputs: BTreeMap<String, Vec<String>>,
+    /// Formulas assigning each derived column, in order
+    formulas: BTreeMap<String, Vec<String>>,
+    /// Columns read before they were first assigned
+    raw: BTreeSet<String>,
+}
+
+impl ColumnLineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lineage of formulas such as `"power = voltage * current"`, in order
+    pub fn from_formulas(formulas: &[String]) -> Result<Self> {
+        let mut lineage = Self::new();
+        for formula in formulas {
+            lineage.add_formula(formula)?;
+        }
+        Ok(lineage)
+    }
+
+    /// Lineage of the `derive` steps of a pipeline, including nested steps
+    pub fn from_config(config: &PipelineConfig) -> Result<Self> {
+        let mut lineage = Self::new();
+        lineage.add_steps(config.operations.iter().map(|s| &s.operation))?;
+        for stage in &config.stages {
+            lineage.add_steps(stage.operations.iter().map(|s| &s.operation))?;
+        }
+        Ok(lineage)
+    }
+
+    fn add_steps<'a>(
+        &mut self,
+        steps: impl IntoIterator<Item = &'a OperationConfig>,
+    ) -> Result<()> {
+        for step in steps {
+            match step {
+                OperationConfig::Derive { exprs, .. } => {
+                    for formula in exprs {
+                        self.add_formula(formula)?;
+                    }
+                }
+                OperationConfig::PerGroup { operations, .. } => self.add_steps(operations)?,
+                OperationConfig::Recipe { recipes } => {
+                    for recipe in recipes {
+                        self.add_steps(&recipe.operations)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a formula assigning a column
+    pub fn add_formula(&mut self, formula: &str) -> Result<()> {
+        let parsed = Formula::parse(formula)?;
+        let target = parsed.target().to_string();
+        let derived = self.inputs.contains_key(&target);
+        let mut inputs = self.inputs.remove(&target).unwrap_or_default();
+        for input in parsed.inputs() {
+            self.note(&input);
+            let assigned = self.inputs.contains_key(&input) || (input == target && derived);
+            if !assigned {
+                self.raw.insert(input.clone());
+            }
+            if input != target && !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+        self.note(&target);
+        self.inputs.insert(target.clone(), inputs);
+        self.formulas
+            .entry(target)
+            .or_default()
+            .push(formula.trim().to_string());
+        Ok(())
+    }
+
+    fn note(&mut self, column: &str) {
+        if !self.columns.iter().any(|c| c == column) {
+            self.columns.push(column.to_string());
+        }
+    }
+
+    /// Columns read before they were assigned (raw tags), in order of appearance
+    pub fn raw_columns(&self) -> Vec<String> {
+        self.in_order(|c| self.raw.contains(c))
+    }
+
+    /// Columns assigned by a formula, in order of appearance
+    pub fn derived_columns(&self) -> Vec<String> {
+        self.in_order(|c| self.inputs.contains_key(c))
+    }
+
+    /// Columns a derived column is directly computed from
+    pub fn inputs(&self, column: &str) -> &[String] {
+        self.inputs.get(column).map_or(&[], Vec::as_slice)
+    }
+
+    /// Formulas assigning `column`, in order
+    pub fn formulas(&self, column: &str) -> &[String] {
+        self.formulas.get(column).map_or(&[], Vec::as_slice)
+    }
+
+    /// Columns `column` is computed from, directly or through other derived columns
+    pub fn upstream(&self, column: &str) -> Vec<String> {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![column];
+        while let Some(current) = pending.pop() {
+            for input in self.inputs(current) {
+                if seen.insert(input.as_str()) {
+                    pending.push(input);
+                }
+            }
+        }
+        self.in_order(|c| seen.contains(c))
+    }
+
+    /// Raw tags feeding `column`; a raw column feeds itself
+    pub fn raw_inputs(&self, column: &str) -> Vec<String> {
+        let upstream = self.upstream(column);
+        self.in_order(|c| self.raw.contains(c) && (c == column || upstream.iter().any(|u| u == c)))
+    }
+
+    /// Derived columns computed from `column`, directly or indirectly
+    pub fn downstream(&self, column: &str) -> Vec<String> {
+        self.in_order(|c| c != column && self.upstream(c).iter().any(|u| u == column))
+    }
+
+    fn in_order(&self, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        self.columns.iter().filter(|c| keep(c)).cloned().collect()
+    }
+
+    /// Graphviz rendering, edges pointing from inputs to derived columns
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph lineage {\n    rankdir=LR;\n");
+        for column in &self.columns {
+            let shape = if self.inputs.contains_key(column) {
+                "ellipse"
+            } else {
+                "box"
+            };
+            let _ = write!(dot, "    {} [shape={}", quote(column), shape);
+            if let Some(formulas) = self.formulas.get(column) {
+                let _ = write!(dot, ", tooltip={}", quote(&formulas.join("\n")));
+            }
+            dot.push_str("];\n");
+        }
+        for column in &self.columns {
+            for input in self.inputs(column) {
+                let _ = writeln!(dot, "    {} -> {};", quote(input), quote(column));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Graphviz quoted identifier
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lineage_traces_kpi_to_raw_tags() {
+        let formulas = [
+            "power = voltage * current",
+            "TI101.PV = TI101.PV * 1.8 + 32",
+            "efficiency = power / max(fuel_flow, 0.1)",
+            "kpi = efficiency * 100 - `TI101.PV` / 10",
+        ]
+        .map(String::from);
+        let lineage = ColumnLineage::from_formulas(&formulas).unwrap();
+
+        assert_eq!(
+            lineage.raw_columns(),
+            ["voltage", "current", "TI101.PV", "fuel_flow"]
+        );
+        assert_eq!(
+            lineage.derived_columns(),
+            ["power", "TI101.PV", "efficiency", "kpi"]
+        );
+        assert_eq!(lineage.inputs("kpi"), ["efficiency", "TI101.PV"]);
+        assert_eq!(
+            lineage.raw_inputs("kpi"),
+            ["voltage", "current", "TI101.PV", "fuel_flow"]
+        );
+        assert_eq!(lineage.raw_inputs("power"), ["voltage", "current"]);
+        assert_eq!(
+            lineage.downstream("current"),
+            ["power", "efficiency", "kpi"]
+        );
+        assert!(lineage.upstream("voltage").is_empty());
+
+        let dot = lineage.to_dot();
+        assert!(dot.contains("\"voltage\" [shape=box];"));
+        assert!(dot.contains("\"power\" -> \"efficiency\";"));
+        assert!(dot.contains("tooltip=\"power = voltage * current\""));
+    }
+}
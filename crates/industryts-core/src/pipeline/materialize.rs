@@ -0,0 +1,205 @@
+//! Materialization of the result of a configured run
+//!
+//! `Pipeline::run` returns the output in memory, which is fine for a report but
+//! not for a backfill producing more rows than fit in RAM twice. The
+//! materialization policy of a pipeline lets the caller choose how
+//! `Pipeline::run_materialized` hands back the result: in memory, spilled to a
+//! temporary Arrow IPC file read back lazily, or written to the `[sink]` file
+//! only, returning the execution report.
+
+use crate::core::{ExecutionReport, TimeSeriesData};
+use crate::error::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How `Pipeline::run_materialized` returns the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Materialization {
+    /// Return the output in memory
+    #[default]
+    Memory,
+    /// Spill the output to a temporary file and return a lazy handle
+    Spill,
+    /// Write the output to the `[sink]` file and return only the report
+    Sink,
+}
+
+/// Result of `Pipeline::run_materialized`
+pub enum RunOutput {
+    Memory(TimeSeriesData),
+    Spilled(SpilledResult),
+    Written(ExecutionReport),
+}
+
+impl RunOutput {
+    /// The output as a dataset, reading a spilled one back
+    ///
+    /// `None` if the output was written to the sink.
+    pub fn into_data(self) -> Result<Option<TimeSeriesData>> {
+        match self {
+            RunOutput::Memory(data) => Ok(Some(data)),
+            RunOutput::Spilled(spilled) => spilled.collect().map(Some),
+            RunOutput::Written(_) => Ok(None),
+        }
+    }
+}
+
+/// Counter distinguishing spill files of one process
+static SPILL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Output spilled to a temporary Arrow IPC file
+///
+/// The file is removed when the handle is dropped, unless it was kept with
+/// `SpilledResult::keep`.
+pub struct SpilledResult {
+    path: PathBuf,
+    rows: usize,
+    time_column: String,
+    group_columns: Vec<String>,
+    tags: HashMap<String, String>,
+    keep: bool,
+}
+
+impl SpilledResult {
+    /// Spill `data` to a new file in `dir`
+    pub fn write(data: &TimeSeriesData, dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "industryts_spill_{}_{}.arrow",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = Self {
+            path,
+            rows: data.len(),
+            time_column: data.time_column().to_string(),
+            group_columns: data.group_columns().to_vec(),
+            tags: data.metadata().tags.clone(),
+            keep: false,
+        };
+        IpcWriter::new(File::create(&result.path)?).finish(&mut data.dataframe().clone())?;
+        Ok(result)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of rows spilled
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    pub fn time_column(&self) -> &str {
+        &self.time_column
+    }
+
+    /// Lazy scan of the spilled output; valid while the handle lives
+    pub fn lazy(&self) -> Result<LazyFrame> {
+        Ok(LazyFrame::scan_ipc(
+            PlPath::Local(self.path.as_path().into()),
+            ScanArgsIpc::default(),
+        )?)
+    }
+
+    /// Read the spilled output back into memory
+    pub fn collect(&self) -> Result<TimeSeriesData> {
+        let df = IpcReader::new(File::open(&self.path)?).finish()?;
+        let mut data = TimeSeriesData::new(df, Some(&self.time_column))?
+            .with_group_columns(&self.group_columns)?;
+        data.metadata_mut().tags = self.tags.clone();
+        Ok(data)
+    }
+
+    /// Keep the file after the handle is dropped and return its path
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.path.clone()
+    }
+}
+
+impl Drop for SpilledResult {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PipelineConfig;
+    use crate::pipeline::Pipeline;
+
+    #[test]
+    fn test_spill_and_sink_materialization() {
+        let dir =
+            std::env::temp_dir().join(format!("industryts_materialize_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("input.csv"),
+            "stamp,value\n1704067200,1.0\n1704067200,2.0\n1704067260,4.0\n",
+        )
+        .unwrap();
+        let output = dir.join("output.csv");
+        let pipeline = |materialize: &str| {
+            Pipeline::from_config(
+                PipelineConfig::from_toml_str(&format!(
+                    r#"
+                    [pipeline]
+                    name = "materialize"
+                    time_column = "stamp"
+
+                    [execution]
+                    materialize = "{materialize}"
+                    spill_dir = "{dir}/spill"
+
+                    [source]
+                    path = "{dir}/input.csv"
+                    epoch_unit = "s"
+
+                    [sink]
+                    path = "{output}"
+
+                    [[operations]]
+                    type = "deduplicate"
+                    "#,
+                    dir = dir.display(),
+                    output = output.display()
+                ))
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        let RunOutput::Spilled(spilled) = pipeline("spill").run_materialized().unwrap() else {
+            panic!("expected a spilled output");
+        };
+        let path = spilled.path().to_path_buf();
+        assert!(path.starts_with(dir.join("spill")));
+        assert_eq!(spilled.len(), 2);
+        let lazy = spilled.lazy().unwrap().collect().unwrap();
+        assert_eq!(lazy.height(), 2);
+        assert_eq!(spilled.collect().unwrap().time_column(), "stamp");
+        drop(spilled);
+        assert!(!path.exists());
+
+        let RunOutput::Written(report) = pipeline("sink").run_materialized().unwrap() else {
+            panic!("expected a report");
+        };
+        assert_eq!(report.pipeline.as_deref(), Some("materialize"));
+        assert_eq!((report.input_rows, report.output_rows), (3, 2));
+        assert!(output.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
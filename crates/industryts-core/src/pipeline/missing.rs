@@ -0,0 +1,306 @@
+//! Steps tolerating missing columns
+//!
+//! A pipeline written for a fleet meets assets that lack some sensors. Rather than
+//! maintaining one configuration per asset, a step can be configured with
+//! `missing_columns = "skip"` to run on the columns that are present (or not at
+//! all), or `missing_columns = "create_null"` to add the missing columns as nulls.
+//! Either way the affected columns are reported as warnings.
+
+use crate::config::{MissingColumns, OperationConfig};
+use crate::core::{ColumnSelector, NamingPolicy, OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::operations::HolidayCalendar;
+use crate::pipeline::Pipeline;
+use polars::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Configured step applying a `MissingColumns` policy to its required columns
+pub struct MissingColumnsStep {
+    config: OperationConfig,
+    policy: MissingColumns,
+    calendar: Option<Arc<HolidayCalendar>>,
+    /// The step built over its configured columns
+    operation: Box<dyn Operation>,
+    seed: Option<u64>,
+    naming: Option<NamingPolicy>,
+}
+
+/// How a step runs on a given input
+enum Plan {
+    /// All required columns are present
+    Run,
+    /// Run on the present columns of the selector
+    Narrow(Vec<String>),
+    /// Pass the data through unchanged
+    Skip,
+    /// Add the missing columns as nulls, then run
+    CreateNull,
+}
+
+impl MissingColumnsStep {
+    pub fn new(
+        config: &OperationConfig,
+        policy: MissingColumns,
+        calendar: Option<Arc<HolidayCalendar>>,
+    ) -> Result<Self> {
+        let operation = Pipeline::create_operation(config, calendar.as_ref())?;
+        Ok(Self {
+            config: config.clone(),
+            policy,
+            calendar,
+            operation,
+            seed: None,
+            naming: None,
+        })
+    }
+
+    /// Required columns absent from `columns`
+    fn missing(&self, columns: &[String], time_column: Option<&str>) -> Vec<String> {
+        self.operation
+            .required_columns()
+            .into_iter()
+            .filter(|c| Some(c.as_str()) != time_column && !columns.contains(c))
+            .collect()
+    }
+
+    fn plan(&self, missing: &[String]) -> Plan {
+        if missing.is_empty() {
+            return Plan::Run;
+        }
+        match self.policy {
+            MissingColumns::Error => Plan::Run,
+            MissingColumns::CreateNull => Plan::CreateNull,
+            MissingColumns::Skip => match self.config.columns() {
+                // Narrowing only helps if the selector lists every missing column
+                Some(ColumnSelector::Names(names)) if missing.iter().all(|c| names.contains(c)) => {
+                    let present: Vec<String> = names
+                        .iter()
+                        .filter(|c| !missing.contains(c))
+                        .cloned()
+                        .collect();
+                    if present.is_empty() {
+                        Plan::Skip
+                    } else {
+                        Plan::Narrow(present)
+                    }
+                }
+                _ => Plan::Skip,
+            },
+        }
+    }
+
+    /// Build the step for `columns`
+    fn build(&self, columns: Vec<String>) -> Result<Box<dyn Operation>> {
+        let mut config = self.config.clone();
+        config.set_columns(Some(ColumnSelector::Names(columns)));
+        let mut operation = Pipeline::create_operation(&config, self.calendar.as_ref())?;
+        if let Some(seed) = self.seed {
+            operation.set_seed(seed);
+        }
+        if let Some(naming) = &self.naming {
+            operation.set_naming(naming);
+        }
+        Ok(operation)
+    }
+
+    /// `data` with `missing` added as null Float64 feature columns
+    fn with_nulls(data: TimeSeriesData, missing: &[String]) -> Result<TimeSeriesData> {
+        let mut df = data.dataframe().clone();
+        let mut metadata = data.metadata().clone();
+        for column in missing {
+            df.with_column(Series::full_null(
+                column.as_str().into(),
+                df.height(),
+                &DataType::Float64,
+            ))?;
+            metadata.feature_columns.push(column.clone());
+        }
+        TimeSeriesData::with_metadata(df, metadata)
+    }
+
+    fn run(&self, data: TimeSeriesData, mut ctx: Option<&mut OpContext>) -> Result<TimeSeriesData> {
+        let missing = self.missing(data.feature_columns(), Some(data.time_column()));
+        let plan = self.plan(&missing);
+        let action = match plan {
+            Plan::Run => None,
+            Plan::Narrow(_) => Some("running without"),
+            Plan::Skip => Some("skipped, missing"),
+            Plan::CreateNull => Some("created null column(s)"),
+        };
+        if let (Some(ctx), Some(action)) = (ctx.as_deref_mut(), action) {
+            ctx.warn(format!("{} {} {}", self.name(), action, missing.join(", ")));
+        }
+
+        let (narrowed, data) = match plan {
+            Plan::Run => (None, data),
+            Plan::Narrow(present) => (Some(self.build(present)?), data),
+            Plan::Skip => return Ok(data),
+            Plan::CreateNull => (None, Self::with_nulls(data, &missing)?),
+        };
+        let operation = narrowed.as_deref().unwrap_or(self.operation.as_ref());
+        match ctx {
+            Some(ctx) => operation.execute_with_context(data, ctx),
+            None => operation.execute(data),
+        }
+    }
+}
+
+impl Operation for MissingColumnsStep {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.run(data, None)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.run(data, Some(ctx))
+    }
+
+    fn name(&self) -> &str {
+        self.operation.name()
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.operation.set_seed(seed);
+        self.seed = Some(seed);
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.operation.set_naming(naming);
+        self.naming = Some(naming.clone());
+    }
+
+    fn required_columns(&self) -> Vec<String> {
+        match self.policy {
+            MissingColumns::Error => self.operation.required_columns(),
+            MissingColumns::Skip | MissingColumns::CreateNull => Vec::new(),
+        }
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        let missing = self.missing(feature_columns, None);
+        match self.plan(&missing) {
+            Plan::Run => self.operation.added_columns(feature_columns),
+            Plan::Narrow(present) => self
+                .build(present)
+                .map(|operation| operation.added_columns(feature_columns))
+                .unwrap_or_default(),
+            Plan::Skip => Vec::new(),
+            Plan::CreateNull => {
+                let mut columns = feature_columns.to_vec();
+                columns.extend(missing.iter().cloned());
+                let mut added = missing;
+                added.extend(self.operation.added_columns(&columns));
+                added
+            }
+        }
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.operation.removes_rows()
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        self.operation.row_bounds(input_rows)
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.operation.reorders_rows()
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        self.operation.manages_time_zone()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.operation.warmup()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipelineConfig;
+    use crate::core::ExecutionContext;
+    use crate::pipeline::Pipeline;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_missing_column_policies() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "fleet"
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            columns = ["flow", "vibration"]
+            missing_columns = "skip"
+
+            [[operations]]
+            type = "difference"
+            columns = ["pressure"]
+            missing_columns = "skip"
+
+            [[operations]]
+            type = "fill_null"
+            method = "zero"
+            columns = ["flow", "humidity"]
+            missing_columns = "create_null"
+            "#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::from_config(config).unwrap();
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("flow", [1.0, 2.0, 3.0])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            pipeline.propagate_schema(&data).unwrap(),
+            ["flow", "flow_lag_1", "humidity"]
+        );
+        let (result, context) = pipeline
+            .process_with_context(data, ExecutionContext::new())
+            .unwrap();
+        assert_eq!(result.feature_columns(), ["flow", "flow_lag_1", "humidity"]);
+        let humidity = result.dataframe().column("humidity").unwrap();
+        assert_eq!(humidity.null_count(), 0);
+        let warnings: Vec<&String> = context.metrics().iter().flat_map(|m| &m.warnings).collect();
+        assert_eq!(
+            warnings,
+            [
+                "lag running without vibration",
+                "difference skipped, missing pressure",
+                "fill_null created null column(s) humidity",
+            ]
+        );
+
+        // Without a policy, the missing column fails the run
+        let strict = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "strict"
+
+            [[operations]]
+            type = "difference"
+            columns = ["pressure"]
+            "#,
+        )
+        .unwrap();
+        let data = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("flow", [1.0, 2.0, 3.0])
+            .build()
+            .unwrap();
+        assert!(
+            Pipeline::from_config(strict)
+                .unwrap()
+                .process(data)
+                .is_err()
+        );
+    }
+}
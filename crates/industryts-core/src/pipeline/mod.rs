@@ -1,14 +1,76 @@
 //! Pipeline execution engine
 //!
 //! This module provides the pipeline infrastructure for chaining and executing operations:
+//! - `audit`: Sampled trail of rows dropped or modified by each step
+//! - `backfill`: Chunked execution over a historical time range
 //! - `builder`: Fluent API for building pipelines
+//! - `cache`: Local read cache for remote range sources
+//! - `chunked`: Chunk-by-chunk execution with state carried between chunks
+//! - `dag`: Branching into named stages with explicit dependencies
 //! - `executor`: Pipeline execution engine
+//! - `fanin`: Concurrent reads of many async sources merged into one dataset
+//! - `incremental`: Recomputing only the tail affected by appended rows
+//! - `missing`: Steps running on assets that lack some of their columns
+//! - `limits`: Per-run timeout, row and memory limits
+//! - `lineage`: Dependency graph between raw tags and derived columns
+//! - `materialize`: Returning run outputs in memory, spilled to disk or only written
+//! - `realtime`: Low-latency scoring of single samples against maintained step state
 //! - `registry`: Operation registration and discovery
+//! - `selected`: Configured steps whose columns are resolved from a selector
+//! - `sink`: Backpressure-aware destinations for streamed batches
+//! - `set`: Several named pipelines run in dependency order
+//! - `stats`: Feature statistics recorded across runs for drift monitoring
+//! - `stream`: Watermark-driven streaming execution with late-data handling
+//! - `templates`: Built-in parameterizable pipeline templates
+//! - `typed`: Type-state builder checking step order at compile time
+//! - `warm`: Step state carried between scheduled incremental runs
+//! - `watchdog`: Stall detection of the sources of a stream
 
+pub mod audit;
+pub mod backfill;
 pub mod builder;
+pub mod cache;
+pub mod chunked;
+pub mod dag;
 pub mod executor;
+pub mod fanin;
+pub mod incremental;
+pub mod limits;
+pub mod lineage;
+pub mod materialize;
+pub mod missing;
+pub mod realtime;
 pub mod registry;
+pub mod selected;
+pub mod set;
+pub mod sink;
+pub mod stats;
+pub mod stream;
+pub mod templates;
+pub mod typed;
+pub mod warm;
+pub mod watchdog;
 
+pub use audit::RowAudit;
+pub use backfill::{BackfillReport, RangeSource};
 pub use builder::PipelineBuilder;
+pub use cache::{CacheStats, CachedSource};
+pub use chunked::ChunkStream;
+pub use dag::{DagOperation, Stage};
 pub use executor::Pipeline;
-pub use registry::OperationRegistry;
+pub use fanin::{AsyncSource, FanIn, FanInReport};
+pub use limits::RunLimits;
+pub use lineage::ColumnLineage;
+pub use materialize::{Materialization, RunOutput, SpilledResult};
+pub use missing::MissingColumnsStep;
+pub use realtime::{ScoredRow, Scorer, ScorerStats};
+pub use registry::{OperationInfo, OperationRegistry, ParameterInfo, SharedRegistry};
+pub use selected::SelectedColumns;
+pub use set::{DataCatalog, PipelineSet};
+pub use sink::{ChannelSink, StreamSink};
+pub use stats::{FeatureStats, RunStats, StatsRegistry};
+pub use stream::{LatePolicy, StreamBatch, StreamProcessor};
+pub use templates::PipelineTemplate;
+pub use typed::TypedPipelineBuilder;
+pub use warm::RunState;
+pub use watchdog::{SourceWatchdog, WatchdogEvent};
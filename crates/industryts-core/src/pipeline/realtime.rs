@@ -0,0 +1,273 @@
+//! Low-latency scoring of single samples
+//!
+//! `Scorer` keeps the step states of `Pipeline::process_stream` alive between
+//! calls, so an edge controller can prime a pipeline on recent history once and
+//! then score each new sample (or a small batch) against the maintained rolling
+//! buffers and running statistics instead of re-running a batch job. A call costs
+//! the steps' work on the new rows plus their carried warm-up rows only.
+//!
+//! Samples must arrive in time order; there is no watermark (see
+//! `StreamProcessor` for out-of-order data). Steps that hold rows back, such as
+//! resampling, release them once a later sample closes their bucket. Every call
+//! is timed against an optional latency budget and summarized in `ScorerStats`.
+
+use crate::core::data::TimeSeriesMetadata;
+use crate::core::{StatefulOperation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use crate::pipeline::chunked::{flush_states, process_chunk, state_of};
+use polars::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Latency of the scoring calls of a `Scorer`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScorerStats {
+    /// Scoring calls made
+    pub calls: u64,
+    /// Calls that exceeded the latency budget
+    pub over_budget: u64,
+    pub last: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl ScorerStats {
+    /// Mean latency of a scoring call
+    pub fn mean(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.calls as u32
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration, budget: Option<Duration>) {
+        self.calls += 1;
+        self.last = elapsed;
+        self.max = self.max.max(elapsed);
+        self.total += elapsed;
+        if budget.is_some_and(|budget| elapsed > budget) {
+            self.over_budget += 1;
+        }
+    }
+}
+
+/// Output sample of `Scorer::score_row`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredRow {
+    pub time_ms: i64,
+    /// Numeric columns in output order, nulls as `None`
+    pub values: Vec<(String, Option<f64>)>,
+}
+
+impl ScoredRow {
+    /// Value of `column`, `None` if it is null or not numeric
+    pub fn get(&self, column: &str) -> Option<f64> {
+        self.values
+            .iter()
+            .find(|(name, _)| name == column)
+            .and_then(|(_, value)| *value)
+    }
+}
+
+/// Pipeline scoring samples one at a time against maintained step state
+pub struct Scorer<'a> {
+    pipeline: &'a Pipeline,
+    states: Vec<Box<dyn StatefulOperation + 'a>>,
+    /// Input schema learned from the first rows, used by `score_row`
+    input: Option<(Schema, TimeSeriesMetadata)>,
+    budget: Option<Duration>,
+    stats: ScorerStats,
+    strict: bool,
+}
+
+impl Pipeline {
+    /// Scorer keeping the state of every step between calls
+    ///
+    /// Fails if the pipeline has async steps.
+    pub fn scorer(&self) -> Result<Scorer<'_>> {
+        let states = self.sync_operations()?.into_iter().map(state_of).collect();
+        Ok(Scorer {
+            pipeline: self,
+            states,
+            input: None,
+            budget: None,
+            stats: ScorerStats::default(),
+            strict: false,
+        })
+    }
+}
+
+impl<'a> Scorer<'a> {
+    /// Count scoring calls slower than `budget` in `ScorerStats::over_budget`
+    pub fn with_latency_budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Fail calls that exceed the latency budget (their state updates are kept)
+    pub fn with_strict_budget(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Feed `history` through the steps to fill their buffers and statistics
+    ///
+    /// The output is discarded and the call is not counted in the statistics.
+    pub fn prime(&mut self, history: TimeSeriesData) -> Result<()> {
+        self.learn_schema(&history);
+        process_chunk(self.pipeline, &mut self.states, history)?;
+        Ok(())
+    }
+
+    /// Score new samples; returns the output rows they complete
+    pub fn score(&mut self, samples: TimeSeriesData) -> Result<TimeSeriesData> {
+        let start = Instant::now();
+        self.learn_schema(&samples);
+        let output = process_chunk(self.pipeline, &mut self.states, samples)?;
+        self.finish_call(start)?;
+        Ok(output)
+    }
+
+    /// Score one sample of numeric values at `time_ms` (Unix milliseconds)
+    ///
+    /// Columns are cast to the dtypes of the rows seen by `prime` or `score`, which
+    /// must come first; columns left out are null. Returns the output rows the
+    /// sample completes, usually one.
+    pub fn score_row(&mut self, time_ms: i64, values: &[(&str, f64)]) -> Result<Vec<ScoredRow>> {
+        let start = Instant::now();
+        let sample = self.row(time_ms, values)?;
+        let output = process_chunk(self.pipeline, &mut self.states, sample)?;
+        let rows = scored_rows(&output)?;
+        self.finish_call(start)?;
+        Ok(rows)
+    }
+
+    /// Latency of the scoring calls so far
+    pub fn stats(&self) -> &ScorerStats {
+        &self.stats
+    }
+
+    /// Output rows still held back by steps, e.g. the open resample bucket
+    pub fn finish(mut self) -> Result<Option<TimeSeriesData>> {
+        flush_states(self.pipeline, &mut self.states)
+    }
+
+    fn learn_schema(&mut self, data: &TimeSeriesData) {
+        if self.input.is_none() && !data.is_empty() {
+            let schema = data.dataframe().schema().as_ref().clone();
+            self.input = Some((schema, data.metadata().clone()));
+        }
+    }
+
+    /// One-row input at `time_ms` in the learned schema
+    fn row(&self, time_ms: i64, values: &[(&str, f64)]) -> Result<TimeSeriesData> {
+        let Some((schema, metadata)) = &self.input else {
+            return Err(IndustrytsError::InvalidOperation(
+                "prime the scorer before scoring single rows".to_string(),
+            ));
+        };
+        if let Some((name, _)) = values.iter().find(|(name, _)| schema.get(name).is_none()) {
+            return Err(IndustrytsError::ColumnNotFound(name.to_string()));
+        }
+        let time_col = metadata.time_column.as_str();
+        let mut columns = Vec::with_capacity(schema.len());
+        for (name, dtype) in schema.iter() {
+            let series = if name == time_col {
+                Series::new(name.clone(), [time_ms])
+                    .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+            } else {
+                let value = values.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+                Series::new(name.clone(), [value])
+            };
+            columns.push(series.cast(dtype)?.into());
+        }
+        TimeSeriesData::with_metadata(DataFrame::new(columns)?, metadata.clone())
+    }
+
+    fn finish_call(&mut self, start: Instant) -> Result<()> {
+        let elapsed = start.elapsed();
+        self.stats.record(elapsed, self.budget);
+        match self.budget {
+            Some(budget) if self.strict && elapsed > budget => {
+                Err(IndustrytsError::OperationError(format!(
+                    "scoring took {:?}, over the latency budget of {:?}",
+                    elapsed, budget
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Numeric values of every row of `data`
+fn scored_rows(data: &TimeSeriesData) -> Result<Vec<ScoredRow>> {
+    let times = data.timestamps_ms()?;
+    let time_col = data.time_column();
+    let mut columns = Vec::new();
+    for column in data.dataframe().get_columns() {
+        if column.name() != time_col && column.dtype().is_primitive_numeric() {
+            let values = column.cast(&DataType::Float64)?;
+            columns.push((column.name().to_string(), values));
+        }
+    }
+    let mut rows = Vec::with_capacity(data.len());
+    for (i, time_ms) in times.into_iter().enumerate() {
+        let values = columns
+            .iter()
+            .map(|(name, values)| Ok((name.clone(), values.f64()?.get(i))))
+            .collect::<Result<Vec<_>>>()?;
+        rows.push(ScoredRow {
+            time_ms: time_ms.unwrap_or_default(),
+            values,
+        });
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{LagOperation, StandardizeOperation};
+
+    #[test]
+    fn test_score_rows_match_batch_run() {
+        let times: Vec<i64> = (0..30).map(|i| i * 1_000).collect();
+        let flow: Vec<f64> = (0..30).map(|i| (i * 7 % 10) as f64).collect();
+        let time_series = Series::new("time".into(), times.clone())
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("flow".into(), flow.clone()).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1, 2], None)));
+        pipeline.add_operation(Box::new(StandardizeOperation::new(Some(vec![
+            "flow".into(),
+        ]))));
+        let expected = pipeline.process(data.clone()).unwrap();
+
+        let mut scorer = pipeline
+            .scorer()
+            .unwrap()
+            .with_latency_budget(Duration::ZERO);
+        let history = data.dataframe().head(Some(20));
+        scorer
+            .prime(TimeSeriesData::with_metadata(history, data.metadata().clone()).unwrap())
+            .unwrap();
+        for i in 20..30 {
+            let rows = scorer.score_row(times[i], &[("flow", flow[i])]).unwrap();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].time_ms, times[i]);
+            let lag = expected.dataframe().column("flow_lag_2").unwrap();
+            assert_eq!(rows[0].get("flow_lag_2"), lag.f64().unwrap().get(i));
+        }
+        assert_eq!(scorer.stats().calls, 10);
+        assert_eq!(scorer.stats().over_budget, 10);
+        assert!(scorer.score_row(31_000, &[("pressure", 1.0)]).is_err());
+    }
+}
@@ -1,14 +1,220 @@
 //! Operation registry for dynamic operation registration and discovery
 //!
 //! This module provides a registry for operations, allowing dynamic registration
-//! and discovery of operations at runtime.
+//! and discovery of operations at runtime. `OperationRegistry::export_catalog`
+//! describes every registered operation as JSON for tools such as a
+//! `list-operations` command or graphical pipeline builders.
+//!
+//! Configuration steps of type `custom` are resolved through the process-wide
+//! registry (`OperationRegistry::global`), which starts out with every built-in
+//! operation. Applications add their own operations with
+//! `OperationRegistry::register_global`; they are then usable from TOML:
+//!
+//! ```toml
+//! [[operations]]
+//! type = "custom"
+//! name = "my_filter"
+//! params = { cutoff = 0.5 }
+//! ```
+//!
+//! A server building pipelines from many request threads shares registries as
+//! `SharedRegistry`: readers take a cheap `Arc` snapshot and create operations
+//! without holding a lock, and registering replaces the snapshot. Once startup is
+//! done, `freeze` makes the registry immutable, after which snapshots are taken
+//! without locking at all (`OperationRegistry::freeze_global` for the global one).
 
-use crate::core::{Operation, OperationCategory};
-use crate::error::Result;
+use crate::config::{
+    DownsampleMethod, EmptyBuckets, OperationConfig, ScaleMethod, StateAggregation, WindowClosed,
+    WindowLabel,
+};
+use crate::core::OperationCategory::{Anomaly, DataQuality, Features, Temporal, Transform};
+use crate::core::{ArithmeticPolicy, NanPolicy, Operation, OperationCategory};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::data_quality::{DuplicateKeep, FlagAction, OutlierAction};
+use crate::operations::monitoring::baseline::SeasonalPeriod;
+use crate::operations::temporal::{DayFilter, Interpolation};
+use crate::operations::{
+    AsofStrategy, MergeHow, NoiseMechanism, SchemaReconciliation, dtypes, selection,
+};
+use crate::pipeline::Pipeline;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+/// Factory creating an operation from its configuration parameters
+pub type OperationFactory = Arc<dyn Fn(&toml::Value) -> Result<Box<dyn Operation>> + Send + Sync>;
 
-/// Factory function for creating operations
-pub type OperationFactory = fn() -> Box<dyn Operation>;
+/// Name, category and description of the built-in operations
+const BUILTIN_OPERATIONS: &[(&str, OperationCategory, &str)] = &[
+    (
+        "fill_null",
+        DataQuality,
+        "Fill or interpolate missing values",
+    ),
+    (
+        "replace_sentinels",
+        DataQuality,
+        "Replace sentinel values with nulls",
+    ),
+    (
+        "normalize_nans",
+        DataQuality,
+        "Convert NaN to null or null to NaN",
+    ),
+    ("outlier", DataQuality, "Detect and treat outliers"),
+    (
+        "apply_flags",
+        DataQuality,
+        "Drop, null or interpolate flagged values",
+    ),
+    ("deduplicate", DataQuality, "Resolve duplicate timestamps"),
+    (
+        "deadband",
+        DataQuality,
+        "Drop rows within tolerance of the last row kept",
+    ),
+    ("sort_by_time", Temporal, "Sort rows by time"),
+    ("resample", Temporal, "Aggregate into fixed time buckets"),
+    (
+        "regularize",
+        Temporal,
+        "Report gaps and insert missing grid timestamps",
+    ),
+    (
+        "reconstruct",
+        Temporal,
+        "Rebuild compressed historian signals on a regular grid",
+    ),
+    (
+        "downsample",
+        Temporal,
+        "Reduce traces for plotting (LTTB, min/max)",
+    ),
+    ("lag", Features, "Lagged copies of columns"),
+    ("difference", Features, "Differences between rows"),
+    ("trend_slope", Features, "Trailing regression slope"),
+    ("rolling_features", Features, "Rolling window statistics"),
+    ("derive", Features, "Columns computed from formulas"),
+    (
+        "fleet_aggregate",
+        Features,
+        "Statistics of a measurement across assets",
+    ),
+    (
+        "ew_correlation",
+        Features,
+        "Exponentially weighted correlation",
+    ),
+    ("cusum", Anomaly, "CUSUM drift alarms"),
+    ("ewma", Anomaly, "EWMA control chart alarms"),
+    (
+        "rate_of_change",
+        Anomaly,
+        "Flag changes faster than a limit",
+    ),
+    ("flatline", Anomaly, "Flag stuck sensors"),
+    (
+        "quantile_band",
+        Anomaly,
+        "Flag excursions from rolling quantile bands",
+    ),
+    (
+        "seasonal_baseline",
+        DataQuality,
+        "Deviation from a seasonal profile",
+    ),
+    (
+        "standardize",
+        Transform,
+        "Scale columns to zero mean and unit variance",
+    ),
+    (
+        "feature_selection",
+        Features,
+        "Drop empty, flat and collinear features",
+    ),
+    (
+        "optimize_dtypes",
+        Transform,
+        "Downcast columns to smaller dtypes",
+    ),
+    ("quantize", Transform, "Round columns to a resolution"),
+    (
+        "validate",
+        DataQuality,
+        "Check rows against validation rules",
+    ),
+    ("expect", DataQuality, "Check expectations on the data"),
+    ("event_label", Features, "Label rows around events"),
+    (
+        "event_window_stats",
+        Features,
+        "Signal statistics before, during and after events",
+    ),
+    (
+        "clean_text",
+        Transform,
+        "Trim, case and rewrite text columns",
+    ),
+    (
+        "convert_units",
+        Transform,
+        "Convert columns to one engineering unit",
+    ),
+    (
+        "extract_pattern",
+        Transform,
+        "Extract regex matches from a text column",
+    ),
+    ("map_columns", Transform, "Map column values"),
+    ("merge", Transform, "Join or append another source"),
+    (
+        "parse_timestamp",
+        Temporal,
+        "Parse string or epoch timestamps",
+    ),
+    (
+        "convert_timezone",
+        Temporal,
+        "Reinterpret timestamps in another zone",
+    ),
+    (
+        "clock_skew",
+        Temporal,
+        "Move timestamps onto a reference clock",
+    ),
+    (
+        "calendar_bucket",
+        Temporal,
+        "Aggregate into local calendar buckets",
+    ),
+    ("state_dwell_time", Features, "Time spent in each state"),
+    ("calendar_filter", Temporal, "Keep working days or holidays"),
+    (
+        "quality_filter",
+        DataQuality,
+        "Null out low-quality samples",
+    ),
+    (
+        "per_group",
+        Transform,
+        "Apply steps to each entity separately",
+    ),
+    ("recipe", Transform, "Apply step chains to column families"),
+    (
+        "anonymize",
+        Transform,
+        "Mask names, timestamps and values reversibly",
+    ),
+    (
+        "privacy_noise",
+        Transform,
+        "Add differential privacy noise to aggregates",
+    ),
+];
+
+static GLOBAL: OnceLock<SharedRegistry> = OnceLock::new();
 
 /// Information about a registered operation
 #[derive(Clone)]
@@ -19,11 +225,88 @@ pub struct OperationInfo {
     pub category: OperationCategory,
     /// Description of the operation
     pub description: String,
+    /// Version of the operation (defaults to the crate version)
+    pub version: String,
+    /// Configuration parameters of the operation
+    pub parameters: Vec<ParameterInfo>,
     /// Factory function to create the operation
     pub factory: OperationFactory,
 }
 
+impl OperationInfo {
+    pub fn new<F>(name: &str, category: OperationCategory, description: &str, factory: F) -> Self
+    where
+        F: Fn(&toml::Value) -> Result<Box<dyn Operation>> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            category,
+            description: description.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            parameters: Vec::new(),
+            factory: Arc::new(factory),
+        }
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn with_parameters(mut self, parameters: Vec<ParameterInfo>) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Catalog entry of the operation
+    fn catalog_entry(&self) -> Value {
+        json!({
+            "name": self.name,
+            "category": self.category.to_string(),
+            "description": self.description,
+            "version": self.version,
+            "parameters": self.parameters,
+        })
+    }
+}
+
+/// Configuration parameter of an operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterInfo {
+    /// Key of the parameter in the operation's configuration
+    pub name: String,
+    /// Value type, e.g. "string", "integer", "float", "bool", "duration", "columns"
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub description: String,
+    pub required: bool,
+    /// Value used when the parameter is omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+impl ParameterInfo {
+    /// Required parameter
+    pub fn new(name: &str, kind: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            description: description.to_string(),
+            required: true,
+            default: None,
+        }
+    }
+
+    /// Make the parameter optional, with `default` applied when omitted
+    pub fn with_default(mut self, default: Value) -> Self {
+        self.required = false;
+        self.default = (!default.is_null()).then_some(default);
+        self
+    }
+}
+
 /// Registry for operations
+#[derive(Clone)]
 pub struct OperationRegistry {
     operations: HashMap<String, OperationInfo>,
 }
@@ -36,21 +319,77 @@ impl OperationRegistry {
         }
     }
 
+    /// Create a registry holding every built-in operation
+    ///
+    /// Built-in factories take the fields of the operation's configuration table
+    /// as parameters, which `OperationInfo::parameters` describes. Calendar-aware
+    /// operations have no pipeline calendar when created this way.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for &(name, category, description) in BUILTIN_OPERATIONS {
+            registry.register_info(
+                OperationInfo::new(name, category, description, move |params| {
+                    let mut table = match params {
+                        toml::Value::Table(table) => table.clone(),
+                        _ => toml::Table::new(),
+                    };
+                    table.insert("type".to_string(), toml::Value::String(name.to_string()));
+                    let config: OperationConfig = toml::Value::Table(table).try_into()?;
+                    Pipeline::create_operation(&config, None)
+                })
+                .with_parameters(builtin_parameters(name)),
+            );
+        }
+        registry
+    }
+
+    /// Snapshot of the process-wide registry used to resolve `custom`
+    /// configuration steps
+    pub fn global() -> Arc<OperationRegistry> {
+        Self::shared_global().snapshot()
+    }
+
+    /// The process-wide registry
+    pub fn shared_global() -> &'static SharedRegistry {
+        GLOBAL.get_or_init(|| SharedRegistry::new(Self::with_builtins()))
+    }
+
+    /// Add an operation to the process-wide registry
+    ///
+    /// Fails once the registry has been frozen with `freeze_global`.
+    pub fn register_global(info: OperationInfo) -> Result<()> {
+        Self::shared_global().register(info)
+    }
+
+    /// Make the process-wide registry immutable, e.g. at the end of startup
+    pub fn freeze_global() -> Arc<OperationRegistry> {
+        Self::shared_global().freeze()
+    }
+
+    /// Create an operation of the process-wide registry
+    ///
+    /// The registry is not locked while the factory runs, so factories may
+    /// resolve nested steps themselves.
+    pub fn create_global(name: &str, params: &toml::Value) -> Result<Box<dyn Operation>> {
+        Self::shared_global().create(name, params)
+    }
+
     /// Register an operation
-    pub fn register(
+    pub fn register<F>(
         &mut self,
         name: String,
         category: OperationCategory,
         description: String,
-        factory: OperationFactory,
-    ) {
-        let info = OperationInfo {
-            name: name.clone(),
-            category,
-            description,
-            factory,
-        };
-        self.operations.insert(name, info);
+        factory: F,
+    ) where
+        F: Fn(&toml::Value) -> Result<Box<dyn Operation>> + Send + Sync + 'static,
+    {
+        self.register_info(OperationInfo::new(&name, category, &description, factory));
+    }
+
+    /// Register an operation described by `info`, including its parameters
+    pub fn register_info(&mut self, info: OperationInfo) {
+        self.operations.insert(info.name.clone(), info);
     }
 
     /// Get an operation by name
@@ -58,12 +397,16 @@ impl OperationRegistry {
         self.operations.get(name)
     }
 
-    /// Create an operation by name
-    pub fn create(&self, name: &str) -> Result<Box<dyn Operation>> {
+    /// Create an operation by name from its configuration parameters
+    pub fn create(&self, name: &str, params: &toml::Value) -> Result<Box<dyn Operation>> {
+        self.factory(name)?(params)
+    }
+
+    fn factory(&self, name: &str) -> Result<OperationFactory> {
         self.get(name)
-            .map(|info| (info.factory)())
+            .map(|info| info.factory.clone())
             .ok_or_else(|| {
-                crate::IndustrytsError::InvalidOperation(format!("Operation not found: {}", name))
+                IndustrytsError::InvalidOperation(format!("Operation not found: {}", name))
             })
     }
 
@@ -80,6 +423,24 @@ impl OperationRegistry {
             .collect()
     }
 
+    /// Machine-readable catalog of all registered operations
+    ///
+    /// Returns pretty-printed JSON with the crate version and, sorted by name, each
+    /// operation's name, category, description, version and parameter schema
+    /// (name, type, description, required, default).
+    pub fn export_catalog(&self) -> Result<String> {
+        let mut operations = self.list_all();
+        operations.sort_by(|a, b| a.name.cmp(&b.name));
+        let catalog = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "operations": operations
+                .iter()
+                .map(|info| info.catalog_entry())
+                .collect::<Vec<_>>(),
+        });
+        Ok(serde_json::to_string_pretty(&catalog)?)
+    }
+
     /// Check if an operation is registered
     pub fn contains(&self, name: &str) -> bool {
         self.operations.contains_key(name)
@@ -102,6 +463,932 @@ impl Default for OperationRegistry {
     }
 }
 
+/// Operation registry shared between threads
+///
+/// Readers work on immutable `Arc` snapshots; `register` copies the registry and
+/// swaps in the extended copy, so it is meant for startup rather than the request
+/// path. After `freeze`, registering fails and snapshots skip the lock.
+pub struct SharedRegistry {
+    current: RwLock<Arc<OperationRegistry>>,
+    frozen: OnceLock<Arc<OperationRegistry>>,
+}
+
+impl SharedRegistry {
+    pub fn new(registry: OperationRegistry) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(registry)),
+            frozen: OnceLock::new(),
+        }
+    }
+
+    /// Current contents of the registry
+    pub fn snapshot(&self) -> Arc<OperationRegistry> {
+        if let Some(frozen) = self.frozen.get() {
+            return frozen.clone();
+        }
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Add an operation; fails once the registry is frozen
+    pub fn register(&self, info: OperationInfo) -> Result<()> {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        if self.is_frozen() {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Cannot register operation '{}': the registry is frozen",
+                info.name
+            )));
+        }
+        Arc::make_mut(&mut current).register_info(info);
+        Ok(())
+    }
+
+    /// Make the registry immutable and return its final contents
+    pub fn freeze(&self) -> Arc<OperationRegistry> {
+        // Holding the lock keeps a concurrent `register` from being lost
+        let current = self.current.read().unwrap_or_else(PoisonError::into_inner);
+        self.frozen.get_or_init(|| current.clone()).clone()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.get().is_some()
+    }
+
+    /// Create an operation by name from its configuration parameters
+    pub fn create(&self, name: &str, params: &toml::Value) -> Result<Box<dyn Operation>> {
+        let factory = self.snapshot().factory(name)?;
+        factory(params)
+    }
+}
+
+/// Parameter schema of a built-in operation, mirroring its `OperationConfig` fields
+fn builtin_parameters(name: &str) -> Vec<ParameterInfo> {
+    let param = ParameterInfo::new;
+    let optional = |name, kind, description| {
+        ParameterInfo::new(name, kind, description).with_default(Value::Null)
+    };
+    let columns = || {
+        optional(
+            "columns",
+            "columns",
+            "Columns to process (default: all features)",
+        )
+    };
+    let partition_by = || {
+        optional(
+            "partition_by",
+            "list<string>",
+            "Segment columns (batch, run, machine) that windows must not cross",
+        )
+    };
+    let aggregation = || {
+        param(
+            "aggregation",
+            "string",
+            "mean, sum, min, max, first, last or count",
+        )
+    };
+
+    match name {
+        "fill_null" => vec![
+            param(
+                "method",
+                "string",
+                "forward, backward, mean, zero, linear or time",
+            ),
+            columns(),
+            optional(
+                "column_methods",
+                "map<string, string>",
+                "Methods replacing `method` for individual columns",
+            ),
+            optional(
+                "max_gap",
+                "duration",
+                "Maximum duration to carry a value forward/backward",
+            ),
+        ],
+        "replace_sentinels" => vec![
+            optional(
+                "values",
+                "list<value>",
+                "Sentinels replaced in every feature column (numbers or strings)",
+            ),
+            optional(
+                "columns",
+                "map<string, list<value>>",
+                "Per-column sentinels, overriding `values` for that column",
+            ),
+            param(
+                "keep_strings",
+                "bool",
+                "Keep string columns as strings even when only numbers remain",
+            )
+            .with_default(json!(false)),
+        ],
+        "normalize_nans" => vec![
+            param(
+                "policy",
+                "string",
+                "null turns NaN into null, nan turns null into NaN",
+            )
+            .with_default(json!(NanPolicy::Null)),
+            columns(),
+        ],
+        "outlier" => vec![
+            param("method", "string", "zscore, iqr, rolling_mad or limits"),
+            optional(
+                "threshold",
+                "float",
+                "Standard deviations (zscore, 3), IQR factor (iqr, 1.5) or scaled MADs (rolling_mad, 3.5)",
+            ),
+            optional("window", "duration", "Trailing window of rolling_mad"),
+            optional(
+                "limits",
+                "map<string, table>",
+                "Physical limits (min, max) per column, for the limits method",
+            ),
+            param("action", "string", "clip, null, drop or flag")
+                .with_default(json!(OutlierAction::default())),
+            columns(),
+        ],
+        "apply_flags" => vec![
+            param("action", "string", "drop, null or interpolate")
+                .with_default(json!(FlagAction::default())),
+            param(
+                "keep_flags",
+                "bool",
+                "Keep the flag columns after applying them",
+            )
+            .with_default(json!(false)),
+            columns(),
+        ],
+        "deduplicate" => vec![
+            param("keep", "string", "first, last or aggregate")
+                .with_default(json!(DuplicateKeep::default())),
+            optional(
+                "aggregation",
+                "string",
+                "Aggregation of duplicates when `keep` is aggregate (default mean)",
+            ),
+            optional(
+                "keys",
+                "list<string>",
+                "Further key columns besides the time and group columns",
+            ),
+        ],
+        "deadband" => vec![
+            param(
+                "tolerance",
+                "float",
+                "Largest change ignored (0: only exact repeats are dropped)",
+            )
+            .with_default(json!(0.0)),
+            optional(
+                "tolerances",
+                "map<string, float>",
+                "Tolerances of individual columns, overriding `tolerance`",
+            ),
+            optional("max_interval", "duration", "Keep a row at least this often"),
+            columns(),
+        ],
+        "resample" => vec![
+            param("rule", "duration", "Bucket length, e.g. 1h"),
+            aggregation(),
+            columns(),
+            optional(
+                "offset",
+                "duration",
+                "Shift of the bucket boundaries from the epoch grid",
+            ),
+            param("label", "string", "Bucket stamp: left (start) or right (end)")
+                .with_default(json!(WindowLabel::default())),
+            param(
+                "closed",
+                "string",
+                "Edge including its boundary sample: left, right, both or none",
+            )
+            .with_default(json!(WindowClosed::default())),
+            param(
+                "state_aggregation",
+                "string",
+                "String state columns when `aggregation` is numeric: mode, first or last",
+            )
+            .with_default(json!(StateAggregation::default())),
+            param(
+                "empty_buckets",
+                "string",
+                "Buckets without samples between the first and last: drop, null or zero",
+            )
+            .with_default(json!(EmptyBuckets::default())),
+            optional(
+                "weights",
+                "map<string, string>",
+                "Weight column per value column, for weighted means and sums",
+            ),
+            optional(
+                "min_coverage",
+                "float",
+                "Minimum share of expected samples; sparser buckets are null",
+            ),
+            optional(
+                "sample_interval",
+                "duration",
+                "Sampling interval of the expected samples (inferred when omitted)",
+            ),
+        ],
+        "regularize" => vec![
+            optional(
+                "interval",
+                "duration",
+                "Grid interval, inferred from the data when omitted",
+            ),
+            optional(
+                "tolerance",
+                "duration",
+                "Longest interval not reported as a gap (default 1.5 grid intervals)",
+            ),
+            param(
+                "insert_missing",
+                "bool",
+                "Insert null rows at missing grid timestamps",
+            )
+            .with_default(json!(true)),
+            optional(
+                "gap_output",
+                "string",
+                "Secondary output receiving the table of gaps",
+            ),
+        ],
+        "reconstruct" => vec![
+            param(
+                "every",
+                "duration",
+                "Grid interval of the reconstructed signal",
+            ),
+            param(
+                "interpolation",
+                "string",
+                "Interpolation of columns without an `interpolation` property",
+            )
+            .with_default(json!(Interpolation::default())),
+            optional(
+                "max_gap",
+                "duration",
+                "Longest interval between stored samples that is bridged",
+            ),
+            columns(),
+        ],
+        "downsample" => vec![
+            param("method", "string", "lttb or minmax")
+                .with_default(json!(DownsampleMethod::default())),
+            optional(
+                "points",
+                "integer",
+                "Target number of points per column and series",
+            ),
+            optional(
+                "bucket",
+                "duration",
+                "Bucket duration instead of a point count",
+            ),
+            columns(),
+        ],
+        "lag" => vec![
+            param("periods", "list<integer>", "Lag periods"),
+            columns(),
+            partition_by(),
+        ],
+        "difference" => vec![
+            param("lag", "integer", "Rows between the differenced values")
+                .with_default(json!(1)),
+            columns(),
+            partition_by(),
+            optional(
+                "on_overflow",
+                "string",
+                "Handling of integer overflow (null, clamp or error); wraps when omitted",
+            ),
+        ],
+        "trend_slope" => vec![
+            param("window", "duration", "Trailing regression window"),
+            param("slope_per", "duration", "Time unit of the slope")
+                .with_default(json!("1h")),
+            optional(
+                "min_periods",
+                "integer",
+                "Samples needed in a window",
+            ),
+            columns(),
+            partition_by(),
+        ],
+        "rolling_features" => vec![
+            param(
+                "windows",
+                "list<string>",
+                "Trailing windows: durations (e.g. 15m) or row counts (e.g. 60)",
+            ),
+            param(
+                "stats",
+                "list<string>",
+                "Statistics among mean, std, min, max and median",
+            )
+            .with_default(json!(["mean", "std", "min", "max"])),
+            optional(
+                "quantiles",
+                "list<float>",
+                "Quantiles in [0, 1] computed in addition to `stats`",
+            ),
+            columns(),
+            optional(
+                "column_windows",
+                "map<string, list<string>>",
+                "Windows replacing `windows` for individual columns",
+            ),
+            optional(
+                "name_template",
+                "string",
+                "Output column name with {column}, {stat} and {window} placeholders",
+            ),
+            optional("min_periods", "integer", "Samples needed in a window"),
+            partition_by(),
+        ],
+        "derive" => vec![
+            param(
+                "exprs",
+                "list<string>",
+                "Formulas such as power = voltage * current, evaluated in order",
+            ),
+            param(
+                "on_overflow",
+                "string",
+                "Handling of overflow and division by zero: null, clamp or error",
+            )
+            .with_default(json!(ArithmeticPolicy::default())),
+        ],
+        "fleet_aggregate" => vec![
+            param("name", "string", "Prefix of the output columns")
+                .with_default(json!("fleet")),
+            param(
+                "stats",
+                "list<string>",
+                "Statistics among mean, min, max, sum and count",
+            )
+            .with_default(json!(["mean", "min", "max", "count"])),
+            param(
+                "level",
+                "integer",
+                "Hierarchy depth of the aggregated nodes; 0 aggregates all columns",
+            )
+            .with_default(json!(0)),
+            optional(
+                "assets",
+                "map<string, string>",
+                "Asset paths (e.g. plant_a/line_1/pump_3) replacing column properties",
+            ),
+            columns(),
+        ],
+        "ew_correlation" => vec![
+            param(
+                "pairs",
+                "list<list<string>>",
+                "Column pairs, e.g. [[\"speed\", \"flow\"]]",
+            ),
+            param(
+                "half_life",
+                "duration",
+                "Time for a sample's weight to halve",
+            ),
+            param(
+                "min_periods",
+                "integer",
+                "Observations required before values are emitted",
+            )
+            .with_default(json!(5)),
+        ],
+        "cusum" => vec![
+            param("k", "float", "Allowance in sigma units").with_default(json!(0.5)),
+            param("h", "float", "Decision interval in sigma units").with_default(json!(5.0)),
+            optional(
+                "target",
+                "float",
+                "Known in-control mean; self-tuned when omitted",
+            ),
+            optional(
+                "sigma",
+                "float",
+                "Known in-control standard deviation (required with `target`)",
+            ),
+            optional(
+                "reference_window",
+                "integer",
+                "Number of leading samples used for self-tuning (default: all)",
+            ),
+            columns(),
+            optional(
+                "explanations",
+                "string",
+                "Secondary output explaining each alarm",
+            ),
+            param(
+                "context_rows",
+                "integer",
+                "Preceding rows summarized per explanation",
+            )
+            .with_default(json!(30)),
+        ],
+        "ewma" => vec![
+            param("lambda", "float", "Smoothing weight in (0, 1]").with_default(json!(0.2)),
+            param("width", "float", "Control limit width in sigma units")
+                .with_default(json!(3.0)),
+            optional(
+                "target",
+                "float",
+                "Known in-control mean; self-tuned when omitted",
+            ),
+            optional(
+                "sigma",
+                "float",
+                "Known in-control standard deviation (required with `target`)",
+            ),
+            optional(
+                "references",
+                "map<string, table>",
+                "Known in-control mean and sigma (target, sigma) per column",
+            ),
+            optional(
+                "reference_window",
+                "integer",
+                "Number of leading samples used for self-tuning (default: all)",
+            ),
+            columns(),
+        ],
+        "rate_of_change" => vec![
+            optional(
+                "max_rate",
+                "float",
+                "Largest allowed absolute change per `per` (or per sample)",
+            ),
+            optional(
+                "per",
+                "duration",
+                "Time unit of the rate; per sample when omitted",
+            ),
+            optional(
+                "limits",
+                "map<string, float>",
+                "Limits replacing `max_rate` for individual columns",
+            ),
+            columns(),
+        ],
+        "flatline" => vec![
+            param("window", "duration", "Shortest flagged run"),
+            param("tolerance", "float", "Largest spread of a stuck run")
+                .with_default(json!(0.0)),
+            optional(
+                "column_tolerances",
+                "map<string, float>",
+                "Tolerances replacing `tolerance` for individual columns",
+            ),
+            columns(),
+        ],
+        "quantile_band" => vec![
+            param(
+                "window",
+                "duration",
+                "Trailing window the bands are computed over",
+            ),
+            param("lower", "float", "Quantile of the lower band").with_default(json!(0.01)),
+            param("upper", "float", "Quantile of the upper band").with_default(json!(0.99)),
+            optional(
+                "min_periods",
+                "integer",
+                "Past samples needed before a band is drawn",
+            ),
+            columns(),
+        ],
+        "seasonal_baseline" => vec![
+            param("period", "string", "Length of the seasonal profile: daily or weekly")
+                .with_default(json!(SeasonalPeriod::default())),
+            param(
+                "bucket",
+                "duration",
+                "Profile bucket width; must divide the period",
+            ),
+            param(
+                "time_zone",
+                "string",
+                "Zone whose local clock defines the buckets",
+            )
+            .with_default(json!("UTC")),
+            optional(
+                "min_samples",
+                "integer",
+                "Samples needed in a bucket of the profile",
+            ),
+            columns(),
+            optional(
+                "explanations",
+                "string",
+                "Secondary output explaining each sample beyond `explain_threshold`",
+            ),
+            param(
+                "explain_threshold",
+                "float",
+                "Absolute deviation flagged in explanations",
+            )
+            .with_default(json!(3.5)),
+            param(
+                "context_rows",
+                "integer",
+                "Preceding rows summarized per explanation",
+            )
+            .with_default(json!(30)),
+        ],
+        "standardize" => vec![
+            columns(),
+            param("method", "string", "standard or robust")
+                .with_default(json!(ScaleMethod::default())),
+            optional(
+                "clip_quantiles",
+                "list<float>",
+                "Clip values to these quantiles (e.g. [0.01, 0.99]) before scaling",
+            ),
+            optional(
+                "reference_window",
+                "integer",
+                "Learn the statistics from the first rows only, then keep them",
+            ),
+        ],
+        "feature_selection" => vec![
+            columns(),
+            param(
+                "max_null_ratio",
+                "float",
+                "Drop columns with a larger share of null or NaN values",
+            )
+            .with_default(json!(selection::DEFAULT_MAX_NULL_RATIO)),
+            param(
+                "max_dominant_ratio",
+                "float",
+                "Drop columns whose most frequent value has a larger share of the values",
+            )
+            .with_default(json!(selection::DEFAULT_MAX_DOMINANT_RATIO)),
+            param(
+                "max_correlation",
+                "float",
+                "Drop columns correlated more strongly with an earlier kept column",
+            )
+            .with_default(json!(selection::DEFAULT_MAX_CORRELATION)),
+            optional(
+                "report",
+                "string",
+                "Secondary output listing the dropped columns and reasons",
+            ),
+        ],
+        "optimize_dtypes" => vec![
+            columns(),
+            param(
+                "max_category_ratio",
+                "float",
+                "Categorize strings with at most this ratio of distinct values (0 disables)",
+            )
+            .with_default(json!(dtypes::DEFAULT_MAX_CATEGORY_RATIO)),
+            param(
+                "keep_f64",
+                "bool",
+                "Never downcast Float64 to Float32, even when lossless",
+            )
+            .with_default(json!(false)),
+        ],
+        "quantize" => vec![
+            optional(
+                "significant_digits",
+                "integer",
+                "Keep this many significant digits",
+            ),
+            optional("resolution", "float", "Round to a multiple of this step"),
+            param("downcast", "bool", "Store quantized columns as f32")
+                .with_default(json!(false)),
+            columns(),
+        ],
+        "validate" => vec![
+            param(
+                "rules",
+                "table",
+                "Rules per column, under a `columns` table",
+            ),
+            param(
+                "warn_only",
+                "bool",
+                "Record failures as tags instead of failing the pipeline",
+            )
+            .with_default(json!(false)),
+            optional(
+                "policy",
+                "string",
+                "Handling of failed rules: fail, warn or drop (overrides `warn_only`)",
+            ),
+        ],
+        "expect" => vec![
+            param("suite", "path", "Path to an expectation suite TOML file"),
+            param(
+                "warn_only",
+                "bool",
+                "Record failures as warnings instead of failing the pipeline",
+            )
+            .with_default(json!(false)),
+        ],
+        "event_label" => vec![
+            param(
+                "events",
+                "path",
+                "Path to an Arrow IPC file with one row per event",
+            ),
+            param("start_column", "string", "Event start column")
+                .with_default(json!("start")),
+            param("end_column", "string", "Event end column").with_default(json!("end")),
+            optional(
+                "asset_column",
+                "string",
+                "Asset ID column present in both the events and the data",
+            ),
+            param(
+                "pre_horizon",
+                "duration",
+                "How long before an event windows count as pre-event",
+            ),
+            optional(
+                "window",
+                "duration",
+                "Length of the window each sample stands for",
+            ),
+            optional("output", "string", "Name of the label column"),
+        ],
+        "event_window_stats" => vec![
+            param(
+                "events",
+                "path",
+                "Path to an Arrow IPC file with one row per event",
+            ),
+            param("start_column", "string", "Event start column")
+                .with_default(json!("start")),
+            param("end_column", "string", "Event end column").with_default(json!("end")),
+            optional(
+                "asset_column",
+                "string",
+                "Asset ID column present in both the events and the data",
+            ),
+            optional("before", "duration", "Window before each event start"),
+            optional("after", "duration", "Window after each event end"),
+            param("during", "bool", "Aggregate over the events themselves")
+                .with_default(json!(true)),
+            param("stats", "list<string>", "Statistics per window")
+                .with_default(json!(["mean", "min", "max"])),
+            columns(),
+        ],
+        "clean_text" => vec![
+            param("trim", "bool", "Strip leading and trailing whitespace")
+                .with_default(json!(false)),
+            optional("case", "string", "Case of the cleaned text: lower or upper"),
+            optional(
+                "replace",
+                "list<table>",
+                "Regex replacements (pattern, replacement) applied in order",
+            ),
+            columns(),
+        ],
+        "convert_units" => vec![
+            param("unit", "string", "Target unit, e.g. degC"),
+            columns(),
+        ],
+        "extract_pattern" => vec![
+            param("column", "string", "Text column to search"),
+            param(
+                "pattern",
+                "string",
+                "Regex whose first capture group (or whole match) is extracted",
+            ),
+            optional(
+                "group",
+                "integer",
+                "Capture group extracted instead, 0 for the whole match",
+            ),
+            optional(
+                "output",
+                "string",
+                "Output column (default <column>_match)",
+            ),
+        ],
+        "map_columns" => vec![
+            param("mapping", "path", "Path to a tag dictionary (.toml or .csv)"),
+            param("strict", "bool", "Fail when a mapped tag is missing")
+                .with_default(json!(false)),
+            param(
+                "drop_unmapped",
+                "bool",
+                "Drop feature columns without a mapping",
+            )
+            .with_default(json!(false)),
+        ],
+        "merge" => vec![
+            optional("source", "table", "File holding the other input"),
+            optional(
+                "input",
+                "string",
+                "Named dataset of the run holding the other input, instead of `source`",
+            ),
+            param(
+                "how",
+                "string",
+                "asof adds its columns, concat appends its rows",
+            )
+            .with_default(json!(MergeHow::default())),
+            optional(
+                "tolerance",
+                "duration",
+                "Largest time distance of an as-of match",
+            ),
+            param(
+                "strategy",
+                "string",
+                "Row matched by an as-of join: backward, forward or nearest",
+            )
+            .with_default(json!(AsofStrategy::default())),
+            param(
+                "schema",
+                "string",
+                "Columns kept when appending parts with different columns: union, intersection or strict",
+            )
+            .with_default(json!(SchemaReconciliation::default())),
+        ],
+        "parse_timestamp" => vec![
+            param("column", "string", "Column holding the timestamps"),
+            optional(
+                "formats",
+                "list<string>",
+                "Candidate formats tried in order (chrono syntax, epoch_s, epoch_ms, rfc3339)",
+            ),
+            param(
+                "lenient",
+                "bool",
+                "Turn unparsable values into nulls instead of failing",
+            )
+            .with_default(json!(false)),
+            optional(
+                "dead_letter",
+                "string",
+                "Secondary output receiving rows with unparsable values",
+            ),
+        ],
+        "convert_timezone" => vec![
+            param(
+                "from",
+                "string",
+                "Zone the timestamps are currently expressed in",
+            )
+            .with_default(json!("UTC")),
+            param("to", "string", "Target zone"),
+        ],
+        "clock_skew" => vec![
+            param(
+                "offset",
+                "duration",
+                "Correction added to the timestamps, e.g. -2min for a clock running ahead",
+            ),
+            optional(
+                "drift_ppm",
+                "float",
+                "Drift of the correction in ppm of the time since `reference_time`",
+            ),
+            optional(
+                "reference_time",
+                "string",
+                "Instant the offset applies to, required with a drift",
+            ),
+        ],
+        "calendar_bucket" => vec![
+            param("time_zone", "string", "Zone whose local calendar defines the buckets"),
+            aggregation(),
+            optional(
+                "day_start",
+                "string",
+                "Local start of the production day (HH:MM)",
+            ),
+            optional("shifts_per_day", "integer", "Shift buckets per day"),
+            columns(),
+            param(
+                "working_days_only",
+                "bool",
+                "Drop buckets on non-working days of the pipeline calendar",
+            )
+            .with_default(json!(false)),
+            param(
+                "state_aggregation",
+                "string",
+                "String state columns when `aggregation` is numeric: mode, first or last",
+            )
+            .with_default(json!(StateAggregation::default())),
+            optional(
+                "weights",
+                "map<string, string>",
+                "Weight column per value column, for weighted means and sums",
+            ),
+        ],
+        "state_dwell_time" => vec![
+            param("column", "string", "String state column"),
+            param("every", "duration", "Bucket length"),
+            optional(
+                "states",
+                "list<string>",
+                "States to report, in order (default: all states seen, sorted)",
+            ),
+            optional(
+                "max_hold",
+                "duration",
+                "Longest time a sample's state is held before the gap counts as unknown",
+            ),
+        ],
+        "calendar_filter" => vec![
+            param("time_zone", "string", "Zone in which dates are judged")
+                .with_default(json!("UTC")),
+            param("keep", "string", "Days kept: working or non_working")
+                .with_default(json!(DayFilter::default())),
+        ],
+        "quality_filter" => vec![
+            param("scheme", "string", "Quality codes: opc_da, opc_ua, label or level"),
+            param(
+                "suffix",
+                "string",
+                "Suffix of the quality companion columns",
+            )
+            .with_default(json!("_quality")),
+            param("min_quality", "string", "Lowest quality kept")
+                .with_default(json!("uncertain")),
+            columns(),
+        ],
+        "per_group" => vec![
+            param(
+                "id_column",
+                "string",
+                "Column identifying the entity (unit, site, sensor) of each row",
+            ),
+            param(
+                "operations",
+                "list<table>",
+                "Steps applied to each entity's series",
+            ),
+        ],
+        "recipe" => vec![param(
+            "recipes",
+            "list<table>",
+            "Step chains (columns, operations) by column family, each column taken by its first match",
+        )],
+        "anonymize" => vec![
+            param(
+                "key_file",
+                "path",
+                "JSON file holding the anonymization key, generated from the data if missing",
+            ),
+            columns(),
+            param("alias_prefix", "string", "Prefix of the column aliases")
+                .with_default(json!("signal")),
+            param(
+                "max_time_shift",
+                "duration",
+                "Largest timestamp shift of a generated key",
+            )
+            .with_default(json!("365d")),
+            optional(
+                "jitter",
+                "duration",
+                "Random per-row timestamp jitter, not reversible",
+            ),
+        ],
+        "privacy_noise" => vec![
+            param("epsilon", "float", "Privacy budget per noised value"),
+            param(
+                "sensitivity",
+                "float",
+                "Largest change of a value caused by one contributor",
+            ),
+            param("mechanism", "string", "laplace or gaussian")
+                .with_default(json!(NoiseMechanism::default())),
+            optional("delta", "float", "Failure probability of Gaussian noise"),
+            optional(
+                "min",
+                "float",
+                "Lower clamp of noised values (e.g. 0 for consumption)",
+            ),
+            optional("max", "float", "Upper clamp of noised values"),
+            columns(),
+        ],
+        _ => Vec::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,7 +1405,7 @@ mod tests {
         let mut registry = OperationRegistry::new();
 
         // Create a dummy operation factory
-        fn dummy_factory() -> Box<dyn Operation> {
+        fn dummy_factory(_params: &toml::Value) -> Result<Box<dyn Operation>> {
             struct DummyOp;
             impl Operation for DummyOp {
                 fn execute(
@@ -131,7 +1418,7 @@ mod tests {
                     "dummy"
                 }
             }
-            Box::new(DummyOp)
+            Ok(Box::new(DummyOp))
         }
 
         registry.register(
@@ -153,7 +1440,7 @@ mod tests {
     fn test_registry_list_by_category() {
         let mut registry = OperationRegistry::new();
 
-        fn dummy_factory() -> Box<dyn Operation> {
+        fn dummy_factory(_params: &toml::Value) -> Result<Box<dyn Operation>> {
             struct DummyOp;
             impl Operation for DummyOp {
                 fn execute(
@@ -166,7 +1453,7 @@ mod tests {
                     "dummy"
                 }
             }
-            Box::new(DummyOp)
+            Ok(Box::new(DummyOp))
         }
 
         registry.register(
@@ -189,4 +1476,167 @@ mod tests {
         let quality_ops = registry.list_by_category(OperationCategory::DataQuality);
         assert_eq!(quality_ops.len(), 1);
     }
+
+    #[test]
+    fn test_export_catalog() {
+        fn lag_factory(_params: &toml::Value) -> Result<Box<dyn Operation>> {
+            Ok(Box::new(crate::operations::LagOperation::new(
+                vec![1],
+                None,
+            )))
+        }
+
+        let mut registry = OperationRegistry::new();
+        registry.register_info(
+            OperationInfo::new(
+                "lag",
+                OperationCategory::Features,
+                "Shift columns by a number of rows",
+                lag_factory,
+            )
+            .with_version("1.1.0")
+            .with_parameters(vec![
+                ParameterInfo::new("periods", "list<integer>", "Lag periods"),
+                ParameterInfo::new("columns", "columns", "Columns to lag")
+                    .with_default(Value::Null),
+            ]),
+        );
+        registry.register(
+            "difference".to_string(),
+            OperationCategory::Features,
+            "Difference columns".to_string(),
+            lag_factory,
+        );
+
+        let catalog: Value = serde_json::from_str(&registry.export_catalog().unwrap()).unwrap();
+        let operations = catalog["operations"].as_array().unwrap();
+        assert_eq!(operations[0]["name"], "difference");
+        assert_eq!(operations[0]["version"], env!("CARGO_PKG_VERSION"));
+        let lag = &operations[1];
+        assert_eq!(lag["category"], "features");
+        assert_eq!(lag["version"], "1.1.0");
+        assert_eq!(lag["parameters"][0]["type"], "list<integer>");
+        assert_eq!(lag["parameters"][0]["required"], true);
+        assert_eq!(lag["parameters"][1]["required"], false);
+        assert!(lag["parameters"][1].get("default").is_none());
+
+        // Built-ins describe their configuration fields; sorting takes none
+        let builtins: Value =
+            serde_json::from_str(&OperationRegistry::with_builtins().export_catalog().unwrap())
+                .unwrap();
+        for operation in builtins["operations"].as_array().unwrap() {
+            let parameters = operation["parameters"].as_array().unwrap();
+            assert_eq!(
+                parameters.is_empty(),
+                operation["name"] == "sort_by_time",
+                "{}",
+                operation["name"]
+            );
+        }
+        let resample = OperationRegistry::with_builtins();
+        let resample = &resample.get("resample").unwrap().parameters;
+        assert!(resample[0].required);
+        assert_eq!(resample[4].default, Some(json!("left")));
+    }
+
+    #[test]
+    fn test_custom_operations_from_config() {
+        use crate::core::TimeSeriesData;
+        use polars::prelude::*;
+
+        /// Multiplies every feature column by a factor
+        struct Scale(f64);
+        impl Operation for Scale {
+            fn execute(&self, mut data: TimeSeriesData) -> Result<TimeSeriesData> {
+                for column in data.feature_columns().to_vec() {
+                    let scaled =
+                        data.dataframe().column(&column)?.as_materialized_series() * self.0;
+                    data.dataframe_mut().replace(&column, scaled)?;
+                }
+                Ok(data)
+            }
+            fn name(&self) -> &str {
+                "scale"
+            }
+        }
+
+        OperationRegistry::register_global(OperationInfo::new(
+            "test_scale",
+            OperationCategory::Transform,
+            "Multiply feature columns by a factor",
+            |params| {
+                let factor = params.get("factor").and_then(toml::Value::as_float);
+                Ok(Box::new(Scale(factor.unwrap_or(1.0))) as Box<dyn Operation>)
+            },
+        ))
+        .unwrap();
+        assert!(OperationRegistry::global().contains("lag"));
+
+        let config = crate::config::PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "custom"
+
+            [[operations]]
+            type = "custom"
+            name = "test_scale"
+            params = { factor = 2.0 }
+
+            [[operations]]
+            type = "custom"
+            name = "lag"
+            params = { periods = [1] }
+            "#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::from_config(config).unwrap();
+
+        let time_series = Series::new("time".into(), &[0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 3.0]).into(),
+        ])
+        .unwrap();
+        let result = pipeline
+            .process(TimeSeriesData::new(df, Some("time")).unwrap())
+            .unwrap();
+        let lagged = result.dataframe().column("value_lag_1").unwrap();
+        assert_eq!(lagged.f64().unwrap().get(1), Some(2.0));
+
+        let missing = toml::Value::Table(toml::Table::new());
+        assert!(OperationRegistry::create_global("not_registered", &missing).is_err());
+    }
+
+    #[test]
+    fn test_shared_registry_concurrent_reads_and_freeze() {
+        use crate::operations::SortByTimeOperation;
+        let noop = |name: &str| {
+            OperationInfo::new(name, OperationCategory::Transform, "No-op", |_| {
+                Ok(Box::new(SortByTimeOperation::new()) as Box<dyn Operation>)
+            })
+        };
+        let shared = Arc::new(SharedRegistry::new(OperationRegistry::with_builtins()));
+        let params: toml::Value = toml::from_str("periods = [1]").unwrap();
+        let before = shared.snapshot();
+
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let (shared, params) = (shared.clone(), params.clone());
+                std::thread::spawn(move || (0..50).all(|_| shared.create("lag", &params).is_ok()))
+            })
+            .collect();
+        shared.register(noop("noop")).unwrap();
+        assert!(readers.into_iter().all(|r| r.join().unwrap()));
+
+        // Snapshots are immutable: the earlier one does not see the registration
+        assert!(!before.contains("noop"));
+        let frozen = shared.freeze();
+        assert!(frozen.contains("noop"));
+        assert!(Arc::ptr_eq(&frozen, &shared.snapshot()));
+        assert!(shared.is_frozen());
+        assert!(shared.register(noop("late")).is_err());
+        assert!(!shared.snapshot().contains("late"));
+    }
 }
@@ -0,0 +1,181 @@
+//! Configured steps with column selectors
+//!
+//! A step configured with `columns = "TI_*"` (or a regex or dtype selector) cannot
+//! be built until the input columns are known. `SelectedColumns` keeps the step
+//! configuration, resolves the selector against each input and builds the
+//! operation for the resolved names.
+
+use crate::config::OperationConfig;
+use crate::core::{ColumnSelector, NamingPolicy, OpContext, Operation, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::operations::HolidayCalendar;
+use crate::pipeline::Pipeline;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Operation built from its configuration once its columns are resolved
+pub struct SelectedColumns {
+    config: OperationConfig,
+    selector: ColumnSelector,
+    calendar: Option<Arc<HolidayCalendar>>,
+    /// The step built over all feature columns, for its name and properties
+    template: Box<dyn Operation>,
+    seed: Option<u64>,
+    naming: Option<NamingPolicy>,
+}
+
+impl SelectedColumns {
+    pub fn new(
+        config: &OperationConfig,
+        selector: ColumnSelector,
+        calendar: Option<Arc<HolidayCalendar>>,
+    ) -> Result<Self> {
+        let mut config = config.clone();
+        config.set_columns(None);
+        let template = Pipeline::create_operation(&config, calendar.as_ref())?;
+        Ok(Self {
+            config,
+            selector,
+            calendar,
+            template,
+            seed: None,
+            naming: None,
+        })
+    }
+
+    /// Build the step for `columns`
+    fn build(&self, columns: Vec<String>) -> Result<Box<dyn Operation>> {
+        let mut config = self.config.clone();
+        config.set_columns(Some(ColumnSelector::Names(columns)));
+        let mut operation = Pipeline::create_operation(&config, self.calendar.as_ref())?;
+        if let Some(seed) = self.seed {
+            operation.set_seed(seed);
+        }
+        if let Some(naming) = &self.naming {
+            operation.set_naming(naming);
+        }
+        Ok(operation)
+    }
+
+    fn resolve(&self, data: &TimeSeriesData) -> Result<Vec<String>> {
+        let columns = self.selector.resolve(data)?;
+        if columns.is_empty() {
+            return Err(IndustrytsError::ColumnNotFound(format!(
+                "no column matches the selector of {}: {:?}",
+                self.template.name(),
+                self.selector
+            )));
+        }
+        Ok(columns)
+    }
+}
+
+impl Operation for SelectedColumns {
+    fn execute(&self, data: TimeSeriesData) -> Result<TimeSeriesData> {
+        self.build(self.resolve(&data)?)?.execute(data)
+    }
+
+    fn execute_with_context(
+        &self,
+        data: TimeSeriesData,
+        ctx: &mut OpContext,
+    ) -> Result<TimeSeriesData> {
+        self.build(self.resolve(&data)?)?
+            .execute_with_context(data, ctx)
+    }
+
+    fn name(&self) -> &str {
+        self.template.name()
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn set_naming(&mut self, naming: &NamingPolicy) {
+        self.template.set_naming(naming);
+        self.naming = Some(naming.clone());
+    }
+
+    fn added_columns(&self, feature_columns: &[String]) -> Vec<String> {
+        // Dtype and quantity filters cannot be checked without data and are assumed
+        // to match
+        self.selector
+            .resolve_names(feature_columns)
+            .and_then(|columns| self.build(columns))
+            .map(|operation| operation.added_columns(feature_columns))
+            .unwrap_or_default()
+    }
+
+    fn removes_rows(&self) -> bool {
+        self.template.removes_rows()
+    }
+
+    fn row_bounds(&self, input_rows: usize) -> (usize, Option<usize>) {
+        self.template.row_bounds(input_rows)
+    }
+
+    fn reorders_rows(&self) -> bool {
+        self.template.reorders_rows()
+    }
+
+    fn manages_time_zone(&self) -> bool {
+        self.template.manages_time_zone()
+    }
+
+    fn warmup(&self) -> Duration {
+        self.template.warmup()
+    }
+
+    fn validate(&self, data: &TimeSeriesData) -> Result<()> {
+        self.build(self.resolve(data)?)?.validate(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::PipelineConfig;
+    use crate::core::TimeSeriesData;
+    use crate::pipeline::Pipeline;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_pattern_selected_step() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "selectors"
+
+            [[operations]]
+            type = "lag"
+            periods = [1]
+            columns = { names = ["TI_*"], exclude = "*_quality" }
+            "#,
+        )
+        .unwrap();
+        let operation = Pipeline::create_operation(&config.operations[0], None).unwrap();
+
+        let time_series = Series::new("time".into(), [0i64, 60_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("TI_101".into(), [1.0, 2.0]).into(),
+            Series::new("TI_101_quality".into(), [192.0, 192.0]).into(),
+            Series::new("FI_101".into(), [3.0, 4.0]).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        assert_eq!(operation.name(), "lag");
+        assert_eq!(
+            operation.added_columns(data.feature_columns()),
+            ["TI_101_lag_1"]
+        );
+        let result = operation.execute(data).unwrap();
+        assert_eq!(
+            result.feature_columns(),
+            ["TI_101", "TI_101_quality", "FI_101", "TI_101_lag_1"]
+        );
+    }
+}
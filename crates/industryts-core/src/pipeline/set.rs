@@ -0,0 +1,250 @@
+//! Orchestration of several pipelines with dependencies
+//!
+//! A `PipelineSet` holds named pipelines, each reading one dataset of a
+//! `DataCatalog` and publishing its output under its own name. A pipeline whose
+//! input is another pipeline's output depends on it; the set runs pipelines in
+//! topological order, with independent pipelines of the same level in parallel.
+//! Datasets read by steps besides the input (`Pipeline::catalog_inputs`, e.g. a
+//! `merge` with weather data) are dependencies too, and are supplied to the run.
+//!
+//! ```text
+//! raw ──► cleaned ──► features
+//!            └──────► quality_report
+//! ```
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Named datasets exchanged between pipelines
+pub type DataCatalog = BTreeMap<String, TimeSeriesData>;
+
+struct Member {
+    name: String,
+    input: String,
+    pipeline: Pipeline,
+}
+
+impl Member {
+    /// The input followed by the datasets read by the steps
+    fn dependencies(&self) -> Vec<String> {
+        let mut dependencies = vec![self.input.clone()];
+        dependencies.extend(self.pipeline.catalog_inputs());
+        dependencies
+    }
+}
+
+/// Named pipelines executed in dependency order
+#[derive(Default)]
+pub struct PipelineSet {
+    members: Vec<Member>,
+}
+
+impl PipelineSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `pipeline`, reading the catalog dataset `input` and publishing `name`
+    pub fn add(&mut self, name: &str, pipeline: Pipeline, input: &str) -> Result<()> {
+        if self.members.iter().any(|m| m.name == name) {
+            return Err(IndustrytsError::ConfigError(format!(
+                "pipeline '{}' is already part of the set",
+                name
+            )));
+        }
+        self.members.push(Member {
+            name: name.to_string(),
+            input: input.to_string(),
+            pipeline,
+        });
+        Ok(())
+    }
+
+    /// Names of the pipelines, grouped into levels that can run in parallel
+    ///
+    /// Each level only depends on earlier levels. Inputs that are not pipeline
+    /// outputs must be among `sources`.
+    pub fn levels(&self, sources: &BTreeSet<String>) -> Result<Vec<Vec<String>>> {
+        for member in &self.members {
+            if sources.contains(&member.name) {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "pipeline '{}' would overwrite the source dataset of the same name",
+                    member.name
+                )));
+            }
+            for input in member.dependencies() {
+                if !sources.contains(&input) && !self.members.iter().any(|m| m.name == input) {
+                    return Err(IndustrytsError::ConfigError(format!(
+                        "input '{}' of pipeline '{}' is neither a source nor a pipeline output",
+                        input, member.name
+                    )));
+                }
+            }
+        }
+
+        let mut available = sources.clone();
+        let mut remaining: Vec<&Member> = self.members.iter().collect();
+        let mut levels = Vec::new();
+        while !remaining.is_empty() {
+            let (ready, blocked): (Vec<&Member>, Vec<&Member>) = remaining
+                .into_iter()
+                .partition(|m| m.dependencies().iter().all(|d| available.contains(d)));
+            if ready.is_empty() {
+                let names: Vec<&str> = blocked.iter().map(|m| m.name.as_str()).collect();
+                return Err(IndustrytsError::ConfigError(format!(
+                    "dependency cycle between pipelines {:?}",
+                    names
+                )));
+            }
+            let level: Vec<String> = ready.iter().map(|m| m.name.clone()).collect();
+            available.extend(level.iter().cloned());
+            levels.push(level);
+            remaining = blocked;
+        }
+        Ok(levels)
+    }
+
+    /// Run every pipeline, returning the catalog with all outputs added
+    pub fn run(&self, mut catalog: DataCatalog) -> Result<DataCatalog> {
+        let sources: BTreeSet<String> = catalog.keys().cloned().collect();
+        for level in self.levels(&sources)? {
+            let outputs = level
+                .par_iter()
+                .map(|name| {
+                    let member = self
+                        .members
+                        .iter()
+                        .find(|m| &m.name == name)
+                        .expect("levels only name members");
+                    let input = catalog[&member.input].clone();
+                    let inputs = member.pipeline.catalog_inputs();
+                    let result = if inputs.is_empty() {
+                        member.pipeline.process(input)
+                    } else {
+                        let inputs: DataCatalog = inputs
+                            .into_iter()
+                            .map(|name| (name.clone(), catalog[&name].clone()))
+                            .collect();
+                        member
+                            .pipeline
+                            .process_with_inputs(input, &inputs)
+                            .map(|(output, _)| output)
+                    };
+                    let output = result.map_err(|e| {
+                        IndustrytsError::OperationError(format!("pipeline '{}': {}", name, e))
+                    })?;
+                    Ok((name.clone(), output))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            catalog.extend(outputs);
+        }
+        Ok(catalog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{DifferenceOperation, LagOperation};
+    use polars::prelude::*;
+
+    #[test]
+    fn test_pipeline_set_runs_in_dependency_order() {
+        let time_series = Series::new("time".into(), &[0i64, 60_000, 120_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), &[1.0, 3.0, 6.0]).into(),
+        ])
+        .unwrap();
+        let raw = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        let mut diff = Pipeline::new();
+        diff.add_operation(Box::new(DifferenceOperation::new(1, None)));
+        let mut lag = Pipeline::new();
+        lag.add_operation(Box::new(LagOperation::new(vec![1], None)));
+
+        let mut set = PipelineSet::new();
+        // Added before its dependency on purpose
+        set.add("features", lag, "cleaned").unwrap();
+        set.add("cleaned", diff, "raw").unwrap();
+        assert!(set.add("cleaned", Pipeline::new(), "raw").is_err());
+
+        let sources = BTreeSet::from(["raw".to_string()]);
+        assert_eq!(
+            set.levels(&sources).unwrap(),
+            vec![vec!["cleaned".to_string()], vec!["features".to_string()]]
+        );
+        assert!(set.levels(&BTreeSet::new()).is_err());
+
+        let catalog = set
+            .run(DataCatalog::from([("raw".to_string(), raw)]))
+            .unwrap();
+        assert_eq!(
+            catalog["features"].feature_columns(),
+            ["value", "value_diff_1", "value_lag_1", "value_diff_1_lag_1"]
+        );
+    }
+
+    #[test]
+    fn test_steps_reading_catalog_datasets() {
+        use crate::config::PipelineConfig;
+        use crate::testing::Fixture;
+        use std::time::Duration;
+
+        let dcs = Fixture::every(Duration::from_secs(3600), 3)
+            .with_column("temp", [80.0, 81.0, 82.0])
+            .build()
+            .unwrap();
+        let lab = Fixture::every(Duration::from_secs(7200), 2)
+            .with_column("purity", [Some(0.95), None])
+            .build()
+            .unwrap();
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            [pipeline]
+            name = "enrich"
+
+            [[operations]]
+            type = "merge"
+            input = "lab_clean"
+            tolerance = "1h"
+            "#,
+        )
+        .unwrap();
+        let enrich = Pipeline::from_config(config).unwrap();
+        assert_eq!(enrich.catalog_inputs(), ["lab_clean"]);
+        assert!(enrich.process(dcs.clone()).is_err());
+
+        let mut clean = Pipeline::new();
+        clean.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        let mut set = PipelineSet::new();
+        set.add("enriched", enrich, "dcs").unwrap();
+        set.add("lab_clean", clean, "lab").unwrap();
+        let sources = BTreeSet::from(["dcs".to_string(), "lab".to_string()]);
+        assert_eq!(
+            set.levels(&sources).unwrap(),
+            vec![vec!["lab_clean".to_string()], vec!["enriched".to_string()]]
+        );
+
+        let catalog = set
+            .run(DataCatalog::from([
+                ("dcs".to_string(), dcs),
+                ("lab".to_string(), lab),
+            ]))
+            .unwrap();
+        let enriched = &catalog["enriched"];
+        let purity = enriched.dataframe().column("purity").unwrap();
+        let purity: Vec<Option<f64>> = purity.f64().unwrap().into_iter().collect();
+        assert_eq!(purity, [Some(0.95), Some(0.95), None]);
+        assert!(
+            enriched
+                .feature_columns()
+                .contains(&"purity_lag_1".to_string())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Backpressure-aware destinations for streaming execution
+//!
+//! A `StreamSink` receives the batches emitted by a `StreamProcessor`. Before each
+//! batch, `StreamProcessor::push_into` awaits `StreamSink::poll_ready`, which
+//! resolves only once the destination can take more data. A slow database or
+//! Kafka producer therefore holds up the next push, and with it the reader feeding
+//! the stream, instead of emitted batches piling up in memory.
+//!
+//! `ChannelSink` hands batches to another task through a bounded channel; the
+//! stream waits whenever the channel is full. The time spent waiting is recorded
+//! as the custom metric `sink.wait_ms` of a `sink` entry in the execution context.
+
+use crate::core::TimeSeriesData;
+use crate::core::context::{ExecutionContext, OperationMetrics};
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::stream::{StreamBatch, StreamProcessor};
+use async_trait::async_trait;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// Destination of streamed batches that can ask the stream to wait
+#[async_trait]
+pub trait StreamSink: Send {
+    /// Wait until the sink can accept a batch
+    async fn poll_ready(&mut self) -> Result<()>;
+
+    /// Deliver a batch; called once after each successful `poll_ready`
+    async fn send(&mut self, batch: StreamBatch) -> Result<()>;
+
+    /// Write out anything the sink buffers, e.g. at the end of a stream
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink passing batches to a consumer task through a bounded channel
+pub struct ChannelSink {
+    sender: mpsc::Sender<StreamBatch>,
+    permit: Option<mpsc::OwnedPermit<StreamBatch>>,
+}
+
+impl ChannelSink {
+    /// Sink holding at most `capacity` undelivered batches, and its receiver
+    pub fn new(capacity: usize) -> (Self, mpsc::Receiver<StreamBatch>) {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let sink = Self {
+            sender,
+            permit: None,
+        };
+        (sink, receiver)
+    }
+}
+
+#[async_trait]
+impl StreamSink for ChannelSink {
+    async fn poll_ready(&mut self) -> Result<()> {
+        if self.permit.is_none() {
+            let permit = self.sender.clone().reserve_owned().await.map_err(|_| {
+                IndustrytsError::OperationError("stream sink receiver was dropped".to_string())
+            })?;
+            self.permit = Some(permit);
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, batch: StreamBatch) -> Result<()> {
+        let permit = self.permit.take().ok_or_else(|| {
+            IndustrytsError::InvalidOperation(
+                "send called on a stream sink that is not ready".to_string(),
+            )
+        })?;
+        permit.send(batch);
+        Ok(())
+    }
+}
+
+impl StreamProcessor<'_> {
+    /// Push a chunk and deliver the released rows and corrections to `sink`
+    ///
+    /// Waits for the sink before returning, so a slow sink slows down the caller.
+    /// Nothing is sent when the watermark releases no rows.
+    pub async fn push_into<S: StreamSink + ?Sized>(
+        &mut self,
+        chunk: TimeSeriesData,
+        context: ExecutionContext,
+        sink: &mut S,
+    ) -> Result<ExecutionContext> {
+        let (batch, context) = self.push(chunk, context)?;
+        deliver(sink, batch, context, false).await
+    }
+
+    /// Release every buffered sample into `sink` and flush it
+    pub async fn flush_into<S: StreamSink + ?Sized>(
+        &mut self,
+        context: ExecutionContext,
+        sink: &mut S,
+    ) -> Result<ExecutionContext> {
+        let (batch, context) = self.flush(context)?;
+        deliver(sink, batch, context, true).await
+    }
+}
+
+/// Send `batch` to `sink` if it holds rows and record the time spent waiting
+async fn deliver<S: StreamSink + ?Sized>(
+    sink: &mut S,
+    batch: StreamBatch,
+    mut context: ExecutionContext,
+    flush: bool,
+) -> Result<ExecutionContext> {
+    let start = Instant::now();
+    let rows = batch.output.as_ref().map_or(0, TimeSeriesData::len)
+        + batch.corrections.as_ref().map_or(0, TimeSeriesData::len);
+    if batch.output.is_some() || batch.corrections.is_some() {
+        sink.poll_ready().await?;
+        sink.send(batch).await?;
+    }
+    if flush {
+        sink.flush().await?;
+    }
+
+    let mut metrics = OperationMetrics::new("sink".to_string());
+    metrics.input_rows = rows;
+    metrics.output_rows = rows;
+    metrics.duration = start.elapsed();
+    metrics.custom.insert(
+        "sink.wait_ms".to_string(),
+        metrics.duration.as_secs_f64() * 1000.0,
+    );
+    context.record_metrics(metrics);
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::pipeline::stream::LatePolicy;
+    use crate::testing::Fixture;
+    use std::future::Future;
+    use std::task::{Context, Waker};
+    use std::time::Duration;
+
+    #[test]
+    fn test_full_channel_holds_up_the_stream() {
+        let chunk = |start_ms: i64| {
+            Fixture::at_times_ms(vec![start_ms, start_ms + 1_000])
+                .with_column("value", [1.0, 2.0])
+                .build()
+                .unwrap()
+        };
+        let pipeline = Pipeline::new();
+        let mut stream = pipeline.stream(Duration::ZERO, LatePolicy::Drop);
+        let (mut sink, mut receiver) = ChannelSink::new(1);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let context = ExecutionContext::new();
+            let context = stream
+                .push_into(chunk(0), context, &mut sink)
+                .await
+                .unwrap();
+            // The channel holds one batch, so the next push waits for the consumer
+            let mut push = Box::pin(stream.push_into(chunk(10_000), context, &mut sink));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(push.as_mut().poll(&mut cx).is_pending());
+
+            let first = receiver.recv().await.unwrap();
+            assert_eq!(first.output.unwrap().len(), 1);
+            let context = push.await.unwrap();
+            assert_eq!(receiver.recv().await.unwrap().output.unwrap().len(), 2);
+            let context = stream.flush_into(context, &mut sink).await.unwrap();
+            assert_eq!(receiver.recv().await.unwrap().output.unwrap().len(), 1);
+
+            let waits = context
+                .metrics()
+                .iter()
+                .filter(|m| m.operation_name == "sink")
+                .count();
+            assert_eq!(waits, 3);
+        });
+    }
+}
@@ -0,0 +1,273 @@
+//! Cross-run feature statistics
+//!
+//! A model watches the drift of its own inputs; a pipeline feeding several
+//! models should be watched once, where the features are made. `StatsRegistry`
+//! appends the distribution of every feature column of each run's output (count,
+//! nulls, mean, std, extremes and quantiles, from a `DatasetSketch`) to a JSON
+//! Lines file, and answers queries over the runs: the history of one feature as
+//! a time series (`trend`), which can itself be run through a monitoring pipeline,
+//! and the shift of the latest run from the previous ones (`mean_shift`).
+//!
+//! Configured runs record into the registry named by `stats_registry` in the
+//! `[execution]` section.
+
+use crate::analysis::{ColumnSketch, DatasetSketch};
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Time column of the series returned by `StatsRegistry::trend`
+pub const RECORDED_AT: &str = "recorded_at";
+
+/// Distribution of one feature in one run
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FeatureStats {
+    pub count: u64,
+    pub null_count: u64,
+    pub mean: Option<f64>,
+    pub std: Option<f64>,
+    pub min: Option<f64>,
+    pub p05: Option<f64>,
+    pub p25: Option<f64>,
+    pub median: Option<f64>,
+    pub p75: Option<f64>,
+    pub p95: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl FeatureStats {
+    pub fn from_sketch(sketch: &ColumnSketch) -> Self {
+        Self {
+            count: sketch.count,
+            null_count: sketch.null_count,
+            mean: sketch.mean(),
+            std: sketch.std(),
+            min: sketch.min,
+            p05: sketch.quantile(0.05),
+            p25: sketch.quantile(0.25),
+            median: sketch.quantile(0.5),
+            p75: sketch.quantile(0.75),
+            p95: sketch.quantile(0.95),
+            max: sketch.max,
+        }
+    }
+
+    /// Named statistics, in the order of the columns of `StatsRegistry::trend`
+    fn values(&self) -> [(&'static str, Option<f64>); 11] {
+        [
+            ("count", Some(self.count as f64)),
+            ("null_count", Some(self.null_count as f64)),
+            ("mean", self.mean),
+            ("std", self.std),
+            ("min", self.min),
+            ("p05", self.p05),
+            ("p25", self.p25),
+            ("median", self.median),
+            ("p75", self.p75),
+            ("p95", self.p95),
+            ("max", self.max),
+        ]
+    }
+}
+
+/// Feature statistics of one run
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RunStats {
+    /// Sequence number of the run in its registry
+    pub run: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    pub recorded_at_ms: i64,
+    pub rows: u64,
+    pub features: BTreeMap<String, FeatureStats>,
+}
+
+/// Append-only registry of the feature statistics of pipeline runs
+#[derive(Debug, Clone)]
+pub struct StatsRegistry {
+    path: PathBuf,
+}
+
+impl StatsRegistry {
+    /// Registry kept in the JSON Lines file at `path`, created on first record
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record the feature statistics of a run's output
+    pub fn record(&self, pipeline: Option<&str>, output: &TimeSeriesData) -> Result<RunStats> {
+        self.record_sketch(pipeline, &output.sketch()?)
+    }
+
+    /// Record a run summarized by a sketch, e.g. of a chunked run
+    /// (`ChunkStream::with_sketch`)
+    pub fn record_sketch(
+        &self,
+        pipeline: Option<&str>,
+        sketch: &DatasetSketch,
+    ) -> Result<RunStats> {
+        let recorded_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let stats = RunStats {
+            run: self.runs()?.len(),
+            pipeline: pipeline.map(str::to_string),
+            recorded_at_ms,
+            rows: sketch.rows,
+            features: sketch
+                .columns
+                .iter()
+                .map(|(name, sketch)| (name.clone(), FeatureStats::from_sketch(sketch)))
+                .collect(),
+        };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&stats)?)?;
+        Ok(stats)
+    }
+
+    /// All recorded runs, oldest first
+    pub fn runs(&self) -> Result<Vec<RunStats>> {
+        let Ok(text) = std::fs::read_to_string(&self.path) else {
+            return Ok(Vec::new());
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Statistics of `feature` in the runs that produced it, oldest first
+    pub fn history(&self, feature: &str) -> Result<Vec<(RunStats, FeatureStats)>> {
+        Ok(self
+            .runs()?
+            .into_iter()
+            .filter_map(|mut run| {
+                let stats = run.features.remove(feature)?;
+                run.features.clear();
+                Some((run, stats))
+            })
+            .collect())
+    }
+
+    /// History of `feature` as a time series: one row per run, stamped with its
+    /// recording time, with the `run` number and one column per statistic
+    pub fn trend(&self, feature: &str) -> Result<TimeSeriesData> {
+        let history = self.history(feature)?;
+        let times: Vec<i64> = history.iter().map(|(run, _)| run.recorded_at_ms).collect();
+        let mut columns: Vec<Column> = vec![
+            Series::new(RECORDED_AT.into(), times)
+                .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+                .into(),
+            Series::new(
+                "run".into(),
+                history
+                    .iter()
+                    .map(|(run, _)| run.run as u64)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+        ];
+        let names = FeatureStats::default().values().map(|(name, _)| name);
+        for (index, name) in names.into_iter().enumerate() {
+            let values: Vec<Option<f64>> = history
+                .iter()
+                .map(|(_, stats)| stats.values()[index].1)
+                .collect();
+            columns.push(Series::new(name.into(), values).into());
+        }
+        TimeSeriesData::new(DataFrame::new(columns)?, Some(RECORDED_AT))
+    }
+
+    /// Shift of the mean of `feature` in the latest run from its mean over up to
+    /// `baseline` runs before, in units of their average within-run std
+    ///
+    /// `None` if the feature has no earlier runs or no spread.
+    pub fn mean_shift(&self, feature: &str, baseline: usize) -> Result<Option<f64>> {
+        let history = self.history(feature)?;
+        let Some(((_, latest), earlier)) = history.split_last() else {
+            return Ok(None);
+        };
+        let reference: Vec<(f64, f64)> = earlier
+            .iter()
+            .rev()
+            .take(baseline)
+            .filter_map(|(_, stats)| Some((stats.mean?, stats.std?)))
+            .collect();
+        let (Some(mean), false) = (latest.mean, reference.is_empty()) else {
+            return Ok(None);
+        };
+        let n = reference.len() as f64;
+        let center = reference.iter().map(|(m, _)| m).sum::<f64>() / n;
+        let spread = (reference.iter().map(|(_, s)| s * s).sum::<f64>() / n).sqrt();
+        Ok((spread > 0.0).then(|| (mean - center) / spread))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_runs_trend_and_mean_shift() {
+        let path = std::env::temp_dir().join(format!(
+            "industryts_stats_{}/registry.jsonl",
+            std::process::id()
+        ));
+        let registry = StatsRegistry::new(&path);
+        assert!(registry.runs().unwrap().is_empty());
+
+        let noise = [-1.0, 1.0, -0.5, 0.5, 0.0];
+        for level in [10.0, 10.0, 10.0, 15.0] {
+            let data = Fixture::every(Duration::from_secs(60), 50)
+                .with_column(
+                    "temp",
+                    (0..50).map(|i| level + noise[i % 5]).collect::<Vec<_>>(),
+                )
+                .build()
+                .unwrap();
+            registry.record(Some("features"), &data).unwrap();
+        }
+
+        let runs = registry.runs().unwrap();
+        assert_eq!(runs.len(), 4);
+        assert_eq!(runs[3].run, 3);
+        assert_eq!(runs[3].pipeline.as_deref(), Some("features"));
+        assert_eq!(runs[0].features["temp"].count, 50);
+
+        let trend = registry.trend("temp").unwrap();
+        assert_eq!(trend.len(), 4);
+        assert_eq!(trend.time_column(), RECORDED_AT);
+        let means: Vec<f64> = trend
+            .dataframe()
+            .column("mean")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert!((means[3] - 15.0).abs() < 1e-9);
+
+        let shift = registry.mean_shift("temp", 3).unwrap().unwrap();
+        assert!(shift > 5.0, "{}", shift);
+        assert_eq!(registry.mean_shift("flow", 3).unwrap(), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
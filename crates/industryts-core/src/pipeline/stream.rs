@@ -0,0 +1,490 @@
+//! Streaming execution with watermarks and allowed lateness
+//!
+//! `StreamProcessor` feeds chunks of live data through a pipeline. Samples are
+//! buffered until the watermark (latest timestamp seen minus the allowed
+//! lateness) passes them, so out-of-order samples within the lateness are sorted
+//! into place before anything is emitted. Released rows are processed together
+//! with `Pipeline::warmup` of earlier history, like `Pipeline::backfill` chunks.
+//!
+//! Samples older than the watermark are late. Depending on `LatePolicy` they are
+//! dropped and counted, or merged into the retained history and the affected time
+//! range is reprocessed and emitted as corrections. Each push records a `stream`
+//! entry in the execution context with the custom metrics `stream.late_dropped`,
+//! `stream.late_corrected`, `stream.buffered_rows` and `stream.watermark_ms`.
+//! Pipeline steps are recorded for every processed chunk as well, so
+//! `ExecutionContext::timing_breakdown` reports their p50/p95/max latency.
+//!
+//! Rows are reprocessed for warm-up and corrections, so steps should rely on
+//! their warm-up rather than on streaming state carried across calls.
+//!
+//! A `SourceWatchdog` reports sources of a merged stream that stop delivering
+//! and can fill their outages with null rows; its pushes also record
+//! `stream.stalled_sources` and `stream.gap_filled`.
+
+use crate::core::TimeSeriesData;
+use crate::core::context::{ExecutionContext, OperationMetrics};
+use crate::error::Result;
+use crate::pipeline::Pipeline;
+use crate::pipeline::backfill::slice_range;
+use crate::pipeline::watchdog::{SourceWatchdog, WatchdogEvent};
+use polars::prelude::*;
+use std::time::{Duration, Instant};
+
+/// What happens to samples older than the watermark
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LatePolicy {
+    /// Drop late samples and count them
+    #[default]
+    Drop,
+    /// Reprocess late samples up to `horizon` behind the watermark and emit the
+    /// affected rows as corrections; older samples are dropped
+    Correct { horizon: Duration },
+}
+
+/// Result of one push into a stream
+#[derive(Default)]
+pub struct StreamBatch {
+    /// Rows released by the watermark, in time order
+    pub output: Option<TimeSeriesData>,
+    /// Replacements for rows emitted earlier, covering the range of the late samples
+    pub corrections: Option<TimeSeriesData>,
+    /// Late samples dropped
+    pub late_dropped: usize,
+    /// Late samples merged into corrections
+    pub late_corrected: usize,
+    /// Sources that stalled or resumed
+    pub watchdog: Vec<WatchdogEvent>,
+    /// Null rows added for stalled sources
+    pub gap_filled: usize,
+}
+
+/// Watermark-driven streaming execution of a pipeline
+pub struct StreamProcessor<'a> {
+    pipeline: &'a Pipeline,
+    allowed_lateness: Duration,
+    policy: LatePolicy,
+    /// Samples waiting for the watermark
+    pending: Option<TimeSeriesData>,
+    /// Released samples kept for warm-up and corrections
+    history: Option<TimeSeriesData>,
+    max_seen_ms: Option<i64>,
+    watermark_ms: Option<i64>,
+    watchdog: Option<SourceWatchdog>,
+}
+
+impl Pipeline {
+    /// Stream data through the pipeline, waiting `allowed_lateness` for late samples
+    pub fn stream(&self, allowed_lateness: Duration, policy: LatePolicy) -> StreamProcessor<'_> {
+        StreamProcessor::new(self, allowed_lateness, policy)
+    }
+}
+
+impl<'a> StreamProcessor<'a> {
+    pub fn new(pipeline: &'a Pipeline, allowed_lateness: Duration, policy: LatePolicy) -> Self {
+        Self {
+            pipeline,
+            allowed_lateness,
+            policy,
+            pending: None,
+            history: None,
+            max_seen_ms: None,
+            watermark_ms: None,
+            watchdog: None,
+        }
+    }
+
+    /// Watch the sources of the stream for stalls
+    pub fn with_watchdog(mut self, watchdog: SourceWatchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Watchdog of the stream's sources, if any
+    pub fn watchdog(&self) -> Option<&SourceWatchdog> {
+        self.watchdog.as_ref()
+    }
+
+    /// Current watermark in milliseconds since the Unix epoch
+    pub fn watermark_ms(&self) -> Option<i64> {
+        self.watermark_ms
+    }
+
+    /// Samples buffered until the watermark passes them
+    pub fn buffered_rows(&self) -> usize {
+        self.pending.as_ref().map_or(0, TimeSeriesData::len)
+    }
+
+    /// Add a chunk of samples and emit whatever the watermark releases
+    ///
+    /// Samples without a timestamp are dropped as late.
+    pub fn push(
+        &mut self,
+        chunk: TimeSeriesData,
+        mut context: ExecutionContext,
+    ) -> Result<(StreamBatch, ExecutionContext)> {
+        let start = Instant::now();
+        let input_rows = chunk.len();
+        let mut batch = StreamBatch::default();
+
+        let timestamps = chunk.timestamps_ms()?;
+        let on_time = match self.watermark_ms {
+            Some(watermark) => timestamps.gt_eq(watermark),
+            None => timestamps.is_not_null(),
+        };
+        let late = filter(&chunk, &!&on_time)?;
+        let on_time = filter(&chunk, &on_time)?;
+        if !late.is_empty() {
+            context = self.correct(late, &mut batch, context)?;
+        }
+
+        if let Some(max) = on_time.timestamps_ms()?.max() {
+            self.advance(max);
+            self.pending = Some(append(self.pending.take(), on_time)?);
+        }
+        self.watch(Some(&chunk), &mut batch)?;
+        if let Some(watermark) = self.watermark_ms {
+            context = self.release(watermark, &mut batch, context)?;
+        }
+
+        self.record(&mut context, start, input_rows, &batch);
+        context.record_chunk();
+        Ok((batch, context))
+    }
+
+    /// Move the stream time to `time_ms` without samples, e.g. from a timer while
+    /// no source delivers, so stalls are detected and buffered rows released
+    pub fn advance_to(
+        &mut self,
+        time_ms: i64,
+        mut context: ExecutionContext,
+    ) -> Result<(StreamBatch, ExecutionContext)> {
+        let start = Instant::now();
+        let mut batch = StreamBatch::default();
+        self.advance(time_ms);
+        self.watch(None, &mut batch)?;
+        if let Some(watermark) = self.watermark_ms {
+            context = self.release(watermark, &mut batch, context)?;
+        }
+        self.record(&mut context, start, 0, &batch);
+        Ok((batch, context))
+    }
+
+    /// Raise the latest timestamp seen and the watermark
+    fn advance(&mut self, max: i64) {
+        let max = self.max_seen_ms.map_or(max, |seen| seen.max(max));
+        self.max_seen_ms = Some(max);
+        let watermark = max - self.allowed_lateness.as_millis() as i64;
+        self.watermark_ms = Some(self.watermark_ms.map_or(watermark, |w| w.max(watermark)));
+    }
+
+    /// Update the watchdog and buffer its gap-filling rows
+    fn watch(&mut self, chunk: Option<&TimeSeriesData>, batch: &mut StreamBatch) -> Result<()> {
+        let (Some(watchdog), Some(stream_ms), Some(watermark)) =
+            (self.watchdog.as_mut(), self.max_seen_ms, self.watermark_ms)
+        else {
+            return Ok(());
+        };
+        let (events, rows) = watchdog.update(chunk, stream_ms, watermark)?;
+        batch.watchdog.extend(events);
+        let Some(rows) = rows else {
+            return Ok(());
+        };
+        let time_col = match (&self.pending, chunk) {
+            (Some(pending), _) => pending.time_column().to_string(),
+            (None, Some(chunk)) => chunk.time_column().to_string(),
+            (None, None) => match &self.history {
+                Some(history) => history.time_column().to_string(),
+                None => return Ok(()),
+            },
+        };
+        batch.gap_filled += rows.height();
+        let rows = TimeSeriesData::new(rows, Some(&time_col))?;
+        self.pending = Some(append(self.pending.take(), rows)?);
+        Ok(())
+    }
+
+    /// Release every buffered sample, e.g. at the end of a stream
+    pub fn flush(
+        &mut self,
+        mut context: ExecutionContext,
+    ) -> Result<(StreamBatch, ExecutionContext)> {
+        let start = Instant::now();
+        let mut batch = StreamBatch::default();
+        if let Some(max) = self.max_seen_ms {
+            self.watermark_ms = Some(max + 1);
+            context = self.release(max + 1, &mut batch, context)?;
+        }
+        self.record(&mut context, start, 0, &batch);
+        Ok((batch, context))
+    }
+
+    /// Process pending samples before `watermark` and move them to the history
+    fn release(
+        &mut self,
+        watermark: i64,
+        batch: &mut StreamBatch,
+        context: ExecutionContext,
+    ) -> Result<ExecutionContext> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(context);
+        };
+        let timestamps = pending.timestamps_ms()?;
+        let released = sort(&filter(&pending, &timestamps.lt(watermark))?)?;
+        let remaining = filter(&pending, &timestamps.gt_eq(watermark))?;
+        self.pending = (!remaining.is_empty()).then_some(remaining);
+        let Some(from) = released.timestamps_ms()?.min() else {
+            return Ok(context);
+        };
+
+        let history = append(self.history.take(), released)?;
+        let (output, context) = self.process(&history, from, watermark, context)?;
+        batch.output = Some(output);
+        self.history = Some(self.trim(history, watermark)?);
+        Ok(context)
+    }
+
+    /// Apply the late policy to samples older than the watermark
+    fn correct(
+        &mut self,
+        late: TimeSeriesData,
+        batch: &mut StreamBatch,
+        context: ExecutionContext,
+    ) -> Result<ExecutionContext> {
+        let (LatePolicy::Correct { horizon }, Some(watermark)) = (self.policy, self.watermark_ms)
+        else {
+            batch.late_dropped += late.len();
+            return Ok(context);
+        };
+        let timestamps = late.timestamps_ms()?;
+        let accepted = timestamps.gt_eq(watermark - horizon.as_millis() as i64);
+        let accepted_rows = accepted.sum().unwrap_or(0) as usize;
+        batch.late_dropped += late.len() - accepted_rows;
+        batch.late_corrected += accepted_rows;
+        let late = filter(&late, &accepted)?;
+        let Some(from) = late.timestamps_ms()?.min() else {
+            return Ok(context);
+        };
+
+        let history = sort(&append(self.history.take(), late)?)?;
+        let (corrections, context) = self.process(&history, from, watermark, context)?;
+        batch.corrections = Some(corrections);
+        self.history = Some(history);
+        Ok(context)
+    }
+
+    /// Run the pipeline on `history` with warm-up and keep the rows in `[from, to)`
+    fn process(
+        &self,
+        history: &TimeSeriesData,
+        from: i64,
+        to: i64,
+        context: ExecutionContext,
+    ) -> Result<(TimeSeriesData, ExecutionContext)> {
+        let warmup_ms = self.pipeline.warmup().as_millis() as i64;
+        let input = slice_range(history, from - warmup_ms, to)?;
+        let (result, context) = self.pipeline.process_with_context(input, context)?;
+        Ok((slice_range(&result, from, to)?, context))
+    }
+
+    /// Drop history no longer needed for warm-up or corrections
+    fn trim(&self, history: TimeSeriesData, watermark: i64) -> Result<TimeSeriesData> {
+        let horizon = match self.policy {
+            LatePolicy::Drop => Duration::ZERO,
+            LatePolicy::Correct { horizon } => horizon,
+        };
+        let keep_ms = (self.pipeline.warmup() + horizon).as_millis() as i64;
+        slice_range(&history, watermark - keep_ms, i64::MAX)
+    }
+
+    fn record(
+        &self,
+        context: &mut ExecutionContext,
+        start: Instant,
+        input_rows: usize,
+        batch: &StreamBatch,
+    ) {
+        let mut metrics = OperationMetrics::new("stream".to_string());
+        metrics.input_rows = input_rows;
+        metrics.output_rows = batch.output.as_ref().map_or(0, TimeSeriesData::len);
+        metrics.duration = start.elapsed();
+        metrics
+            .custom
+            .insert("stream.late_dropped".to_string(), batch.late_dropped as f64);
+        metrics.custom.insert(
+            "stream.late_corrected".to_string(),
+            batch.late_corrected as f64,
+        );
+        metrics.custom.insert(
+            "stream.buffered_rows".to_string(),
+            self.buffered_rows() as f64,
+        );
+        if let Some(watermark) = self.watermark_ms {
+            metrics
+                .custom
+                .insert("stream.watermark_ms".to_string(), watermark as f64);
+        }
+        if batch.late_dropped > 0 {
+            metrics.warnings.push(format!(
+                "dropped {} samples older than the watermark",
+                batch.late_dropped
+            ));
+        }
+        if let Some(watchdog) = &self.watchdog {
+            metrics.custom.insert(
+                "stream.stalled_sources".to_string(),
+                watchdog.stalled_sources().len() as f64,
+            );
+            metrics
+                .custom
+                .insert("stream.gap_filled".to_string(), batch.gap_filled as f64);
+        }
+        for event in &batch.watchdog {
+            if let WatchdogEvent::Stalled {
+                source,
+                last_seen_ms,
+                ..
+            } = event
+            {
+                metrics.warnings.push(match last_seen_ms {
+                    Some(last) => {
+                        format!("source '{}' stalled, last sample at {} ms", source, last)
+                    }
+                    None => format!("source '{}' has not delivered", source),
+                });
+            }
+        }
+        context.record_metrics(metrics);
+    }
+}
+
+/// Rows of `data` where `mask` is true
+fn filter(data: &TimeSeriesData, mask: &BooleanChunked) -> Result<TimeSeriesData> {
+    let df = data
+        .dataframe()
+        .filter(&mask.fill_null_with_values(false)?)?;
+    let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+    result.metadata_mut().tags = data.metadata().tags.clone();
+    Ok(result)
+}
+
+/// Sort `data` by time, keeping the arrival order of equal timestamps
+fn sort(data: &TimeSeriesData) -> Result<TimeSeriesData> {
+    let df = data.dataframe().sort(
+        [data.time_column()],
+        SortMultipleOptions::default().with_maintain_order(true),
+    )?;
+    let mut result = TimeSeriesData::new(df, Some(data.time_column()))?;
+    result.metadata_mut().tags = data.metadata().tags.clone();
+    Ok(result)
+}
+
+/// Append `rows` to `buffer`
+fn append(buffer: Option<TimeSeriesData>, rows: TimeSeriesData) -> Result<TimeSeriesData> {
+    let Some(buffer) = buffer else {
+        return Ok(rows);
+    };
+    let mut df = buffer.dataframe().clone();
+    df.vstack_mut(rows.dataframe())?;
+    let mut result = TimeSeriesData::new(df, Some(buffer.time_column()))?;
+    result.metadata_mut().tags = buffer.metadata().tags.clone();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::LagOperation;
+
+    fn chunk(times: &[i64], values: &[f64]) -> TimeSeriesData {
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    fn values(data: &TimeSeriesData, column: &str) -> Vec<Option<f64>> {
+        data.dataframe()
+            .column(column)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_watermark_orders_and_corrects_late_samples() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.set_warmup(Duration::from_secs(60));
+        let mut stream = pipeline.stream(
+            Duration::from_secs(120),
+            LatePolicy::Correct {
+                horizon: Duration::from_secs(300),
+            },
+        );
+        let context = ExecutionContext::new();
+
+        // Minute 1 arrives after minute 2 but within the lateness
+        let (batch, context) = stream
+            .push(chunk(&[0, 120_000, 60_000], &[0.0, 2.0, 1.0]), context)
+            .unwrap();
+        assert!(batch.output.is_none());
+        assert_eq!(stream.buffered_rows(), 3);
+
+        let (batch, context) = stream
+            .push(chunk(&[180_000, 240_000], &[3.0, 4.0]), context)
+            .unwrap();
+        let output = batch.output.unwrap();
+        assert_eq!(values(&output, "value"), vec![Some(0.0), Some(1.0)]);
+        assert_eq!(values(&output, "value_lag_1"), vec![None, Some(0.0)]);
+        assert_eq!(stream.watermark_ms(), Some(120_000));
+
+        // A sample for minute 1.5 arrives after minute 1 was emitted; its correction
+        // sees minute 1 through the warm-up
+        let (batch, context) = stream
+            .push(chunk(&[90_000, 300_000], &[1.5, 5.0]), context)
+            .unwrap();
+        assert_eq!(batch.late_corrected, 1);
+        let corrections = batch.corrections.unwrap();
+        assert_eq!(values(&corrections, "value"), vec![Some(1.5)]);
+        assert_eq!(values(&corrections, "value_lag_1"), vec![Some(1.0)]);
+        let output = batch.output.unwrap();
+        assert_eq!(values(&output, "value_lag_1"), vec![Some(1.5)]);
+
+        let (batch, context) = stream.flush(context).unwrap();
+        assert_eq!(batch.output.unwrap().len(), 3);
+        let metrics = context
+            .metrics()
+            .iter()
+            .filter(|m| m.operation_name == "stream")
+            .collect::<Vec<_>>();
+        assert_eq!(metrics.len(), 4);
+        assert_eq!(metrics[2].custom["stream.late_corrected"], 1.0);
+    }
+
+    #[test]
+    fn test_drop_policy_counts_late_samples() {
+        let pipeline = Pipeline::new();
+        let mut stream = pipeline.stream(Duration::ZERO, LatePolicy::Drop);
+        let (batch, context) = stream
+            .push(chunk(&[0, 60_000], &[0.0, 1.0]), ExecutionContext::new())
+            .unwrap();
+        assert_eq!(batch.output.unwrap().len(), 1);
+
+        let (batch, context) = stream
+            .push(chunk(&[30_000, 120_000], &[0.5, 2.0]), context)
+            .unwrap();
+        assert_eq!(batch.late_dropped, 1);
+        assert!(batch.corrections.is_none());
+        assert_eq!(values(&batch.output.unwrap(), "value"), vec![Some(1.0)]);
+        let last = context.metrics().last().unwrap();
+        assert_eq!(last.custom["stream.late_dropped"], 1.0);
+        assert_eq!(last.warnings.len(), 1);
+    }
+}
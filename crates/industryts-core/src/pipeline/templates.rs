@@ -0,0 +1,270 @@
+//! Built-in pipeline templates
+//!
+//! `Pipeline::template` returns a ready-made configuration for a common task with
+//! a few named parameters. Templates are TOML configurations with `{parameter}`
+//! placeholders; set parameters with `PipelineTemplate::with_param`, adjust the
+//! generated `PipelineConfig` if needed, and build the pipeline:
+//!
+//! ```text
+//! let pipeline = Pipeline::template("kpi_rollup")?
+//!     .with_param("interval", "5min")?
+//!     .build()?;
+//! ```
+//!
+//! - `sensor_cleanup`: sentinel replacement, rolling-MAD outlier removal and
+//!   time-weighted interpolation of short gaps
+//! - `kpi_rollup`: regularization onto the sampling grid and aggregation into
+//!   fixed buckets (1 minute by default)
+//! - `anomaly_monitoring`: gap filling, CUSUM drift alarms with explanations and
+//!   rolling-MAD spike flags
+
+use crate::config::PipelineConfig;
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+struct TemplateDef {
+    name: &'static str,
+    description: &'static str,
+    /// Name, default value and description of each parameter
+    params: &'static [(&'static str, &'static str, &'static str)],
+    toml: &'static str,
+}
+
+const TEMPLATES: &[TemplateDef] = &[
+    TemplateDef {
+        name: "sensor_cleanup",
+        description: "Standard sensor cleanup: sentinels, spikes and short gaps",
+        params: &[
+            (
+                "sentinels",
+                "-9999.0",
+                "Comma-separated numeric sentinel values",
+            ),
+            ("outlier_window", "1h", "Trailing window of the rolling MAD"),
+            (
+                "outlier_threshold",
+                "3.5",
+                "Scaled MADs beyond which values are removed",
+            ),
+            ("max_gap", "10min", "Longest gap bridged by interpolation"),
+        ],
+        toml: r#"
+            [pipeline]
+            name = "sensor_cleanup"
+
+            [[operations]]
+            type = "replace_sentinels"
+            values = [{sentinels}]
+
+            [[operations]]
+            type = "outlier"
+            method = "rolling_mad"
+            window = "{outlier_window}"
+            threshold = {outlier_threshold}
+            action = "null"
+
+            [[operations]]
+            type = "fill_null"
+            method = "time"
+            max_gap = "{max_gap}"
+        "#,
+    },
+    TemplateDef {
+        name: "kpi_rollup",
+        description: "KPI rollup: regularize and aggregate into fixed buckets",
+        params: &[
+            ("interval", "1min", "Bucket width"),
+            (
+                "aggregation",
+                "mean",
+                "Aggregation of each bucket (mean, sum, min, max, ...)",
+            ),
+        ],
+        toml: r#"
+            [pipeline]
+            name = "kpi_rollup"
+
+            [[operations]]
+            type = "regularize"
+            insert_missing = false
+            gap_output = "gaps"
+
+            [[operations]]
+            type = "resample"
+            rule = "{interval}"
+            aggregation = "{aggregation}"
+        "#,
+    },
+    TemplateDef {
+        name: "anomaly_monitoring",
+        description: "Anomaly monitoring: CUSUM drift alarms and spike flags",
+        params: &[
+            (
+                "max_gap",
+                "5min",
+                "Longest gap carried forward before monitoring",
+            ),
+            ("k", "0.5", "CUSUM allowance in sigma units"),
+            ("h", "5.0", "CUSUM decision interval in sigma units"),
+            (
+                "spike_window",
+                "1h",
+                "Trailing window of the rolling MAD spike check",
+            ),
+        ],
+        toml: r#"
+            [pipeline]
+            name = "anomaly_monitoring"
+
+            [[operations]]
+            type = "fill_null"
+            method = "forward"
+            max_gap = "{max_gap}"
+
+            [[operations]]
+            type = "cusum"
+            k = {k}
+            h = {h}
+            explanations = "alarms"
+
+            [[operations]]
+            type = "outlier"
+            method = "rolling_mad"
+            window = "{spike_window}"
+            action = "flag"
+        "#,
+    },
+];
+
+/// A built-in pipeline configuration with named parameters
+#[derive(Debug, Clone)]
+pub struct PipelineTemplate {
+    def: &'static TemplateDef,
+    values: BTreeMap<&'static str, String>,
+}
+
+impl PipelineTemplate {
+    pub fn name(&self) -> &str {
+        self.def.name
+    }
+
+    pub fn description(&self) -> &str {
+        self.def.description
+    }
+
+    /// Name, current value and description of each parameter
+    pub fn params(&self) -> Vec<(&str, &str, &str)> {
+        self.def
+            .params
+            .iter()
+            .map(|(name, _, doc)| (*name, self.values[name].as_str(), *doc))
+            .collect()
+    }
+
+    /// Set the parameter `name`
+    pub fn with_param(mut self, name: &str, value: &str) -> Result<Self> {
+        let Some((key, _, _)) = self.def.params.iter().find(|(key, _, _)| *key == name) else {
+            let known: Vec<&str> = self.def.params.iter().map(|(key, _, _)| *key).collect();
+            return Err(IndustrytsError::ConfigError(format!(
+                "template '{}' has no parameter '{}' (parameters: {:?})",
+                self.def.name, name, known
+            )));
+        };
+        self.values.insert(key, value.to_string());
+        Ok(self)
+    }
+
+    /// Configuration with the parameters filled in, for further customization
+    pub fn config(&self) -> Result<PipelineConfig> {
+        let mut toml = self.def.toml.to_string();
+        for (name, value) in &self.values {
+            toml = toml.replace(&format!("{{{}}}", name), value);
+        }
+        PipelineConfig::from_toml_str(&toml).map_err(|e| {
+            IndustrytsError::ConfigError(format!("template '{}': {}", self.def.name, e))
+        })
+    }
+
+    /// Build the pipeline
+    pub fn build(&self) -> Result<Pipeline> {
+        Pipeline::from_config(self.config()?)
+    }
+}
+
+impl Pipeline {
+    /// Built-in template `name` with default parameters (see `Pipeline::template_names`)
+    pub fn template(name: &str) -> Result<PipelineTemplate> {
+        let def = TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+            IndustrytsError::ConfigError(format!(
+                "unknown pipeline template '{}' (templates: {:?})",
+                name,
+                Self::template_names()
+            ))
+        })?;
+        Ok(PipelineTemplate {
+            def,
+            values: def
+                .params
+                .iter()
+                .map(|(key, default, _)| (*key, default.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Names of the built-in templates
+    pub fn template_names() -> Vec<&'static str> {
+        TEMPLATES.iter().map(|t| t.name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TimeSeriesData;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_templates_build_and_run() {
+        // Ten minutes of 20-second samples with a sentinel and a missing value
+        let times: Vec<i64> = (0..30).map(|i| i * 20_000).collect();
+        let mut values: Vec<Option<f64>> = (0..30).map(|i| Some(10.0 + (i % 3) as f64)).collect();
+        values[4] = Some(-9999.0);
+        values[12] = None;
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        let data = TimeSeriesData::new(df, Some("time")).unwrap();
+
+        for name in Pipeline::template_names() {
+            let pipeline = Pipeline::template(name).unwrap().build().unwrap();
+            assert!(pipeline.process(data.clone()).is_ok(), "{}", name);
+        }
+
+        let cleaned = Pipeline::template("sensor_cleanup")
+            .unwrap()
+            .build()
+            .unwrap()
+            .process(data.clone())
+            .unwrap();
+        assert_eq!(cleaned.dataframe().column("value").unwrap().null_count(), 0);
+
+        let template = Pipeline::template("kpi_rollup")
+            .unwrap()
+            .with_param("interval", "5min")
+            .unwrap();
+        assert_eq!(template.params()[0], ("interval", "5min", "Bucket width"));
+        let rollup = template.build().unwrap().process(data).unwrap();
+        assert_eq!(rollup.len(), 2);
+
+        assert!(Pipeline::template("missing").is_err());
+        let template = Pipeline::template("kpi_rollup").unwrap();
+        assert!(template.with_param("window", "1h").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Type-state pipeline builder
+//!
+//! `TypedPipelineBuilder` tracks in its type whether the data is known to be on a
+//! regular time grid. Operations that assume a regular grid (e.g. lags counted in
+//! rows) can only be added once a regularizing step (e.g. calendar bucketing) has
+//! been added or regularity has been asserted, so ordering mistakes become compile
+//! errors instead of silently wrong results. Operations that may remove rows
+//! (e.g. dropping outliers) leave gaps in the grid, so adding one with
+//! `add_filter` returns to the irregular state:
+//!
+//! ```compile_fail
+//! use industryts_core::operations::LagOperation;
+//! use industryts_core::pipeline::typed::TypedPipelineBuilder;
+//!
+//! // Lag before any regularizing step does not compile
+//! TypedPipelineBuilder::new().add_on_grid(LagOperation::new(vec![1], None));
+//! ```
+
+use crate::core::Operation;
+use crate::error::{IndustrytsError, Result};
+use crate::operations::*;
+use crate::pipeline::executor::Pipeline;
+use std::marker::PhantomData;
+
+/// Type-level state: timestamps may be irregular
+pub struct Irregular;
+
+/// Type-level state: timestamps are on a regular grid
+pub struct Regular;
+
+/// Marker for operations that work on any time grid and preserve it
+pub trait GridAgnostic: Operation {}
+
+/// Marker for operations that keep the grid unless configured to remove rows
+pub trait MayRemoveRows: Operation {}
+
+/// Marker for operations that produce a regular time grid
+pub trait Regularizes: Operation {}
+
+/// Marker for operations that require a regular time grid
+pub trait RequiresRegularGrid: Operation {}
+
+impl GridAgnostic for FillNullOperation {}
+impl GridAgnostic for StandardizeOperation {}
+impl GridAgnostic for FeatureSelectionOperation {}
+impl GridAgnostic for QuantizeOperation {}
+impl GridAgnostic for OptimizeDtypesOperation {}
+impl GridAgnostic for QualityFilterOperation {}
+impl GridAgnostic for ReplaceSentinelsOperation {}
+impl GridAgnostic for NormalizeNansOperation {}
+impl GridAgnostic for TrendSlopeOperation {}
+impl GridAgnostic for RollingFeaturesOperation {}
+impl GridAgnostic for EwCorrelationOperation {}
+impl GridAgnostic for CusumOperation {}
+impl GridAgnostic for EwmaOperation {}
+impl GridAgnostic for RateOfChangeOperation {}
+impl GridAgnostic for FlatlineOperation {}
+impl GridAgnostic for QuantileBandOperation {}
+impl GridAgnostic for SeasonalBaselineOperation {}
+impl GridAgnostic for EventLabelOperation {}
+impl GridAgnostic for MapColumnsOperation {}
+impl GridAgnostic for FleetAggregateOperation {}
+impl GridAgnostic for DeriveOperation {}
+impl GridAgnostic for CleanTextOperation {}
+impl GridAgnostic for ExtractPatternOperation {}
+impl GridAgnostic for ConvertUnitsOperation {}
+impl GridAgnostic for ExpectationOperation {}
+impl GridAgnostic for SortByTimeOperation {}
+impl GridAgnostic for PrivacyNoiseOperation {}
+impl MayRemoveRows for OutlierOperation {}
+impl MayRemoveRows for ApplyFlagsOperation {}
+impl MayRemoveRows for ValidateOperation {}
+impl MayRemoveRows for ParseTimestampOperation {}
+impl MayRemoveRows for DeduplicateOperation {}
+impl Regularizes for CalendarBucketOperation {}
+impl Regularizes for ResampleOperation {}
+impl Regularizes for ReconstructOperation {}
+impl Regularizes for RegularizeOperation {}
+impl Regularizes for StateDwellTimeOperation {}
+impl RequiresRegularGrid for LagOperation {}
+impl RequiresRegularGrid for DifferenceOperation {}
+
+/// Pipeline builder whose type tracks time-grid regularity
+pub struct TypedPipelineBuilder<S> {
+    operations: Vec<Box<dyn Operation>>,
+    state: PhantomData<S>,
+}
+
+impl TypedPipelineBuilder<Irregular> {
+    /// Create a builder for data with possibly irregular timestamps
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+            state: PhantomData,
+        }
+    }
+
+    /// Assert that the source data is already on a regular grid
+    pub fn assume_regular(self) -> TypedPipelineBuilder<Regular> {
+        self.transition()
+    }
+}
+
+impl Default for TypedPipelineBuilder<Irregular> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypedPipelineBuilder<Regular> {
+    /// Add an operation that requires a regular grid
+    pub fn add_on_grid<O: RequiresRegularGrid + 'static>(mut self, operation: O) -> Self {
+        self.operations.push(Box::new(operation));
+        self
+    }
+}
+
+impl<S> TypedPipelineBuilder<S> {
+    /// Add an operation that works on any grid
+    pub fn add_operation<O: GridAgnostic + 'static>(mut self, operation: O) -> Self {
+        self.operations.push(Box::new(operation));
+        self
+    }
+
+    /// Add an operation that may remove rows; the grid is no longer known to be regular
+    pub fn add_filter<O: MayRemoveRows + 'static>(
+        mut self,
+        operation: O,
+    ) -> TypedPipelineBuilder<Irregular> {
+        self.operations.push(Box::new(operation));
+        self.transition()
+    }
+
+    /// Add an operation configured to keep all rows, so the grid is preserved
+    ///
+    /// Fails if the operation is configured to remove rows; use `add_filter` for those.
+    pub fn add_keeping_rows<O: MayRemoveRows + 'static>(mut self, operation: O) -> Result<Self> {
+        if operation.removes_rows() {
+            return Err(IndustrytsError::InvalidOperation(format!(
+                "{} removes rows and does not preserve the time grid",
+                operation.name()
+            )));
+        }
+        self.operations.push(Box::new(operation));
+        Ok(self)
+    }
+
+    /// Add an operation that puts the data on a regular grid
+    pub fn regularize<O: Regularizes + 'static>(
+        mut self,
+        operation: O,
+    ) -> TypedPipelineBuilder<Regular> {
+        self.operations.push(Box::new(operation));
+        self.transition()
+    }
+
+    /// Add an operation without grid checks (e.g. custom operations)
+    pub fn add_unchecked(mut self, operation: Box<dyn Operation>) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Get the number of operations in the builder
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Check if the builder is empty
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Build the pipeline
+    pub fn build(self) -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        for operation in self.operations {
+            pipeline.add_operation(operation);
+        }
+        pipeline
+    }
+
+    fn transition<T>(self) -> TypedPipelineBuilder<T> {
+        TypedPipelineBuilder {
+            operations: self.operations,
+            state: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AggMethod, FillMethod};
+    use crate::operations::data_quality::{
+        FlagAction, OutlierAction, OutlierMethod, ValidationPolicy, ValidationRules,
+    };
+
+    #[test]
+    fn test_typed_builder_orders_steps() {
+        let pipeline = TypedPipelineBuilder::new()
+            .add_operation(FillNullOperation::new(FillMethod::Forward, None))
+            .regularize(CalendarBucketOperation::new("UTC", AggMethod::Mean, None).unwrap())
+            .add_on_grid(LagOperation::new(vec![1], None))
+            .add_operation(StandardizeOperation::new(None))
+            .build();
+        assert_eq!(pipeline.len(), 4);
+
+        let assumed = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_on_grid(LagOperation::new(vec![1, 2], None));
+        assert_eq!(assumed.len(), 1);
+    }
+
+    #[test]
+    fn test_typed_builder_row_removal() {
+        let outliers =
+            |action| OutlierOperation::new(OutlierMethod::ZScore { threshold: 3.0 }, action, None);
+        let builder = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_keeping_rows(outliers(OutlierAction::Flag))
+            .unwrap()
+            .add_on_grid(LagOperation::new(vec![1], None));
+        assert!(
+            builder
+                .add_keeping_rows(outliers(OutlierAction::Drop))
+                .is_err()
+        );
+
+        // Dropping rows leaves gaps, so lags need another regularizing step
+        let pipeline = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_filter(outliers(OutlierAction::Drop))
+            .regularize(CalendarBucketOperation::new("UTC", AggMethod::Mean, None).unwrap())
+            .add_on_grid(LagOperation::new(vec![1], None))
+            .build();
+        assert_eq!(pipeline.len(), 3);
+
+        let flags = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_keeping_rows(ApplyFlagsOperation::new(FlagAction::Interpolate, None))
+            .unwrap();
+        assert!(
+            flags
+                .add_keeping_rows(ApplyFlagsOperation::new(FlagAction::Drop, None))
+                .is_err()
+        );
+
+        let validate =
+            |policy| ValidateOperation::new(ValidationRules::default()).with_policy(policy);
+        let validated = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_keeping_rows(validate(ValidationPolicy::Warn))
+            .unwrap();
+        assert!(
+            validated
+                .add_keeping_rows(validate(ValidationPolicy::Drop))
+                .is_err()
+        );
+
+        let parse = || ParseTimestampOperation::new("time".to_string(), None);
+        let parsed = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_keeping_rows(parse())
+            .unwrap();
+        assert!(
+            parsed
+                .add_keeping_rows(parse().with_dead_letter("dead_letter"))
+                .is_err()
+        );
+
+        // Deduplication always removes rows
+        let dedup = || DeduplicateOperation::new(DuplicateKeep::Last);
+        let regular = TypedPipelineBuilder::new().assume_regular();
+        assert!(regular.add_keeping_rows(dedup()).is_err());
+        let deduplicated = TypedPipelineBuilder::new()
+            .assume_regular()
+            .add_filter(dedup())
+            .regularize(CalendarBucketOperation::new("UTC", AggMethod::Mean, None).unwrap())
+            .add_on_grid(LagOperation::new(vec![1], None));
+        assert_eq!(deduplicated.len(), 3);
+    }
+}
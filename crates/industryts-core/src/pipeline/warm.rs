@@ -0,0 +1,257 @@
+//! Warm starts of scheduled incremental runs
+//!
+//! A pipeline scheduled every few minutes on the rows that arrived since its last
+//! run would restart EWMA charts, CUSUM sums and lags on every run. With
+//! `Pipeline::process_warm` each run continues from the step states saved by the
+//! previous one (`RunState`), exactly as chunks of `Pipeline::process_stream`
+//! continue from each other, so the outputs of the runs together match one
+//! continuous run. Between runs the state is kept in a directory with
+//! `RunState::save` and `RunState::load`.
+
+use crate::core::{StatefulOperation, StepState, TimeSeriesData};
+use crate::error::{IndustrytsError, Result};
+use crate::pipeline::Pipeline;
+use crate::pipeline::chunked::{flush_states, process_chunk, state_of};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+/// File of a saved `RunState` listing the step states
+const MANIFEST: &str = "state.json";
+
+/// States of the steps of a pipeline at the end of a run
+#[derive(Debug, Clone, Default)]
+pub struct RunState {
+    /// Step name and state, in step order
+    steps: Vec<(String, StepState)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    steps: Vec<ManifestStep>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestStep {
+    name: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    values: serde_json::Value,
+    /// Arrow IPC file of the carried rows, relative to the state directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rows: Option<String>,
+}
+
+impl RunState {
+    /// Number of steps
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Save to `dir`, replacing a state saved there before
+    ///
+    /// Carried rows go to one Arrow IPC file per step; the manifest is written
+    /// last, so an interrupted save leaves the previous state readable.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for (index, (name, state)) in self.steps.iter().enumerate() {
+            let rows = match &state.rows {
+                Some(rows) => {
+                    let file = format!("step_{}.arrow", index);
+                    let tmp_path = dir.join(format!("{}.tmp", file));
+                    IpcWriter::new(File::create(&tmp_path)?).finish(&mut rows.clone())?;
+                    std::fs::rename(&tmp_path, dir.join(&file))?;
+                    Some(file)
+                }
+                None => None,
+            };
+            steps.push(ManifestStep {
+                name: name.clone(),
+                values: state.values.clone(),
+                rows,
+            });
+        }
+        let tmp_path = dir.join(format!("{}.tmp", MANIFEST));
+        std::fs::write(
+            &tmp_path,
+            serde_json::to_string_pretty(&Manifest { steps })?,
+        )?;
+        std::fs::rename(&tmp_path, dir.join(MANIFEST))?;
+        Ok(())
+    }
+
+    /// Load a state saved with `save`, or `None` if `dir` holds none yet
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let Ok(manifest) = std::fs::read_to_string(dir.join(MANIFEST)) else {
+            return Ok(None);
+        };
+        let manifest: Manifest = serde_json::from_str(&manifest)?;
+        let steps = manifest
+            .steps
+            .into_iter()
+            .map(|step| {
+                let rows = match &step.rows {
+                    Some(file) => Some(IpcReader::new(File::open(dir.join(file))?).finish()?),
+                    None => None,
+                };
+                Ok((
+                    step.name,
+                    StepState {
+                        rows,
+                        values: step.values,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self { steps }))
+    }
+}
+
+impl Pipeline {
+    /// Process the rows of a scheduled run, continuing from the previous run
+    ///
+    /// `state` is the state returned by the previous run (`None` for the first).
+    /// Returns the output rows that are final and the state for the next run;
+    /// rows a step holds back (e.g. an open resample bucket) come out of a later
+    /// run or `flush_warm`. Fails if a step's state cannot be saved or the state
+    /// was saved by a pipeline with other steps.
+    pub fn process_warm(
+        &self,
+        data: TimeSeriesData,
+        state: Option<&RunState>,
+    ) -> Result<(TimeSeriesData, RunState)> {
+        let mut states = self.warm_states(state)?;
+        let output = if data.is_empty() {
+            data
+        } else {
+            process_chunk(self, &mut states, data)?
+        };
+        Ok((output, self.save_states(&states)?))
+    }
+
+    /// Rows held back by the steps at the end of the run that saved `state`
+    pub fn flush_warm(&self, state: &RunState) -> Result<Option<TimeSeriesData>> {
+        let mut states = self.warm_states(Some(state))?;
+        flush_states(self, &mut states)
+    }
+
+    /// Step states, restored from `state` if given
+    fn warm_states(
+        &self,
+        state: Option<&RunState>,
+    ) -> Result<Vec<Box<dyn StatefulOperation + '_>>> {
+        let operations = self.sync_operations()?;
+        let mut states: Vec<_> = operations.iter().map(|op| state_of(*op)).collect();
+        let Some(state) = state else {
+            return Ok(states);
+        };
+        let names: Vec<&str> = operations.iter().map(|op| op.name()).collect();
+        let saved: Vec<&str> = state.steps.iter().map(|(name, _)| name.as_str()).collect();
+        if names != saved {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Saved run state is for the steps {:?}, the pipeline has {:?}",
+                saved, names
+            )));
+        }
+        for (index, (step, (name, saved))) in states.iter_mut().zip(&state.steps).enumerate() {
+            step.restore_state(saved.clone()).map_err(|e| {
+                IndustrytsError::InvalidOperation(format!("step {} ({}): {}", index, name, e))
+            })?;
+        }
+        Ok(states)
+    }
+
+    fn save_states(&self, states: &[Box<dyn StatefulOperation + '_>]) -> Result<RunState> {
+        let operations = self.sync_operations()?;
+        let steps = operations
+            .iter()
+            .zip(states)
+            .enumerate()
+            .map(|(index, (operation, state))| {
+                let saved = state.save_state().map_err(|e| {
+                    IndustrytsError::InvalidOperation(format!(
+                        "step {} ({}): {}",
+                        index,
+                        operation.name(),
+                        e
+                    ))
+                })?;
+                Ok((operation.name().to_string(), saved))
+            })
+            .collect::<Result<_>>()?;
+        Ok(RunState { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AggMethod;
+    use crate::operations::{CusumOperation, EwmaOperation, LagOperation, ResampleOperation};
+    use crate::testing::Fixture;
+    use std::time::Duration;
+
+    #[test]
+    fn test_warm_runs_match_continuous_run() {
+        let noise = [0.5, -0.5, 1.0, -1.0, 0.0];
+        let values: Vec<f64> = (0..60)
+            .map(|i| noise[i % 5] + if i < 45 { 0.0 } else { 3.0 })
+            .collect();
+        let data = Fixture::every(Duration::from_secs(60), 60)
+            .with_column("temp", values)
+            .build()
+            .unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_operation(Box::new(
+            ResampleOperation::new("2m", AggMethod::Mean, None).unwrap(),
+        ));
+        pipeline.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        pipeline.add_operation(Box::new(
+            EwmaOperation::new(0.2, 3.0, Some(vec!["temp".to_string()]))
+                .unwrap()
+                .with_target(0.0, 1.0),
+        ));
+        pipeline.add_operation(Box::new(
+            CusumOperation::new(0.5, 5.0, Some(vec!["temp".to_string()])).with_target(0.0, 1.0),
+        ));
+        let expected = pipeline.process(data.clone()).unwrap();
+
+        // Three scheduled runs, the state kept on disk in between
+        let dir = std::env::temp_dir().join(format!("industryts_warm_{}", std::process::id()));
+        let mut combined: Option<DataFrame> = None;
+        for offset in [0, 20, 40] {
+            let rows = data.dataframe().slice(offset, 20);
+            let run = TimeSeriesData::with_metadata(rows, data.metadata().clone()).unwrap();
+            let state = RunState::load(&dir).unwrap();
+            assert_eq!(state.is_some(), offset > 0);
+            let (output, state) = pipeline.process_warm(run, state.as_ref()).unwrap();
+            state.save(&dir).unwrap();
+            match &mut combined {
+                Some(df) => df.vstack_mut(output.dataframe()).unwrap(),
+                None => combined.insert(output.dataframe().clone()),
+            };
+        }
+        // The last resample bucket is held back until the runs are flushed
+        let state = RunState::load(&dir).unwrap().unwrap();
+        assert_eq!(state.len(), 4);
+        let flushed = pipeline.flush_warm(&state).unwrap().unwrap();
+        assert_eq!(flushed.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut combined = combined.unwrap();
+        combined.vstack_mut(flushed.dataframe()).unwrap();
+        assert!(combined.equals_missing(expected.dataframe()));
+        let alarms = combined.column("temp_cusum_alarm").unwrap();
+        assert!(alarms.i32().unwrap().into_no_null_iter().any(|a| a == 1));
+
+        let mut other = Pipeline::new();
+        other.add_operation(Box::new(LagOperation::new(vec![1], None)));
+        assert!(other.process_warm(data, Some(&state)).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//! Stall detection of streaming sources
+//!
+//! A stream merged from several connectors names the connector of each row in a
+//! source column. `SourceWatchdog` compares the latest sample of every source
+//! with the stream time: the latest timestamp of any source, or a heartbeat
+//! given to `StreamProcessor::advance_to` when all of them are quiet. A source
+//! whose latest sample falls further behind than its threshold is stalled until
+//! it delivers again. Both transitions are reported as `WatchdogEvent`s in the
+//! `StreamBatch` and logged as warnings.
+//!
+//! With gap filling, a stalled source gets a row of nulls every `interval` after
+//! its last sample, keeping its group keys, so KPIs computed downstream show the
+//! outage instead of carrying on as if the source were healthy. Null rows are
+//! only added ahead of the watermark, so the whole outage is filled when the
+//! threshold is within the allowed lateness. Sources that never delivered are
+//! reported but not filled.
+
+use crate::core::TimeSeriesData;
+use crate::error::Result;
+use crate::operations::temporal::regularize::null_rows;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Change of the state of a watched source
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchdogEvent {
+    /// No sample for longer than the threshold
+    Stalled {
+        source: String,
+        /// Latest sample of the source, `None` if it never delivered
+        last_seen_ms: Option<i64>,
+        /// Stream time at which the stall was detected
+        stream_ms: i64,
+    },
+    /// Samples arrive again after a stall
+    Resumed {
+        source: String,
+        /// Latest sample before the stall
+        last_seen_ms: Option<i64>,
+        /// First sample after the stall
+        resumed_ms: i64,
+    },
+}
+
+struct WatchedSource {
+    threshold: Duration,
+    last_seen_ms: Option<i64>,
+    /// Latest row of the source, the template of gap-filling rows
+    last_row: Option<DataFrame>,
+    stalled: bool,
+    /// Latest timestamp up to which the gap has been filled
+    filled_to_ms: Option<i64>,
+}
+
+impl WatchedSource {
+    fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last_seen_ms: None,
+            last_row: None,
+            stalled: false,
+            filled_to_ms: None,
+        }
+    }
+}
+
+/// Per-source stall detection of a stream (see `StreamProcessor::with_watchdog`)
+pub struct SourceWatchdog {
+    source_column: String,
+    threshold: Duration,
+    fill_interval: Option<Duration>,
+    sources: BTreeMap<String, WatchedSource>,
+    /// Stream time when watching began, the reference of sources never seen
+    started_ms: Option<i64>,
+    time_column: Option<String>,
+    group_columns: Vec<String>,
+}
+
+impl SourceWatchdog {
+    /// Watch the sources named in `source_column`, stalled after `threshold`
+    /// without a sample
+    pub fn new(source_column: &str, threshold: Duration) -> Self {
+        Self {
+            source_column: source_column.to_string(),
+            threshold,
+            fill_interval: None,
+            sources: BTreeMap::new(),
+            started_ms: None,
+            time_column: None,
+            group_columns: Vec::new(),
+        }
+    }
+
+    /// Threshold of one source, e.g. a connector polling less often; the source
+    /// is expected even before it delivers
+    pub fn with_source_threshold(mut self, source: &str, threshold: Duration) -> Self {
+        self.sources
+            .insert(source.to_string(), WatchedSource::new(threshold));
+        self
+    }
+
+    /// Fill stalled periods with null rows every `interval`
+    pub fn with_gap_fill(mut self, interval: Duration) -> Self {
+        self.fill_interval = Some(interval).filter(|i| !i.is_zero());
+        self
+    }
+
+    /// Sources currently stalled
+    pub fn stalled_sources(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .filter(|(_, s)| s.stalled)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Latest sample of `source`
+    pub fn last_seen_ms(&self, source: &str) -> Option<i64> {
+        self.sources.get(source)?.last_seen_ms
+    }
+
+    /// Record the samples of `chunk`, if any, and check every source against
+    /// `stream_ms`; returns the events and the null rows filling outages
+    pub(crate) fn update(
+        &mut self,
+        chunk: Option<&TimeSeriesData>,
+        stream_ms: i64,
+        watermark_ms: i64,
+    ) -> Result<(Vec<WatchdogEvent>, Option<DataFrame>)> {
+        let mut events = Vec::new();
+        let mut fills = Vec::new();
+        self.started_ms.get_or_insert(stream_ms);
+        if let Some(chunk) = chunk {
+            self.observe(chunk, watermark_ms, &mut events, &mut fills)?;
+        }
+
+        let started = self.started_ms.unwrap_or(stream_ms);
+        for (name, source) in self.sources.iter_mut() {
+            let behind = stream_ms - source.last_seen_ms.unwrap_or(started);
+            if !source.stalled && behind > source.threshold.as_millis() as i64 {
+                source.stalled = true;
+                tracing::warn!(
+                    source = %name,
+                    last_seen_ms = ?source.last_seen_ms,
+                    stream_ms,
+                    "streaming source stalled"
+                );
+                events.push(WatchdogEvent::Stalled {
+                    source: name.clone(),
+                    last_seen_ms: source.last_seen_ms,
+                    stream_ms,
+                });
+            }
+        }
+        let gap_fill = self.gap_fill();
+        for source in self.sources.values_mut().filter(|s| s.stalled) {
+            fills.extend(fill(source, gap_fill.as_ref(), stream_ms, watermark_ms)?);
+        }
+
+        let mut fills = fills.into_iter();
+        let Some(mut rows) = fills.next() else {
+            return Ok((events, None));
+        };
+        for more in fills {
+            rows.vstack_mut(&more)?;
+        }
+        Ok((events, Some(rows)))
+    }
+
+    fn observe(
+        &mut self,
+        chunk: &TimeSeriesData,
+        watermark_ms: i64,
+        events: &mut Vec<WatchdogEvent>,
+        fills: &mut Vec<DataFrame>,
+    ) -> Result<()> {
+        self.time_column = Some(chunk.time_column().to_string());
+        self.group_columns = chunk.group_columns().to_vec();
+        let gap_fill = self.gap_fill();
+
+        // First and latest sample (with its row) of each source in the chunk
+        let mut spans: BTreeMap<String, (i64, i64, usize)> = BTreeMap::new();
+        let names = chunk
+            .dataframe()
+            .column(&self.source_column)?
+            .as_materialized_series()
+            .clone();
+        let times = chunk.timestamps_ms()?;
+        for (row, (name, time)) in names.iter().zip(times.iter()).enumerate() {
+            let Some(time) = time else {
+                continue;
+            };
+            let name = match name {
+                AnyValue::Null => continue,
+                name => name
+                    .get_str()
+                    .map_or_else(|| name.to_string(), String::from),
+            };
+            let span = spans.entry(name).or_insert((time, time, row));
+            span.0 = span.0.min(time);
+            if time >= span.1 {
+                (span.1, span.2) = (time, row);
+            }
+        }
+
+        for (name, (first, latest, row)) in spans {
+            let threshold = self.threshold;
+            let mut source = self
+                .sources
+                .remove(&name)
+                .unwrap_or_else(|| WatchedSource::new(threshold));
+            if source.stalled {
+                fills.extend(fill(
+                    &mut source,
+                    gap_fill.as_ref(),
+                    first - 1,
+                    watermark_ms,
+                )?);
+                tracing::info!(source = %name, resumed_ms = first, "streaming source resumed");
+                events.push(WatchdogEvent::Resumed {
+                    source: name.clone(),
+                    last_seen_ms: source.last_seen_ms,
+                    resumed_ms: first,
+                });
+                source.stalled = false;
+                source.filled_to_ms = None;
+            }
+            if source.last_seen_ms.is_none_or(|seen| latest >= seen) {
+                source.last_seen_ms = Some(latest);
+                source.last_row = Some(chunk.dataframe().slice(row as i64, 1));
+            }
+            self.sources.insert(name, source);
+        }
+        Ok(())
+    }
+
+    fn gap_fill(&self) -> Option<GapFill> {
+        let mut key_columns = vec![self.source_column.clone()];
+        key_columns.extend(self.group_columns.iter().cloned());
+        Some(GapFill {
+            interval_ms: self.fill_interval?.as_millis() as i64,
+            time_column: self.time_column.clone()?,
+            key_columns,
+        })
+    }
+}
+
+/// Grid and layout of gap-filling rows
+struct GapFill {
+    interval_ms: i64,
+    time_column: String,
+    key_columns: Vec<String>,
+}
+
+/// Null rows of `source` on its fill grid up to `to_ms`, ahead of the watermark
+fn fill(
+    source: &mut WatchedSource,
+    gap_fill: Option<&GapFill>,
+    to_ms: i64,
+    watermark_ms: i64,
+) -> Result<Option<DataFrame>> {
+    let (Some(gap_fill), Some(last_seen), Some(last_row)) =
+        (gap_fill, source.last_seen_ms, &source.last_row)
+    else {
+        return Ok(None);
+    };
+    let interval = gap_fill.interval_ms;
+    let from = source
+        .filled_to_ms
+        .unwrap_or(last_seen)
+        .max(watermark_ms - 1);
+    // Grid points after `from`, at whole intervals from the last sample
+    let mut t = last_seen + ((from - last_seen).div_euclid(interval) + 1) * interval;
+    let mut millis = Vec::new();
+    while t <= to_ms {
+        millis.push(t);
+        t += interval;
+    }
+    source.filled_to_ms = Some(source.filled_to_ms.map_or(to_ms, |f| f.max(to_ms)));
+    if millis.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(null_rows(
+        last_row,
+        &gap_fill.time_column,
+        &gap_fill.key_columns,
+        &millis,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ExecutionContext;
+    use crate::pipeline::{LatePolicy, Pipeline};
+
+    const MIN: i64 = 60_000;
+
+    fn chunk(minute: i64, sources: &[&str]) -> TimeSeriesData {
+        let times = vec![minute * MIN; sources.len()];
+        let time_series = Series::new("time".into(), times)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("source".into(), sources).into(),
+            Series::new("kw".into(), vec![minute as f64; sources.len()]).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_stalled_source_is_reported_and_filled() {
+        let pipeline = Pipeline::new();
+        let mut stream = pipeline
+            .stream(Duration::from_secs(300), LatePolicy::Drop)
+            .with_watchdog(
+                SourceWatchdog::new("source", Duration::from_secs(180))
+                    .with_gap_fill(Duration::from_secs(60)),
+            );
+        let mut context = ExecutionContext::new();
+        let mut events = Vec::new();
+        let mut outputs = Vec::new();
+        // Source b stops after minute 2 and comes back at minute 8
+        for minute in 0..10 {
+            let sources = if (3..8).contains(&minute) {
+                vec!["a"]
+            } else {
+                vec!["a", "b"]
+            };
+            let (batch, ctx) = stream.push(chunk(minute, &sources), context).unwrap();
+            context = ctx;
+            if minute == 6 {
+                assert_eq!(stream.watchdog().unwrap().stalled_sources(), ["b"]);
+            }
+            events.extend(batch.watchdog);
+            outputs.extend(batch.output);
+        }
+        let (batch, _) = stream.flush(context).unwrap();
+        outputs.extend(batch.output);
+
+        assert_eq!(
+            events,
+            [
+                WatchdogEvent::Stalled {
+                    source: "b".to_string(),
+                    last_seen_ms: Some(2 * MIN),
+                    stream_ms: 6 * MIN,
+                },
+                WatchdogEvent::Resumed {
+                    source: "b".to_string(),
+                    last_seen_ms: Some(2 * MIN),
+                    resumed_ms: 8 * MIN,
+                },
+            ]
+        );
+        assert!(stream.watchdog().unwrap().stalled_sources().is_empty());
+
+        // The outage of b shows as null rows every minute, not as a gap
+        let mut b: Vec<(i64, Option<f64>)> = Vec::new();
+        for output in &outputs {
+            let df = output.dataframe();
+            let times = output.timestamps_ms().unwrap();
+            let sources = df.column("source").unwrap().str().unwrap().clone();
+            let kw = df.column("kw").unwrap().f64().unwrap().clone();
+            for i in 0..df.height() {
+                if sources.get(i) == Some("b") {
+                    b.push((times.get(i).unwrap() / MIN, kw.get(i)));
+                }
+            }
+        }
+        let expected: Vec<(i64, Option<f64>)> = (0..10)
+            .map(|m| (m, (!(3..8).contains(&m)).then_some(m as f64)))
+            .collect();
+        assert_eq!(b, expected);
+    }
+}
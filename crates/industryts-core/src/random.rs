@@ -0,0 +1,68 @@
+//! Deterministic randomness
+//!
+//! All stochastic code in the crate draws from a `SeedSequence` so that runs are
+//! reproducible for audits. A sequence derives independent, stable sub-seeds by key
+//! (e.g. per pipeline step or per generated channel), so adding or reordering
+//! consumers does not change the random streams of the others.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Random number generator used throughout the crate
+pub type DeterministicRng = ChaCha8Rng;
+
+/// Root seed from which keyed sub-seeds and generators are derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeedSequence {
+    seed: u64,
+}
+
+impl SeedSequence {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Root seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Derive a stable sub-seed for `key`
+    pub fn derive(&self, key: &str) -> u64 {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.seed.to_le_bytes());
+        hasher.update(key.as_bytes());
+        let bytes = hasher.finalize();
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&bytes.as_bytes()[..8]);
+        u64::from_le_bytes(seed)
+    }
+
+    /// Child sequence for `key`
+    pub fn child(&self, key: &str) -> Self {
+        Self::new(self.derive(key))
+    }
+
+    /// Generator for `key`
+    pub fn rng(&self, key: &str) -> DeterministicRng {
+        DeterministicRng::seed_from_u64(self.derive(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_derived_streams_are_stable_and_independent() {
+        let seq = SeedSequence::new(42);
+        assert_eq!(seq.derive("a"), SeedSequence::new(42).derive("a"));
+        assert_ne!(seq.derive("a"), seq.derive("b"));
+        assert_ne!(seq.derive("a"), SeedSequence::new(43).derive("a"));
+
+        let x: u64 = seq.rng("a").random();
+        let y: u64 = seq.rng("a").random();
+        assert_eq!(x, y);
+    }
+}
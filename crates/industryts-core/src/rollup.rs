@@ -0,0 +1,344 @@
+//! Multi-resolution rollup cache
+//!
+//! Long-range trend queries over raw historian data are dominated by I/O. A
+//! `RollupCache` precomputes aggregates of a dataset at increasing resolutions
+//! (e.g. raw → 1m → 1h → 1d), stores each level as an Arrow IPC file next to a TOML
+//! manifest, and answers a time-range query from the finest level that fits a
+//! point budget.
+//!
+//! Every rollup level keeps `<column>_sum`, `_count`, `_min` and `_max`, so coarser
+//! levels are computed from the previous level without loss and queries can return
+//! exact means. Levels must nest (each interval a multiple of the previous one).
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use crate::utils::parse_frequency;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Name of the raw (unaggregated) level
+pub const RAW_LEVEL: &str = "raw";
+
+const MANIFEST_FILE: &str = "rollup.toml";
+
+/// Stored level of a rollup cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollupLevel {
+    /// Level name, e.g. "raw" or "1h"
+    pub name: String,
+    /// Bucket interval (None for the raw level)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub every: Option<String>,
+    pub rows: usize,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+impl RollupLevel {
+    /// Expected number of rows in `[start_ms, end_ms)`
+    fn estimate_rows(&self, start_ms: i64, end_ms: i64) -> f64 {
+        let span = (self.end_ms - self.start_ms).max(1) as f64;
+        let overlap = (end_ms.min(self.end_ms + 1) - start_ms.max(self.start_ms)).max(0) as f64;
+        self.rows as f64 * overlap / span
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    time_column: String,
+    columns: Vec<String>,
+    levels: Vec<RollupLevel>,
+}
+
+/// Precomputed multi-resolution aggregates of a dataset
+pub struct RollupCache {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl RollupCache {
+    /// Build the cache for `data` in `dir` with rollup levels such as `["1m", "1h", "1d"]`
+    ///
+    /// Only numeric feature columns are rolled up. Existing level files are replaced.
+    pub fn build<P: Into<PathBuf>>(dir: P, data: &TimeSeriesData, levels: &[&str]) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        let time_col = data.time_column().to_string();
+        let columns: Vec<String> = data
+            .feature_columns()
+            .iter()
+            .filter(|c| {
+                data.dataframe()
+                    .column(c)
+                    .map(|s| s.dtype().is_primitive_numeric())
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+
+        let mut selected = vec![time_col.clone()];
+        selected.extend(columns.iter().cloned());
+        let raw = data
+            .dataframe()
+            .select(selected)?
+            .sort([time_col.as_str()], SortMultipleOptions::default())?;
+
+        let mut cache = Self {
+            dir,
+            manifest: Manifest {
+                time_column: time_col,
+                columns,
+                levels: Vec::new(),
+            },
+        };
+        cache.store_level(RAW_LEVEL, None, raw.clone())?;
+
+        let mut previous = raw;
+        let mut previous_ns = 0;
+        for &every in levels {
+            let duration = parse_frequency(every)?;
+            if duration.duration_ns() <= previous_ns {
+                return Err(IndustrytsError::ConfigError(format!(
+                    "Rollup levels must be increasing, got {} after a finer or equal level",
+                    every
+                )));
+            }
+            let rolled = cache.roll_up(&previous, duration, previous_ns == 0)?;
+            cache.store_level(every, Some(every.to_string()), rolled.clone())?;
+            previous = rolled;
+            previous_ns = duration.duration_ns();
+        }
+
+        let manifest = toml::to_string_pretty(&cache.manifest).map_err(|e| {
+            IndustrytsError::ConfigError(format!("Failed to write rollup manifest: {}", e))
+        })?;
+        std::fs::write(cache.dir.join(MANIFEST_FILE), manifest)?;
+        Ok(cache)
+    }
+
+    /// Open a cache previously built in `dir`
+    pub fn open<P: Into<PathBuf>>(dir: P) -> Result<Self> {
+        let dir = dir.into();
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE))?;
+        let manifest: Manifest = toml::from_str(&manifest)?;
+        Ok(Self { dir, manifest })
+    }
+
+    /// Stored levels, finest first
+    pub fn levels(&self) -> &[RollupLevel] {
+        &self.manifest.levels
+    }
+
+    /// Aggregate `df` into buckets of `every`; `from_raw` selects raw or rollup inputs
+    fn roll_up(&self, df: &DataFrame, every: Duration, from_raw: bool) -> Result<DataFrame> {
+        let time_col = self.manifest.time_column.as_str();
+        let mut aggs = Vec::new();
+        for c in &self.manifest.columns {
+            let name = |stat: &str| format!("{}_{}", c, stat);
+            if from_raw {
+                aggs.push(
+                    col(c.as_str())
+                        .cast(DataType::Float64)
+                        .sum()
+                        .alias(name("sum")),
+                );
+                aggs.push(
+                    col(c.as_str())
+                        .count()
+                        .cast(DataType::Int64)
+                        .alias(name("count")),
+                );
+                aggs.push(
+                    col(c.as_str())
+                        .cast(DataType::Float64)
+                        .min()
+                        .alias(name("min")),
+                );
+                aggs.push(
+                    col(c.as_str())
+                        .cast(DataType::Float64)
+                        .max()
+                        .alias(name("max")),
+                );
+            } else {
+                aggs.push(col(name("sum")).sum());
+                aggs.push(col(name("count")).sum());
+                aggs.push(col(name("min")).min());
+                aggs.push(col(name("max")).max());
+            }
+        }
+
+        let options = DynamicGroupOptions {
+            every,
+            period: every,
+            offset: Duration::parse("0ns"),
+            start_by: StartBy::WindowBound,
+            closed_window: ClosedWindow::Left,
+            label: Label::Left,
+            include_boundaries: false,
+            ..Default::default()
+        };
+        Ok(df
+            .clone()
+            .lazy()
+            .group_by_dynamic(col(time_col), [], options)
+            .agg(aggs)
+            .collect()?)
+    }
+
+    fn level_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.arrow", name))
+    }
+
+    fn store_level(&mut self, name: &str, every: Option<String>, mut df: DataFrame) -> Result<()> {
+        let data = TimeSeriesData::new(df.clone(), Some(&self.manifest.time_column))?;
+        let timestamps = data.timestamps_ms()?;
+        let mut file = File::create(self.level_path(name))?;
+        IpcWriter::new(&mut file).finish(&mut df)?;
+
+        self.manifest.levels.push(RollupLevel {
+            name: name.to_string(),
+            every,
+            rows: df.height(),
+            start_ms: timestamps.min().unwrap_or(0),
+            end_ms: timestamps.max().unwrap_or(0),
+        });
+        Ok(())
+    }
+
+    /// Load a stored level as is
+    pub fn load_level(&self, name: &str) -> Result<DataFrame> {
+        if !self.manifest.levels.iter().any(|l| l.name == name) {
+            return Err(IndustrytsError::ConfigError(format!(
+                "Unknown rollup level: {}",
+                name
+            )));
+        }
+        let file = File::open(self.level_path(name))?;
+        Ok(IpcReader::new(file).finish()?)
+    }
+
+    /// Finest level expected to return at most `max_points` rows for the range
+    pub fn select_level(&self, start_ms: i64, end_ms: i64, max_points: usize) -> &RollupLevel {
+        let levels = &self.manifest.levels;
+        levels
+            .iter()
+            .find(|l| l.estimate_rows(start_ms, end_ms) <= max_points as f64)
+            .unwrap_or(&levels[levels.len() - 1])
+    }
+
+    /// Query `[start_ms, end_ms)` at the finest resolution within `max_points` rows
+    ///
+    /// Raw results keep the original columns; rollup results have `<column>_mean`,
+    /// `_min`, `_max` and `_count` per column. The level used is recorded in the
+    /// `rollup.level` tag.
+    pub fn query(&self, start_ms: i64, end_ms: i64, max_points: usize) -> Result<TimeSeriesData> {
+        let level = self.select_level(start_ms, end_ms, max_points);
+        let time_col = self.manifest.time_column.as_str();
+
+        let df = self.load_level(&level.name)?;
+        let timestamps = TimeSeriesData::new(df.clone(), Some(time_col))?.timestamps_ms()?;
+        let in_range = timestamps.gt_eq(start_ms) & timestamps.lt(end_ms);
+        let mut df = df.filter(&in_range)?;
+
+        if level.every.is_some() {
+            let mut exprs = vec![col(time_col)];
+            for c in &self.manifest.columns {
+                let name = |stat: &str| format!("{}_{}", c, stat);
+                exprs.push(
+                    (col(name("sum")) / col(name("count")).cast(DataType::Float64))
+                        .alias(name("mean")),
+                );
+                exprs.push(col(name("min")));
+                exprs.push(col(name("max")));
+                exprs.push(col(name("count")));
+            }
+            df = df.lazy().select(exprs).collect()?;
+        }
+
+        let mut result = TimeSeriesData::new(df, Some(time_col))?;
+        result.add_tag("rollup.level".to_string(), level.name.clone());
+        Ok(result)
+    }
+
+    /// Directory holding the cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data() -> TimeSeriesData {
+        // Two days of 10-second samples
+        let n = 2 * 8_640;
+        let dates_ms: Vec<i64> = (0..n).map(|i| 1704067200000i64 + i * 10_000).collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let values: Vec<f64> = (0..n).map(|i| (i % 360) as f64).collect();
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_rollup_cache_serves_levels() {
+        let dir = std::env::temp_dir().join("industryts_rollup_test");
+        let data = make_data();
+        RollupCache::build(&dir, &data, &["1m", "1h", "1d"]).unwrap();
+
+        let cache = RollupCache::open(&dir).unwrap();
+        let names: Vec<&str> = cache.levels().iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["raw", "1m", "1h", "1d"]);
+
+        let start = 1704067200000i64;
+        let hour = 3_600_000;
+        let raw = cache.query(start, start + hour, 1_000).unwrap();
+        assert_eq!(raw.get_tag("rollup.level"), Some("raw"));
+        assert_eq!(raw.len(), 360);
+
+        let hourly = cache.query(start, start + 48 * hour, 100).unwrap();
+        assert_eq!(hourly.get_tag("rollup.level"), Some("1h"));
+        assert_eq!(hourly.len(), 48);
+        let means = hourly
+            .dataframe()
+            .column("value_mean")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .clone();
+        assert_eq!(means.get(0), Some(179.5));
+
+        let daily = cache.query(start, start + 48 * hour, 2).unwrap();
+        assert_eq!(daily.get_tag("rollup.level"), Some("1d"));
+        let counts = daily
+            .dataframe()
+            .column("value_count")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .clone();
+        assert_eq!(counts.get(0), Some(8_640));
+        assert_eq!(
+            daily
+                .dataframe()
+                .column("value_max")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(0),
+            Some(359.0)
+        );
+
+        assert!(RollupCache::build(&dir, &data, &["1h", "1m"]).is_err());
+    }
+}
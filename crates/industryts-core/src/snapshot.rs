@@ -0,0 +1,295 @@
+//! Golden-output snapshot testing
+//!
+//! Compares a pipeline's output against a stored snapshot with per-column
+//! absolute/relative tolerances. Snapshots are written as Arrow IPC files so dtypes
+//! round-trip exactly. A missing snapshot is created on first run; set
+//! `INDUSTRYTS_UPDATE_SNAPSHOTS=1` to overwrite existing ones.
+
+use crate::core::TimeSeriesData;
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+/// Environment variable that forces snapshots to be rewritten
+pub const UPDATE_SNAPSHOTS_ENV: &str = "INDUSTRYTS_UPDATE_SNAPSHOTS";
+
+/// Numeric tolerance: values match when `|actual - expected| <= abs + rel * |expected|`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub abs: f64,
+    pub rel: f64,
+}
+
+impl Tolerance {
+    pub fn new(abs: f64, rel: f64) -> Self {
+        Self { abs, rel }
+    }
+
+    /// Exact equality
+    pub fn exact() -> Self {
+        Self::new(0.0, 0.0)
+    }
+
+    fn matches(&self, actual: f64, expected: f64) -> bool {
+        if actual.is_nan() || expected.is_nan() {
+            return actual.is_nan() && expected.is_nan();
+        }
+        (actual - expected).abs() <= self.abs + self.rel * expected.abs()
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::new(1e-9, 1e-9)
+    }
+}
+
+/// Differences found between actual output and a snapshot
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiff {
+    pub mismatches: Vec<String>,
+}
+
+impl SnapshotDiff {
+    /// Whether the output matched the snapshot
+    pub fn is_match(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_match() {
+            return write!(f, "output matches snapshot");
+        }
+        write!(f, "{} mismatch(es):", self.mismatches.len())?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares output data against golden snapshots
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotComparator {
+    tolerance: Tolerance,
+    column_tolerances: HashMap<String, Tolerance>,
+}
+
+impl SnapshotComparator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tolerance for numeric columns without a specific override
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Tolerance for a single column
+    pub fn with_column_tolerance(mut self, column: &str, tolerance: Tolerance) -> Self {
+        self.column_tolerances.insert(column.to_string(), tolerance);
+        self
+    }
+
+    /// Compare `actual` against an expected DataFrame
+    pub fn compare(&self, actual: &DataFrame, expected: &DataFrame) -> Result<SnapshotDiff> {
+        let mut diff = SnapshotDiff::default();
+
+        let actual_names: Vec<String> = actual
+            .get_column_names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let expected_names: Vec<String> = expected
+            .get_column_names()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        if actual_names != expected_names {
+            diff.mismatches.push(format!(
+                "columns {:?} != expected {:?}",
+                actual_names, expected_names
+            ));
+        }
+        if actual.height() != expected.height() {
+            diff.mismatches.push(format!(
+                "row count {} != expected {}",
+                actual.height(),
+                expected.height()
+            ));
+            return Ok(diff);
+        }
+
+        for name in expected_names.iter().filter(|n| actual_names.contains(n)) {
+            let a = actual.column(name)?.as_materialized_series();
+            let e = expected.column(name)?.as_materialized_series();
+
+            if a.dtype() != e.dtype() {
+                diff.mismatches.push(format!(
+                    "{}: dtype {} != expected {}",
+                    name,
+                    a.dtype(),
+                    e.dtype()
+                ));
+                continue;
+            }
+
+            if a.dtype().is_float() {
+                let tolerance = self.column_tolerances.get(name).unwrap_or(&self.tolerance);
+                self.compare_floats(name, a, e, tolerance, &mut diff)?;
+            } else if !a.equals_missing(e) {
+                let first = (0..a.len()).find(|&i| a.get(i).ok() != e.get(i).ok());
+                diff.mismatches.push(match first {
+                    Some(i) => format!(
+                        "{}: row {} is {} (expected {})",
+                        name,
+                        i,
+                        a.get(i)?,
+                        e.get(i)?
+                    ),
+                    None => format!("{}: values differ", name),
+                });
+            }
+        }
+
+        Ok(diff)
+    }
+
+    fn compare_floats(
+        &self,
+        name: &str,
+        actual: &Series,
+        expected: &Series,
+        tolerance: &Tolerance,
+        diff: &mut SnapshotDiff,
+    ) -> Result<()> {
+        let a = actual.cast(&DataType::Float64)?;
+        let e = expected.cast(&DataType::Float64)?;
+
+        let mut count = 0;
+        let mut first = None;
+        for (i, (a, e)) in a.f64()?.into_iter().zip(e.f64()?).enumerate() {
+            let ok = match (a, e) {
+                (Some(a), Some(e)) => tolerance.matches(a, e),
+                (None, None) => true,
+                _ => false,
+            };
+            if !ok {
+                count += 1;
+                first.get_or_insert((i, a, e));
+            }
+        }
+
+        if let Some((i, a, e)) = first {
+            diff.mismatches.push(format!(
+                "{}: {} value(s) outside tolerance (abs {}, rel {}), first at row {}: {:?} vs {:?}",
+                name, count, tolerance.abs, tolerance.rel, i, a, e
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compare `data` against the snapshot at `path`
+    ///
+    /// Writes the snapshot instead when it does not exist yet or when
+    /// `INDUSTRYTS_UPDATE_SNAPSHOTS` is set. Returns a `ValidationError` describing
+    /// the differences on mismatch.
+    pub fn assert_snapshot(&self, data: &TimeSeriesData, path: &Path) -> Result<()> {
+        if !path.exists() || std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            return save_snapshot(data, path);
+        }
+
+        let expected = load_snapshot(path)?;
+        let diff = self.compare(data.dataframe(), &expected)?;
+        if diff.is_match() {
+            Ok(())
+        } else {
+            Err(IndustrytsError::ValidationError(format!(
+                "snapshot {}: {}",
+                path.display(),
+                diff
+            )))
+        }
+    }
+}
+
+/// Write `data` as a snapshot file
+pub fn save_snapshot(data: &TimeSeriesData, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut df = data.dataframe().clone();
+    let mut file = File::create(path)?;
+    IpcWriter::new(&mut file).finish(&mut df)?;
+    Ok(())
+}
+
+/// Read a snapshot file
+pub fn load_snapshot(path: &Path) -> Result<DataFrame> {
+    let file = File::open(path)?;
+    Ok(IpcReader::new(file).finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_data(values: &[f64]) -> TimeSeriesData {
+        let dates_ms: Vec<i64> = (0..values.len() as i64)
+            .map(|i| 1704067200000i64 + i * 60_000)
+            .collect();
+        let time_series = Series::new("time".into(), dates_ms)
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+
+        let df = DataFrame::new(vec![
+            time_series.into(),
+            Series::new("value".into(), values).into(),
+        ])
+        .unwrap();
+        TimeSeriesData::new(df, Some("time")).unwrap()
+    }
+
+    #[test]
+    fn test_compare_with_tolerance() {
+        let expected = make_data(&[1.0, 2.0, 3.0]);
+        let actual = make_data(&[1.0, 2.001, 3.0]);
+
+        let strict = SnapshotComparator::new();
+        let diff = strict
+            .compare(actual.dataframe(), expected.dataframe())
+            .unwrap();
+        assert!(!diff.is_match());
+
+        let loose =
+            SnapshotComparator::new().with_column_tolerance("value", Tolerance::new(0.01, 0.0));
+        let diff = loose
+            .compare(actual.dataframe(), expected.dataframe())
+            .unwrap();
+        assert!(diff.is_match());
+    }
+
+    #[test]
+    fn test_assert_snapshot_roundtrip() {
+        let path = std::env::temp_dir().join("industryts_snapshot_test.arrow");
+        std::fs::remove_file(&path).ok();
+
+        let comparator = SnapshotComparator::new();
+        comparator
+            .assert_snapshot(&make_data(&[1.0, 2.0]), &path)
+            .unwrap();
+        comparator
+            .assert_snapshot(&make_data(&[1.0, 2.0]), &path)
+            .unwrap();
+        let result = comparator.assert_snapshot(&make_data(&[1.0, 5.0]), &path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(IndustrytsError::ValidationError(_))));
+    }
+}
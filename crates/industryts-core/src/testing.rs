@@ -0,0 +1,266 @@
+//! Test helpers for operations
+//!
+//! `assert_operation` runs an operation on an input fixture and compares the
+//! output with an expected fixture, panicking with a readable diff on mismatch.
+//! Fixtures are built inline with `Fixture` or parsed from CSV text, so authors
+//! of third-party operations can test them the same way as built-in ones:
+//!
+//! ```
+//! use industryts_core::operations::DifferenceOperation;
+//! use industryts_core::testing::{Fixture, assert_operation};
+//!
+//! let input = "time,flow\n2024-01-01 00:00:00,1.0\n2024-01-01 00:01:00,3.5\n";
+//! let expected = Fixture::csv(
+//!     "time,flow,flow_diff_1\n2024-01-01 00:00:00,1.0,\n2024-01-01 00:01:00,3.5,2.5\n",
+//! );
+//! assert_operation(&DifferenceOperation::new(1, None), input, expected);
+//! ```
+//!
+//! Outputs are compared with `snapshot::SnapshotComparator`. CSV fixtures carry no
+//! dtypes, so their columns are cast to the dtypes of the actual output first.
+
+use crate::core::{OpContext, Operation, TimeSeriesData};
+use crate::error::Result;
+use crate::io::ReadOptions;
+use crate::snapshot::{SnapshotComparator, SnapshotDiff, Tolerance};
+use polars::prelude::*;
+use std::time::Duration;
+
+/// Start of the timestamps of `Fixture::every` (2024-01-01T00:00:00Z)
+const FIXTURE_START_MS: i64 = 1_704_067_200_000;
+
+/// Input or expected data of an operation test
+#[derive(Clone)]
+pub struct Fixture {
+    source: FixtureSource,
+}
+
+#[derive(Clone)]
+enum FixtureSource {
+    Columns {
+        times_ms: Vec<i64>,
+        columns: Vec<Series>,
+        group_columns: Vec<String>,
+    },
+    Csv(String),
+    Data(TimeSeriesData),
+}
+
+impl Fixture {
+    /// Fixture with a `time` column at these Unix timestamps in milliseconds
+    pub fn at_times_ms(times_ms: Vec<i64>) -> Self {
+        Self {
+            source: FixtureSource::Columns {
+                times_ms,
+                columns: Vec::new(),
+                group_columns: Vec::new(),
+            },
+        }
+    }
+
+    /// Fixture of `rows` timestamps `interval` apart, from 2024-01-01T00:00:00Z
+    pub fn every(interval: Duration, rows: usize) -> Self {
+        let step = interval.as_millis() as i64;
+        Self::at_times_ms(
+            (0..rows as i64)
+                .map(|i| FIXTURE_START_MS + i * step)
+                .collect(),
+        )
+    }
+
+    /// Fixture parsed from CSV text with a header row
+    ///
+    /// The time column is detected by name and parsed like `TimeSeriesData::read_csv`
+    /// does; empty fields are null.
+    pub fn csv(text: &str) -> Self {
+        Self {
+            source: FixtureSource::Csv(text.to_string()),
+        }
+    }
+
+    /// Add a column, e.g. `.with_column("temp", [20.5, 21.0])`
+    ///
+    /// Ignored by CSV fixtures.
+    pub fn with_column<V, T: ?Sized>(mut self, name: &str, values: V) -> Self
+    where
+        Series: NamedFrom<V, T>,
+    {
+        if let FixtureSource::Columns { columns, .. } = &mut self.source {
+            columns.push(Series::new(name.into(), values));
+        }
+        self
+    }
+
+    /// Mark columns as group (entity) columns
+    ///
+    /// Ignored by CSV fixtures.
+    pub fn with_group_columns(mut self, names: &[&str]) -> Self {
+        if let FixtureSource::Columns { group_columns, .. } = &mut self.source {
+            group_columns.extend(names.iter().map(|n| n.to_string()));
+        }
+        self
+    }
+
+    /// Whether the fixture was parsed from CSV and has inferred dtypes
+    fn is_csv(&self) -> bool {
+        matches!(self.source, FixtureSource::Csv(_))
+    }
+
+    /// Build the time series of the fixture
+    pub fn build(self) -> Result<TimeSeriesData> {
+        match self.source {
+            FixtureSource::Columns {
+                times_ms,
+                columns,
+                group_columns,
+            } => {
+                let time = Series::new("time".into(), times_ms)
+                    .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?;
+                let mut frame = vec![time.into()];
+                frame.extend(columns.into_iter().map(Column::from));
+                let data = TimeSeriesData::new(DataFrame::new(frame)?, Some("time"))?;
+                if group_columns.is_empty() {
+                    Ok(data)
+                } else {
+                    data.with_group_columns(&group_columns)
+                }
+            }
+            FixtureSource::Csv(text) => TimeSeriesData::read_csv_str(&text, &ReadOptions::new()),
+            FixtureSource::Data(data) => Ok(data),
+        }
+    }
+}
+
+impl From<TimeSeriesData> for Fixture {
+    fn from(data: TimeSeriesData) -> Self {
+        Self {
+            source: FixtureSource::Data(data),
+        }
+    }
+}
+
+/// CSV text
+impl From<&str> for Fixture {
+    fn from(text: &str) -> Self {
+        Self::csv(text)
+    }
+}
+
+/// Runs an operation on fixtures and compares its output
+pub struct OperationTest<'a> {
+    operation: &'a dyn Operation,
+    comparator: SnapshotComparator,
+}
+
+impl<'a> OperationTest<'a> {
+    pub fn new(operation: &'a dyn Operation) -> Self {
+        Self {
+            operation,
+            comparator: SnapshotComparator::new(),
+        }
+    }
+
+    /// Tolerance for float columns without a specific override
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.comparator = self.comparator.with_tolerance(tolerance);
+        self
+    }
+
+    /// Tolerance for a single float column
+    pub fn with_column_tolerance(mut self, column: &str, tolerance: Tolerance) -> Self {
+        self.comparator = self.comparator.with_column_tolerance(column, tolerance);
+        self
+    }
+
+    /// Run the operation on `input`; returns its output and the differences from
+    /// `expected`
+    pub fn run(
+        &self,
+        input: impl Into<Fixture>,
+        expected: impl Into<Fixture>,
+    ) -> Result<(TimeSeriesData, SnapshotDiff)> {
+        let expected: Fixture = expected.into();
+        let relax_dtypes = expected.is_csv();
+        let mut ctx = OpContext::new();
+        let actual = self
+            .operation
+            .execute_with_context(input.into().build()?, &mut ctx)?;
+
+        let mut expected = expected.build()?.into_dataframe();
+        if relax_dtypes {
+            for column in actual.dataframe().get_columns() {
+                let name = column.name().clone();
+                if let Ok(e) = expected.column(&name)
+                    && e.dtype() != column.dtype()
+                    && let Ok(cast) = e.cast(column.dtype())
+                {
+                    expected.with_column(cast)?;
+                }
+            }
+        }
+        let diff = self.comparator.compare(actual.dataframe(), &expected)?;
+        Ok((actual, diff))
+    }
+
+    /// Panic with a readable diff unless the output on `input` matches `expected`
+    #[track_caller]
+    pub fn assert(&self, input: impl Into<Fixture>, expected: impl Into<Fixture>) {
+        let expected: Fixture = expected.into();
+        let shown = expected.clone();
+        match self.run(input, expected) {
+            Ok((_, diff)) if diff.is_match() => {}
+            Ok((actual, diff)) => panic!(
+                "operation `{}` output differs: {}\n\nactual:\n{}\n\nexpected:\n{}",
+                self.operation.name(),
+                diff,
+                actual.dataframe(),
+                shown
+                    .build()
+                    .map(|data| data.dataframe().to_string())
+                    .unwrap_or_default()
+            ),
+            Err(e) => panic!("operation `{}` failed: {}", self.operation.name(), e),
+        }
+    }
+}
+
+/// Panic with a readable diff unless `operation` turns `input` into `expected`
+///
+/// Fixtures are `Fixture`s, `TimeSeriesData` or CSV text. Floats are compared
+/// with the default `Tolerance`; use `OperationTest` to change it.
+#[track_caller]
+pub fn assert_operation(
+    operation: &dyn Operation,
+    input: impl Into<Fixture>,
+    expected: impl Into<Fixture>,
+) {
+    OperationTest::new(operation).assert(input, expected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::LagOperation;
+
+    #[test]
+    fn test_operation_fixtures() {
+        let op = LagOperation::new(vec![1], Some(vec!["temp".into()]));
+        let input = Fixture::every(Duration::from_secs(60), 3)
+            .with_column("temp", [20.0, 21.0, 23.0])
+            .with_column("state", ["run", "run", "stop"]);
+        let expected = Fixture::csv(
+            "time,temp,state,temp_lag_1\n\
+             2024-01-01 00:00:00,20.0,run,\n\
+             2024-01-01 00:01:00,21.0,run,20.0\n\
+             2024-01-01 00:02:00,23.0,stop,21.0\n",
+        );
+        assert_operation(&op, input.clone(), expected);
+
+        let wrong = input
+            .clone()
+            .with_column("temp_lag_1", [None, Some(20.0), Some(22.0)]);
+        let (_, diff) = OperationTest::new(&op).run(input, wrong).unwrap();
+        assert_eq!(diff.mismatches.len(), 1);
+        assert!(diff.mismatches[0].contains("temp_lag_1"));
+    }
+}
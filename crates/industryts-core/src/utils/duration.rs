@@ -0,0 +1,260 @@
+//! Human-friendly duration and frequency parsing
+//!
+//! Durations are sequences of number-unit pairs such as "5min", "1h30m", "1.5h" or
+//! "2 days"; a bare number means seconds. Units (case-insensitive):
+//!
+//! | unit        | aliases                           |
+//! |-------------|-----------------------------------|
+//! | nanosecond  | ns                                |
+//! | microsecond | us, µs                            |
+//! | millisecond | ms, msec                          |
+//! | second      | s, sec, secs                      |
+//! | minute      | m, min, mins                      |
+//! | hour        | h, hr, hrs                        |
+//! | day         | d                                 |
+//! | week        | w                                 |
+//! | month       | mo (frequencies only)             |
+//! | quarter     | q (frequencies only)              |
+//! | year        | y, yr (frequencies only)          |
+//!
+//! Plural forms of the long names are accepted. `parse_duration` yields a fixed
+//! `std::time::Duration`; `parse_frequency` also accepts calendar units and yields
+//! a Polars `Duration` for windowing and resampling.
+
+use crate::error::{IndustrytsError, Result};
+use polars::prelude::Duration as PolarsDuration;
+use std::time::Duration;
+
+/// Time unit of a duration component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Nanos,
+    Micros,
+    Millis,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Quarters,
+    Years,
+}
+
+impl Unit {
+    fn parse(s: &str) -> Option<Self> {
+        let unit = match s {
+            "ns" | "nanosecond" | "nanoseconds" => Unit::Nanos,
+            "us" | "µs" | "microsecond" | "microseconds" => Unit::Micros,
+            "ms" | "msec" | "millisecond" | "milliseconds" => Unit::Millis,
+            "" | "s" | "sec" | "secs" | "second" | "seconds" => Unit::Seconds,
+            "m" | "min" | "mins" | "minute" | "minutes" => Unit::Minutes,
+            "h" | "hr" | "hrs" | "hour" | "hours" => Unit::Hours,
+            "d" | "day" | "days" => Unit::Days,
+            "w" | "week" | "weeks" => Unit::Weeks,
+            "mo" | "month" | "months" => Unit::Months,
+            "q" | "quarter" | "quarters" => Unit::Quarters,
+            "y" | "yr" | "year" | "years" => Unit::Years,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Length in nanoseconds, `None` for calendar units
+    fn nanos(self) -> Option<f64> {
+        let nanos = match self {
+            Unit::Nanos => 1.0,
+            Unit::Micros => 1e3,
+            Unit::Millis => 1e6,
+            Unit::Seconds => 1e9,
+            Unit::Minutes => 60e9,
+            Unit::Hours => 3_600e9,
+            Unit::Days => 86_400e9,
+            Unit::Weeks => 604_800e9,
+            Unit::Months | Unit::Quarters | Unit::Years => return None,
+        };
+        Some(nanos)
+    }
+
+    /// Unit suffix in Polars duration syntax
+    fn polars_suffix(self) -> &'static str {
+        match self {
+            Unit::Nanos => "ns",
+            Unit::Micros => "us",
+            Unit::Millis => "ms",
+            Unit::Seconds => "s",
+            Unit::Minutes => "m",
+            Unit::Hours => "h",
+            Unit::Days => "d",
+            Unit::Weeks => "w",
+            Unit::Months => "mo",
+            Unit::Quarters => "q",
+            Unit::Years => "y",
+        }
+    }
+}
+
+fn invalid(s: &str, reason: &str) -> IndustrytsError {
+    IndustrytsError::ConfigError(format!("Invalid duration '{}': {}", s, reason))
+}
+
+/// Split a duration string into (value, unit) components
+fn tokenize(s: &str) -> Result<Vec<(f64, Unit)>> {
+    let text = s.trim().to_lowercase();
+    if text.is_empty() {
+        return Err(invalid(s, "empty"));
+    }
+
+    let mut components = Vec::new();
+    let mut rest = text.as_str();
+    while !rest.is_empty() {
+        let num_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if num_end == 0 {
+            return Err(invalid(s, "expected a number"));
+        }
+        let value: f64 = rest[..num_end]
+            .parse()
+            .map_err(|_| invalid(s, "malformed number"))?;
+        rest = rest[num_end..].trim_start();
+
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let unit_str = &rest[..unit_end];
+        if unit_str.is_empty() && (!rest.is_empty() || !components.is_empty()) {
+            return Err(invalid(s, "missing unit"));
+        }
+        let unit = Unit::parse(unit_str)
+            .ok_or_else(|| invalid(s, &format!("unknown unit '{}'", unit_str)))?;
+        rest = rest[unit_end..].trim_start();
+
+        components.push((value, unit));
+    }
+
+    Ok(components)
+}
+
+/// Parse a fixed-length duration such as "30s", "5min", "1h30m" or "1.5d"
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let mut nanos = 0.0;
+    for (value, unit) in tokenize(s)? {
+        let unit_nanos = unit
+            .nanos()
+            .ok_or_else(|| invalid(s, "calendar units have no fixed length"))?;
+        nanos += value * unit_nanos;
+    }
+    Ok(Duration::from_nanos(nanos.round() as u64))
+}
+
+/// Parse a frequency such as "15min", "1h30m", "1d" or "1mo" into a Polars duration
+///
+/// Calendar units (months, quarters, years) are allowed; days and weeks follow Polars
+/// calendar semantics in time-zone-aware columns.
+pub fn parse_frequency(s: &str) -> Result<PolarsDuration> {
+    let mut polars_str = String::new();
+    for (value, unit) in tokenize(s)? {
+        if value.fract() != 0.0 {
+            // Fractional components are expressed in nanoseconds
+            let nanos = unit
+                .nanos()
+                .ok_or_else(|| invalid(s, "fractional calendar units are not supported"))?;
+            polars_str.push_str(&format!("{}ns", (value * nanos).round() as i64));
+        } else {
+            polars_str.push_str(&format!("{}{}", value as i64, unit.polars_suffix()));
+        }
+    }
+    PolarsDuration::try_parse(&polars_str).map_err(|e| invalid(s, &e.to_string()))
+}
+
+/// Parse a signed time offset such as "-2.5s" or "+1h" into milliseconds
+pub fn parse_offset_ms(s: &str) -> Result<i64> {
+    let s = s.trim();
+    match s.strip_prefix('-') {
+        Some(magnitude) => Ok(-(parse_duration(magnitude)?.as_millis() as i64)),
+        None => Ok(parse_duration(s.trim_start_matches('+'))?.as_millis() as i64),
+    }
+}
+
+/// Format a signed offset in milliseconds, e.g. "-2m30s"
+pub fn format_offset_ms(offset_ms: i64) -> String {
+    let magnitude = format_duration(Duration::from_millis(offset_ms.unsigned_abs()));
+    if offset_ms < 0 {
+        format!("-{}", magnitude)
+    } else {
+        magnitude
+    }
+}
+
+/// Convert a fixed duration into a Polars duration
+pub fn to_polars_duration(duration: Duration) -> PolarsDuration {
+    PolarsDuration::new(duration.as_nanos() as i64)
+}
+
+/// Format a duration compactly, e.g. "1h30m" or "250ms"
+pub fn format_duration(duration: Duration) -> String {
+    let mut nanos = duration.as_nanos();
+    if nanos == 0 {
+        return "0s".to_string();
+    }
+
+    let mut out = String::new();
+    for (unit, len) in [
+        ("d", 86_400_000_000_000u128),
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ] {
+        if nanos >= len {
+            out.push_str(&format!("{}{}", nanos / len, unit));
+            nanos %= len;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10min").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(
+            parse_duration("1.5 hours").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert!(parse_duration("abc").is_err());
+        assert!(parse_duration("1mo").is_err());
+        assert!(parse_duration("1h30").is_err());
+    }
+
+    #[test]
+    fn test_frequency_and_formatting() {
+        assert_eq!(
+            parse_frequency("15min").unwrap(),
+            PolarsDuration::parse("15m")
+        );
+        assert_eq!(
+            parse_frequency("1mo").unwrap(),
+            PolarsDuration::parse("1mo")
+        );
+        assert_eq!(
+            to_polars_duration(Duration::from_secs(90)).duration_ns(),
+            90_000_000_000
+        );
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(parse_offset_ms("-2.5s").unwrap(), -2500);
+        assert_eq!(parse_offset_ms("+1min").unwrap(), 60_000);
+        assert_eq!(format_offset_ms(-150_000), "-2m30s");
+    }
+}
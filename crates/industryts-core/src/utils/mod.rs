@@ -1,5 +1,13 @@
 //! Utility functions
 
+pub mod duration;
+pub mod number;
+pub mod size;
+
+pub use duration::{parse_duration, parse_frequency};
+pub use number::NumberFormat;
+pub use size::parse_size;
+
 /// Helper functions for time series processing
 pub fn columns_or_default(columns: Option<&[String]>, default: &[String]) -> Vec<String> {
     columns
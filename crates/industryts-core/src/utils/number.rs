@@ -0,0 +1,93 @@
+//! Parsing of localized numbers
+
+/// Decimal and thousands separators of numbers written in a locale
+///
+/// European exports write `1.234,5` for 1234.5; other locales group digits with
+/// spaces or apostrophes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub thousands: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            thousands: None,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Parse `value`, returning `None` if it is not a number in this format
+    ///
+    /// Thousands separators must group the integer digits by three, so dates
+    /// such as `01.07.2024` are not taken for numbers. `NaN` and `inf` are
+    /// accepted in any case.
+    pub fn parse(&self, value: &str) -> Option<f64> {
+        let value = value.trim();
+        let unsigned = value.strip_prefix(['-', '+']).unwrap_or(value);
+        if unsigned.chars().all(|c| c.is_ascii_alphabetic()) {
+            return value.parse().ok();
+        }
+        let (integer, fraction) = match unsigned.split_once(self.decimal) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let integer: String = match self.thousands {
+            Some(thousands) if integer.contains(thousands) => {
+                let groups: Vec<&str> = integer.split(thousands).collect();
+                let grouped = groups[0].len() <= 3
+                    && groups.iter().all(|g| digits(g))
+                    && groups[1..].iter().all(|g| g.len() == 3);
+                if !grouped {
+                    return None;
+                }
+                groups.concat()
+            }
+            _ => integer.to_string(),
+        };
+        let valid = match fraction {
+            Some(fraction) => digits(fraction) && (integer.is_empty() || digits(&integer)),
+            None => digits(&integer),
+        };
+        if !valid {
+            return None;
+        }
+        let sign = if value.starts_with('-') { "-" } else { "" };
+        format!("{}{}.{}", sign, integer, fraction.unwrap_or("0"))
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_localized_numbers() {
+        let german = NumberFormat {
+            decimal: ',',
+            thousands: Some('.'),
+        };
+        assert_eq!(german.parse("1.234,5"), Some(1234.5));
+        assert_eq!(german.parse("-12,75"), Some(-12.75));
+        assert_eq!(german.parse(",5"), Some(0.5));
+        assert_eq!(german.parse("1.234.567"), Some(1_234_567.0));
+        assert!(german.parse("NaN").unwrap().is_nan());
+        assert_eq!(german.parse("01.07.2024"), None);
+        assert_eq!(german.parse("1,2,3"), None);
+        assert_eq!(german.parse("12.5"), None);
+        assert_eq!(german.parse(""), None);
+
+        let swiss = NumberFormat {
+            decimal: '.',
+            thousands: Some('\''),
+        };
+        assert_eq!(swiss.parse("12'500.25"), Some(12_500.25));
+        assert_eq!(NumberFormat::default().parse("3.5"), Some(3.5));
+    }
+}
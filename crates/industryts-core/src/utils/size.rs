@@ -0,0 +1,46 @@
+//! Parsing of memory sizes
+
+use crate::error::{IndustrytsError, Result};
+
+/// Parse a memory size such as "512MB", "2GiB" or "1048576" into bytes
+///
+/// `KB`/`MB`/`GB`/`TB` are decimal, `KiB`/`MiB`/`GiB`/`TiB` binary; a bare number
+/// or `B` means bytes.
+pub fn parse_size(s: &str) -> Result<usize> {
+    let trimmed = s.trim();
+    let unit_start = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_start);
+    let invalid =
+        |reason: &str| IndustrytsError::ConfigError(format!("Invalid size '{}': {}", s, reason));
+    let value: f64 = number.parse().map_err(|_| invalid("expected a number"))?;
+    let multiplier: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "tb" => 1e12,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => return Err(invalid(&format!("unknown unit '{}'", other))),
+    };
+    Ok((value * multiplier).round() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576").unwrap(), 1_048_576);
+        assert_eq!(parse_size("512MB").unwrap(), 512_000_000);
+        assert_eq!(parse_size("2 GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("1.5kb").unwrap(), 1500);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("3 parsecs").is_err());
+    }
+}
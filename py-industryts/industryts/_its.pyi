@@ -15,12 +15,18 @@ class TimeSeriesData:
     Python wrapper `industryts.TimeSeriesData` instead.
     """
 
-    def __init__(self, data: pl.DataFrame, time_column: str | None = None) -> None:
+    def __init__(
+        self,
+        data: pl.DataFrame,
+        time_column: str | None = None,
+        coerce_time: bool = False,
+    ) -> None:
         """Initialize TimeSeriesData from Polars DataFrame.
 
         Args:
             data: Polars DataFrame
             time_column: Name of time column (auto-detected if None)
+            coerce_time: Convert epoch/string time columns to datetime
         """
         ...
 
@@ -89,6 +95,18 @@ class Pipeline:
         """
         ...
 
+    @staticmethod
+    def from_file(path: str) -> Pipeline:
+        """Load pipeline from a TOML or JSON file (format chosen by extension).
+
+        Args:
+            path: Path to .toml or .json file
+
+        Returns:
+            Configured Pipeline instance
+        """
+        ...
+
     def process(self, data: TimeSeriesData) -> TimeSeriesData:
         """Execute pipeline on time series data.
 
@@ -100,6 +118,17 @@ class Pipeline:
         """
         ...
 
+    def process_with_report(self, data: TimeSeriesData) -> tuple[TimeSeriesData, str]:
+        """Execute pipeline and report per-step metrics.
+
+        Args:
+            data: Input TimeSeriesData
+
+        Returns:
+            Processed TimeSeriesData and the execution report as JSON
+        """
+        ...
+
     def to_toml(self, path: str) -> None:
         """Save pipeline configuration to TOML file.
 
@@ -108,6 +137,14 @@ class Pipeline:
         """
         ...
 
+    def to_file(self, path: str) -> None:
+        """Save pipeline configuration as TOML or JSON (format chosen by extension).
+
+        Args:
+            path: Output .toml or .json file path
+        """
+        ...
+
     def __len__(self) -> int:
         """Get the number of operations in the pipeline.
 
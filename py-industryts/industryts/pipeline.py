@@ -2,6 +2,7 @@
 
 from __future__ import annotations
 
+import json
 from pathlib import Path
 
 from industryts import _its
@@ -91,6 +92,30 @@ class Pipeline:
         instance._inner = inner
         return instance
 
+    @classmethod
+    def from_file(cls, path: str | Path) -> Pipeline:
+        """Load pipeline from a TOML or JSON configuration file.
+
+        The format is chosen by the file extension (.toml or .json). A JSON file
+        has the same structure as the TOML file described in `from_toml`.
+
+        Args:
+            path: Path to .toml or .json configuration file
+
+        Returns:
+            Pipeline instance loaded from config
+
+        Raises:
+            IOError: If file cannot be read or its format is not supported
+
+        Example:
+            >>> pipeline = Pipeline.from_file("pipeline.json")
+        """
+        inner = _its.Pipeline.from_file(str(path))
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
     def process(self, data: TimeSeriesData) -> TimeSeriesData:
         """Process time series data through the pipeline.
 
@@ -116,6 +141,32 @@ class Pipeline:
         result._inner = result_inner
         return result
 
+    def process_with_report(self, data: TimeSeriesData) -> tuple[TimeSeriesData, dict]:
+        """Process data and collect per-step metrics of the run.
+
+        The report holds the total duration and, for every step, its duration,
+        input/output rows and columns, estimated output memory and null counts
+        before and after the step.
+
+        Args:
+            data: Input time series data
+
+        Returns:
+            Processed time series data and the execution report
+
+        Raises:
+            RuntimeError: If any operation fails
+
+        Example:
+            >>> result, report = pipeline.process_with_report(ts_data)
+            >>> [step["duration_ms"] for step in report["steps"]]
+        """
+        result_inner, report = self._inner.process_with_report(data._inner)
+
+        result = TimeSeriesData.__new__(TimeSeriesData)
+        result._inner = result_inner
+        return result, json.loads(report)
+
     def to_toml(self, path: str | Path) -> None:
         """Save pipeline configuration to TOML file.
 
@@ -131,6 +182,20 @@ class Pipeline:
         """
         self._inner.to_toml(str(path))
 
+    def to_file(self, path: str | Path) -> None:
+        """Save pipeline configuration as TOML or JSON, chosen by file extension.
+
+        Args:
+            path: Output .toml or .json file path
+
+        Raises:
+            IOError: If file cannot be written or its format is not supported
+
+        Example:
+            >>> pipeline.to_file("saved_pipeline.json")
+        """
+        self._inner.to_file(str(path))
+
     def __len__(self) -> int:
         """Get the number of operations in the pipeline.
 
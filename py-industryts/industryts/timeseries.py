@@ -41,6 +41,7 @@ class TimeSeriesData:
         self,
         data: pl.DataFrame,
         time_column: str | None = None,
+        coerce_time: bool = False,
     ) -> None:
         """Create a new TimeSeriesData instance.
 
@@ -49,6 +50,9 @@ class TimeSeriesData:
             time_column: Name of the time column. If None, will auto-detect from
                 common names like 'DateTime', 'tagTime', 'timestamp', etc.
                 If no match found, uses first column.
+            coerce_time: If True, integer epoch (seconds or milliseconds) and
+                string (ISO-8601 and common formats) time columns are converted
+                to datetime instead of being rejected.
 
         Raises:
             ValueError: If data is empty or time column cannot be determined
@@ -57,7 +61,7 @@ class TimeSeriesData:
             >>> df = pl.DataFrame({"time": [...], "value": [...]})
             >>> ts = TimeSeriesData(df, time_column="time")
         """
-        self._inner = _its.TimeSeriesData(data, time_column)
+        self._inner = _its.TimeSeriesData(data, time_column, coerce_time)
 
     @property
     def time_column(self) -> str:
@@ -2,6 +2,7 @@
 //!
 //! This module provides Python bindings for the Rust-based industryts library.
 
+use industryts_core::core::TimeColumnOptions;
 use industryts_core::{Pipeline as CorePipeline, TimeSeriesData as CoreTimeSeriesData};
 use pyo3::prelude::*;
 use pyo3_polars::PyDataFrame;
@@ -16,10 +17,15 @@ pub struct PyTimeSeriesData {
 impl PyTimeSeriesData {
     /// Create a new TimeSeriesData from a Polars DataFrame
     #[new]
-    #[pyo3(signature = (data, time_column=None))]
-    pub fn new(data: PyDataFrame, time_column: Option<&str>) -> PyResult<Self> {
+    #[pyo3(signature = (data, time_column=None, coerce_time=false))]
+    pub fn new(data: PyDataFrame, time_column: Option<&str>, coerce_time: bool) -> PyResult<Self> {
         let df = data.into();
-        let ts = CoreTimeSeriesData::new(df, time_column)
+        let options = if coerce_time {
+            TimeColumnOptions::coerce()
+        } else {
+            TimeColumnOptions::default()
+        };
+        let ts = CoreTimeSeriesData::new_with_options(df, time_column, &options)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
 
         Ok(Self { inner: ts })
@@ -90,6 +96,15 @@ impl PyPipeline {
         Ok(Self { inner: pipeline })
     }
 
+    /// Load pipeline from a TOML or JSON file (chosen by extension)
+    #[staticmethod]
+    pub fn from_file(path: &str) -> PyResult<Self> {
+        let pipeline = CorePipeline::from_file(path)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))?;
+
+        Ok(Self { inner: pipeline })
+    }
+
     /// Process time series data through the pipeline
     pub fn process(&self, data: &PyTimeSeriesData) -> PyResult<PyTimeSeriesData> {
         let result = self
@@ -100,6 +115,22 @@ impl PyPipeline {
         Ok(PyTimeSeriesData { inner: result })
     }
 
+    /// Process data and return the execution report of the run as JSON
+    pub fn process_with_report(
+        &self,
+        data: &PyTimeSeriesData,
+    ) -> PyResult<(PyTimeSeriesData, String)> {
+        let (result, report) = self
+            .inner
+            .process_with_report(data.inner.clone())
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+        let report = report
+            .to_json_string()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+
+        Ok((PyTimeSeriesData { inner: result }, report))
+    }
+
     /// Save pipeline to TOML file
     pub fn to_toml(&self, path: &str) -> PyResult<()> {
         self.inner
@@ -107,6 +138,13 @@ impl PyPipeline {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))
     }
 
+    /// Save pipeline to a TOML or JSON file (chosen by extension)
+    pub fn to_file(&self, path: &str) -> PyResult<()> {
+        self.inner
+            .to_file(path)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(e.to_string()))
+    }
+
     /// Get number of operations
     pub fn __len__(&self) -> usize {
         self.inner.len()